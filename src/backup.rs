@@ -0,0 +1,312 @@
+//! `nyxflare backup` and `restore`: every zone of every account in the profile saved as
+//! JSON and as a BIND zone file, in a timestamped directory with a manifest, and one
+//! zone's JSON replayed through create calls.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::{ChangeSet, Step};
+use crate::headless::{Options, check_secret, note_truncated};
+use crate::sync::{key, same_content};
+use crate::{
+    Account, Config, DnsBackend, DnsRecord, Zone, bind, collect_pages, compact_timestamp,
+    is_managed_by_cloudflare, now_unix,
+};
+
+/// How long a backup waits between zones unless `--pause` says otherwise, to stay well
+/// inside the providers' rate limits.
+const DEFAULT_PAUSE: Duration = Duration::from_millis(500);
+
+const MANIFEST: &str = "manifest.json";
+
+/// What a backup holds, written last as `manifest.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    created_at: i64,
+    profile: String,
+    zones: Vec<ZoneEntry>,
+}
+
+/// A zone saved, or an account or zone that couldn't be.
+#[derive(Debug, Serialize, Deserialize)]
+struct ZoneEntry {
+    account: String,
+    /// `None` when the account's zones couldn't be listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zone: Option<String>,
+    #[serde(default)]
+    records: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Saves every zone of every account in the profile under a new timestamped directory in
+/// `--out`, noting progress on `notes` and printing the directory on `out`. A zone that
+/// fails is recorded in the manifest and the rest are still saved; the run fails after.
+pub(crate) fn run(
+    config: &Config,
+    backend: &mut impl DnsBackend,
+    options: &Options,
+    out: &mut impl Write,
+    notes: &mut impl Write,
+) -> Result<()> {
+    let accounts = config.profile_accounts(config.profile_name());
+    if accounts.is_empty() {
+        return Err(anyhow!("The config has no accounts yet"));
+    }
+    let pause = match options.pause.as_deref() {
+        None => DEFAULT_PAUSE,
+        Some(ms) => Duration::from_millis(
+            ms.trim()
+                .parse()
+                .map_err(|_| anyhow!("--pause {ms} should be milliseconds"))?,
+        ),
+    };
+    let created_at = now_unix();
+    let dir = new_dir(
+        Path::new(options.out.as_deref().unwrap_or_default()),
+        &compact_timestamp(created_at),
+    )?;
+    let mut manifest = Manifest {
+        created_at,
+        profile: config.profile_name().to_string(),
+        zones: Vec::new(),
+    };
+    let mut first = true;
+    for account in accounts {
+        let zones = check_secret(account).and_then(|()| {
+            backend
+                .list_zones(account)
+                .with_context(|| format!("Listing the zones of {}", account.name))
+        });
+        let zones = match zones {
+            Ok(zones) => zones,
+            Err(err) => {
+                writeln!(notes, "Skipping {}: {err:#}", account.name)?;
+                manifest.zones.push(ZoneEntry {
+                    account: account.name.clone(),
+                    zone: None,
+                    records: 0,
+                    error: Some(format!("{err:#}")),
+                });
+                continue;
+            }
+        };
+        note_truncated(&zones, "zones", notes)?;
+        let folder = dir.join(file_name(&account.name));
+        fs::create_dir_all(&folder).with_context(|| format!("Creating {}", folder.display()))?;
+        for (index, zone) in zones.items.iter().enumerate() {
+            if !first {
+                thread::sleep(pause);
+            }
+            first = false;
+            writeln!(
+                notes,
+                "Backing up {} of {} ({}/{})…",
+                zone.name,
+                account.name,
+                index + 1,
+                zones.items.len()
+            )?;
+            let (records, error) = match save_zone(backend, account, zone, &folder) {
+                Ok(records) => (records, None),
+                Err(err) => {
+                    writeln!(notes, "Couldn't back up {}: {err:#}", zone.name)?;
+                    (0, Some(format!("{err:#}")))
+                }
+            };
+            manifest.zones.push(ZoneEntry {
+                account: account.name.clone(),
+                zone: Some(zone.name.clone()),
+                records,
+                error,
+            });
+        }
+    }
+
+    let path = dir.join(MANIFEST);
+    fs::write(&path, serde_json::to_string_pretty(&manifest)? + "\n")
+        .with_context(|| format!("Writing {}", path.display()))?;
+    writeln!(out, "{}", dir.display())?;
+    let failed = manifest
+        .zones
+        .iter()
+        .filter(|entry| entry.error.is_some())
+        .count();
+    let saved: Vec<&ZoneEntry> = manifest
+        .zones
+        .iter()
+        .filter(|entry| entry.error.is_none())
+        .collect();
+    writeln!(
+        notes,
+        "Backed up {} zone(s) with {} record(s) to {}",
+        saved.len(),
+        saved.iter().map(|entry| entry.records).sum::<usize>(),
+        dir.display()
+    )?;
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} account(s) or zone(s) couldn't be backed up; see {}",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes `zone`'s records to `folder` as `<zone>.json` and `<zone>.zone`; returns how
+/// many there were. A listing cut short is a failure, not a partial backup.
+fn save_zone(
+    backend: &mut impl DnsBackend,
+    account: &Account,
+    zone: &Zone,
+    folder: &Path,
+) -> Result<usize> {
+    let records = collect_pages(|page| backend.list_records_page(account, zone, page, None))
+        .with_context(|| format!("Listing the records of {}", zone.name))?;
+    if records.truncated {
+        return Err(anyhow!(
+            "{} has more records than {} pages hold",
+            zone.name,
+            records.pages
+        ));
+    }
+    let base = file_name(&zone.name);
+    let json = folder.join(format!("{base}.json"));
+    fs::write(&json, serde_json::to_string_pretty(&records.items)? + "\n")
+        .with_context(|| format!("Writing {}", json.display()))?;
+    let bind = folder.join(format!("{base}.zone"));
+    fs::write(&bind, bind::write_zone(&zone.name, &records.items))
+        .with_context(|| format!("Writing {}", bind.display()))?;
+    Ok(records.items.len())
+}
+
+/// `root/stamp`, or `root/stamp-2` and so on when a backup already took that second.
+fn new_dir(root: &Path, stamp: &str) -> Result<PathBuf> {
+    fs::create_dir_all(root).with_context(|| format!("Creating {}", root.display()))?;
+    for attempt in 1.. {
+        let dir = match attempt {
+            1 => root.join(stamp),
+            n => root.join(format!("{stamp}-{n}")),
+        };
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Creating {}", dir.display()));
+            }
+        }
+    }
+    unreachable!("the attempts never run out")
+}
+
+/// `name` with anything but letters, digits, `.`, `-` and `_` made `_`, for a file name.
+fn file_name(name: &str) -> String {
+    name.trim()
+        .trim_end_matches('.')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// One zone's records as a backup saved them.
+#[derive(Debug, Default)]
+pub(crate) struct Snapshot {
+    /// The account the zone was saved from, the one to restore into unless `--account`
+    /// says otherwise.
+    pub(crate) account: String,
+    pub(crate) path: PathBuf,
+    pub(crate) records: Vec<DnsRecord>,
+}
+
+impl Snapshot {
+    /// `zone` in the backup at `dir`, by its manifest so a zone that failed to save isn't
+    /// picked up; `account` picks between several accounts that had it.
+    pub(crate) fn find(dir: &Path, zone: &str, account: Option<&str>) -> Result<Self> {
+        let path = dir.join(MANIFEST);
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let manifest: Manifest =
+            serde_json::from_str(&text).with_context(|| format!("Reading {}", path.display()))?;
+        let wanted = zone.trim().trim_end_matches('.');
+        let mut found: Vec<&ZoneEntry> = manifest
+            .zones
+            .iter()
+            .filter(|entry| entry.error.is_none())
+            .filter(|entry| {
+                entry
+                    .zone
+                    .as_deref()
+                    .is_some_and(|name| name.trim_end_matches('.').eq_ignore_ascii_case(wanted))
+            })
+            .collect();
+        if let Some(account) = account
+            && found.len() > 1
+        {
+            found.retain(|entry| entry.account == account);
+        }
+        let entry = match found.as_slice() {
+            [entry] => entry,
+            [] => return Err(anyhow!("{} has no backup of {wanted}", dir.display())),
+            several => {
+                let accounts: Vec<&str> = several.iter().map(|e| e.account.as_str()).collect();
+                return Err(anyhow!(
+                    "{wanted} was backed up from {}; pick one with --account",
+                    accounts.join(", ")
+                ));
+            }
+        };
+        let name = entry.zone.as_deref().unwrap_or(wanted);
+        let path = dir
+            .join(file_name(&entry.account))
+            .join(format!("{}.json", file_name(name)));
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let records =
+            serde_json::from_str(&text).with_context(|| format!("Reading {}", path.display()))?;
+        Ok(Self {
+            account: entry.account.clone(),
+            path,
+            records,
+        })
+    }
+
+    /// Creates for the saved records `live` lacks; those it has already are unchanged,
+    /// and the SOA and apex NS records the provider manages are left out.
+    pub(crate) fn change_set(&self, live: &[DnsRecord], zone_name: &str) -> ChangeSet {
+        let mut set = ChangeSet::default();
+        let mut managed = 0;
+        for saved in &self.records {
+            if is_managed_by_cloudflare(saved, zone_name) {
+                managed += 1;
+            } else if live
+                .iter()
+                .any(|record| key(record) == key(saved) && same_content(record, saved))
+            {
+                set.unchanged += 1;
+            } else {
+                set.steps.push(Step::Create {
+                    record: DnsRecord {
+                        id: String::new(),
+                        modified_on: None,
+                        ..saved.clone()
+                    },
+                });
+            }
+        }
+        if managed > 0 {
+            set.left_out
+                .push((managed, "left to the provider (SOA, apex NS)".to_string()));
+        }
+        set
+    }
+}
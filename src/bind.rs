@@ -0,0 +1,399 @@
+//! Minimal BIND zone-file reader: enough of RFC 1035 master-file syntax to preview
+//! imports and load the files Cloudflare's export endpoint produces.
+
+use anyhow::{Result, anyhow};
+
+use crate::DnsRecord;
+
+/// TTL used when neither the record nor a `$TTL` directive sets one.
+const DEFAULT_TTL: u32 = 3600;
+
+/// Parses `text` into records, resolving relative names against `origin` (a zone name,
+/// with or without the trailing dot). Record ids are left empty.
+///
+/// Supported: `$ORIGIN`, `$TTL`, `;` comments, parenthesised multi-line records, `@`,
+/// blank owners (repeat the previous one), optional TTL and `IN` class in either order,
+/// and Cloudflare's `cf_tags=cf-proxied:true` comments.
+pub(crate) fn parse_zone(text: &str, origin: &str) -> Result<Vec<DnsRecord>> {
+    let mut origin = absolute(origin);
+    let mut default_ttl = DEFAULT_TTL;
+    let mut previous_owner: Option<String> = None;
+    let mut records = Vec::new();
+
+    for (line_no, line, comment) in logical_lines(text)? {
+        let continues_owner = line.starts_with([' ', '\t']);
+        let mut fields = fields(&line).into_iter().peekable();
+        let Some(first) = fields.peek().copied() else {
+            continue;
+        };
+
+        match first.to_ascii_uppercase().as_str() {
+            "$ORIGIN" => {
+                fields.next();
+                let name = fields
+                    .next()
+                    .ok_or_else(|| anyhow!("line {line_no}: $ORIGIN needs a name"))?;
+                origin = qualify(name, &origin);
+                continue;
+            }
+            "$TTL" => {
+                fields.next();
+                default_ttl = fields.next().and_then(parse_ttl).ok_or_else(|| {
+                    anyhow!("line {line_no}: $TTL needs seconds or a TTL like 1h")
+                })?;
+                continue;
+            }
+            directive if directive.starts_with('$') => {
+                return Err(anyhow!("line {line_no}: unsupported directive {first}"));
+            }
+            _ => {}
+        }
+
+        let owner = if continues_owner {
+            previous_owner
+                .clone()
+                .ok_or_else(|| anyhow!("line {line_no}: record has no owner name"))?
+        } else {
+            let name = fields.next().unwrap_or_default();
+            qualify(name, &origin)
+        };
+        previous_owner = Some(owner.clone());
+
+        let mut ttl = None;
+        let mut record_type = None;
+        for field in fields.by_ref() {
+            if field.eq_ignore_ascii_case("IN") {
+                continue;
+            }
+            if ttl.is_none()
+                && let Some(value) = parse_ttl(field)
+            {
+                ttl = Some(value);
+                continue;
+            }
+            record_type = Some(field.to_ascii_uppercase());
+            break;
+        }
+        let record_type =
+            record_type.ok_or_else(|| anyhow!("line {line_no}: missing record type"))?;
+        let rdata: Vec<&str> = fields.collect();
+        if rdata.is_empty() {
+            return Err(anyhow!("line {line_no}: {record_type} record has no data"));
+        }
+
+        records.push(DnsRecord {
+            id: String::new(),
+            name: relative_to_root(&owner),
+            content: content_for(&record_type, &rdata, &origin),
+            record_type,
+            ttl: ttl.unwrap_or(default_ttl),
+            proxied: comment.is_some_and(|c| c.contains("cf-proxied:true")),
+            unmodeled: Vec::new(),
+            modified_on: None,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Joins parenthesised continuations and splits off comments, yielding
+/// `(first line number, text, comment)` for each logical line.
+fn logical_lines(text: &str) -> Result<Vec<(usize, String, Option<String>)>> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String, Option<String>)> = None;
+    let mut depth = 0;
+
+    for (idx, raw) in text.lines().enumerate() {
+        let (body, comment) = split_comment(raw);
+        let (body, depth_change) = strip_parens(body);
+        depth += depth_change;
+
+        match pending.as_mut() {
+            Some((_, joined, joined_comment)) => {
+                joined.push(' ');
+                joined.push_str(body.trim());
+                if joined_comment.is_none() {
+                    *joined_comment = comment;
+                }
+                if depth <= 0 {
+                    lines.extend(pending.take());
+                }
+            }
+            None if depth > 0 => pending = Some((idx + 1, body, comment)),
+            None => lines.push((idx + 1, body, comment)),
+        }
+        depth = depth.max(0);
+    }
+
+    if let Some((line_no, _, _)) = pending {
+        return Err(anyhow!("line {line_no}: unclosed parenthesis"));
+    }
+    Ok(lines)
+}
+
+/// `body` with the parentheses outside quoted strings blanked, and how many more it
+/// opens than closes.
+fn strip_parens(body: &str) -> (String, isize) {
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let text = body
+        .chars()
+        .map(|ch| match ch {
+            _ if escaped => {
+                escaped = false;
+                ch
+            }
+            '\\' => {
+                escaped = true;
+                ch
+            }
+            '"' => {
+                quoted = !quoted;
+                ch
+            }
+            '(' if !quoted => {
+                depth += 1;
+                ' '
+            }
+            ')' if !quoted => {
+                depth -= 1;
+                ' '
+            }
+            _ => ch,
+        })
+        .collect();
+    (text, depth)
+}
+
+/// Splits `line` at whitespace outside quoted strings, so TXT data keeps its spacing.
+fn fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ch if ch.is_whitespace() && !quoted => {
+                if let Some(from) = start.take() {
+                    fields.push(&line[from..idx]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(idx);
+    }
+    fields.extend(start.map(|from| &line[from..]));
+    fields
+}
+
+/// Splits `line` at the first `;` outside a quoted string.
+fn split_comment(line: &str) -> (&str, Option<String>) {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return (&line[..idx], Some(line[idx + 1..].trim().to_string())),
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// A TTL in seconds, or in BIND's units: `1h`, `2d`, `1h30m` (`s`, `m`, `h`, `d`, `w`,
+/// either case).
+fn parse_ttl(field: &str) -> Option<u32> {
+    if let Ok(secs) = field.parse() {
+        return Some(secs);
+    }
+    let mut total: u32 = 0;
+    let mut digits = String::new();
+    for ch in field.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let unit = match ch.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        let count: u32 = digits.parse().ok()?;
+        total = total.checked_add(count.checked_mul(unit)?)?;
+        digits.clear();
+    }
+    digits.is_empty().then_some(total)
+}
+
+fn absolute(name: &str) -> String {
+    let name = name.trim();
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+/// Resolves `@` and relative names against `origin` (which ends with a dot).
+fn qualify(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.{origin}")
+    }
+}
+
+/// Drops the trailing root dot, matching how records are named elsewhere in the app.
+fn relative_to_root(name: &str) -> String {
+    name.trim_end_matches('.').to_string()
+}
+
+fn content_for(record_type: &str, rdata: &[&str], origin: &str) -> String {
+    match record_type {
+        "CNAME" | "NS" | "PTR" | "DNAME" => relative_to_root(&qualify(rdata[0], origin)),
+        "MX" if rdata.len() == 2 => format!(
+            "{} {}",
+            rdata[0],
+            relative_to_root(&qualify(rdata[1], origin))
+        ),
+        "TXT" | "SPF" => normalize_txt(&rdata.join(" ")),
+        _ => rdata.join(" "),
+    }
+}
+
+/// TXT content in one form whichever way it was written: the text of a single string
+/// unquoted, several as `"one" "two"`. Cloudflare hands out `"v=spf1 -all"` where a
+/// zone file read gives `v=spf1 -all`, so both sides of a comparison go through this.
+pub(crate) fn normalize_txt(content: &str) -> String {
+    txt_content(txt_strings(content))
+}
+
+fn txt_content(strings: Vec<String>) -> String {
+    match <[String; 1]>::try_from(strings) {
+        Ok([single]) => single,
+        Err(strings) => quote_txt(&strings),
+    }
+}
+
+/// The character strings of TXT content: each `"…"` with its `\"` and `\\` escapes
+/// undone, or the whole content when it isn't quoted.
+fn txt_strings(content: &str) -> Vec<String> {
+    let content = content.trim();
+    if !content.starts_with('"') {
+        return vec![content.to_string()];
+    }
+    let mut strings = Vec::new();
+    let mut chars = content.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                let mut text = String::new();
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' => text.extend(chars.next()),
+                        '"' => break,
+                        _ => text.push(ch),
+                    }
+                }
+                strings.push(text);
+            }
+            ch if ch.is_whitespace() => {}
+            // A bare word between quoted strings counts as a string of its own.
+            _ => {
+                let mut text = String::from(ch);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '"' {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                strings.push(text);
+            }
+        }
+    }
+    strings
+}
+
+/// `strings` quoted for a zone file or Cloudflare, escaping `"` and `\`.
+fn quote_txt(strings: &[String]) -> String {
+    strings
+        .iter()
+        .map(|text| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Renders `records` as a zone file for `origin`: a `$ORIGIN` line, then one record per
+/// line with owners relative to the origin. Targets are written fully qualified, TXT
+/// data quoted and proxied records tagged the way Cloudflare's export does, so
+/// `parse_zone` reads the output back unchanged.
+pub(crate) fn write_zone(origin: &str, records: &[DnsRecord]) -> String {
+    let origin = absolute(origin);
+    let mut text = format!("$ORIGIN {origin}\n");
+    for record in records {
+        text.push_str(&format!(
+            "{}\t{}\tIN\t{}\t{}{}\n",
+            owner_for(&record.name, &origin),
+            record.ttl,
+            record.record_type,
+            rdata_for(record),
+            if record.proxied {
+                " ; cf_tags=cf-proxied:true"
+            } else {
+                ""
+            }
+        ));
+    }
+    text
+}
+
+fn owner_for(name: &str, origin: &str) -> String {
+    let name = absolute(name);
+    if name.eq_ignore_ascii_case(origin) {
+        return "@".to_string();
+    }
+    match name.strip_suffix(origin) {
+        Some(prefix) if prefix.ends_with('.') => prefix.trim_end_matches('.').to_string(),
+        _ => name,
+    }
+}
+
+fn rdata_for(record: &DnsRecord) -> String {
+    let content = record.content.trim();
+    let fields: Vec<&str> = content.split_whitespace().collect();
+    match (record.record_type.as_str(), fields.as_slice()) {
+        ("CNAME" | "NS" | "PTR" | "DNAME", [target]) => absolute(target),
+        ("MX", [priority, target]) => format!("{priority} {}", absolute(target)),
+        ("SRV", [priority, weight, port, target]) => {
+            format!("{priority} {weight} {port} {}", absolute(target))
+        }
+        ("TXT" | "SPF", _) => quote_txt(&txt_strings(content)),
+        _ => content.to_string(),
+    }
+}
+
+/// The next SOA serial after `serial`. Date-style serials (`YYYYMMDDnn`) move to
+/// `today`'s first revision when that is higher; any other serial is incremented.
+pub(crate) fn bump_serial(serial: u32, today: (i64, i64, i64)) -> u32 {
+    let (year, month, day) = today;
+    let dated = (year * 1_000_000 + month * 10_000 + day * 100) as u32;
+    let looks_dated = (1_970_010_100..=2_999_123_199).contains(&serial);
+    if looks_dated && dated > serial {
+        dated
+    } else {
+        serial.wrapping_add(1)
+    }
+}
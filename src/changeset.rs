@@ -0,0 +1,148 @@
+//! A set of record changes worked out before anything is sent: what `import`, `apply`
+//! and the TUI's bulk changes show as a diff for approval, then send one by one.
+
+use std::io::Write;
+
+use anyhow::Result;
+use crossterm::style::Stylize;
+use serde::Serialize;
+
+use crate::{DnsRecord, ttl_label};
+
+/// One change of a [`ChangeSet`]; `--json` prints it with its `action`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub(crate) enum Step {
+    Create { record: DnsRecord },
+    Update { before: DnsRecord, after: DnsRecord },
+    Delete { record: DnsRecord },
+}
+
+impl Step {
+    /// `create`, `update` or `delete`.
+    pub(crate) fn verb(&self) -> &'static str {
+        match self {
+            Step::Create { .. } => "create",
+            Step::Update { .. } => "update",
+            Step::Delete { .. } => "delete",
+        }
+    }
+
+    /// How the step starts its diff line.
+    pub(crate) fn sign(&self) -> char {
+        match self {
+            Step::Create { .. } => '+',
+            Step::Update { .. } => '~',
+            Step::Delete { .. } => '-',
+        }
+    }
+
+    /// The record as the step leaves it, or the one it deletes.
+    pub(crate) fn record(&self) -> &DnsRecord {
+        match self {
+            Step::Create { record } | Step::Delete { record } => record,
+            Step::Update { after, .. } => after,
+        }
+    }
+
+    /// The diff line without its sign: `A www.example.com 192.0.2.1 (TTL auto, proxied)`,
+    /// with `old → new` for what an update changes.
+    pub(crate) fn describe(&self) -> String {
+        let record = self.record();
+        let mut text = format!("{} {} ", record.record_type, record.name);
+        let mut details = Vec::new();
+        match self {
+            Step::Create { record } | Step::Delete { record } => {
+                text.push_str(&record.content);
+                details.push(format!("TTL {}", ttl_label(record.ttl)));
+                if record.proxied {
+                    details.push("proxied".to_string());
+                }
+            }
+            Step::Update { before, after } => {
+                text.push_str(&before.content);
+                if before.content != after.content {
+                    text.push_str(&format!(" → {}", after.content));
+                }
+                details.push(match (before.ttl, after.ttl) {
+                    (old, new) if old == new => format!("TTL {}", ttl_label(new)),
+                    (old, new) => format!("TTL {} → {}", ttl_label(old), ttl_label(new)),
+                });
+                match (before.proxied, after.proxied) {
+                    (false, true) => details.push("DNS only → proxied".to_string()),
+                    (true, false) => details.push("proxied → DNS only".to_string()),
+                    (true, true) => details.push("proxied".to_string()),
+                    (false, false) => {}
+                }
+            }
+        }
+        format!("{text} ({})", details.join(", "))
+    }
+}
+
+/// Creates, updates (with the record before and after) and deletes, in the order they
+/// are sent, with what was left alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ChangeSet {
+    pub(crate) steps: Vec<Step>,
+    /// Records already as asked.
+    pub(crate) unchanged: usize,
+    /// Records the change doesn't reach, counted by why, e.g. `(2, "not in the file")`.
+    pub(crate) left_out: Vec<(usize, String)>,
+    /// What the updates would lose, to show beside the diff before it's approved.
+    pub(crate) warnings: Vec<String>,
+}
+
+impl ChangeSet {
+    /// Deletes first, so a CNAME can replace the records it can't sit beside, then
+    /// updates, then creates.
+    pub(crate) fn ordered(deletes: Vec<Step>, updates: Vec<Step>, creates: Vec<Step>) -> Self {
+        let mut steps = deletes;
+        steps.extend(updates);
+        steps.extend(creates);
+        Self {
+            steps,
+            ..Self::default()
+        }
+    }
+
+    /// How many records nothing is sent for.
+    pub(crate) fn skipped(&self) -> usize {
+        self.unchanged + self.left_out.iter().map(|(count, _)| count).sum::<usize>()
+    }
+
+    /// `2 to create, 1 to update, 0 to delete, 14 unchanged`, and what was left out.
+    pub(crate) fn summary(&self) -> String {
+        let count = |verb| self.steps.iter().filter(|s| s.verb() == verb).count();
+        let mut summary = format!(
+            "{} to create, {} to update, {} to delete, {} unchanged",
+            count("create"),
+            count("update"),
+            count("delete"),
+            self.unchanged
+        );
+        for (count, why) in &self.left_out {
+            summary.push_str(&format!(", {count} {why}"));
+        }
+        summary
+    }
+
+    /// The set as a diff, a step a line: `+` creates in green, `~` updates in yellow and
+    /// `-` deletes in red when `color`.
+    pub(crate) fn write_diff(&self, color: bool, out: &mut impl Write) -> Result<()> {
+        for step in &self.steps {
+            let line = format!("{} {}", step.sign(), step.describe());
+            if !color {
+                writeln!(out, "{line}")?;
+                continue;
+            }
+            let line = match step {
+                Step::Create { .. } => line.green(),
+                Step::Update { .. } => line.yellow(),
+                Step::Delete { .. } => line.red(),
+            };
+            writeln!(out, "{line}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Cloudflare credentials other tools already hold: wrangler's config file and the
+//! environment variables wrangler and flarectl read. `--import-credentials` offers each
+//! find as an account; nothing here writes anything.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{Account, AuthMode, Provider};
+
+/// Credentials found somewhere, as the account they would become.
+#[derive(Debug)]
+pub(crate) struct Found {
+    /// Where they were found, e.g. `CF_API_KEY + CF_API_EMAIL`.
+    pub(crate) source: String,
+    pub(crate) account: Account,
+}
+
+/// Everything a probe turned up: usable credentials, and notes on places that held
+/// something that can't be imported.
+#[derive(Debug, Default)]
+pub(crate) struct Probe {
+    pub(crate) found: Vec<Found>,
+    pub(crate) notes: Vec<String>,
+}
+
+/// The fields of wrangler's `default.toml` worth reading; `wrangler login` writes the
+/// OAuth ones, older releases wrote `api_token` or `api_key` with `email`.
+#[derive(Deserialize)]
+struct WranglerConfig {
+    api_token: Option<String>,
+    api_key: Option<String>,
+    email: Option<String>,
+    oauth_token: Option<String>,
+}
+
+/// The account a wrangler `default.toml` holds credentials for.
+pub(crate) fn parse_wrangler_config(text: &str) -> Result<Account> {
+    let config: WranglerConfig = toml::from_str(text).context("isn't valid TOML")?;
+    let present = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    if let Some(token) = present(config.api_token) {
+        return Ok(cloudflare_account("wrangler", token, None, None));
+    }
+    match (present(config.api_key), present(config.email)) {
+        (Some(key), Some(email)) => Ok(cloudflare_account("wrangler", key, Some(email), None)),
+        (Some(_), None) => Err(anyhow!("has an api_key but no email to go with it")),
+        _ if present(config.oauth_token).is_some() => Err(anyhow!(
+            "only holds a `wrangler login` session, which expires; create an API token instead"
+        )),
+        _ => Err(anyhow!("holds no API token or key")),
+    }
+}
+
+/// Credentials in the environment variables wrangler and flarectl read, with the
+/// matching account ID when one is set. `var` looks up environment variables.
+pub(crate) fn from_env(var: impl Fn(&str) -> Option<String>) -> Probe {
+    let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+    let mut probe = Probe::default();
+    // (tool, token variable, key variable, email variable, account ID variable)
+    let tools = [
+        (
+            "wrangler",
+            "CLOUDFLARE_API_TOKEN",
+            "CLOUDFLARE_API_KEY",
+            "CLOUDFLARE_EMAIL",
+            "CLOUDFLARE_ACCOUNT_ID",
+        ),
+        (
+            "flarectl",
+            "CF_API_TOKEN",
+            "CF_API_KEY",
+            "CF_API_EMAIL",
+            "CF_ACCOUNT_ID",
+        ),
+    ];
+    for (tool, token_var, key_var, email_var, id_var) in tools {
+        let account_id = var(id_var);
+        if let Some(token) = var(token_var) {
+            probe.found.push(Found {
+                source: token_var.to_string(),
+                account: cloudflare_account(tool, token, None, account_id.clone()),
+            });
+        }
+        match (var(key_var), var(email_var)) {
+            (Some(key), Some(email)) => probe.found.push(Found {
+                source: format!("{key_var} + {email_var}"),
+                account: cloudflare_account(tool, key, Some(email), account_id),
+            }),
+            (Some(_), None) => probe.notes.push(format!(
+                "{key_var} is set without {email_var}; a global API key needs both"
+            )),
+            _ => {}
+        }
+    }
+    probe
+}
+
+/// Where wrangler keeps `default.toml`: under `~/.wrangler` for older releases, and
+/// under the XDG config directory (or `~/Library/Preferences` on macOS) for newer ones.
+pub(crate) fn wrangler_config_paths(
+    home: Option<&Path>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Vec<PathBuf> {
+    let tail = Path::new(".wrangler").join("config").join("default.toml");
+    let mut paths = Vec::new();
+    if let Some(home) = home {
+        paths.push(home.join(&tail));
+    }
+    match var("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => paths.push(Path::new(&dir).join(&tail)),
+        None => {
+            if let Some(home) = home {
+                paths.push(home.join(".config").join(&tail));
+            }
+        }
+    }
+    if cfg!(target_os = "macos")
+        && let Some(home) = home
+    {
+        paths.push(home.join("Library").join("Preferences").join(&tail));
+    }
+    paths
+}
+
+/// Probes wrangler's config files in `paths` and the environment; the same secret found
+/// in several places is offered once, under the first place it was seen.
+pub(crate) fn probe(paths: &[PathBuf], var: impl Fn(&str) -> Option<String>) -> Probe {
+    let mut probe = Probe::default();
+    for path in paths {
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        match parse_wrangler_config(&text) {
+            Ok(account) => probe.found.push(Found {
+                source: path.display().to_string(),
+                account,
+            }),
+            Err(err) => probe.notes.push(format!("{} {err:#}", path.display())),
+        }
+    }
+    let env = from_env(var);
+    probe.found.extend(env.found);
+    probe.notes.extend(env.notes);
+
+    let mut seen = Vec::new();
+    probe.found.retain(|found| {
+        let key = (found.account.api_token.clone(), found.account.email.clone());
+        let fresh = !seen.contains(&key);
+        seen.push(key);
+        fresh
+    });
+    probe
+}
+
+fn cloudflare_account(
+    name: &str,
+    secret: String,
+    email: Option<String>,
+    account_id: Option<String>,
+) -> Account {
+    Account {
+        name: name.to_string(),
+        api_token: secret.trim().to_string(),
+        auth_mode: if email.is_some() {
+            AuthMode::GlobalKey
+        } else {
+            AuthMode::Token
+        },
+        email: email.map(|email| email.trim().to_string()),
+        account_id: account_id.map(|id| id.trim().to_string()),
+        account_name: None,
+        base_url: None,
+        provider: Provider::Cloudflare,
+        aws_access_key_id: None,
+        zone_dir: None,
+        dns_server: None,
+        tsig_key_name: None,
+        tsig_algorithm: None,
+        zones: Vec::new(),
+        token_ref: None,
+        token_cmd: None,
+        token_error: None,
+        default_ttl: None,
+        default_proxied: None,
+        label: None,
+        color: None,
+    }
+}
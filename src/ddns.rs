@@ -0,0 +1,162 @@
+//! `nyxflare ddns`: the machine's public address, from a web service that echoes it back
+//! or from the route out of a local interface, and the change that points a name's A
+//! or AAAA record at it.
+
+use std::net::{IpAddr, UdpSocket};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::blocking::Client;
+
+use crate::DnsRecord;
+use crate::changeset::Step;
+
+/// Where the address comes from when neither `--source` nor `ddns_source` says.
+const DEFAULT_V4_SOURCE: &str = "https://api.ipify.org";
+const DEFAULT_V6_SOURCE: &str = "https://api6.ipify.org";
+
+/// The source that reads the address of the interface traffic leaves by.
+pub(crate) const LOCAL_SOURCE: &str = "local";
+
+/// An address family, by the record type that holds it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    /// `A` or `AAAA`, ignoring case.
+    pub(crate) fn of_type(record_type: &str) -> Result<Self> {
+        match record_type.trim().to_uppercase().as_str() {
+            "A" => Ok(Family::V4),
+            "AAAA" => Ok(Family::V6),
+            other => Err(anyhow!("ddns keeps A and AAAA records, not {other}")),
+        }
+    }
+
+    fn of(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => Family::V4,
+            IpAddr::V6(_) => Family::V6,
+        }
+    }
+
+    pub(crate) fn record_type(self) -> &'static str {
+        match self {
+            Family::V4 => "A",
+            Family::V6 => "AAAA",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Family::V4 => "IPv4",
+            Family::V6 => "IPv6",
+        }
+    }
+
+    pub(crate) fn default_source(self) -> &'static str {
+        match self {
+            Family::V4 => DEFAULT_V4_SOURCE,
+            Family::V6 => DEFAULT_V6_SOURCE,
+        }
+    }
+}
+
+/// Checks that `source` is `local` or an http(s) URL before anything is asked of it.
+pub(crate) fn check_source(source: &str) -> Result<()> {
+    if source == LOCAL_SOURCE || source.starts_with("https://") || source.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "--source {source} should be an http(s) URL or {LOCAL_SOURCE}"
+        ))
+    }
+}
+
+/// The current `family` address according to `source`.
+pub(crate) fn detect(family: Family, source: &str, client: &Client) -> Result<IpAddr> {
+    if source == LOCAL_SOURCE {
+        return local_address(family);
+    }
+    let body = client
+        .get(source)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .with_context(|| format!("Asking {source} for the {} address", family.label()))?;
+    parse_answer(&body, family).with_context(|| format!("{source} answered"))
+}
+
+/// A lookup service's answer: the address as plain text, or as `{"ip": "…"}`.
+pub(crate) fn parse_answer(body: &str, family: Family) -> Result<IpAddr> {
+    let body = body.trim();
+    let text = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(object)) => object
+            .get("ip")
+            .and_then(|ip| ip.as_str())
+            .ok_or_else(|| anyhow!("a JSON object without an \"ip\""))?
+            .to_string(),
+        _ => body.to_string(),
+    };
+    let shown: String = text.chars().take(60).collect();
+    let ip: IpAddr = text
+        .parse()
+        .map_err(|_| anyhow!("{shown:?}, which isn't an IP address"))?;
+    if Family::of(ip) != family {
+        return Err(anyhow!("{ip}, which isn't an {} address", family.label()));
+    }
+    Ok(ip)
+}
+
+/// The address of the interface the machine would reach the internet by. Connecting a
+/// UDP socket sends nothing; it only picks the route.
+fn local_address(family: Family) -> Result<IpAddr> {
+    let (bind, peer) = match family {
+        Family::V4 => ("0.0.0.0:0", "1.1.1.1:53"),
+        Family::V6 => ("[::]:0", "[2606:4700:4700::1111]:53"),
+    };
+    let socket = UdpSocket::bind(bind)
+        .and_then(|socket| {
+            socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+            socket.connect(peer)?;
+            Ok(socket)
+        })
+        .with_context(|| format!("Finding the local {} address", family.label()))?;
+    Ok(socket.local_addr()?.ip())
+}
+
+/// What makes `wanted` the name's record: a create when `existing` is empty, an update
+/// of the one record when anything differs, nothing when it already matches. Several
+/// records of the name and type are refused, since it's unclear which one to move.
+pub(crate) fn step(existing: &[&DnsRecord], wanted: &DnsRecord) -> Result<Option<Step>> {
+    match existing {
+        [] => Ok(Some(Step::Create {
+            record: wanted.clone(),
+        })),
+        [before] => {
+            // Addresses compare as addresses, so `2001:db8::1` matches `2001:0db8::1`.
+            let address = |content: &str| content.trim().parse::<IpAddr>().ok();
+            let same = address(&before.content).is_some()
+                && address(&before.content) == address(&wanted.content)
+                && (before.ttl, before.proxied) == (wanted.ttl, wanted.proxied);
+            Ok((!same).then(|| Step::Update {
+                before: (*before).clone(),
+                after: DnsRecord {
+                    id: before.id.clone(),
+                    content: wanted.content.clone(),
+                    ttl: wanted.ttl,
+                    proxied: wanted.proxied,
+                    ..(*before).clone()
+                },
+            }))
+        }
+        several => Err(anyhow!(
+            "{} {} records are named {}; ddns keeps a single one",
+            several.len(),
+            wanted.record_type,
+            wanted.name
+        )),
+    }
+}
@@ -0,0 +1,567 @@
+//! DigitalOcean DNS behind `DnsBackend`. Domains map to `Zone` (the domain name is its
+//! id) and domain records to `DnsRecord`, with DO's relative names (`@`, `www`) expanded
+//! to full names and numeric record ids carried as strings.
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{
+    Account, AuditEntry, BatchRequest, BatchResult, CloudflareAccount, DnsBackend, DnsRecord,
+    DnssecStatus, ImportResult, Listing, Page, Timeouts, TokenStatus, Zone, ZoneSetup, bind,
+    collect_pages, truncate_body,
+};
+
+const DO_API_BASE: &str = "https://api.digitalocean.com/v2";
+/// DigitalOcean's maximum page size.
+const DO_PER_PAGE: u32 = 200;
+
+pub(crate) struct DigitalOceanBackend {
+    client: Client,
+    base_url: String,
+    timeouts: Timeouts,
+}
+
+impl DigitalOceanBackend {
+    pub(crate) fn new(timeouts: Timeouts) -> Result<Self> {
+        Self::with_base(DO_API_BASE, timeouts)
+    }
+
+    fn with_base(base_url: impl Into<String>, timeouts: Timeouts) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent("nyxflare/0.1")
+            .timeout(Duration::from_secs(timeouts.request_secs))
+            .connect_timeout(Duration::from_secs(timeouts.connect_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: base_url.into(),
+            timeouts,
+        })
+    }
+
+    /// The account's own API base when it has one, otherwise the backend's.
+    fn base_for<'a>(&'a self, account: &'a Account) -> &'a str {
+        account
+            .base_url
+            .as_deref()
+            .map(|base| base.trim_end_matches('/'))
+            .filter(|base| !base.is_empty())
+            .unwrap_or(&self.base_url)
+    }
+
+    fn send(&self, request: RequestBuilder, account: &Account, label: &str) -> Result<Response> {
+        let response = request
+            .bearer_auth(&account.api_token)
+            .send()
+            .map_err(|err| self.timeouts.explain(err))
+            .with_context(|| label.to_string())?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let message = serde_json::from_str::<DoError>(&text)
+            .map(|err| err.message)
+            .unwrap_or_default();
+        Err(anyhow!(
+            "{label} ({status}): {message} | body: {}",
+            truncate_body(&text)
+        ))
+    }
+
+    fn get<T: DeserializeOwned>(&self, account: &Account, path: &str, label: &str) -> Result<T> {
+        let url = format!("{}{path}", self.base_for(account));
+        let response = self.send(self.client.get(url), account, label)?;
+        let text = response.text().with_context(|| label.to_string())?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("{label}: unexpected response: {}", truncate_body(&text)))
+    }
+
+    fn write_record(
+        &self,
+        request: RequestBuilder,
+        account: &Account,
+        zone: &Zone,
+        record: &DnsRecord,
+        label: &str,
+    ) -> Result<DnsRecord> {
+        let body = DoRecordWrite::from_record(record, &zone.name);
+        let response = self.send(request.json(&body), account, label)?;
+        let text = response.text().with_context(|| label.to_string())?;
+        let parsed: DoRecordResponse = serde_json::from_str(&text)
+            .with_context(|| format!("{label}: unexpected response: {}", truncate_body(&text)))?;
+        Ok(parsed.domain_record.into_record(&zone.name))
+    }
+
+    fn records_url(&self, account: &Account, zone: &Zone) -> String {
+        format!("{}/domains/{}/records", self.base_for(account), zone.name)
+    }
+}
+
+impl DnsBackend for DigitalOceanBackend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        collect_pages(|page| {
+            let parsed: DoDomains = self.get(
+                account,
+                &format!("/domains?page={page}&per_page={DO_PER_PAGE}"),
+                "Domains",
+            )?;
+            Ok(Page {
+                items: parsed
+                    .domains
+                    .into_iter()
+                    .map(DoDomain::into_zone)
+                    .collect(),
+                page,
+                total_pages: parsed.links.total_pages(page),
+                total_count: parsed.meta.total,
+            })
+        })
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        let url = format!("{}/domains/{}", self.base_for(account), zone.name);
+        self.send(self.client.delete(url), account, "Delete domain")?;
+        Ok(())
+    }
+
+    /// DigitalOcean has no partial setup; every domain is fully hosted.
+    fn create_zone(&mut self, account: &Account, name: &str, _setup: ZoneSetup) -> Result<Zone> {
+        let url = format!("{}/domains", self.base_for(account));
+        let response = self.send(
+            self.client
+                .post(url)
+                .json(&serde_json::json!({ "name": name })),
+            account,
+            "Create domain",
+        )?;
+        let parsed: DoDomainResponse = response.json().context("Create domain")?;
+        Ok(parsed.domain.into_zone())
+    }
+
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        let mut path = format!(
+            "/domains/{}/records?page={page}&per_page={DO_PER_PAGE}",
+            zone.name
+        );
+        if let Some(record_type) = record_type {
+            path.push_str(&format!("&type={record_type}"));
+        }
+        let parsed: DoRecords = self.get(account, &path, "Records")?;
+        Ok(Page {
+            items: parsed
+                .domain_records
+                .into_iter()
+                .map(|r| r.into_record(&zone.name))
+                .collect(),
+            page,
+            total_pages: parsed.links.total_pages(page),
+            total_count: parsed.meta.total,
+        })
+    }
+
+    /// DO can only filter by exact name and type, so matching happens locally.
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        let needle = query.to_lowercase();
+        let listing = collect_pages(|page| self.list_records_page(account, zone, page, None))?;
+        Ok(listing
+            .items
+            .into_iter()
+            .filter(|r| {
+                r.name.to_lowercase().contains(&needle)
+                    || r.content.to_lowercase().contains(&needle)
+            })
+            .collect())
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let url = self.records_url(account, zone);
+        self.write_record(
+            self.client.post(url),
+            account,
+            zone,
+            &record,
+            "Create record",
+        )
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let url = format!("{}/{}", self.records_url(account, zone), record.id);
+        self.write_record(
+            self.client.put(url),
+            account,
+            zone,
+            &record,
+            "Update record",
+        )
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        let url = format!("{}/{record_id}", self.records_url(account, zone));
+        self.send(self.client.delete(url), account, "Delete record")?;
+        Ok(())
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        let parsed: DoRecordResponse = self.get(
+            account,
+            &format!("/domains/{}/records/{record_id}", zone.name),
+            "Record",
+        )?;
+        Ok(parsed.domain_record.into_record(&zone.name))
+    }
+
+    /// DO has no batch endpoint, so the parts are sent one by one in Cloudflare's order.
+    /// A failure stops the batch with the earlier changes already applied.
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        let mut result = BatchResult::default();
+        for id in request.deletes {
+            let record = self.get_record(account, zone, &id)?;
+            self.delete_record(account, zone, &id)?;
+            result.deletes.push(record);
+        }
+        for record in request.patches {
+            result
+                .patches
+                .push(self.update_record(account, zone, record)?);
+        }
+        for record in request.puts {
+            result.puts.push(self.update_record(account, zone, record)?);
+        }
+        for record in request.posts {
+            result
+                .posts
+                .push(self.create_record(account, zone, record)?);
+        }
+        Ok(result)
+    }
+
+    /// DigitalOcean keeps no per-record history.
+    fn record_audit_log(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        let parsed: DoAccountResponse = self.get(account, "/account", "Verify token")?;
+        Ok(TokenStatus {
+            status: parsed.account.status,
+            expires_on: None,
+        })
+    }
+
+    fn list_cf_accounts(&mut self, _account: &Account) -> Result<Vec<CloudflareAccount>> {
+        Ok(Vec::new())
+    }
+
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        let parsed: DoDomainResponse =
+            self.get(account, &format!("/domains/{}", zone.name), "Export domain")?;
+        parsed
+            .domain
+            .zone_file
+            .ok_or_else(|| anyhow!("DigitalOcean returned no zone file for {}", zone.name))
+    }
+
+    /// Creates each record of the file in turn; SOA and apex NS records are skipped
+    /// because DigitalOcean manages them. `proxied` has no meaning here.
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        _proxied: bool,
+    ) -> Result<ImportResult> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let parsed: Vec<DnsRecord> = bind::parse_zone(&text, &zone.name)?
+            .into_iter()
+            .filter(|r| {
+                r.record_type != "SOA"
+                    && !(r.record_type == "NS" && r.name.eq_ignore_ascii_case(&zone.name))
+            })
+            .collect();
+        let mut added = 0;
+        for record in &parsed {
+            if self.create_record(account, zone, record.clone()).is_ok() {
+                added += 1;
+            }
+        }
+        Ok(ImportResult {
+            added,
+            parsed: parsed.len() as u32,
+        })
+    }
+
+    fn dnssec_status(&mut self, _account: &Account, _zone: &Zone) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for DigitalOcean accounts"))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _enabled: bool,
+    ) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for DigitalOcean accounts"))
+    }
+}
+
+#[derive(Deserialize)]
+struct DoError {
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize, Default)]
+struct DoLinks {
+    #[serde(default)]
+    pages: Option<DoPages>,
+}
+
+#[derive(Deserialize, Default)]
+struct DoPages {
+    #[serde(default)]
+    next: Option<String>,
+    #[serde(default)]
+    last: Option<String>,
+}
+
+impl DoLinks {
+    /// The page count, read from the `page` parameter of the `last` link. DO omits
+    /// `last` on the final page and `pages` entirely when everything fits in one.
+    fn total_pages(&self, current: u32) -> u32 {
+        let Some(pages) = &self.pages else {
+            return current;
+        };
+        let last = pages.last.as_deref().and_then(page_param);
+        match (last, &pages.next) {
+            (Some(last), _) => last.max(current),
+            (None, Some(_)) => current + 1,
+            (None, None) => current,
+        }
+    }
+}
+
+fn page_param(url: &str) -> Option<u32> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("page="))
+        .and_then(|page| page.parse().ok())
+}
+
+#[derive(Deserialize, Default)]
+struct DoMeta {
+    #[serde(default)]
+    total: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct DoDomains {
+    domains: Vec<DoDomain>,
+    #[serde(default)]
+    links: DoLinks,
+    #[serde(default)]
+    meta: DoMeta,
+}
+
+#[derive(Deserialize)]
+struct DoDomainResponse {
+    domain: DoDomain,
+}
+
+#[derive(Deserialize)]
+struct DoDomain {
+    name: String,
+    #[serde(default)]
+    zone_file: Option<String>,
+}
+
+impl DoDomain {
+    fn into_zone(self) -> Zone {
+        Zone {
+            id: self.name.clone(),
+            name: self.name,
+            ..Zone::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DoAccountResponse {
+    account: DoAccount,
+}
+
+#[derive(Deserialize)]
+struct DoAccount {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct DoRecords {
+    domain_records: Vec<DoRecord>,
+    #[serde(default)]
+    links: DoLinks,
+    #[serde(default)]
+    meta: DoMeta,
+}
+
+#[derive(Deserialize)]
+struct DoRecordResponse {
+    domain_record: DoRecord,
+}
+
+#[derive(Deserialize)]
+struct DoRecord {
+    id: u64,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    data: String,
+    #[serde(default)]
+    priority: Option<u16>,
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    weight: Option<u16>,
+    #[serde(default)]
+    ttl: u32,
+}
+
+impl DoRecord {
+    /// MX and SRV records carry their priority (and SRV its weight and port) in front of
+    /// the target, as in a zone file.
+    fn into_record(self, zone: &str) -> DnsRecord {
+        let target = match self.record_type.as_str() {
+            "CNAME" | "MX" | "NS" | "SRV" => absolute(&self.data, zone),
+            _ => self.data,
+        };
+        let content = match self.record_type.as_str() {
+            "MX" => format!("{} {target}", self.priority.unwrap_or_default()),
+            "SRV" => format!(
+                "{} {} {} {target}",
+                self.priority.unwrap_or_default(),
+                self.weight.unwrap_or_default(),
+                self.port.unwrap_or_default()
+            ),
+            _ => target,
+        };
+        DnsRecord {
+            id: self.id.to_string(),
+            name: absolute(&self.name, zone),
+            record_type: self.record_type,
+            content,
+            ttl: self.ttl,
+            proxied: false,
+            unmodeled: Vec::new(),
+            modified_on: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DoRecordWrite {
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    data: String,
+    ttl: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+}
+
+impl DoRecordWrite {
+    fn from_record(record: &DnsRecord, zone: &str) -> Self {
+        let mut write = Self {
+            record_type: record.record_type.clone(),
+            name: relative(&record.name, zone),
+            data: record.content.trim().to_string(),
+            ttl: record.ttl,
+            priority: None,
+            weight: None,
+            port: None,
+        };
+        let fields: Vec<&str> = record.content.split_whitespace().collect();
+        match (record.record_type.as_str(), fields.as_slice()) {
+            ("MX", [priority, target]) => {
+                write.priority = priority.parse().ok();
+                write.data = fqdn(target);
+            }
+            ("SRV", [priority, weight, port, target]) => {
+                write.priority = priority.parse().ok();
+                write.weight = weight.parse().ok();
+                write.port = port.parse().ok();
+                write.data = fqdn(target);
+            }
+            ("CNAME" | "NS", [target]) => write.data = fqdn(target),
+            _ => {}
+        }
+        write
+    }
+}
+
+/// Expands DO's `@` and zone-relative names to full names without the root dot.
+fn absolute(name: &str, zone: &str) -> String {
+    match name {
+        "@" | "" => zone.to_string(),
+        name if name.ends_with('.') => name.trim_end_matches('.').to_string(),
+        name => format!("{name}.{zone}"),
+    }
+}
+
+/// The zone-relative form DO expects for record names.
+fn relative(name: &str, zone: &str) -> String {
+    let name = name.trim().trim_end_matches('.');
+    if name.eq_ignore_ascii_case(zone) {
+        return "@".to_string();
+    }
+    name.strip_suffix(zone)
+        .and_then(|prefix| prefix.strip_suffix('.'))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// DO treats hostnames without a trailing dot as relative to the zone.
+fn fqdn(name: &str) -> String {
+    format!("{}.", name.trim_end_matches('.'))
+}
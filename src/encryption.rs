@@ -0,0 +1,107 @@
+//! Passphrase encryption of the accounts file. PBKDF2-HMAC-SHA256 stretches the
+//! passphrase into a ChaCha20-Poly1305 key; the file is a header line naming the scheme
+//! and iteration count, then base64 of the salt, the nonce and the sealed config text.
+//! The header is authenticated too, so the iteration count can't be lowered unnoticed.
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::num::NonZeroU32;
+
+use crate::ENCRYPTED_HEADER;
+
+/// PBKDF2 rounds for new files; tests use few so they stay quick in debug builds.
+pub(crate) const ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+/// The most rounds a header may ask for: an edited count would otherwise keep startup
+/// busy deriving a key before the passphrase could be turned down.
+const MAX_ITERATIONS: u32 = 10 * ITERATIONS;
+const SALT_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+const KDF: &str = "pbkdf2-sha256";
+const CIPHER: &str = "chacha20poly1305";
+
+fn key(passphrase: &str, salt: &[u8], iterations: NonZeroU32) -> LessSafeKey {
+    let mut bytes = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut bytes,
+    );
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &bytes).expect("a 32-byte key"))
+}
+
+/// `plain` sealed under `passphrase`, as the text of an encrypted config file.
+pub(crate) fn encrypt(plain: &str, passphrase: &str, iterations: u32) -> Result<String> {
+    let rounds = NonZeroU32::new(iterations).context("PBKDF2 needs at least one round")?;
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|()| rng.fill(&mut nonce))
+        .map_err(|_| anyhow!("the system has no randomness to offer"))?;
+
+    let header = format!("{ENCRYPTED_HEADER} v1 {KDF}:{iterations} {CIPHER}");
+    let mut sealed = plain.as_bytes().to_vec();
+    key(passphrase, &salt, rounds)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(header.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + sealed.len());
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&sealed);
+    let body = base64::engine::general_purpose::STANDARD.encode(payload);
+    Ok(format!("{header}\n{body}\n"))
+}
+
+/// The config text inside an encrypted file: `None` when `passphrase` doesn't open it,
+/// an error when the file isn't in a shape that any passphrase could open.
+pub(crate) fn decrypt(text: &str, passphrase: &str) -> Result<Option<String>> {
+    let (header, body) = text.split_once('\n').unwrap_or((text, ""));
+    let header = header.trim_end();
+    let fields: Vec<&str> = header.split(' ').collect();
+    let iterations = match fields.as_slice() {
+        [tag, "v1", kdf, CIPHER] if *tag == ENCRYPTED_HEADER => kdf
+            .strip_prefix(KDF)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .and_then(|count| count.parse::<u32>().ok())
+            .and_then(NonZeroU32::new),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("unrecognised header {header:?}"))?;
+    if iterations.get() > MAX_ITERATIONS {
+        return Err(anyhow!(
+            "the header asks for {iterations} PBKDF2 rounds, more than the {MAX_ITERATIONS} nyxflare accepts"
+        ));
+    }
+
+    let body: String = body.split_whitespace().collect();
+    let payload = base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(|err| anyhow!("the encrypted body isn't valid base64 ({err})"))?;
+    if payload.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+        return Err(anyhow!("the encrypted body is cut short"));
+    }
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).expect("a nonce-sized slice");
+    let mut sealed = sealed.to_vec();
+    let Ok(plain) = key(passphrase, salt, iterations).open_in_place(
+        nonce,
+        Aad::from(header.as_bytes()),
+        &mut sealed,
+    ) else {
+        return Ok(None);
+    };
+    String::from_utf8(plain.to_vec())
+        .map(Some)
+        .map_err(|_| anyhow!("the decrypted config isn't text"))
+}
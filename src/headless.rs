@@ -0,0 +1,1331 @@
+//! `nyxflare list-zones`, `list-records`, `record create|update|delete`, `export`,
+//! `import`, `apply`, `backup`, `restore` and `ddns`: the config's accounts put to use
+//! from scripts, without the TUI.
+//! Results go to `out` as a table or as JSON lines; what goes wrong is returned, for
+//! `main` to print on stderr and exit non-zero.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use crate::backup::{self, Snapshot};
+use crate::changeset::{ChangeSet, Step};
+use crate::ddns::{self, Family};
+use crate::sync::{self, StateFile};
+use crate::{
+    Account, ChangeAction, Config, CreateConflict, DnsBackend, DnsRecord, Journal, JournalEntry,
+    Listing, Policy, RecordDraft, Zone, bind, collect_pages, find_create_conflict,
+    format_timestamp, is_proxiable, now_unix, parse_bulk_ttl, qualify_name, ttl_label,
+};
+
+/// What a headless run does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    ListZones,
+    ListRecords,
+    CreateRecord,
+    UpdateRecord,
+    DeleteRecord,
+    ExportZone,
+    ImportZone,
+    ApplyState,
+    Backup,
+    Restore,
+    Ddns,
+}
+
+impl Command {
+    /// The subcommand `word` starts, taking `record`'s action from `rest`; `None` when
+    /// `word` isn't a subcommand.
+    pub(crate) fn parse(
+        word: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<Option<Self>> {
+        Ok(Some(match word {
+            "list-zones" => Command::ListZones,
+            "list-records" => Command::ListRecords,
+            "record" => match rest.next().as_deref() {
+                Some("create") => Command::CreateRecord,
+                Some("update") => Command::UpdateRecord,
+                Some("delete") => Command::DeleteRecord,
+                _ => return Err(anyhow!("record needs create, update or delete")),
+            },
+            "export" => Command::ExportZone,
+            "import" => Command::ImportZone,
+            "apply" => Command::ApplyState,
+            "backup" => Command::Backup,
+            "restore" => Command::Restore,
+            "ddns" => Command::Ddns,
+            _ => return Ok(None),
+        }))
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Command::ListZones => "list-zones",
+            Command::ListRecords => "list-records",
+            Command::CreateRecord => "record create",
+            Command::UpdateRecord => "record update",
+            Command::DeleteRecord => "record delete",
+            Command::ExportZone => "export",
+            Command::ImportZone => "import",
+            Command::ApplyState => "apply",
+            Command::Backup => "backup",
+            Command::Restore => "restore",
+            Command::Ddns => "ddns",
+        }
+    }
+
+    /// The options the subcommand takes besides `--account`.
+    fn accepts(self) -> &'static [&'static str] {
+        match self {
+            Command::ListZones => &["--json"],
+            Command::ListRecords => &["--zone", "--json"],
+            Command::ExportZone => &["--zone", "--out"],
+            Command::ImportZone => &[
+                "--zone",
+                "--file",
+                "--replace",
+                "--json",
+                "--dry-run",
+                "--on-failure",
+            ],
+            Command::ApplyState => &[
+                "--zone",
+                "--file",
+                "--prune",
+                "--yes",
+                "--json",
+                "--dry-run",
+                "--on-failure",
+            ],
+            Command::Backup => &["--out", "--pause"],
+            Command::Restore => &["--from", "--zone", "--json", "--dry-run", "--on-failure"],
+            Command::Ddns => &[
+                "--zone",
+                "--name",
+                "--type",
+                "--ttl",
+                "--proxied",
+                "--source",
+                "--ip",
+                "--interval",
+                "--json",
+                "--dry-run",
+            ],
+            Command::CreateRecord => &[
+                "--json",
+                "--zone",
+                "--name",
+                "--type",
+                "--content",
+                "--ttl",
+                "--proxied",
+                "--dry-run",
+            ],
+            Command::UpdateRecord => &[
+                "--json",
+                "--zone",
+                "--id",
+                "--name",
+                "--type",
+                "--content",
+                "--ttl",
+                "--proxied",
+                "--dry-run",
+            ],
+            Command::DeleteRecord => &[
+                "--json",
+                "--zone",
+                "--id",
+                "--name",
+                "--type",
+                "--content",
+                "--dry-run",
+            ],
+        }
+    }
+}
+
+/// The subcommands' options, as given on the command line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Options {
+    /// May be left out when the profile has a single account.
+    pub(crate) account: Option<String>,
+    pub(crate) zone: Option<String>,
+    pub(crate) json: bool,
+    pub(crate) id: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) record_type: Option<String>,
+    pub(crate) content: Option<String>,
+    pub(crate) ttl: Option<String>,
+    pub(crate) proxied: Option<bool>,
+    /// Print what would be sent and send nothing.
+    pub(crate) dry_run: bool,
+    /// Where `export` writes the zone file; stdout when not given.
+    pub(crate) out: Option<String>,
+    /// The zone file `import` reads, or the desired-state file `apply` does.
+    pub(crate) file: Option<String>,
+    /// Whether `import` deletes the records the file doesn't have.
+    pub(crate) replace: bool,
+    /// Whether `apply` deletes the names and types the file doesn't have.
+    pub(crate) prune: bool,
+    /// Apply the plan without asking.
+    pub(crate) yes: bool,
+    /// `--on-failure stop` or `continue`; the config's `stop_on_failure` when not given.
+    pub(crate) stop_on_failure: Option<bool>,
+    /// The backup directory `restore` reads.
+    pub(crate) from: Option<String>,
+    /// Milliseconds `backup` waits between zones.
+    pub(crate) pause: Option<String>,
+    /// Where `ddns` looks up the public address: a URL, or `local`.
+    pub(crate) source: Option<String>,
+    /// The address `ddns` sets, instead of looking it up.
+    pub(crate) ip: Option<String>,
+    /// Seconds between `ddns` checks; without it, `ddns` checks once.
+    pub(crate) interval: Option<String>,
+    /// Whether `apply` colors its diff; not a flag, but whether stdout is a terminal.
+    pub(crate) color: bool,
+}
+
+impl Options {
+    /// Takes `arg`, and its value from `rest` unless written `--flag=value`, when it's
+    /// one of the subcommand options.
+    pub(crate) fn take(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<bool> {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg, None),
+        };
+        let slot = match flag {
+            "--account" => &mut self.account,
+            "--zone" => &mut self.zone,
+            "--id" => &mut self.id,
+            "--name" => &mut self.name,
+            "--type" => &mut self.record_type,
+            "--content" => &mut self.content,
+            "--ttl" => &mut self.ttl,
+            "--out" => &mut self.out,
+            "--file" => &mut self.file,
+            "--from" => &mut self.from,
+            "--pause" => &mut self.pause,
+            "--source" => &mut self.source,
+            "--ip" => &mut self.ip,
+            "--interval" => &mut self.interval,
+            "--replace" if inline.is_none() => {
+                self.replace = true;
+                return Ok(true);
+            }
+            "--prune" if inline.is_none() => {
+                self.prune = true;
+                return Ok(true);
+            }
+            "--yes" if inline.is_none() => {
+                self.yes = true;
+                return Ok(true);
+            }
+            "--json" if inline.is_none() => {
+                self.json = true;
+                return Ok(true);
+            }
+            "--dry-run" if inline.is_none() => {
+                self.dry_run = true;
+                return Ok(true);
+            }
+            "--on-failure" => {
+                let value = match inline {
+                    Some(value) => value,
+                    None => rest.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                };
+                self.stop_on_failure = Some(match value.as_str() {
+                    "stop" => true,
+                    "continue" => false,
+                    other => {
+                        return Err(anyhow!("--on-failure {other} should be stop or continue"));
+                    }
+                });
+                return Ok(true);
+            }
+            "--proxied" => {
+                self.proxied = Some(match inline.as_deref() {
+                    None | Some("yes" | "true") => true,
+                    Some("no" | "false") => false,
+                    Some(other) => {
+                        return Err(anyhow!("--proxied={other} should be yes or no"));
+                    }
+                });
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+        let value = match inline {
+            Some(value) => value,
+            None => rest.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+        };
+        *slot = Some(value);
+        Ok(true)
+    }
+
+    /// The options that were given, by flag.
+    fn given(&self) -> Vec<&'static str> {
+        [
+            ("--json", self.json),
+            ("--zone", self.zone.is_some()),
+            ("--id", self.id.is_some()),
+            ("--name", self.name.is_some()),
+            ("--type", self.record_type.is_some()),
+            ("--content", self.content.is_some()),
+            ("--ttl", self.ttl.is_some()),
+            ("--proxied", self.proxied.is_some()),
+            ("--dry-run", self.dry_run),
+            ("--out", self.out.is_some()),
+            ("--file", self.file.is_some()),
+            ("--replace", self.replace),
+            ("--prune", self.prune),
+            ("--yes", self.yes),
+            ("--on-failure", self.stop_on_failure.is_some()),
+            ("--from", self.from.is_some()),
+            ("--pause", self.pause.is_some()),
+            ("--source", self.source.is_some()),
+            ("--ip", self.ip.is_some()),
+            ("--interval", self.interval.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect()
+    }
+}
+
+/// A subcommand with its options, checked to fit each other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Request {
+    pub(crate) command: Command,
+    pub(crate) options: Options,
+}
+
+impl Request {
+    pub(crate) fn new(command: Command, options: Options) -> Result<Self> {
+        let name = command.name();
+        if let Some(flag) = options
+            .given()
+            .into_iter()
+            .find(|flag| !command.accepts().contains(flag))
+        {
+            return Err(anyhow!("{flag} doesn't go with {name}"));
+        }
+        let identified =
+            options.id.is_some() || (options.name.is_some() && options.record_type.is_some());
+        let missing = match command {
+            Command::ListZones => None,
+            // The zone may also come from the file.
+            Command::ApplyState if options.file.is_none() => Some("--file"),
+            Command::ApplyState => None,
+            Command::Backup if options.out.is_none() => Some("--out"),
+            Command::Backup => None,
+            Command::Restore if options.from.is_none() => Some("--from"),
+            _ if options.zone.is_none() => Some("--zone"),
+            Command::CreateRecord
+                if options.name.is_none()
+                    || options.record_type.is_none()
+                    || options.content.is_none() =>
+            {
+                Some("--name, --type and --content")
+            }
+            Command::UpdateRecord | Command::DeleteRecord if !identified => {
+                Some("--id, or --name and --type")
+            }
+            Command::ImportZone if options.file.is_none() => Some("--file"),
+            Command::Ddns if options.name.is_none() => Some("--name"),
+            _ => None,
+        };
+        if let Some(missing) = missing {
+            return Err(anyhow!("{name} needs {missing}"));
+        }
+        let changes = if options.id.is_some() {
+            [
+                &options.name,
+                &options.record_type,
+                &options.content,
+                &options.ttl,
+            ]
+            .iter()
+            .any(|value| value.is_some())
+        } else {
+            options.content.is_some() || options.ttl.is_some()
+        };
+        if command == Command::UpdateRecord && !changes && options.proxied.is_none() {
+            return Err(anyhow!(
+                "{name} needs something to change: --content, --ttl or --proxied (or, with --id, --name or --type)"
+            ));
+        }
+        Ok(Self { command, options })
+    }
+}
+
+/// How a run that didn't fail ended, for the exit status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Finish {
+    Done,
+    /// `ddns` found the records already pointing at the address.
+    Unchanged,
+}
+
+/// Runs `request` against the accounts of the profile in use, journalling changes to
+/// `journal` as the TUI does. Notes that aren't results, such as a listing cut short or
+/// a backup's progress, go to `notes`; `confirm` asks whether to go ahead with an
+/// `apply` plan.
+pub(crate) fn run(
+    request: &Request,
+    config: &Config,
+    backend: &mut impl DnsBackend,
+    journal: &Journal,
+    out: &mut impl Write,
+    notes: &mut impl Write,
+    confirm: impl FnOnce(&str) -> Result<bool>,
+) -> Result<Finish> {
+    let options = &request.options;
+    if request.command == Command::Backup {
+        return backup::run(config, backend, options, out, notes).map(|()| Finish::Done);
+    }
+    let snapshot = match (options.from.as_deref(), options.zone.as_deref()) {
+        (Some(from), Some(zone)) => Some(Snapshot::find(
+            Path::new(from),
+            zone,
+            options.account.as_deref(),
+        )?),
+        _ => None,
+    };
+    // A restore goes back to the account the zone was saved from, unless told otherwise.
+    let account = pick_account(
+        config.profile_accounts(config.profile_name()),
+        options
+            .account
+            .as_deref()
+            .or(snapshot.as_ref().map(|snapshot| snapshot.account.as_str())),
+    )?;
+    let zones = backend
+        .list_zones(account)
+        .with_context(|| format!("Listing the zones of {}", account.name))?;
+    note_truncated(&zones, "zones", notes)?;
+    let (state, wanted) = match (request.command, options.file.as_deref()) {
+        (Command::ApplyState, Some(file)) => {
+            let state = StateFile::read(Path::new(file))?;
+            let wanted = state.zone_name(options.zone.as_deref())?.ok_or_else(|| {
+                anyhow!("apply needs --zone, or a zone = \"<name>\" line in {file}")
+            })?;
+            (Some(state), Some(wanted))
+        }
+        _ => (None, options.zone.clone()),
+    };
+    let Some(wanted) = wanted.as_deref() else {
+        return write_zones(&zones.items, options.json, out).map(|()| Finish::Done);
+    };
+    let zone = find_zone(&zones.items, wanted)
+        .ok_or_else(|| anyhow!("{} has no zone named {wanted}", account.name))?;
+    // Record ids are looked up directly, and ddns lists the zone on every check;
+    // everything else needs the zone's records.
+    let records = match (request.command, &options.id) {
+        (Command::UpdateRecord | Command::DeleteRecord, Some(_)) | (Command::Ddns, _) => Vec::new(),
+        _ => {
+            let records =
+                collect_pages(|page| backend.list_records_page(account, zone, page, None))
+                    .with_context(|| format!("Listing the records of {}", zone.name))?;
+            if records.truncated
+                && matches!(
+                    request.command,
+                    Command::ExportZone
+                        | Command::ImportZone
+                        | Command::ApplyState
+                        | Command::Restore
+                )
+            {
+                return Err(anyhow!(
+                    "{} has more records than {} pages hold; nothing done",
+                    zone.name,
+                    records.pages
+                ));
+            }
+            note_truncated(&records, "records", notes)?;
+            records.items
+        }
+    };
+    let change = Change {
+        account,
+        zone,
+        options,
+        journal,
+        stop: options.stop_on_failure.unwrap_or(config.stop_on_failure),
+        policy: Policy::from_config(config),
+    };
+    let done = match request.command {
+        Command::Ddns => {
+            let source = options.source.as_deref().or(config.ddns_source.as_deref());
+            return change.ddns(backend, source, out, notes);
+        }
+        Command::ListZones => write_zones(&zones.items, options.json, out),
+        Command::ListRecords => write_records(&records, options.json, out),
+        Command::CreateRecord => change.create(backend, &records, out, notes),
+        Command::UpdateRecord => change.update(backend, &records, out, notes),
+        Command::DeleteRecord => change.delete(backend, &records, out, notes),
+        Command::ExportZone => export_zone(zone, &records, options.out.as_deref(), out, notes),
+        Command::ImportZone => change.import(backend, &records, out, notes),
+        Command::ApplyState => {
+            let state = state.unwrap_or_default();
+            change.apply(backend, &records, &state, confirm, out, notes)
+        }
+        Command::Restore => {
+            let snapshot = snapshot.unwrap_or_default();
+            change.restore(backend, &records, &snapshot, out, notes)
+        }
+        Command::Backup => unreachable!("backups run before an account is picked"),
+    };
+    done.map(|()| Finish::Done)
+}
+
+/// Writes `records` as a BIND zone file to `path`, or to `out` without one.
+fn export_zone(
+    zone: &Zone,
+    records: &[DnsRecord],
+    path: Option<&str>,
+    out: &mut impl Write,
+    notes: &mut impl Write,
+) -> Result<()> {
+    let text = bind::write_zone(&zone.name, records);
+    let Some(path) = path else {
+        out.write_all(text.as_bytes())?;
+        return Ok(());
+    };
+    fs::write(path, text).with_context(|| format!("Writing {path}"))?;
+    writeln!(
+        notes,
+        "Wrote {} record(s) of {} to {path}",
+        records.len(),
+        zone.name
+    )?;
+    Ok(())
+}
+
+/// A step's outcome, as `--json` reports it once the step was tried.
+#[derive(Serialize)]
+struct Outcome<'a> {
+    #[serde(flatten)]
+    step: &'a Step,
+    /// `done`, `failed`, or `not sent` after an earlier failure.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A record change asked for on the command line, in the zone it's for.
+struct Change<'a> {
+    account: &'a Account,
+    zone: &'a Zone,
+    options: &'a Options,
+    journal: &'a Journal,
+    /// Whether a failed step stops the ones after it.
+    stop: bool,
+    policy: Policy,
+}
+
+impl Change<'_> {
+    fn create(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let options = self.options;
+        let mut draft = RecordDraft {
+            name: options.name.clone().unwrap_or_default(),
+            record_type: options.record_type.clone().unwrap_or_default(),
+            content: options.content.clone().unwrap_or_default(),
+            ..RecordDraft::default()
+        };
+        draft.record_type = draft.record_type.trim().to_uppercase();
+        if let Some(ttl) = self.account.default_ttl {
+            draft.ttl = ttl.to_string();
+        }
+        draft.proxied = self.proxied(&draft.record_type, None)?;
+        let record = self.apply_ttl(draft)?.to_record("new".to_string())?;
+        match find_create_conflict(records, &self.zone.name, &record) {
+            Some(CreateConflict::Duplicate { existing_id }) => {
+                return Err(anyhow!(
+                    "{} already has this {} record ({existing_id}); nothing created",
+                    self.zone.name,
+                    record.record_type
+                ));
+            }
+            Some(CreateConflict::CnameClash {
+                existing_id,
+                existing_type,
+            }) => {
+                return Err(anyhow!(
+                    "A CNAME can't share its name with the {existing_type} record {existing_id}; nothing created"
+                ));
+            }
+            None => {}
+        }
+        if options.dry_run {
+            return write_change("Would create", &record, options.json, out);
+        }
+        let created = backend
+            .create_record(self.account, self.zone, record)
+            .context("Creating the record")?;
+        self.journal(ChangeAction::Create, None, Some(&created), notes)?;
+        write_change("Created", &created, options.json, out)
+    }
+
+    fn update(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let options = self.options;
+        let before = self.target(backend, records, false)?;
+        if let Some(warning) = self.policy.check_update(&before)? {
+            writeln!(notes, "{warning}")?;
+        }
+        let mut draft = RecordDraft::from_record(&before);
+        if options.id.is_some() {
+            if let Some(name) = &options.name {
+                draft.name = name.clone();
+            }
+            if let Some(record_type) = &options.record_type {
+                draft.record_type = record_type.trim().to_uppercase();
+            }
+        }
+        if let Some(content) = &options.content {
+            draft.content = content.clone();
+        }
+        draft.proxied = self.proxied(&draft.record_type, Some(before.proxied))?;
+        let after = self.apply_ttl(draft)?.to_record(before.id.clone())?;
+        let unchanged = (&after.name, &after.record_type, &after.content)
+            == (&before.name, &before.record_type, &before.content)
+            && (after.ttl, after.proxied) == (before.ttl, before.proxied);
+        if unchanged {
+            writeln!(notes, "{} is already like that; nothing sent", before.name)?;
+            return write_change("Unchanged", &before, options.json, out);
+        }
+        if options.dry_run {
+            return write_change("Would update", &after, options.json, out);
+        }
+        let updated = backend
+            .update_record(self.account, self.zone, after)
+            .context("Updating the record")?;
+        self.journal(ChangeAction::Update, Some(&before), Some(&updated), notes)?;
+        write_change("Updated", &updated, options.json, out)
+    }
+
+    fn delete(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let before = self.target(backend, records, true)?;
+        if self.options.dry_run {
+            return write_change("Would delete", &before, self.options.json, out);
+        }
+        backend
+            .delete_record(self.account, self.zone, &before.id)
+            .context("Deleting the record")?;
+        self.journal(ChangeAction::Delete, Some(&before), None, notes)?;
+        write_change("Deleted", &before, self.options.json, out)
+    }
+
+    /// Makes the zone hold the records of `--file`: creates what's missing, updates the
+    /// TTL and proxying of what differs, and with `--replace` deletes what the file
+    /// doesn't have. Records are matched by name, type and content; SOA records are
+    /// left to the provider. Each step is reported, and any failure fails the run.
+    fn import(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let options = self.options;
+        let file = options.file.as_deref().unwrap_or_default();
+        let text = fs::read_to_string(file).with_context(|| format!("Reading {file}"))?;
+        let zone_name = &self.zone.name;
+        let is_soa = |record: &DnsRecord| record.record_type.eq_ignore_ascii_case("SOA");
+        let key = |record: &DnsRecord| {
+            let record_type = record.record_type.to_ascii_uppercase();
+            let content = match record_type.as_str() {
+                "TXT" | "SPF" => bind::normalize_txt(&record.content),
+                _ => record.content.trim().to_string(),
+            };
+            (qualify_name(&record.name, zone_name), record_type, content)
+        };
+        let has_proxy = self.account.provider.has_proxy();
+        let wanted: Vec<DnsRecord> = bind::parse_zone(&text, zone_name)
+            .with_context(|| format!("Reading {file}"))?
+            .into_iter()
+            .filter(|record| !is_soa(record))
+            .map(|mut record| {
+                record.proxied &= has_proxy && is_proxiable(&record.record_type);
+                record
+            })
+            .collect();
+        let existing: Vec<&DnsRecord> = records.iter().filter(|record| !is_soa(record)).collect();
+
+        let mut deletes = Vec::new();
+        if options.replace {
+            deletes.extend(
+                existing
+                    .iter()
+                    .filter(|record| !wanted.iter().any(|w| key(w) == key(record)))
+                    .map(|record| Step::Delete {
+                        record: (*record).clone(),
+                    }),
+            );
+        }
+        let mut updates = Vec::new();
+        let mut creates = Vec::new();
+        let mut unchanged = 0;
+        for record in &wanted {
+            match existing.iter().find(|e| key(e) == key(record)) {
+                Some(found) if (found.ttl, found.proxied) == (record.ttl, record.proxied) => {
+                    unchanged += 1;
+                }
+                Some(found) => updates.push(Step::Update {
+                    before: (*found).clone(),
+                    after: DnsRecord {
+                        ttl: record.ttl,
+                        proxied: record.proxied,
+                        ..(*found).clone()
+                    },
+                }),
+                None => creates.push(Step::Create {
+                    record: record.clone(),
+                }),
+            }
+        }
+
+        let mut set = ChangeSet::ordered(deletes, updates, creates);
+        set.unchanged = unchanged;
+        self.check_updates(&set, notes)?;
+        if options.dry_run {
+            self.write_set(&set, out)?;
+            writeln!(notes, "Would import into {zone_name}: {}", set.summary())?;
+            return Ok(());
+        }
+        self.send(backend, &set, out, notes)?;
+        writeln!(
+            notes,
+            "Imported into {zone_name}: {} change(s) made, {unchanged} unchanged",
+            set.steps.len()
+        )?;
+        Ok(())
+    }
+
+    /// Converges the zone on a desired-state file: prints the plan as a diff (or as JSON
+    /// lines), then sends it once `--yes` or `confirm` says so. A dry run, or a plan
+    /// with nothing in it, stops after the diff.
+    fn apply(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        state: &StateFile,
+        confirm: impl FnOnce(&str) -> Result<bool>,
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let options = self.options;
+        let file = options.file.as_deref().unwrap_or_default();
+        let desired = state
+            .records(&self.zone.name, self.account)
+            .with_context(|| format!("Reading {file}"))?;
+        let set = sync::plan(records, &desired, options.prune);
+        self.check_updates(&set, notes)?;
+        self.write_set(&set, out)?;
+        // The diff has to be on screen before the question.
+        out.flush()?;
+        let summary = format!("{}: {}", self.zone.name, set.summary());
+        if set.steps.is_empty() {
+            writeln!(notes, "Nothing to change in {summary}")?;
+            return Ok(());
+        }
+        if options.dry_run {
+            writeln!(notes, "Plan for {summary}")?;
+            return Ok(());
+        }
+        let prompt = format!(
+            "Apply {} change(s) to {} ({})? [y/N] ",
+            set.steps.len(),
+            self.zone.name,
+            self.account.name
+        );
+        if !options.yes && !confirm(&prompt)? {
+            writeln!(notes, "Nothing applied")?;
+            return Ok(());
+        }
+        self.send(backend, &set, out, notes)?;
+        writeln!(
+            notes,
+            "Applied {} change(s) to {}",
+            set.steps.len(),
+            self.zone.name
+        )?;
+        Ok(())
+    }
+
+    /// Creates the records of a backed-up zone that the zone lacks, one by one; what it
+    /// has already is left alone. `--dry-run` prints them as a diff instead.
+    fn restore(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        snapshot: &Snapshot,
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let set = snapshot.change_set(records, &self.zone.name);
+        if self.options.dry_run {
+            self.write_set(&set, out)?;
+            writeln!(
+                notes,
+                "Would restore {} from {}: {}",
+                self.zone.name,
+                snapshot.path.display(),
+                set.summary()
+            )?;
+            return Ok(());
+        }
+        self.send(backend, &set, out, notes)?;
+        writeln!(
+            notes,
+            "Restored {} record(s) into {}; {} were already there",
+            set.steps.len(),
+            self.zone.name,
+            set.unchanged
+        )?;
+        Ok(())
+    }
+
+    /// Points `--name`'s A (or `--type`'s) records at the current public address, once
+    /// or every `--interval` seconds. Checking once, a failure is the run's; looping, it's
+    /// logged with the time and the next check comes as usual.
+    fn ddns(
+        &self,
+        backend: &mut impl DnsBackend,
+        source: Option<&str>,
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<Finish> {
+        let options = self.options;
+        let families = options
+            .record_type
+            .as_deref()
+            .unwrap_or("A")
+            .split(',')
+            .map(Family::of_type)
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(source) = source {
+            ddns::check_source(source)?;
+        }
+        let given = match &options.ip {
+            Some(ip) if families.len() > 1 => {
+                return Err(anyhow!("--ip {ip} sets one type; pick it with --type"));
+            }
+            Some(ip) => Some(ddns::parse_answer(ip, families[0]).context("--ip")?),
+            None => None,
+        };
+        let interval = match options.interval.as_deref() {
+            None => None,
+            Some(secs) => match secs.trim().parse::<u64>() {
+                Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+                _ => return Err(anyhow!("--interval {secs} should be a number of seconds")),
+            },
+        };
+        let client = Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .context("Setting up the address lookup")?;
+        loop {
+            let mut changed = false;
+            let mut result = Ok(());
+            for &family in &families {
+                let ip = match given {
+                    Some(ip) => Ok(ip),
+                    None => {
+                        let source = source.unwrap_or(family.default_source());
+                        ddns::detect(family, source, &client)
+                    }
+                };
+                let checked =
+                    ip.and_then(|ip| self.point_at(backend, family, &ip.to_string(), out, notes));
+                match checked {
+                    Ok(step) => changed |= step,
+                    Err(err) => result = Err(err),
+                }
+            }
+            out.flush()?;
+            let Some(interval) = interval else {
+                result?;
+                return Ok(if changed {
+                    Finish::Done
+                } else {
+                    Finish::Unchanged
+                });
+            };
+            if let Err(err) = result {
+                writeln!(notes, "{}: {err:#}", format_timestamp(now_unix()))?;
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    /// Makes `--name`'s `family` record hold `ip`; whether anything was (or, in a dry
+    /// run, would be) sent.
+    fn point_at(
+        &self,
+        backend: &mut impl DnsBackend,
+        family: Family,
+        ip: &str,
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<bool> {
+        let options = self.options;
+        let record_type = family.record_type();
+        let name = qualify_name(options.name.as_deref().unwrap_or_default(), &self.zone.name);
+        let records =
+            collect_pages(|page| backend.list_records_page(self.account, self.zone, page, None))
+                .with_context(|| format!("Listing the records of {}", self.zone.name))?;
+        let existing: Vec<&DnsRecord> = records
+            .items
+            .iter()
+            .filter(|record| qualify_name(&record.name, &self.zone.name) == name)
+            .filter(|record| record.record_type.eq_ignore_ascii_case(record_type))
+            .collect();
+        let mut draft = match existing.as_slice() {
+            [before] => RecordDraft::from_record(before),
+            _ => RecordDraft {
+                name: name.clone(),
+                record_type: record_type.to_string(),
+                ttl: self.account.default_ttl.unwrap_or(1).to_string(),
+                ..RecordDraft::default()
+            },
+        };
+        draft.content = ip.to_string();
+        let was = existing.first().map(|record| record.proxied);
+        draft.proxied = self.proxied(record_type, was)?;
+        let wanted = self.apply_ttl(draft)?.to_record("new".to_string())?;
+        let Some(step) = ddns::step(&existing, &wanted)? else {
+            // Looping, only changes are logged.
+            if options.interval.is_none() {
+                writeln!(notes, "{record_type} {name} is already {ip}")?;
+            }
+            return Ok(false);
+        };
+        if let Step::Update { before, .. } = &step
+            && let Some(warning) = self.policy.check_update(before)?
+        {
+            writeln!(notes, "{warning}")?;
+        }
+        if options.dry_run {
+            let verb = match step {
+                Step::Create { .. } => "Would create",
+                _ => "Would update",
+            };
+            write_change(verb, step.record(), options.json, out)?;
+            return Ok(true);
+        }
+        let (verb, done) = match &step {
+            Step::Create { record } => {
+                let created = backend
+                    .create_record(self.account, self.zone, record.clone())
+                    .context("Creating the record")?;
+                self.journal(ChangeAction::Create, None, Some(&created), notes)?;
+                ("Created", created)
+            }
+            Step::Update { before, after } => {
+                let updated = backend
+                    .update_record(self.account, self.zone, after.clone())
+                    .context("Updating the record")?;
+                self.journal(ChangeAction::Update, Some(before), Some(&updated), notes)?;
+                ("Updated", updated)
+            }
+            Step::Delete { .. } => unreachable!("ddns never deletes"),
+        };
+        if self.options.interval.is_some() {
+            writeln!(
+                notes,
+                "{}: {verb} {record_type} {name} → {ip}",
+                format_timestamp(now_unix())
+            )?;
+        }
+        write_change(verb, &done, options.json, out)?;
+        Ok(true)
+    }
+
+    /// Runs `set`'s updates past the policy as `update` does, before anything is shown
+    /// or sent: strict mode refuses the whole set over one that would drop fields
+    /// nyxflare doesn't model; otherwise each loss is noted.
+    fn check_updates(&self, set: &ChangeSet, notes: &mut impl Write) -> Result<()> {
+        for step in &set.steps {
+            if let Step::Update { before, .. } = step
+                && let Some(warning) = self.policy.check_update(before)?
+            {
+                writeln!(notes, "{warning}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints `set` before anything is sent: as a diff, or as JSON lines without a
+    /// `status`.
+    fn write_set(&self, set: &ChangeSet, out: &mut impl Write) -> Result<()> {
+        if self.options.json {
+            write_json_lines(&set.steps, out)
+        } else {
+            set.write_diff(self.options.color, out)
+        }
+    }
+
+    /// Sends `set` in order, journalling each step done and reporting every step's
+    /// outcome. A failure is noted on `notes` and the rest are still sent, unless
+    /// stopping on failure, when they're reported as not sent. Fails when a step did.
+    fn send(
+        &self,
+        backend: &mut impl DnsBackend,
+        set: &ChangeSet,
+        out: &mut impl Write,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        let (mut failed, mut not_sent) = (0, 0);
+        for step in &set.steps {
+            if failed > 0 && self.stop {
+                not_sent += 1;
+                self.report(step, "not sent", None, out)?;
+                continue;
+            }
+            let result = match step {
+                Step::Create { record } => backend
+                    .create_record(self.account, self.zone, record.clone())
+                    .map(|record| Step::Create { record }),
+                Step::Update { before, after } => backend
+                    .update_record(self.account, self.zone, after.clone())
+                    .map(|after| Step::Update {
+                        before: before.clone(),
+                        after,
+                    }),
+                Step::Delete { record } => backend
+                    .delete_record(self.account, self.zone, &record.id)
+                    .map(|()| step.clone()),
+            };
+            let done = match result {
+                Ok(done) => done,
+                Err(err) => {
+                    let record = step.record();
+                    writeln!(
+                        notes,
+                        "Couldn't {} {} {} {}: {err:#}",
+                        step.verb(),
+                        record.record_type,
+                        record.name,
+                        record.content
+                    )?;
+                    failed += 1;
+                    self.report(step, "failed", Some(format!("{err:#}")), out)?;
+                    continue;
+                }
+            };
+            match &done {
+                Step::Create { record } => {
+                    self.journal(ChangeAction::Create, None, Some(record), notes)?;
+                }
+                Step::Update { before, after } => {
+                    self.journal(ChangeAction::Update, Some(before), Some(after), notes)?;
+                }
+                Step::Delete { record } => {
+                    self.journal(ChangeAction::Delete, Some(record), None, notes)?;
+                }
+            }
+            self.report(&done, "done", None, out)?;
+        }
+        if failed == 0 {
+            return Ok(());
+        }
+        let mut message = format!("{failed} of {} change(s) failed", set.steps.len());
+        if not_sent > 0 {
+            message.push_str(&format!("; stopped there, {not_sent} not sent"));
+        }
+        Err(anyhow!(message))
+    }
+
+    /// A step's outcome on `out`: with `--json`, the step with its `status` (and
+    /// `error`); otherwise a `Created …` line for a step done, failures being noted.
+    fn report(
+        &self,
+        step: &Step,
+        status: &'static str,
+        error: Option<String>,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        if self.options.json {
+            let outcome = Outcome {
+                step,
+                status,
+                error,
+            };
+            writeln!(out, "{}", serde_json::to_string(&outcome)?)?;
+        } else if status == "done" {
+            let verb = match step {
+                Step::Create { .. } => "Created",
+                Step::Update { .. } => "Updated",
+                Step::Delete { .. } => "Deleted",
+            };
+            write_change(verb, step.record(), false, out)?;
+        }
+        Ok(())
+    }
+
+    /// The record `--id` names, else the one record with `--name` and `--type` (and
+    /// `--content`, when `by_content`).
+    fn target(
+        &self,
+        backend: &mut impl DnsBackend,
+        records: &[DnsRecord],
+        by_content: bool,
+    ) -> Result<DnsRecord> {
+        let options = self.options;
+        if let Some(id) = &options.id {
+            return backend
+                .get_record(self.account, self.zone, id)
+                .with_context(|| format!("Fetching record {id}"));
+        }
+        let name = qualify_name(options.name.as_deref().unwrap_or_default(), &self.zone.name);
+        let record_type = options.record_type.as_deref().unwrap_or_default().trim();
+        // An update's --content is the new content, not part of what to look for.
+        let content = options.content.as_deref().filter(|_| by_content);
+        let matches: Vec<&DnsRecord> = records
+            .iter()
+            .filter(|record| qualify_name(&record.name, &self.zone.name) == name)
+            .filter(|record| record.record_type.eq_ignore_ascii_case(record_type))
+            .filter(|record| content.is_none_or(|content| record.content.trim() == content.trim()))
+            .collect();
+        match matches.as_slice() {
+            [record] => Ok((*record).clone()),
+            [] => Err(anyhow!(
+                "No {record_type} record named {name} in {}",
+                self.zone.name
+            )),
+            several => {
+                let ids: Vec<&str> = several.iter().map(|record| record.id.as_str()).collect();
+                Err(anyhow!(
+                    "{} {record_type} records are named {name}; pick one with --id: {}",
+                    several.len(),
+                    ids.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Whether the record goes through the proxy: `--proxied` if given, else what it was,
+    /// else the account's default where the provider has a proxy and the type allows it.
+    fn proxied(&self, record_type: &str, was: Option<bool>) -> Result<bool> {
+        let allowed = self.account.provider.has_proxy() && is_proxiable(record_type);
+        match self.options.proxied {
+            Some(true) if !allowed => Err(anyhow!(
+                "{record_type} records can't be proxied on {}",
+                self.account.provider.label()
+            )),
+            Some(proxied) => Ok(proxied),
+            None => Ok(allowed && was.or(self.account.default_proxied).unwrap_or(true)),
+        }
+    }
+
+    /// `draft` with `--ttl`, read as `auto` or 30 to 86400 seconds, when it's given.
+    fn apply_ttl(&self, mut draft: RecordDraft) -> Result<RecordDraft> {
+        if let Some(ttl) = &self.options.ttl {
+            draft.ttl = parse_bulk_ttl(ttl)?.to_string();
+        }
+        Ok(draft)
+    }
+
+    /// Appends the change to the journal, unless it was only a dry run; like the TUI, a
+    /// failure is only a note.
+    fn journal(
+        &self,
+        action: ChangeAction,
+        before: Option<&DnsRecord>,
+        after: Option<&DnsRecord>,
+        notes: &mut impl Write,
+    ) -> Result<()> {
+        if self.options.dry_run {
+            return Ok(());
+        }
+        let Some(record_id) = after.or(before).map(|record| record.id.clone()) else {
+            return Ok(());
+        };
+        let entry = JournalEntry {
+            timestamp: now_unix(),
+            account: self.account.name.clone(),
+            zone_id: self.zone.id.clone(),
+            record_id,
+            action,
+            before: before.cloned(),
+            after: after.cloned(),
+        };
+        if let Err(err) = self.journal.append(&entry) {
+            writeln!(notes, "Journal not written: {err:#}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The account named `name`, or the only one when no name is given; either way one
+/// whose secret is at hand.
+fn pick_account<'a>(accounts: &'a [Account], name: Option<&str>) -> Result<&'a Account> {
+    let names = || {
+        accounts
+            .iter()
+            .map(|account| account.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let account = match (name, accounts) {
+        (_, []) => return Err(anyhow!("The config has no accounts yet")),
+        (Some(name), _) => accounts
+            .iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| anyhow!("No account named {name:?}; the config has {}", names()))?,
+        (None, [only]) => only,
+        (None, _) => return Err(anyhow!("Pick an account with --account: {}", names())),
+    };
+    check_secret(account)?;
+    Ok(account)
+}
+
+/// Fails when `account`'s secret isn't at hand.
+pub(crate) fn check_secret(account: &Account) -> Result<()> {
+    if let Some(reason) = &account.token_error {
+        return Err(anyhow!("{} has no secret: {reason}", account.name));
+    }
+    if account.lacks_secret() {
+        return Err(anyhow!(
+            "{} has no secret yet; enter it in nyxflare first",
+            account.name
+        ));
+    }
+    Ok(())
+}
+
+/// `name` among `zones`, ignoring case and a trailing dot.
+fn find_zone<'a>(zones: &'a [Zone], name: &str) -> Option<&'a Zone> {
+    let name = name.trim().trim_end_matches('.');
+    zones
+        .iter()
+        .find(|zone| zone.name.trim_end_matches('.').eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn note_truncated<T>(
+    listing: &Listing<T>,
+    what: &str,
+    notes: &mut impl Write,
+) -> Result<()> {
+    if listing.truncated {
+        writeln!(
+            notes,
+            "Only the first {} {what}: stopped after {} pages",
+            listing.items.len(),
+            listing.pages
+        )?;
+    }
+    Ok(())
+}
+
+fn write_zones(zones: &[Zone], json: bool, out: &mut impl Write) -> Result<()> {
+    if json {
+        return write_json_lines(zones, out);
+    }
+    let rows = zones
+        .iter()
+        .map(|zone| {
+            vec![
+                zone.name.clone(),
+                zone.status.clone().unwrap_or_default(),
+                zone.id.clone(),
+            ]
+        })
+        .collect();
+    write_table(&["NAME", "STATUS", "ID"], rows, out)
+}
+
+fn write_records(records: &[DnsRecord], json: bool, out: &mut impl Write) -> Result<()> {
+    if json {
+        return write_json_lines(records, out);
+    }
+    let rows = records
+        .iter()
+        .map(|record| {
+            vec![
+                record.record_type.clone(),
+                record.name.clone(),
+                record.content.clone(),
+                record.ttl.to_string(),
+                if record.proxied { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect();
+    write_table(&["TYPE", "NAME", "CONTENT", "TTL", "PROXIED"], rows, out)
+}
+
+/// A changed record: the record as a JSON line, or a line saying what became of it.
+fn write_change(verb: &str, record: &DnsRecord, json: bool, out: &mut impl Write) -> Result<()> {
+    if json {
+        return write_json_lines(std::slice::from_ref(record), out);
+    }
+    let proxied = if record.proxied { ", proxied" } else { "" };
+    writeln!(
+        out,
+        "{verb} {} {} {} (TTL {}{proxied}, id {})",
+        record.record_type,
+        record.name,
+        record.content,
+        ttl_label(record.ttl),
+        record.id
+    )?;
+    Ok(())
+}
+
+fn write_json_lines<T: serde::Serialize>(items: &[T], out: &mut impl Write) -> Result<()> {
+    for item in items {
+        writeln!(out, "{}", serde_json::to_string(item)?)?;
+    }
+    Ok(())
+}
+
+/// `rows` under `header`, each column as wide as its widest cell.
+fn write_table(header: &[&str], rows: Vec<Vec<String>>, out: &mut impl Write) -> Result<()> {
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header = header.iter().map(|title| title.to_string()).collect();
+    for row in std::iter::once(header).chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,602 @@
+//! Background fetching of zone lists and record pages, so a slow API call never
+//! blocks the event loop. Requests carry the generation the app was on when it issued
+//! them; the app drops responses from older generations. Record writes and the one-off
+//! calls behind actions such as deleting a zone go through the same queue, so a fetch
+//! issued after them sees their result; their outcomes are always applied.
+//!
+//! A fetch the app has moved on from is abandoned rather than finished: the backend
+//! watches a `Cancel` and gives up between pages and retries, so the worker and the
+//! backend are free again for the next request.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use std::path::PathBuf;
+
+use crate::{
+    Account, AuditEntry, BatchRequest, BatchResult, CloudflareAccount, DnsBackend, DnsRecord,
+    DnssecStatus, ImportResult, Listing, Page, TokenStatus, Zone, ZoneSetup, collect_pages,
+};
+
+/// A fetch the app wants done.
+#[derive(Clone, Debug)]
+pub(crate) enum Load {
+    Zones {
+        account: Account,
+    },
+    Records {
+        account: Account,
+        zone: Zone,
+        page: u32,
+        record_type: Option<&'static str>,
+    },
+    /// A record change the app has already shown; `write_id` matches the outcome to it.
+    Write {
+        account: Account,
+        zone: Zone,
+        write_id: u64,
+        write: Box<RecordWrite>,
+    },
+    /// A backend call behind an action, whose outcome the app carries on from.
+    Call {
+        account: Account,
+        call: Box<Call>,
+    },
+}
+
+/// A one-off backend call for an action the user took.
+#[derive(Clone, Debug)]
+pub(crate) enum Call {
+    /// Checks the credentials of the current account.
+    Verify,
+    /// Checks a new account's credentials and, when it has no account ID, looks up the
+    /// accounts they reach.
+    AddAccount,
+    Search {
+        zone: Zone,
+        query: String,
+    },
+    /// Deletes `records` in one atomic batch, leaving out any changed on the server since
+    /// they were loaded.
+    DeleteRecords {
+        zone: Zone,
+        records: Vec<DnsRecord>,
+    },
+    Export {
+        zone: Zone,
+    },
+    CreateZone {
+        name: String,
+        setup: ZoneSetup,
+    },
+    /// Counts the zone's records for the delete confirmation.
+    CountRecords {
+        zone: Zone,
+    },
+    DeleteZone {
+        zone: Zone,
+    },
+    Import {
+        zone: Zone,
+        path: PathBuf,
+        proxied: bool,
+    },
+    /// Lists every record in the zone to plan converging it on `desired`.
+    PlanApply {
+        zone: Zone,
+        desired: Vec<DnsRecord>,
+        prune: bool,
+    },
+    Dnssec {
+        zone: Zone,
+    },
+    SetDnssec {
+        zone: Zone,
+        enabled: bool,
+    },
+    AuditLog {
+        zone: Zone,
+        record_id: String,
+    },
+}
+
+/// What a `Call` got back, along with what it was asked.
+#[derive(Debug)]
+pub(crate) enum Called {
+    Verified(Result<TokenStatus>),
+    /// `choices` is empty when the account has an ID or they couldn't be listed.
+    AccountChecked {
+        token: Result<TokenStatus>,
+        choices: Vec<CloudflareAccount>,
+    },
+    Searched {
+        zone: Zone,
+        query: String,
+        result: Result<Vec<DnsRecord>>,
+    },
+    RecordsDeleted {
+        zone: Zone,
+        /// The records the batch was for.
+        records: Vec<DnsRecord>,
+        /// The server's copies of the records left out because they changed.
+        changed: Vec<DnsRecord>,
+        result: Result<BatchResult>,
+    },
+    Exported {
+        zone: Zone,
+        result: Result<String>,
+    },
+    ZoneCreated(Result<Zone>),
+    Counted {
+        zone: Zone,
+        result: Result<usize>,
+    },
+    ZoneDeleted {
+        zone: Zone,
+        result: Result<()>,
+    },
+    Imported {
+        zone: Zone,
+        result: Result<ImportResult>,
+    },
+    Planned {
+        zone: Zone,
+        desired: Vec<DnsRecord>,
+        prune: bool,
+        result: Result<Listing<DnsRecord>>,
+    },
+    Dnssec {
+        zone: Zone,
+        result: Result<DnssecStatus>,
+    },
+    DnssecSet {
+        zone: Zone,
+        enabled: bool,
+        result: Result<DnssecStatus>,
+    },
+    AuditLog {
+        zone: Zone,
+        record_id: String,
+        result: Result<Vec<AuditEntry>>,
+    },
+}
+
+/// A record change to send. `loaded` is the copy the change was based on: when set, the
+/// record is re-fetched first and nothing is written if it changed on the server since.
+#[derive(Clone, Debug)]
+pub(crate) enum RecordWrite {
+    Create(DnsRecord),
+    Update {
+        record: DnsRecord,
+        loaded: Option<DnsRecord>,
+    },
+    Delete {
+        record_id: String,
+        loaded: Option<DnsRecord>,
+    },
+}
+
+/// What a `RecordWrite` did.
+#[derive(Debug)]
+pub(crate) enum Written {
+    Created(DnsRecord),
+    Updated(DnsRecord),
+    Deleted,
+    /// The record as it is on the server, which differs from the one the change was
+    /// based on; nothing was written.
+    Conflict(DnsRecord),
+}
+
+/// The outcome of a `Load`, echoing what was asked for.
+#[derive(Debug)]
+pub(crate) enum Loaded {
+    Zones {
+        account: Account,
+        listing: Listing<Zone>,
+    },
+    Records {
+        account: Account,
+        zone: Zone,
+        record_type: Option<&'static str>,
+        page: Page<DnsRecord>,
+    },
+    Written {
+        write_id: u64,
+        result: Result<Written>,
+    },
+    Called {
+        account: Account,
+        called: Box<Called>,
+    },
+}
+
+impl Load {
+    /// Whether the load only fetches, so it can be abandoned once the app moves on.
+    fn is_fetch(&self) -> bool {
+        matches!(self, Load::Zones { .. } | Load::Records { .. })
+    }
+
+    /// What the load is doing, for the status line while it runs.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Load::Zones { account } => format!("Loading zones for {}", account.name),
+            Load::Records { zone, page: 1, .. } => format!("Loading records for {}", zone.name),
+            Load::Records { zone, page, .. } => {
+                format!("Loading records for {} (page {page})", zone.name)
+            }
+            Load::Write { zone, .. } => format!("Saving a record in {}", zone.name),
+            Load::Call { account, call } => call.describe(account),
+        }
+    }
+
+    pub(crate) fn run<B: DnsBackend>(self, backend: &mut B) -> Result<Loaded> {
+        match self {
+            Load::Zones { account } => {
+                let listing = backend.list_zones(&account)?;
+                Ok(Loaded::Zones { account, listing })
+            }
+            Load::Records {
+                account,
+                zone,
+                page,
+                record_type,
+            } => {
+                let page = backend.list_records_page(&account, &zone, page, record_type)?;
+                Ok(Loaded::Records {
+                    account,
+                    zone,
+                    record_type,
+                    page,
+                })
+            }
+            Load::Write {
+                account,
+                zone,
+                write_id,
+                write,
+            } => Ok(Loaded::Written {
+                write_id,
+                result: write.run(backend, &account, &zone),
+            }),
+            Load::Call { account, call } => {
+                let called = call.run(backend, &account);
+                Ok(Loaded::Called {
+                    account,
+                    called: Box::new(called),
+                })
+            }
+        }
+    }
+}
+
+impl Call {
+    /// What the call is doing, for the status line while it runs.
+    pub(crate) fn describe(&self, account: &Account) -> String {
+        match self {
+            Call::Verify => format!("Verifying the key for {}", account.name),
+            Call::AddAccount => format!("Verifying {}", account.name),
+            Call::Search { zone, query } => format!("Searching {} for \"{query}\"", zone.name),
+            Call::DeleteRecords { zone, records } => {
+                format!("Deleting {} record(s) from {}", records.len(), zone.name)
+            }
+            Call::Export { zone } => format!("Exporting {}", zone.name),
+            Call::CreateZone { name, .. } => format!("Creating zone {name}"),
+            Call::CountRecords { zone } => format!("Counting the records in {}", zone.name),
+            Call::DeleteZone { zone } => format!("Deleting zone {}", zone.name),
+            Call::Import { zone, .. } => format!("Importing into {}", zone.name),
+            Call::PlanApply { zone, .. } => format!("Listing the records of {}", zone.name),
+            Call::Dnssec { zone } => format!("Looking up DNSSEC for {}", zone.name),
+            Call::SetDnssec { zone, enabled } => format!(
+                "Turning DNSSEC {} for {}",
+                if *enabled { "on" } else { "off" },
+                zone.name
+            ),
+            Call::AuditLog { record_id, .. } => format!("Loading the history of {record_id}"),
+        }
+    }
+
+    fn run<B: DnsBackend>(self, backend: &mut B, account: &Account) -> Called {
+        match self {
+            Call::Verify => Called::Verified(backend.verify_account(account)),
+            Call::AddAccount => {
+                let token = backend.verify_account(account);
+                let discover = account.account_id.is_none()
+                    && token.as_ref().is_ok_and(TokenStatus::is_active);
+                // Tokens without Account Settings read access can't list accounts;
+                // zones still load, just without the account filter.
+                let choices = if discover {
+                    backend.list_cf_accounts(account).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                Called::AccountChecked { token, choices }
+            }
+            Call::Search { zone, query } => {
+                let result = backend.search_records(account, &zone, &query);
+                Called::Searched {
+                    zone,
+                    query,
+                    result,
+                }
+            }
+            Call::DeleteRecords { zone, records } => {
+                let (records, changed) =
+                    match split_changed(backend, account, &zone, records.clone()) {
+                        Ok(split) => split,
+                        Err(err) => {
+                            return Called::RecordsDeleted {
+                                zone,
+                                records,
+                                changed: Vec::new(),
+                                result: Err(err),
+                            };
+                        }
+                    };
+                let result = if records.is_empty() {
+                    Ok(BatchResult::default())
+                } else {
+                    let request = BatchRequest {
+                        deletes: records.iter().map(|r| r.id.clone()).collect(),
+                        ..BatchRequest::default()
+                    };
+                    backend.batch(account, &zone, request)
+                };
+                Called::RecordsDeleted {
+                    zone,
+                    records,
+                    changed,
+                    result,
+                }
+            }
+            Call::Export { zone } => {
+                let result = backend.export_zone(account, &zone);
+                Called::Exported { zone, result }
+            }
+            Call::CreateZone { name, setup } => {
+                Called::ZoneCreated(backend.create_zone(account, &name, setup))
+            }
+            Call::CountRecords { zone } => {
+                let result = backend
+                    .list_records_page(account, &zone, 1, None)
+                    .map(|first| {
+                        first
+                            .total_count
+                            .map(|n| n as usize)
+                            .unwrap_or(first.items.len())
+                    });
+                Called::Counted { zone, result }
+            }
+            Call::DeleteZone { zone } => {
+                let result = backend.delete_zone(account, &zone);
+                Called::ZoneDeleted { zone, result }
+            }
+            Call::Import {
+                zone,
+                path,
+                proxied,
+            } => {
+                let result = backend.import_zone(account, &zone, &path, proxied);
+                Called::Imported { zone, result }
+            }
+            Call::PlanApply {
+                zone,
+                desired,
+                prune,
+            } => {
+                let result =
+                    collect_pages(|page| backend.list_records_page(account, &zone, page, None));
+                Called::Planned {
+                    zone,
+                    desired,
+                    prune,
+                    result,
+                }
+            }
+            Call::Dnssec { zone } => {
+                let result = backend.dnssec_status(account, &zone);
+                Called::Dnssec { zone, result }
+            }
+            Call::SetDnssec { zone, enabled } => {
+                let result = backend.set_dnssec(account, &zone, enabled);
+                Called::DnssecSet {
+                    zone,
+                    enabled,
+                    result,
+                }
+            }
+            Call::AuditLog { zone, record_id } => {
+                let result = backend.record_audit_log(account, &zone, &record_id);
+                Called::AuditLog {
+                    zone,
+                    record_id,
+                    result,
+                }
+            }
+        }
+    }
+}
+
+impl RecordWrite {
+    fn run<B: DnsBackend>(
+        self,
+        backend: &mut B,
+        account: &Account,
+        zone: &Zone,
+    ) -> Result<Written> {
+        match self {
+            RecordWrite::Create(record) => Ok(Written::Created(
+                backend.create_record(account, zone, record)?,
+            )),
+            RecordWrite::Update { record, loaded } => {
+                if let Some(remote) = remote_change(backend, account, zone, loaded.as_ref())? {
+                    return Ok(Written::Conflict(remote));
+                }
+                Ok(Written::Updated(
+                    backend.update_record(account, zone, record)?,
+                ))
+            }
+            RecordWrite::Delete { record_id, loaded } => {
+                if let Some(remote) = remote_change(backend, account, zone, loaded.as_ref())? {
+                    return Ok(Written::Conflict(remote));
+                }
+                backend.delete_record(account, zone, &record_id)?;
+                Ok(Written::Deleted)
+            }
+        }
+    }
+}
+
+/// Re-fetches `loaded` and returns the server's copy when its `modified_on` differs,
+/// i.e. someone changed the record since it was loaded.
+fn remote_change<B: DnsBackend>(
+    backend: &mut B,
+    account: &Account,
+    zone: &Zone,
+    loaded: Option<&DnsRecord>,
+) -> Result<Option<DnsRecord>> {
+    let Some(loaded) = loaded else {
+        return Ok(None);
+    };
+    let remote = backend.get_record(account, zone, &loaded.id)?;
+    Ok((remote.modified_on != loaded.modified_on).then_some(remote))
+}
+
+/// Splits `records` into the ones unchanged on the server since they were loaded, and
+/// the server's copies of the ones that changed.
+fn split_changed<B: DnsBackend>(
+    backend: &mut B,
+    account: &Account,
+    zone: &Zone,
+    records: Vec<DnsRecord>,
+) -> Result<(Vec<DnsRecord>, Vec<DnsRecord>)> {
+    let mut unchanged = Vec::new();
+    let mut changed = Vec::new();
+    for record in records {
+        match remote_change(backend, account, zone, Some(&record))? {
+            Some(remote) => changed.push(remote),
+            None => unchanged.push(record),
+        }
+    }
+    Ok((unchanged, changed))
+}
+
+/// How often a cancellable wait looks at its `Cancel`.
+const CANCEL_POLL: Duration = Duration::from_millis(20);
+
+/// Tells a backend that the fetch it is running has been abandoned. The app moves
+/// `current` on to its newest generation; the worker sets `running` to the generation
+/// of the fetch it runs, or `u64::MAX` while it runs a write or call, which are never
+/// abandoned.
+#[derive(Clone, Debug)]
+pub(crate) struct Cancel(Arc<CancelState>);
+
+#[derive(Debug)]
+struct CancelState {
+    current: AtomicU64,
+    running: AtomicU64,
+}
+
+impl Default for Cancel {
+    fn default() -> Self {
+        Self(Arc::new(CancelState {
+            current: AtomicU64::new(0),
+            running: AtomicU64::new(u64::MAX),
+        }))
+    }
+}
+
+impl Cancel {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.running.load(Ordering::SeqCst) < self.0.current.load(Ordering::SeqCst)
+    }
+
+    /// Sleeps for `wait`, or less if the work is abandoned meanwhile; says which.
+    pub(crate) fn sleep(&self, wait: Duration) -> bool {
+        let until = Instant::now() + wait;
+        loop {
+            if self.is_cancelled() {
+                return true;
+            }
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            thread::sleep(left.min(CANCEL_POLL));
+        }
+    }
+}
+
+/// A worker thread that runs `Load`s one at a time against the shared backend.
+pub(crate) struct Loader {
+    requests: Sender<(u64, Load)>,
+    responses: Receiver<(u64, Result<Loaded>)>,
+    cancel: Cancel,
+}
+
+impl Loader {
+    pub(crate) fn spawn<B: DnsBackend + 'static>(backend: Arc<Mutex<B>>) -> Self {
+        let (requests, jobs) = mpsc::channel::<(u64, Load)>();
+        let (done, responses) = mpsc::channel();
+        let cancel = Cancel::default();
+        backend
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .watch_cancel(cancel.clone());
+        let state = Arc::clone(&cancel.0);
+        thread::spawn(move || {
+            for (generation, load) in jobs {
+                let running = if load.is_fetch() {
+                    generation
+                } else {
+                    u64::MAX
+                };
+                state.running.store(running, Ordering::SeqCst);
+                // Fetches the app moved on from before they started are dropped unsent.
+                if running < state.current.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let result = {
+                    let mut backend = backend.lock().unwrap_or_else(PoisonError::into_inner);
+                    load.run(&mut *backend)
+                };
+                state.running.store(u64::MAX, Ordering::SeqCst);
+                if done.send((generation, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            responses,
+            cancel,
+        }
+    }
+
+    /// Abandons fetches from generations before `generation`, queued or running.
+    pub(crate) fn cancel_before(&self, generation: u64) {
+        self.cancel
+            .0
+            .current
+            .fetch_max(generation, Ordering::SeqCst);
+    }
+
+    pub(crate) fn send(&self, generation: u64, load: Load) -> Result<()> {
+        self.requests
+            .send((generation, load))
+            .map_err(|_| anyhow!("Background loader stopped"))
+    }
+
+    /// A finished load, if one is waiting.
+    pub(crate) fn try_recv(&self) -> Result<Option<(u64, Result<Loaded>)>> {
+        match self.responses.try_recv() {
+            Ok(response) => Ok(Some(response)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(anyhow!("Background loader stopped")),
+        }
+    }
+}
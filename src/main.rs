@@ -1,51 +1,294 @@
-use std::collections::HashMap;
+mod backup;
+mod bind;
+mod changeset;
+mod credentials;
+mod ddns;
+mod digitalocean;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod headless;
+mod loader;
+#[cfg(feature = "rfc2136")]
+mod rfc2136;
+#[cfg(feature = "route53")]
+mod route53;
+mod sync;
+mod zonefile;
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+use std::panic;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::process;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result, anyhow};
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use base64::Engine;
+use changeset::{ChangeSet, Step};
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 use crossterm::{execute, queue};
+use loader::{Call, Called, Cancel, Load, Loaded, Loader, RecordWrite, Written};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+};
 use ratatui::{Frame, Terminal};
-use reqwest::blocking::{Client, RequestBuilder};
+use regex::{Regex, RegexBuilder};
+use reqwest::StatusCode;
+use reqwest::blocking::multipart::{Form, Part};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 const LEGACY_CONFIG_PATH: &str = "config/accounts.json";
+/// The profile made of the config's top-level `accounts`.
+const DEFAULT_PROFILE: &str = "default";
+/// Starts the first line of a passphrase-encrypted config file.
+const ENCRYPTED_HEADER: &str = "NYXFLARE-ENCRYPTED";
+/// Stands in for every secret in a config written by `--export-config`.
+const EXPORTED_SECRET: &str = "<not exported; enter it in nyxflare>";
+/// Wrong passphrases allowed at startup before giving up.
+const PASSPHRASE_ATTEMPTS: usize = 3;
+/// Longest an account's `token_cmd` may run before it's given up on.
+const TOKEN_CMD_TIMEOUT: Duration = Duration::from_secs(30);
+/// Names a config file to use instead of the default one; `--config` outranks it.
+const CONFIG_ENV: &str = "NYXFLARE_CONFIG";
+const USAGE: &str = "Usage: nyxflare [--config <path>] [--profile <name>] [--encrypt | --decrypt | --import-credentials]
+                [--export-config <path> | --import-config <path>] [--print-config-path]
+                [--account <name> [--zone <name>]]
+                [--offline | --fixture <path> | --generate <count>] [--version] [--help]
+       nyxflare list-zones [--account <name>] [--json] [--config <path>] [--profile <name>]
+       nyxflare list-records --zone <name> [--account <name>] [--json] [--config <path>]
+                [--profile <name>]
+       nyxflare record create|update|delete --zone <name> [--id <id>] [--name <name>]
+                [--type <type>] [--content <text>] [--ttl <auto|seconds>] [--proxied[=no]]
+                [--account <name>] [--json] [--dry-run] [--config <path>] [--profile <name>]
+       nyxflare export --zone <name> [--out <path>] [--account <name>] [--config <path>]
+                [--profile <name>]
+       nyxflare import --zone <name> --file <path> [--replace] [--dry-run] [--json]
+                [--on-failure <stop|continue>] [--account <name>] [--config <path>]
+                [--profile <name>]
+       nyxflare apply --file <path> [--zone <name>] [--prune] [--yes] [--dry-run] [--json]
+                [--on-failure <stop|continue>] [--account <name>] [--config <path>]
+                [--profile <name>]
+       nyxflare backup --out <dir> [--pause <ms>] [--config <path>] [--profile <name>]
+       nyxflare restore --from <dir> --zone <name> [--dry-run] [--json]
+                [--on-failure <stop|continue>] [--account <name>] [--config <path>]
+                [--profile <name>]
+       nyxflare ddns --zone <name> --name <name> [--type <A|AAAA|A,AAAA>] [--source <url|local>]
+                [--ip <address>] [--interval <seconds>] [--ttl <auto|seconds>]
+                [--proxied[=no]] [--dry-run] [--json] [--account <name>] [--config <path>]
+                [--profile <name>]
+
+  -c, --config <path>  accounts file to use (.json or .toml); created on first save
+                       if missing. Also read from NYXFLARE_CONFIG.
+      --profile <name> start with that profile's accounts instead of the default ones
+      --encrypt        encrypt the accounts file with a passphrase (or change it), then exit
+      --decrypt        store the accounts file unencrypted again, then exit
+      --import-credentials
+                       offer the Cloudflare credentials wrangler or flarectl already
+                       use as accounts, one by one, then exit
+      --export-config <path>
+                       write a copy of the config without any secrets, to share, then exit
+      --import-config <path>
+                       add the accounts and settings of such a copy that this config lacks,
+                       then ask for each new account's secret
+      --print-config-path
+                       print the path of the config file in use, then exit
+      --offline        use built-in demo data instead of the providers' APIs; with the
+                       subcommands too
+      --fixture <path> use the accounts, zones and records of a JSON fixture instead,
+                       writing changes back to it
+      --generate <count>
+                       use demo data with that many records per zone
+  -V, --version        print the version and the config file in use
+  -h, --help           print this help
+
+  Without a subcommand, --account starts the TUI on that account, and --zone on one of
+  its zones, instead of where it was left.
+
+  list-zones           print the account's zones instead of starting the TUI
+  list-records         print the records of the zone named by --zone
+  record create        create the record given by --name, --type and --content
+  record update        change the record --id names (or the one with --name and --type);
+                       only the fields given change
+  record delete        delete the record --id names (or the one with --name and --type,
+                       and --content when several share them)
+  export               write the zone's records as a BIND zone file
+  import               create or update the records of a BIND zone file, one by one
+  apply                make the zone match a YAML, TOML or JSON file of the records it should
+                       have: prints the changes, then asks before making them
+  backup               save every zone of every account in the profile as JSON and BIND
+                       files, in a new timestamped directory under --out
+  restore              create the records of one zone of a backup that the zone lacks
+  ddns                 point --name's A record (or --type's) at the machine's public
+                       address, creating it if missing; exits 0 after a change, 2 when
+                       there was nothing to change and 1 on errors
+      --account <name> the account to use; may be left out when there is only one
+      --zone <name>    the zone to list or change records in
+      --json           print one JSON object per line instead of a table
+      --dry-run        print the record (or, for import and apply, the diff) that would be
+                       sent, and send nothing
+      --out <path>     file to export to instead of stdout, or directory to back up to
+      --from <dir>     the backup to restore from, as backup printed it
+      --pause <ms>     how long backup waits between zones (default: 500)
+      --source <url|local>
+                       where ddns looks up the address: a URL that answers with it, or
+                       the local interface (default: api.ipify.org, or \"ddns_source\")
+      --ip <address>   the address ddns sets, instead of looking it up
+      --interval <seconds>
+                       keep running, checking that often and logging changes to stderr
+      --file <path>    zone file to import, or records file to apply
+      --replace        also delete the zone's records the file lacks
+      --prune          also delete the records whose name and type the file lacks
+      --yes            apply without asking
+      --on-failure <stop|continue>
+                       whether import and apply go on after a change fails (default:
+                       continue, or stop with \"stop_on_failure\": true in the config)";
 const CF_API_BASE: &str = "https://api.cloudflare.com/client/v4";
+const CF_PER_PAGE: u32 = 200;
+/// `GET /accounts` caps `per_page` lower than the zone and record endpoints.
+const CF_ACCOUNTS_PER_PAGE: u32 = 50;
+/// Safeguard against runaway pagination loops.
+const CF_MAX_PAGES: u32 = 50;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 15;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_ZONE_CACHE_SECS: u64 = 300;
+const DEFAULT_SCROLL_STEP: usize = 3;
+/// Characters Left/Right move the records table's Content column.
+const CONTENT_SCROLL_STEP: usize = 8;
+/// Encodes to just under 100,000 bytes, the cap xterm and hterm put on OSC 52 payloads.
+const DEFAULT_OSC52_MAX_BYTES: usize = 74_994;
+/// Cloudflare's global API rate limit is counted over a rolling five minutes.
+const RATE_WINDOW: Duration = Duration::from_secs(300);
 
 fn main() -> Result<()> {
-    let (config_path, config) = load_config()?;
-    let accounts = config.accounts;
-
-    let backend = if env::var("CF_TUI_OFFLINE").is_ok() {
-        Backend::Mock(MockBackend::new())
-    } else {
-        Backend::Cloudflare(CloudflareBackend::new()?)
+    let args = CliArgs::parse(env::args().skip(1))?;
+    let source = ConfigSource::resolve(args.config, env::var_os(CONFIG_ENV));
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    if args.version {
+        println!("nyxflare {}", env!("CARGO_PKG_VERSION"));
+        println!("config: {}", source.path().display());
+        return Ok(());
+    }
+    if args.print_config_path {
+        println!("{}", source.path().display());
+        if let Some(note) = pending_migration_note(&source) {
+            eprintln!("{note}");
+        }
+        return Ok(());
+    }
+    if let Some(request) = &args.headless {
+        return run_headless(
+            &source,
+            args.profile.as_deref(),
+            args.offline.as_ref(),
+            request,
+        );
+    }
+    let lock = lock_config(source.path(), process_alive, confirm_on_terminal)?;
+    let writes_config =
+        args.encrypt || args.decrypt || args.import_credentials || args.import_config.is_some();
+    if let Some(holder) = lock.holder()
+        && writes_config
+    {
+        return Err(anyhow!(
+            "{holder} has {} open; close it first",
+            source.path().display()
+        ));
+    }
+    let (config_path, mut config) = load_config(&source, read_passphrase, confirm_on_terminal)?;
+    if args.encrypt || args.decrypt {
+        return migrate_encryption(&config_path, config, args.encrypt);
+    }
+    if let Some(target) = &args.export_config {
+        export_config(&config, &config_path, target)?;
+        println!("Wrote {} without secrets", target.display());
+        return Ok(());
+    }
+    let imported = match &args.import_config {
+        Some(shared) => import_shared_config(&mut config, &config_path, shared)?,
+        None => Vec::new(),
     };
+    if let Some(profile) = &args.profile {
+        config.use_profile(profile, &config_path)?;
+    }
+    if args.import_credentials {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let var = |name: &str| env::var(name).ok();
+        let paths = credentials::wrangler_config_paths(home.as_deref(), var);
+        let probe = credentials::probe(&paths, var);
+        return import_credentials(&config_path, config, probe, confirm_on_terminal);
+    }
+    let token_failures = config.run_token_commands(TOKEN_CMD_TIMEOUT);
+    let warnings = std::mem::take(&mut config.warnings);
 
-    let mut app = App::new(config_path, accounts, backend)?;
+    if let Some(account) = &args.account {
+        config.start_at(account, args.zone.clone(), &config_path)?;
+    }
+    let (backend, deprecation) = providers(&config, args.offline.as_ref())?;
+
+    let note = config
+        .migration
+        .take()
+        .or_else(|| shadowed_config_note(&config_path))
+        .unwrap_or_else(|| source.describe(&config_path));
+    let mut app = App::threaded(config_path, config, backend)?;
+    app.push_message(MessageLevel::Info, note);
+    app.check_config_permissions();
+    for failure in warnings.into_iter().chain(token_failures) {
+        app.push_message(MessageLevel::Error, failure);
+    }
+    for note in imported {
+        app.push_message(MessageLevel::Info, note);
+    }
+    if let Some(deprecation) = deprecation {
+        app.push_message(MessageLevel::Info, deprecation);
+    }
+    if let Some(holder) = lock.holder() {
+        app.open_read_only(holder);
+    }
+    app.ask_for_missing_secrets();
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    queue!(stdout, EnterAlternateScreen)?;
+    queue!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     stdout.flush()?;
+    // Hand the terminal back before the panic message is printed.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let result = run_app(&mut terminal, &mut app);
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    restore_terminal();
     terminal.show_cursor()?;
 
     if let Err(err) = result {
@@ -55,2149 +298,23597 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn load_config() -> Result<(PathBuf, Config)> {
-    let config_path = default_config_path();
+/// The mock data `--offline`, `--fixture` or `--generate` ask to work against instead of
+/// the providers' APIs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Offline {
+    Demo,
+    Fixture(PathBuf),
+    Generate(usize),
+}
 
-    let config = if config_path.exists() {
-        Config::load(&config_path)?
-    } else {
-        let legacy_path = PathBuf::from(LEGACY_CONFIG_PATH);
-        if legacy_path.exists() {
-            Config::load(&legacy_path)?
-        } else {
-            Config::default()
-        }
-    };
+impl Offline {
+    /// What `CF_TUI_OFFLINE` asks for, from before the flags: `1`, `generate:N` or a
+    /// fixture path.
+    fn from_env(value: &str) -> Result<Self> {
+        Ok(match value {
+            "" | "1" | "true" => Offline::Demo,
+            other => match other.strip_prefix("generate:") {
+                Some(count) => Offline::Generate(count.trim().parse().with_context(|| {
+                    format!("CF_TUI_OFFLINE=generate:N needs a record count, not {count:?}")
+                })?),
+                None => Offline::Fixture(PathBuf::from(other)),
+            },
+        })
+    }
+}
 
-    Ok((config_path, config))
+const OFFLINE_ENV_NOTE: &str = "CF_TUI_OFFLINE is deprecated and will go in a later release; use --offline, --fixture <file> or --generate <count>";
+
+/// The backends serving each provider: live ones, or the mock `offline` (or else the
+/// deprecated `CF_TUI_OFFLINE`) asks for. The second value is the deprecation notice,
+/// when the variable was what asked.
+fn providers(config: &Config, offline: Option<&Offline>) -> Result<(Providers, Option<String>)> {
+    let from_env = match offline {
+        Some(_) => None,
+        None => match env::var("CF_TUI_OFFLINE") {
+            Ok(value) => Some(Offline::from_env(&value)?),
+            Err(_) => return Ok((Providers::live(config)?, None)),
+        },
+    };
+    let mock = match offline.or(from_env.as_ref()) {
+        None | Some(Offline::Demo) => MockBackend::new(),
+        Some(Offline::Generate(count)) => MockBackend::generating(*count),
+        Some(Offline::Fixture(path)) => MockBackend::new_from_file(path)?,
+    };
+    let mock = mock
+        .with_failures(&env::var("CF_TUI_MOCK_FAILURES").unwrap_or_default())?
+        .with_latency(&env::var("CF_TUI_MOCK_LATENCY").unwrap_or_default())?;
+    let note = from_env.map(|_| OFFLINE_ENV_NOTE.to_string());
+    Ok((Providers::offline(Backend::Mock(Box::new(mock))), note))
 }
 
-fn default_config_path() -> PathBuf {
-    #[cfg(target_os = "windows")]
-    {
-        env::var_os("APPDATA")
-            .map(PathBuf::from)
-            .or_else(|| env::var_os("HOME").map(PathBuf::from))
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("nyxflare")
-            .join("accounts.json")
+/// The subcommands: print to stdout without starting the TUI. They don't change the
+/// config, so they don't take the lock; warnings and errors go to stderr. A `ddns` check
+/// that found nothing to change exits with 2, so timers can tell it from an update.
+fn run_headless(
+    source: &ConfigSource,
+    profile: Option<&str>,
+    offline: Option<&Offline>,
+    request: &headless::Request,
+) -> Result<()> {
+    let legacy_paths = legacy_config_paths(source.path());
+    let (config_path, mut config) = load_headless_config(source, &legacy_paths, read_passphrase)?;
+    if let Some(profile) = profile {
+        config.use_profile(profile, &config_path)?;
+    }
+    for warning in config.warnings.drain(..) {
+        eprintln!("{warning}");
+    }
+    for failure in config.run_token_commands(TOKEN_CMD_TIMEOUT) {
+        eprintln!("{failure}");
+    }
+    let (mut backend, deprecation) = providers(&config, offline)?;
+    if let Some(deprecation) = deprecation {
+        eprintln!("{deprecation}");
+    }
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let journal = Journal::for_config(&config_path);
+    let mut request = request.clone();
+    request.options.color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let finish = headless::run(
+        &request,
+        &config,
+        &mut backend,
+        &journal,
+        &mut out,
+        &mut io::stderr(),
+        |prompt| {
+            if !io::stdin().is_terminal() {
+                return Err(anyhow!(
+                    "No terminal to ask on; add --yes to apply the plan"
+                ));
+            }
+            confirm_on_terminal(prompt)
+        },
+    )?;
+    out.flush()?;
+    if finish == headless::Finish::Unchanged {
+        process::exit(2);
     }
+    Ok(())
+}
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        env::var_os("XDG_CONFIG_HOME")
-            .map(PathBuf::from)
-            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
-            .unwrap_or_else(|| PathBuf::from(".").join(".config"))
-            .join("nyxflare")
-            .join("accounts.json")
+/// `load_config_at` for the subcommands, which run without the config lock and so leave
+/// rewriting files to the TUI: an older config still to be moved is an error, and so is
+/// one that doesn't parse, without offering its backup.
+fn load_headless_config(
+    source: &ConfigSource,
+    legacy_paths: &[PathBuf],
+    ask: impl FnMut(&str) -> Result<String>,
+) -> Result<(PathBuf, Config)> {
+    if let Some(legacy) = pending_migration(source, legacy_paths) {
+        return Err(anyhow!(
+            "{} hasn't been moved to {} yet; start nyxflare once without a subcommand to move it",
+            legacy.display(),
+            source.path().display()
+        ));
     }
+    load_config_at(source, legacy_paths, ask, |_| Ok(false))
 }
 
-fn run_app<B: DnsBackend>(
+/// Leaves raw mode, the alternate screen, mouse capture and bracketed paste; errors are
+/// ignored since this also runs from the panic hook.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    );
+}
+
+/// Takes the terminal back after something else had it: raw mode, the alternate screen,
+/// mouse capture, bracketed paste, and a full redraw over whatever was left on screen.
+fn resume_terminal(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()?;
+    Ok(())
+}
+
+/// Hands the terminal to the user's editor for `record`, takes it back however the editor
+/// ended, and only then applies the result.
+fn edit_externally<B: DnsBackend>(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     app: &mut App<B>,
+    record: DnsRecord,
 ) -> Result<()> {
-    loop {
-        terminal.draw(|frame| draw(frame, app))?;
+    let path = editor_path(&app.config_path)?;
+    restore_terminal();
+    let edited = edit_until_valid(&path, &record, app.has_proxy(), run_editor);
+    let _ = fs::remove_file(&path);
+    resume_terminal(terminal)?;
+    app.apply_external_edit(&record, edited)
+}
 
-        if event::poll(Duration::from_millis(250))?
-            && let Event::Key(key) = event::read()?
-            && key.kind == KeyEventKind::Press
-            && handle_key(key.code, app)?
-        {
-            return Ok(());
+/// Where a record is written for the editor: `edits/` beside the config, which only the
+/// user can open, rather than the shared temp directory. A file left there by an earlier
+/// run of this process ID is removed.
+fn editor_path(config_path: &Path) -> Result<PathBuf> {
+    let dir = config_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .join("edits");
+    create_private_dir(&dir)?;
+    let path = dir.join(format!("record-{}.txt", std::process::id()));
+    match fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Removing {}", path.display()))
         }
+        _ => Ok(path),
     }
 }
 
-fn handle_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    match app.mode {
-        Mode::Normal => handle_normal_key(code, app),
-        Mode::AddingAccount(_) => handle_add_account_key(code, app),
-        Mode::RecordForm(_) => handle_record_form_key(code, app),
-        Mode::ConfirmDelete(_) => handle_confirm_delete_key(code, app),
-        Mode::Searching(_) => handle_search_key(code, app),
+/// Runs `$VISUAL`, else `$EDITOR`, else `vi` on `path`; arguments in the variable (as in
+/// `code --wait`) are passed along.
+fn run_editor(path: &Path) -> Result<()> {
+    let command = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "windows") {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Starting {command}"))?;
+    if !status.success() {
+        return Err(anyhow!("{command} exited with {status}"));
     }
+    Ok(())
 }
 
-fn handle_normal_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    match code {
-        KeyCode::Char('q') => return Ok(true),
-        KeyCode::Char('r') => {
-            app.refresh_current()?;
-        }
-        KeyCode::Char('a') => {
-            app.start_add_account();
-        }
-        KeyCode::Char('/') => {
-            app.mode = Mode::Searching(app.record_filter.clone());
+/// Writes `record` to `path`, a new file only the user can read, and runs `run` (the
+/// editor) on it until what comes back parses, putting each parse error at the top of
+/// the file for the next round. `None` means the user emptied the file to cancel.
+fn edit_until_valid(
+    path: &Path,
+    record: &DnsRecord,
+    proxy: bool,
+    mut run: impl FnMut(&Path) -> Result<()>,
+) -> Result<Option<DnsRecord>> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(editor_document(record, proxy).as_bytes()))
+        .with_context(|| format!("Writing {}", path.display()))?;
+    loop {
+        run(path)?;
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        match parse_editor_document(&text, record) {
+            Ok(edited) => return Ok(edited),
+            Err(err) => fs::write(path, with_error_comment(&text, &err))
+                .with_context(|| format!("Writing {}", path.display()))?,
         }
-        KeyCode::Char('n') => {
-            app.start_record_form(false);
+    }
+}
+
+/// What the command line asked for.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CliArgs {
+    config: Option<PathBuf>,
+    profile: Option<String>,
+    version: bool,
+    help: bool,
+    encrypt: bool,
+    decrypt: bool,
+    import_credentials: bool,
+    export_config: Option<PathBuf>,
+    import_config: Option<PathBuf>,
+    print_config_path: bool,
+    /// Mock data to use instead of the providers' APIs.
+    offline: Option<Offline>,
+    /// The account (and zone) the TUI starts on; with a subcommand these are its options.
+    account: Option<String>,
+    zone: Option<String>,
+    /// `list-zones` or `list-records`, which print and exit instead of starting the TUI.
+    headless: Option<headless::Request>,
+}
+
+impl CliArgs {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = CliArgs::default();
+        let mut command = None;
+        let mut options = headless::Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(word) = headless::Command::parse(&arg, &mut args)? {
+                if command.is_some() {
+                    return Err(anyhow!("Pick one of list-zones, list-records and record"));
+                }
+                command = Some(word);
+                continue;
+            }
+            if options.take(&arg, &mut args)? {
+                continue;
+            }
+            match arg.as_str() {
+                "-c" | "--config" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg} needs a path\n\n{USAGE}"))?;
+                    parsed.config = Some(PathBuf::from(path));
+                }
+                "--profile" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg} needs a profile name\n\n{USAGE}"))?;
+                    parsed.profile = Some(name);
+                }
+                "--fixture" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg} needs a path\n\n{USAGE}"))?;
+                    parsed.pick_offline(Offline::Fixture(PathBuf::from(path)))?;
+                }
+                "--generate" => {
+                    let count = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg} needs a record count\n\n{USAGE}"))?;
+                    parsed.pick_offline(Offline::Generate(parse_count(&arg, &count)?))?;
+                }
+                "--export-config" | "--import-config" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg} needs a path\n\n{USAGE}"))?;
+                    if arg == "--export-config" {
+                        parsed.export_config = Some(PathBuf::from(path));
+                    } else {
+                        parsed.import_config = Some(PathBuf::from(path));
+                    }
+                }
+                "-V" | "--version" => parsed.version = true,
+                "--print-config-path" => parsed.print_config_path = true,
+                "-h" | "--help" => parsed.help = true,
+                "--encrypt" => parsed.encrypt = true,
+                "--decrypt" => parsed.decrypt = true,
+                "--import-credentials" => parsed.import_credentials = true,
+                "--offline" => parsed.pick_offline(Offline::Demo)?,
+                _ => {
+                    if let Some(path) = arg.strip_prefix("--config=") {
+                        parsed.config = Some(PathBuf::from(path));
+                    } else if let Some(name) = arg.strip_prefix("--profile=") {
+                        parsed.profile = Some(name.to_string());
+                    } else if let Some(path) = arg.strip_prefix("--export-config=") {
+                        parsed.export_config = Some(PathBuf::from(path));
+                    } else if let Some(path) = arg.strip_prefix("--import-config=") {
+                        parsed.import_config = Some(PathBuf::from(path));
+                    } else if let Some(path) = arg.strip_prefix("--fixture=") {
+                        parsed.pick_offline(Offline::Fixture(PathBuf::from(path)))?;
+                    } else if let Some(count) = arg.strip_prefix("--generate=") {
+                        let count = parse_count("--generate", count)?;
+                        parsed.pick_offline(Offline::Generate(count))?;
+                    } else {
+                        return Err(anyhow!("Unknown argument {arg:?}\n\n{USAGE}"));
+                    }
+                }
+            }
         }
-        KeyCode::Char('e') => {
-            app.start_record_form(true);
+        if parsed.encrypt && parsed.decrypt {
+            return Err(anyhow!("Pick one of --encrypt and --decrypt"));
         }
-        KeyCode::Char('d') => {
-            app.ask_delete_record();
+        let actions = [
+            parsed.encrypt || parsed.decrypt,
+            parsed.import_credentials,
+            parsed.export_config.is_some(),
+            parsed.import_config.is_some(),
+        ];
+        if actions.iter().filter(|&&action| action).count() > 1 {
+            return Err(anyhow!(
+                "Pick one of --encrypt, --decrypt, --import-credentials, --export-config and --import-config"
+            ));
         }
-        KeyCode::BackTab => {
-            app.focus = match app.focus {
-                Focus::Accounts => Focus::Records,
-                Focus::Zones => Focus::Accounts,
-                Focus::Records => Focus::Zones,
+        let Some(command) = command else {
+            // Without a subcommand, --account and --zone pick where the TUI starts.
+            parsed.account = options.account.take();
+            parsed.zone = options.zone.take();
+            if options != headless::Options::default() {
+                return Err(anyhow!(
+                    "--name, --type and the other record options go with list-records, record and the other subcommands\n\n{USAGE}"
+                ));
             }
-        }
-        KeyCode::Tab => {
-            app.focus = match app.focus {
-                Focus::Accounts => Focus::Zones,
-                Focus::Zones => Focus::Records,
-                Focus::Records => Focus::Accounts,
+            if parsed.zone.is_some() && parsed.account.is_none() {
+                return Err(anyhow!("--zone needs --account to start the TUI on it"));
             }
+            return Ok(parsed);
+        };
+        if actions.contains(&true) {
+            return Err(anyhow!(
+                "{} can't be combined with --encrypt, --decrypt, --import-credentials, --export-config or --import-config",
+                command.name()
+            ));
         }
-        KeyCode::Up => match app.focus {
-            Focus::Accounts => app.previous_account()?,
-            Focus::Zones => app.previous_zone()?,
-            Focus::Records => app.previous_record(),
-        },
-        KeyCode::Down => match app.focus {
-            Focus::Accounts => app.next_account()?,
-            Focus::Zones => app.next_zone()?,
-            Focus::Records => app.next_record(),
-        },
-        KeyCode::PageDown => app.next_page(),
-        KeyCode::PageUp => app.previous_page(),
-        _ => {}
+        parsed.headless = Some(
+            headless::Request::new(command, options).map_err(|err| anyhow!("{err}\n\n{USAGE}"))?,
+        );
+        Ok(parsed)
     }
 
-    Ok(false)
+    fn pick_offline(&mut self, offline: Offline) -> Result<()> {
+        if self.offline.is_some() {
+            return Err(anyhow!("Pick one of --offline, --fixture and --generate"));
+        }
+        self.offline = Some(offline);
+        Ok(())
+    }
 }
 
-fn handle_add_account_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    let Some(form) = (match &mut app.mode {
-        Mode::AddingAccount(form) => Some(form),
-        _ => None,
-    }) else {
-        return Ok(false);
-    };
+/// `--generate`'s record count.
+fn parse_count(flag: &str, count: &str) -> Result<usize> {
+    count
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("{flag} needs a record count, not {count:?}"))
+}
 
-    match code {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-            app.ensure_onboarding_prompt();
+/// Where the config file comes from: `--config`, `NYXFLARE_CONFIG`, or the default
+/// location (where a legacy `config/accounts.json` is still picked up).
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ConfigSource {
+    Flag(PathBuf),
+    Env(PathBuf),
+    Default(PathBuf),
+}
+
+impl ConfigSource {
+    fn resolve(flag: Option<PathBuf>, env_value: Option<std::ffi::OsString>) -> Self {
+        match (flag, env_value) {
+            (Some(path), _) => ConfigSource::Flag(path),
+            (None, Some(path)) if !path.is_empty() => ConfigSource::Env(PathBuf::from(path)),
+            _ => ConfigSource::Default(preferred_config_path(&default_config_path())),
         }
-        KeyCode::Enter => {
-            if form.field_index < 2 {
-                form.next_field();
-            } else {
-                match form.build_account() {
-                    Ok(account) => {
-                        app.finish_add_account(account)?;
-                    }
-                    Err(msg) => {
-                        app.last_message = msg.to_string();
-                    }
-                }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            ConfigSource::Flag(path) | ConfigSource::Env(path) | ConfigSource::Default(path) => {
+                path
             }
         }
-        KeyCode::Tab | KeyCode::Down => form.next_field(),
-        KeyCode::BackTab | KeyCode::Up => form.previous_field(),
-        KeyCode::Backspace => form.backspace(),
-        KeyCode::Char(c) => form.insert_char(c),
-        _ => {}
     }
 
-    Ok(false)
+    /// The startup message naming the config file in use, `path` being where it's saved.
+    fn describe(&self, path: &Path) -> String {
+        let origin = match self {
+            ConfigSource::Flag(_) => " (from --config)",
+            ConfigSource::Env(_) => " (from NYXFLARE_CONFIG)",
+            ConfigSource::Default(_) => "",
+        };
+        let fresh = if path.exists() {
+            ""
+        } else {
+            "; not there yet, created on first save"
+        };
+        format!("Config: {}{origin}{fresh}", path.display())
+    }
 }
 
-fn handle_record_form_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    let Some(form) = (match &mut app.mode {
-        Mode::RecordForm(form) => Some(form),
-        _ => None,
-    }) else {
-        return Ok(false);
+/// The first of `legacy_paths` that exists, when the default location has no config yet
+/// and so that one is moved there on the next start.
+fn pending_migration<'a>(source: &ConfigSource, legacy_paths: &'a [PathBuf]) -> Option<&'a Path> {
+    let ConfigSource::Default(path) = source else {
+        return None;
     };
-
-    match code {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-        }
-        KeyCode::Tab | KeyCode::Down => form.field_index = (form.field_index + 1).min(4),
-        KeyCode::BackTab | KeyCode::Up => {
-            if form.field_index > 0 {
-                form.field_index -= 1;
-            }
-        }
-        KeyCode::Char(' ') if form.field_index == 4 => {
-            form.draft.proxied = !form.draft.proxied;
-        }
-        KeyCode::Enter => {
-            if form.field_index < 4 {
-                form.field_index += 1;
-            } else {
-                let record_id = form.target_id.clone().unwrap_or_else(|| "new".to_string());
-                match form.draft.to_record(record_id.clone()) {
-                    Ok(record) => {
-                        if form.is_edit {
-                            app.update_record(record.clone())?;
-                        } else {
-                            app.create_record(record)?;
-                        }
-                    }
-                    Err(err) => app.last_message = err.to_string(),
-                }
-            }
-        }
-        KeyCode::Backspace => match form.field_index {
-            0 => {
-                form.draft.name.pop();
-            }
-            1 => {
-                form.draft.record_type.pop();
-            }
-            2 => {
-                form.draft.content.pop();
-            }
-            3 => {
-                form.draft.ttl.pop();
-            }
-            _ => {}
-        },
-        KeyCode::Char(c) => match form.field_index {
-            0 => form.draft.name.push(c),
-            1 => form.draft.record_type.push(c),
-            2 => form.draft.content.push(c),
-            3 => form.draft.ttl.push(c),
-            _ => {}
-        },
-        _ => {}
+    if path.exists() {
+        return None;
     }
+    legacy_paths
+        .iter()
+        .find(|legacy| legacy.exists())
+        .map(PathBuf::as_path)
+}
 
-    Ok(false)
+/// For `--print-config-path`: says where the config is read from for now, when the
+/// default location has none yet but an older version's does.
+fn pending_migration_note(source: &ConfigSource) -> Option<String> {
+    let legacy_paths = legacy_config_paths(source.path());
+    let legacy = pending_migration(source, &legacy_paths)?;
+    Some(format!(
+        "Not there yet: {} is read instead, and moved there on the next start",
+        legacy.display()
+    ))
 }
 
-fn handle_confirm_delete_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    let Some(confirm) = (match &app.mode {
-        Mode::ConfirmDelete(c) => Some(c.clone()),
-        _ => None,
-    }) else {
-        return Ok(false);
-    };
+/// Loads the config `source` names. An explicitly named file that doesn't exist yet
+/// starts empty and is saved to that exact path. An encrypted one is opened with a
+/// passphrase from `ask`. One that doesn't parse is an error, unless `confirm` agrees to
+/// restoring its backup.
+fn load_config(
+    source: &ConfigSource,
+    ask: impl FnMut(&str) -> Result<String>,
+    confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<(PathBuf, Config)> {
+    let legacy_paths = legacy_config_paths(source.path());
+    load_config_at(source, &legacy_paths, ask, confirm)
+}
 
-    match code {
-        KeyCode::Esc => app.mode = Mode::Normal,
-        KeyCode::Char('q') => return Ok(true),
-        KeyCode::Enter => {
-            app.delete_record(confirm.record_id)?;
-            app.mode = Mode::Normal;
+/// `load_config` with the configs of older versions at `legacy_paths`, the first one
+/// that exists counting. When the default location has no config yet, that one is read
+/// and saved there straight away, and `confirm` is asked whether to rename it
+/// `.migrated`; from then on it's no longer read.
+fn load_config_at(
+    source: &ConfigSource,
+    legacy_paths: &[PathBuf],
+    mut ask: impl FnMut(&str) -> Result<String>,
+    mut confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<(PathBuf, Config)> {
+    let config_path = source.path().to_path_buf();
+    let by_default = matches!(source, ConfigSource::Default(_));
+    let legacy_path = legacy_paths
+        .iter()
+        .find(|path| path.exists())
+        .map_or(Path::new(LEGACY_CONFIG_PATH), PathBuf::as_path);
+    let migrating = by_default && !config_path.exists() && legacy_path.exists();
+    let source_path = if migrating {
+        legacy_path.to_path_buf()
+    } else {
+        config_path.clone()
+    };
+    let text = match fs::read_to_string(&source_path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let (mut text, mut passphrase) = match text {
+        Some(text) if is_encrypted(&text) => {
+            let (plain, passphrase) = unlock_config(&source_path, &text, &mut ask)?;
+            (Some(plain), Some(passphrase))
         }
-        _ => {}
+        text => (text, None),
+    };
+    let mut config = match text
+        .as_deref()
+        .map(|text| Config::parse(&source_path, text))
+    {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            let (plain, restored_passphrase) = restore_backup(
+                &source_path,
+                err,
+                passphrase.as_deref(),
+                &mut ask,
+                &mut confirm,
+            )?;
+            let config = Config::parse(&source_path, &plain)?;
+            text = Some(plain);
+            passphrase = restored_passphrase;
+            config
+        }
+        None => Config::default(),
+    };
+    config.passphrase = passphrase;
+    config.resolve_token_refs(&source_path, |name| env::var(name).ok())?;
+
+    if let Some(text) = &text {
+        let issues = config_issues(&source_path, text)?;
+        Policy::from_config(&config).check_startup(&source_path, &issues)?;
+        config.warnings = unknown_config_fields(&source_path, text)?
+            .into_iter()
+            .map(|field| format!("{}: {field} is ignored", source_path.display()))
+            .collect();
     }
 
-    Ok(false)
+    if migrating {
+        config.save(&config_path)?;
+        let mut migrated = legacy_path.as_os_str().to_os_string();
+        migrated.push(".migrated");
+        let migrated = PathBuf::from(migrated);
+        let prompt = format!(
+            "Copied the config from {} to {}, which is used from now on. Rename the old file to {} so it isn't mistaken for the live one? [y/N] ",
+            legacy_path.display(),
+            config_path.display(),
+            migrated.display()
+        );
+        let note = if confirm(&prompt)? {
+            fs::rename(legacy_path, &migrated)
+                .with_context(|| format!("Renaming {}", legacy_path.display()))?;
+            format!("the old file is now {}", migrated.display())
+        } else {
+            format!("{} is no longer read", legacy_path.display())
+        };
+        config.migration = Some(format!(
+            "Moved the config from {} to {}; {note}",
+            legacy_path.display(),
+            config_path.display()
+        ));
+    } else if by_default && legacy_path.exists() {
+        config.migration = Some(format!(
+            "Config: {}; the old {} is no longer read and can be deleted",
+            config_path.display(),
+            legacy_path.display()
+        ));
+    }
+
+    Ok((config_path, config))
 }
 
-fn handle_search_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
-    let Some(current) = (match &mut app.mode {
-        Mode::Searching(text) => Some(text),
-        _ => None,
-    }) else {
-        return Ok(false);
+/// Where the previous version of the config at `path` is kept: `accounts.json.bak`.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Offers, after the config at `path` failed to parse with `err`, to put its backup
+/// back when that one reads cleanly, opening an encrypted backup with `passphrase` or
+/// else one from `ask`. The unreadable file is kept beside it as `.broken`. Without a
+/// usable backup, or when `confirm` says no, this is `err` again. On success, returns
+/// the restored config text and the passphrase it was sealed with.
+fn restore_backup(
+    path: &Path,
+    err: anyhow::Error,
+    passphrase: Option<&str>,
+    ask: impl FnMut(&str) -> Result<String>,
+    confirm: impl FnOnce(&str) -> Result<bool>,
+) -> Result<(String, Option<String>)> {
+    let err = err.context(format!("{} can't be read", path.display()));
+    let backup = backup_path(path);
+    let Ok(text) = fs::read_to_string(&backup) else {
+        return Err(err);
+    };
+    let opened = if !is_encrypted(&text) {
+        Some((text.clone(), None))
+    } else if let Some(plain) = passphrase.and_then(|p| open_config(&text, p).ok().flatten()) {
+        Some((plain, passphrase.map(str::to_string)))
+    } else {
+        unlock_config(&backup, &text, ask)
+            .ok()
+            .map(|(plain, passphrase)| (plain, Some(passphrase)))
+    };
+    let Some((plain, passphrase)) = opened else {
+        return Err(err);
+    };
+    let Ok(config) = Config::parse(path, &plain) else {
+        return Err(err);
     };
 
-    match code {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-        }
-        KeyCode::Enter => {
-            app.record_filter = current.clone();
-            app.record_page = 0;
-            app.selected_record = 0;
-            app.mode = Mode::Normal;
-        }
-        KeyCode::Backspace => {
-            current.pop();
+    let mut broken = path.as_os_str().to_os_string();
+    broken.push(".broken");
+    let broken = PathBuf::from(broken);
+    let prompt = format!(
+        "{err:#}\nIts backup {} still reads fine ({} account(s)). Restore it? The unreadable file is kept as {}. [y/N] ",
+        backup.display(),
+        config.accounts.len(),
+        broken.display()
+    );
+    if !confirm(&prompt)? {
+        return Err(err);
+    }
+    fs::rename(path, &broken).with_context(|| format!("Moving {} aside", path.display()))?;
+    fs::copy(&backup, path).with_context(|| format!("Restoring {}", path.display()))?;
+    Ok((plain, passphrase))
+}
+
+/// Asks a yes/no question on the terminal before the TUI starts; anything but `y` is no.
+fn confirm_on_terminal(prompt: &str) -> Result<bool> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes"))
+}
+
+fn is_encrypted(text: &str) -> bool {
+    text.starts_with(ENCRYPTED_HEADER)
+}
+
+/// Opens an encrypted config with a passphrase from `ask`, which gets the prompt to
+/// show; a wrong passphrase is asked for again, up to three tries in all.
+fn unlock_config(
+    path: &Path,
+    text: &str,
+    mut ask: impl FnMut(&str) -> Result<String>,
+) -> Result<(String, String)> {
+    if !cfg!(feature = "encryption") {
+        return Err(anyhow!(
+            "{} is encrypted, and this build can't decrypt it; rebuild with `--features encryption`",
+            path.display()
+        ));
+    }
+    let mut prompt = format!("Passphrase for {}: ", path.display());
+    for _ in 0..PASSPHRASE_ATTEMPTS {
+        let passphrase = ask(&prompt)?;
+        let opened = open_config(text, &passphrase)
+            .with_context(|| format!("{} is damaged", path.display()))?;
+        match opened {
+            Some(plain) => return Ok((plain, passphrase)),
+            None => prompt = format!("Wrong passphrase. Passphrase for {}: ", path.display()),
         }
-        KeyCode::Char(c) => current.push(c),
-        _ => {}
     }
+    Err(anyhow!(
+        "Wrong passphrase {PASSPHRASE_ATTEMPTS} times; {} stays locked",
+        path.display()
+    ))
+}
 
-    Ok(false)
+#[cfg(feature = "encryption")]
+fn open_config(text: &str, passphrase: &str) -> Result<Option<String>> {
+    encryption::decrypt(text, passphrase)
 }
 
-fn draw<B: DnsBackend>(frame: &mut Frame<'_>, app: &mut App<B>) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(5), Constraint::Length(4)])
-        .split(frame.size());
+#[cfg(not(feature = "encryption"))]
+fn open_config(_text: &str, _passphrase: &str) -> Result<Option<String>> {
+    Err(anyhow!(
+        "this build can't decrypt configs; rebuild with `--features encryption`"
+    ))
+}
 
-    let body_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(28), Constraint::Percentage(72)])
-        .split(chunks[0]);
+#[cfg(feature = "encryption")]
+fn seal_config(text: &str, passphrase: &str) -> Result<String> {
+    encryption::encrypt(text, passphrase, encryption::ITERATIONS)
+}
 
-    draw_accounts(frame, body_chunks[0], app);
-    draw_zones_and_records(frame, body_chunks[1], app);
-    draw_status(frame, chunks[1], app);
+#[cfg(not(feature = "encryption"))]
+fn seal_config(_text: &str, _passphrase: &str) -> Result<String> {
+    Err(anyhow!(
+        "this build can't encrypt configs; rebuild with `--features encryption`"
+    ))
+}
 
-    match &app.mode {
-        Mode::AddingAccount(form) => draw_account_form(frame, form),
-        Mode::RecordForm(form) => draw_record_form(frame, form),
-        Mode::ConfirmDelete(confirm) => draw_confirm_delete(frame, confirm),
-        Mode::Searching(text) => draw_search_overlay(frame, text),
-        Mode::Normal => {}
-    }
+/// Reads a passphrase from the terminal without echoing it, before the TUI starts.
+fn read_passphrase(prompt: &str) -> Result<String> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    enable_raw_mode()?;
+    let mut text = String::new();
+    let read = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(anyhow!("Cancelled")),
+                KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                    break Err(anyhow!("Cancelled"));
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(err) => break Err(err.into()),
+        }
+    };
+    disable_raw_mode()?;
+    eprintln!();
+    read.map(|()| text)
 }
 
-fn draw_accounts<B: DnsBackend>(frame: &mut Frame<'_>, area: ratatui::prelude::Rect, app: &App<B>) {
-    let items: Vec<ListItem> = app
-        .accounts
-        .iter()
-        .map(|account| {
-            let mut spans = vec![Span::raw(account.name.clone())];
-            if let Some(id) = &account.account_id {
-                spans.push(Span::raw(format!("  ({id})")));
+/// `--encrypt` / `--decrypt`: rewrites the config file sealed under a new passphrase,
+/// or in the clear, and exits.
+fn migrate_encryption(path: &Path, mut config: Config, encrypt: bool) -> Result<()> {
+    if encrypt {
+        let passphrase = read_passphrase("New passphrase: ")?;
+        if passphrase.is_empty() {
+            return Err(anyhow!("An empty passphrase wouldn't protect anything"));
+        }
+        if read_passphrase("Repeat it: ")? != passphrase {
+            return Err(anyhow!("The passphrases don't match; nothing was changed"));
+        }
+        config.passphrase = Some(passphrase);
+    } else if config.passphrase.take().is_none() {
+        println!("{} isn't encrypted", path.display());
+        return Ok(());
+    }
+    config.save(path)?;
+    if encrypt {
+        // The backup is the unencrypted file this replaced.
+        let backup = backup_path(path);
+        match fs::remove_file(&backup) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err).with_context(|| format!("Removing {}", backup.display()));
             }
-            ListItem::new(Line::from(spans))
-        })
-        .collect();
-
-    let mut state = ListState::default();
-    if !app.accounts.is_empty() {
-        state.select(Some(app.selected_account));
+            _ => {}
+        }
     }
+    println!(
+        "{} is now {}",
+        path.display(),
+        if encrypt {
+            "encrypted"
+        } else {
+            "stored unencrypted"
+        }
+    );
+    Ok(())
+}
 
-    let title = match app.focus {
-        Focus::Accounts => "Accounts (selected)",
-        Focus::Zones | Focus::Records => "Accounts",
-    };
+/// `--import-credentials`: offers each set of credentials in `probe` as an account of the
+/// profile in use, asking `confirm` about every one, and saves the config if any were
+/// taken. Secrets the config already has are only mentioned.
+fn import_credentials(
+    path: &Path,
+    mut config: Config,
+    probe: credentials::Probe,
+    mut confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<()> {
+    for note in &probe.notes {
+        println!("Skipped: {note}");
+    }
+    if probe.found.is_empty() {
+        println!("No wrangler or flarectl credentials found; nothing was changed");
+        return Ok(());
+    }
+    let profile = config.profile_name().to_string();
+    let mut imported = 0;
+    for found in probe.found {
+        let mut account = found.account;
+        let known = config.account_lists().find_map(|(list, accounts)| {
+            accounts
+                .iter()
+                .find(|known| known.api_token == account.api_token && known.email == account.email)
+                .map(|known| format!("{list} as {:?}", known.name))
+        });
+        let credential = match &account.email {
+            Some(email) => format!(
+                "global API key {} for {email}",
+                mask_secret(&account.api_token)
+            ),
+            None => format!("API token {}", mask_secret(&account.api_token)),
+        };
+        if let Some(known) = known {
+            println!("{}: {credential} is already in {known}", found.source);
+            continue;
+        }
+        let accounts = config.profile_accounts_mut(&profile);
+        let base = account.name.clone();
+        let mut suffix = 1;
+        while accounts.iter().any(|known| known.name == account.name) {
+            suffix += 1;
+            account.name = format!("{base}-{suffix}");
+        }
+        let prompt = format!(
+            "{}: {credential}\n  Import it as account {:?}? [y/N] ",
+            found.source, account.name
+        );
+        if confirm(&prompt)? {
+            accounts.push(account);
+            imported += 1;
+        }
+    }
+    if imported > 0 {
+        config.save(path)?;
+    }
+    println!(
+        "{imported} account{} imported into {}",
+        if imported == 1 { "" } else { "s" },
+        path.display()
+    );
+    Ok(())
+}
 
-    let accounts_list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(title)
-                .border_style(match app.focus {
-                    Focus::Accounts => Style::default().fg(Color::Cyan),
-                    _ => Style::default(),
-                }),
-        )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-        .highlight_symbol("→ ");
+/// `--export-config`: writes `config` (loaded from `from`) to `to` with every secret
+/// taken out, as JSON or TOML by `to`'s extension.
+fn export_config(config: &Config, from: &Path, to: &Path) -> Result<()> {
+    if to == from {
+        return Err(anyhow!(
+            "{} is the config in use; export to another file",
+            to.display()
+        ));
+    }
+    config.shared().save(to)
+}
 
-    frame.render_stateful_widget(accounts_list, area, &mut state);
+/// `--import-config`: merges the shared config at `shared` into `config` and saves it
+/// to `path`. Returns what was added or passed over, for the message history.
+fn import_shared_config(config: &mut Config, path: &Path, shared: &Path) -> Result<Vec<String>> {
+    let text =
+        fs::read_to_string(shared).with_context(|| format!("Reading {}", shared.display()))?;
+    if is_encrypted(&text) {
+        return Err(anyhow!(
+            "{} is encrypted; share a copy made with --export-config",
+            shared.display()
+        ));
+    }
+    let notes = config.merge_shared(Config::parse(shared, &text)?)?;
+    config.save(path)?;
+    Ok(notes)
 }
 
-fn draw_zones_and_records<B: DnsBackend>(
-    frame: &mut Frame<'_>,
-    area: ratatui::prelude::Rect,
-    app: &mut App<B>,
-) {
-    let vertical = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(area);
+/// Creates `dir` and its missing parents, owner-only on Unix.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(dir)
+        .with_context(|| format!("Creating {}", dir.display()))
+}
 
-    app.update_record_page_size(vertical[1].height);
+/// This instance's claim on a config file: `<config>.lock` beside it, holding the PID.
+/// Dropping it removes the file, unless another instance has since taken it over.
+struct ConfigLock {
+    path: PathBuf,
+}
 
-    let zones_title = match app.focus {
-        Focus::Zones => "Zones (selected)",
-        _ => "Zones",
-    };
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        if lock_holder(&self.path) == Some(std::process::id()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
 
-    let zone_items: Vec<ListItem> = app
-        .zones
-        .iter()
-        .map(|zone| ListItem::new(zone.name.clone()))
-        .collect();
+/// What startup found at the config's lock file.
+enum LockState {
+    /// This instance has it; the lock is released when this is dropped.
+    Held { _lock: ConfigLock },
+    /// Another instance has the config, by PID when the lock file names one; this one
+    /// starts read-only.
+    HeldBy(Option<u32>),
+}
 
-    let mut zone_state = ListState::default();
-    if !app.zones.is_empty() {
-        zone_state.select(Some(app.selected_zone));
+impl LockState {
+    /// Who has the config when it isn't this instance, e.g. `nyxflare process 4242`.
+    fn holder(&self) -> Option<String> {
+        match self {
+            LockState::Held { .. } => None,
+            LockState::HeldBy(Some(pid)) => Some(format!("nyxflare process {pid}")),
+            LockState::HeldBy(None) => Some("another nyxflare".to_string()),
+        }
     }
+}
 
-    let zones_list = List::new(zone_items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(zones_title)
-                .border_style(match app.focus {
-                    Focus::Zones => Style::default().fg(Color::Cyan),
-                    _ => Style::default(),
-                }),
-        )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-        .highlight_symbol("→ ");
+fn lock_path(config_path: &Path) -> PathBuf {
+    let mut path = config_path.as_os_str().to_os_string();
+    path.push(".lock");
+    PathBuf::from(path)
+}
 
-    frame.render_stateful_widget(zones_list, vertical[0], &mut zone_state);
+/// The PID in a lock file, if it holds one.
+fn lock_holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
 
-    let paged = app.paged_records();
-    let start_index = app.record_page * app.page_size();
-    let rows = paged.iter().enumerate().map(|(i, record)| {
-        let global_index = start_index + i;
-        let mut row = Row::new(vec![
-            record.record_type.clone(),
-            record.name.clone(),
-            record.content.clone(),
-            record.ttl.to_string(),
-            if record.proxied {
-                "Proxied"
-            } else {
-                "DNS only"
+/// Takes the lock on the config at `config_path`. A lock left by a process that isn't
+/// running (`alive` says which are) is taken over once `confirm` agrees; one held by a
+/// running process, or a stale one the user leaves alone, means starting read-only.
+fn lock_config(
+    config_path: &Path,
+    alive: impl Fn(u32) -> bool,
+    mut confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<LockState> {
+    let path = lock_path(config_path);
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_private_dir(parent)?;
+    }
+    loop {
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{}", std::process::id()));
+        match created {
+            Ok(()) => {
+                return Ok(LockState::Held {
+                    _lock: ConfigLock { path },
+                });
             }
-            .to_string(),
-        ]);
-
-        if app.focus == Focus::Records && global_index == app.selected_record {
-            row = row.style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            );
+            Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+                return Err(err).with_context(|| format!("Creating {}", path.display()));
+            }
+            Err(_) => {}
         }
-        row
-    });
+        let holder = lock_holder(&path);
+        let left_by = match holder {
+            Some(pid) if pid == std::process::id() => {
+                return Ok(LockState::Held {
+                    _lock: ConfigLock { path },
+                });
+            }
+            Some(pid) if alive(pid) => return Ok(LockState::HeldBy(Some(pid))),
+            Some(pid) => format!("nyxflare process {pid}, which isn't running any more"),
+            None => "a nyxflare that didn't record its process".to_string(),
+        };
+        let prompt = format!(
+            "{} was left by {left_by}. Take it over? Otherwise nyxflare starts read-only. [y/N] ",
+            path.display()
+        );
+        if !confirm(&prompt)? {
+            return Ok(LockState::HeldBy(holder));
+        }
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err).with_context(|| format!("Removing {}", path.display()));
+            }
+            _ => {}
+        }
+    }
+}
 
-    let border_style = if app.focus == Focus::Records {
-        Style::default().fg(Color::Cyan)
+/// Whether a process with `pid` is running. When that can't be told it's taken to be,
+/// so a lock is never taken from a live instance without asking.
+fn process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+    let output = if cfg!(windows) {
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).contains(&pid.to_string()))
     } else {
-        Style::default()
+        std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
     };
-
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(8),
-            Constraint::Percentage(25),
-            Constraint::Percentage(40),
-            Constraint::Length(6),
-            Constraint::Length(10),
-        ],
-    )
-    .header(
-        Row::new(vec!["Type", "Name", "Content", "TTL", "Mode"]).style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        ),
-    )
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(border_style)
-            .title("DNS Records"),
-    )
-    .column_spacing(1);
-
-    frame.render_widget(table, vertical[1]);
+    output.unwrap_or(true)
 }
 
-fn draw_status<B: DnsBackend>(frame: &mut Frame<'_>, area: ratatui::prelude::Rect, app: &App<B>) {
-    let (line1, line2) = app.status_message();
-    let footer = Paragraph::new(vec![Line::raw(line1), Line::raw(line2)])
-        .block(Block::default().borders(Borders::ALL).title("Status"));
-    frame.render_widget(footer, area);
+/// The modification time and size of the config file at `path`, or `None` while it
+/// doesn't exist.
+fn config_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
 }
 
-fn draw_account_form(frame: &mut Frame<'_>, form: &AccountForm) {
-    let area = centered_rect(70, 50, frame.size());
-    let mut lines = vec![
-        Line::from(Span::styled(
-            "Add a Cloudflare account",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from("Enter to advance/submit • Tab to move • Esc to cancel"),
-        Line::from(""),
-        form_line("Account Name", &form.name, form.field_index == 0, true),
-        form_line("API Token", &form.api_token, form.field_index == 1, true),
-        form_line(
-            "Email (optional)",
-            &form.email,
-            form.field_index == 2,
-            false,
-        ),
-        form_line(
-            "Account ID (optional, needed for scoped tokens)",
-            &form.account_id,
-            form.field_index == 3,
-            false,
-        ),
-        Line::from(""),
-    ];
+/// How `after` differs from `before`, account by account: `added x`, `changed y`,
+/// `removed z`.
+fn account_changes(before: &[Account], after: &[Account]) -> Vec<String> {
+    let find = |accounts: &[Account], name: &str| {
+        accounts
+            .iter()
+            .find(|account| account.name == name)
+            .cloned()
+    };
+    let mut changes = Vec::new();
+    for account in after {
+        match find(before, &account.name) {
+            None => changes.push(format!("added {}", account.name)),
+            Some(old) if &old != account => changes.push(format!("changed {}", account.name)),
+            Some(_) => {}
+        }
+    }
+    for account in before {
+        if find(after, &account.name).is_none() {
+            changes.push(format!("removed {}", account.name));
+        }
+    }
+    changes
+}
 
-    if !form.is_ready() {
-        lines.push(Line::from("Name and API token are required."));
+/// `accounts.toml` next to the JSON config `path` when there is one, else `path`: a
+/// hand-written TOML file wins over the JSON one.
+fn preferred_config_path(path: &Path) -> PathBuf {
+    let toml = path.with_extension("toml");
+    if toml.exists() {
+        toml
     } else {
-        lines.push(Line::from("Press Enter on the last field to save."));
+        path.to_path_buf()
     }
+}
 
-    let paragraph = Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Onboarding: Add Account"),
-    );
+/// Says the JSON config beside the TOML one in use is being ignored, so edits to the
+/// old file don't silently go nowhere after a move to TOML.
+fn shadowed_config_note(path: &Path) -> Option<String> {
+    let json = path.with_extension("json");
+    (ConfigFormat::of(path) == ConfigFormat::Toml && json.exists()).then(|| {
+        format!(
+            "Using {}; {} is ignored and can be deleted",
+            path.display(),
+            json.display()
+        )
+    })
+}
 
-    frame.render_widget(Clear, area);
-    frame.render_widget(paragraph, area);
+/// `nyxflare/accounts.json` in the platform's config directory: `$XDG_CONFIG_HOME` or
+/// `~/.config` on Linux, `~/Library/Application Support` on macOS, `%APPDATA%` on
+/// Windows. A set `XDG_CONFIG_HOME` is honoured on macOS too, where `dirs` ignores it.
+fn default_config_path() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| cfg!(unix) && !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(dirs::config_dir)
+        .unwrap_or_else(|| PathBuf::from(".config"))
+        .join("nyxflare")
+        .join("accounts.json")
 }
 
-fn draw_record_form(frame: &mut Frame<'_>, form: &RecordForm) {
-    let area = centered_rect(70, 60, frame.size());
-    let labels = ["Name", "Type", "Content", "TTL", "Proxied"];
-    let values = [
-        form.draft.name.clone(),
-        form.draft.record_type.clone(),
-        form.draft.content.clone(),
-        form.draft.ttl.clone(),
-        if form.draft.proxied {
-            "true".to_string()
+/// Where the config used to be kept by default, most recent first: the path nyxflare
+/// worked out itself before using the platform's config directory, where that differs
+/// from `current`, then `config/accounts.json` under the working directory.
+fn legacy_config_paths(current: &Path) -> Vec<PathBuf> {
+    let before_dirs = preferred_config_path(&pre_dirs_config_path());
+    let mut paths = Vec::new();
+    if before_dirs != current {
+        paths.push(before_dirs);
+    }
+    paths.push(PathBuf::from(LEGACY_CONFIG_PATH));
+    paths
+}
+
+/// The default config path before the platform's config directory was used: under
+/// `%APPDATA%`, else `%HOME%`, else the working directory on Windows, and `~/.config`
+/// on macOS.
+fn pre_dirs_config_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        env::var_os("APPDATA")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nyxflare")
+            .join("accounts.json")
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(|| PathBuf::from(".").join(".config"))
+            .join("nyxflare")
+            .join("accounts.json")
+    }
+}
+
+fn run_app<B: DnsBackend>(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    app: &mut App<B>,
+) -> Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        // Poll more often while a fetch is in flight so its result shows up promptly.
+        let timeout = if app.is_busy() {
+            Duration::from_millis(50)
         } else {
-            "false".to_string()
+            Duration::from_millis(250)
+        };
+        if event::poll(timeout)? {
+            let handled = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => handle_key(key.code, app),
+                Event::Mouse(mouse) => handle_mouse(mouse, app).map(|()| false),
+                Event::Paste(text) => {
+                    handle_paste(&text, app);
+                    Ok(false)
+                }
+                _ => Ok(false),
+            };
+            match handled {
+                Ok(true) => {
+                    app.remember_selection();
+                    return Ok(());
+                }
+                Ok(false) => {}
+                Err(err) => app.report_error(err),
+            }
+        }
+        if !app.terminal_output.is_empty() {
+            let backend = terminal.backend_mut();
+            backend.write_all(std::mem::take(&mut app.terminal_output).as_bytes())?;
+            io::Write::flush(backend)?;
+        }
+        if let Some(record) = app.external_edit.take() {
+            edit_externally(terminal, app, record)?;
+        }
+        if let Err(err) = app.tick() {
+            app.report_error(err);
+        }
+        app.remember_selection();
+    }
+}
+
+fn handle_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    match app.mode {
+        Mode::Normal => handle_normal_key(code, app),
+        Mode::AddingAccount(_) => handle_add_account_key(code, app),
+        Mode::RecordForm(_) => handle_record_form_key(code, app),
+        Mode::ConfirmDelete(_) => handle_confirm_delete_key(code, app),
+        Mode::ConfirmCreate(_) => handle_confirm_create_key(code, app),
+        Mode::Searching(_) => handle_search_key(code, app),
+        Mode::RecordDetail(_) => handle_record_detail_key(code, app),
+        Mode::ImportZone(_) => handle_import_key(code, app),
+        Mode::ApplyState(_) => handle_apply_key(code, app),
+        Mode::ConfirmBulkDelete(_) => handle_confirm_bulk_delete_key(code, app),
+        Mode::CreateZone(_) => handle_zone_form_key(code, app),
+        Mode::ConfirmDeleteZone(_) => handle_confirm_delete_zone_key(code, app),
+        Mode::ZoneDetail(_) => handle_zone_detail_key(code, app),
+        Mode::PickAccount(_) => handle_account_picker_key(code, app),
+        Mode::RecordConflict(_) => handle_record_conflict_key(code, app),
+        Mode::Help(_) => handle_help_key(code, app),
+        Mode::BulkReport(_) => handle_bulk_report_key(code, app),
+        Mode::ConfirmBulkUpdate(_) => handle_confirm_bulk_update_key(code, app),
+        Mode::BulkTtl(_) => handle_bulk_ttl_key(code, app),
+        Mode::FilteringZones(_) => handle_zone_filter_key(code, app),
+        Mode::MessageHistory(_) => handle_message_history_key(code, app),
+        Mode::Error(_) => handle_error_key(code, app),
+        Mode::QuickEdit(_) => handle_quick_edit_key(code, app),
+        Mode::ConfirmDeleteAccount(_) => handle_confirm_delete_account_key(code, app),
+        Mode::PickProfile(_) => handle_profile_picker_key(code, app),
+        Mode::ConfigConflict(_) => handle_config_conflict_key(code, app),
+    }
+}
+
+/// Clicks focus the panel under the pointer and select the row clicked; a second click
+/// on the same record opens it for editing. The wheel focuses the panel under the pointer
+/// and moves its selection `scroll_step` rows. Only the main screen takes mouse input.
+fn handle_mouse<B: DnsBackend>(mouse: MouseEvent, app: &mut App<B>) -> Result<()> {
+    if app.mode != Mode::Normal {
+        return Ok(());
+    }
+    let Some((panel, row)) = app.panels.hit(mouse.column, mouse.row) else {
+        return Ok(());
+    };
+    let down = match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => None,
+        MouseEventKind::ScrollDown => Some(true),
+        MouseEventKind::ScrollUp => Some(false),
+        _ => return Ok(()),
+    };
+    app.focus = panel;
+    if let Some(down) = down {
+        return app.scroll(down);
+    }
+    let Some(row) = row else {
+        app.last_click = None;
+        return Ok(());
+    };
+    match panel {
+        Focus::Accounts => app.select_account_at(app.panels.account_offset + row)?,
+        Focus::Zones => app.select_zone_at(app.panels.zone_offset + row)?,
+        Focus::Records => {
+            // Screen rows past a wrapped record belong to the rows it pushed down.
+            let row = match app.panels.wrapped_row {
+                Some((at, height)) if row >= at + height => row - (height - 1),
+                Some((at, _)) if row >= at => at,
+                _ => row,
+            };
+            let index = app.record_page * app.page_size() + row;
+            let total = app.filtered_records().len();
+            if index < total {
+                let now = Instant::now();
+                let double = app.last_click.is_some_and(|(at, clicked)| {
+                    clicked == index && now.duration_since(at) < DOUBLE_CLICK
+                });
+                app.selected_record = index;
+                app.ensure_record_visible(total);
+                if double {
+                    app.last_click = None;
+                    app.start_record_form(true);
+                } else {
+                    app.last_click = Some((now, index));
+                }
+                return Ok(());
+            }
+        }
+    }
+    app.last_click = None;
+    Ok(())
+}
+
+fn handle_normal_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let pending = app.pending_key.take();
+    match code {
+        KeyCode::Char('q') => return Ok(true),
+        KeyCode::Char('?') => {
+            app.mode = Mode::Help(HelpView {
+                focus: app.focus,
+                scroll: 0,
+            });
+        }
+        KeyCode::Esc if app.is_fetching() => app.cancel_loading(),
+        KeyCode::Esc if !app.selected_ids.is_empty() => app.clear_selection(),
+        KeyCode::Esc if app.focus == Focus::Zones && !app.zone_filter.is_empty() => {
+            app.zone_filter.clear();
+            app.push_message(MessageLevel::Info, "Zone filter cleared");
+        }
+        KeyCode::Esc if app.is_locating() => app.clear_locate(),
+        KeyCode::Esc if !app.record_filter.trim().is_empty() => app.clear_record_filter(),
+        KeyCode::Char('r') => {
+            app.refresh_current()?;
+        }
+        KeyCode::Char('a') => {
+            app.start_add_account();
+        }
+        KeyCode::Char(digit @ '1'..='9') => {
+            app.jump_to_account(digit as usize - '0' as usize)?;
+        }
+        KeyCode::Char('v') => {
+            app.verify_current_account()?;
+        }
+        KeyCode::Char('/') if app.focus == Focus::Zones => app.start_zone_filter(),
+        KeyCode::Char('/') => {
+            let locate = !app.locate.trim().is_empty();
+            app.mode = Mode::Searching(SearchPrompt {
+                text: if locate {
+                    app.locate.clone()
+                } else {
+                    app.record_filter.clone()
+                },
+                scope: if app.records_partial() {
+                    SearchScope::Server
+                } else {
+                    app.record_filter_scope
+                },
+                selected: app.current_record().map(|r| r.id.clone()),
+                locate,
+            });
+        }
+        KeyCode::Char('n') if app.focus == Focus::Records && app.is_locating() => {
+            app.jump_to_match(true);
+        }
+        KeyCode::Char('N') if app.focus == Focus::Records && app.is_locating() => {
+            app.jump_to_match(false);
+        }
+        KeyCode::Char('n') => {
+            app.start_record_form(false);
+        }
+        KeyCode::Char('e') if app.focus == Focus::Accounts => app.start_edit_account(),
+        KeyCode::Char('e') => {
+            app.start_record_form(true);
+        }
+        KeyCode::Char('c') if app.focus == Focus::Records => {
+            app.start_clone_record();
+        }
+        KeyCode::Char('d') if app.focus == Focus::Accounts => app.ask_delete_account(),
+        KeyCode::Char('d') if !app.selected_ids.is_empty() => {
+            app.ask_delete_selected();
+        }
+        KeyCode::Char('d') => {
+            app.ask_delete_record();
+        }
+        KeyCode::Char(' ') if app.focus == Focus::Records => {
+            app.toggle_selected();
+        }
+        KeyCode::Enter if app.focus == Focus::Records => {
+            app.open_record_detail();
+        }
+        KeyCode::Enter if app.focus == Focus::Zones => {
+            app.open_zone_detail()?;
+        }
+        KeyCode::Char('t') if app.focus == Focus::Records => {
+            app.cycle_type_filter()?;
+        }
+        KeyCode::Char('s') if app.focus == Focus::Records => {
+            app.set_record_sort(|sort| sort.column = sort.column.next());
+        }
+        KeyCode::Char('S') if app.focus == Focus::Records => {
+            app.set_record_sort(|sort| sort.descending = !sort.descending);
+        }
+        KeyCode::Char('L') => app.open_bulk_report(),
+        KeyCode::Char('M') => app.open_message_history(),
+        KeyCode::Char('o') => app.open_profile_picker(),
+        KeyCode::Char('F') => app.restrict_config_permissions()?,
+        KeyCode::Char('w') if app.focus == Focus::Records => app.toggle_wrap_content(),
+        KeyCode::Left if app.focus == Focus::Records => app.scroll_content(false),
+        KeyCode::Right if app.focus == Focus::Records => app.scroll_content(true),
+        KeyCode::Char('y') => app.copy_selected(),
+        KeyCode::Char('p') if app.focus == Focus::Records => {
+            app.toggle_proxied(pending == Some('p'))?;
+        }
+        KeyCode::Char('P') if app.focus == Focus::Records => app.ask_bulk_proxied(),
+        KeyCode::Char('E') if app.focus == Focus::Records => app.request_external_edit(),
+        KeyCode::Char('T') if app.focus == Focus::Records => app.ask_bulk_ttl(),
+        KeyCode::Char('D') if app.focus == Focus::Records => {
+            app.ask_delete_filtered();
+        }
+        KeyCode::Char('x') if app.focus == Focus::Zones => {
+            app.export_current_zone()?;
+        }
+        KeyCode::Char('i') if app.focus == Focus::Zones => {
+            app.start_import();
+        }
+        KeyCode::Char('A') if app.focus == Focus::Zones => {
+            app.start_apply();
+        }
+        KeyCode::Char('N') if app.focus == Focus::Zones => {
+            app.start_create_zone();
+        }
+        KeyCode::Char('D') if app.focus == Focus::Zones => {
+            app.ask_delete_zone()?;
+        }
+        KeyCode::BackTab => {
+            app.focus = match app.focus {
+                Focus::Accounts => Focus::Records,
+                Focus::Zones if app.config.collapse_accounts => Focus::Records,
+                Focus::Zones => Focus::Accounts,
+                Focus::Records => Focus::Zones,
+            }
+        }
+        KeyCode::Tab => {
+            app.focus = match app.focus {
+                Focus::Accounts => Focus::Zones,
+                Focus::Zones => Focus::Records,
+                Focus::Records if app.config.collapse_accounts => Focus::Zones,
+                Focus::Records => Focus::Accounts,
+            }
+        }
+        // Vim-style: h/l step between panels without wrapping, j/k move like the arrows.
+        KeyCode::Char('h') => {
+            app.focus = match app.focus {
+                Focus::Zones if app.config.collapse_accounts => Focus::Zones,
+                Focus::Accounts | Focus::Zones => Focus::Accounts,
+                Focus::Records => Focus::Zones,
+            }
+        }
+        KeyCode::Char('z') => app.toggle_accounts_panel()?,
+        KeyCode::Char('l') => {
+            app.focus = match app.focus {
+                Focus::Accounts => Focus::Zones,
+                Focus::Zones | Focus::Records => Focus::Records,
+            }
+        }
+        KeyCode::Up | KeyCode::Char('k') => match app.focus {
+            Focus::Accounts => app.previous_account()?,
+            Focus::Zones => app.previous_zone()?,
+            Focus::Records => app.previous_record(),
+        },
+        KeyCode::Down | KeyCode::Char('j') => match app.focus {
+            Focus::Accounts => app.next_account()?,
+            Focus::Zones => app.next_zone()?,
+            Focus::Records => app.next_record(),
         },
+        KeyCode::PageDown => app.next_page(),
+        KeyCode::PageUp => app.previous_page(),
+        KeyCode::Home => app.jump_to_end(false)?,
+        KeyCode::Char('g') if pending == Some('g') => app.jump_to_end(false)?,
+        KeyCode::Char('g') => app.pending_key = Some('g'),
+        KeyCode::End | KeyCode::Char('G') => app.jump_to_end(true)?,
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HelpSection {
+    General,
+    Navigation,
+    Accounts,
+    Zones,
+    Records,
+    Search,
+}
+
+impl HelpSection {
+    const ALL: [HelpSection; 6] = [
+        HelpSection::General,
+        HelpSection::Navigation,
+        HelpSection::Accounts,
+        HelpSection::Zones,
+        HelpSection::Records,
+        HelpSection::Search,
     ];
 
-    let mut lines = vec![
-        Line::from(Span::styled(
-            if form.is_edit {
-                "Edit DNS record"
-            } else {
-                "Create DNS record"
-            },
+    fn title(self) -> &'static str {
+        match self {
+            HelpSection::General => "General",
+            HelpSection::Navigation => "Navigation",
+            HelpSection::Accounts => "Accounts",
+            HelpSection::Zones => "Zones",
+            HelpSection::Records => "Records",
+            HelpSection::Search => "Search",
+        }
+    }
+
+    /// The panel whose keys this section lists, if it belongs to one.
+    fn panel(self) -> Option<Focus> {
+        match self {
+            HelpSection::Accounts => Some(Focus::Accounts),
+            HelpSection::Zones => Some(Focus::Zones),
+            HelpSection::Records => Some(Focus::Records),
+            HelpSection::General | HelpSection::Navigation | HelpSection::Search => None,
+        }
+    }
+}
+
+/// A line of the `?` overlay. `codes` are the keys `handle_normal_key` answers to for
+/// it; pressing one in the overlay runs it, and the tests use them to catch bindings
+/// missing from `BINDINGS`.
+struct Binding {
+    section: HelpSection,
+    keys: &'static str,
+    codes: &'static [KeyCode],
+    action: &'static str,
+}
+
+const BINDINGS: &[Binding] = &[
+    Binding {
+        section: HelpSection::General,
+        keys: "q",
+        codes: &[KeyCode::Char('q')],
+        action: "quit",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "?",
+        codes: &[KeyCode::Char('?')],
+        action: "show or hide this help",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "r",
+        codes: &[KeyCode::Char('r')],
+        action: "refresh zones and records",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "Esc",
+        codes: &[KeyCode::Esc],
+        action: "cancel the load in progress, or clear the selected records, a located search, else the zone's filter",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "M",
+        codes: &[KeyCode::Char('M')],
+        action: "message history, errors in red",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "L",
+        codes: &[KeyCode::Char('L')],
+        action: "what failed in the last bulk change",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "o",
+        codes: &[KeyCode::Char('o')],
+        action: "profiles: switch to another set of accounts, or n there to make one",
+    },
+    Binding {
+        section: HelpSection::General,
+        keys: "F",
+        codes: &[KeyCode::Char('F')],
+        action: "make a config file other users can read private (mode 600)",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "Tab / Shift+Tab",
+        codes: &[KeyCode::Tab, KeyCode::BackTab],
+        action: "cycle focus Accounts → Zones → Records",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "z",
+        codes: &[KeyCode::Char('z')],
+        action: "hide or show the Accounts panel to widen the records",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "h / l",
+        codes: &[KeyCode::Char('h'), KeyCode::Char('l')],
+        action: "focus the panel to the left / right",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "↑ / k, ↓ / j",
+        codes: &[
+            KeyCode::Up,
+            KeyCode::Char('k'),
+            KeyCode::Down,
+            KeyCode::Char('j'),
+        ],
+        action: "move the selection",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "gg / Home, G / End",
+        codes: &[
+            KeyCode::Char('g'),
+            KeyCode::Home,
+            KeyCode::Char('G'),
+            KeyCode::End,
+        ],
+        action: "jump to the first / last entry",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "PgUp / PgDn",
+        codes: &[KeyCode::PageUp, KeyCode::PageDown],
+        action: "previous / next page of records",
+    },
+    Binding {
+        section: HelpSection::Navigation,
+        keys: "mouse",
+        codes: &[],
+        action: "click to select, double-click a record to edit, wheel to scroll",
+    },
+    Binding {
+        section: HelpSection::Accounts,
+        keys: "1-9",
+        codes: &[
+            KeyCode::Char('1'),
+            KeyCode::Char('2'),
+            KeyCode::Char('3'),
+            KeyCode::Char('4'),
+            KeyCode::Char('5'),
+            KeyCode::Char('6'),
+            KeyCode::Char('7'),
+            KeyCode::Char('8'),
+            KeyCode::Char('9'),
+        ],
+        action: "switch to the account with that number, from any panel",
+    },
+    Binding {
+        section: HelpSection::Accounts,
+        keys: "a",
+        codes: &[KeyCode::Char('a')],
+        action: "add an account",
+    },
+    Binding {
+        section: HelpSection::Accounts,
+        keys: "e",
+        codes: &[KeyCode::Char('e')],
+        action: "edit the account (the saved token stays unless you type a new one)",
+    },
+    Binding {
+        section: HelpSection::Accounts,
+        keys: "d",
+        codes: &[KeyCode::Char('d')],
+        action: "remove the account from the config (asks first)",
+    },
+    Binding {
+        section: HelpSection::Accounts,
+        keys: "v",
+        codes: &[KeyCode::Char('v')],
+        action: "verify the account's token",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "Enter",
+        codes: &[KeyCode::Enter],
+        action: "zone details and DNSSEC",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "/",
+        codes: &[KeyCode::Char('/')],
+        action: "filter zones by name (Esc clears the filter)",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "N",
+        codes: &[KeyCode::Char('N')],
+        action: "create a zone",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "D",
+        codes: &[KeyCode::Char('D')],
+        action: "delete the zone",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "x / i",
+        codes: &[KeyCode::Char('x'), KeyCode::Char('i')],
+        action: "export / import a BIND zone file",
+    },
+    Binding {
+        section: HelpSection::Zones,
+        keys: "A",
+        codes: &[KeyCode::Char('A')],
+        action: "apply a records file: see the changes it makes, then make them",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "n / e / d",
+        codes: &[KeyCode::Char('n'), KeyCode::Char('e'), KeyCode::Char('d')],
+        action: "new / edit / delete record (d deletes the selected records, if any)",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "n / N",
+        codes: &[KeyCode::Char('n'), KeyCode::Char('N')],
+        action: "next / previous match of a located search (F4 in the / prompt)",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "w / ← →",
+        codes: &[KeyCode::Char('w'), KeyCode::Left, KeyCode::Right],
+        action: "wrap the selected content / scroll the Content column sideways",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "c",
+        codes: &[KeyCode::Char('c')],
+        action: "clone the record into a new-record form",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "Space",
+        codes: &[KeyCode::Char(' ')],
+        action: "select or unselect the record for bulk actions",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "Enter",
+        codes: &[KeyCode::Enter],
+        action: "record details and history",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "t",
+        codes: &[KeyCode::Char('t')],
+        action: "cycle the record type filter",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "s / S",
+        codes: &[KeyCode::Char('s'), KeyCode::Char('S')],
+        action: "cycle the sort column / flip its direction",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "p",
+        codes: &[KeyCode::Char('p')],
+        action: "toggle proxied on the highlighted record (twice at the apex)",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "E",
+        codes: &[KeyCode::Char('E')],
+        action: "edit the highlighted record in $VISUAL / $EDITOR",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "P",
+        codes: &[KeyCode::Char('P')],
+        action: "turn proxying on or off for the selected (or filtered) records",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "T",
+        codes: &[KeyCode::Char('T')],
+        action: "set the TTL of the selected (or filtered, else highlighted) records",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "y",
+        codes: &[KeyCode::Char('y')],
+        action: "copy the record content (zone or account name in those panels)",
+    },
+    Binding {
+        section: HelpSection::Records,
+        keys: "D",
+        codes: &[KeyCode::Char('D')],
+        action: "delete every record the filter shows",
+    },
+    Binding {
+        section: HelpSection::Search,
+        keys: "/",
+        codes: &[KeyCode::Char('/')],
+        action: "filter records by text",
+    },
+    Binding {
+        section: HelpSection::Search,
+        keys: "Tab (in the prompt)",
+        codes: &[],
+        action: "switch between a local filter and a server search",
+    },
+    Binding {
+        section: HelpSection::Search,
+        keys: "Enter / Esc (in the prompt)",
+        codes: &[],
+        action: "apply / cancel",
+    },
+];
+
+/// The overlay's lines: the focused panel's section first, then the rest in order.
+fn help_lines(focus: Focus) -> Vec<Line<'static>> {
+    let mut sections = HelpSection::ALL.to_vec();
+    sections.sort_by_key(|section| section.panel() != Some(focus));
+    let mut lines = Vec::new();
+    for section in sections {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        let mut title = section.title().to_string();
+        if section.panel() == Some(focus) {
+            title.push_str(" (focused)");
+        }
+        lines.push(Line::from(Span::styled(
+            title,
             Style::default()
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
-        )),
-        Line::from("Enter to advance/submit • Tab to move • Space toggles proxied • Esc to cancel"),
-        Line::from(""),
-    ];
+        )));
+        for binding in BINDINGS.iter().filter(|b| b.section == section) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<28}", binding.keys),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(binding.action),
+            ]));
+        }
+    }
+    lines
+}
 
-    for (idx, label) in labels.iter().enumerate() {
-        let active = idx == form.field_index;
-        let mut display = values[idx].clone();
-        if display.is_empty() {
-            display = "<required>".to_string();
+fn handle_help_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::Help(help) = &mut app.mode else {
+        return Ok(false);
+    };
+    let last_line = help_lines(help.focus).len().saturating_sub(1);
+    match code {
+        KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => app.mode = Mode::Normal,
+        KeyCode::Down | KeyCode::Char('j') => help.scroll = (help.scroll + 1).min(last_line),
+        KeyCode::Up | KeyCode::Char('k') => help.scroll = help.scroll.saturating_sub(1),
+        KeyCode::PageDown => help.scroll = (help.scroll + 10).min(last_line),
+        KeyCode::PageUp => help.scroll = help.scroll.saturating_sub(10),
+        // Any other listed key closes the overlay and does what it says.
+        code if BINDINGS.iter().any(|b| b.codes.contains(&code)) => {
+            app.mode = Mode::Normal;
+            return handle_normal_key(code, app);
         }
-        let style = if active {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
-        lines.push(Line::from(vec![
-            Span::styled(format!("{label}: "), style),
-            Span::styled(display, style),
-        ]));
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_confirm_bulk_update_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::ConfirmBulkUpdate(confirm) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Bulk change cancelled");
+        }
+        KeyCode::Char(' ') | KeyCode::Tab => {
+            if let BulkAction::SetProxied(on) = confirm.action {
+                confirm.action = BulkAction::SetProxied(!on);
+                confirm.scroll = 0;
+            }
+        }
+        KeyCode::PageUp | KeyCode::PageDown => {
+            let steps = confirm.change_set().steps.len();
+            scroll_steps(&mut confirm.scroll, code, steps);
+        }
+        KeyCode::Enter => {
+            let set = confirm.change_set();
+            let action = confirm.action;
+            app.mode = Mode::Normal;
+            if set.steps.is_empty() {
+                let ineligible = set.skipped() - set.unchanged;
+                app.push_message(
+                    MessageLevel::Info,
+                    format!(
+                        "Nothing to change ({} already set, {ineligible} not applicable)",
+                        set.unchanged
+                    ),
+                );
+            } else {
+                app.start_bulk(action, set);
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_profile_picker_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::PickProfile(picker) = &mut app.mode else {
+        return Ok(false);
+    };
+    if let Some(name) = &mut picker.new_name {
+        match code {
+            KeyCode::Esc => picker.new_name = None,
+            KeyCode::Enter => {
+                let name = name.trim().to_string();
+                app.create_profile(&name)?;
+            }
+            _ => {
+                edit_line(name, code);
+            }
+        }
+        return Ok(false);
+    }
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => app.mode = Mode::Normal,
+        KeyCode::Up | KeyCode::Char('k') => picker.selected = picker.selected.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            picker.selected = (picker.selected + 1).min(picker.profiles.len().saturating_sub(1));
+        }
+        KeyCode::Char('n') => picker.new_name = Some(String::new()),
+        KeyCode::Enter => {
+            if let Some((name, _)) = picker.profiles.get(picker.selected) {
+                let name = name.clone();
+                app.switch_profile(&name)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_config_conflict_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    match code {
+        KeyCode::Char('r') => {
+            app.mode = Mode::Normal;
+            app.reload_config()?;
+        }
+        KeyCode::Char('k') => {
+            app.mode = Mode::Normal;
+            app.config_stamp = config_stamp(&app.config_path);
+            app.save_accounts()?;
+            let message = format!(
+                "Kept your accounts; {} overwritten",
+                app.config_path.display()
+            );
+            app.push_message(MessageLevel::Info, message);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_zone_filter_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::FilteringZones(prompt) = &mut app.mode else {
+        return Ok(false);
+    };
+    let step = |visible: &[usize], highlight: Option<usize>, down: bool| {
+        let pos = highlight.and_then(|zone| visible.iter().position(|&i| i == zone));
+        let pos = match (pos, down) {
+            (Some(pos), true) => (pos + 1).min(visible.len().saturating_sub(1)),
+            (Some(pos), false) => pos.saturating_sub(1),
+            (None, _) => 0,
+        };
+        visible.get(pos).copied()
+    };
+    match code {
+        KeyCode::Esc => {
+            app.zone_filter = std::mem::take(&mut prompt.previous);
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let Some(index) = prompt.highlight else {
+                let message = format!("No zones match '{}'", prompt.text);
+                app.push_message(MessageLevel::Info, message);
+                return Ok(false);
+            };
+            app.mode = Mode::Normal;
+            if index != app.selected_zone {
+                app.selected_zone = index;
+                app.load_records(true)?;
+            }
+        }
+        KeyCode::Down | KeyCode::Up => {
+            let visible = app.visible_zones();
+            let Mode::FilteringZones(prompt) = &mut app.mode else {
+                return Ok(false);
+            };
+            prompt.highlight = step(&visible, prompt.highlight, code == KeyCode::Down);
+        }
+        KeyCode::Backspace | KeyCode::Char(_) => {
+            edit_line(&mut prompt.text, code);
+            app.sync_zone_filter();
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Puts a bracketed paste into the focused text field in one go, so a pasted token
+/// can't be cut short or submit the form through a trailing newline. Pastes with no
+/// field to land in, e.g. in Normal mode, are dropped.
+fn handle_paste<B: DnsBackend>(text: &str, app: &mut App<B>) {
+    let text = paste_text(text);
+    let Some(field) = app.mode.paste_target() else {
+        return;
+    };
+    field.push_str(&text);
+    match &mut app.mode {
+        Mode::ImportZone(form) => form.preview = None,
+        Mode::ApplyState(form) => form.preview = None,
+        Mode::FilteringZones(_) => app.sync_zone_filter(),
+        Mode::Searching(_) => app.sync_search(),
+        _ => {}
+    }
+}
+
+/// A paste as one line: trailing newlines go, and any other line breaks or control
+/// characters inside it are dropped since every field is single-line.
+fn paste_text(text: &str) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+/// Whether `haystack` contains the already-lowercased `needle`, ignoring case. ASCII
+/// needles are compared in place, so filtering thousands of records on every keystroke
+/// doesn't lowercase a copy of each field.
+fn contains_lowercase(haystack: &str, needle: &str) -> bool {
+    if !needle.is_ascii() {
+        return haystack.to_lowercase().contains(needle);
+    }
+    let needle = needle.as_bytes();
+    needle.is_empty()
+        || haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Applies typing and Backspace to a one-line prompt; false for any other key.
+fn edit_line(text: &mut String, code: KeyCode) -> bool {
+    match code {
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        KeyCode::Char(c) => text.push(c),
+        _ => return false,
+    }
+    true
+}
+
+fn handle_quick_edit_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::QuickEdit(edit) = &mut app.mode else {
+        return Ok(false);
+    };
+    if edit_line(&mut edit.text, code) {
+        return Ok(false);
+    }
+    match code {
+        KeyCode::Esc => {
+            let message = format!("{} left unchanged", edit.field.label());
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, message);
+        }
+        KeyCode::Enter => {
+            let record = match edit.field.apply(&edit.record, &edit.text) {
+                Ok(record) => record,
+                Err(err) => {
+                    app.push_message(MessageLevel::Error, err.to_string());
+                    return Ok(false);
+                }
+            };
+            let label = edit.field.label();
+            let old = edit.field.value(&edit.record);
+            let new = edit.field.value(&record);
+            app.mode = Mode::Normal;
+            if old == new {
+                app.push_message(
+                    MessageLevel::Info,
+                    format!("{} already has {label} {old}", record.name),
+                );
+            } else {
+                app.write_update(record, true, Some(format!("{label} {old} → {new}")))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_bulk_ttl_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::BulkTtl(prompt) = &mut app.mode else {
+        return Ok(false);
+    };
+    if edit_line(&mut prompt.text, code) {
+        return Ok(false);
+    }
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Bulk change cancelled");
+        }
+        KeyCode::Enter => {
+            let ttl = match parse_bulk_ttl(&prompt.text) {
+                Ok(ttl) => ttl,
+                Err(err) => {
+                    app.push_message(MessageLevel::Error, err.to_string());
+                    return Ok(false);
+                }
+            };
+            let records = std::mem::take(&mut prompt.records);
+            let mut set = BulkAction::SetTtl(ttl).change_set(&records);
+            app.policy.screen(&mut set);
+            app.mode = Mode::Normal;
+            if set.steps.is_empty() && set.left_out.is_empty() {
+                app.push_message(
+                    MessageLevel::Info,
+                    format!(
+                        "All {} record(s) already have TTL {}",
+                        set.unchanged,
+                        ttl_label(ttl)
+                    ),
+                );
+            } else if set.steps.is_empty() {
+                app.push_message(
+                    MessageLevel::Error,
+                    format!("Nothing to change: {}", set.summary()),
+                );
+            } else {
+                app.open_bulk_update(BulkAction::SetTtl(ttl), records);
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_bulk_report_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::BulkReport(report) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => app.mode = Mode::Normal,
+        KeyCode::Down | KeyCode::Char('j') => {
+            report.scroll = (report.scroll + 1).min(report.failures.len().saturating_sub(1));
+        }
+        KeyCode::Up | KeyCode::Char('k') => report.scroll = report.scroll.saturating_sub(1),
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_error_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::Error(details) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+            app.mode = details.form.take().map_or(Mode::Normal, Mode::RecordForm);
+        }
+        KeyCode::Char('y') => {
+            let text = details.full_text();
+            app.copy_to_clipboard("error details", &text);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_message_history_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::MessageHistory(history) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('M') => app.mode = Mode::Normal,
+        KeyCode::Down | KeyCode::Char('j') => {
+            history.scroll = (history.scroll + 1).min(app.messages.len().saturating_sub(1));
+        }
+        KeyCode::Up | KeyCode::Char('k') => history.scroll = history.scroll.saturating_sub(1),
+        KeyCode::PageDown => {
+            history.scroll = (history.scroll + 10).min(app.messages.len().saturating_sub(1));
+        }
+        KeyCode::PageUp => history.scroll = history.scroll.saturating_sub(10),
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_add_account_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(form) = (match &mut app.mode {
+        Mode::AddingAccount(form) => Some(form),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.next_missing_secret();
+            app.ensure_onboarding_prompt();
+        }
+        KeyCode::Enter => {
+            if form.field_index < form.submit_from_field() {
+                form.next_field();
+            } else {
+                match (form.build_account(), form.editing) {
+                    (Ok(account), Some(index)) => app.finish_edit_account(index, account)?,
+                    (Ok(account), None) => {
+                        app.finish_add_account(account)?;
+                    }
+                    (Err(msg), _) => {
+                        app.push_message(MessageLevel::Info, msg.to_string());
+                    }
+                }
+            }
+        }
+        KeyCode::Tab | KeyCode::Down => form.next_field(),
+        KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+        KeyCode::Left | KeyCode::Right if form.on_auth_mode_field() => form.toggle_auth_mode(),
+        KeyCode::F(2) => form.reveal_secret = !form.reveal_secret,
+        KeyCode::Backspace => form.backspace(),
+        KeyCode::Char(c) => form.insert_char(c),
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_account_picker_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(picker) = (match &mut app.mode {
+        Mode::PickAccount(picker) => Some(picker),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+        KeyCode::Down => {
+            picker.selected = (picker.selected + 1).min(picker.choices.len().saturating_sub(1));
+        }
+        KeyCode::Enter => {
+            let mut picker = picker.clone();
+            if let Some(choice) = picker.choices.get(picker.selected) {
+                picker.account.account_id = Some(choice.id.clone());
+                picker.account.account_name = Some(choice.name.clone());
+            }
+            app.save_new_account(picker.account, &picker.token)?;
+        }
+        KeyCode::Esc => {
+            let picker = picker.clone();
+            app.save_new_account(picker.account, &picker.token)?;
+            app.amend_message(" (no account ID set)");
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_record_form_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(form) = (match &mut app.mode {
+        Mode::RecordForm(form) => Some(form),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Tab | KeyCode::Down => form.field_index = (form.field_index + 1).min(4),
+        KeyCode::BackTab | KeyCode::Up => form.field_index = form.field_index.saturating_sub(1),
+        KeyCode::Char(' ') if form.field_index == 4 => {
+            form.draft.proxied = !form.draft.proxied;
+        }
+        KeyCode::Enter => {
+            if form.field_index < 4 {
+                form.field_index += 1;
+            } else {
+                let record_id = form.target_id.clone().unwrap_or_else(|| "new".to_string());
+                match form.draft.to_record(record_id.clone()) {
+                    Ok(record) => {
+                        if form.is_edit {
+                            app.update_record(record.clone())?;
+                        } else {
+                            app.create_record(record)?;
+                        }
+                    }
+                    Err(err) => app.push_message(MessageLevel::Error, err.to_string()),
+                }
+            }
+        }
+        KeyCode::Backspace => match form.field_index {
+            0 => {
+                form.draft.name.pop();
+            }
+            1 => {
+                form.draft.record_type.pop();
+            }
+            2 => {
+                form.draft.content.pop();
+            }
+            3 => {
+                form.draft.ttl.pop();
+            }
+            _ => {}
+        },
+        KeyCode::Char(c) => match form.field_index {
+            0 => form.draft.name.push(c),
+            1 => form.draft.record_type.push(c),
+            2 => form.draft.content.push(c),
+            3 => form.draft.ttl.push(c),
+            _ => {}
+        },
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_confirm_delete_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(confirm) = (match &app.mode {
+        Mode::ConfirmDelete(c) => Some(c.clone()),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => app.mode = Mode::Normal,
+        KeyCode::Char(c) if confirm.require_typed => {
+            if let Mode::ConfirmDelete(c_mut) = &mut app.mode {
+                c_mut.typed.push(c);
+            }
+        }
+        KeyCode::Backspace => {
+            if let Mode::ConfirmDelete(c_mut) = &mut app.mode {
+                c_mut.typed.pop();
+            }
+        }
+        KeyCode::Char('q') => return Ok(true),
+        KeyCode::Enter if !confirm.is_confirmed() => {
+            app.push_message(
+                MessageLevel::Info,
+                format!(
+                    "Type {} or {} exactly to confirm",
+                    confirm.record_name, confirm.code
+                ),
+            );
+        }
+        KeyCode::Enter => {
+            app.mode = Mode::Normal;
+            app.delete_record(confirm.record_id)?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_confirm_bulk_delete_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(confirm) = (match &mut app.mode {
+        Mode::ConfirmBulkDelete(c) => Some(c),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Bulk delete cancelled");
+        }
+        KeyCode::PageUp | KeyCode::PageDown => {
+            scroll_steps(&mut confirm.scroll, code, confirm.records.len());
+        }
+        KeyCode::Char(c) => confirm.typed.push(c),
+        KeyCode::Backspace => {
+            confirm.typed.pop();
+        }
+        KeyCode::Enter if !confirm.is_confirmed() => {
+            let message = format!("Type {} exactly to confirm", confirm.zone_name);
+            app.push_message(MessageLevel::Info, message);
+        }
+        KeyCode::Enter => {
+            let records = std::mem::take(&mut confirm.records);
+            app.mode = Mode::Normal;
+            app.delete_records(records)?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_confirm_delete_account_key<B: DnsBackend>(
+    code: KeyCode,
+    app: &mut App<B>,
+) -> Result<bool> {
+    let Mode::ConfirmDeleteAccount(confirm) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Account kept");
+        }
+        KeyCode::Char(c) if confirm.require_typed => confirm.typed.push(c),
+        KeyCode::Backspace => {
+            confirm.typed.pop();
+        }
+        KeyCode::Enter if !confirm.is_confirmed() => {
+            let message = format!("Type {} exactly to confirm", confirm.account.name);
+            app.push_message(MessageLevel::Info, message);
+        }
+        KeyCode::Enter => {
+            let index = confirm.index;
+            app.mode = Mode::Normal;
+            app.delete_account(index)?;
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn handle_confirm_delete_zone_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(confirm) = (match &mut app.mode {
+        Mode::ConfirmDeleteZone(c) => Some(c),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Zone delete cancelled");
+        }
+        KeyCode::Char(c) => confirm.typed.push(c),
+        KeyCode::Backspace => {
+            confirm.typed.pop();
+        }
+        KeyCode::Enter if !confirm.is_confirmed() => {
+            let message = format!("Type {} exactly to confirm", confirm.zone.name);
+            app.push_message(MessageLevel::Info, message);
+        }
+        KeyCode::Enter => {
+            let zone = confirm.zone.clone();
+            app.mode = Mode::Normal;
+            app.delete_zone(&zone)?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_zone_form_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(form) = (match &mut app.mode {
+        Mode::CreateZone(form) => Some(form),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Zone creation cancelled");
+        }
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+            form.field_index = 1 - form.field_index.min(1);
+        }
+        KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right if form.field_index == 1 => {
+            form.setup = form.setup.toggled();
+        }
+        KeyCode::Char(c) if form.field_index == 0 => form.name.push(c),
+        KeyCode::Backspace if form.field_index == 0 => {
+            form.name.pop();
+        }
+        KeyCode::Enter if form.field_index == 0 => form.field_index = 1,
+        KeyCode::Enter => {
+            let form = form.clone();
+            app.create_zone(&form)?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_confirm_create_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(confirm) = (match &app.mode {
+        Mode::ConfirmCreate(c) => Some(c.clone()),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::RecordForm(confirm.form);
+            app.push_message(MessageLevel::Info, "Create cancelled");
+        }
+        KeyCode::Enter => {
+            app.mode = Mode::Normal;
+            app.force_create_record(confirm.record)?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_record_conflict_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(conflict) = (match &app.mode {
+        Mode::RecordConflict(c) => Some(c.clone()),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    let resolution = match code {
+        KeyCode::Char('o') => Resolution::Overwrite,
+        KeyCode::Char('r') => Resolution::Reload,
+        KeyCode::Char('c') | KeyCode::Esc => Resolution::Cancel,
+        _ => return Ok(false),
+    };
+    app.resolve_record_conflict(conflict, resolution)?;
+    Ok(false)
+}
+
+fn handle_import_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(form) = (match &mut app.mode {
+        Mode::ImportZone(form) => Some(form),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Import cancelled");
+        }
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+            form.field_index = 1 - form.field_index.min(1);
+        }
+        KeyCode::Char(' ') if form.field_index == 1 => form.proxied = !form.proxied,
+        KeyCode::Char(c) if form.field_index == 0 => {
+            form.path.push(c);
+            form.preview = None;
+        }
+        KeyCode::Backspace if form.field_index == 0 => {
+            form.path.pop();
+            form.preview = None;
+        }
+        KeyCode::Enter => {
+            if form.preview.is_some() {
+                let form = form.clone();
+                app.import_zone(&form)?;
+            } else {
+                app.preview_import();
+            }
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_apply_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Mode::ApplyState(form) = &mut app.mode else {
+        return Ok(false);
+    };
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.push_message(MessageLevel::Info, "Apply cancelled");
+        }
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+            form.field_index = 1 - form.field_index.min(1);
+        }
+        KeyCode::Char(' ') if form.field_index == 1 => {
+            form.prune = !form.prune;
+            form.preview = None;
+        }
+        KeyCode::PageUp | KeyCode::PageDown => {
+            let steps = form.preview.as_ref().map_or(0, |plan| plan.steps.len());
+            scroll_steps(&mut form.scroll, code, steps);
+        }
+        KeyCode::Char(c) if form.preview.is_some() && form.confirm_zone.is_some() => {
+            form.typed.push(c);
+        }
+        KeyCode::Backspace if form.preview.is_some() && form.confirm_zone.is_some() => {
+            form.typed.pop();
+        }
+        KeyCode::Char(c) if form.field_index == 0 => {
+            form.path.push(c);
+            form.preview = None;
+        }
+        KeyCode::Backspace if form.field_index == 0 => {
+            form.path.pop();
+            form.preview = None;
+        }
+        KeyCode::Enter if form.preview.is_some() && !form.is_confirmed() => {
+            let zone = form.confirm_zone.clone().unwrap_or_default();
+            app.push_message(
+                MessageLevel::Info,
+                format!("Type {zone} exactly to confirm the deletes"),
+            );
+        }
+        KeyCode::Enter => match form.preview.clone() {
+            Some(plan) => app.apply_plan(plan),
+            None => app.preview_apply()?,
+        },
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Moves a change set overlay's first shown step a page up or down, `steps` being how
+/// many the set has.
+fn scroll_steps(scroll: &mut usize, code: KeyCode, steps: usize) {
+    match code {
+        KeyCode::PageDown => *scroll = (*scroll + 10).min(steps.saturating_sub(1)),
+        KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+        _ => {}
+    }
+}
+
+fn handle_zone_detail_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(detail) = (match &mut app.mode {
+        Mode::ZoneDetail(detail) => Some(detail),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc | KeyCode::Char('n') if detail.confirm_toggle => {
+            detail.confirm_toggle = false;
+            app.push_message(MessageLevel::Info, "DNSSEC unchanged");
+        }
+        KeyCode::Esc | KeyCode::Char('q') => app.mode = Mode::Normal,
+        KeyCode::Char('s') if detail.dnssec.is_some() => detail.confirm_toggle = true,
+        KeyCode::Char('y') if detail.confirm_toggle => {
+            detail.confirm_toggle = false;
+            app.toggle_dnssec()?;
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_record_detail_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(detail) = (match &mut app.mode {
+        Mode::RecordDetail(detail) => Some(detail),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app.mode = Mode::Normal,
+        KeyCode::Char('y') => {
+            let content = detail.record.content.clone();
+            app.copy_to_clipboard("the record content", &content);
+        }
+        KeyCode::Char('h') => {
+            if detail.tab == DetailTab::History {
+                detail.tab = DetailTab::Details;
+            } else {
+                app.load_record_history()?;
+            }
+        }
+        KeyCode::PageDown | KeyCode::Down if detail.tab == DetailTab::History => {
+            let page_count = detail.history.len().div_ceil(HISTORY_PAGE_SIZE);
+            detail.history_page = (detail.history_page + 1).min(page_count.saturating_sub(1));
+        }
+        KeyCode::PageUp | KeyCode::Up if detail.tab == DetailTab::History => {
+            detail.history_page = detail.history_page.saturating_sub(1);
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+fn handle_search_key<B: DnsBackend>(code: KeyCode, app: &mut App<B>) -> Result<bool> {
+    let Some(prompt) = (match &mut app.mode {
+        Mode::Searching(prompt) => Some(prompt),
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    match code {
+        KeyCode::Esc => {
+            let selected = prompt.selected.take();
+            app.mode = Mode::Normal;
+            app.select_record(selected.as_deref());
+        }
+        KeyCode::Enter => {
+            if let Err(err) = RecordQuery::parse(&prompt.text) {
+                app.push_message(MessageLevel::Error, err);
+                return Ok(false);
+            }
+            let prompt = std::mem::take(prompt);
+            app.mode = Mode::Normal;
+            if prompt.locate {
+                app.apply_locate(prompt.text);
+            } else {
+                app.apply_search(prompt)?;
+            }
+        }
+        KeyCode::Tab => prompt.scope = prompt.scope.toggled(),
+        KeyCode::F(4) => {
+            prompt.locate = !prompt.locate;
+            app.sync_search();
+        }
+        KeyCode::F(2) => {
+            prompt.text = toggle_filter_prefix(&prompt.text, REGEX_FILTER_PREFIX);
+            app.sync_search();
+        }
+        KeyCode::F(3) => {
+            prompt.text = toggle_filter_prefix(&prompt.text, FUZZY_FILTER_PREFIX);
+            app.sync_search();
+        }
+        _ => {
+            if edit_line(&mut prompt.text, code) {
+                app.sync_search();
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn draw<B: DnsBackend>(frame: &mut Frame<'_>, app: &mut App<B>) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(4)])
+        .split(frame.size());
+
+    if app.config.collapse_accounts {
+        app.panels.accounts = Rect::default();
+        draw_zones_and_records(frame, chunks[0], app);
+    } else {
+        let body_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(28), Constraint::Percentage(72)])
+            .split(chunks[0]);
+
+        draw_accounts(frame, body_chunks[0], app);
+        draw_zones_and_records(frame, body_chunks[1], app);
+    }
+    draw_status(frame, chunks[1], app);
+
+    // Deletes and bulk changes act on the current account; a danger one makes them loud.
+    let danger = app
+        .current_account()
+        .filter(|account| app.config.is_danger(account));
+    match &app.mode {
+        Mode::AddingAccount(form) => draw_account_form(frame, form),
+        Mode::RecordForm(form) => draw_record_form(frame, form),
+        Mode::ConfirmDelete(confirm) => draw_confirm_delete(frame, confirm, danger),
+        Mode::ConfirmCreate(confirm) => draw_confirm_create(frame, confirm),
+        Mode::Searching(prompt) if prompt.locate => {
+            draw_search_overlay(frame, prompt, app.locate_matches().len())
+        }
+        Mode::Searching(prompt) => draw_search_overlay(frame, prompt, app.filtered_records().len()),
+        Mode::RecordDetail(detail) => draw_record_detail(frame, detail),
+        Mode::ImportZone(form) => draw_import_form(frame, form, app.check_import(form)),
+        Mode::ApplyState(form) => draw_apply_form(frame, form, danger),
+        Mode::ConfirmBulkDelete(confirm) => draw_confirm_bulk_delete(frame, confirm, danger),
+        Mode::CreateZone(form) => draw_zone_form(frame, form),
+        Mode::ConfirmDeleteZone(confirm) => draw_confirm_delete_zone(frame, confirm, danger),
+        Mode::ZoneDetail(detail) => draw_zone_detail(frame, detail),
+        Mode::PickAccount(picker) => draw_account_picker(frame, picker),
+        Mode::RecordConflict(conflict) => draw_record_conflict(frame, conflict),
+        Mode::Help(help) => draw_help(frame, help),
+        Mode::BulkReport(report) => draw_bulk_report(frame, report),
+        Mode::ConfirmBulkUpdate(confirm) => draw_confirm_bulk_update(frame, confirm, danger),
+        Mode::BulkTtl(prompt) => draw_bulk_ttl(frame, prompt),
+        Mode::FilteringZones(prompt) => draw_zone_filter(frame, prompt),
+        Mode::MessageHistory(history) => draw_message_history(frame, history, &app.messages),
+        Mode::Error(details) => draw_error(frame, details),
+        Mode::QuickEdit(edit) => draw_quick_edit(frame, edit),
+        Mode::ConfirmDeleteAccount(confirm) => draw_confirm_delete_account(
+            frame,
+            confirm,
+            &app.config_path,
+            app.config.is_danger(&confirm.account),
+        ),
+        Mode::PickProfile(picker) => draw_profile_picker(frame, picker, app.config.profile_name()),
+        Mode::ConfigConflict(conflict) => draw_config_conflict(frame, conflict, &app.config_path),
+        Mode::Normal => {}
+    }
+}
+
+fn draw_accounts<B: DnsBackend>(
+    frame: &mut Frame<'_>,
+    area: ratatui::prelude::Rect,
+    app: &mut App<B>,
+) {
+    let items: Vec<ListItem> = app
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(index, account)| {
+            // Number keys jump to the first nine accounts.
+            let number = if index < 9 {
+                format!("{} ", index + 1)
+            } else {
+                "  ".to_string()
+            };
+            let mut spans = vec![
+                Span::styled(number, Style::default().fg(Color::DarkGray)),
+                Span::raw(account.name.clone()),
+            ];
+            if let Some(tag) = account_tag(account) {
+                spans.extend([Span::raw(" "), tag]);
+            }
+            match (&account.account_name, &account.account_id) {
+                (Some(name), _) => spans.push(Span::styled(
+                    format!("  ({name})"),
+                    Style::default().fg(Color::DarkGray),
+                )),
+                (None, Some(id)) => spans.push(Span::raw(format!("  ({id})"))),
+                (None, None) => {}
+            }
+            if account.token_error.is_some() || account.lacks_secret() {
+                spans.push(Span::styled("  no token", Style::default().fg(Color::Red)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    if !app.accounts.is_empty() {
+        state.select(Some(app.selected_account));
+    }
+
+    let title = match app.focus {
+        Focus::Accounts => "Accounts (selected)",
+        Focus::Zones | Focus::Records => "Accounts",
+    };
+
+    let accounts_list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(match app.focus {
+                    Focus::Accounts => Style::default().fg(Color::Cyan),
+                    _ => Style::default(),
+                }),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("→ ");
+
+    frame.render_stateful_widget(accounts_list, area, &mut state);
+    app.panels.accounts = area;
+    app.panels.account_offset = state.offset();
+}
+
+fn draw_zones_and_records<B: DnsBackend>(
+    frame: &mut Frame<'_>,
+    area: ratatui::prelude::Rect,
+    app: &mut App<B>,
+) {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(area);
+
+    app.content_width = record_content_width(vertical[1].width);
+    app.update_record_page_size(vertical[1].height);
+
+    let mut zones_title = match app.focus {
+        Focus::Zones => "Zones (selected)",
+        _ => "Zones",
+    }
+    .to_string();
+    if let Some(spinner) = app.panel_spinner(Focus::Zones) {
+        zones_title.push_str(&format!(" {spinner} loading"));
+    }
+
+    let visible = app.visible_zones();
+    if !app.zone_filter.is_empty() {
+        zones_title.push_str(&format!(
+            " — /{} ({} of {})",
+            app.zone_filter,
+            visible.len(),
+            app.zones.len()
+        ));
+    }
+    let zone_items: Vec<ListItem> = visible
+        .iter()
+        .map(|&index| &app.zones[index])
+        .map(|zone| {
+            if zone.is_pending() {
+                ListItem::new(format!("{} (pending)", zone.name))
+                    .style(Style::default().fg(Color::DarkGray))
+            } else {
+                ListItem::new(zone.name.clone())
+            }
+        })
+        .collect();
+
+    let highlighted = match &app.mode {
+        Mode::FilteringZones(prompt) => prompt.highlight,
+        _ => Some(app.selected_zone),
+    };
+    let mut zone_state = ListState::default();
+    zone_state.select(highlighted.and_then(|zone| visible.iter().position(|&i| i == zone)));
+
+    let zones_list = List::new(zone_items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(zones_title)
+                .border_style(match app.focus {
+                    Focus::Zones => Style::default().fg(Color::Cyan),
+                    _ => Style::default(),
+                }),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("→ ");
+
+    frame.render_stateful_widget(zones_list, vertical[0], &mut zone_state);
+    app.panels.zones = vertical[0];
+    app.panels.zone_offset = zone_state.offset();
+    app.panels.records = vertical[1];
+
+    let paged = app.paged_records();
+    let start_index = app.record_page * app.page_size();
+    let has_proxy = app.has_proxy();
+    let sort = app.record_sort();
+    // The live draft while searching, so matches light up as they're typed; a located
+    // search outshines the filter.
+    let highlight = match app.locate_text() {
+        text if text.trim().is_empty() => app.record_filter_text(),
+        text => text,
+    };
+    let query = RecordQuery::parse(highlight).ok().flatten();
+    let match_style = Style::default().add_modifier(Modifier::REVERSED);
+    let cell = |column: RecordColumn, text: &str| -> Cell<'static> {
+        let lines: Vec<Line<'static>> = text
+            .split('\n')
+            .map(|line| match &query {
+                Some(query) => highlighted_line(line, &query.highlights(column, line), match_style),
+                None => Line::from(line.to_string()),
+            })
+            .collect();
+        Cell::from(Text::from(lines))
+    };
+    let mut wrapped_row = None;
+    let mut rows = Vec::with_capacity(paged.len());
+    for (i, record) in paged.iter().enumerate() {
+        let global_index = start_index + i;
+        let marker = if app.selected_ids.contains(&record.id) {
+            "*"
+        } else {
+            ""
+        };
+        let (content, height) = if app.wrap_content && global_index == app.selected_record {
+            let lines = wrap_chars(&record.content, app.content_width);
+            let height = lines.len();
+            wrapped_row = Some((i, height));
+            (lines.join("\n"), height)
+        } else if app.content_scroll > 0 && !app.wrap_content {
+            let rest: String = record.content.chars().skip(app.content_scroll).collect();
+            (format!("…{rest}"), 1)
+        } else {
+            (record.content.clone(), 1)
+        };
+        let pending = app.is_pending_row(&record.id);
+        let highlighted = app.focus == Focus::Records && global_index == app.selected_record;
+        // The highlight and the greyed-out pending look replace the type colors outright.
+        let plain = pending || highlighted;
+        let type_style = match app.config.type_color(&record.record_type) {
+            Some(color) if !plain => Style::default().fg(color),
+            _ => Style::default(),
+        };
+        let mode_style = if has_proxy && record.proxied && !plain {
+            Style::default().fg(PROXIED_COLOR)
+        } else {
+            Style::default()
+        };
+        let row = Row::new(vec![
+            Cell::from(marker),
+            cell(RecordColumn::Type, &record.record_type).style(type_style),
+            cell(RecordColumn::Name, &record.name),
+            cell(RecordColumn::Content, &content),
+            Cell::from(record.ttl.to_string()),
+            Cell::from(match (has_proxy, record.proxied) {
+                (false, _) => "—",
+                (true, true) => "Proxied",
+                (true, false) => "DNS only",
+            })
+            .style(mode_style),
+        ]);
+
+        let mut style = Style::default();
+        if pending {
+            style = style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+        }
+        if highlighted {
+            style = style.fg(Color::Cyan).add_modifier(Modifier::BOLD);
+        }
+        rows.push(row.style(style).height(height as u16));
+    }
+    app.panels.wrapped_row = wrapped_row;
+    let empty = if !app.records.is_empty() {
+        Some("No records match")
+    } else if app.current_zone().is_some() && !app.is_fetching() {
+        Some("No records")
+    } else {
+        None
+    };
+    if rows.is_empty()
+        && let Some(note) = empty
+    {
+        rows.push(
+            Row::new(vec![Cell::from(""), Cell::from(""), Cell::from(note)])
+                .style(Style::default().fg(Color::DarkGray)),
+        );
+    }
+
+    // A coloured account keeps its colour on the border; focus then shows as bold.
+    let account = app.current_account();
+    let border_style = match (account.and_then(account_color), app.focus == Focus::Records) {
+        (Some(color), true) => Style::default().fg(color).add_modifier(Modifier::BOLD),
+        (Some(color), false) => Style::default().fg(color),
+        (None, true) => Style::default().fg(Color::Cyan),
+        (None, false) => Style::default(),
+    };
+    let tag = account.and_then(account_tag);
+
+    let table = Table::new(rows, RECORD_COLUMNS)
+        .header(
+            Row::new(
+                std::iter::once(String::new()).chain(
+                    [
+                        SortColumn::Type,
+                        SortColumn::Name,
+                        SortColumn::Content,
+                        SortColumn::Ttl,
+                        SortColumn::Proxied,
+                    ]
+                    .map(|column| sort.header(column)),
+                ),
+            )
+            .style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title({
+                    let mut title = match app.type_filter {
+                        TypeFilter::All => "DNS Records".to_string(),
+                        filter => format!("DNS Records [{}] (t to cycle)", filter.label()),
+                    };
+                    // Without the Accounts panel, say whose records these are.
+                    if app.config.collapse_accounts
+                        && let (Some(account), Some(zone)) =
+                            (app.current_account(), app.current_zone())
+                    {
+                        title.push_str(&format!(" — {} › {}", account.name, zone.name));
+                    }
+                    if !app.selected_ids.is_empty() {
+                        title.push_str(&format!(" — {} selected", app.selected_ids.len()));
+                    }
+                    if app.wrap_content {
+                        title.push_str(" — wrapped (w)");
+                    } else if app.content_scroll > 0 {
+                        title.push_str(&format!(" — content from col {}", app.content_scroll + 1));
+                    }
+                    if let Some(spinner) = app.panel_spinner(Focus::Records) {
+                        title.push_str(&format!(" {spinner} loading"));
+                    }
+                    match tag {
+                        Some(tag) => Line::from(vec![tag, Span::raw(format!(" {title}"))]),
+                        None => Line::from(title),
+                    }
+                }),
+        )
+        .column_spacing(1);
+
+    frame.render_widget(table, vertical[1]);
+}
+
+/// An account's `color`, when it has one that parses.
+fn account_color(account: &Account) -> Option<Color> {
+    account.color.as_deref().and_then(parse_account_color)
+}
+
+/// The tag drawn beside an account: its label on its colour, or a swatch when it only
+/// has a colour.
+fn account_tag(account: &Account) -> Option<Span<'static>> {
+    let label = account
+        .label
+        .as_deref()
+        .map(str::trim)
+        .filter(|label| !label.is_empty());
+    match (label, account_color(account)) {
+        (Some(label), Some(color)) => Some(Span::styled(
+            format!(" {label} "),
+            Style::default()
+                .fg(color)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        )),
+        (Some(label), None) => Some(Span::styled(
+            format!("[{label}]"),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        (None, Some(color)) => Some(Span::styled("■", Style::default().fg(color))),
+        (None, None) => None,
+    }
+}
+
+/// Cloudflare's orange, for the Mode column of proxied records.
+const PROXIED_COLOR: Color = Color::Indexed(208);
+
+/// Type column colors for the common record types; `type_colors` in the config overrides
+/// them.
+fn default_type_color(record_type: &str) -> Option<Color> {
+    match record_type {
+        "A" => Some(Color::Green),
+        "AAAA" => Some(Color::LightGreen),
+        "CNAME" => Some(Color::LightBlue),
+        "TXT" => Some(Color::Magenta),
+        "MX" => Some(Color::LightYellow),
+        _ => None,
+    }
+}
+
+/// How many status messages the history keeps.
+const MESSAGE_HISTORY_LIMIT: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MessageLevel {
+    Info,
+    /// "Loading…"-style updates; each replaces the one before it in the history.
+    Progress,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LoggedMessage {
+    at: Instant,
+    level: MessageLevel,
+    text: String,
+}
+
+/// The `M` overlay listing past status messages, newest first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct MessageHistory {
+    scroll: usize,
+}
+
+/// A failed backend operation, shown in a modal rather than squeezed onto the status line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ErrorDetails {
+    operation: String,
+    /// The HTTP status, when Cloudflare answered at all.
+    status: Option<String>,
+    message: String,
+    /// The whole response body; the modal shows it truncated and `y` copies all of it.
+    body: Option<String>,
+    /// The record form to reopen, still filled in, once the error is dismissed.
+    form: Option<RecordForm>,
+}
+
+impl ErrorDetails {
+    /// Without an `operation`, the error's outermost context names what was attempted.
+    fn new(operation: Option<String>, err: &anyhow::Error) -> Self {
+        let api = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<ApiError>());
+        let mut causes: Vec<String> = err.chain().map(|cause| cause.to_string()).collect();
+        let operation = match operation {
+            Some(operation) => operation,
+            None if causes.len() > 1 => causes.remove(0),
+            None => api.map_or_else(|| "Last action".to_string(), |api| api.operation.clone()),
+        };
+        ErrorDetails {
+            operation,
+            status: api.map(|api| api.status.to_string()),
+            message: api.map_or_else(|| causes.join(": "), |api| api.message.clone()),
+            body: api.map(|api| api.body.clone()),
+            form: None,
+        }
+    }
+
+    /// Everything known about the failure, for pasting into a bug report.
+    fn full_text(&self) -> String {
+        let mut text = format!("Operation: {}\n", self.operation);
+        if let Some(status) = &self.status {
+            text.push_str(&format!("HTTP status: {status}\n"));
+        }
+        text.push_str(&format!("Error: {}\n", self.message));
+        if let Some(body) = &self.body {
+            text.push_str(&format!("Body: {body}\n"));
+        }
+        text
+    }
+}
+
+/// Marker, type, name, content, TTL and proxy columns of the records table.
+const RECORD_COLUMNS: [Constraint; 6] = [
+    Constraint::Length(1),
+    Constraint::Length(8),
+    Constraint::Percentage(25),
+    Constraint::Percentage(40),
+    Constraint::Length(6),
+    Constraint::Length(10),
+];
+
+/// Width the records table gives its Content column when drawn `table_width` wide.
+fn record_content_width(table_width: u16) -> usize {
+    let inner = Rect::new(0, 0, table_width.saturating_sub(2), 1);
+    let columns = Layout::horizontal(RECORD_COLUMNS).spacing(1).split(inner);
+    usize::from(columns[3].width).max(1)
+}
+
+/// Splits `text` into lines of at most `width` characters.
+fn wrap_chars(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(width.max(1))
+        .map(|line| line.iter().collect())
+        .collect()
+}
+
+/// Braille spinner frame for the current time, advancing every 100ms as the UI redraws.
+fn spinner_frame() -> char {
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    FRAMES[(millis / 100) as usize % FRAMES.len()]
+}
+
+fn draw_status<B: DnsBackend>(frame: &mut Frame<'_>, area: ratatui::prelude::Rect, app: &App<B>) {
+    let (line1, line2) = app.status_message();
+    let line1 = if app.loose_config_mode.is_some() || app.read_only.is_some() {
+        Line::styled(line1, Style::default().fg(Color::Red))
+    } else {
+        Line::raw(line1)
+    };
+    let footer = Paragraph::new(vec![line1, Line::raw(line2)])
+        .block(Block::default().borders(Borders::ALL).title("Status"));
+    frame.render_widget(footer, area);
+}
+
+fn draw_account_picker(frame: &mut Frame<'_>, picker: &AccountPicker) {
+    let area = centered_rect(60, 40, frame.size());
+    let mut lines = vec![
+        Line::from(format!(
+            "{} can reach {} accounts. Which one should it use?",
+            picker.account.name,
+            picker.choices.len()
+        )),
+        Line::from("↑/↓ to choose • Enter to select • Esc to skip"),
+        Line::from(""),
+    ];
+    for (idx, choice) in picker.choices.iter().enumerate() {
+        let style = if idx == picker.selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let marker = if idx == picker.selected { "→ " } else { "  " };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{marker}{}", choice.name), style),
+            Span::styled(
+                format!("  {}", choice.id),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Choose account"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_account_form(frame: &mut Frame<'_>, form: &AccountForm) {
+    let area = centered_rect(70, 70, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            match (form.editing, form.is_rfc2136()) {
+                (Some(_), _) => "Edit account",
+                (None, true) => "Add an RFC 2136 (dynamic update) account",
+                (None, false) => "Add a Cloudflare account",
+            },
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(
+            "Enter to advance/submit • Tab to move • F2 to show/hide the secret • Esc to cancel",
+        ),
+        Line::from(""),
+        form_line("Account Name", &form.name, form.field_index == 0, true),
+        form_line(
+            "Auth Mode (Space/←/→ to switch, p for RFC 2136)",
+            form.mode_label(),
+            form.on_auth_mode_field(),
+            true,
+        ),
+        form_line(
+            form.secret_label(),
+            &form.secret_display(),
+            form.field_index == 2,
+            true,
+        ),
+    ];
+    if form.is_rfc2136() {
+        lines.extend([
+            form_line(
+                "TSIG Key Name",
+                &form.tsig_key_name,
+                form.field_index == 3,
+                true,
+            ),
+            form_line(
+                "Server (host or host:port)",
+                &form.dns_server,
+                form.field_index == 4,
+                true,
+            ),
+            form_line(
+                "Zones (comma-separated)",
+                &form.zones,
+                form.field_index == 5,
+                true,
+            ),
+            form_line(
+                "TSIG Algorithm (optional, default hmac-sha256)",
+                &form.tsig_algorithm,
+                form.field_index == 6,
+                false,
+            ),
+            form_line(
+                "TTL for new records (optional: auto or seconds)",
+                &form.default_ttl,
+                form.field_index == 7,
+                false,
+            ),
+        ]);
+    } else {
+        lines.extend([
+            form_line(
+                if form.requires_email() {
+                    "Email"
+                } else {
+                    "Email (optional)"
+                },
+                &form.email,
+                form.field_index == 3,
+                form.requires_email(),
+            ),
+            form_line(
+                "Account ID (optional, needed for scoped tokens)",
+                &form.account_id,
+                form.field_index == 4,
+                false,
+            ),
+            form_line(
+                "API base URL (advanced, optional)",
+                &form.base_url,
+                form.field_index == 5,
+                false,
+            ),
+            form_line(
+                "TTL for new records (optional: auto or seconds)",
+                &form.default_ttl,
+                form.field_index == 6,
+                false,
+            ),
+            form_line(
+                "Proxy new records (optional: yes or no)",
+                &form.default_proxied,
+                form.field_index == 7,
+                false,
+            ),
+        ]);
+    }
+    lines.extend([
+        form_line(
+            "Label (optional, e.g. PROD)",
+            &form.label,
+            form.field_index == 8,
+            false,
+        ),
+        form_line(
+            "Label color (optional: red or #ff8800)",
+            &form.color,
+            form.field_index == 9,
+            false,
+        ),
+    ]);
+    lines.push(Line::from(""));
+
+    if !form.is_ready() {
+        lines.push(Line::from(match (form.is_rfc2136(), &form.auth_mode) {
+            (true, _) => "Name, TSIG secret, key name, server and zones are required.",
+            (false, AuthMode::Token) => "Name and API token are required.",
+            (false, AuthMode::GlobalKey) => "Name, Global API key and email are required.",
+        }));
+    } else {
+        lines.push(Line::from("Press Enter on the last field to save."));
+    }
+
+    let paragraph = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(
+        if form.editing.is_some() {
+            "Edit Account"
+        } else {
+            "Onboarding: Add Account"
+        },
+    ));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_record_form(frame: &mut Frame<'_>, form: &RecordForm) {
+    let area = centered_rect(70, 60, frame.size());
+    let labels = ["Name", "Type", "Content", "TTL", "Proxied"];
+    let values = [
+        form.draft.name.clone(),
+        form.draft.record_type.clone(),
+        form.draft.content.clone(),
+        form.draft.ttl.clone(),
+        if form.draft.proxied {
+            "true".to_string()
+        } else {
+            "false".to_string()
+        },
+    ];
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            match (&form.cloned_from, form.is_edit) {
+                (_, true) => "Edit DNS record".to_string(),
+                (Some(source), false) => format!("Create DNS record (cloned from {source})"),
+                (None, false) => "Create DNS record".to_string(),
+            },
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from("Enter to advance/submit • Tab to move • Space toggles proxied • Esc to cancel"),
+        Line::from(""),
+    ];
+    if let Some(defaults) = &form.account_defaults {
+        lines.insert(
+            2,
+            Line::from(Span::styled(
+                format!("Starts with this account's defaults: {defaults}"),
+                Style::default().fg(Color::DarkGray),
+            )),
+        );
+    }
+
+    for (idx, label) in labels.iter().enumerate() {
+        let active = idx == form.field_index;
+        let mut display = values[idx].clone();
+        if display.is_empty() {
+            display = "<required>".to_string();
+        }
+        let style = if active {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{label}: "), style),
+            Span::styled(display, style),
+        ]));
+    }
+
+    let paragraph = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(
+        if form.is_edit {
+            "Edit record"
+        } else {
+            "Create record"
+        },
+    ));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_confirm_delete(frame: &mut Frame<'_>, confirm: &ConfirmDelete, danger: Option<&Account>) {
+    let area = centered_rect(60, 30, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Confirm delete",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("Delete record {}?", confirm.record_name)),
+    ];
+    lines.extend(danger.map(danger_line));
+    if let Some(danger) = confirm.danger {
+        lines.push(Line::from(Span::styled(
+            format!("This record is {danger}."),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    if confirm.require_typed {
+        lines.push(Line::from(vec![
+            Span::raw("Type "),
+            Span::styled(
+                confirm.record_name.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" or "),
+            Span::styled(
+                confirm.code.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" to confirm:"),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::styled(
+                confirm.typed.clone(),
+                if confirm.is_confirmed() {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default()
+                },
+            ),
+        ]));
+    }
+    lines.push(Line::from("Enter to confirm • Esc to cancel"));
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(danger_border(danger))
+            .title("Delete record"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// The warning a destructive confirmation shows for a danger account, in its colour.
+fn danger_line(account: &Account) -> Line<'static> {
+    let label = account.label.as_deref().unwrap_or_default().trim();
+    Line::from(Span::styled(
+        format!(" {label} account {} ", account.name),
+        danger_border(Some(account)).add_modifier(Modifier::REVERSED | Modifier::BOLD),
+    ))
+}
+
+/// A confirmation's border: the danger account's colour (red when it has none).
+fn danger_border(danger: Option<&Account>) -> Style {
+    match danger {
+        Some(account) => Style::default().fg(account_color(account).unwrap_or(Color::Red)),
+        None => Style::default(),
+    }
+}
+
+fn draw_zone_form(frame: &mut Frame<'_>, form: &ZoneForm) {
+    let area = centered_rect(60, 30, frame.size());
+    let lines = vec![
+        Line::from(Span::styled(
+            "Create a zone",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from("Enter to advance/submit • Tab to move • Esc to cancel"),
+        Line::from(""),
+        form_line("Zone name", &form.name, form.field_index == 0, true),
+        form_line(
+            "Setup (Space/←/→ to switch)",
+            form.setup.label(),
+            form.field_index == 1,
+            true,
+        ),
+    ];
+    let paragraph =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("New Zone"));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_confirm_delete_account(
+    frame: &mut Frame<'_>,
+    confirm: &ConfirmDeleteAccount,
+    config_path: &Path,
+    danger: bool,
+) {
+    let area = centered_rect(60, 30, frame.size());
+    let account = &confirm.account;
+    let danger = danger.then_some(account);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Confirm account removal",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Remove the {} account {}?",
+            account.provider.label(),
+            account.name
+        )),
+        Line::from(format!(
+            "Its credentials are deleted from {}; zones and records at the provider stay as they are.",
+            config_path.display()
+        )),
+    ];
+    lines.extend(danger.map(danger_line));
+    if confirm.require_typed {
+        lines.extend([
+            Line::from("Type the account name to confirm:"),
+            Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Yellow)),
+                Span::styled(
+                    confirm.typed.clone(),
+                    if confirm.is_confirmed() {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default()
+                    },
+                ),
+            ]),
+        ]);
+    }
+    lines.extend([
+        Line::from(""),
+        Line::from("Enter to remove • Esc to keep it"),
+    ]);
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(match danger {
+                    Some(_) => danger_border(danger),
+                    None => Style::default().fg(Color::Magenta),
+                })
+                .title("Remove account"),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_confirm_delete_zone(
+    frame: &mut Frame<'_>,
+    confirm: &ConfirmDeleteZone,
+    danger: Option<&Account>,
+) {
+    let area = centered_rect(60, 30, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Confirm zone delete",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Delete {} and its {} record(s)? This cannot be undone.",
+            confirm.zone.name, confirm.record_count
+        )),
+    ];
+    lines.extend(danger.map(danger_line));
+    lines.extend([
+        Line::from("Type the zone name to confirm:"),
+        Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::styled(
+                confirm.typed.clone(),
+                if confirm.is_confirmed() {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default()
+                },
+            ),
+        ]),
+        Line::from("Enter to confirm • Esc to cancel"),
+    ]);
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(danger_border(danger))
+            .title("Delete zone"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_confirm_bulk_delete(
+    frame: &mut Frame<'_>,
+    confirm: &ConfirmBulkDelete,
+    danger: Option<&Account>,
+) {
+    let area = centered_rect(60, 40, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Confirm bulk delete",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Delete {} {} record(s) from {} in one batch?",
+            confirm.records.len(),
+            if confirm.selection {
+                "selected"
+            } else {
+                "filtered"
+            },
+            confirm.zone_name
+        )),
+    ];
+    lines.extend(danger.map(danger_line));
+    let set = confirm.change_set();
+    // Room for the diff, inside the border, after the lines around it.
+    let room = (area.height as usize).saturating_sub(lines.len() + 6);
+    lines.extend(change_set_lines(&set, confirm.scroll, room));
+    lines.push(Line::from("Type the zone name to confirm:"));
+    lines.push(Line::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Yellow)),
+        Span::styled(
+            confirm.typed.clone(),
+            if confirm.is_confirmed() {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            },
+        ),
+    ]));
+    lines.push(Line::from(format!(
+        "Enter to confirm • Esc to cancel{}",
+        scroll_hint(&set, confirm.scroll, room)
+    )));
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(danger_border(danger))
+            .title("Delete records"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_confirm_create(frame: &mut Frame<'_>, confirm: &ConfirmCreate) {
+    let area = centered_rect(60, 30, frame.size());
+    let lines = vec![
+        Line::from(Span::styled(
+            "Possible conflict",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(confirm.conflict.describe()),
+        Line::from(format!(
+            "New: {} {} {}",
+            confirm.record.record_type, confirm.record.name, confirm.record.content
+        )),
+        Line::from("Enter to create anyway • Esc to go back to the form"),
+    ];
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Create record?"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// The one-line prompt shared by search, the zone filter and the TTL edits: what is
+/// being asked, the text typed after `marker`, and the keys that apply.
+fn draw_line_prompt(
+    frame: &mut Frame<'_>,
+    area: Rect,
+    title: &str,
+    heading: &str,
+    marker: &str,
+    text: &str,
+    keys: &str,
+) {
+    let mut lines = vec![
+        Line::from(heading.to_string()),
+        Line::from(vec![
+            Span::styled(format!("{marker} "), Style::default().fg(Color::Yellow)),
+            Span::raw(text.to_string()),
+        ]),
+    ];
+    lines.extend(keys.lines().map(|line| Line::from(line.to_string())));
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title.to_string()),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_profile_picker(frame: &mut Frame<'_>, picker: &ProfilePicker, current: &str) {
+    let area = centered_rect(50, 40, frame.size());
+    let mut lines = vec![
+        Line::from("↑/↓ to choose • Enter to switch • n for a new profile • Esc to close"),
+        Line::from(""),
+    ];
+    for (idx, (name, count)) in picker.profiles.iter().enumerate() {
+        let selected = idx == picker.selected && picker.new_name.is_none();
+        let style = if selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let marker = if selected { "→ " } else { "  " };
+        let in_use = if name == current { ", in use" } else { "" };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{marker}{name}"), style),
+            Span::styled(
+                format!("  {count} account(s){in_use}"),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    if let Some(name) = &picker.new_name {
+        lines.push(Line::from(""));
+        lines.push(form_line("New profile name", name, true, true));
+        lines.push(Line::from(
+            "Enter to create and switch to it • Esc to go back",
+        ));
+    }
+    let paragraph =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Profiles"));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_config_conflict(frame: &mut Frame<'_>, conflict: &ConfigConflict, path: &Path) {
+    let area = centered_rect(70, 40, frame.size());
+    let describe = |changes: &[String]| match changes {
+        [] => "no account changes".to_string(),
+        changes => changes.join(", "),
+    };
+    let lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "{} changed outside nyxflare before your changes were saved",
+                path.display()
+            ),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(format!("Yours:  {}", describe(&conflict.yours))),
+        Line::from(format!("Theirs: {}", describe(&conflict.theirs))),
+        Line::from(""),
+        Line::from("r: reload theirs and drop yours • k: keep yours and overwrite the file"),
+    ];
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Config conflict"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_zone_filter(frame: &mut Frame<'_>, prompt: &ZoneFilterPrompt) {
+    // Sits at the bottom so the narrowing zone list stays in view.
+    let full = frame.size();
+    let area = Rect {
+        x: full.x + full.width / 5,
+        y: full.bottom().saturating_sub(9),
+        width: full.width * 3 / 5,
+        height: 5.min(full.height),
+    };
+    draw_line_prompt(
+        frame,
+        area,
+        "Zones",
+        "Filter zones by name",
+        "/",
+        &prompt.text,
+        "Enter to open the highlighted zone • ↑/↓ pick • Esc to cancel",
+    );
+}
+
+fn draw_bulk_ttl(frame: &mut Frame<'_>, prompt: &TtlPrompt) {
+    draw_line_prompt(
+        frame,
+        centered_rect(60, 20, frame.size()),
+        "Set TTL",
+        &format!(
+            "New TTL for {} record(s) (auto or seconds)",
+            prompt.records.len()
+        ),
+        "TTL",
+        &prompt.text,
+        "Enter to apply • Esc to cancel",
+    );
+}
+
+fn draw_quick_edit(frame: &mut Frame<'_>, edit: &QuickEdit) {
+    let label = edit.field.label();
+    draw_line_prompt(
+        frame,
+        centered_rect(60, 20, frame.size()),
+        &format!("Set {label}"),
+        &format!(
+            "{label} for {} (now {}; {})",
+            edit.record.name,
+            edit.field.value(&edit.record),
+            edit.field.hint()
+        ),
+        label,
+        &edit.text,
+        "Enter to apply • Esc to cancel",
+    );
+}
+
+fn draw_confirm_bulk_update(
+    frame: &mut Frame<'_>,
+    confirm: &ConfirmBulkUpdate,
+    danger: Option<&Account>,
+) {
+    let area = centered_rect(70, 50, frame.size());
+    let set = confirm.change_set();
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "{} {} record(s)?",
+                confirm.action.describe(),
+                set.steps.len()
+            ),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(set.summary()),
+    ];
+    lines.extend(danger.map(danger_line));
+    lines.extend(warning_lines(&set));
+    let room = (area.height as usize).saturating_sub(lines.len() + 4);
+    lines.extend(change_set_lines(&set, confirm.scroll, room));
+    lines.push(Line::from(""));
+    let mut help = "Enter to apply • Esc to cancel".to_string();
+    if matches!(confirm.action, BulkAction::SetProxied(_)) {
+        help.push_str(" • Space to switch on/off");
+    }
+    help.push_str(&scroll_hint(&set, confirm.scroll, room));
+    lines.push(Line::from(help));
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(danger_border(danger))
+            .title("Change records"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_bulk_report(frame: &mut Frame<'_>, report: &BulkReport) {
+    let area = centered_rect(70, 60, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            report.summary.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    if report.failures.is_empty() {
+        lines.push(Line::from("Nothing failed."));
+    }
+    lines.extend(report.failures.iter().skip(report.scroll).map(|failure| {
+        Line::from(Span::styled(
+            failure.clone(),
+            Style::default().fg(Color::Red),
+        ))
+    }));
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Bulk change — ↑/↓ to scroll • Esc to close"),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_error(frame: &mut Frame<'_>, details: &ErrorDetails) {
+    let area = centered_rect(70, 50, frame.size());
+    let label = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Operation: ", label),
+        Span::raw(details.operation.clone()),
+    ])];
+    if let Some(status) = &details.status {
+        lines.push(Line::from(vec![
+            Span::styled("HTTP status: ", label),
+            Span::raw(status.clone()),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        details.message.clone(),
+        Style::default().fg(Color::Red),
+    )));
+    if let Some(body) = &details.body {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Response body:", label)));
+        lines.push(Line::from(Span::styled(
+            truncate_body(body),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    let close = if details.form.is_some() {
+        "Esc to edit again"
+    } else {
+        "Esc to close"
+    };
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red))
+                .title(format!("Error — y to copy • {close}")),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_message_history(
+    frame: &mut Frame<'_>,
+    history: &MessageHistory,
+    messages: &VecDeque<LoggedMessage>,
+) {
+    let area = centered_rect(80, 70, frame.size());
+    let lines: Vec<Line> = messages
+        .iter()
+        .rev()
+        .skip(history.scroll)
+        .map(|message| {
+            let style = match message.level {
+                MessageLevel::Error => Style::default().fg(Color::Red),
+                MessageLevel::Progress => Style::default().fg(Color::DarkGray),
+                MessageLevel::Info => Style::default(),
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>8} ", format_age(message.at.elapsed())),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(message.text.clone(), style),
+            ])
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Messages ({}, newest first) — ↑/↓ to scroll • Esc to close",
+            messages.len()
+        )))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_help(frame: &mut Frame<'_>, help: &HelpView) {
+    let area = centered_rect(70, 80, frame.size());
+    let lines = help_lines(help.focus);
+    // Stop scrolling once the last line is on screen.
+    let visible = area.height.saturating_sub(2) as usize;
+    let scroll = help.scroll.min(lines.len().saturating_sub(visible));
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Keys — ↑/↓ to scroll • ? or Esc to close"),
+        )
+        .scroll((scroll as u16, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_record_conflict(frame: &mut Frame<'_>, conflict: &RecordConflict) {
+    let area = centered_rect(70, 40, frame.size());
+    let describe = |record: &DnsRecord| {
+        format!(
+            "{} {} {} (TTL {}, {})",
+            record.record_type,
+            record.name,
+            record.content,
+            record.ttl,
+            if record.proxied {
+                "proxied"
+            } else {
+                "DNS only"
+            }
+        )
+    };
+    let remote = &conflict.remote;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Changed elsewhere since it was loaded",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Modified on: {}",
+            remote.modified_on.as_deref().unwrap_or("unknown")
+        )),
+        Line::from(format!("Now:   {}", describe(remote))),
+    ];
+    let action = match &conflict.pending {
+        PendingChange::Update(record) => {
+            lines.push(Line::from(format!("Yours: {}", describe(record))));
+            "overwrite with yours"
+        }
+        PendingChange::Delete => "delete anyway",
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "o: {action} • r: reload theirs • c/Esc: cancel"
+    )));
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Record conflict"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_import_form(frame: &mut Frame<'_>, form: &ImportForm, check: Result<Option<String>>) {
+    let area = centered_rect(70, 50, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Import a BIND zone file",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from("Enter to preview, Enter again to import • Tab to move • Esc to cancel"),
+        Line::from(""),
+        form_line("Zone file path", &form.path, form.field_index == 0, true),
+        form_line(
+            "Proxy imported A/AAAA/CNAME records (Space)",
+            if form.proxied { "yes" } else { "no" },
+            form.field_index == 1,
+            true,
+        ),
+        Line::from(""),
+    ];
+
+    match &form.preview {
+        Some(preview) => {
+            lines.push(Line::from(format!(
+                "{} record(s) will be added: {}",
+                preview.records,
+                preview.type_summary()
+            )));
+            if preview.skipped > 0 {
+                lines.push(Line::from(format!(
+                    "{} SOA/apex NS record(s) skipped (managed by Cloudflare)",
+                    preview.skipped
+                )));
+            }
+            match check {
+                Ok(None) => {}
+                Ok(Some(warning)) => lines.push(Line::from(Span::styled(
+                    warning,
+                    Style::default().fg(Color::Yellow),
+                ))),
+                Err(err) => lines.push(Line::from(Span::styled(
+                    err.to_string(),
+                    Style::default().fg(Color::Red),
+                ))),
+            }
+            lines.push(Line::from("Press Enter to import."));
+        }
+        None => lines.push(Line::from("Press Enter to preview the file.")),
+    }
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Import Zone File"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_apply_form(frame: &mut Frame<'_>, form: &ApplyForm, danger: Option<&Account>) {
+    let area = centered_rect(80, 70, frame.size());
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Apply a records file (YAML, TOML or JSON)",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from("Enter to compare, Enter again to apply • Tab to move • Esc to cancel"),
+        Line::from(""),
+        form_line("Records file path", &form.path, form.field_index == 0, true),
+        form_line(
+            "Delete names and types the file lacks (Space)",
+            if form.prune { "yes" } else { "no" },
+            form.field_index == 1,
+            true,
+        ),
+        Line::from(""),
+    ];
+    lines.extend(danger.map(danger_line));
+
+    let Some(plan) = &form.preview else {
+        lines.push(Line::from("Press Enter to compare the file with the zone."));
+        return draw_apply_lines(frame, area, lines, danger);
+    };
+    lines.push(Line::from(Span::styled(
+        plan.summary(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )));
+    lines.extend(warning_lines(plan));
+    if plan.steps.is_empty() {
+        lines.push(Line::from(
+            "The zone already matches the file. Enter to close.",
+        ));
+        return draw_apply_lines(frame, area, lines, danger);
+    }
+    let confirm = form.confirm_zone.as_ref().map(|_| {
+        [
+            Line::from("Type the zone name to confirm the deletes:"),
+            Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Yellow)),
+                Span::styled(
+                    form.typed.clone(),
+                    if form.is_confirmed() {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default()
+                    },
+                ),
+            ]),
+        ]
+    });
+    // What's left of the overlay, inside its border, after the lines above and the hint.
+    let below = 4 + confirm.as_ref().map_or(0, |lines| lines.len());
+    let room = (area.height as usize).saturating_sub(lines.len() + below);
+    lines.extend(change_set_lines(plan, form.scroll, room));
+    lines.push(Line::from(""));
+    lines.extend(confirm.into_iter().flatten());
+    lines.push(Line::from(format!(
+        "Enter to make these changes{}",
+        scroll_hint(plan, form.scroll, room)
+    )));
+    draw_apply_lines(frame, area, lines, danger);
+}
+
+/// Up to `room` steps of `set` from `scroll` on, as `+` creates in green, `~` updates in
+/// yellow and `-` deletes in red: the diff every bulk change is confirmed from.
+fn change_set_lines(set: &ChangeSet, scroll: usize, room: usize) -> Vec<Line<'static>> {
+    set.steps
+        .iter()
+        .skip(scroll)
+        .take(room)
+        .map(|step| {
+            let color = match step {
+                Step::Create { .. } => Color::Green,
+                Step::Update { .. } => Color::Yellow,
+                Step::Delete { .. } => Color::Red,
+            };
+            Line::from(Span::styled(
+                format!("{} {}", step.sign(), step.describe()),
+                Style::default().fg(color),
+            ))
+        })
+        .collect()
+}
+
+/// The set's policy warnings, the first few in full and the rest counted.
+fn warning_lines(set: &ChangeSet) -> Vec<Line<'static>> {
+    const SHOWN: usize = 3;
+    let style = Style::default().fg(Color::Yellow);
+    let mut lines: Vec<Line<'static>> = set
+        .warnings
+        .iter()
+        .take(SHOWN)
+        .map(|warning| Line::from(Span::styled(format!("⚠ {warning}"), style)))
+        .collect();
+    if set.warnings.len() > SHOWN {
+        let more = format!("⚠ …and {} more", set.warnings.len() - SHOWN);
+        lines.push(Line::from(Span::styled(more, style)));
+    }
+    lines
+}
+
+/// ` • PgUp/PgDn to scroll (1–10 of 40)` when the diff doesn't fit in `room` lines.
+fn scroll_hint(set: &ChangeSet, scroll: usize, room: usize) -> String {
+    let total = set.steps.len();
+    if total <= room {
+        return String::new();
+    }
+    format!(
+        " • PgUp/PgDn to scroll ({}–{} of {total})",
+        scroll + 1,
+        (scroll + room).min(total)
+    )
+}
+
+fn draw_apply_lines(
+    frame: &mut Frame<'_>,
+    area: Rect,
+    lines: Vec<Line<'static>>,
+    danger: Option<&Account>,
+) {
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(danger_border(danger))
+            .title("Apply Records File"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn join_or_none(names: &[String]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn draw_zone_detail(frame: &mut Frame<'_>, detail: &ZoneDetail) {
+    let area = centered_rect(80, 60, frame.size());
+    let zone = &detail.zone;
+    let mut lines = vec![
+        Line::from("s: toggle DNSSEC • Esc to close"),
+        Line::from(""),
+        Line::from(format!("ID: {}", zone.id)),
+        Line::from(format!("Name: {}", zone.name)),
+        Line::from(vec![
+            Span::raw("Status: "),
+            Span::styled(
+                zone.status.clone().unwrap_or_else(|| "unknown".to_string()),
+                if zone.is_pending() {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                },
+            ),
+        ]),
+        Line::from(format!(
+            "Plan: {}",
+            zone.plan.as_deref().unwrap_or("unknown")
+        )),
+        Line::from(format!("Nameservers: {}", join_or_none(&zone.name_servers))),
+        Line::from(format!(
+            "Original nameservers: {}",
+            join_or_none(&zone.original_name_servers)
+        )),
+        Line::from(""),
+    ];
+
+    match (&detail.dnssec, &detail.dnssec_note) {
+        (Some(dnssec), _) => {
+            let color = match dnssec.status.as_str() {
+                "active" => Color::Green,
+                "disabled" => Color::DarkGray,
+                _ => Color::Yellow,
+            };
+            lines.push(Line::from(vec![
+                Span::raw("DNSSEC: "),
+                Span::styled(dnssec.status.clone(), Style::default().fg(color)),
+            ]));
+            if let Some(digest) = &dnssec.digest {
+                lines.push(Line::from(format!("Digest: {digest}")));
+            }
+            if let Some(ds) = &dnssec.ds {
+                lines.push(Line::from("DS record (add at your registrar):"));
+                lines.push(Line::from(ds.clone()));
+            }
+        }
+        (None, Some(note)) => lines.push(Line::from(Span::styled(
+            note.clone(),
+            Style::default().fg(Color::Yellow),
+        ))),
+        (None, None) => lines.push(Line::from("DNSSEC: unknown")),
+    }
+
+    if detail.confirm_toggle {
+        let action = if detail.dnssec.as_ref().is_some_and(|d| d.is_enabled()) {
+            "Disable"
+        } else {
+            "Enable"
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{action} DNSSEC for {}? y to confirm • n to cancel",
+                zone.name
+            ),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Zone {}", zone.name)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_record_detail(frame: &mut Frame<'_>, detail: &RecordDetail) {
+    let area = centered_rect(80, 70, frame.size());
+    let record = &detail.record;
+    let tab_style = |tab: DetailTab| {
+        if detail.tab == tab {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Details", tab_style(DetailTab::Details)),
+            Span::raw("  |  "),
+            Span::styled("History", tab_style(DetailTab::History)),
+        ]),
+        Line::from("h: toggle history • ↑/↓ PgUp/PgDn: page • Esc to close"),
+        Line::from(""),
+    ];
+
+    match detail.tab {
+        DetailTab::Details => {
+            lines.push(Line::from(format!("ID: {}", record.id)));
+            lines.push(Line::from(format!("Name: {}", record.name)));
+            lines.push(Line::from(format!("Type: {}", record.record_type)));
+            lines.push(Line::from(format!("Content: {}", record.content)));
+            lines.push(Line::from(format!("TTL: {}", record.ttl)));
+            lines.push(Line::from(format!("Proxied: {}", record.proxied)));
+        }
+        DetailTab::History => {
+            if let Some(note) = &detail.history_note {
+                lines.push(Line::from(Span::styled(
+                    note.clone(),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            if detail.history.is_empty() {
+                lines.push(Line::from("No known changes for this record."));
+            }
+            let page_count = detail.history.len().div_ceil(HISTORY_PAGE_SIZE).max(1);
+            let start = detail.history_page * HISTORY_PAGE_SIZE;
+            for entry in detail.history.iter().skip(start).take(HISTORY_PAGE_SIZE) {
+                let actor = entry.actor.as_deref().unwrap_or("unknown");
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format_timestamp(entry.timestamp),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw(format!(
+                        "  [{}] {} by {}",
+                        entry.source.label(),
+                        entry.action.label(),
+                        actor
+                    )),
+                ]));
+                for change in &entry.changes {
+                    lines.push(Line::from(format!("    {}", change.describe())));
+                }
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!(
+                "Page {}/{} ({} event(s))",
+                detail.history_page + 1,
+                page_count,
+                detail.history.len()
+            )));
+        }
+    }
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Record {}", record.name)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_search_overlay(frame: &mut Frame<'_>, prompt: &SearchPrompt, matches: usize) {
+    draw_line_prompt(
+        frame,
+        centered_rect(60, 20, frame.size()),
+        if prompt.locate { "Locate" } else { "Search" },
+        &match (RecordQuery::parse(&prompt.text), prompt.scope) {
+            (Err(err), _) => err,
+            (Ok(_), _) if prompt.locate => {
+                format!("Jump between matches with n/N, list left whole — {matches} match(es)")
+            }
+            (Ok(_), SearchScope::Local) => {
+                format!("Filter loaded records (name/content/type) — {matches} shown")
+            }
+            (Ok(_), SearchScope::Server) => format!(
+                "Search all records via the API (name/content) — {matches} loaded match(es)"
+            ),
+        },
+        "/",
+        &prompt.text,
+        &format!(
+            "{FILTER_SYNTAX_HINT}\nEnter apply • Esc cancel • Tab scope • F2 re: • F3 fz: • F4 locate"
+        ),
+    );
+}
+
+fn form_line(label: &str, value: &str, active: bool, required: bool) -> Line<'static> {
+    let mut display = if value.is_empty() {
+        if required {
+            "<required>".to_string()
+        } else {
+            "<optional>".to_string()
+        }
+    } else {
+        value.to_string()
+    };
+
+    if !required && value.is_empty() {
+        display.push_str(" (press Enter to skip)");
+    }
+
+    let style = if active {
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+
+    Line::from(vec![
+        Span::styled(format!("{label}: "), style),
+        Span::styled(display, style),
+    ])
+}
+
+fn centered_rect(
+    percent_x: u16,
+    percent_y: u16,
+    r: ratatui::prelude::Rect,
+) -> ratatui::prelude::Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    let vertical = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1]);
+
+    vertical[1]
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Account {
+    name: String,
+    /// Empty in the file when `token_cmd` supplies it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    api_token: String,
+    email: Option<String>,
+    #[serde(default)]
+    account_id: Option<String>,
+    /// Cloudflare's name for `account_id`, recorded when it was discovered via `/accounts`.
+    #[serde(default)]
+    account_name: Option<String>,
+    #[serde(default)]
+    auth_mode: AuthMode,
+    /// API base to use instead of `api.cloudflare.com`, e.g. a compatible staging shim.
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Which provider the credentials are for; the backend serving the account follows it.
+    #[serde(default)]
+    provider: Provider,
+    /// Route53 only: the access key ID; `api_token` holds its secret.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aws_access_key_id: Option<String>,
+    /// Zone-file accounts only: the directory holding one file per zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zone_dir: Option<String>,
+    /// RFC 2136 only: the server taking updates and transfers (`host` or `host:port`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dns_server: Option<String>,
+    /// RFC 2136 only: the TSIG key's name; `api_token` holds its base64 secret.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tsig_key_name: Option<String>,
+    /// RFC 2136 only: `hmac-sha256` (the default), `hmac-sha384` or `hmac-sha512`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tsig_algorithm: Option<String>,
+    /// RFC 2136 only: the zones to offer, since the protocol can't list them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    zones: Vec<String>,
+    /// Set when `api_token` was resolved from an `env:`/`file:` reference at startup.
+    #[serde(skip)]
+    token_ref: Option<TokenRef>,
+    /// Shell command printing the secret (`pass show cloudflare/personal`), run at
+    /// startup; saved in place of what it prints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_cmd: Option<String>,
+    /// Why `token_cmd` gave no secret; the account can't be loaded until it does.
+    #[serde(skip)]
+    token_error: Option<String>,
+    /// TTL new records start with (1 is auto) instead of 300.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_ttl: Option<u32>,
+    /// Whether new records start proxied, where the provider has a proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_proxied: Option<bool>,
+    /// Short tag shown beside the name, e.g. `PROD`; danger labels make deletes louder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    /// Colour of the tag and the records border: a name like `red` or a hex `#ff8800`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+}
+
+impl Account {
+    /// Whether the account came from a shared config and its secret hasn't been entered.
+    fn lacks_secret(&self) -> bool {
+        self.api_token == EXPORTED_SECRET
+    }
+}
+
+/// An account secret the config names instead of holding: `env:NAME` or `file:/path`.
+#[derive(Clone, PartialEq, Eq)]
+struct TokenRef {
+    /// As written in the config, and written back on save.
+    raw: String,
+    /// What it resolved to; once the secret is replaced the reference no longer applies.
+    resolved: String,
+}
+
+/// The secret an `env:NAME` or `file:/path` token reference points at, or why it can't
+/// be had; `None` for a literal token. `var` looks up environment variables.
+fn resolve_token_ref(
+    raw: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Option<Result<String, String>> {
+    let (value, blank) = if let Some(name) = raw.strip_prefix("env:") {
+        match var(name) {
+            Some(value) => (value, "is empty"),
+            None => return Some(Err("is not set".to_string())),
+        }
+    } else {
+        let path = raw.strip_prefix("file:")?;
+        match fs::read_to_string(path) {
+            Ok(text) => (text, "is an empty file"),
+            Err(err) => return Some(Err(format!("can't be read: {err}"))),
+        }
+    };
+    let value = value.trim();
+    Some(if value.is_empty() {
+        Err(blank.to_string())
+    } else {
+        Ok(value.to_string())
+    })
+}
+
+/// Runs `command` through the shell and returns what it printed, trimmed, as a secret.
+/// It can still prompt on the terminal (gpg's pinentry), so it's run before the TUI
+/// starts. Errors carry the exit status and stderr, never stdout.
+fn run_token_command(command: &str, timeout: Duration) -> Result<String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = std::process::Command::new(shell)
+        .args([flag, command])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("couldn't start it")?;
+    // Drained on threads so a chatty command can't fill a pipe and stall.
+    let drain = |pipe: Option<Box<dyn io::Read + Send>>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as _));
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("timed out after {timeout:?}"));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(match stderr.trim() {
+            "" => anyhow!("{status}"),
+            stderr => anyhow!("{status}: {stderr}"),
+        });
+    }
+    match stdout.trim() {
+        "" => Err(anyhow!("printed nothing")),
+        secret => Ok(secret.to_string()),
+    }
+}
+
+/// Everything but the secret, so accounts can end up in logs and error strings safely.
+impl std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("name", &self.name)
+            .field("api_token", &mask_secret(&self.api_token))
+            .field("email", &self.email)
+            .field("account_id", &self.account_id)
+            .field("account_name", &self.account_name)
+            .field("auth_mode", &self.auth_mode)
+            .field("base_url", &self.base_url)
+            .field("provider", &self.provider)
+            .field("aws_access_key_id", &self.aws_access_key_id)
+            .field("zone_dir", &self.zone_dir)
+            .field("dns_server", &self.dns_server)
+            .field("tsig_key_name", &self.tsig_key_name)
+            .field("tsig_algorithm", &self.tsig_algorithm)
+            .field("zones", &self.zones)
+            .field("token_ref", &self.token_ref.as_ref().map(|r| &r.raw))
+            .field("token_cmd", &self.token_cmd)
+            .field("token_error", &self.token_error)
+            .field("label", &self.label)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
+/// A secret reduced to its last four characters, or bullets alone when it's too short to spare any.
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "••••".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("••••{tail}")
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+enum Provider {
+    #[default]
+    Cloudflare,
+    Route53,
+    #[serde(rename = "digitalocean")]
+    DigitalOcean,
+    /// BIND zone files in a local directory (`zone_dir`).
+    ZoneFile,
+    /// RFC 2136 dynamic updates with TSIG, e.g. to BIND.
+    #[serde(rename = "rfc2136")]
+    Rfc2136,
+}
+
+impl Provider {
+    fn label(self) -> &'static str {
+        match self {
+            Provider::Cloudflare => "Cloudflare",
+            Provider::Route53 => "Route53",
+            Provider::DigitalOcean => "DigitalOcean",
+            Provider::ZoneFile => "zone-file",
+            Provider::Rfc2136 => "RFC 2136",
+        }
+    }
+
+    /// Only Cloudflare proxies traffic; elsewhere the proxied column shows "—".
+    fn has_proxy(self) -> bool {
+        self == Provider::Cloudflare
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum AuthMode {
+    #[default]
+    Token,
+    GlobalKey,
+}
+
+/// An account the credentials can access, as listed by `GET /accounts`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+struct CloudflareAccount {
+    id: String,
+    name: String,
+}
+
+/// What Cloudflare reports about an account's credentials.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+struct TokenStatus {
+    /// `active`, `disabled` or `expired` for API tokens.
+    status: String,
+    #[serde(default)]
+    expires_on: Option<String>,
+}
+
+impl TokenStatus {
+    fn is_active(&self) -> bool {
+        self.status == "active"
+    }
+
+    fn describe(&self) -> String {
+        let expiry = match &self.expires_on {
+            Some(at) => match parse_rfc3339(at) {
+                Some(ts) => format!("expires {}", format_timestamp(ts)),
+                None => format!("expires {at}"),
+            },
+            None => "no expiry".to_string(),
+        };
+        format!("token {} ({expiry})", self.status)
+    }
+}
+
+/// A zone's DNSSEC state, as `GET /zones/{id}/dnssec` reports it.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+struct DnssecStatus {
+    /// `active`, `pending`, `disabled`, `pending-disabled` or `error`.
+    status: String,
+    /// The DS record to publish at the registrar, once the zone is signed.
+    #[serde(default)]
+    ds: Option<String>,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+impl DnssecStatus {
+    /// Whether DNSSEC is on or being turned on, i.e. a toggle would disable it.
+    fn is_enabled(&self) -> bool {
+        matches!(self.status.as_str(), "active" | "pending")
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+    /// `active`, `pending` (nameservers not switched yet), `initializing`, `moved`...
+    #[serde(default)]
+    status: Option<String>,
+    /// Nameservers Cloudflare assigned; the registrar must point at these.
+    #[serde(default)]
+    name_servers: Vec<String>,
+    /// Nameservers the domain used before it was added to Cloudflare.
+    #[serde(default)]
+    original_name_servers: Vec<String>,
+    #[serde(default)]
+    plan: Option<String>,
+}
+
+impl Zone {
+    fn is_pending(&self) -> bool {
+        self.status.as_deref() == Some("pending")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct DnsRecord {
+    id: String,
+    name: String,
+    record_type: String,
+    content: String,
+    ttl: u32,
+    proxied: bool,
+    /// API fields set on the record (comment, tags, settings) that an update would not send back.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unmodeled: Vec<String>,
+    /// When Cloudflare last changed the record, for spotting edits made elsewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified_on: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Config {
+    /// The accounts of the default profile.
+    #[serde(default)]
+    accounts: Vec<Account>,
+    /// Further named sets of accounts, such as `work` and `personal`; one is in use at a time.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Vec<Account>>,
+    /// Where `x` writes zone exports; defaults to `exports/` next to the config file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    export_dir: Option<PathBuf>,
+    /// Hardened mode: validate config at startup and refuse lossy or unconfirmed operations.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
+    /// Attempts for requests Cloudflare rate-limits (429), including the first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rate_limit_attempts: Option<u32>,
+    /// Whole-request timeout for Cloudflare API calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_timeout_secs: Option<u64>,
+    /// Timeout for establishing the connection (including any proxy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_secs: Option<u64>,
+    /// Re-download the zone's records after every edit instead of patching the loaded list.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    full_refresh_after_edit: bool,
+    /// Stop a bulk change at its first failed step instead of sending the rest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stop_on_failure: bool,
+    /// Where `ddns` looks up the public address: a URL that answers with it, or `local`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ddns_source: Option<String>,
+    /// How long a zone list is reused when switching back to an account; 0 disables reuse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zone_cache_secs: Option<u64>,
+    /// Rows the selection moves per mouse wheel notch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scroll_step: Option<usize>,
+    /// How `y` reaches the clipboard.
+    #[serde(default, skip_serializing_if = "is_default")]
+    clipboard: ClipboardMode,
+    /// Longest text sent through OSC 52; longer copies are cut short with a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    osc52_max_bytes: Option<usize>,
+    /// Hide the Accounts panel so the zones and records get the full width.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    collapse_accounts: bool,
+    /// Type column colors by record type (`"TXT": "cyan"`, `"#ff8800"`, or `"none"`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    type_colors: HashMap<String, String>,
+    /// Account labels that make delete and bulk confirmations loud; empty means `PROD`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    danger_labels: Vec<String>,
+    /// Set when the file is encrypted, or is to be; `save` seals it with this.
+    #[serde(skip)]
+    passphrase: Option<String>,
+    /// What loading found odd but could get past, such as unknown fields, for the
+    /// message history.
+    #[serde(skip)]
+    warnings: Vec<String>,
+    /// What became of a config found at the legacy path, for the status line.
+    #[serde(skip)]
+    migration: Option<String>,
+    /// The profile in use, `None` being the default one.
+    #[serde(skip)]
+    profile: Option<String>,
+    /// The account and zone name `--account` and `--zone` start the TUI on, in place of
+    /// the remembered selection.
+    #[serde(skip)]
+    start: Option<(String, Option<String>)>,
+}
+
+/// The comment block a TOML file starts with, blank lines included, followed by a blank
+/// line; empty when the file doesn't start with a comment.
+fn leading_comments(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .take_while(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+        .collect();
+    let header = lines.join("\n");
+    let header = header.trim();
+    if header.is_empty() {
+        String::new()
+    } else {
+        format!("{header}\n\n")
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ClipboardMode {
+    /// A clipboard command when one is installed and the session is local, else OSC 52.
+    #[default]
+    Auto,
+    /// Always the OSC 52 escape sequence, which the terminal turns into a local copy.
+    Osc52,
+    /// Only clipboard commands (`pbcopy`, `wl-copy`, `xclip`, `xsel`, `clip`).
+    System,
+}
+
+/// How a config file is written, going by its extension: `.toml`, else JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
+
+impl Config {
+    /// The unencrypted config saved at `path`, for checking what was written; startup
+    /// goes through `load_config`, which can also unlock an encrypted file.
+    #[cfg(test)]
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        if is_encrypted(&contents) {
+            return Err(anyhow!(
+                "{} is encrypted and needs its passphrase",
+                path.display()
+            ));
+        }
+        Self::parse(path, &contents)
+    }
+
+    /// `contents` of the config file at `path`, read in the format its extension names.
+    /// Errors name the file, the line, and for a bad value the account and field it's in.
+    fn parse(path: &Path, contents: &str) -> Result<Self> {
+        let config: Config = match ConfigFormat::of(path) {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|err| {
+                let at = format!("line {} column {}", err.line(), err.column());
+                let message = err.to_string();
+                let message = message
+                    .strip_suffix(&format!(" at {at}"))
+                    .unwrap_or(&message);
+                let tree = serde_json::from_str(contents).ok();
+                config_error(path, "JSON", tree, message, &at)
+            })?,
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|err| {
+                let at = err.span().map_or_else(String::new, |span| {
+                    let breaks = contents.as_bytes()[..span.start]
+                        .iter()
+                        .filter(|&&byte| byte == b'\n')
+                        .count();
+                    format!("line {}", breaks + 1)
+                });
+                let tree = contents
+                    .parse::<toml::Table>()
+                    .ok()
+                    .and_then(|table| serde_json::to_value(table).ok());
+                config_error(path, "TOML", tree, err.message(), &at)
+            })?,
+        };
+        let problems = config.problems();
+        if !problems.is_empty() {
+            let report: Vec<String> = problems.iter().map(|p| format!("  - {p}")).collect();
+            return Err(anyhow!(
+                "{} has {} problem(s):\n{}",
+                path.display(),
+                problems.len(),
+                report.join("\n")
+            ));
+        }
+        Ok(config)
+    }
+
+    /// What the types can't rule out: a global API key without the email it goes with,
+    /// a default TTL Cloudflare wouldn't take, two accounts by one name in a profile,
+    /// which switching and the session state go by, and a named profile clashing with
+    /// the default one.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.profiles.contains_key(DEFAULT_PROFILE) {
+            problems.push(format!(
+                "profiles.{DEFAULT_PROFILE}: the {DEFAULT_PROFILE} profile is the top-level accounts list"
+            ));
+        }
+        for (list, accounts) in self.account_lists() {
+            for (idx, account) in accounts.iter().enumerate() {
+                let label = format!("{list}[{idx}] ({})", account.name);
+                if account.auth_mode == AuthMode::GlobalKey
+                    && account
+                        .email
+                        .as_deref()
+                        .is_none_or(|email| email.trim().is_empty())
+                {
+                    problems.push(format!(
+                        "{label}: auth_mode global_key needs the account's email"
+                    ));
+                }
+                if let Some(ttl) = account.default_ttl
+                    && parse_bulk_ttl(&ttl.to_string()).is_err()
+                {
+                    problems.push(format!(
+                        "{label}: default_ttl {ttl} isn't 1 (auto) or 30 to 86400 seconds"
+                    ));
+                }
+                if let Some(color) = &account.color
+                    && parse_account_color(color).is_none()
+                {
+                    problems.push(format!(
+                        "{label}: color {color:?} isn't a name like red or a hex like #ff8800"
+                    ));
+                }
+                if let Some(first) = accounts[..idx]
+                    .iter()
+                    .position(|other| other.name == account.name)
+                {
+                    problems.push(format!(
+                        "{label}: the name is already used by {list}[{first}]"
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Writes the config in the format its extension names, sealed when it has a
+    /// passphrase, after copying the file it replaces to `.bak`. An unencrypted TOML
+    /// file keeps the comments at its top; comments further down don't survive the
+    /// rewrite.
+    fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let config = self.stored();
+        let text = match ConfigFormat::of(path) {
+            ConfigFormat::Json => serde_json::to_string_pretty(&config)?,
+            ConfigFormat::Toml => {
+                let previous = fs::read_to_string(path).unwrap_or_default();
+                format!(
+                    "{}{}",
+                    leading_comments(&previous),
+                    toml::to_string_pretty(&config)?
+                )
+            }
+        };
+        let text = match &self.passphrase {
+            Some(passphrase) => seal_config(&text, passphrase)?,
+            None => text,
+        };
+        if path.exists() {
+            let backup = backup_path(path);
+            fs::copy(path, &backup).with_context(|| format!("Writing {}", backup.display()))?;
+        }
+        write_private(path, &text)
+    }
+
+    /// The config as written to disk: secrets that came from a reference go back to
+    /// being the reference, so saving never spells them out.
+    fn stored(&self) -> Config {
+        let mut config = self.clone();
+        let accounts = config
+            .account_lists_mut()
+            .flat_map(|(_, accounts)| accounts);
+        for account in accounts {
+            if account.token_cmd.is_some() {
+                account.api_token.clear();
+            }
+            if let Some(token_ref) = account.token_ref.take()
+                && token_ref.resolved == account.api_token
+            {
+                account.api_token = token_ref.raw;
+            }
+        }
+        config
+    }
+
+    /// A copy to hand to someone else: every secret is replaced by `EXPORTED_SECRET`,
+    /// with the `token_cmd`s and `env:`/`file:` references that supply them, and
+    /// `export_dir` is left out since it names a directory on this machine.
+    fn shared(&self) -> Config {
+        let mut config = Config {
+            export_dir: None,
+            passphrase: None,
+            ..self.clone()
+        };
+        for account in config
+            .account_lists_mut()
+            .flat_map(|(_, accounts)| accounts)
+        {
+            account.api_token = EXPORTED_SECRET.to_string();
+            account.token_ref = None;
+            account.token_cmd = None;
+            account.token_error = None;
+        }
+        config
+    }
+
+    /// Adds what a shared config has and this one lacks: accounts whose names are new
+    /// to their profile, and settings left unset here. Returns a note on each account
+    /// and setting, taken or not.
+    fn merge_shared(&mut self, mut shared: Config) -> Result<Vec<String>> {
+        let mut notes = Vec::new();
+        let lists = std::iter::once((
+            DEFAULT_PROFILE.to_string(),
+            std::mem::take(&mut shared.accounts),
+        ))
+        .chain(std::mem::take(&mut shared.profiles));
+        for (profile, accounts) in lists {
+            let mine = self.profile_accounts_mut(&profile);
+            for account in accounts {
+                if mine.iter().any(|known| known.name == account.name) {
+                    notes.push(format!(
+                        "Kept your own account {} in profile {profile}",
+                        account.name
+                    ));
+                } else {
+                    notes.push(format!(
+                        "Added account {} to profile {profile}",
+                        account.name
+                    ));
+                    mine.push(account);
+                }
+            }
+        }
+
+        // Unset settings are left out of the serialized form, so a key missing from
+        // ours is one to take from theirs.
+        let settings = |config: &Config| {
+            serde_json::to_value(Config {
+                accounts: Vec::new(),
+                profiles: BTreeMap::new(),
+                ..config.clone()
+            })
+        };
+        let (serde_json::Value::Object(mut ours), serde_json::Value::Object(theirs)) =
+            (settings(self)?, settings(&shared)?)
+        else {
+            return Err(anyhow!("a config serializes to a JSON object"));
+        };
+        for (key, value) in theirs {
+            if !ours.contains_key(&key) {
+                notes.push(format!("Took the setting {key} from the shared config"));
+                ours.insert(key, value);
+            }
+        }
+        let merged: Config = serde_json::from_value(serde_json::Value::Object(ours))?;
+        *self = Config {
+            accounts: std::mem::take(&mut self.accounts),
+            profiles: std::mem::take(&mut self.profiles),
+            passphrase: self.passphrase.take(),
+            warnings: std::mem::take(&mut self.warnings),
+            migration: self.migration.take(),
+            profile: self.profile.take(),
+            ..merged
+        };
+        Ok(notes)
+    }
+
+    /// Swaps `env:`/`file:` token references for the secrets they name, remembering each
+    /// reference so it's what gets saved. Fails listing every reference that doesn't
+    /// resolve, rather than leaving them to come back from the API as a 403.
+    fn resolve_token_refs(
+        &mut self,
+        path: &Path,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<()> {
+        let mut problems = Vec::new();
+        let accounts = self.account_lists_mut().flat_map(|(list, accounts)| {
+            accounts
+                .iter_mut()
+                .enumerate()
+                .map(move |(idx, account)| (format!("{list}[{idx}]"), account))
+        });
+        for (place, account) in accounts {
+            match resolve_token_ref(&account.api_token, &var) {
+                None => {}
+                Some(Ok(secret)) => {
+                    let raw = std::mem::replace(&mut account.api_token, secret.clone());
+                    account.token_ref = Some(TokenRef {
+                        raw,
+                        resolved: secret,
+                    });
+                }
+                Some(Err(err)) => problems.push(format!(
+                    "  - {place} ({}): {} {err}",
+                    account.name, account.api_token
+                )),
+            }
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "{} has {} API token reference(s) that don't resolve:\n{}",
+            path.display(),
+            problems.len(),
+            problems.join("\n")
+        ))
+    }
+
+    /// Runs each account's `token_cmd` for its secret, in every profile so switching
+    /// later needs no terminal. One that fails leaves its account without a token,
+    /// marked with why, and the others start as usual. Returns the failures for the
+    /// message history.
+    fn run_token_commands(&mut self, timeout: Duration) -> Vec<String> {
+        let mut failures = Vec::new();
+        for account in self.account_lists_mut().flat_map(|(_, accounts)| accounts) {
+            let Some(command) = &account.token_cmd else {
+                continue;
+            };
+            match run_token_command(command, timeout) {
+                Ok(secret) => account.api_token = secret,
+                Err(err) => {
+                    account.api_token.clear();
+                    let reason = format!("{err:#}");
+                    failures.push(format!("token_cmd for {} failed: {reason}", account.name));
+                    account.token_error = Some(reason);
+                }
+            }
+        }
+        failures
+    }
+
+    /// Every account list with where it sits in the file: `accounts` (the default
+    /// profile), then `profiles.<name>` by name.
+    fn account_lists(&self) -> impl Iterator<Item = (String, &Vec<Account>)> {
+        std::iter::once(("accounts".to_string(), &self.accounts)).chain(
+            self.profiles
+                .iter()
+                .map(|(name, accounts)| (format!("profiles.{name}"), accounts)),
+        )
+    }
+
+    fn account_lists_mut(&mut self) -> impl Iterator<Item = (String, &mut Vec<Account>)> {
+        std::iter::once(("accounts".to_string(), &mut self.accounts)).chain(
+            self.profiles
+                .iter_mut()
+                .map(|(name, accounts)| (format!("profiles.{name}"), accounts)),
+        )
+    }
+
+    fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// The default profile, then the named ones alphabetically.
+    fn profile_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profiles.keys().cloned())
+            .collect()
+    }
+
+    fn profile_accounts(&self, name: &str) -> &[Account] {
+        if name == DEFAULT_PROFILE {
+            &self.accounts
+        } else {
+            self.profiles.get(name).map_or(&[], Vec::as_slice)
+        }
+    }
+
+    /// The accounts of profile `name`, which is created empty if it's new.
+    fn profile_accounts_mut(&mut self, name: &str) -> &mut Vec<Account> {
+        if name == DEFAULT_PROFILE {
+            &mut self.accounts
+        } else {
+            self.profiles.entry(name.to_string()).or_default()
+        }
+    }
+
+    /// Makes `name` the profile in use; it has to be in the config at `path`.
+    fn use_profile(&mut self, name: &str, path: &Path) -> Result<()> {
+        if !self.profile_names().iter().any(|known| known == name) {
+            return Err(anyhow!(
+                "No profile named {name:?} in {}; it has {}",
+                path.display(),
+                self.profile_names().join(", ")
+            ));
+        }
+        self.profile = (name != DEFAULT_PROFILE).then(|| name.to_string());
+        Ok(())
+    }
+
+    /// Starts the TUI on `account` of the profile in use, and on `zone` once its zones
+    /// have loaded; the account has to be in the config at `path`.
+    fn start_at(&mut self, account: &str, zone: Option<String>, path: &Path) -> Result<()> {
+        let accounts = self.profile_accounts(self.profile_name());
+        if !accounts.iter().any(|known| known.name == account) {
+            let names: Vec<&str> = accounts.iter().map(|known| known.name.as_str()).collect();
+            return Err(anyhow!(
+                "No account named {account:?} in profile {} of {}; it has {}",
+                self.profile_name(),
+                path.display(),
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ));
+        }
+        self.start = Some((account.to_string(), zone));
+        Ok(())
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            request_secs: self
+                .request_timeout_secs
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS),
+            connect_secs: self
+                .connect_timeout_secs
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        }
+    }
+
+    fn zone_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.zone_cache_secs.unwrap_or(DEFAULT_ZONE_CACHE_SECS))
+    }
+
+    fn scroll_step(&self) -> usize {
+        self.scroll_step.unwrap_or(DEFAULT_SCROLL_STEP).max(1)
+    }
+
+    /// The Type column color for `record_type`: the configured one if it parses, else the
+    /// default palette's.
+    fn type_color(&self, record_type: &str) -> Option<Color> {
+        match self.type_colors.get(record_type) {
+            Some(name) if name.eq_ignore_ascii_case("none") => None,
+            Some(name) => name
+                .parse()
+                .ok()
+                .or_else(|| default_type_color(record_type)),
+            None => default_type_color(record_type),
+        }
+    }
+
+    fn osc52_max_bytes(&self) -> usize {
+        self.osc52_max_bytes.unwrap_or(DEFAULT_OSC52_MAX_BYTES)
+    }
+
+    /// Whether `account`'s label is one of the danger labels, ignoring case.
+    fn is_danger(&self, account: &Account) -> bool {
+        let Some(label) = account.label.as_deref().map(str::trim) else {
+            return false;
+        };
+        if self.danger_labels.is_empty() {
+            return label.eq_ignore_ascii_case(DEFAULT_DANGER_LABEL);
+        }
+        self.danger_labels
+            .iter()
+            .any(|danger| label.eq_ignore_ascii_case(danger.trim()))
+    }
+}
+
+/// The label that marks a dangerous account when `danger_labels` isn't set.
+const DEFAULT_DANGER_LABEL: &str = "PROD";
+
+/// An account's `color`: a colour name like `red`, or a hex like `#ff8800`.
+fn parse_account_color(name: &str) -> Option<Color> {
+    name.trim().parse().ok()
+}
+
+/// The OSC 52 sequence that puts `text` on the terminal's clipboard, keeping at most
+/// `max_bytes` of it (cut at a character boundary). Inside tmux the sequence is wrapped
+/// for passthrough. Returns the sequence and how many bytes of `text` it carries.
+fn osc52_sequence(text: &str, max_bytes: usize, tmux: bool) -> (String, usize) {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let payload = base64::engine::general_purpose::STANDARD.encode(&text[..end]);
+    let sequence = if tmux {
+        format!("\x1bPtmux;\x1b\x1b]52;c;{payload}\x07\x1b\\")
+    } else {
+        format!("\x1b]52;c;{payload}\x07")
+    };
+    (sequence, end)
+}
+
+/// Pipes `text` into the first clipboard command that works, returning its name.
+fn copy_with_command(text: &str) -> Result<&'static str> {
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])]
+    } else {
+        &[
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    for (program, args) in candidates {
+        let child = std::process::Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(program);
+        }
+    }
+    Err(anyhow!("no clipboard command found"))
+}
+
+/// The error for a config at `path` that didn't deserialize: `message` and where it was
+/// found, plus the account and field at fault when `tree` (the file as parsed without
+/// the config's types) shows which. Without a tree the file's syntax is broken.
+fn config_error(
+    path: &Path,
+    format: &str,
+    tree: Option<serde_json::Value>,
+    message: &str,
+    at: &str,
+) -> anyhow::Error {
+    let at = if at.is_empty() {
+        String::new()
+    } else {
+        format!(" ({at})")
+    };
+    let Some(tree) = tree else {
+        return anyhow!("{} isn't valid {format}: {message}{at}", path.display());
+    };
+    match config_error_place(&tree) {
+        Some(place) => anyhow!("{}: {place}: {message}{at}", path.display()),
+        None => anyhow!("{}: {message}{at}", path.display()),
+    }
+}
+
+/// Which account and field of the config `tree` won't deserialize, tried one at a time
+/// beside the fields that are required, or else which top-level setting.
+fn config_error_place(tree: &serde_json::Value) -> Option<String> {
+    use serde_json::{Map, Value};
+    let alone = |required: (&str, Value), key: &String, value: &Value| {
+        let mut object = Map::new();
+        object.insert(required.0.to_string(), required.1);
+        object.insert(key.clone(), value.clone());
+        Value::Object(object)
+    };
+    let settings = tree.as_object()?;
+    let profiles = settings.get("profiles").and_then(Value::as_object);
+    let lists = std::iter::once(("accounts".to_string(), settings.get("accounts"))).chain(
+        profiles
+            .into_iter()
+            .flatten()
+            .map(|(name, accounts)| (format!("profiles.{name}"), Some(accounts))),
+    );
+    let accounts = lists.flat_map(|(list, accounts)| {
+        let accounts = accounts.and_then(Value::as_array).into_iter().flatten();
+        accounts
+            .enumerate()
+            .map(move |(idx, account)| (format!("{list}[{idx}]"), account))
+    });
+    for (place, account) in accounts {
+        if Account::deserialize(account).is_ok() {
+            continue;
+        }
+        let place = match account.get("name").and_then(Value::as_str) {
+            Some(name) => format!("{place} ({name})"),
+            None => place,
+        };
+        let field = account.as_object().and_then(|fields| {
+            fields.iter().find(|(key, value)| {
+                Account::deserialize(&alone(("name", Value::from("")), key, value)).is_err()
+            })
+        });
+        return Some(match field {
+            Some((key, _)) => format!("{place}, field `{key}`"),
+            None => place,
+        });
+    }
+    settings
+        .iter()
+        .find(|(key, value)| {
+            Config::deserialize(&alone(("accounts", Value::Array(Vec::new())), key, value)).is_err()
+        })
+        .map(|(key, _)| format!("field `{key}`"))
+}
+
+/// Fields of the config `text` read from `path` that nyxflare doesn't know, which are
+/// ignored, as in ``accounts[0] (prod): unknown field `tokn` `` or
+/// ``profiles.work[1] (ops): unknown field `tokn` ``.
+fn unknown_config_fields(path: &Path, text: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let unknown = |field: serde_ignored::Path| fields.push(field.to_string());
+    let config: Config = match ConfigFormat::of(path) {
+        ConfigFormat::Json => {
+            let mut deserializer = serde_json::Deserializer::from_str(text);
+            serde_ignored::deserialize(&mut deserializer, unknown)?
+        }
+        ConfigFormat::Toml => {
+            serde_ignored::deserialize(toml::Deserializer::parse(text)?, unknown)?
+        }
+    };
+    Ok(fields
+        .into_iter()
+        .map(|field| {
+            let in_account = config.account_lists().find_map(|(list, accounts)| {
+                let rest = field.strip_prefix(&format!("{list}."))?;
+                let (idx, rest) = rest.split_once('.')?;
+                let account = accounts.get(idx.parse::<usize>().ok()?)?;
+                Some(format!(
+                    "{list}[{idx}] ({}): unknown field `{rest}`",
+                    account.name
+                ))
+            });
+            in_account.unwrap_or_else(|| format!("unknown field `{field}`"))
+        })
+        .collect())
+}
+
+/// Problems strict mode refuses to start with in the config `text` read from `path`:
+/// unknown fields, malformed account IDs, and config files readable by other users.
+fn config_issues(path: &Path, text: &str) -> Result<Vec<String>> {
+    let mut issues = unknown_config_fields(path, text)?;
+    let config = Config::parse(path, text)?;
+
+    for (list, accounts) in config.account_lists() {
+        for (idx, account) in accounts.iter().enumerate() {
+            if let Some(id) = &account.account_id
+                && !is_valid_account_id(id)
+            {
+                issues.push(format!(
+                    "{list}[{idx}] ({}): account_id `{id}` is not a 32-character hex ID",
+                    account.name
+                ));
+            }
+        }
+    }
+
+    if let Some(mode) = loose_config_mode(path) {
+        issues.push(format!(
+            "file mode {mode:o} lets other users read your tokens (expected 600)"
+        ));
+    }
+
+    Ok(issues)
+}
+
+/// Replaces `path` with `text` all at once: the text goes to a temporary file in the same
+/// directory, is synced to disk, then renamed over `path`, so a crash leaves either the
+/// old file or the new one. A new file is readable by its owner only (mode 600), as are
+/// the directories made for it (700); an existing file keeps its mode.
+fn write_private(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent)?;
+    }
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} names no file", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&temp);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&temp).and_then(|mut file| {
+        file.write_all(text.as_bytes())?;
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.with_context(|| format!("Writing {}", path.display()))?;
+    // The rename itself is only durable once the directory is synced.
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
+/// The permission bits of the file at `path` when other users may read or write it.
+/// Always `None` off Unix, where there are no mode bits to check.
+fn loose_config_mode(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+        (mode & 0o077 != 0).then_some(mode)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Makes the file at `path` readable and writable by its owner only; nothing to do off Unix.
+fn restrict_config_mode(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Changing the mode of {}", path.display()))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+fn is_valid_account_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The decisions strict mode changes, consulted wherever the app is about to do something risky.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Policy {
+    strict: bool,
+}
+
+impl Policy {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            strict: config.strict,
+        }
+    }
+
+    fn check_startup(&self, path: &Path, issues: &[String]) -> Result<()> {
+        if !self.strict || issues.is_empty() {
+            return Ok(());
+        }
+        let report: Vec<String> = issues.iter().map(|i| format!("  - {i}")).collect();
+        Err(anyhow!(
+            "Strict mode: refusing to start, {} has {} problem(s):\n{}",
+            path.display(),
+            issues.len(),
+            report.join("\n")
+        ))
+    }
+
+    /// Full-record updates drop fields nyxflare does not model. Returns a warning to show after
+    /// the update, or an error when strict mode forbids it.
+    fn check_update(&self, existing: &DnsRecord) -> Result<Option<String>> {
+        if existing.unmodeled.is_empty() {
+            return Ok(None);
+        }
+        let fields = existing.unmodeled.join(", ");
+        if self.strict {
+            Err(anyhow!(
+                "Strict mode: {} has {fields} that an update would drop; edit it elsewhere",
+                existing.name
+            ))
+        } else {
+            Ok(Some(format!(
+                "{fields} on {} were not preserved",
+                existing.name
+            )))
+        }
+    }
+
+    /// Imports go through an endpoint that can't keep per-record proxy state. Returns a
+    /// warning for the import overlay when `unproxied` records would lose it, or an error
+    /// when strict mode forbids it.
+    fn check_import(&self, zone_name: &str, unproxied: usize) -> Result<Option<String>> {
+        if unproxied == 0 {
+            return Ok(None);
+        }
+        if self.strict {
+            Err(anyhow!(
+                "Strict mode: importing into {zone_name} would leave {unproxied} proxied record(s) unproxied"
+            ))
+        } else {
+            Ok(Some(format!(
+                "{unproxied} proxied record(s) will be imported unproxied"
+            )))
+        }
+    }
+
+    /// Runs `set`'s updates past `check_update`: strict mode takes out the ones that
+    /// would drop unmodeled fields, counting them as left out; otherwise they stay, each
+    /// with its warning.
+    fn screen(&self, set: &mut ChangeSet) {
+        let mut refused = 0;
+        set.steps.retain(|step| {
+            let Step::Update { before, .. } = step else {
+                return true;
+            };
+            match self.check_update(before) {
+                Ok(warning) => {
+                    set.warnings.extend(warning);
+                    true
+                }
+                Err(_) => {
+                    refused += 1;
+                    false
+                }
+            }
+        });
+        if refused > 0 {
+            set.left_out.push((
+                refused,
+                "refused in strict mode (an update would drop fields nyxflare doesn't model)"
+                    .to_string(),
+            ));
+        }
+    }
+
+    fn requires_typed_confirmation(&self) -> bool {
+        self.strict
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Focus {
+    Accounts,
+    Zones,
+    Records,
+}
+
+/// Two clicks on the same record this close together open it for editing.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Where `draw` last put each panel, so mouse clicks can be mapped back to rows.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct PanelLayout {
+    accounts: Rect,
+    zones: Rect,
+    records: Rect,
+    /// First list entry shown, when the list is scrolled to keep its selection visible.
+    account_offset: usize,
+    zone_offset: usize,
+    /// Page row and height of the selected record while its content is wrapped.
+    wrapped_row: Option<(usize, usize)>,
+}
+
+impl PanelLayout {
+    /// The panel at a screen position and, when it is on a row, which one counting from
+    /// the first row shown (the records table's header is not a row).
+    fn hit(&self, column: u16, row: u16) -> Option<(Focus, Option<usize>)> {
+        [
+            (Focus::Accounts, self.accounts, 1),
+            (Focus::Zones, self.zones, 1),
+            (Focus::Records, self.records, 2),
+        ]
+        .into_iter()
+        .find(|(_, area, _)| {
+            (area.left()..area.right()).contains(&column)
+                && (area.top()..area.bottom()).contains(&row)
+        })
+        .map(|(panel, area, header)| {
+            let inside = column > area.left() && column + 1 < area.right();
+            let first_row = area.top() + header;
+            let on_row = inside && row >= first_row && row + 1 < area.bottom();
+            (panel, on_row.then(|| (row - first_row) as usize))
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    AddingAccount(AccountForm),
+    RecordForm(RecordForm),
+    ConfirmDelete(ConfirmDelete),
+    ConfirmCreate(ConfirmCreate),
+    Searching(SearchPrompt),
+    RecordDetail(RecordDetail),
+    ImportZone(ImportForm),
+    ApplyState(ApplyForm),
+    ConfirmBulkDelete(ConfirmBulkDelete),
+    CreateZone(ZoneForm),
+    ConfirmDeleteZone(ConfirmDeleteZone),
+    ZoneDetail(ZoneDetail),
+    PickAccount(AccountPicker),
+    RecordConflict(RecordConflict),
+    Help(HelpView),
+    BulkReport(BulkReport),
+    ConfirmBulkUpdate(ConfirmBulkUpdate),
+    BulkTtl(TtlPrompt),
+    FilteringZones(ZoneFilterPrompt),
+    MessageHistory(MessageHistory),
+    QuickEdit(QuickEdit),
+    Error(ErrorDetails),
+    ConfirmDeleteAccount(ConfirmDeleteAccount),
+    PickProfile(ProfilePicker),
+    ConfigConflict(ConfigConflict),
+}
+
+impl Mode {
+    /// The text field a paste goes into, if this mode has one focused.
+    fn paste_target(&mut self) -> Option<&mut String> {
+        match self {
+            Mode::AddingAccount(form) => form.active_value_mut(),
+            Mode::RecordForm(form) => match form.field_index {
+                0 => Some(&mut form.draft.name),
+                1 => Some(&mut form.draft.record_type),
+                2 => Some(&mut form.draft.content),
+                3 => Some(&mut form.draft.ttl),
+                _ => None,
+            },
+            Mode::ConfirmDelete(confirm) if confirm.require_typed => Some(&mut confirm.typed),
+            Mode::ConfirmBulkDelete(confirm) => Some(&mut confirm.typed),
+            Mode::ConfirmDeleteZone(confirm) => Some(&mut confirm.typed),
+            Mode::CreateZone(form) if form.field_index == 0 => Some(&mut form.name),
+            Mode::ImportZone(form) if form.field_index == 0 => Some(&mut form.path),
+            Mode::ApplyState(form) if form.field_index == 0 => Some(&mut form.path),
+            Mode::Searching(prompt) => Some(&mut prompt.text),
+            Mode::FilteringZones(prompt) => Some(&mut prompt.text),
+            Mode::BulkTtl(prompt) => Some(&mut prompt.text),
+            Mode::QuickEdit(edit) => Some(&mut edit.text),
+            Mode::PickProfile(picker) => picker.new_name.as_mut(),
+            _ => None,
+        }
+    }
+}
+
+/// The zone list's `/` prompt; the list narrows as the text changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ZoneFilterPrompt {
+    text: String,
+    /// Index into `zones` of the match Enter would select.
+    highlight: Option<usize>,
+    /// The filter to put back on Esc.
+    previous: String,
+}
+
+/// The `o` overlay: the config's profiles, and the name of one to create after `n`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProfilePicker {
+    /// Names with their account counts, the default profile first.
+    profiles: Vec<(String, usize)>,
+    selected: usize,
+    new_name: Option<String>,
+}
+
+/// Shown when the config file changed outside nyxflare while account changes made here
+/// weren't saved; each side is summed up as `added x`, `changed y`, `removed z`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfigConflict {
+    yours: Vec<String>,
+    theirs: Vec<String>,
+}
+
+/// The `?` overlay, opened from whichever panel had focus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HelpView {
+    focus: Focus,
+    scroll: usize,
+}
+
+/// Shown when a record about to be updated or deleted changed on the server since it
+/// was loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecordConflict {
+    /// The record as it is on the server now.
+    remote: DnsRecord,
+    pending: PendingChange,
+}
+
+/// What was about to be done to a conflicting record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PendingChange {
+    Update(DnsRecord),
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Resolution {
+    Overwrite,
+    Reload,
+    Cancel,
+}
+
+/// Shown after adding credentials that can reach several Cloudflare accounts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AccountPicker {
+    account: Account,
+    token: TokenStatus,
+    choices: Vec<CloudflareAccount>,
+    selected: usize,
+}
+
+/// Cloudflare zone setup: full (Cloudflare is authoritative) or partial (CNAME setup).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ZoneSetup {
+    #[default]
+    Full,
+    Partial,
+}
+
+impl ZoneSetup {
+    fn label(self) -> &'static str {
+        match self {
+            ZoneSetup::Full => "full",
+            ZoneSetup::Partial => "partial (CNAME)",
+        }
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            ZoneSetup::Full => ZoneSetup::Partial,
+            ZoneSetup::Partial => ZoneSetup::Full,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ZoneForm {
+    name: String,
+    setup: ZoneSetup,
+    field_index: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ImportForm {
+    path: String,
+    proxied: bool,
+    field_index: usize,
+    /// Local parse of `path`; set once the user has seen what will be imported.
+    preview: Option<ImportPreview>,
+}
+
+impl ImportForm {
+    /// How many previewed records would come out unproxied against what was asked for:
+    /// the endpoint only takes the one `proxied` flag, so per-record `cf-proxied` tags
+    /// are lost when it is off, and a provider without a proxy honours neither.
+    fn unproxied(&self, has_proxy: bool) -> usize {
+        let Some(preview) = &self.preview else {
+            return 0;
+        };
+        match (has_proxy, self.proxied) {
+            (true, true) => 0,
+            (false, true) => preview.proxiable,
+            (_, false) => preview.proxied,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ImportPreview {
+    records: usize,
+    /// Record counts per type, in first-seen order.
+    by_type: Vec<(String, usize)>,
+    skipped: usize,
+    /// Of `records`, how many can be proxied, and how many the file marks proxied.
+    proxiable: usize,
+    proxied: usize,
+}
+
+impl ImportPreview {
+    /// Counts what Cloudflare would add: everything except the SOA and apex NS set,
+    /// which Cloudflare manages itself.
+    fn from_records(records: &[DnsRecord], zone_name: &str) -> Self {
+        let mut preview = ImportPreview {
+            records: 0,
+            by_type: Vec::new(),
+            skipped: 0,
+            proxiable: 0,
+            proxied: 0,
+        };
+        for record in records {
+            if is_managed_by_cloudflare(record, zone_name) {
+                preview.skipped += 1;
+                continue;
+            }
+            preview.records += 1;
+            if is_proxiable(&record.record_type) {
+                preview.proxiable += 1;
+                preview.proxied += usize::from(record.proxied);
+            }
+            match preview
+                .by_type
+                .iter_mut()
+                .find(|(t, _)| *t == record.record_type)
+            {
+                Some((_, count)) => *count += 1,
+                None => preview.by_type.push((record.record_type.clone(), 1)),
+            }
+        }
+        preview
+    }
+
+    fn type_summary(&self) -> String {
+        self.by_type
+            .iter()
+            .map(|(record_type, count)| format!("{count} {record_type}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The `A` overlay: a desired-state file for the current zone, and the plan for it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ApplyForm {
+    path: String,
+    /// Also delete the names and types the file doesn't have.
+    prune: bool,
+    field_index: usize,
+    /// Worked out against the whole zone once the user asks to see it.
+    preview: Option<ChangeSet>,
+    /// The first step of the plan shown.
+    scroll: usize,
+    /// The zone name to type before the previewed plan is sent: strict mode asks for it
+    /// when the plan deletes records.
+    confirm_zone: Option<String>,
+    typed: String,
+}
+
+impl ApplyForm {
+    fn is_confirmed(&self) -> bool {
+        self.confirm_zone
+            .as_ref()
+            .is_none_or(|zone| &self.typed == zone)
+    }
+}
+
+fn is_managed_by_cloudflare(record: &DnsRecord, zone_name: &str) -> bool {
+    record.record_type == "SOA"
+        || (record.record_type == "NS" && record.name.eq_ignore_ascii_case(zone_name))
+}
+
+/// What Cloudflare reported after an import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+struct ImportResult {
+    #[serde(rename = "recs_added")]
+    added: u32,
+    #[serde(rename = "total_records_parsed")]
+    parsed: u32,
+}
+
+impl ImportResult {
+    fn failed(&self) -> u32 {
+        self.parsed.saturating_sub(self.added)
+    }
+}
+
+/// Expands a leading `~/` so typed paths behave like they would in a shell.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Record types `t` cycles through before "other"; each maps to the API's `type` filter.
+const QUICK_FILTER_TYPES: [&str; 5] = ["A", "AAAA", "CNAME", "TXT", "MX"];
+
+/// Quick record-type filter for the records panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum TypeFilter {
+    #[default]
+    All,
+    Only(&'static str),
+    /// Everything not in `QUICK_FILTER_TYPES`; the API can't express this, so it is local-only.
+    Other,
+}
+
+impl TypeFilter {
+    fn next(self) -> Self {
+        match self {
+            TypeFilter::All => TypeFilter::Only(QUICK_FILTER_TYPES[0]),
+            TypeFilter::Only(current) => QUICK_FILTER_TYPES
+                .iter()
+                .position(|t| *t == current)
+                .and_then(|idx| QUICK_FILTER_TYPES.get(idx + 1))
+                .map_or(TypeFilter::Other, |t| TypeFilter::Only(t)),
+            TypeFilter::Other => TypeFilter::All,
+        }
+    }
+
+    fn matches(self, record_type: &str) -> bool {
+        match self {
+            TypeFilter::All => true,
+            TypeFilter::Only(wanted) => record_type.eq_ignore_ascii_case(wanted),
+            TypeFilter::Other => !QUICK_FILTER_TYPES
+                .iter()
+                .any(|t| record_type.eq_ignore_ascii_case(t)),
+        }
+    }
+
+    /// The `type` query parameter to send when listing records, if any.
+    fn api_type(self) -> Option<&'static str> {
+        match self {
+            TypeFilter::Only(record_type) => Some(record_type),
+            TypeFilter::All | TypeFilter::Other => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TypeFilter::All => "all types",
+            TypeFilter::Only(record_type) => record_type,
+            TypeFilter::Other => "other types",
+        }
+    }
+}
+
+/// Column the records table is ordered by; `Api` keeps the order records were listed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SortColumn {
+    #[default]
+    Api,
+    Name,
+    Type,
+    Content,
+    Ttl,
+    Proxied,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            SortColumn::Api => SortColumn::Name,
+            SortColumn::Name => SortColumn::Type,
+            SortColumn::Type => SortColumn::Content,
+            SortColumn::Content => SortColumn::Ttl,
+            SortColumn::Ttl => SortColumn::Proxied,
+            SortColumn::Proxied => SortColumn::Api,
+        }
+    }
+
+    /// The records table header this column sorts by.
+    fn header(self) -> Option<&'static str> {
+        match self {
+            SortColumn::Api => None,
+            SortColumn::Name => Some("Name"),
+            SortColumn::Type => Some("Type"),
+            SortColumn::Content => Some("Content"),
+            SortColumn::Ttl => Some("TTL"),
+            SortColumn::Proxied => Some("Mode"),
+        }
+    }
+}
+
+/// How a zone's records are ordered, chosen with `s`/`S` and kept per zone for the session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct RecordSort {
+    column: SortColumn,
+    descending: bool,
+}
+
+impl RecordSort {
+    fn compare(self, a: &DnsRecord, b: &DnsRecord) -> std::cmp::Ordering {
+        let ordering = match self.column {
+            SortColumn::Api => std::cmp::Ordering::Equal,
+            SortColumn::Name => natural_cmp(&a.name, &b.name),
+            SortColumn::Type => a.record_type.cmp(&b.record_type),
+            SortColumn::Content => natural_cmp(&a.content, &b.content),
+            SortColumn::Ttl => a.ttl.cmp(&b.ttl),
+            SortColumn::Proxied => a.proxied.cmp(&b.proxied),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// The header text for `column`, with an arrow when the table is sorted by it.
+    fn header(self, column: SortColumn) -> String {
+        let label = column.header().unwrap_or_default();
+        match (self.column == column, self.descending) {
+            (false, _) => label.to_string(),
+            (true, false) => format!("{label} ▲"),
+            (true, true) => format!("{label} ▼"),
+        }
+    }
+}
+
+/// Case-insensitive ordering that compares runs of digits by value, so `a2` sorts before
+/// `a10`.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Where a confirmed `/` search looks for matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SearchScope {
+    /// Filter only the records already loaded.
+    #[default]
+    Local,
+    /// Ask the API for matching records and merge them into the loaded set.
+    Server,
+}
+
+impl SearchScope {
+    fn label(self) -> &'static str {
+        match self {
+            SearchScope::Local => "local filter",
+            SearchScope::Server => "server search",
+        }
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            SearchScope::Local => SearchScope::Server,
+            SearchScope::Server => SearchScope::Local,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct SearchPrompt {
+    text: String,
+    scope: SearchScope,
+    /// The record highlighted when the prompt opened, highlighted again whenever the
+    /// draft filter still shows it and on Esc.
+    selected: Option<String>,
+    /// F4: Enter locates matches for `n`/`N` instead of filtering, so the list stays whole.
+    locate: bool,
+}
+
+/// Starts a record filter that is a regular expression rather than plain text.
+const REGEX_FILTER_PREFIX: &str = "re:";
+/// Starts a record filter matched fzf-style, best matches first.
+const FUZZY_FILTER_PREFIX: &str = "fz:";
+
+/// `text` switched to the filter mode `prefix` starts, or back to plain text when it's
+/// already in that mode.
+fn toggle_filter_prefix(text: &str, prefix: &str) -> String {
+    let trimmed = text.trim_start();
+    let (current, rest) = [REGEX_FILTER_PREFIX, FUZZY_FILTER_PREFIX]
+        .into_iter()
+        .find_map(|mode| Some((Some(mode), trimmed.strip_prefix(mode)?)))
+        .unwrap_or((None, text));
+    if current == Some(prefix) {
+        rest.to_string()
+    } else {
+        format!("{prefix}{rest}")
+    }
+}
+
+/// The record filter's text, ready to match: filter terms (see [`parse_filter_terms`]),
+/// a `re:` regex, or an `fz:` fuzzy needle, tried against name, content and type. All
+/// ignore case.
+enum RecordQuery {
+    Terms(Vec<FilterTerm>),
+    Regex(Regex),
+    /// Lowercase.
+    Fuzzy(String),
+}
+
+impl RecordQuery {
+    /// `None` for a blank filter; the error says what's wrong with a bad `re:` pattern
+    /// or filter term.
+    fn parse(text: &str) -> Result<Option<Self>, String> {
+        if let Some(pattern) = text.trim_start().strip_prefix(REGEX_FILTER_PREFIX) {
+            if pattern.is_empty() {
+                return Ok(None);
+            }
+            return RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(|regex| Some(RecordQuery::Regex(regex)))
+                // Syntax errors span several lines pointing at the pattern; the last says what's wrong.
+                .map_err(|err| {
+                    let message = err.to_string();
+                    let last = message.lines().last().unwrap_or_default();
+                    format!("Invalid regex: {}", last.trim_start_matches("error: "))
+                });
+        }
+        if let Some(needle) = text.trim_start().strip_prefix(FUZZY_FILTER_PREFIX) {
+            let needle: String = needle.to_lowercase().split_whitespace().collect();
+            return Ok((!needle.is_empty()).then_some(RecordQuery::Fuzzy(needle)));
+        }
+        let terms = parse_filter_terms(text)?;
+        Ok((!terms.is_empty()).then_some(RecordQuery::Terms(terms)))
+    }
+
+    /// "regex" or "fuzzy" for filter text in one of those modes.
+    fn mode(text: &str) -> Option<&'static str> {
+        let text = text.trim_start();
+        if text.starts_with(REGEX_FILTER_PREFIX) {
+            Some("regex")
+        } else if text.starts_with(FUZZY_FILTER_PREFIX) {
+            Some("fuzzy")
+        } else {
+            None
+        }
+    }
+
+    /// `None` when `record` doesn't match; otherwise how well it does, which only
+    /// varies in fuzzy mode.
+    fn score(&self, record: &DnsRecord) -> Option<i64> {
+        let fields = [&record.name, &record.content, &record.record_type];
+        match self {
+            RecordQuery::Terms(terms) => terms.iter().all(|term| term.matches(record)).then_some(0),
+            RecordQuery::Regex(regex) => fields.iter().any(|f| regex.is_match(f)).then_some(0),
+            RecordQuery::Fuzzy(needle) => {
+                fields.iter().filter_map(|f| fuzzy_score(f, needle)).max()
+            }
+        }
+    }
+
+    /// Byte ranges of `text`, shown in the `column` cell, that this filter matched, in
+    /// order and merged. Fuzzy matches light up the whole cell.
+    fn highlights(&self, column: RecordColumn, text: &str) -> Vec<Range<usize>> {
+        let ranges = match self {
+            RecordQuery::Terms(terms) => terms
+                .iter()
+                .filter(|term| !term.negated)
+                .flat_map(|term| term.highlights(column, text))
+                .collect(),
+            RecordQuery::Regex(regex) => regex
+                .find_iter(text)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+            RecordQuery::Fuzzy(needle) if fuzzy_score(text, needle).is_some() => {
+                std::iter::once(0..text.len()).collect()
+            }
+            RecordQuery::Fuzzy(_) => Vec::new(),
+        };
+        merge_ranges(ranges)
+    }
+}
+
+/// The record table cells a filter can match in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RecordColumn {
+    Type,
+    Name,
+    Content,
+}
+
+/// Byte ranges of every non-overlapping occurrence of the already-lowercased `needle`
+/// in `haystack`, ignoring case.
+fn find_lowercase(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    while !needle.is_empty() && start < haystack.len() {
+        let mut rest = haystack[start..].char_indices();
+        let hit = needle.chars().all(|wanted| {
+            rest.next()
+                .is_some_and(|(_, c)| c.to_lowercase().eq(std::iter::once(wanted)))
+        });
+        if hit {
+            let end = rest.next().map_or(haystack.len(), |(i, _)| start + i);
+            found.push(start..end);
+            start = end;
+        } else {
+            start += haystack[start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    found
+}
+
+/// Sorts `ranges` and joins the ones that overlap or touch.
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// `text` as one line of spans with the byte `ranges` picked out in `style`.
+fn highlighted_line(text: &str, ranges: &[Range<usize>], style: Style) -> Line<'static> {
+    let mut spans = Vec::with_capacity(ranges.len() * 2 + 1);
+    let mut at = 0;
+    for range in ranges {
+        if range.start > at {
+            spans.push(Span::raw(text[at..range.start].to_string()));
+        }
+        spans.push(Span::styled(text[range.clone()].to_string(), style));
+        at = range.end;
+    }
+    if at < text.len() {
+        spans.push(Span::raw(text[at..].to_string()));
+    }
+    Line::from(spans)
+}
+
+/// fzf-style score of `needle` (lowercase) in `haystack`: `None` unless every character
+/// of the needle appears in order. Runs of adjacent characters and characters starting
+/// a label or word (`api` in `dev.api-1`) score extra; gaps and a late start cost a little.
+fn fuzzy_score(haystack: &str, needle: &str) -> Option<i64> {
+    let mut wanted = needle.chars().peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for (i, c) in haystack.chars().enumerate() {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            score += 1;
+            score += match last_match {
+                Some(last) if last + 1 == i => 5,
+                Some(last) => -((i - last - 1).min(5) as i64),
+                None => -(i.min(3) as i64),
+            };
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            last_match = Some(i);
+            wanted.next();
+        }
+        previous = Some(c);
+    }
+    wanted.peek().is_none().then_some(score)
+}
+
+/// One space-separated term of a plain record filter; a record must match them all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FilterTerm {
+    /// A leading `-`: the record must not match.
+    negated: bool,
+    test: FilterTest,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FilterTest {
+    /// A bare term: a substring of the name, content or type. Lowercase.
+    Any(String),
+    /// `name:` substring. Lowercase.
+    Name(String),
+    /// `content:` substring. Lowercase.
+    Content(String),
+    /// `type:` exactly, so `type:A` leaves out AAAA. Uppercase.
+    Type(String),
+    /// `ttl:` seconds, or `auto` (1).
+    Ttl(u32),
+    /// `proxied:yes` / `proxied:no`.
+    Proxied(bool),
+}
+
+impl FilterTerm {
+    fn matches(&self, record: &DnsRecord) -> bool {
+        let hit = match &self.test {
+            FilterTest::Any(needle) => [&record.name, &record.content, &record.record_type]
+                .iter()
+                .any(|f| contains_lowercase(f, needle)),
+            FilterTest::Name(needle) => contains_lowercase(&record.name, needle),
+            FilterTest::Content(needle) => contains_lowercase(&record.content, needle),
+            FilterTest::Type(record_type) => record.record_type.eq_ignore_ascii_case(record_type),
+            FilterTest::Ttl(ttl) => record.ttl == *ttl,
+            FilterTest::Proxied(proxied) => record.proxied == *proxied,
+        };
+        hit != self.negated
+    }
+
+    /// Where this term matched `text` in the `column` cell; a `type:` match is the whole
+    /// cell. Negated and ttl/proxied terms have nothing to point at.
+    fn highlights(&self, column: RecordColumn, text: &str) -> Vec<Range<usize>> {
+        match (&self.test, column) {
+            (FilterTest::Any(needle), _)
+            | (FilterTest::Name(needle), RecordColumn::Name)
+            | (FilterTest::Content(needle), RecordColumn::Content) => find_lowercase(text, needle),
+            (FilterTest::Type(record_type), RecordColumn::Type)
+                if text.eq_ignore_ascii_case(record_type) =>
+            {
+                std::iter::once(0..text.len()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Reminder of the filter syntax, shown under the search prompt.
+const FILTER_SYNTAX_HINT: &str = "name:/type:/content:/ttl:/proxied: • -term • \"with spaces\"";
+
+/// Splits a record filter into terms: `value` matches any of name, content and type,
+/// `field:value` only that field (name, type, content, ttl or proxied), and a leading
+/// `-` negates. Double quotes keep spaces inside a value (`content:"v=spf1 -all"`).
+/// A prefix that isn't a field name stays part of a bare term, so `include:_spf` still
+/// searches as typed.
+fn parse_filter_terms(text: &str) -> Result<Vec<FilterTerm>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    // Whether the current word has anything in it yet, even an empty pair of quotes.
+    let mut started = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                }
+                started = false;
+            }
+            c => {
+                word.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        words.push(word);
+    }
+
+    let mut terms = Vec::new();
+    for word in words {
+        let (negated, word) = match word.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, word.as_str()),
+        };
+        let (field, value) = word.split_once(':').unwrap_or(("", word));
+        let lower = value.to_lowercase();
+        let test = match field.to_ascii_lowercase().as_str() {
+            "name" => FilterTest::Name(lower),
+            "content" => FilterTest::Content(lower),
+            "type" => FilterTest::Type(value.to_ascii_uppercase()),
+            "ttl" if lower == "auto" => FilterTest::Ttl(1),
+            "ttl" => FilterTest::Ttl(
+                value
+                    .parse()
+                    .map_err(|_| format!("ttl: takes seconds or auto, not '{value}'"))?,
+            ),
+            "proxied" => FilterTest::Proxied(match lower.as_str() {
+                "yes" | "true" | "on" => true,
+                "no" | "false" | "off" => false,
+                _ => return Err(format!("proxied: takes yes or no, not '{value}'")),
+            }),
+            _ => FilterTest::Any(word.to_lowercase()),
+        };
+        // `name:` with nothing after it yet matches everything rather than nothing.
+        if let FilterTest::Name(v) | FilterTest::Content(v) | FilterTest::Type(v) = &test
+            && v.is_empty()
+        {
+            continue;
+        }
+        terms.push(FilterTerm { negated, test });
+    }
+    Ok(terms)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct AccountForm {
+    name: String,
+    auth_mode: AuthMode,
+    /// Cloudflare, or `Rfc2136` for a TSIG-keyed dynamic-update server.
+    provider: Provider,
+    api_token: String,
+    email: String,
+    account_id: String,
+    base_url: String,
+    /// RFC 2136 fields, shown in place of the Cloudflare ones.
+    tsig_key_name: String,
+    dns_server: String,
+    zones: String,
+    tsig_algorithm: String,
+    /// Seeds for new records: `auto` or seconds, and yes/no; empty keeps the usual ones.
+    default_ttl: String,
+    default_proxied: String,
+    /// Tag and its colour, shared by every provider's layout.
+    label: String,
+    color: String,
+    field_index: usize,
+    /// Index into `accounts` when the form edits a saved account instead of adding one.
+    editing: Option<usize>,
+    /// The saved secret, kept unless something is typed into the secret field.
+    saved_secret: Option<String>,
+    /// Whether the typed secret is shown in clear (F2); a fresh form always starts masked.
+    reveal_secret: bool,
+}
+
+impl AccountForm {
+    /// Index of the Token / Global Key toggle, the only field that isn't free text.
+    const AUTH_MODE_FIELD: usize = 1;
+    /// Enter advances until this field (email), then submits.
+    const SUBMIT_FROM_FIELD: usize = 3;
+    const LAST_FIELD: usize = 9;
+    /// RFC 2136 accounts need their zones (field 5) before Enter submits.
+    const RFC2136_SUBMIT_FROM_FIELD: usize = 5;
+    const RFC2136_LAST_FIELD: usize = 9;
+
+    /// The form for changing `account`; the secret field starts empty and masked.
+    fn for_account(index: usize, account: &Account) -> Self {
+        AccountForm {
+            name: account.name.clone(),
+            auth_mode: account.auth_mode.clone(),
+            provider: account.provider,
+            api_token: String::new(),
+            email: account.email.clone().unwrap_or_default(),
+            account_id: account.account_id.clone().unwrap_or_default(),
+            base_url: account.base_url.clone().unwrap_or_default(),
+            tsig_key_name: account.tsig_key_name.clone().unwrap_or_default(),
+            dns_server: account.dns_server.clone().unwrap_or_default(),
+            zones: account.zones.join(", "),
+            tsig_algorithm: account.tsig_algorithm.clone().unwrap_or_default(),
+            default_ttl: account.default_ttl.map(ttl_label).unwrap_or_default(),
+            default_proxied: match account.default_proxied {
+                Some(true) => "yes".to_string(),
+                Some(false) => "no".to_string(),
+                None => String::new(),
+            },
+            label: account.label.clone().unwrap_or_default(),
+            color: account.color.clone().unwrap_or_default(),
+            field_index: 0,
+            editing: Some(index),
+            saved_secret: (!account.lacks_secret()).then(|| account.api_token.clone()),
+            reveal_secret: false,
+        }
+    }
+
+    /// The secret field as drawn: bullets and a length unless revealed, never the saved secret.
+    fn secret_display(&self) -> String {
+        if self.api_token.is_empty() {
+            if self.saved_secret.is_some() {
+                return "•••••••• (saved; type to replace)".to_string();
+            }
+            return String::new();
+        }
+        if self.reveal_secret {
+            return self.api_token.clone();
+        }
+        let len = self.api_token.chars().count();
+        format!("{} ({len} chars, F2 to show)", "•".repeat(len.min(32)))
+    }
+
+    /// What was typed into the secret field, else the saved secret when editing.
+    fn secret(&self) -> &str {
+        match &self.saved_secret {
+            Some(saved) if self.api_token.trim().is_empty() => saved,
+            _ => self.api_token.trim(),
+        }
+    }
+
+    fn active_value_mut(&mut self) -> Option<&mut String> {
+        if self.is_rfc2136() {
+            return match self.field_index {
+                0 => Some(&mut self.name),
+                Self::AUTH_MODE_FIELD => None,
+                2 => Some(&mut self.api_token),
+                3 => Some(&mut self.tsig_key_name),
+                4 => Some(&mut self.dns_server),
+                5 => Some(&mut self.zones),
+                6 => Some(&mut self.tsig_algorithm),
+                7 => Some(&mut self.default_ttl),
+                8 => Some(&mut self.label),
+                _ => Some(&mut self.color),
+            };
+        }
+        match self.field_index {
+            0 => Some(&mut self.name),
+            Self::AUTH_MODE_FIELD => None,
+            2 => Some(&mut self.api_token),
+            3 => Some(&mut self.email),
+            4 => Some(&mut self.account_id),
+            5 => Some(&mut self.base_url),
+            6 => Some(&mut self.default_ttl),
+            7 => Some(&mut self.default_proxied),
+            8 => Some(&mut self.label),
+            _ => Some(&mut self.color),
+        }
+    }
+
+    fn is_rfc2136(&self) -> bool {
+        self.provider == Provider::Rfc2136
+    }
+
+    fn submit_from_field(&self) -> usize {
+        if self.is_rfc2136() {
+            Self::RFC2136_SUBMIT_FROM_FIELD
+        } else {
+            Self::SUBMIT_FROM_FIELD
+        }
+    }
+
+    fn last_field(&self) -> usize {
+        if self.is_rfc2136() {
+            Self::RFC2136_LAST_FIELD
+        } else {
+            Self::LAST_FIELD
+        }
+    }
+
+    fn on_auth_mode_field(&self) -> bool {
+        self.field_index == Self::AUTH_MODE_FIELD
+    }
+
+    /// Flips between API token and Global API key; from RFC 2136 it returns to a token.
+    /// A saved secret is for the old mode, so it has to be typed again.
+    fn toggle_auth_mode(&mut self) {
+        self.saved_secret = None;
+        if self.is_rfc2136() {
+            self.provider = Provider::Cloudflare;
+            self.auth_mode = AuthMode::Token;
+            return;
+        }
+        self.auth_mode = match self.auth_mode {
+            AuthMode::Token => AuthMode::GlobalKey,
+            AuthMode::GlobalKey => AuthMode::Token,
+        };
+    }
+
+    /// Switches between a Cloudflare account and an RFC 2136 (TSIG) one.
+    fn toggle_rfc2136(&mut self) {
+        self.saved_secret = None;
+        self.provider = if self.is_rfc2136() {
+            Provider::Cloudflare
+        } else {
+            Provider::Rfc2136
+        };
+        self.auth_mode = AuthMode::Token;
+    }
+
+    fn secret_label(&self) -> &'static str {
+        if self.is_rfc2136() {
+            return "TSIG Secret (base64)";
+        }
+        match self.auth_mode {
+            AuthMode::Token => "API Token",
+            AuthMode::GlobalKey => "Global API Key",
+        }
+    }
+
+    fn mode_label(&self) -> &'static str {
+        if self.is_rfc2136() {
+            "RFC 2136 (TSIG)"
+        } else {
+            self.secret_label()
+        }
+    }
+
+    fn requires_email(&self) -> bool {
+        !self.is_rfc2136() && self.auth_mode == AuthMode::GlobalKey
+    }
+
+    fn next_field(&mut self) {
+        self.field_index = (self.field_index + 1).min(self.last_field());
+    }
+
+    fn previous_field(&mut self) {
+        if self.field_index == 0 {
+            self.field_index = 0;
+        } else {
+            self.field_index -= 1;
+        }
+    }
+
+    fn insert_char(&mut self, c: char) {
+        if let Some(value) = self.active_value_mut() {
+            value.push(c);
+        } else if c == ' ' {
+            self.toggle_auth_mode();
+        } else if c == 'p' {
+            self.toggle_rfc2136();
+        }
+    }
+
+    fn backspace(&mut self) {
+        if let Some(value) = self.active_value_mut() {
+            value.pop();
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.name.trim().is_empty()
+            && !self.secret().is_empty()
+            && (!self.requires_email() || !self.email.trim().is_empty())
+            && (!self.is_rfc2136()
+                || !(self.tsig_key_name.trim().is_empty()
+                    || self.dns_server.trim().is_empty()
+                    || self.zones.trim().is_empty()))
+    }
+
+    fn build_account(&self) -> Result<Account, &'static str> {
+        if self.is_rfc2136() {
+            return self.build_rfc2136_account();
+        }
+        if self.name.trim().is_empty() || self.secret().is_empty() {
+            return Err(match self.auth_mode {
+                AuthMode::Token => "Name and API token are required",
+                AuthMode::GlobalKey => "Name and Global API key are required",
+            });
+        }
+        if self.requires_email() && self.email.trim().is_empty() {
+            return Err("Email is required for Global API Key accounts");
+        }
+        let base_url = self.base_url.trim().trim_end_matches('/');
+        if !base_url.is_empty()
+            && !base_url.starts_with("https://")
+            && !base_url.starts_with("http://")
+        {
+            return Err("API base URL must start with https:// or http://");
+        }
+        let default_ttl = self.default_ttl()?;
+        let default_proxied = match self.default_proxied.trim().to_ascii_lowercase().as_str() {
+            "" => None,
+            "yes" | "y" | "true" | "on" => Some(true),
+            "no" | "n" | "false" | "off" => Some(false),
+            _ => return Err("Proxy new records must be yes, no, or empty"),
+        };
+        let (label, color) = self.tag()?;
+        Ok(Account {
+            name: self.name.trim().to_string(),
+            api_token: self.secret().to_string(),
+            email: if self.email.trim().is_empty() {
+                None
+            } else {
+                Some(self.email.trim().to_string())
+            },
+            account_id: if self.account_id.trim().is_empty() {
+                None
+            } else {
+                Some(self.account_id.trim().to_string())
+            },
+            account_name: None,
+            auth_mode: self.auth_mode.clone(),
+            base_url: (!base_url.is_empty()).then(|| base_url.to_string()),
+            provider: Provider::Cloudflare,
+            aws_access_key_id: None,
+            zone_dir: None,
+            dns_server: None,
+            tsig_key_name: None,
+            tsig_algorithm: None,
+            zones: Vec::new(),
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl,
+            default_proxied,
+            label,
+            color,
+        })
+    }
+
+    /// The label and colour as typed, each `None` when left empty.
+    fn tag(&self) -> Result<(Option<String>, Option<String>), &'static str> {
+        let filled = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        let color = filled(&self.color);
+        if let Some(color) = &color
+            && parse_account_color(color).is_none()
+        {
+            return Err("Color must be a name like red or a hex like #ff8800");
+        }
+        Ok((filled(&self.label), color))
+    }
+
+    fn default_ttl(&self) -> Result<Option<u32>, &'static str> {
+        if self.default_ttl.trim().is_empty() {
+            return Ok(None);
+        }
+        parse_bulk_ttl(&self.default_ttl)
+            .map(Some)
+            .map_err(|_| "TTL for new records must be auto or 30 to 86400 seconds")
+    }
+
+    fn build_rfc2136_account(&self) -> Result<Account, &'static str> {
+        let filled = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        let zones: Vec<String> = self.zones.split([',', ' ']).filter_map(filled).collect();
+        if self.name.trim().is_empty() || self.secret().is_empty() {
+            return Err("Name and TSIG secret are required");
+        }
+        if self.tsig_key_name.trim().is_empty() || self.dns_server.trim().is_empty() {
+            return Err("TSIG key name and server address are required");
+        }
+        if zones.is_empty() {
+            return Err("List at least one zone");
+        }
+        let default_ttl = self.default_ttl()?;
+        let (label, color) = self.tag()?;
+        Ok(Account {
+            name: self.name.trim().to_string(),
+            api_token: self.secret().to_string(),
+            email: None,
+            account_id: None,
+            account_name: None,
+            auth_mode: AuthMode::Token,
+            base_url: None,
+            provider: Provider::Rfc2136,
+            aws_access_key_id: None,
+            zone_dir: None,
+            dns_server: filled(&self.dns_server),
+            tsig_key_name: filled(&self.tsig_key_name),
+            tsig_algorithm: filled(&self.tsig_algorithm),
+            zones,
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl,
+            default_proxied: None,
+            label,
+            color,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecordForm {
+    draft: RecordDraft,
+    field_index: usize,
+    is_edit: bool,
+    target_id: Option<String>,
+    /// Name of the record a create form was prefilled from, shown in its title.
+    cloned_from: Option<String>,
+    /// Which starting values came from the account's defaults, e.g. `TTL auto, proxied`.
+    account_defaults: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecordDraft {
+    name: String,
+    record_type: String,
+    content: String,
+    ttl: String,
+    proxied: bool,
+}
+
+impl Default for RecordDraft {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            record_type: "A".to_string(),
+            content: String::new(),
+            ttl: "300".to_string(),
+            proxied: true,
+        }
+    }
+}
+
+impl RecordDraft {
+    fn from_record(record: &DnsRecord) -> Self {
+        Self {
+            name: record.name.clone(),
+            record_type: record.record_type.clone(),
+            content: record.content.clone(),
+            ttl: record.ttl.to_string(),
+            proxied: record.proxied,
+        }
+    }
+
+    fn to_record(&self, id: String) -> Result<DnsRecord> {
+        let ttl: u32 = self
+            .ttl
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("TTL must be a number"))?;
+        if self.name.trim().is_empty() || self.record_type.trim().is_empty() {
+            return Err(anyhow!("Name and type are required"));
+        }
+        Ok(DnsRecord {
+            id,
+            name: self.name.trim().to_string(),
+            record_type: self.record_type.trim().to_string(),
+            content: self.content.trim().to_string(),
+            ttl,
+            proxied: self.proxied,
+            unmodeled: Vec::new(),
+            modified_on: None,
+        })
+    }
+}
+
+const EDITOR_ERROR_PREFIX: &str = "# error: ";
+
+/// The record as `field = value` lines for `E`; values run to the end of the line, so
+/// TXT content needs no quoting or escaping.
+fn editor_document(record: &DnsRecord, proxy: bool) -> String {
+    let DnsRecord {
+        name,
+        record_type,
+        ttl,
+        ..
+    } = record;
+    let mut text = format!(
+        "# Editing {name} ({record_type}). Save and quit to apply; empty the file to cancel.\n\
+         # Lines starting with # are ignored.\n\
+         name = {name}\ntype = {record_type}\nttl = {ttl}\n"
+    );
+    if proxy {
+        text.push_str(&format!("proxied = {}\n", record.proxied));
+    }
+    text.push_str(&format!("content = {}\n", record.content));
+    text
+}
+
+/// Reads back an [`editor_document`]; fields left out keep `original`'s values. `None`
+/// when nothing but comments is left.
+fn parse_editor_document(text: &str, original: &DnsRecord) -> Result<Option<DnsRecord>> {
+    let mut draft = RecordDraft::from_record(original);
+    let mut seen = HashSet::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = number + 1;
+        let Some((field, value)) = line.split_once('=') else {
+            return Err(anyhow!("line {number}: expected `field = value`"));
+        };
+        let (field, value) = (field.trim(), value.trim().to_string());
+        if !seen.insert(field.to_string()) {
+            return Err(anyhow!("line {number}: `{field}` is set twice"));
+        }
+        match field {
+            "name" => draft.name = value,
+            "type" => draft.record_type = value.to_uppercase(),
+            "content" => draft.content = value,
+            "ttl" => draft.ttl = value,
+            "proxied" => {
+                draft.proxied = value
+                    .parse()
+                    .map_err(|_| anyhow!("line {number}: proxied must be true or false"))?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "line {number}: unknown field `{field}` (use name, type, ttl, proxied or content)"
+                ));
+            }
+        }
+    }
+    if seen.is_empty() {
+        return Ok(None);
+    }
+    draft.to_record(original.id.clone()).map(Some)
+}
+
+/// Puts `err` above the user's text, replacing the previous round's, so nothing typed
+/// is lost when the editor reopens.
+fn with_error_comment(text: &str, err: &anyhow::Error) -> String {
+    let kept: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.starts_with(EDITOR_ERROR_PREFIX))
+        .collect();
+    format!("{EDITOR_ERROR_PREFIX}{err:#}\n{}\n", kept.join("\n"))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmDelete {
+    record_id: String,
+    record_name: String,
+    /// When set, Enter only works once the record name or `code` has been typed exactly.
+    require_typed: bool,
+    typed: String,
+    /// Short random alternative to typing a long record name.
+    code: String,
+    /// Why this record needs more than a single Enter, from [`deletion_danger`].
+    danger: Option<&'static str>,
+}
+
+impl ConfirmDelete {
+    fn is_confirmed(&self) -> bool {
+        !self.require_typed
+            || self.typed == self.record_name
+            || self.typed.eq_ignore_ascii_case(&self.code)
+    }
+}
+
+fn is_apex(name: &str, zone_name: &str) -> bool {
+    name.trim_end_matches('.')
+        .eq_ignore_ascii_case(zone_name.trim_end_matches('.'))
+}
+
+/// Records whose loss takes a whole zone or its mail down: the apex, wildcards, NS and MX.
+fn deletion_danger(record: &DnsRecord, zone_name: &str) -> Option<&'static str> {
+    if is_apex(&record.name, zone_name) {
+        Some("the zone apex")
+    } else if record.name.starts_with("*.") {
+        Some("a wildcard")
+    } else {
+        match record.record_type.as_str() {
+            "NS" => Some("a name server delegation"),
+            "MX" => Some("a mail exchanger"),
+            _ => None,
+        }
+    }
+}
+
+/// Four characters to type instead of a record name, leaving out ones easily misread.
+fn confirmation_code() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(now_unix().unsigned_abs());
+    let mut bits = hasher.finish();
+    (0..4)
+        .map(|_| {
+            let c = ALPHABET[(bits % ALPHABET.len() as u64) as usize] as char;
+            bits /= ALPHABET.len() as u64;
+            c
+        })
+        .collect()
+}
+
+/// Deleting every filtered (or selected) record at once always asks for the zone name to
+/// be typed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmBulkDelete {
+    zone_name: String,
+    records: Vec<DnsRecord>,
+    typed: String,
+    /// The records were marked with Space rather than matched by the filter.
+    selection: bool,
+    /// The first record of the diff shown.
+    scroll: usize,
+}
+
+impl ConfirmBulkDelete {
+    fn is_confirmed(&self) -> bool {
+        self.typed == self.zone_name
+    }
+
+    fn change_set(&self) -> ChangeSet {
+        BulkAction::Delete.change_set(&self.records)
+    }
+}
+
+/// Deleting a zone removes every record with it, so the full name must be typed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmDeleteZone {
+    zone: Zone,
+    record_count: usize,
+    typed: String,
+}
+
+/// Asks before an account's saved credentials are dropped from the config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmDeleteAccount {
+    index: usize,
+    account: Account,
+    /// Strict mode wants the account name typed before Enter removes it.
+    require_typed: bool,
+    typed: String,
+}
+
+impl ConfirmDeleteAccount {
+    fn is_confirmed(&self) -> bool {
+        !self.require_typed || self.typed == self.account.name
+    }
+}
+
+impl ConfirmDeleteZone {
+    fn is_confirmed(&self) -> bool {
+        self.typed == self.zone.name
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmCreate {
+    record: DnsRecord,
+    conflict: CreateConflict,
+    form: RecordForm,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CreateConflict {
+    /// An existing record already has the same name, type, and content.
+    Duplicate { existing_id: String },
+    /// A CNAME cannot share its name with any other record.
+    CnameClash {
+        existing_id: String,
+        existing_type: String,
+    },
+}
+
+impl CreateConflict {
+    fn describe(&self) -> String {
+        match self {
+            CreateConflict::Duplicate { existing_id } => {
+                format!("Duplicate of {existing_id} — create anyway?")
+            }
+            CreateConflict::CnameClash {
+                existing_id,
+                existing_type,
+            } => format!(
+                "A CNAME cannot coexist with the {existing_type} record {existing_id} at this name; Cloudflare will likely reject it — create anyway?"
+            ),
+        }
+    }
+}
+
+/// Expands a record name the way Cloudflare does: `@` is the apex and bare labels are relative
+/// to the zone.
+fn qualify_name(name: &str, zone_name: &str) -> String {
+    let name = name.trim().trim_end_matches('.').to_lowercase();
+    let zone_name = zone_name.trim_end_matches('.').to_lowercase();
+    if name == "@" || name.is_empty() {
+        zone_name
+    } else if name == zone_name || name.ends_with(&format!(".{zone_name}")) {
+        name
+    } else {
+        format!("{name}.{zone_name}")
+    }
+}
+
+/// Looks for an existing record the new one would duplicate or clash with.
+fn find_create_conflict(
+    records: &[DnsRecord],
+    zone_name: &str,
+    candidate: &DnsRecord,
+) -> Option<CreateConflict> {
+    let name = qualify_name(&candidate.name, zone_name);
+    let same_name = records
+        .iter()
+        .filter(|r| qualify_name(&r.name, zone_name) == name);
+
+    let mut clash = None;
+    for existing in same_name {
+        let same_type = existing
+            .record_type
+            .eq_ignore_ascii_case(&candidate.record_type);
+        if same_type && existing.content.trim() == candidate.content.trim() {
+            return Some(CreateConflict::Duplicate {
+                existing_id: existing.id.clone(),
+            });
+        }
+        let is_cname = |t: &str| t.eq_ignore_ascii_case("CNAME");
+        if clash.is_none()
+            && !same_type
+            && (is_cname(&candidate.record_type) || is_cname(&existing.record_type))
+        {
+            clash = Some(CreateConflict::CnameClash {
+                existing_id: existing.id.clone(),
+                existing_type: existing.record_type.clone(),
+            });
+        }
+    }
+    clash
+}
+
+const HISTORY_PAGE_SIZE: usize = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum DetailTab {
+    Details,
+    History,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ZoneDetail {
+    zone: Zone,
+    dnssec: Option<DnssecStatus>,
+    /// Why the DNSSEC status couldn't be loaded.
+    dnssec_note: Option<String>,
+    /// Set by `s` until the toggle is confirmed with `y` or dismissed.
+    confirm_toggle: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecordDetail {
+    record: DnsRecord,
+    tab: DetailTab,
+    history: Vec<HistoryEntry>,
+    history_note: Option<String>,
+    history_page: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl ChangeAction {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeAction::Create => "created",
+            ChangeAction::Update => "updated",
+            ChangeAction::Delete => "deleted",
+        }
+    }
+
+    /// Maps Cloudflare audit log action types (`rec_add`, `rec_set`, `rec_del`, ...).
+    fn from_audit_type(action: &str) -> Option<Self> {
+        match action {
+            "rec_add" | "create" => Some(ChangeAction::Create),
+            "rec_set" | "update" => Some(ChangeAction::Update),
+            "rec_del" | "delete" => Some(ChangeAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FieldChange {
+    field: &'static str,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl FieldChange {
+    fn describe(&self) -> String {
+        format!(
+            "{}: {} → {}",
+            self.field,
+            self.before.as_deref().unwrap_or("∅"),
+            self.after.as_deref().unwrap_or("∅")
+        )
+    }
+}
+
+/// Field-level differences between two versions of a record.
+fn diff_records(before: Option<&DnsRecord>, after: Option<&DnsRecord>) -> Vec<FieldChange> {
+    let fields = |r: Option<&DnsRecord>| -> [Option<String>; 5] {
+        match r {
+            Some(r) => [
+                Some(r.name.clone()),
+                Some(r.record_type.clone()),
+                Some(r.content.clone()),
+                Some(r.ttl.to_string()),
+                Some(r.proxied.to_string()),
+            ],
+            None => Default::default(),
+        }
+    };
+    diff_fields(fields(before), fields(after))
+}
+
+fn diff_fields(before: [Option<String>; 5], after: [Option<String>; 5]) -> Vec<FieldChange> {
+    const NAMES: [&str; 5] = ["name", "type", "content", "ttl", "proxied"];
+    NAMES
+        .iter()
+        .zip(before.into_iter().zip(after))
+        .filter(|(_, (b, a))| b != a)
+        .map(|(field, (before, after))| FieldChange {
+            field,
+            before,
+            after,
+        })
+        .collect()
+}
+
+/// A change nyxflare made, appended to the local journal next to the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct JournalEntry {
+    timestamp: i64,
+    account: String,
+    zone_id: String,
+    record_id: String,
+    action: ChangeAction,
+    before: Option<DnsRecord>,
+    after: Option<DnsRecord>,
+}
+
+struct Journal {
+    path: PathBuf,
+}
+
+/// The account and zone selected when nyxflare last ran, kept next to the config file so
+/// the next run starts there. Names and ids only, never credentials.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct SessionState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    account: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zone_id: Option<String>,
+}
+
+impl SessionState {
+    fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_extension("state.json")
+    }
+
+    /// The saved state; a missing or unreadable file is the same as none.
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Writing {}", path.display()))
+    }
+}
+
+impl Journal {
+    fn for_config(config_path: &Path) -> Self {
+        Self {
+            path: config_path.with_extension("journal.jsonl"),
+        }
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Opening journal {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    fn entries_for(&self, zone_id: &str, record_id: &str) -> Result<Vec<JournalEntry>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+            .filter(|e| e.zone_id == zone_id && e.record_id == record_id)
+            .collect())
+    }
+}
+
+/// A Cloudflare audit log event concerning a single record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AuditEntry {
+    id: String,
+    timestamp: i64,
+    action: ChangeAction,
+    actor: Option<String>,
+    changes: Vec<FieldChange>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum HistorySource {
+    Local,
+    Cloudflare,
+    Both,
+}
+
+impl HistorySource {
+    fn label(self) -> &'static str {
+        match self {
+            HistorySource::Local => "local",
+            HistorySource::Cloudflare => "cloudflare",
+            HistorySource::Both => "local+cloudflare",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HistoryEntry {
+    timestamp: i64,
+    source: HistorySource,
+    action: ChangeAction,
+    actor: Option<String>,
+    changes: Vec<FieldChange>,
+}
+
+/// Local and audit events this close together with the same action are treated as one change.
+const HISTORY_DEDUP_WINDOW_SECS: i64 = 120;
+
+/// Interleaves journal and audit entries oldest-first, collapsing events seen in both sources.
+fn merge_history(local: Vec<JournalEntry>, remote: Vec<AuditEntry>) -> Vec<HistoryEntry> {
+    let mut remote: Vec<Option<AuditEntry>> = remote.into_iter().map(Some).collect();
+    let mut merged = Vec::new();
+
+    for entry in local {
+        let changes = diff_records(entry.before.as_ref(), entry.after.as_ref());
+        let twin = remote.iter_mut().find(|slot| {
+            slot.as_ref().is_some_and(|audit| {
+                audit.action == entry.action
+                    && (audit.timestamp - entry.timestamp).abs() <= HISTORY_DEDUP_WINDOW_SECS
+                    && (audit.changes.is_empty() || audit.changes == changes)
+            })
+        });
+        match twin.and_then(Option::take) {
+            Some(audit) => merged.push(HistoryEntry {
+                timestamp: audit.timestamp.min(entry.timestamp),
+                source: HistorySource::Both,
+                action: entry.action,
+                actor: audit.actor.or(Some(entry.account)),
+                changes,
+            }),
+            None => merged.push(HistoryEntry {
+                timestamp: entry.timestamp,
+                source: HistorySource::Local,
+                action: entry.action,
+                actor: Some(entry.account),
+                changes,
+            }),
+        }
+    }
+
+    merged.extend(remote.into_iter().flatten().map(|audit| HistoryEntry {
+        timestamp: audit.timestamp,
+        source: HistorySource::Cloudflare,
+        action: audit.action,
+        actor: audit.actor,
+        changes: audit.changes,
+    }));
+    merged.sort_by_key(|e| e.timestamp);
+    merged
+}
+
+fn now_unix() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Parses the RFC 3339 timestamps Cloudflare returns (`2024-05-01T12:30:00.123Z`, offsets allowed).
+fn parse_rfc3339(text: &str) -> Option<i64> {
+    let (date, time) = text.trim().split_once(['T', ' '])?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+
+    let (clock, offset_secs) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else if let Some(idx) = time.rfind(['+', '-']) {
+        let (clock, offset) = time.split_at(idx);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (oh, om) = offset[1..].split_once(':')?;
+        (
+            clock,
+            sign * (oh.parse::<i64>().ok()? * 3600 + om.parse::<i64>().ok()? * 60),
+        )
+    } else {
+        (time, 0)
+    };
+    let clock = clock.split('.').next()?;
+    let mut clock_parts = clock.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (
+        clock_parts.next()??,
+        clock_parts.next()??,
+        clock_parts.next().flatten().unwrap_or(0),
+    );
+
+    Some(
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second
+            - offset_secs,
+    )
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86_400));
+    let secs = timestamp.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+/// `20261015T093000Z`, safe for file names.
+fn compact_timestamp(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86_400));
+    let secs = timestamp.rem_euclid(86_400);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+/// `, showing 200 of 1,731` when fewer items are loaded than the server reported.
+fn truncation_note(loaded: usize, total: Option<u32>) -> String {
+    match total {
+        Some(total) if (total as usize) > loaded => format!(
+            ", showing {} of {}",
+            format_count(loaded as u64),
+            format_count(u64::from(total))
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Formats a count with thousands separators.
+fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats an elapsed time coarsely, e.g. "45s" or "3m".
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs @ 0..60 => format!("{secs}s"),
+        secs @ 60..3600 => format!("{}m", secs / 60),
+        secs => format!("{}h", secs / 3600),
+    }
+}
+
+/// A record write shown in the table before the backend has confirmed it.
+struct PendingWrite {
+    write_id: u64,
+    account: Account,
+    zone: Zone,
+    action: ChangeAction,
+    /// The row shown meanwhile: the new record (under a placeholder id) or the edited one.
+    provisional: Option<DnsRecord>,
+    /// The record as it was, restored if the write fails.
+    before: Option<DnsRecord>,
+    /// Where `before` sat, so a failed delete puts it back in place.
+    index: usize,
+    /// Policy warning to repeat once an update lands.
+    warning: Option<String>,
+    /// What a one-key edit (proxied toggle, quick TTL) changed, e.g. `TTL 300 → auto`,
+    /// for the success message. Form edits have none and reopen the form on failure.
+    quick: Option<String>,
+}
+
+/// Only address and alias records can go through Cloudflare's proxy.
+fn is_proxiable(record_type: &str) -> bool {
+    matches!(record_type, "A" | "AAAA" | "CNAME")
+}
+
+/// What a bulk job does to each of its records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BulkAction {
+    Delete,
+    SetProxied(bool),
+    SetTtl(u32),
+    /// The steps of an `A` plan, each with its own write.
+    Apply,
+}
+
+/// What a bulk update would do to one record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum BulkStep {
+    Change(DnsRecord),
+    /// Already as asked; sending it would waste a call.
+    Unchanged,
+    /// The change doesn't apply to this kind of record.
+    Ineligible,
+}
+
+impl BulkAction {
+    fn step(self, record: &DnsRecord) -> BulkStep {
+        match self {
+            BulkAction::Delete | BulkAction::Apply => BulkStep::Change(record.clone()),
+            BulkAction::SetProxied(_) if !is_proxiable(&record.record_type) => BulkStep::Ineligible,
+            BulkAction::SetProxied(on) if record.proxied == on => BulkStep::Unchanged,
+            BulkAction::SetProxied(on) => BulkStep::Change(DnsRecord {
+                proxied: on,
+                ..record.clone()
+            }),
+            BulkAction::SetTtl(ttl) if record.ttl == ttl => BulkStep::Unchanged,
+            BulkAction::SetTtl(ttl) => BulkStep::Change(DnsRecord {
+                ttl,
+                ..record.clone()
+            }),
+        }
+    }
+
+    /// What the action does to `records`: an update with the record before and after
+    /// for each one it changes, or a delete; records it doesn't reach are counted.
+    fn change_set(self, records: &[DnsRecord]) -> ChangeSet {
+        let mut set = ChangeSet::default();
+        let mut ineligible: Vec<&str> = Vec::new();
+        for record in records {
+            match self.step(record) {
+                BulkStep::Change(_) if self == BulkAction::Delete => {
+                    set.steps.push(Step::Delete {
+                        record: record.clone(),
+                    });
+                }
+                BulkStep::Change(changed) => set.steps.push(Step::Update {
+                    before: record.clone(),
+                    after: changed,
+                }),
+                BulkStep::Unchanged => set.unchanged += 1,
+                BulkStep::Ineligible => ineligible.push(&record.record_type),
+            }
+        }
+        if !ineligible.is_empty() {
+            let count = ineligible.len();
+            ineligible.sort_unstable();
+            ineligible.dedup();
+            set.left_out.push((
+                count,
+                format!("skipped ({} can't be proxied)", ineligible.join(", ")),
+            ));
+        }
+        set
+    }
+
+    /// The write for one step of the job, with the record to report it by. Only apply
+    /// sends the record as planned along, so one changed elsewhere since isn't overwritten.
+    fn write(self, step: Step) -> (RecordWrite, DnsRecord) {
+        let checked = |record: DnsRecord| {
+            matches!(self, BulkAction::Apply | BulkAction::Delete).then_some(record)
+        };
+        match step {
+            Step::Create { record } => (RecordWrite::Create(record.clone()), record),
+            Step::Update { before, after } => (
+                RecordWrite::Update {
+                    record: after.clone(),
+                    loaded: checked(before),
+                },
+                after,
+            ),
+            Step::Delete { record } => (
+                RecordWrite::Delete {
+                    record_id: record.id.clone(),
+                    loaded: checked(record.clone()),
+                },
+                record,
+            ),
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            BulkAction::Delete => "Delete".to_string(),
+            BulkAction::SetProxied(true) => "Turn proxying on for".to_string(),
+            BulkAction::SetProxied(false) => "Switch to DNS only".to_string(),
+            BulkAction::SetTtl(ttl) => format!("Set TTL {} on", ttl_label(ttl)),
+            BulkAction::Apply => "Apply".to_string(),
+        }
+    }
+
+    fn progress(self) -> &'static str {
+        match self {
+            BulkAction::Delete => "Deleting",
+            BulkAction::SetProxied(_) | BulkAction::SetTtl(_) => "Updating",
+            BulkAction::Apply => "Applying",
+        }
+    }
+
+    fn done(self) -> &'static str {
+        match self {
+            BulkAction::Delete => "deleted",
+            BulkAction::SetProxied(_) | BulkAction::SetTtl(_) => "changed",
+            BulkAction::Apply => "applied",
+        }
+    }
+}
+
+fn ttl_label(ttl: u32) -> String {
+    if ttl == 1 {
+        "auto".to_string()
+    } else {
+        ttl.to_string()
+    }
+}
+
+/// Reads a TTL typed for a bulk change or an account's new records: `auto` (Cloudflare's
+/// 1) or 30 to 86400 seconds.
+fn parse_bulk_ttl(text: &str) -> Result<u32> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("auto") {
+        return Ok(1);
+    }
+    match text.parse::<u32>() {
+        Ok(ttl) if ttl == 1 || (30..=86400).contains(&ttl) => Ok(ttl),
+        _ => Err(anyhow!(
+            "TTL must be auto or a number of seconds from 30 to 86400"
+        )),
+    }
+}
+
+/// A field of the highlighted record that can be edited on its own from a one-line
+/// prompt. Another quick edit only needs another variant here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QuickField {
+    Ttl,
+}
+
+impl QuickField {
+    fn label(self) -> &'static str {
+        match self {
+            QuickField::Ttl => "TTL",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            QuickField::Ttl => "auto or seconds",
+        }
+    }
+
+    fn value(self, record: &DnsRecord) -> String {
+        match self {
+            QuickField::Ttl => ttl_label(record.ttl),
+        }
+    }
+
+    /// The record with this field set from `text`, or why `text` won't do.
+    fn apply(self, record: &DnsRecord, text: &str) -> Result<DnsRecord> {
+        let mut record = record.clone();
+        match self {
+            QuickField::Ttl => record.ttl = parse_bulk_ttl(text)?,
+        }
+        Ok(record)
+    }
+}
+
+/// The one-line prompt for a [`QuickField`], prefilled with the record's current value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct QuickEdit {
+    field: QuickField,
+    record: DnsRecord,
+    text: String,
+}
+
+/// The prompt for a new TTL, applied to the selected (or filtered) records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TtlPrompt {
+    text: String,
+    records: Vec<DnsRecord>,
+}
+
+/// Confirmation for a bulk update, showing what would change and what is left alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConfirmBulkUpdate {
+    action: BulkAction,
+    /// The selected (or filtered) records, before the change.
+    records: Vec<DnsRecord>,
+    /// The first step of the diff shown.
+    scroll: usize,
+    policy: Policy,
+}
+
+impl ConfirmBulkUpdate {
+    /// The action's change set, with what strict mode refuses taken out.
+    fn change_set(&self) -> ChangeSet {
+        let mut set = self.action.change_set(&self.records);
+        self.policy.screen(&mut set);
+        set
+    }
+}
+
+/// A change set sent one call at a time, so progress can be shown and a failure doesn't
+/// stop the rest, unless `stop_on_failure` says so. Each outcome is applied to the loaded
+/// records as it arrives.
+struct BulkJob {
+    action: BulkAction,
+    account: Account,
+    zone: Zone,
+    /// The writes left, each with the record to report it by.
+    queue: VecDeque<(RecordWrite, DnsRecord)>,
+    total: usize,
+    /// The record whose write is awaited, and its write id.
+    in_flight: Option<(u64, DnsRecord)>,
+    succeeded: usize,
+    /// Records left alone before the job started, for the summary.
+    skipped: usize,
+    failures: Vec<(DnsRecord, String)>,
+    /// What was still queued when a failure stopped the job.
+    not_sent: Vec<DnsRecord>,
+}
+
+/// What went wrong in the last bulk job, shown with `L`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BulkReport {
+    summary: String,
+    failures: Vec<String>,
+    scroll: usize,
+}
+
+/// A zone list as last fetched for an account.
+struct CachedZones {
+    listing: Listing<Zone>,
+    fetched_at: Instant,
+}
+
+/// A multi-page record fetch in progress, continued one page per event-loop tick.
+struct RecordLoad {
+    account: Account,
+    zone: Zone,
+    record_type: Option<&'static str>,
+    next_page: u32,
+    /// Pages that will be fetched (the server total capped by the safeguard).
+    total_pages: u32,
+    server_pages: u32,
+}
+
+/// The highlighted record to find again once a reload of its zone lands.
+struct Reselect {
+    zone_id: String,
+    record_id: String,
+    /// Where it was in the filtered list, the fallback when it's gone.
+    index: usize,
+}
+
+struct App<B: DnsBackend> {
+    config_path: PathBuf,
+    journal: Journal,
+    state_path: PathBuf,
+    /// What the state file holds, so it's only rewritten when the selection changes.
+    saved_state: SessionState,
+    /// The zone to select once the account's zones have loaded: the restored one, or
+    /// one just created.
+    restore_zone: Option<String>,
+    /// The zone `--zone` names, to select once the account's zones have loaded.
+    start_zone: Option<String>,
+    /// Shared with the loader thread, which holds the lock while a fetch runs; the UI
+    /// thread leaves calls to the loader and only reads the backend's notices.
+    backend: Arc<Mutex<B>>,
+    /// Runs backend calls off the UI thread; without one they run inline.
+    loader: Option<Loader>,
+    /// Bumped whenever zones or records are reloaded; responses for older ones are dropped.
+    generation: u64,
+    /// The fetch in flight for the current generation, e.g. "Loading zones for acme".
+    is_loading: Option<String>,
+    /// Which panel that fetch fills, for its title spinner.
+    loading_panel: Focus,
+    /// The status line when that fetch was issued; it is only replaced on completion
+    /// if nothing newer was shown meanwhile.
+    status_at_dispatch: String,
+    /// The action call in flight, e.g. "Deleting zone example.com"; one runs at a time.
+    calling: Option<String>,
+    notices: Option<NoticeBoard>,
+    rate: Option<RateMeter>,
+    accounts: Vec<Account>,
+    selected_account: usize,
+    /// Zone lists by account name, reused when switching accounts until they go stale.
+    zone_cache: HashMap<String, CachedZones>,
+    zones: Vec<Zone>,
+    /// Indexes `zones`, not the filtered list.
+    selected_zone: usize,
+    /// Narrows the zone list by name; cleared when switching accounts.
+    zone_filter: String,
+    /// Show the selected record's whole content over several lines.
+    wrap_content: bool,
+    /// Characters of content skipped in every row while not wrapping.
+    content_scroll: usize,
+    /// Width of the Content column when last drawn.
+    content_width: usize,
+    selected_record: usize,
+    records: Vec<DnsRecord>,
+    focus: Focus,
+    mode: Mode,
+    /// First key of a two-key command such as `gg`, waiting for the second.
+    pending_key: Option<char>,
+    panels: PanelLayout,
+    /// When and which record row was last clicked, for spotting double clicks.
+    last_click: Option<(Instant, usize)>,
+    /// The shown zone's text filter; the other zones' are parked in `record_filters`.
+    record_filter: String,
+    record_filter_scope: SearchScope,
+    /// The zone `record_filter` belongs to.
+    filter_zone: String,
+    /// Filters of zones not currently shown, by zone id, put back when the zone is.
+    record_filters: HashMap<String, String>,
+    /// A located search: the records it matches are stepped through with `n`/`N`
+    /// without filtering the rest out. Dropped when another zone is shown.
+    locate: String,
+    /// The zone `locate` was applied in.
+    locate_zone: String,
+    type_filter: TypeFilter,
+    /// Records marked with Space, by id; kept through paging and filtering, dropped when
+    /// another zone is shown.
+    selected_ids: HashSet<String>,
+    /// The zone `selected_ids` belong to.
+    selection_zone: String,
+    /// Sort order per zone id; zones not in here keep the API order.
+    record_sorts: HashMap<String, RecordSort>,
+    record_page: usize,
+    record_page_size: usize,
+    record_load: Option<RecordLoad>,
+    reselect: Option<Reselect>,
+    /// Record writes already shown in `records`, awaiting the backend's answer.
+    pending_writes: Vec<PendingWrite>,
+    writes_sent: u64,
+    bulk: Option<BulkJob>,
+    bulk_report: Option<BulkReport>,
+    /// Escape sequences (OSC 52 copies) for the UI loop to write straight to the terminal.
+    terminal_output: String,
+    /// A record for the UI loop to open in `$VISUAL`/`$EDITOR`, which needs the terminal.
+    external_edit: Option<DnsRecord>,
+    /// Server-reported totals, which may exceed what has been loaded.
+    zone_total_count: Option<u32>,
+    record_total_count: Option<u32>,
+    last_message: String,
+    /// Everything shown on the status line, oldest first, for the `M` overlay.
+    messages: VecDeque<LoggedMessage>,
+    policy: Policy,
+    /// Settings loaded at startup; the account list itself lives in `accounts`.
+    config: Config,
+    /// The config file's mode while other users can read it, warned about until `F` fixes it.
+    loose_config_mode: Option<u32>,
+    /// Accounts from a shared config still to be asked for their secrets, by name.
+    missing_secrets: VecDeque<String>,
+    /// The config file's modification time and size when last read or written, to
+    /// notice when something else changes it.
+    config_stamp: Option<(SystemTime, u64)>,
+    /// The profile's accounts as the config file has them, to tell unsaved changes apart.
+    saved_accounts: Vec<Account>,
+    /// Who holds the config's lock when this instance doesn't; accounts can't be
+    /// changed here while it's set.
+    read_only: Option<String>,
+}
+
+impl<B: DnsBackend> App<B> {
+    /// Fetches run inline, so each call has finished loading when it returns.
+    #[cfg(test)]
+    fn new(config_path: impl Into<PathBuf>, config: Config, backend: B) -> Result<Self> {
+        let mut app = Self::unloaded(config_path.into(), config, backend);
+        app.load_initial()?;
+        Ok(app)
+    }
+
+    /// Like `new`, but zone and record fetches run on a background thread.
+    fn threaded(config_path: impl Into<PathBuf>, config: Config, backend: B) -> Result<Self>
+    where
+        B: 'static,
+    {
+        let mut app = Self::unloaded(config_path.into(), config, backend);
+        app.loader = Some(Loader::spawn(Arc::clone(&app.backend)));
+        app.load_initial()?;
+        Ok(app)
+    }
+
+    fn unloaded(config_path: PathBuf, mut config: Config, backend: B) -> Self {
+        let profile = config.profile_name().to_string();
+        let accounts = std::mem::take(config.profile_accounts_mut(&profile));
+        let state_path = SessionState::path_for(&config_path);
+        let saved_state = SessionState::load(&state_path);
+        let config_stamp = config_stamp(&config_path);
+        let saved_accounts = accounts.clone();
+        // A remembered account that's gone means starting from the top, zone and all.
+        let restored = saved_state
+            .account
+            .as_ref()
+            .and_then(|name| accounts.iter().position(|account| &account.name == name));
+        let starts = config.start.is_some();
+        let (restored, restore_zone, start_zone) = match config.start.take() {
+            Some((name, zone)) => (
+                accounts.iter().position(|account| account.name == name),
+                None,
+                zone,
+            ),
+            None => (restored, restored.and(saved_state.zone_id.clone()), None),
+        };
+        Self {
+            journal: Journal::for_config(&config_path),
+            state_path,
+            restore_zone,
+            start_zone,
+            saved_state,
+            config_path,
+            notices: backend.notice_board(),
+            rate: backend.rate_meter(),
+            backend: Arc::new(Mutex::new(backend)),
+            loader: None,
+            generation: 0,
+            is_loading: None,
+            loading_panel: Focus::Zones,
+            status_at_dispatch: String::new(),
+            calling: None,
+            accounts,
+            selected_account: restored.unwrap_or(0),
+            zone_cache: HashMap::new(),
+            zones: Vec::new(),
+            selected_zone: 0,
+            zone_filter: String::new(),
+            wrap_content: false,
+            content_scroll: 0,
+            content_width: 40,
+            selected_record: 0,
+            records: Vec::new(),
+            focus: if starts {
+                Focus::Zones
+            } else {
+                Focus::Accounts
+            },
+            mode: Mode::Normal,
+            pending_key: None,
+            panels: PanelLayout::default(),
+            last_click: None,
+            record_filter: String::new(),
+            record_filter_scope: SearchScope::Local,
+            filter_zone: String::new(),
+            record_filters: HashMap::new(),
+            locate: String::new(),
+            locate_zone: String::new(),
+            type_filter: TypeFilter::All,
+            selected_ids: HashSet::new(),
+            selection_zone: String::new(),
+            record_sorts: HashMap::new(),
+            record_page: 0,
+            record_page_size: 10,
+            record_load: None,
+            reselect: None,
+            pending_writes: Vec::new(),
+            writes_sent: 0,
+            bulk: None,
+            bulk_report: None,
+            terminal_output: String::new(),
+            external_edit: None,
+            zone_total_count: None,
+            record_total_count: None,
+            last_message: String::new(),
+            messages: VecDeque::new(),
+            policy: Policy::from_config(&config),
+            config,
+            loose_config_mode: None,
+            missing_secrets: VecDeque::new(),
+            config_stamp,
+            saved_accounts,
+            read_only: None,
+        }
+    }
+
+    fn load_initial(&mut self) -> Result<()> {
+        self.refresh_current()?;
+        if self.accounts.is_empty() {
+            self.mode = Mode::AddingAccount(AccountForm::default());
+            self.push_message(
+                MessageLevel::Info,
+                "Add your first Cloudflare account (name + API token), or quit and run nyxflare --import-credentials to use wrangler's or flarectl's.",
+            );
+        }
+        Ok(())
+    }
+
+    fn backend(&self) -> MutexGuard<'_, B> {
+        self.backend.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn current_account(&self) -> Option<&Account> {
+        self.accounts.get(self.selected_account)
+    }
+
+    /// Whether the current account's provider has a proxied setting at all.
+    fn has_proxy(&self) -> bool {
+        self.current_account()
+            .is_none_or(|account| account.provider.has_proxy())
+    }
+
+    fn current_zone(&self) -> Option<&Zone> {
+        self.zones.get(self.selected_zone)
+    }
+
+    fn next_account(&mut self) -> Result<()> {
+        if self.accounts.is_empty() {
+            return Ok(());
+        }
+        self.selected_account = (self.selected_account + 1) % self.accounts.len();
+        self.selected_zone = 0;
+        self.show_account()
+    }
+
+    fn previous_account(&mut self) -> Result<()> {
+        if self.accounts.is_empty() {
+            return Ok(());
+        }
+
+        if self.selected_account == 0 {
+            self.selected_account = self.accounts.len() - 1;
+        } else {
+            self.selected_account -= 1;
+        }
+        self.selected_zone = 0;
+        self.show_account()
+    }
+
+    fn next_zone(&mut self) -> Result<()> {
+        let visible = self.visible_zones();
+        if visible.is_empty() {
+            return Ok(());
+        }
+        let next = self
+            .zone_position()
+            .map_or(0, |pos| (pos + 1) % visible.len());
+        self.selected_zone = visible[next];
+        self.load_records(true)
+    }
+
+    fn previous_zone(&mut self) -> Result<()> {
+        let visible = self.visible_zones();
+        if visible.is_empty() {
+            return Ok(());
+        }
+        let previous = match self.zone_position() {
+            Some(0) | None => visible.len() - 1,
+            Some(pos) => pos - 1,
+        };
+        self.selected_zone = visible[previous];
+        self.load_records(true)
+    }
+
+    /// Indices into `zones` of the zones whose names contain the zone filter.
+    fn visible_zones(&self) -> Vec<usize> {
+        let needle = self.zone_filter.to_lowercase();
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|(_, zone)| zone.name.to_lowercase().contains(&needle))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Narrows the zone list to the filter prompt's text, moving its highlight onto the
+    /// first match if the old one was filtered out.
+    fn sync_zone_filter(&mut self) {
+        let Mode::FilteringZones(prompt) = &self.mode else {
+            return;
+        };
+        self.zone_filter = prompt.text.trim().to_string();
+        let visible = self.visible_zones();
+        let Mode::FilteringZones(prompt) = &mut self.mode else {
+            return;
+        };
+        if prompt.highlight.is_none_or(|zone| !visible.contains(&zone)) {
+            prompt.highlight = visible.first().copied();
+        }
+    }
+
+    /// Where the selected zone sits in the filtered list, if the filter lets it through.
+    fn zone_position(&self) -> Option<usize> {
+        self.visible_zones()
+            .iter()
+            .position(|&index| index == self.selected_zone)
+    }
+
+    /// Opens the zone filter prompt, highlighting the selected zone.
+    fn start_zone_filter(&mut self) {
+        self.mode = Mode::FilteringZones(ZoneFilterPrompt {
+            text: self.zone_filter.clone(),
+            highlight: self.zone_position().map(|_| self.selected_zone),
+            previous: self.zone_filter.clone(),
+        });
+    }
+
+    fn next_record(&mut self) {
+        let total = self.filtered_records().len();
+        if total == 0 {
+            return;
+        }
+        self.selected_record = (self.selected_record + 1).min(total.saturating_sub(1));
+        self.ensure_record_visible(total);
+    }
+
+    fn previous_record(&mut self) {
+        let total = self.filtered_records().len();
+        if total == 0 {
+            return;
+        }
+        if self.selected_record == 0 {
+            self.selected_record = 0;
+        } else {
+            self.selected_record -= 1;
+        }
+        self.ensure_record_visible(total);
+    }
+
+    /// Selects the account at `index`, if there is one and it isn't already selected.
+    fn select_account_at(&mut self, index: usize) -> Result<()> {
+        if index >= self.accounts.len() || index == self.selected_account {
+            return Ok(());
+        }
+        self.selected_account = index;
+        self.selected_zone = 0;
+        self.show_account()
+    }
+
+    /// Shows `text` on the status line and adds it to the message history.
+    fn push_message(&mut self, level: MessageLevel, text: impl Into<String>) {
+        let text = text.into();
+        if level == MessageLevel::Progress
+            && self
+                .messages
+                .back()
+                .is_some_and(|last| last.level == MessageLevel::Progress)
+        {
+            self.messages.pop_back();
+        }
+        if self.messages.len() == MESSAGE_HISTORY_LIMIT {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(LoggedMessage {
+            at: Instant::now(),
+            level,
+            text: text.clone(),
+        });
+        self.last_message = text;
+    }
+
+    /// Adds `suffix` to the current message, on the status line and in the history.
+    fn amend_message(&mut self, suffix: &str) {
+        self.last_message.push_str(suffix);
+        if let Some(last) = self.messages.back_mut() {
+            last.text.push_str(suffix);
+        }
+    }
+
+    /// Puts an earlier message back on the status line; it is already in the history.
+    fn restore_message(&mut self, text: String) {
+        self.last_message = text;
+    }
+
+    /// Logs a failure that reached the event loop and shows it in the error modal.
+    fn report_error(&mut self, err: anyhow::Error) {
+        self.push_message(MessageLevel::Error, format!("{err:#}"));
+        self.show_error(ErrorDetails::new(None, &err));
+    }
+
+    /// Opens the error modal unless another overlay is up; the status line and message
+    /// history already say what went wrong.
+    fn show_error(&mut self, details: ErrorDetails) {
+        if self.mode == Mode::Normal {
+            self.mode = Mode::Error(details);
+        }
+    }
+
+    /// Checks who can read the config file, keeping a warning up while other users can.
+    fn check_config_permissions(&mut self) {
+        self.loose_config_mode = loose_config_mode(&self.config_path);
+        if let Some(mode) = self.loose_config_mode {
+            self.push_message(
+                MessageLevel::Error,
+                format!(
+                    "{} is mode {mode:o}, so other users can read your tokens; press F to make it 600",
+                    self.config_path.display()
+                ),
+            );
+        }
+    }
+
+    /// Starts read-only because `holder` has the config's lock: account changes are
+    /// turned away, and reloads still show what the other instance saves.
+    fn open_read_only(&mut self, holder: String) {
+        if matches!(self.mode, Mode::AddingAccount(_)) {
+            self.mode = Mode::Normal;
+        }
+        let message = format!(
+            "{holder} has {} open, so accounts are read-only here; close it and restart to change them",
+            self.config_path.display()
+        );
+        self.read_only = Some(holder);
+        self.push_message(MessageLevel::Error, message);
+    }
+
+    /// Whether account changes are off because another instance has the config; says
+    /// so on the status line when they are.
+    fn refuse_if_read_only(&mut self) -> bool {
+        let Some(holder) = &self.read_only else {
+            return false;
+        };
+        let message = format!("Read-only: {holder} has the config open");
+        self.push_message(MessageLevel::Info, message);
+        true
+    }
+
+    /// The `F` key: takes group and world access off the config file.
+    fn restrict_config_permissions(&mut self) -> Result<()> {
+        if self.loose_config_mode.is_none() {
+            self.push_message(MessageLevel::Info, "The config file is already private");
+            return Ok(());
+        }
+        restrict_config_mode(&self.config_path)?;
+        self.loose_config_mode = None;
+        self.push_message(
+            MessageLevel::Info,
+            format!("{} is now mode 600", self.config_path.display()),
+        );
+        Ok(())
+    }
+
+    fn open_profile_picker(&mut self) {
+        let current = self.config.profile_name();
+        let profiles: Vec<(String, usize)> = self
+            .config
+            .profile_names()
+            .into_iter()
+            .map(|name| {
+                let count = if name == current {
+                    self.accounts.len()
+                } else {
+                    self.config.profile_accounts(&name).len()
+                };
+                (name, count)
+            })
+            .collect();
+        let selected = profiles
+            .iter()
+            .position(|(name, _)| name == current)
+            .unwrap_or(0);
+        self.mode = Mode::PickProfile(ProfilePicker {
+            profiles,
+            selected,
+            new_name: None,
+        });
+    }
+
+    /// Parks the shown accounts back in their profile and shows profile `name`'s instead,
+    /// dropping the zones, filters and selections that belonged to the old ones.
+    fn switch_profile(&mut self, name: &str) -> Result<()> {
+        self.mode = Mode::Normal;
+        let current = self.config.profile_name().to_string();
+        if name == current {
+            self.push_message(MessageLevel::Info, format!("Already using profile {name}"));
+            return Ok(());
+        }
+        *self.config.profile_accounts_mut(&current) = std::mem::take(&mut self.accounts);
+        self.accounts = std::mem::take(self.config.profile_accounts_mut(name));
+        self.saved_accounts = self.accounts.clone();
+        self.config.profile = (name != DEFAULT_PROFILE).then(|| name.to_string());
+        self.selected_account = 0;
+        self.selected_zone = 0;
+        self.zone_cache.clear();
+        self.zone_filter.clear();
+        self.record_filter.clear();
+        self.record_filters.clear();
+        self.locate.clear();
+        self.selected_ids.clear();
+        self.refresh_current()?;
+        self.push_message(
+            MessageLevel::Info,
+            format!("Profile {name}: {} account(s)", self.accounts.len()),
+        );
+        self.ask_for_missing_secrets();
+        self.ensure_onboarding_prompt();
+        Ok(())
+    }
+
+    /// Adds an empty profile called `name`, saves it, and switches to it.
+    fn create_profile(&mut self, name: &str) -> Result<()> {
+        if self.refuse_if_read_only() {
+            return Ok(());
+        }
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            self.push_message(
+                MessageLevel::Error,
+                "A profile name needs at least one character and no spaces",
+            );
+            return Ok(());
+        }
+        if self
+            .config
+            .profile_names()
+            .iter()
+            .any(|known| known == name)
+        {
+            self.push_message(
+                MessageLevel::Error,
+                format!("There's already a profile named {name}"),
+            );
+            return Ok(());
+        }
+        self.config.profiles.insert(name.to_string(), Vec::new());
+        self.save_accounts()?;
+        self.push_message(MessageLevel::Info, format!("Created profile {name}"));
+        self.switch_profile(name)
+    }
+
+    fn open_message_history(&mut self) {
+        if self.messages.is_empty() {
+            self.push_message(MessageLevel::Info, "No messages yet");
+        }
+        self.mode = Mode::MessageHistory(MessageHistory { scroll: 0 });
+    }
+
+    /// Hides or shows the Accounts panel and remembers the choice in the config.
+    fn toggle_accounts_panel(&mut self) -> Result<()> {
+        self.config.collapse_accounts = !self.config.collapse_accounts;
+        if self.config.collapse_accounts && self.focus == Focus::Accounts {
+            self.focus = Focus::Zones;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            if self.config.collapse_accounts {
+                "Accounts panel hidden (z to show; 1-9 still switch accounts)".to_string()
+            } else {
+                "Accounts panel shown".to_string()
+            },
+        );
+        self.save_accounts()
+    }
+
+    /// Switches the selected record's content between one cut-off line and wrapped lines.
+    fn toggle_wrap_content(&mut self) {
+        self.wrap_content = !self.wrap_content;
+        self.content_scroll = 0;
+        self.push_message(
+            MessageLevel::Info,
+            if self.wrap_content {
+                "Wrapping the selected record's content".to_string()
+            } else {
+                "Content cut to one line (←/→ to scroll)".to_string()
+            },
+        );
+    }
+
+    /// Scrolls the Content column sideways, stopping once the longest content on the page
+    /// fits.
+    fn scroll_content(&mut self, right: bool) {
+        if self.wrap_content {
+            return;
+        }
+        let longest = self
+            .paged_records()
+            .iter()
+            .map(|record| record.content.chars().count())
+            .max()
+            .unwrap_or(0);
+        let max_scroll = longest.saturating_sub(self.content_width.saturating_sub(1));
+        self.content_scroll = if right {
+            (self.content_scroll + CONTENT_SCROLL_STEP).min(max_scroll)
+        } else {
+            self.content_scroll.saturating_sub(CONTENT_SCROLL_STEP)
+        };
+    }
+
+    /// Switches to the account shown with `number` (counting from 1) in the Accounts panel.
+    fn jump_to_account(&mut self, number: usize) -> Result<()> {
+        if number > self.accounts.len() {
+            self.push_message(
+                MessageLevel::Info,
+                format!(
+                    "No account {number}; there are {} account(s)",
+                    self.accounts.len()
+                ),
+            );
+            return Ok(());
+        }
+        self.select_account_at(number - 1)
+    }
+
+    /// Selects the zone at `position` in the (filtered) zone list and loads its records,
+    /// unless it is already shown.
+    fn select_zone_at(&mut self, position: usize) -> Result<()> {
+        let Some(&index) = self.visible_zones().get(position) else {
+            return Ok(());
+        };
+        if index == self.selected_zone {
+            return Ok(());
+        }
+        self.selected_zone = index;
+        self.load_records(true)
+    }
+
+    /// Moves the focused list's selection `scroll_step` rows down or up, stopping at the
+    /// ends rather than wrapping.
+    fn scroll(&mut self, down: bool) -> Result<()> {
+        let step = self.config.scroll_step();
+        let moved = |from: usize, len: usize| {
+            if down {
+                (from + step).min(len.saturating_sub(1))
+            } else {
+                from.saturating_sub(step)
+            }
+        };
+        match self.focus {
+            Focus::Accounts => {
+                self.select_account_at(moved(self.selected_account, self.accounts.len()))
+            }
+            Focus::Zones => {
+                let len = self.visible_zones().len();
+                self.select_zone_at(moved(self.zone_position().unwrap_or(0), len))
+            }
+            Focus::Records => {
+                for _ in 0..step {
+                    if down {
+                        self.next_record();
+                    } else {
+                        self.previous_record();
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Selects the first (or last) entry of the focused list.
+    fn jump_to_end(&mut self, last: bool) -> Result<()> {
+        let pick = |len: usize| if last { len.saturating_sub(1) } else { 0 };
+        match self.focus {
+            Focus::Accounts => self.select_account_at(pick(self.accounts.len())),
+            Focus::Zones => self.select_zone_at(pick(self.visible_zones().len())),
+            Focus::Records => {
+                let total = self.filtered_records().len();
+                self.selected_record = pick(total);
+                self.ensure_record_visible(total);
+                Ok(())
+            }
+        }
+    }
+
+    fn next_page(&mut self) {
+        let total = self.filtered_records().len();
+        let page_count = self.record_page_count(total);
+        if page_count == 0 {
+            return;
+        }
+        self.record_page = (self.record_page + 1) % page_count;
+        self.selected_record = self.record_page * self.page_size();
+    }
+
+    fn previous_page(&mut self) {
+        let total = self.filtered_records().len();
+        let page_count = self.record_page_count(total);
+        if page_count == 0 {
+            return;
+        }
+        if self.record_page == 0 {
+            self.record_page = page_count - 1;
+        } else {
+            self.record_page -= 1;
+        }
+        self.selected_record = self.record_page * self.page_size();
+    }
+
+    /// Shows the selected account's zones from the cache while they're fresh, loading
+    /// just the records; otherwise reloads everything like `refresh_current`.
+    fn show_account(&mut self) -> Result<()> {
+        self.zone_filter.clear();
+        let ttl = self.config.zone_cache_ttl();
+        let cached = self
+            .current_account()
+            .and_then(|account| self.zone_cache.get(&account.name))
+            .filter(|cached| cached.fetched_at.elapsed() < ttl)
+            .map(|cached| (cached.listing.clone(), cached.fetched_at.elapsed()));
+        let Some((listing, age)) = cached else {
+            return self.refresh_current();
+        };
+
+        self.next_generation();
+        self.zone_total_count = listing.total_count;
+        self.zones = listing.items;
+        let name = self
+            .current_account()
+            .map(|a| a.name.clone())
+            .unwrap_or_default();
+        self.push_message(
+            MessageLevel::Info,
+            format!(
+                "{} cached zone(s) for {name} (loaded {} ago; r to refresh)",
+                self.zones.len(),
+                format_age(age)
+            ),
+        );
+        self.load_records(false)
+    }
+
+    /// Reloads the account's zones and then the selected zone's records, in the
+    /// background when a loader is running. What's shown is cleared first so nothing
+    /// acts on another account's zones while the new ones load.
+    fn refresh_current(&mut self) -> Result<()> {
+        self.remember_highlight();
+        self.next_generation();
+        self.zones.clear();
+        self.zone_total_count = None;
+        self.clear_records();
+        let Some(account) = self.current_account().cloned() else {
+            return Ok(());
+        };
+        if let Some(reason) = &account.token_error {
+            let message = format!("No API token for {}: token_cmd {reason}", account.name);
+            self.push_message(MessageLevel::Error, message);
+            return Ok(());
+        }
+        if account.lacks_secret() {
+            let message = format!(
+                "No secret for {} yet: it came from a shared config; press e to enter it",
+                account.name
+            );
+            self.push_message(MessageLevel::Error, message);
+            return Ok(());
+        }
+        self.dispatch(Load::Zones { account }, true)
+    }
+
+    fn apply_zones(&mut self, account: &Account, listing: Listing<Zone>) {
+        let pages = listing.pages_suffix();
+        self.zone_cache.insert(
+            account.name.clone(),
+            CachedZones {
+                listing: listing.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        self.zone_total_count = listing.total_count;
+        self.zones = listing.items;
+        if let Some(zone_id) = self.restore_zone.take()
+            && let Some(index) = self.zones.iter().position(|zone| zone.id == zone_id)
+        {
+            self.selected_zone = index;
+        }
+        let missing = self.start_zone.take().and_then(|name| {
+            let wanted = name.trim().trim_end_matches('.');
+            match self
+                .zones
+                .iter()
+                .position(|zone| zone.name.eq_ignore_ascii_case(wanted))
+            {
+                Some(index) => {
+                    self.selected_zone = index;
+                    self.focus = Focus::Records;
+                    None
+                }
+                None => Some(wanted.to_string()),
+            }
+        });
+        if self.selected_zone >= self.zones.len() {
+            self.selected_zone = self.zones.len().saturating_sub(1);
+        }
+        if self.zone_position().is_none()
+            && let Some(&first) = self.visible_zones().first()
+        {
+            self.selected_zone = first;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!(
+                "Loaded {} zone(s) for {}{pages}",
+                self.zones.len(),
+                account.name
+            ),
+        );
+        // The records of the first zone load next, so the window keeps this in view.
+        if let Some(name) = missing {
+            let err = anyhow!("{} has no zone named {name}", account.name);
+            self.push_message(MessageLevel::Error, err.to_string());
+            self.show_error(ErrorDetails::new(Some("Open --zone".to_string()), &err));
+        }
+    }
+
+    /// Reloads the selected zone's records, keeping the loaded ones on screen until the
+    /// first page replaces them.
+    fn refresh_records(&mut self) -> Result<()> {
+        self.remember_highlight();
+        self.next_generation();
+        self.follow_zone_filter();
+        match (
+            self.current_account().cloned(),
+            self.current_zone().cloned(),
+        ) {
+            (Some(account), Some(zone)) => {
+                let load = Load::Records {
+                    account,
+                    zone,
+                    page: 1,
+                    record_type: self.type_filter.api_type(),
+                };
+                self.dispatch(load, false)
+            }
+            _ => {
+                self.clear_records();
+                Ok(())
+            }
+        }
+    }
+
+    /// Reloads the selected zone's records in the background when a loader is running.
+    /// `announce` puts the loading status on the status line.
+    fn load_records(&mut self, announce: bool) -> Result<()> {
+        self.remember_highlight();
+        self.next_generation();
+        self.clear_records();
+        if self
+            .current_zone()
+            .is_none_or(|zone| zone.id != self.selection_zone)
+        {
+            self.selected_ids.clear();
+        }
+        let (Some(account), Some(zone)) = (
+            self.current_account().cloned(),
+            self.current_zone().cloned(),
+        ) else {
+            return Ok(());
+        };
+        let load = Load::Records {
+            account,
+            zone,
+            page: 1,
+            record_type: self.type_filter.api_type(),
+        };
+        self.dispatch(load, announce)
+    }
+
+    fn clear_records(&mut self) {
+        self.follow_zone_filter();
+        self.records.clear();
+        self.record_load = None;
+        self.record_total_count = None;
+        self.record_filter_scope = SearchScope::Local;
+        self.selected_record = 0;
+        self.record_page = 0;
+    }
+
+    /// Parks the filter of the zone that was shown and puts back the selected zone's,
+    /// once the selection has moved to another zone.
+    fn follow_zone_filter(&mut self) {
+        let zone_id = self
+            .current_zone()
+            .map(|zone| zone.id.clone())
+            .unwrap_or_default();
+        // No zone while the list reloads; the located search waits for what comes back.
+        if !zone_id.is_empty() && zone_id != self.locate_zone {
+            self.locate.clear();
+        }
+        if zone_id == self.filter_zone {
+            return;
+        }
+        let previous = std::mem::take(&mut self.record_filter);
+        if !previous.trim().is_empty() {
+            self.record_filters
+                .insert(std::mem::take(&mut self.filter_zone), previous);
+        }
+        self.record_filter = self.record_filters.remove(&zone_id).unwrap_or_default();
+        self.filter_zone = zone_id;
+    }
+
+    /// Drops the shown zone's text filter; other zones keep theirs.
+    fn clear_record_filter(&mut self) {
+        let selected = self.current_record().map(|r| r.id.clone());
+        self.record_filter.clear();
+        self.record_filter_scope = SearchScope::Local;
+        self.select_record(selected.as_deref());
+        self.push_message(MessageLevel::Info, "Record filter cleared");
+    }
+
+    /// Starts a new generation, so fetches already in flight are abandoned and their
+    /// responses ignored.
+    fn next_generation(&mut self) {
+        self.generation += 1;
+        if let Some(loader) = &self.loader {
+            loader.cancel_before(self.generation);
+        }
+        self.is_loading = None;
+        self.record_load = None;
+    }
+
+    /// Hands `load` to the loader thread, or runs it here when there is none.
+    /// `announce` puts what it's doing on the status line.
+    fn dispatch(&mut self, load: Load, announce: bool) -> Result<()> {
+        let description = load.describe();
+        if announce {
+            self.push_message(MessageLevel::Progress, format!("{description}…"));
+        }
+        self.status_at_dispatch = self.last_message.clone();
+        self.is_loading = Some(description);
+        self.loading_panel = match load {
+            Load::Zones { .. } => Focus::Zones,
+            Load::Records { .. } | Load::Write { .. } | Load::Call { .. } => Focus::Records,
+        };
+        match &self.loader {
+            Some(loader) => {
+                let sent = loader.send(self.generation, load);
+                if sent.is_err() {
+                    self.is_loading = None;
+                    self.record_load = None;
+                }
+                sent
+            }
+            None => {
+                let result = load.run(&mut *self.backend());
+                self.apply_loaded(self.generation, result)
+            }
+        }
+    }
+
+    /// Hands the call behind an action on the current account to the loader thread, or
+    /// runs it here when there is none.
+    fn start_call(&mut self, call: Call) -> Result<()> {
+        match self.current_account().cloned() {
+            Some(account) => self.start_call_for(account, call),
+            None => Ok(()),
+        }
+    }
+
+    /// Like `start_call`, for `account`. A second call waits for the first to finish.
+    fn start_call_for(&mut self, account: Account, call: Call) -> Result<()> {
+        if let Some(busy) = &self.calling {
+            let message = format!("Still busy: {busy}");
+            self.push_message(MessageLevel::Info, message);
+            return Ok(());
+        }
+        let description = call.describe(&account);
+        self.push_message(MessageLevel::Progress, format!("{description}…"));
+        self.calling = Some(description);
+        let load = Load::Call {
+            account,
+            call: Box::new(call),
+        };
+        match &self.loader {
+            Some(loader) => {
+                let sent = loader.send(self.generation, load);
+                if sent.is_err() {
+                    self.calling = None;
+                }
+                sent
+            }
+            None => {
+                let result = load.run(&mut *self.backend());
+                self.apply_loaded(self.generation, result)
+            }
+        }
+    }
+
+    /// Carries on with the action a finished call was for.
+    fn apply_called(&mut self, account: Account, called: Called) -> Result<()> {
+        match called {
+            Called::Verified(verified) => self.show_verified(&account, verified),
+            Called::AccountChecked { token, choices } => {
+                return self.account_checked(account, token, choices);
+            }
+            Called::Searched {
+                zone,
+                query,
+                result,
+            } => self.show_search_matches(&zone, &query, result?),
+            Called::RecordsDeleted {
+                zone,
+                records,
+                changed,
+                result,
+            } => return self.records_deleted(&account, &zone, records, changed, result),
+            Called::Exported { zone, result } => self.zone_exported(&zone, result),
+            Called::ZoneCreated(result) => return self.zone_created(result?),
+            Called::Counted { zone, result } => self.confirm_delete_zone(zone, result?),
+            Called::ZoneDeleted { zone, result } => {
+                result?;
+                return self.zone_deleted(&zone);
+            }
+            Called::Imported { zone, result } => return self.zone_imported(&zone, result?),
+            Called::Planned {
+                zone,
+                desired,
+                prune,
+                result,
+            } => {
+                let planned = self.plan_apply(&zone, &desired, prune, result);
+                self.show_apply_plan(&zone, planned);
+            }
+            Called::Dnssec { zone, result } => self.show_zone_detail(zone, result),
+            Called::DnssecSet {
+                zone,
+                enabled,
+                result,
+            } => self.dnssec_set(&zone, enabled, result?),
+            Called::AuditLog {
+                zone,
+                record_id,
+                result,
+            } => self.show_record_history(&zone, &record_id, result),
+        }
+        Ok(())
+    }
+
+    fn apply_loaded(&mut self, generation: u64, result: Result<Loaded>) -> Result<()> {
+        // Writes and calls are settled whatever happened since; the user asked for them.
+        let result = match result {
+            Ok(Loaded::Written { write_id, result }) => {
+                return self.apply_written(write_id, result);
+            }
+            Ok(Loaded::Called { account, called }) => {
+                self.calling = None;
+                return self.apply_called(account, *called);
+            }
+            other => other,
+        };
+        if generation != self.generation {
+            return Ok(());
+        }
+        // Keep anything newer said on the status line while the fetch ran.
+        let announce =
+            self.is_loading.take().is_some() && self.status_at_dispatch == self.last_message;
+        let previous = (!announce).then(|| self.last_message.clone());
+        let zones_loaded = matches!(result, Ok(Loaded::Zones { .. }));
+
+        match result {
+            Err(err) => {
+                self.record_load = None;
+                return Err(err);
+            }
+            Ok(Loaded::Zones { account, listing }) => self.apply_zones(&account, listing),
+            Ok(Loaded::Records {
+                account,
+                zone,
+                record_type,
+                page,
+            }) => self.apply_record_page(account, zone, record_type, page),
+            Ok(Loaded::Written { .. } | Loaded::Called { .. }) => {
+                unreachable!("writes and calls are applied above")
+            }
+        }
+        if let Some(previous) = previous {
+            self.restore_message(previous);
+        }
+
+        if zones_loaded {
+            self.load_records(announce)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Applies a page of records: the first replaces what's loaded and starts paging
+    /// through the rest, later ones are merged in.
+    fn apply_record_page(
+        &mut self,
+        account: Account,
+        zone: Zone,
+        record_type: Option<&'static str>,
+        page: Page<DnsRecord>,
+    ) {
+        if page.page > 1 {
+            self.apply_later_record_page(page);
+            return;
+        }
+        self.reselect = self.reselect.take().filter(|keep| keep.zone_id == zone.id);
+
+        self.record_total_count = page.total_count;
+        self.records = page.items;
+        self.overlay_pending_writes();
+        if page.total_pages > 1 {
+            let total_pages = page.total_pages.min(CF_MAX_PAGES);
+            self.push_message(
+                MessageLevel::Progress,
+                format!("Loading records for {}… page 1/{total_pages}", zone.name),
+            );
+            self.record_load = Some(RecordLoad {
+                account,
+                zone,
+                record_type,
+                next_page: 2,
+                total_pages,
+                server_pages: page.total_pages,
+            });
+        } else {
+            self.record_load = None;
+            self.push_message(
+                MessageLevel::Info,
+                format!("{} record(s) in {}", self.records.len(), zone.name),
+            );
+        }
+        // Server matches merged by an earlier search are gone after a reload.
+        self.record_filter_scope = SearchScope::Local;
+        self.selected_record = 0;
+        self.record_page = 0;
+        if let Some(keep) = &self.reselect {
+            self.selected_record = keep.index;
+        }
+        self.find_highlight();
+    }
+
+    /// Notes the highlighted record before its zone is reloaded, so the reload can put
+    /// the highlight back instead of starting at the top. A reload of another zone
+    /// starts at the top as before.
+    fn remember_highlight(&mut self) {
+        if self
+            .current_zone()
+            .is_none_or(|zone| zone.id != self.filter_zone)
+        {
+            return;
+        }
+        let Some(record) = self.current_record() else {
+            return;
+        };
+        self.reselect = Some(Reselect {
+            zone_id: self.filter_zone.clone(),
+            record_id: record.id.clone(),
+            index: self.selected_record,
+        });
+    }
+
+    /// Moves the highlight onto the remembered record once a page brings it in. Until
+    /// then, and for good if the record is gone, it stays at the remembered position.
+    fn find_highlight(&mut self) {
+        let Some(keep) = &self.reselect else {
+            return;
+        };
+        let id = keep.record_id.clone();
+        let found = self.filtered_records().iter().any(|r| r.id == id);
+        self.select_record(Some(&id));
+        if found || self.record_load.is_none() {
+            self.reselect = None;
+        }
+    }
+
+    /// Abandons the fetch in flight; its response is dropped when it arrives. Each
+    /// response is applied whole, so a cancelled multi-page load keeps the pages that
+    /// already landed, shown as a partial load.
+    fn cancel_loading(&mut self) {
+        if !self.is_fetching() {
+            return;
+        }
+        let paging = self.record_load.is_some();
+        self.next_generation();
+
+        self.push_message(
+            MessageLevel::Info,
+            match (self.loading_panel, paging) {
+                (Focus::Records, _) | (_, true) => {
+                    let zone = self
+                        .current_zone()
+                        .map(|z| z.name.clone())
+                        .unwrap_or_default();
+                    match self.record_total_count {
+                        Some(total) if paging && total as usize > self.records.len() => format!(
+                            "Cancelled loading records for {zone} ({} of {} loaded)",
+                            format_count(self.records.len() as u64),
+                            format_count(u64::from(total))
+                        ),
+                        _ => format!("Cancelled loading records for {zone}"),
+                    }
+                }
+                _ => {
+                    let account = self
+                        .current_account()
+                        .map(|a| a.name.clone())
+                        .unwrap_or_default();
+                    format!("Cancelled loading zones for {account}")
+                }
+            },
+        );
+    }
+
+    /// A spinner frame when a fetch for `panel` is in flight, or for the records panel
+    /// while writes are being confirmed.
+    fn panel_spinner(&self, panel: Focus) -> Option<char> {
+        let fetching = self.is_loading.is_some() && self.loading_panel == panel;
+        let saving = panel == Focus::Records && !self.pending_writes.is_empty();
+        (fetching || saving).then(spinner_frame)
+    }
+
+    fn is_fetching(&self) -> bool {
+        self.is_loading.is_some() || self.record_load.is_some()
+    }
+
+    /// Fetching, running an action's call, or waiting for record writes to be confirmed.
+    fn is_busy(&self) -> bool {
+        self.is_fetching()
+            || self.calling.is_some()
+            || !self.pending_writes.is_empty()
+            || self.bulk.is_some()
+    }
+
+    /// Whether the record row with `id` shows a change the backend hasn't confirmed yet.
+    fn is_pending_row(&self, id: &str) -> bool {
+        self.pending_writes
+            .iter()
+            .any(|p| p.provisional.as_ref().is_some_and(|r| r.id == id))
+    }
+
+    /// Whether the zone has records that haven't been loaded, so a local filter may miss some.
+    fn records_partial(&self) -> bool {
+        self.record_load.is_some()
+            || self
+                .record_total_count
+                .is_some_and(|total| total as usize > self.records.len())
+    }
+
+    /// Adds records not already loaded (by id), returning how many were new.
+    fn merge_records(&mut self, items: Vec<DnsRecord>) -> usize {
+        let known: HashSet<String> = self.records.iter().map(|r| r.id.clone()).collect();
+        let before = self.records.len();
+        self.records
+            .extend(items.into_iter().filter(|r| !known.contains(&r.id)));
+        self.records.len() - before
+    }
+
+    fn apply_search(&mut self, prompt: SearchPrompt) -> Result<()> {
+        self.record_filter = prompt.text;
+        self.record_filter_scope = prompt.scope;
+        self.record_page = 0;
+        self.selected_record = 0;
+
+        let needle = self.record_filter.trim().to_string();
+        if prompt.scope == SearchScope::Server
+            && let Some(mode) = RecordQuery::mode(&needle)
+        {
+            self.record_filter_scope = SearchScope::Local;
+            self.push_message(
+                MessageLevel::Info,
+                format!("The API has no {mode} search; filtering the loaded records instead"),
+            );
+            return Ok(());
+        }
+        if prompt.scope == SearchScope::Local || needle.is_empty() {
+            return Ok(());
+        }
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        self.start_call(Call::Search {
+            zone,
+            query: needle,
+        })
+    }
+
+    /// Merges a server search's matches into the loaded records, if their zone is still shown.
+    fn show_search_matches(&mut self, zone: &Zone, needle: &str, found: Vec<DnsRecord>) {
+        if self.current_zone().is_none_or(|shown| shown.id != zone.id) {
+            return;
+        }
+        let matched = found.len();
+        let added = self.merge_records(found);
+        self.push_message(MessageLevel::Info, format!(
+            "Server search: {matched} match(es) for \"{needle}\" in {} ({added} not loaded before)",
+            zone.name
+        ));
+    }
+
+    /// Continues background work between key presses: one page of a multi-page record fetch.
+    /// Applies finished background fetches, then asks for the next page of records
+    /// if a multi-page load is under way and nothing is in flight.
+    fn tick(&mut self) -> Result<()> {
+        while let Some((generation, result)) = match &self.loader {
+            Some(loader) => loader.try_recv()?,
+            None => None,
+        } {
+            self.apply_loaded(generation, result)?;
+        }
+
+        if self
+            .bulk
+            .as_ref()
+            .is_some_and(|job| job.in_flight.is_none())
+        {
+            self.advance_bulk()?;
+        }
+
+        if self.is_loading.is_none()
+            && let Some(load) = &self.record_load
+        {
+            let load = Load::Records {
+                account: load.account.clone(),
+                zone: load.zone.clone(),
+                page: load.next_page,
+                record_type: load.record_type,
+            };
+            self.dispatch(load, false)?;
+        }
+        self.check_config_file();
+        Ok(())
+    }
+
+    fn apply_later_record_page(&mut self, page: Page<DnsRecord>) {
+        let Some(load) = self.record_load.take() else {
+            return;
+        };
+        let fetched_empty = page.items.is_empty();
+        self.merge_records(page.items);
+
+        if fetched_empty || load.next_page >= load.total_pages {
+            let stopped = if load.server_pages > load.total_pages {
+                format!(
+                    " (stopped after {} of {} pages)",
+                    load.next_page, load.server_pages
+                )
+            } else {
+                format!(" ({} pages)", load.next_page)
+            };
+            self.push_message(
+                MessageLevel::Info,
+                format!(
+                    "{} record(s) in {}{stopped}",
+                    self.records.len(),
+                    load.zone.name
+                ),
+            );
+        } else {
+            self.push_message(
+                MessageLevel::Progress,
+                format!(
+                    "Loading records for {}… page {}/{} ({} so far)",
+                    load.zone.name,
+                    load.next_page,
+                    load.total_pages,
+                    self.records.len()
+                ),
+            );
+            self.record_load = Some(RecordLoad {
+                next_page: load.next_page + 1,
+                ..load
+            });
+        }
+        self.find_highlight();
+    }
+
+    /// The text records are filtered by: the search prompt's draft while it's open,
+    /// otherwise the applied filter.
+    fn record_filter_text(&self) -> &str {
+        match &self.mode {
+            Mode::Searching(prompt) if !prompt.locate => &prompt.text,
+            _ => &self.record_filter,
+        }
+    }
+
+    /// The text `n`/`N` step through matches of: the search prompt's draft while it's
+    /// open in locate mode, otherwise the applied one.
+    fn locate_text(&self) -> &str {
+        match &self.mode {
+            Mode::Searching(prompt) if prompt.locate => &prompt.text,
+            _ => &self.locate,
+        }
+    }
+
+    fn is_locating(&self) -> bool {
+        !self.locate.trim().is_empty()
+    }
+
+    /// Positions in `filtered_records` of the records the located search matches,
+    /// worked out afresh each time so refreshes and edits are always reflected.
+    fn locate_matches(&self) -> Vec<usize> {
+        let Ok(Some(query)) = RecordQuery::parse(self.locate_text()) else {
+            return Vec::new();
+        };
+        self.filtered_records()
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| query.score(record).is_some())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Highlights the next match after the selected record (the previous one going
+    /// backward), wrapping around the ends of the list.
+    fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.locate_matches();
+        let current = self.selected_record;
+        let next = if forward {
+            matches.iter().find(|&&i| i > current)
+        } else {
+            matches.iter().rev().find(|&&i| i < current)
+        };
+        let wrapped = if forward {
+            matches.first()
+        } else {
+            matches.last()
+        };
+        match (next, wrapped) {
+            (Some(&index), _) => self.selected_record = index,
+            (None, Some(&index)) => {
+                self.selected_record = index;
+                self.push_message(
+                    MessageLevel::Info,
+                    if forward {
+                        "Search wrapped to the top"
+                    } else {
+                        "Search wrapped to the bottom"
+                    },
+                );
+            }
+            (None, None) => {
+                self.push_message(
+                    MessageLevel::Info,
+                    format!("No records match \"{}\"", self.locate_text().trim()),
+                );
+                return;
+            }
+        }
+        self.select_record(None);
+    }
+
+    /// Keeps `text` for `n`/`N` and highlights the first match from the selection on.
+    fn apply_locate(&mut self, text: String) {
+        self.locate = text;
+        self.locate_zone = self.filter_zone.clone();
+        if !self.is_locating() {
+            return;
+        }
+        if self.locate_matches().contains(&self.selected_record) {
+            return;
+        }
+        self.jump_to_match(true);
+    }
+
+    fn clear_locate(&mut self) {
+        self.locate.clear();
+        self.push_message(MessageLevel::Info, "Located search cleared");
+    }
+
+    /// Re-clamps the selection and page to what the search prompt's draft shows,
+    /// keeping the record highlighted when the prompt opened if it's still there.
+    /// In fuzzy mode the best match is highlighted instead.
+    fn sync_search(&mut self) {
+        let Mode::Searching(prompt) = &self.mode else {
+            return;
+        };
+        if prompt.locate {
+            let selected = prompt.selected.clone();
+            self.select_record(selected.as_deref());
+            if !self.locate_matches().contains(&self.selected_record) {
+                let (from, matches) = (self.selected_record, self.locate_matches());
+                if let Some(&index) = matches.iter().find(|&&i| i >= from).or(matches.first()) {
+                    self.selected_record = index;
+                    self.select_record(None);
+                }
+            }
+            return;
+        }
+        if RecordQuery::mode(&prompt.text) == Some("fuzzy") {
+            self.selected_record = 0;
+            self.select_record(None);
+            return;
+        }
+        let selected = prompt.selected.clone();
+        self.select_record(selected.as_deref());
+    }
+
+    fn filtered_records(&self) -> Vec<&DnsRecord> {
+        let typed = self
+            .records
+            .iter()
+            .filter(|r| self.type_filter.matches(&r.record_type));
+        let query = RecordQuery::parse(self.record_filter_text());
+        let mut scored: Vec<(i64, &DnsRecord)> = match &query {
+            Ok(None) => typed.map(|r| (0, r)).collect(),
+            Ok(Some(query)) => typed.filter_map(|r| Some((query.score(r)?, r))).collect(),
+            // The prompt shows why; an applied filter is always valid.
+            Err(_) => Vec::new(),
+        };
+        let sort = self.record_sort();
+        if sort.column != SortColumn::Api {
+            scored.sort_by(|(_, a), (_, b)| sort.compare(a, b));
+        }
+        // Best fuzzy matches first; the sort above still orders equal scores.
+        if let Ok(Some(RecordQuery::Fuzzy(_))) = query {
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        scored.into_iter().map(|(_, record)| record).collect()
+    }
+
+    fn record_sort(&self) -> RecordSort {
+        self.current_zone()
+            .and_then(|zone| self.record_sorts.get(&zone.id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Changes the current zone's sort order, keeping the selected record selected.
+    fn set_record_sort(&mut self, change: impl FnOnce(&mut RecordSort)) {
+        let Some(zone_id) = self.current_zone().map(|zone| zone.id.clone()) else {
+            return;
+        };
+        let selected = self.current_record().map(|r| r.id.clone());
+        let mut sort = self.record_sort();
+        change(&mut sort);
+        self.record_sorts.insert(zone_id, sort);
+        self.select_record(selected.as_deref());
+        self.push_message(
+            MessageLevel::Info,
+            match sort.column.header() {
+                Some(label) => format!(
+                    "Sorted by {} ({})",
+                    label.to_lowercase(),
+                    if sort.descending {
+                        "descending"
+                    } else {
+                        "ascending"
+                    }
+                ),
+                None => "Records in API order".to_string(),
+            },
+        );
+    }
+
+    /// Moves to the next quick type filter and reloads, so the API only returns that type.
+    fn cycle_type_filter(&mut self) -> Result<()> {
+        let previous = self.type_filter;
+        self.type_filter = previous.next();
+        // Narrowing from All to one type or to "other" can reuse what's loaded only when
+        // the zone is fully loaded; otherwise ask the API.
+        if self.type_filter.api_type().is_some()
+            || previous.api_type().is_some()
+            || self.records_partial()
+        {
+            self.refresh_records()?;
+        } else {
+            self.selected_record = 0;
+            self.record_page = 0;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!(
+                "Showing {} ({} record(s))",
+                self.type_filter.label(),
+                self.filtered_records().len()
+            ),
+        );
+        Ok(())
+    }
+
+    fn update_record_page_size(&mut self, area_height: u16) {
+        // Table uses one row for the header and two for borders.
+        let usable_rows = (area_height as usize).saturating_sub(3);
+        // A wrapped row can be any record on the page, so leave room for the tallest.
+        let extra_rows = if self.wrap_content {
+            self.filtered_records()
+                .iter()
+                .map(|record| wrap_chars(&record.content, self.content_width).len())
+                .max()
+                .unwrap_or(1)
+                - 1
+        } else {
+            0
+        };
+        self.record_page_size = usable_rows.saturating_sub(extra_rows).max(1);
+        // Whatever changed the list since the last frame, the highlight stays on it.
+        self.select_record(None);
+    }
+
+    fn page_size(&self) -> usize {
+        self.record_page_size.max(1)
+    }
+
+    fn record_page_count(&self, total: usize) -> usize {
+        if total == 0 {
+            0
+        } else {
+            total.div_ceil(self.page_size())
+        }
+    }
+
+    fn paged_records(&self) -> Vec<&DnsRecord> {
+        let filtered = self.filtered_records();
+        if filtered.is_empty() {
+            return filtered;
+        }
+        let start = self.record_page * self.page_size();
+        let end = (start + self.page_size()).min(filtered.len());
+        filtered[start..end].to_vec()
+    }
+
+    fn ensure_record_visible(&mut self, total: usize) {
+        if total == 0 {
+            self.record_page = 0;
+            self.selected_record = 0;
+            return;
+        }
+        let page = self.selected_record / self.page_size();
+        self.record_page = page.min(self.record_page_count(total).saturating_sub(1));
+    }
+
+    fn status_message(&self) -> (String, String) {
+        let help = match (self.loose_config_mode, &self.read_only) {
+            (Some(mode), _) => format!(
+                "Warning: the config file is mode {mode:o} and other users can read your tokens. Press F to make it 600."
+            ),
+            (None, Some(holder)) => format!(
+                "Read-only: {holder} has the config open, so accounts can't be added, edited or removed here."
+            ),
+            (None, None) => "?: help  q: quit  a: add account  v: verify  r: refresh  Tab/Shift+Tab/h/l: focus  ↑/↓/j/k: move  /: search  n/e/d: new/edit/del  PgUp/PgDn: pages  gg/G: first/last".to_string(),
+        };
+        // Only worth a mention once there's more than the one profile.
+        let profile = if self.config.profiles.is_empty() {
+            String::new()
+        } else {
+            format!("Profile: {} | ", self.config.profile_name())
+        };
+        if self.accounts.is_empty() {
+            return (
+                help,
+                format!(
+                    "{profile}No accounts configured. Press 'a' to add one. Tokens are stored locally."
+                ),
+            );
+        }
+
+        let account_name = self
+            .current_account()
+            .map(|a| a.name.as_str())
+            .unwrap_or("No account");
+        let zone_name = self
+            .current_zone()
+            .map(|z| z.name.as_str())
+            .unwrap_or("No zone");
+        let account_index = if self.accounts.is_empty() {
+            0
+        } else {
+            self.selected_account + 1
+        };
+        let zone_index = if self.zones.is_empty() {
+            0
+        } else {
+            self.selected_zone + 1
+        };
+        let zone_total = self.zones.len().max(1);
+
+        let filtered_count = self.filtered_records().len();
+        let page_count = self.record_page_count(filtered_count).max(1);
+        let filter_suffix = if self.record_filter.trim().is_empty() {
+            String::new()
+        } else {
+            let kind = match RecordQuery::mode(&self.record_filter) {
+                Some(mode) => format!(" by {mode}"),
+                None => String::new(),
+            };
+            format!(
+                " filtered{kind} \"{}\", {}; Esc clears",
+                self.record_filter.trim(),
+                self.record_filter_scope.label()
+            )
+        };
+        let locate_suffix = if self.is_locating() {
+            let matches = self.locate_matches();
+            let at = match matches.iter().position(|&i| i == self.selected_record) {
+                Some(at) => format!("match {}/{}", at + 1, matches.len()),
+                None => format!("{} match(es)", matches.len()),
+            };
+            format!(", {at} for \"{}\" (n/N)", self.locate.trim())
+        } else {
+            String::new()
+        };
+        let zone_truncation = truncation_note(self.zones.len(), self.zone_total_count);
+        let notice = self
+            .notices
+            .as_ref()
+            .and_then(|board| board.lock().ok()?.clone());
+        let mut message = match notice {
+            Some(notice) => format!("{notice} | {}", self.last_message),
+            None => self.last_message.clone(),
+        };
+        if let Some(operation) = &self.is_loading {
+            let spinner = spinner_frame();
+            message = if self.last_message == format!("{operation}…") {
+                format!("{spinner} {message}")
+            } else {
+                format!("{spinner} {operation}… | {message}")
+            };
+        }
+        let record_truncation = truncation_note(self.records.len(), self.record_total_count);
+        if let Some(rate) = self.rate_status() {
+            message = format!("{message} | {rate}");
+        }
+
+        (
+            help,
+            format!(
+                "{}Account: {} ({}/{}) | Zone: {} ({}/{}{}) | Records: page {}/{} ({} shown{}{}{}) | {}",
+                profile,
+                account_name,
+                account_index,
+                self.accounts.len(),
+                zone_name,
+                zone_index,
+                zone_total,
+                zone_truncation,
+                self.record_page + 1,
+                page_count,
+                self.paged_records().len(),
+                filter_suffix,
+                record_truncation,
+                locate_suffix,
+                message
+            ),
+        )
+    }
+
+    /// API usage for the status line, when the backend counts it.
+    fn rate_status(&self) -> Option<String> {
+        let mut stats = self.rate.as_ref()?.lock().ok()?;
+        Some(stats.rate_status(Instant::now()))
+    }
+
+    fn start_add_account(&mut self) {
+        if self.refuse_if_read_only() {
+            return;
+        }
+        self.mode = Mode::AddingAccount(AccountForm::default());
+        self.push_message(
+            MessageLevel::Info,
+            if self.accounts.is_empty() {
+                "Add a Cloudflare API token for this account, or quit and run nyxflare --import-credentials to use wrangler's or flarectl's"
+            } else {
+                "Add a Cloudflare API token for this account"
+            },
+        );
+    }
+
+    /// Verifies the new account's credentials before it is saved.
+    fn finish_add_account(&mut self, account: Account) -> Result<()> {
+        self.start_call_for(account, Call::AddAccount)
+    }
+
+    /// Saves the account once its credentials verify; otherwise the form stays open
+    /// with the reason so a truncated token is never written to the config. Nothing is
+    /// saved if the form was closed while they were checked.
+    ///
+    /// Without an account ID, the accounts the credentials can reach were looked up: a
+    /// single one is filled in, several open a picker.
+    fn account_checked(
+        &mut self,
+        mut account: Account,
+        verified: Result<TokenStatus>,
+        mut choices: Vec<CloudflareAccount>,
+    ) -> Result<()> {
+        if !matches!(self.mode, Mode::AddingAccount(_)) {
+            return Ok(());
+        }
+        let token = match verified {
+            Ok(token) if token.is_active() => token,
+            Ok(token) => {
+                self.push_message(
+                    MessageLevel::Error,
+                    format!("Not saved: {}", token.describe()),
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                self.push_message(
+                    MessageLevel::Error,
+                    format!("Not saved, verification failed: {err:#}"),
+                );
+                return Ok(());
+            }
+        };
+
+        if choices.len() == 1 {
+            let choice = choices.remove(0);
+            account.account_id = Some(choice.id);
+            account.account_name = Some(choice.name);
+        } else if choices.len() > 1 {
+            self.push_message(MessageLevel::Info, "Choose the Cloudflare account to use");
+            self.mode = Mode::PickAccount(AccountPicker {
+                account,
+                token,
+                choices,
+                selected: 0,
+            });
+            return Ok(());
+        }
+
+        self.save_new_account(account, &token)
+    }
+
+    fn start_edit_account(&mut self) {
+        if self.refuse_if_read_only() {
+            return;
+        }
+        let Some(account) = self.current_account() else {
+            self.push_message(MessageLevel::Info, "No account selected");
+            return;
+        };
+        if !matches!(account.provider, Provider::Cloudflare | Provider::Rfc2136) {
+            let message = format!(
+                "{} accounts can't be edited here; change {} in {}",
+                account.provider.label(),
+                account.name,
+                self.config_path.display()
+            );
+            self.push_message(MessageLevel::Info, message);
+            return;
+        }
+        let form = AccountForm::for_account(self.selected_account, account);
+        self.push_message(MessageLevel::Info, format!("Editing account {}", form.name));
+        self.mode = Mode::AddingAccount(form);
+    }
+
+    /// Replaces the account at `index` with what the edit form built and reloads it.
+    fn finish_edit_account(&mut self, index: usize, mut account: Account) -> Result<()> {
+        let Some(original) = self.accounts.get(index).cloned() else {
+            return Ok(());
+        };
+        if account.account_id == original.account_id {
+            account.account_name = original.account_name;
+        }
+        // An untouched secret keeps pointing at where it came from.
+        if account.api_token == original.api_token {
+            account.token_ref = original.token_ref;
+            account.token_cmd = original.token_cmd;
+        }
+        self.zone_cache.remove(&original.name);
+        let name = account.name.clone();
+        self.accounts[index] = account;
+        self.selected_account = index;
+        self.selected_zone = 0;
+        self.zone_filter.clear();
+        self.mode = Mode::Normal;
+        self.save_accounts()?;
+        self.refresh_current()?;
+        self.push_message(MessageLevel::Info, format!("Saved account {name}"));
+        self.next_missing_secret();
+        Ok(())
+    }
+
+    /// Queues every account of the profile in use that came from a shared config
+    /// without its secret, and opens the edit form for the first.
+    fn ask_for_missing_secrets(&mut self) {
+        self.missing_secrets = self
+            .accounts
+            .iter()
+            .filter(|account| account.lacks_secret())
+            .map(|account| account.name.clone())
+            .collect();
+        self.next_missing_secret();
+    }
+
+    /// Opens the edit form for the next queued account still without its secret; ones
+    /// the form can't edit are only named, with where to put their secret.
+    fn next_missing_secret(&mut self) {
+        if !matches!(self.mode, Mode::Normal) || self.read_only.is_some() {
+            return;
+        }
+        while let Some(name) = self.missing_secrets.pop_front() {
+            let Some(index) = self
+                .accounts
+                .iter()
+                .position(|account| account.name == name && account.lacks_secret())
+            else {
+                continue;
+            };
+            let account = &self.accounts[index];
+            if !matches!(account.provider, Provider::Cloudflare | Provider::Rfc2136) {
+                let message = format!(
+                    "{name} came from a shared config without its secret; set api_token in {}",
+                    self.config_path.display()
+                );
+                self.push_message(MessageLevel::Info, message);
+                continue;
+            }
+            let mut form = AccountForm::for_account(index, account);
+            form.field_index = 2;
+            if let Err(err) = self.select_account_at(index) {
+                self.push_message(MessageLevel::Error, format!("{err:#}"));
+            }
+            self.mode = Mode::AddingAccount(form);
+            self.push_message(
+                MessageLevel::Info,
+                format!("{name} came from a shared config: enter its secret, or Esc to skip"),
+            );
+            return;
+        }
+    }
+
+    fn ask_delete_account(&mut self) {
+        if self.refuse_if_read_only() {
+            return;
+        }
+        let Some(account) = self.current_account().cloned() else {
+            self.push_message(MessageLevel::Info, "No account selected");
+            return;
+        };
+        if !self.pending_writes.is_empty() || self.bulk.is_some() {
+            self.push_message(
+                MessageLevel::Info,
+                "Wait for the changes in progress to finish first",
+            );
+            return;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!("Remove account {}?", account.name),
+        );
+        self.mode = Mode::ConfirmDeleteAccount(ConfirmDeleteAccount {
+            index: self.selected_account,
+            account,
+            require_typed: self.policy.requires_typed_confirmation(),
+            typed: String::new(),
+        });
+    }
+
+    /// Drops the account at `index` and everything remembered for it, saves, and shows
+    /// the account that takes its place (or onboarding, when none are left).
+    fn delete_account(&mut self, index: usize) -> Result<()> {
+        if index >= self.accounts.len() {
+            return Ok(());
+        }
+        let account = self.accounts.remove(index);
+        let mut zone_ids: Vec<String> = self
+            .zone_cache
+            .remove(&account.name)
+            .map(|cached| cached.listing.items.into_iter().map(|z| z.id).collect())
+            .unwrap_or_default();
+        if self.selected_account == index {
+            zone_ids.extend(self.zones.iter().map(|z| z.id.clone()));
+        }
+        for id in &zone_ids {
+            self.record_sorts.remove(id);
+            self.record_filters.remove(id);
+        }
+        if self.selected_account > index || self.selected_account >= self.accounts.len() {
+            self.selected_account = self.selected_account.saturating_sub(1);
+        }
+        self.selected_zone = 0;
+        self.zone_filter.clear();
+        self.selected_ids.clear();
+        self.save_accounts()?;
+        self.refresh_current()?;
+        self.push_message(
+            MessageLevel::Info,
+            format!("Removed account {}", account.name),
+        );
+        self.ensure_onboarding_prompt();
+        Ok(())
+    }
+
+    fn save_new_account(&mut self, account: Account, token: &TokenStatus) -> Result<()> {
+        let name = account.name.clone();
+        self.accounts.push(account);
+        self.selected_account = self.accounts.len().saturating_sub(1);
+        self.selected_zone = 0;
+        self.zone_filter.clear();
+        self.mode = Mode::Normal;
+        self.save_accounts()?;
+        self.refresh_current()?;
+        self.push_message(
+            MessageLevel::Info,
+            format!("Added account {name}: {}", token.describe()),
+        );
+        Ok(())
+    }
+
+    fn verify_current_account(&mut self) -> Result<()> {
+        if self.current_account().is_none() {
+            self.push_message(MessageLevel::Info, "No account selected");
+            return Ok(());
+        }
+        self.start_call(Call::Verify)
+    }
+
+    fn show_verified(&mut self, account: &Account, verified: Result<TokenStatus>) {
+        match verified {
+            Ok(token) => self.push_message(
+                MessageLevel::Info,
+                format!("{}: {}", account.name, token.describe()),
+            ),
+            Err(err) => {
+                self.push_message(
+                    MessageLevel::Error,
+                    format!("{}: verification failed: {err:#}", account.name),
+                );
+                self.show_error(ErrorDetails::new(
+                    Some(format!("Verify key for {}", account.name)),
+                    &err,
+                ));
+            }
+        }
+    }
+
+    /// Writes the selected account and zone to the state file when they've changed.
+    /// Nothing is written while the remembered (or `--zone`'s) zone is still loading, so
+    /// a quick quit doesn't forget it.
+    fn remember_selection(&mut self) {
+        if self.restore_zone.is_some() || self.start_zone.is_some() {
+            return;
+        }
+        let state = SessionState {
+            account: self.current_account().map(|account| account.name.clone()),
+            zone_id: self.current_zone().map(|zone| zone.id.clone()),
+        };
+        if state == self.saved_state {
+            return;
+        }
+        if let Err(err) = state.save(&self.state_path) {
+            self.push_message(
+                MessageLevel::Error,
+                format!("Couldn't remember the selection: {err:#}"),
+            );
+        }
+        self.saved_state = state;
+    }
+
+    /// Saves the config with the accounts shown as the current profile's; the other
+    /// profiles are written as they were loaded. A file changed by something else since
+    /// it was read is left alone, and the next tick asks which version to keep.
+    fn save_accounts(&mut self) -> Result<()> {
+        if let Some(holder) = &self.read_only {
+            return Err(anyhow!(
+                "Not saved: {holder} has {} open, so this one is read-only",
+                self.config_path.display()
+            ));
+        }
+        if config_stamp(&self.config_path) != self.config_stamp {
+            return Err(anyhow!(
+                "Not saved: {} changed outside nyxflare",
+                self.config_path.display()
+            ));
+        }
+        let mut config = self.config.clone();
+        let profile = config.profile_name().to_string();
+        *config.profile_accounts_mut(&profile) = self.accounts.clone();
+        config.save(&self.config_path)?;
+        self.config_stamp = config_stamp(&self.config_path);
+        self.saved_accounts = self.accounts.clone();
+        Ok(())
+    }
+
+    /// Picks up changes made to the config file outside nyxflare, checked every tick
+    /// while no form or prompt is open. Without unsaved account changes here the file
+    /// is reloaded; with them, a prompt asks which side to keep.
+    fn check_config_file(&mut self) {
+        if !matches!(self.mode, Mode::Normal)
+            || config_stamp(&self.config_path) == self.config_stamp
+        {
+            return;
+        }
+        let result = if self.accounts == self.saved_accounts {
+            self.reload_config()
+        } else {
+            self.read_config_file().map(|mut config| {
+                let profile = self.config.profile_name().to_string();
+                let theirs = std::mem::take(config.profile_accounts_mut(&profile));
+                self.mode = Mode::ConfigConflict(ConfigConflict {
+                    yours: account_changes(&self.saved_accounts, &self.accounts),
+                    theirs: account_changes(&self.saved_accounts, &theirs),
+                });
+            })
+        };
+        if let Err(err) = result {
+            // Not retried until the file changes again.
+            self.config_stamp = config_stamp(&self.config_path);
+            let message = format!("Not reloading {}: {err:#}", self.config_path.display());
+            self.push_message(MessageLevel::Error, message);
+        }
+    }
+
+    /// The config file as it is now, opened with the passphrase in use. `token_cmd`
+    /// secrets are carried over from the accounts they were run for, since a command
+    /// may need the terminal.
+    fn read_config_file(&self) -> Result<Config> {
+        let path = &self.config_path;
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let encrypted = is_encrypted(&text);
+        let text = if encrypted {
+            let passphrase = self
+                .config
+                .passphrase
+                .as_deref()
+                .ok_or_else(|| anyhow!("it's encrypted now; restart nyxflare to unlock it"))?;
+            open_config(&text, passphrase)?
+                .ok_or_else(|| anyhow!("the passphrase in use no longer opens it"))?
+        } else {
+            text
+        };
+        let mut config = Config::parse(path, &text)?;
+        config.resolve_token_refs(path, |name| env::var(name).ok())?;
+        if encrypted {
+            config.passphrase = self.config.passphrase.clone();
+        }
+        let known: Vec<&Account> = self
+            .accounts
+            .iter()
+            .chain(
+                self.config
+                    .account_lists()
+                    .flat_map(|(_, accounts)| accounts),
+            )
+            .collect();
+        for account in config
+            .account_lists_mut()
+            .flat_map(|(_, accounts)| accounts)
+        {
+            let Some(command) = &account.token_cmd else {
+                continue;
+            };
+            match known.iter().find(|known| {
+                known.name == account.name && known.token_cmd.as_ref() == Some(command)
+            }) {
+                Some(known) => {
+                    account.api_token = known.api_token.clone();
+                    account.token_error = known.token_error.clone();
+                }
+                None => {
+                    account.token_error = Some("is new; restart nyxflare to run it".to_string());
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Replaces the accounts and settings with the config file's, keeping the selected
+    /// account by name and the zones of accounts that didn't change.
+    fn reload_config(&mut self) -> Result<()> {
+        self.config_stamp = config_stamp(&self.config_path);
+        let mut config = self.read_config_file()?;
+        let mut profile = self.config.profile_name().to_string();
+        if !config.profile_names().contains(&profile) {
+            let message =
+                format!("Profile {profile} is gone from the config; using {DEFAULT_PROFILE}");
+            self.push_message(MessageLevel::Info, message);
+            profile = DEFAULT_PROFILE.to_string();
+        }
+        config.profile = (profile != DEFAULT_PROFILE).then(|| profile.clone());
+        let accounts = std::mem::take(config.profile_accounts_mut(&profile));
+        let changes = account_changes(&self.saved_accounts, &accounts);
+        for old in &self.accounts {
+            if !accounts.contains(old) {
+                self.zone_cache.remove(&old.name);
+            }
+        }
+        let current = self.current_account().cloned();
+        self.config = config;
+        self.accounts = accounts;
+        self.saved_accounts = self.accounts.clone();
+        self.selected_account = current
+            .as_ref()
+            .and_then(|current| {
+                self.accounts
+                    .iter()
+                    .position(|account| account.name == current.name)
+            })
+            .unwrap_or(0);
+        if self.current_account() != current.as_ref() {
+            self.selected_zone = 0;
+            self.zone_filter.clear();
+            self.refresh_current()?;
+        }
+        let changes = match changes.as_slice() {
+            [] => "no account changes".to_string(),
+            changes => changes.join(", "),
+        };
+        let message = format!(
+            "Reloaded {}, changed outside nyxflare: {changes}",
+            self.config_path.display()
+        );
+        self.push_message(MessageLevel::Info, message);
+        self.ensure_onboarding_prompt();
+        Ok(())
+    }
+
+    fn ensure_onboarding_prompt(&mut self) {
+        if self.accounts.is_empty() {
+            self.start_add_account();
+        }
+    }
+
+    /// The highlighted record, or `None` with a status message saying there isn't one,
+    /// for keys that act on it.
+    fn highlighted_record(&mut self) -> Option<DnsRecord> {
+        let record = self.current_record().cloned();
+        if record.is_none() {
+            self.push_message(MessageLevel::Info, "No record selected");
+        }
+        record
+    }
+
+    fn start_record_form(&mut self, is_edit: bool) {
+        let (draft, target_id, account_defaults) = if is_edit {
+            let Some(record) = self.highlighted_record() else {
+                return;
+            };
+            (RecordDraft::from_record(&record), Some(record.id), None)
+        } else {
+            let (draft, seeded) = self.new_record_draft();
+            (draft, None, seeded)
+        };
+        self.mode = Mode::RecordForm(RecordForm {
+            draft,
+            field_index: 0,
+            is_edit,
+            target_id,
+            cloned_from: None,
+            account_defaults,
+        });
+        self.push_message(
+            MessageLevel::Info,
+            if is_edit {
+                "Editing DNS record".to_string()
+            } else {
+                "Create DNS record".to_string()
+            },
+        );
+    }
+
+    /// A blank record with the current account's TTL and proxy defaults, and which of
+    /// them it took, to say so in the form.
+    fn new_record_draft(&self) -> (RecordDraft, Option<String>) {
+        let mut draft = RecordDraft::default();
+        let mut seeded = Vec::new();
+        let account = self.current_account();
+        if let Some(ttl) = account.and_then(|a| a.default_ttl) {
+            draft.ttl = ttl.to_string();
+            seeded.push(format!("TTL {}", ttl_label(ttl)));
+        }
+        if let Some(proxied) = account.and_then(|a| a.default_proxied)
+            && self.has_proxy()
+        {
+            draft.proxied = proxied;
+            seeded.push(if proxied { "proxied" } else { "DNS only" }.to_string());
+        }
+        (draft, (!seeded.is_empty()).then(|| seeded.join(", ")))
+    }
+
+    /// Opens a create form prefilled from the highlighted record, ready for a new name.
+    fn start_clone_record(&mut self) {
+        let Some(record) = self.highlighted_record() else {
+            return;
+        };
+        let form = RecordForm {
+            draft: RecordDraft::from_record(&record),
+            field_index: 0,
+            is_edit: false,
+            target_id: None,
+            cloned_from: Some(record.name.clone()),
+            account_defaults: None,
+        };
+        self.push_message(MessageLevel::Info, format!("Cloning {}", record.name));
+        self.mode = Mode::RecordForm(form);
+    }
+
+    fn ask_delete_record(&mut self) {
+        if let Some(record) = self.highlighted_record() {
+            let danger = self
+                .current_zone()
+                .and_then(|zone| deletion_danger(&record, &zone.name));
+            self.mode = Mode::ConfirmDelete(ConfirmDelete {
+                record_id: record.id.clone(),
+                record_name: record.name.clone(),
+                require_typed: danger.is_some() || self.policy.requires_typed_confirmation(),
+                typed: String::new(),
+                code: confirmation_code(),
+                danger,
+            });
+            self.push_message(MessageLevel::Info, format!("Delete {}?", record.name));
+        }
+    }
+
+    fn current_record(&self) -> Option<&DnsRecord> {
+        self.filtered_records().get(self.selected_record).copied()
+    }
+
+    fn create_record(&mut self, record: DnsRecord) -> Result<()> {
+        let Some(zone) = self.current_zone() else {
+            return Ok(());
+        };
+
+        if let Some(conflict) = find_create_conflict(&self.records, &zone.name, &record) {
+            let form = match &self.mode {
+                Mode::RecordForm(form) => form.clone(),
+                _ => RecordForm {
+                    draft: RecordDraft::from_record(&record),
+                    field_index: 0,
+                    is_edit: false,
+                    target_id: None,
+                    cloned_from: None,
+                    account_defaults: None,
+                },
+            };
+            self.push_message(MessageLevel::Info, conflict.describe());
+            self.mode = Mode::ConfirmCreate(ConfirmCreate {
+                record,
+                conflict,
+                form,
+            });
+            return Ok(());
+        }
+
+        self.force_create_record(record)
+    }
+
+    /// Creates the record without checking for duplicates.
+    fn force_create_record(&mut self, record: DnsRecord) -> Result<()> {
+        let (account, zone) = match (self.current_account(), self.current_zone()) {
+            (Some(a), Some(z)) => (a.clone(), z.clone()),
+            _ => return Ok(()),
+        };
+
+        self.mode = Mode::Normal;
+        self.writes_sent += 1;
+        let mut provisional = record.clone();
+        provisional.id = format!("pending-{}", self.writes_sent);
+        self.records.push(provisional.clone());
+        self.select_record(Some(&provisional.id));
+        self.push_message(MessageLevel::Progress, format!("Creating {}…", record.name));
+        let pending = PendingWrite {
+            write_id: self.writes_sent,
+            account,
+            zone,
+            action: ChangeAction::Create,
+            provisional: Some(provisional),
+            before: None,
+            index: self.records.len() - 1,
+            warning: None,
+            quick: None,
+        };
+        self.send_write(pending, RecordWrite::Create(record))
+    }
+
+    /// Updates the record unless it changed on the server since it was loaded, in which
+    /// case the conflict overlay asks what to do.
+    fn update_record(&mut self, record: DnsRecord) -> Result<()> {
+        self.write_update(record, true, None)
+    }
+
+    /// Updates the record without checking whether it changed elsewhere.
+    fn force_update_record(&mut self, record: DnsRecord) -> Result<()> {
+        self.write_update(record, false, None)
+    }
+
+    /// Flips the highlighted record between proxied and DNS-only. Taking the proxy off
+    /// the apex exposes the origin address, so that needs `p` pressed twice.
+    fn toggle_proxied(&mut self, confirmed: bool) -> Result<()> {
+        let Some(mut record) = self.highlighted_record() else {
+            return Ok(());
+        };
+        if !self.has_proxy() {
+            self.push_message(
+                MessageLevel::Info,
+                "Proxying only applies to Cloudflare accounts",
+            );
+            return Ok(());
+        }
+        if !is_proxiable(&record.record_type) {
+            self.push_message(
+                MessageLevel::Info,
+                format!(
+                    "{} records can't be proxied; only A, AAAA and CNAME can",
+                    record.record_type
+                ),
+            );
+            return Ok(());
+        }
+        let apex = self
+            .current_zone()
+            .is_some_and(|zone| is_apex(&record.name, &zone.name));
+        if record.proxied && apex && !confirmed {
+            self.pending_key = Some('p');
+            self.push_message(
+                MessageLevel::Info,
+                format!(
+                    "DNS-only exposes the origin IP of {}; press p again to confirm",
+                    record.name
+                ),
+            );
+            return Ok(());
+        }
+        record.proxied = !record.proxied;
+        let change = if record.proxied {
+            "DNS only → proxied"
+        } else {
+            "proxied → DNS only"
+        };
+        self.write_update(record, true, Some(change.to_string()))
+    }
+
+    fn write_update(
+        &mut self,
+        record: DnsRecord,
+        check_remote: bool,
+        quick: Option<String>,
+    ) -> Result<()> {
+        let (account, zone) = match (self.current_account(), self.current_zone()) {
+            (Some(a), Some(z)) => (a.clone(), z.clone()),
+            _ => return Ok(()),
+        };
+        if self.refuse_while_pending(&record.id) {
+            return Ok(());
+        }
+
+        let index = self.records.iter().position(|r| r.id == record.id);
+        let before = index.map(|idx| self.records[idx].clone());
+        let warning = match before.as_ref().map(|b| self.policy.check_update(b)) {
+            Some(Err(err)) => {
+                self.push_message(MessageLevel::Error, err.to_string());
+                return Ok(());
+            }
+            Some(Ok(warning)) => warning,
+            None => None,
+        };
+
+        self.mode = Mode::Normal;
+        if let Some(idx) = index {
+            self.records[idx] = record.clone();
+        }
+        self.select_record(Some(&record.id));
+        self.push_message(MessageLevel::Progress, format!("Updating {}…", record.name));
+        self.writes_sent += 1;
+        let pending = PendingWrite {
+            write_id: self.writes_sent,
+            account,
+            zone,
+            action: ChangeAction::Update,
+            provisional: Some(record.clone()),
+            before: before.clone(),
+            index: index.unwrap_or_default(),
+            warning,
+            quick,
+        };
+        let write = RecordWrite::Update {
+            record,
+            loaded: before.filter(|_| check_remote),
+        };
+        self.send_write(pending, write)
+    }
+
+    /// Deletes the record unless it changed on the server since it was loaded, in which
+    /// case the conflict overlay asks what to do.
+    fn delete_record(&mut self, record_id: String) -> Result<()> {
+        self.write_delete(record_id, true)
+    }
+
+    /// Deletes the record without checking whether it changed elsewhere.
+    fn force_delete_record(&mut self, record_id: String) -> Result<()> {
+        self.write_delete(record_id, false)
+    }
+
+    fn write_delete(&mut self, record_id: String, check_remote: bool) -> Result<()> {
+        let (account, zone) = match (self.current_account(), self.current_zone()) {
+            (Some(a), Some(z)) => (a.clone(), z.clone()),
+            _ => return Ok(()),
+        };
+        if self.refuse_while_pending(&record_id) {
+            return Ok(());
+        }
+
+        let index = self.records.iter().position(|r| r.id == record_id);
+        let before = index.map(|idx| self.records.remove(idx));
+        self.select_record(None);
+        self.push_message(
+            MessageLevel::Progress,
+            match &before {
+                Some(record) => format!("Deleting {}…", record.name),
+                None => "Deleting record…".to_string(),
+            },
+        );
+        self.writes_sent += 1;
+        let pending = PendingWrite {
+            write_id: self.writes_sent,
+            account,
+            zone,
+            action: ChangeAction::Delete,
+            provisional: None,
+            before: before.clone(),
+            index: index.unwrap_or_default(),
+            warning: None,
+            quick: None,
+        };
+        let write = RecordWrite::Delete {
+            record_id,
+            loaded: before.filter(|_| check_remote),
+        };
+        self.send_write(pending, write)
+    }
+
+    /// Refuses a second change to a record whose previous one is still being saved,
+    /// since it would be checked against a copy the server hasn't confirmed.
+    fn refuse_while_pending(&mut self, record_id: &str) -> bool {
+        let pending = self.is_pending_row(record_id)
+            || self
+                .pending_writes
+                .iter()
+                .any(|p| p.before.as_ref().is_some_and(|b| b.id == record_id));
+        if pending {
+            self.push_message(
+                MessageLevel::Info,
+                "Still saving the previous change to that record",
+            );
+        }
+        pending
+    }
+
+    /// Hands a write that's already shown in `records` to the loader thread, or runs
+    /// it here when there is none.
+    fn send_write(&mut self, pending: PendingWrite, write: RecordWrite) -> Result<()> {
+        let write_id = pending.write_id;
+        let load = Load::Write {
+            account: pending.account.clone(),
+            zone: pending.zone.clone(),
+            write_id,
+            write: Box::new(write),
+        };
+        self.pending_writes.push(pending);
+        match &self.loader {
+            Some(loader) => match loader.send(self.generation, load) {
+                Ok(()) => Ok(()),
+                Err(err) => self.apply_written(write_id, Err(err)),
+            },
+            None => {
+                let result = load.run(&mut *self.backend());
+                self.apply_loaded(self.generation, result)
+            }
+        }
+    }
+
+    /// Settles a write: swaps the provisional row for the server's record, or rolls the
+    /// change back and says why.
+    fn apply_written(&mut self, write_id: u64, result: Result<Written>) -> Result<()> {
+        if let Some(job) = &mut self.bulk
+            && job
+                .in_flight
+                .as_ref()
+                .is_some_and(|(id, _)| *id == write_id)
+        {
+            return self.apply_bulk_written(result);
+        }
+        let Some(pos) = self
+            .pending_writes
+            .iter()
+            .position(|p| p.write_id == write_id)
+        else {
+            return Ok(());
+        };
+        let pending = self.pending_writes.remove(pos);
+        let shown = self
+            .current_zone()
+            .is_some_and(|zone| zone.id == pending.zone.id);
+
+        let after = match result {
+            Ok(Written::Created(record) | Written::Updated(record)) => Some(record),
+            Ok(Written::Deleted) => None,
+            Ok(Written::Conflict(remote)) => {
+                if shown {
+                    self.roll_back(&pending);
+                }
+                let change = pending
+                    .provisional
+                    .map_or(PendingChange::Delete, PendingChange::Update);
+                if self.mode == Mode::Normal {
+                    self.open_record_conflict(remote, change);
+                } else {
+                    self.push_message(
+                        MessageLevel::Error,
+                        format!(
+                            "{} was changed elsewhere since it was loaded; nothing was saved",
+                            remote.name
+                        ),
+                    );
+                }
+                return Ok(());
+            }
+            Err(err) => {
+                if shown {
+                    self.roll_back(&pending);
+                }
+                let verb = match pending.action {
+                    ChangeAction::Create => "Create",
+                    ChangeAction::Update => "Update",
+                    ChangeAction::Delete => "Delete",
+                };
+                self.push_message(
+                    MessageLevel::Error,
+                    format!("{verb} failed and was rolled back: {err:#}"),
+                );
+                let name = pending
+                    .provisional
+                    .as_ref()
+                    .or(pending.before.as_ref())
+                    .map(|r| r.name.as_str())
+                    .unwrap_or_default();
+                let mut details = ErrorDetails::new(Some(format!("{verb} record {name}")), &err);
+                // Hand the typed values back rather than making the user enter them again.
+                if shown
+                    && pending.quick.is_none()
+                    && let Some(record) = &pending.provisional
+                {
+                    let is_edit = pending.action == ChangeAction::Update;
+                    details.form = Some(RecordForm {
+                        draft: RecordDraft::from_record(record),
+                        field_index: 4,
+                        is_edit,
+                        target_id: is_edit.then(|| record.id.clone()),
+                        cloned_from: None,
+                        account_defaults: None,
+                    });
+                }
+                self.show_error(details);
+                return Ok(());
+            }
+        };
+
+        if shown {
+            self.settle(&pending, after.as_ref())?;
+        }
+        let name = after
+            .as_ref()
+            .or(pending.before.as_ref())
+            .map(|r| r.name.clone())
+            .unwrap_or_default();
+        self.push_message(
+            MessageLevel::Info,
+            match pending.action {
+                ChangeAction::Create => format!("Created {name}"),
+                ChangeAction::Update => {
+                    let mut message = format!("Updated {name}");
+                    if let Some(change) = &pending.quick {
+                        message.push_str(&format!(": {change}"));
+                    }
+                    if let Some(warning) = &pending.warning {
+                        message.push_str(&format!(" (warning: {warning})"));
+                    }
+                    message
+                }
+                ChangeAction::Delete => "Record deleted".to_string(),
+            },
+        );
+        self.journal_change(
+            &pending.account,
+            &pending.zone,
+            pending.action,
+            pending.before.as_ref(),
+            after.as_ref(),
+        );
+        Ok(())
+    }
+
+    /// Replaces the provisional row with what the server stored. A refresh that landed
+    /// meanwhile may already have brought the record in, so it is never added twice.
+    fn settle(&mut self, pending: &PendingWrite, after: Option<&DnsRecord>) -> Result<()> {
+        match after {
+            Some(record) => {
+                let placeholder = pending.provisional.as_ref().map(|p| p.id.as_str());
+                let existing = self.records.iter().position(|r| r.id == record.id);
+                let provisional = self
+                    .records
+                    .iter()
+                    .position(|r| Some(r.id.as_str()) == placeholder);
+                match (existing, provisional) {
+                    (Some(idx), Some(extra)) if idx != extra => {
+                        self.records[idx] = record.clone();
+                        self.records.remove(extra);
+                    }
+                    (Some(idx), _) | (None, Some(idx)) => self.records[idx] = record.clone(),
+                    (None, None) => self.records.push(record.clone()),
+                }
+                if pending.action == ChangeAction::Create
+                    && let Some(total) = self.record_total_count.as_mut()
+                {
+                    *total += 1;
+                }
+            }
+            None => {
+                if let Some(before) = &pending.before {
+                    self.records.retain(|r| r.id != before.id);
+                }
+                if let Some(total) = self.record_total_count.as_mut() {
+                    *total = total.saturating_sub(1);
+                }
+            }
+        }
+
+        let select = after.map(|r| r.id.clone());
+        if self.config.full_refresh_after_edit && !self.is_fetching() {
+            self.refresh_records()?;
+        }
+        if pending.action == ChangeAction::Delete {
+            self.select_record(None);
+        } else {
+            self.select_record(select.as_deref());
+        }
+        Ok(())
+    }
+
+    /// Undoes the provisional change after a failed or refused write.
+    fn roll_back(&mut self, pending: &PendingWrite) {
+        match (&pending.provisional, &pending.before) {
+            (Some(provisional), None) => self.records.retain(|r| r.id != provisional.id),
+            (_, Some(before)) => match self.records.iter_mut().find(|r| r.id == before.id) {
+                Some(row) => *row = before.clone(),
+                None => {
+                    let idx = pending.index.min(self.records.len());
+                    self.records.insert(idx, before.clone());
+                }
+            },
+            (None, None) => {}
+        }
+        self.select_record(None);
+    }
+
+    /// Re-applies writes still in flight to a freshly loaded list, which was fetched
+    /// before they reached the server.
+    fn overlay_pending_writes(&mut self) {
+        let Some(zone_id) = self.current_zone().map(|z| z.id.clone()) else {
+            return;
+        };
+        for pending in self.pending_writes.iter().filter(|p| p.zone.id == zone_id) {
+            match (&pending.provisional, &pending.before) {
+                (Some(provisional), None) => self.records.push(provisional.clone()),
+                (Some(provisional), Some(_)) => {
+                    if let Some(row) = self.records.iter_mut().find(|r| r.id == provisional.id) {
+                        *row = provisional.clone();
+                    }
+                }
+                (None, Some(before)) => self.records.retain(|r| r.id != before.id),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn open_record_conflict(&mut self, remote: DnsRecord, pending: PendingChange) {
+        self.push_message(
+            MessageLevel::Info,
+            format!("{} was changed elsewhere since it was loaded", remote.name),
+        );
+        self.mode = Mode::RecordConflict(RecordConflict { remote, pending });
+    }
+
+    /// Settles the conflict overlay: overwrite the remote change, reload it, or leave it.
+    fn resolve_record_conflict(
+        &mut self,
+        conflict: RecordConflict,
+        resolution: Resolution,
+    ) -> Result<()> {
+        self.mode = Mode::Normal;
+        let RecordConflict { remote, pending } = conflict;
+        match (resolution, pending) {
+            (Resolution::Overwrite, PendingChange::Update(record)) => {
+                self.force_update_record(record)
+            }
+            (Resolution::Overwrite, PendingChange::Delete) => self.force_delete_record(remote.id),
+            (Resolution::Reload, _) => {
+                let name = remote.name.clone();
+                self.patch_records(&[], std::slice::from_ref(&remote), Some(&remote.id))?;
+                self.push_message(
+                    MessageLevel::Info,
+                    format!("Reloaded {name} with the change made elsewhere"),
+                );
+                Ok(())
+            }
+            (Resolution::Cancel, _) => {
+                self.push_message(MessageLevel::Info, format!("Left {} as it is", remote.name));
+                Ok(())
+            }
+        }
+    }
+
+    /// Opens the bulk-delete confirmation for everything the current filters show. Refuses
+    /// without a filter so a stray keypress can't offer to wipe the whole zone.
+    fn ask_delete_filtered(&mut self) {
+        let Some(zone) = self.current_zone().cloned() else {
+            return;
+        };
+        if self.record_filter.trim().is_empty() && self.type_filter == TypeFilter::All {
+            self.push_message(
+                MessageLevel::Info,
+                "Filter the records first (/ or t); D deletes everything shown",
+            );
+            return;
+        }
+        let records: Vec<DnsRecord> = self.filtered_records().into_iter().cloned().collect();
+        if records.is_empty() {
+            self.push_message(MessageLevel::Info, "No records match the current filter");
+            return;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!("Delete {} record(s) from {}?", records.len(), zone.name),
+        );
+        self.mode = Mode::ConfirmBulkDelete(ConfirmBulkDelete {
+            zone_name: zone.name,
+            records,
+            typed: String::new(),
+            selection: false,
+            scroll: 0,
+        });
+    }
+
+    /// Starts sending `set` to the current zone, one write per tick, as a bulk job
+    /// labelled by `action`. What the set left alone is only counted in the summary.
+    fn start_bulk(&mut self, action: BulkAction, set: ChangeSet) {
+        let (Some(account), Some(zone)) = (
+            self.current_account().cloned(),
+            self.current_zone().cloned(),
+        ) else {
+            return;
+        };
+        let skipped = set.skipped();
+        let total = set.steps.len();
+        self.bulk = Some(BulkJob {
+            action,
+            account,
+            zone,
+            total,
+            queue: set
+                .steps
+                .into_iter()
+                .map(|step| action.write(step))
+                .collect(),
+            in_flight: None,
+            succeeded: 0,
+            skipped,
+            failures: Vec::new(),
+            not_sent: Vec::new(),
+        });
+        self.push_message(
+            MessageLevel::Progress,
+            format!("{} 0/{total}…", action.progress()),
+        );
+    }
+
+    /// Sends the bulk job's next write, or wraps the job up when nothing is left.
+    fn advance_bulk(&mut self) -> Result<()> {
+        let Some(job) = &mut self.bulk else {
+            return Ok(());
+        };
+        let Some((write, record)) = job.queue.pop_front() else {
+            self.finish_bulk();
+            return Ok(());
+        };
+        self.writes_sent += 1;
+        let write_id = self.writes_sent;
+        let load = Load::Write {
+            account: job.account.clone(),
+            zone: job.zone.clone(),
+            write_id,
+            write: Box::new(write),
+        };
+        job.in_flight = Some((write_id, record));
+        match &self.loader {
+            Some(loader) => match loader.send(self.generation, load) {
+                Ok(()) => Ok(()),
+                Err(err) => self.apply_bulk_written(Err(err)),
+            },
+            None => {
+                let result = load.run(&mut *self.backend());
+                self.apply_loaded(self.generation, result)
+            }
+        }
+    }
+
+    /// Records one bulk write's outcome and shows it in the loaded records straight away.
+    fn apply_bulk_written(&mut self, result: Result<Written>) -> Result<()> {
+        let Some(job) = &mut self.bulk else {
+            return Ok(());
+        };
+        let Some((_, record)) = job.in_flight.take() else {
+            return Ok(());
+        };
+        let zone_id = job.zone.id.clone();
+        match result {
+            Ok(Written::Deleted) => {
+                job.succeeded += 1;
+                self.selected_ids.remove(&record.id);
+                if self.current_zone().is_some_and(|zone| zone.id == zone_id) {
+                    self.patch_loaded(std::slice::from_ref(&record.id), &[]);
+                    self.select_record(None);
+                }
+            }
+            Ok(Written::Created(updated) | Written::Updated(updated)) => {
+                job.succeeded += 1;
+                if self.current_zone().is_some_and(|zone| zone.id == zone_id) {
+                    self.patch_loaded(&[], std::slice::from_ref(&updated));
+                }
+            }
+            Ok(Written::Conflict(_)) => job.failures.push((
+                record,
+                "changed on the server since it was loaded".to_string(),
+            )),
+            Err(err) => job.failures.push((record, format!("{err:#}"))),
+        }
+        let stop = self.config.stop_on_failure;
+        let Some(job) = &mut self.bulk else {
+            return Ok(());
+        };
+        if stop && !job.failures.is_empty() {
+            job.not_sent
+                .extend(job.queue.drain(..).map(|(_, record)| record));
+        }
+        let attempted = job.succeeded + job.failures.len();
+        let progress = format!("{} {attempted}/{}…", job.action.progress(), job.total);
+        self.push_message(MessageLevel::Progress, progress);
+        Ok(())
+    }
+
+    fn finish_bulk(&mut self) {
+        let Some(job) = self.bulk.take() else {
+            return;
+        };
+        let done = job.action.done();
+        let mut summary = match (job.action, job.failures.len()) {
+            (BulkAction::Delete, 0) => {
+                format!("{} record(s) {done} in {}", job.succeeded, job.zone.name)
+            }
+            (BulkAction::Delete, failed) => format!("{} {done}, {failed} failed", job.succeeded),
+            (BulkAction::Apply, 0) => {
+                format!("{} change(s) {done} to {}", job.succeeded, job.zone.name)
+            }
+            (BulkAction::Apply, failed) => format!("{} {done}, {failed} failed", job.succeeded),
+            (_, failed) => format!(
+                "{} {done}, {} skipped, {failed} failed",
+                job.succeeded, job.skipped
+            ),
+        };
+        if !job.not_sent.is_empty() {
+            summary.push_str(&format!(
+                ", stopped there with {} not sent",
+                job.not_sent.len()
+            ));
+        }
+        if !job.failures.is_empty() {
+            summary.push_str(" — press L for details");
+        }
+        let level = if job.failures.is_empty() {
+            MessageLevel::Info
+        } else {
+            MessageLevel::Error
+        };
+        self.push_message(level, summary.clone());
+        self.bulk_report = Some(BulkReport {
+            summary,
+            failures: job
+                .failures
+                .iter()
+                .map(|(record, err)| format!("{} {}: {err}", record.record_type, record.name))
+                .chain(
+                    job.not_sent
+                        .iter()
+                        .map(|record| format!("{} {}: not sent", record.record_type, record.name)),
+                )
+                .collect(),
+            scroll: 0,
+        });
+    }
+
+    fn open_bulk_report(&mut self) {
+        match &self.bulk_report {
+            Some(report) => self.mode = Mode::BulkReport(report.clone()),
+            None => self.push_message(MessageLevel::Info, "No bulk changes to report yet"),
+        }
+    }
+
+    /// The records a bulk action works on: the selection, or else everything the filter
+    /// shows. Says what to do on the status line when there are none.
+    fn bulk_targets(&mut self) -> Option<Vec<DnsRecord>> {
+        let records = self.bulk_candidates();
+        if records.is_empty() {
+            self.push_message(
+                MessageLevel::Info,
+                "Select records with Space (or filter them) first",
+            );
+            return None;
+        }
+        Some(records)
+    }
+
+    /// Asks to turn proxying on (or, when every eligible target is already proxied, off)
+    /// for the selected or filtered records.
+    fn ask_bulk_proxied(&mut self) {
+        if !self.has_proxy() {
+            self.push_message(
+                MessageLevel::Info,
+                "Proxying only applies to Cloudflare accounts",
+            );
+            return;
+        }
+        let Some(records) = self.bulk_targets() else {
+            return;
+        };
+        let on = records
+            .iter()
+            .any(|r| is_proxiable(&r.record_type) && !r.proxied);
+        self.open_bulk_update(BulkAction::SetProxied(on), records);
+    }
+
+    /// The selected records, else the filtered ones, else none.
+    fn bulk_candidates(&self) -> Vec<DnsRecord> {
+        if !self.selected_ids.is_empty() {
+            self.records
+                .iter()
+                .filter(|r| self.selected_ids.contains(&r.id))
+                .cloned()
+                .collect()
+        } else if !self.record_filter.trim().is_empty() || self.type_filter != TypeFilter::All {
+            self.filtered_records().into_iter().cloned().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Asks the UI loop to open the highlighted record in the user's editor.
+    fn request_external_edit(&mut self) {
+        let Some(record) = self.highlighted_record() else {
+            return;
+        };
+        if self.refuse_while_pending(&record.id) {
+            return;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!("Editing {} in your editor…", record.name),
+        );
+        self.external_edit = Some(record);
+    }
+
+    /// Applies what came back from [`edit_until_valid`] for `original`.
+    fn apply_external_edit(
+        &mut self,
+        original: &DnsRecord,
+        edited: Result<Option<DnsRecord>>,
+    ) -> Result<()> {
+        match edited {
+            Ok(Some(record))
+                if RecordDraft::from_record(&record) != RecordDraft::from_record(original) =>
+            {
+                self.update_record(record)?;
+            }
+            Ok(Some(_)) => {
+                self.push_message(
+                    MessageLevel::Info,
+                    format!("No changes to {}", original.name),
+                );
+            }
+            Ok(None) => {
+                self.push_message(
+                    MessageLevel::Info,
+                    format!("Edit of {} cancelled", original.name),
+                );
+            }
+            Err(err) => {
+                self.push_message(
+                    MessageLevel::Error,
+                    format!(
+                        "Editing {} failed, nothing was saved: {err:#}",
+                        original.name
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the one-line prompt for `field` of the highlighted record.
+    fn start_quick_edit(&mut self, field: QuickField) {
+        let Some(record) = self.current_record().cloned() else {
+            return;
+        };
+        self.mode = Mode::QuickEdit(QuickEdit {
+            field,
+            text: field.value(&record),
+            record,
+        });
+    }
+
+    /// Prompts for a TTL to set on the selected or filtered records, or with neither,
+    /// on the highlighted one.
+    fn ask_bulk_ttl(&mut self) {
+        if self.bulk_candidates().is_empty() && self.current_record().is_some() {
+            self.start_quick_edit(QuickField::Ttl);
+            return;
+        }
+        let Some(records) = self.bulk_targets() else {
+            return;
+        };
+        self.mode = Mode::BulkTtl(TtlPrompt {
+            text: String::new(),
+            records,
+        });
+    }
+
+    fn open_bulk_update(&mut self, action: BulkAction, records: Vec<DnsRecord>) {
+        let confirm = ConfirmBulkUpdate {
+            action,
+            records,
+            scroll: 0,
+            policy: self.policy.clone(),
+        };
+        let changes = confirm.change_set().steps.len();
+        self.push_message(
+            MessageLevel::Info,
+            format!("{} {changes} record(s)?", action.describe()),
+        );
+        self.mode = Mode::ConfirmBulkUpdate(confirm);
+    }
+
+    /// Copies the highlighted record's content, or the highlighted zone's or account's name.
+    fn copy_selected(&mut self) {
+        let (what, text) = match self.focus {
+            Focus::Records => match self.current_record() {
+                Some(record) => ("the record content", record.content.clone()),
+                None => return,
+            },
+            Focus::Zones => match self.current_zone() {
+                Some(zone) => ("the zone name", zone.name.clone()),
+                None => return,
+            },
+            Focus::Accounts => match self.current_account() {
+                Some(account) => ("the account name", account.name.clone()),
+                None => return,
+            },
+        };
+        self.copy_to_clipboard(what, &text);
+    }
+
+    /// Puts `text` on the clipboard as `clipboard` says, falling back to OSC 52 in `auto`
+    /// mode over SSH or when no clipboard command works.
+    fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        let mode = self.config.clipboard;
+        let over_ssh = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+        if mode == ClipboardMode::System || (mode == ClipboardMode::Auto && !over_ssh) {
+            match copy_with_command(text) {
+                Ok(program) => {
+                    self.push_message(MessageLevel::Info, format!("Copied {what} ({program})"));
+                    return;
+                }
+                Err(err) if mode == ClipboardMode::System => {
+                    self.push_message(MessageLevel::Error, format!("Copy failed: {err:#}"));
+                    return;
+                }
+                Err(_) => {}
+            }
+        }
+        let max_bytes = self.config.osc52_max_bytes();
+        let (sequence, kept) = osc52_sequence(text, max_bytes, env::var_os("TMUX").is_some());
+        self.terminal_output.push_str(&sequence);
+        self.push_message(
+            MessageLevel::Info,
+            if kept < text.len() {
+                format!(
+                    "Copied only the first {kept} of {} bytes of {what} (osc52_max_bytes)",
+                    text.len()
+                )
+            } else {
+                format!("Copied {what} (OSC 52)")
+            },
+        );
+    }
+
+    /// Marks or unmarks the highlighted record for the next bulk action.
+    fn toggle_selected(&mut self) {
+        let Some(id) = self.current_record().map(|r| r.id.clone()) else {
+            return;
+        };
+        if let Some(zone) = self.current_zone() {
+            self.selection_zone = zone.id.clone();
+        }
+        if !self.selected_ids.remove(&id) {
+            self.selected_ids.insert(id);
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!("{} selected", self.selected_ids.len()),
+        );
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected_ids.clear();
+        self.push_message(MessageLevel::Info, "Selection cleared");
+    }
+
+    /// Asks to delete the marked records, including any the filter currently hides.
+    fn ask_delete_selected(&mut self) {
+        let Some(zone) = self.current_zone().cloned() else {
+            return;
+        };
+        let records: Vec<DnsRecord> = self
+            .records
+            .iter()
+            .filter(|r| self.selected_ids.contains(&r.id))
+            .cloned()
+            .collect();
+        if records.is_empty() {
+            self.push_message(
+                MessageLevel::Info,
+                "None of the selected records are loaded",
+            );
+            return;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!("Delete {} selected record(s)?", records.len()),
+        );
+        self.mode = Mode::ConfirmBulkDelete(ConfirmBulkDelete {
+            zone_name: zone.name,
+            records,
+            typed: String::new(),
+            selection: true,
+            scroll: 0,
+        });
+    }
+
+    /// Deletes `records` in a single atomic batch call. Ones changed on the server since
+    /// they were loaded are left alone, as when deleting a single record.
+    fn delete_records(&mut self, records: Vec<DnsRecord>) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        self.start_call(Call::DeleteRecords { zone, records })
+    }
+
+    fn records_deleted(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        records: Vec<DnsRecord>,
+        changed: Vec<DnsRecord>,
+        batched: Result<BatchResult>,
+    ) -> Result<()> {
+        let shown = self.current_zone().is_some_and(|z| z.id == zone.id);
+        // A batch is all or nothing; when it fails (or the provider has none), delete one
+        // at a time so whatever can go does.
+        let result = match batched {
+            Ok(result) => result,
+            Err(err) if !shown => return Err(err),
+            Err(err) => {
+                self.start_bulk(BulkAction::Delete, BulkAction::Delete.change_set(&records));
+                if let Some(job) = &mut self.bulk {
+                    job.total += changed.len();
+                    job.failures.extend(changed.into_iter().map(|record| {
+                        (
+                            record,
+                            "changed on the server since it was loaded".to_string(),
+                        )
+                    }));
+                }
+                self.push_message(
+                    MessageLevel::Error,
+                    format!(
+                        "Batch delete failed ({err:#}); {}",
+                        self.last_message.to_lowercase()
+                    ),
+                );
+                return Ok(());
+            }
+        };
+        let deleted: Vec<String> = result.deletes.iter().map(|r| r.id.clone()).collect();
+        for id in &deleted {
+            self.selected_ids.remove(id);
+        }
+        if shown {
+            self.patch_records(&deleted, &changed, None)?;
+        }
+        let mut message = format!(
+            "Deleted {} record(s) from {} in one batch",
+            result.deletes.len(),
+            zone.name
+        );
+        if !changed.is_empty() {
+            let names: Vec<&str> = changed.iter().map(|r| r.name.as_str()).collect();
+            message.push_str(&format!(
+                "; left {} alone, changed on the server since loaded",
+                names.join(", ")
+            ));
+        }
+        self.push_message(MessageLevel::Info, message);
+        for record in &records {
+            self.journal_change(account, zone, ChangeAction::Delete, Some(record), None);
+        }
+        Ok(())
+    }
+
+    /// Folds a successful edit into the loaded records rather than re-downloading the
+    /// zone: `removed` ids are dropped, `upserted` records replace theirs by id or are
+    /// appended. The selection moves to `select` when it's shown, otherwise it stays put.
+    /// Reloads instead with `full_refresh_after_edit`, or while pages are still arriving.
+    fn patch_records(
+        &mut self,
+        removed: &[String],
+        upserted: &[DnsRecord],
+        select: Option<&str>,
+    ) -> Result<()> {
+        if self.config.full_refresh_after_edit || self.is_fetching() {
+            self.refresh_records()?;
+        } else {
+            self.patch_loaded(removed, upserted);
+        }
+
+        self.select_record(select);
+        Ok(())
+    }
+
+    /// The in-place part of `patch_records`, keeping the server total in step.
+    fn patch_loaded(&mut self, removed: &[String], upserted: &[DnsRecord]) {
+        let before = self.records.len();
+        self.records.retain(|r| !removed.contains(&r.id));
+        let mut added = 0;
+        for record in upserted {
+            match self.records.iter_mut().find(|r| r.id == record.id) {
+                Some(existing) => *existing = record.clone(),
+                None => {
+                    self.records.push(record.clone());
+                    added += 1;
+                }
+            }
+        }
+        let dropped = before + added - self.records.len();
+        if let Some(total) = self.record_total_count.as_mut() {
+            *total = (*total + added as u32).saturating_sub(dropped as u32);
+        }
+    }
+
+    /// Moves the selection to the record with `id` when it's shown, otherwise keeps it
+    /// within the list.
+    fn select_record(&mut self, id: Option<&str>) {
+        let filtered = self.filtered_records();
+        let total = filtered.len();
+        if let Some(idx) = id.and_then(|id| filtered.iter().position(|r| r.id == id)) {
+            self.selected_record = idx;
+        }
+        self.selected_record = self.selected_record.min(total.saturating_sub(1));
+        self.ensure_record_visible(total);
+    }
+
+    /// Appends a change to the local journal; failures are reported but never block the edit.
+    fn journal_change(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        action: ChangeAction,
+        before: Option<&DnsRecord>,
+        after: Option<&DnsRecord>,
+    ) {
+        let Some(record_id) = after.or(before).map(|r| r.id.clone()) else {
+            return;
+        };
+        let entry = JournalEntry {
+            timestamp: now_unix(),
+            account: account.name.clone(),
+            zone_id: zone.id.clone(),
+            record_id,
+            action,
+            before: before.cloned(),
+            after: after.cloned(),
+        };
+        if let Err(err) = self.journal.append(&entry) {
+            self.amend_message(&format!(" (journal not written: {err})"));
+        }
+    }
+
+    fn export_dir(&self) -> PathBuf {
+        self.config.export_dir.clone().unwrap_or_else(|| {
+            self.config_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+                .join("exports")
+        })
+    }
+
+    /// Fetches the selected zone's BIND export to write it out.
+    fn export_current_zone(&mut self) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            self.push_message(MessageLevel::Info, "Select a zone to export");
+            return Ok(());
+        };
+        self.start_call(Call::Export { zone })
+    }
+
+    /// Writes a fetched BIND export; failures land in the status line.
+    fn zone_exported(&mut self, zone: &Zone, text: Result<String>) {
+        match text.and_then(|text| self.write_zone_export(zone, text)) {
+            Ok(path) => self.push_message(
+                MessageLevel::Info,
+                format!("Exported {} to {}", zone.name, path.display()),
+            ),
+            Err(err) => {
+                self.push_message(
+                    MessageLevel::Error,
+                    format!("Export of {} failed: {err:#}", zone.name),
+                );
+                self.show_error(ErrorDetails::new(
+                    Some(format!("Export {}", zone.name)),
+                    &err,
+                ));
+            }
+        }
+    }
+
+    fn write_zone_export(&self, zone: &Zone, text: String) -> Result<PathBuf> {
+        let dir = self.export_dir();
+        fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+        let path = dir.join(format!(
+            "{}.zone-{}.txt",
+            zone.name,
+            compact_timestamp(now_unix())
+        ));
+        fs::write(&path, text).with_context(|| format!("Writing {}", path.display()))?;
+        Ok(path)
+    }
+
+    fn start_create_zone(&mut self) {
+        match self.current_account() {
+            None => self.push_message(MessageLevel::Info, "Add an account before creating zones"),
+            Some(account) if account.account_id.is_none() => {
+                self.push_message(
+                    MessageLevel::Info,
+                    format!(
+                        "{} has no account ID configured; zone creation needs one",
+                        account.name
+                    ),
+                );
+            }
+            Some(_) => {
+                self.mode = Mode::CreateZone(ZoneForm::default());
+                self.push_message(MessageLevel::Info, "Enter the domain to add");
+            }
+        }
+    }
+
+    fn create_zone(&mut self, form: &ZoneForm) -> Result<()> {
+        let name = form.name.trim().trim_end_matches('.').to_lowercase();
+        if name.is_empty() || !name.contains('.') {
+            self.push_message(MessageLevel::Info, "Enter a domain such as example.com");
+            return Ok(());
+        }
+        self.start_call(Call::CreateZone {
+            name,
+            setup: form.setup,
+        })
+    }
+
+    /// Reloads the zones with the new one selected.
+    fn zone_created(&mut self, created: Zone) -> Result<()> {
+        if matches!(self.mode, Mode::CreateZone(_)) {
+            self.mode = Mode::Normal;
+        }
+        // The new zone might not match the filter, and it is about to be selected.
+        self.zone_filter.clear();
+        self.restore_zone = Some(created.id.clone());
+        self.focus = Focus::Zones;
+        self.refresh_current()?;
+        self.push_message(
+            MessageLevel::Info,
+            if created.name_servers.is_empty() {
+                format!("Created zone {}", created.name)
+            } else {
+                format!(
+                    "Created zone {}; point it at {}",
+                    created.name,
+                    created.name_servers.join(", ")
+                )
+            },
+        );
+        Ok(())
+    }
+
+    /// Counts the zone's records for the delete confirmation's warning.
+    fn ask_delete_zone(&mut self) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        self.start_call(Call::CountRecords { zone })
+    }
+
+    /// Opens the zone delete confirmation, unless something else was opened meanwhile.
+    fn confirm_delete_zone(&mut self, zone: Zone, record_count: usize) {
+        if self.mode != Mode::Normal {
+            return;
+        }
+        self.push_message(MessageLevel::Info, format!("Delete zone {}?", zone.name));
+        self.mode = Mode::ConfirmDeleteZone(ConfirmDeleteZone {
+            zone,
+            record_count,
+            typed: String::new(),
+        });
+    }
+
+    fn delete_zone(&mut self, zone: &Zone) -> Result<()> {
+        self.start_call(Call::DeleteZone { zone: zone.clone() })
+    }
+
+    fn zone_deleted(&mut self, zone: &Zone) -> Result<()> {
+        self.refresh_current()?;
+        self.push_message(MessageLevel::Info, format!("Deleted zone {}", zone.name));
+        Ok(())
+    }
+
+    fn start_import(&mut self) {
+        if self.current_zone().is_none() {
+            self.push_message(MessageLevel::Info, "Select a zone to import into");
+            return;
+        }
+        self.mode = Mode::ImportZone(ImportForm::default());
+        self.push_message(
+            MessageLevel::Info,
+            "Type the path of a BIND zone file to import",
+        );
+    }
+
+    /// Parses the form's file locally so the overlay can show what would be added.
+    fn preview_import(&mut self) {
+        let Some(zone_name) = self.current_zone().map(|z| z.name.clone()) else {
+            return;
+        };
+        let Mode::ImportZone(form) = &mut self.mode else {
+            return;
+        };
+        let path = expand_home(form.path.trim());
+        let parsed = fs::read_to_string(&path)
+            .with_context(|| format!("Reading {}", path.display()))
+            .and_then(|text| bind::parse_zone(&text, &zone_name));
+        match parsed {
+            Ok(records) => {
+                let preview = ImportPreview::from_records(&records, &zone_name);
+                let message = format!(
+                    "{} will add {} record(s) to {zone_name}",
+                    path.display(),
+                    preview.records
+                );
+                form.preview = Some(preview);
+                self.push_message(MessageLevel::Info, message);
+            }
+            Err(err) => self.push_message(MessageLevel::Error, format!("Cannot import: {err:#}")),
+        }
+    }
+
+    /// Runs the import in `form` past the policy: whether it would lose proxy state.
+    fn check_import(&self, form: &ImportForm) -> Result<Option<String>> {
+        let zone_name = self.current_zone().map_or("", |zone| zone.name.as_str());
+        self.policy
+            .check_import(zone_name, form.unproxied(self.has_proxy()))
+    }
+
+    fn import_zone(&mut self, form: &ImportForm) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        if let Err(err) = self.check_import(form) {
+            self.push_message(MessageLevel::Error, err.to_string());
+            return Ok(());
+        }
+        let path = expand_home(form.path.trim());
+        self.start_call(Call::Import {
+            zone,
+            path,
+            proxied: form.proxied,
+        })
+    }
+
+    fn zone_imported(&mut self, zone: &Zone, result: ImportResult) -> Result<()> {
+        if matches!(self.mode, Mode::ImportZone(_)) {
+            self.mode = Mode::Normal;
+        }
+        if self.current_zone().is_some_and(|shown| shown.id == zone.id) {
+            self.refresh_records()?;
+        }
+        self.push_message(
+            MessageLevel::Info,
+            format!(
+                "Imported {} of {} record(s) into {} ({} failed)",
+                result.added,
+                result.parsed,
+                zone.name,
+                result.failed()
+            ),
+        );
+        Ok(())
+    }
+
+    fn start_apply(&mut self) {
+        if self.current_zone().is_none() {
+            self.push_message(MessageLevel::Info, "Select a zone to apply a file to");
+            return;
+        }
+        if !self.pending_writes.is_empty() || self.bulk.is_some() {
+            self.push_message(
+                MessageLevel::Info,
+                "Wait for the changes in progress to finish first",
+            );
+            return;
+        }
+        self.mode = Mode::ApplyState(ApplyForm::default());
+        self.push_message(
+            MessageLevel::Info,
+            "Type the path of a YAML, TOML or JSON file of the records the zone should have",
+        );
+    }
+
+    /// Reads the form's file and fetches the whole zone, so the overlay can show the
+    /// plan before anything is sent.
+    fn preview_apply(&mut self) -> Result<()> {
+        let (Some(account), Some(zone)) = (
+            self.current_account().cloned(),
+            self.current_zone().cloned(),
+        ) else {
+            return Ok(());
+        };
+        let Mode::ApplyState(form) = &self.mode else {
+            return Ok(());
+        };
+        let path = expand_home(form.path.trim());
+        let prune = form.prune;
+        match desired_records(&account, &zone, &path) {
+            Ok(desired) => self.start_call(Call::PlanApply {
+                zone,
+                desired,
+                prune,
+            }),
+            Err(err) => {
+                self.push_message(MessageLevel::Error, format!("Cannot apply: {err:#}"));
+                Ok(())
+            }
+        }
+    }
+
+    /// Shows the plan in the apply overlay, if it's still open.
+    fn show_apply_plan(&mut self, zone: &Zone, planned: Result<ChangeSet>) {
+        match planned {
+            Ok(plan) => {
+                let message = format!("{}: {}", zone.name, plan.summary());
+                let deletes = plan
+                    .steps
+                    .iter()
+                    .any(|step| matches!(step, Step::Delete { .. }));
+                let typed = deletes && self.policy.requires_typed_confirmation();
+                if let Mode::ApplyState(form) = &mut self.mode {
+                    form.preview = Some(plan);
+                    form.scroll = 0;
+                    form.confirm_zone = typed.then(|| zone.name.clone());
+                    form.typed.clear();
+                }
+                self.push_message(MessageLevel::Info, message);
+            }
+            Err(err) => self.push_message(MessageLevel::Error, format!("Cannot apply: {err:#}")),
+        }
+    }
+
+    fn plan_apply(
+        &self,
+        zone: &Zone,
+        desired: &[DnsRecord],
+        prune: bool,
+        live: Result<Listing<DnsRecord>>,
+    ) -> Result<ChangeSet> {
+        let live = live.with_context(|| format!("Listing the records of {}", zone.name))?;
+        if live.truncated {
+            return Err(anyhow!(
+                "{} has more records than {} pages hold",
+                zone.name,
+                live.pages
+            ));
+        }
+        let mut plan = sync::plan(&live.items, desired, prune);
+        self.policy.screen(&mut plan);
+        Ok(plan)
+    }
+
+    /// Sends a previewed plan as a bulk job. Each update and delete is checked against
+    /// the record as it was planned, so one changed elsewhere since is reported, not
+    /// overwritten.
+    fn apply_plan(&mut self, plan: ChangeSet) {
+        self.mode = Mode::Normal;
+        if plan.steps.is_empty() {
+            self.push_message(MessageLevel::Info, "Nothing to change");
+            return;
+        }
+        self.start_bulk(BulkAction::Apply, plan);
+    }
+
+    /// Looks up the zone's DNSSEC state to open its popup.
+    fn open_zone_detail(&mut self) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        self.start_call(Call::Dnssec { zone })
+    }
+
+    /// Opens the zone popup, unless something else was opened meanwhile; a DNSSEC
+    /// lookup failure is shown inside it rather than blocking it.
+    fn show_zone_detail(&mut self, zone: Zone, looked_up: Result<DnssecStatus>) {
+        if self.mode != Mode::Normal {
+            return;
+        }
+        let (dnssec, dnssec_note) = match looked_up {
+            Ok(dnssec) => (Some(dnssec), None),
+            Err(err) => (None, Some(format!("DNSSEC unavailable: {err:#}"))),
+        };
+        self.push_message(
+            MessageLevel::Info,
+            match dnssec.as_ref().and_then(|d| d.ds.as_ref()) {
+                Some(ds) => format!("DS for {}: {ds}", zone.name),
+                None => format!("Zone {}", zone.name),
+            },
+        );
+        self.mode = Mode::ZoneDetail(ZoneDetail {
+            zone,
+            dnssec,
+            dnssec_note,
+            confirm_toggle: false,
+        });
+    }
+
+    fn toggle_dnssec(&mut self) -> Result<()> {
+        let Mode::ZoneDetail(detail) = &self.mode else {
+            return Ok(());
+        };
+        let zone = detail.zone.clone();
+        let enabled = !detail.dnssec.as_ref().is_some_and(|d| d.is_enabled());
+        self.start_call(Call::SetDnssec { zone, enabled })
+    }
+
+    fn dnssec_set(&mut self, zone: &Zone, enable: bool, dnssec: DnssecStatus) {
+        self.push_message(
+            MessageLevel::Info,
+            match (&dnssec.ds, enable) {
+                (Some(ds), true) => format!("DNSSEC {} for {}; DS: {ds}", dnssec.status, zone.name),
+                _ => format!("DNSSEC {} for {}", dnssec.status, zone.name),
+            },
+        );
+        if let Mode::ZoneDetail(detail) = &mut self.mode
+            && detail.zone.id == zone.id
+        {
+            detail.dnssec = Some(dnssec);
+            detail.dnssec_note = None;
+        }
+    }
+
+    fn open_record_detail(&mut self) {
+        if let Some(record) = self.current_record().cloned() {
+            self.mode = Mode::RecordDetail(RecordDetail {
+                record,
+                tab: DetailTab::Details,
+                history: Vec::new(),
+                history_note: None,
+                history_page: 0,
+            });
+        }
+    }
+
+    /// Fetches the audit log of the record in the detail popup.
+    fn load_record_history(&mut self) -> Result<()> {
+        let Some(zone) = self.current_zone().cloned() else {
+            return Ok(());
+        };
+        let Mode::RecordDetail(detail) = &self.mode else {
+            return Ok(());
+        };
+        let record_id = detail.record.id.clone();
+        self.start_call(Call::AuditLog { zone, record_id })
+    }
+
+    /// Switches the detail popup to its history tab, merging the journal with the audit
+    /// log, if it still shows the record.
+    fn show_record_history(
+        &mut self,
+        zone: &Zone,
+        record_id: &str,
+        audit_log: Result<Vec<AuditEntry>>,
+    ) {
+        if !matches!(&self.mode, Mode::RecordDetail(detail) if detail.record.id == record_id) {
+            return;
+        }
+        let mut notes = Vec::new();
+        let local = self
+            .journal
+            .entries_for(&zone.id, record_id)
+            .unwrap_or_else(|err| {
+                notes.push(format!("Journal unavailable: {err}"));
+                Vec::new()
+            });
+        let remote = audit_log.unwrap_or_else(|err| {
+            notes.push(format!("Audit log unavailable: {err}"));
+            Vec::new()
+        });
+        let history = merge_history(local, remote);
+        self.push_message(
+            MessageLevel::Info,
+            format!("{} history event(s) for {}", history.len(), record_id),
+        );
+
+        if let Mode::RecordDetail(detail) = &mut self.mode {
+            detail.tab = DetailTab::History;
+            detail.history_page = history.len().saturating_sub(1) / HISTORY_PAGE_SIZE;
+            detail.history = history;
+            detail.history_note = if notes.is_empty() {
+                None
+            } else {
+                Some(notes.join(" | "))
+            };
+        }
+    }
+}
+
+/// A list assembled from one or more API pages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Listing<T> {
+    items: Vec<T>,
+    pages: u32,
+    /// Set when the page safeguard stopped the fetch before the last page.
+    truncated: bool,
+    /// Total the server reported (`result_info.total_count`), when it reported one.
+    total_count: Option<u32>,
+}
+
+impl<T> Listing<T> {
+    fn single(items: Vec<T>) -> Self {
+        Self {
+            items,
+            pages: 1,
+            truncated: false,
+            total_count: None,
+        }
+    }
+
+    fn pages_suffix(&self) -> String {
+        match (self.pages, self.truncated) {
+            (pages, true) => format!(" (stopped after {pages} pages)"),
+            (0 | 1, false) => String::new(),
+            (pages, false) => format!(" ({pages} pages)"),
+        }
+    }
+}
+
+/// A single page of a list endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Page<T> {
+    items: Vec<T>,
+    page: u32,
+    total_pages: u32,
+    total_count: Option<u32>,
+}
+
+/// The records the desired-state file at `path` wants in `zone`.
+fn desired_records(account: &Account, zone: &Zone, path: &Path) -> Result<Vec<DnsRecord>> {
+    let state = sync::StateFile::read(path)?;
+    state.zone_name(Some(&zone.name))?;
+    state
+        .records(&zone.name, account)
+        .with_context(|| format!("Reading {}", path.display()))
+}
+
+/// Fetches pages starting at 1 until the server's last page (or the safeguard) is reached.
+fn collect_pages<T>(mut fetch: impl FnMut(u32) -> Result<Page<T>>) -> Result<Listing<T>> {
+    let mut items = Vec::new();
+    let mut page = 1;
+    loop {
+        let batch = fetch(page)?;
+        let done = batch.items.is_empty() || page >= batch.total_pages;
+        items.extend(batch.items);
+        if done || page >= CF_MAX_PAGES {
+            return Ok(Listing {
+                items,
+                pages: page,
+                truncated: !done,
+                total_count: batch.total_count,
+            });
+        }
+        page += 1;
+    }
+}
+
+trait DnsBackend: Send {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>>;
+    /// Removes the zone and every record in it.
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()>;
+    /// Adds `name` to the account; needs the account's `account_id`.
+    fn create_zone(&mut self, account: &Account, name: &str, setup: ZoneSetup) -> Result<Zone>;
+    /// One page of the zone's records, optionally only those of `record_type`.
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>>;
+    /// Records whose name or content contains `query`, across the whole zone.
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>>;
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord>;
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord>;
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()>;
+    /// Fetches one record as it is on the server now.
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord>;
+    /// Applies every operation in `request` atomically: all succeed or nothing changes.
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult>;
+    fn record_audit_log(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record_id: &str,
+    ) -> Result<Vec<AuditEntry>>;
+    /// Checks that the account's credentials are accepted before they are relied on.
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus>;
+    /// The Cloudflare accounts the credentials can access.
+    fn list_cf_accounts(&mut self, account: &Account) -> Result<Vec<CloudflareAccount>>;
+    /// The zone as a BIND zone file.
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String>;
+    /// Adds the records in the BIND file at `path`; `proxied` applies to proxiable records.
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        proxied: bool,
+    ) -> Result<ImportResult>;
+    fn dnssec_status(&mut self, account: &Account, zone: &Zone) -> Result<DnssecStatus>;
+    /// Turns DNSSEC on or off, returning the new state.
+    fn set_dnssec(&mut self, account: &Account, zone: &Zone, enabled: bool)
+    -> Result<DnssecStatus>;
+    /// Where the backend posts transient notes about work in progress, such as waiting
+    /// out a rate limit. Readable while a request holds the backend.
+    fn notice_board(&self) -> Option<NoticeBoard> {
+        None
+    }
+    /// Where the backend counts its API calls, for the status line. Readable while a
+    /// request holds the backend.
+    fn rate_meter(&self) -> Option<RateMeter> {
+        None
+    }
+    /// Hands the backend the loader's `Cancel`, to give up on a fetch the app has
+    /// abandoned between pages and retries.
+    fn watch_cancel(&mut self, _cancel: Cancel) {}
+}
+
+type NoticeBoard = Arc<Mutex<Option<String>>>;
+type RateMeter = Arc<Mutex<RateStats>>;
+
+enum Backend {
+    Cloudflare(CloudflareBackend),
+    Mock(Box<MockBackend>),
+}
+
+impl DnsBackend for Backend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        match self {
+            Backend::Cloudflare(client) => client.list_zones(account),
+            Backend::Mock(mock) => mock.list_zones(account),
+        }
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        match self {
+            Backend::Cloudflare(client) => client.delete_zone(account, zone),
+            Backend::Mock(mock) => mock.delete_zone(account, zone),
+        }
+    }
+
+    fn create_zone(&mut self, account: &Account, name: &str, setup: ZoneSetup) -> Result<Zone> {
+        match self {
+            Backend::Cloudflare(client) => client.create_zone(account, name, setup),
+            Backend::Mock(mock) => mock.create_zone(account, name, setup),
+        }
+    }
+
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        match self {
+            Backend::Cloudflare(client) => {
+                client.list_records_page(account, zone, page, record_type)
+            }
+            Backend::Mock(mock) => mock.list_records_page(account, zone, page, record_type),
+        }
+    }
+
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        match self {
+            Backend::Cloudflare(client) => client.search_records(account, zone, query),
+            Backend::Mock(mock) => mock.search_records(account, zone, query),
+        }
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        match self {
+            Backend::Cloudflare(client) => client.create_record(account, zone, record),
+            Backend::Mock(mock) => mock.create_record(account, zone, record),
+        }
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        match self {
+            Backend::Cloudflare(client) => client.update_record(account, zone, record),
+            Backend::Mock(mock) => mock.update_record(account, zone, record),
+        }
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        match self {
+            Backend::Cloudflare(client) => client.delete_record(account, zone, record_id),
+            Backend::Mock(mock) => mock.delete_record(account, zone, record_id),
+        }
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        match self {
+            Backend::Cloudflare(client) => client.get_record(account, zone, record_id),
+            Backend::Mock(mock) => mock.get_record(account, zone, record_id),
+        }
+    }
+
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        match self {
+            Backend::Cloudflare(client) => client.batch(account, zone, request),
+            Backend::Mock(mock) => mock.batch(account, zone, request),
+        }
+    }
+
+    fn record_audit_log(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        match self {
+            Backend::Cloudflare(client) => client.record_audit_log(account, zone, record_id),
+            Backend::Mock(mock) => mock.record_audit_log(account, zone, record_id),
+        }
+    }
+
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        match self {
+            Backend::Cloudflare(client) => client.verify_account(account),
+            Backend::Mock(mock) => mock.verify_account(account),
+        }
+    }
+
+    fn list_cf_accounts(&mut self, account: &Account) -> Result<Vec<CloudflareAccount>> {
+        match self {
+            Backend::Cloudflare(client) => client.list_cf_accounts(account),
+            Backend::Mock(mock) => mock.list_cf_accounts(account),
+        }
+    }
+
+    fn dnssec_status(&mut self, account: &Account, zone: &Zone) -> Result<DnssecStatus> {
+        match self {
+            Backend::Cloudflare(client) => client.dnssec_status(account, zone),
+            Backend::Mock(mock) => mock.dnssec_status(account, zone),
+        }
+    }
+
+    fn set_dnssec(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        enabled: bool,
+    ) -> Result<DnssecStatus> {
+        match self {
+            Backend::Cloudflare(client) => client.set_dnssec(account, zone, enabled),
+            Backend::Mock(mock) => mock.set_dnssec(account, zone, enabled),
+        }
+    }
+
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        match self {
+            Backend::Cloudflare(client) => client.export_zone(account, zone),
+            Backend::Mock(mock) => mock.export_zone(account, zone),
+        }
+    }
+
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        proxied: bool,
+    ) -> Result<ImportResult> {
+        match self {
+            Backend::Cloudflare(client) => client.import_zone(account, zone, path, proxied),
+            Backend::Mock(mock) => mock.import_zone(account, zone, path, proxied),
+        }
+    }
+
+    fn notice_board(&self) -> Option<NoticeBoard> {
+        match self {
+            Backend::Cloudflare(client) => Some(Arc::clone(&client.notice)),
+            Backend::Mock(mock) => mock.notice_board(),
+        }
+    }
+
+    fn rate_meter(&self) -> Option<RateMeter> {
+        match self {
+            Backend::Cloudflare(client) => Some(Arc::clone(&client.rate)),
+            Backend::Mock(mock) => mock.rate_meter(),
+        }
+    }
+
+    fn watch_cancel(&mut self, cancel: Cancel) {
+        match self {
+            Backend::Cloudflare(client) => client.cancel = cancel,
+            Backend::Mock(mock) => mock.watch_cancel(cancel),
+        }
+    }
+}
+
+/// Serves each account from the backend instantiated for its `provider`.
+#[derive(Default)]
+struct Providers {
+    backends: HashMap<Provider, Box<dyn DnsBackend>>,
+    /// Serves accounts whose provider has no backend of its own (offline mode's mock).
+    fallback: Option<Box<dyn DnsBackend>>,
+}
+
+impl Providers {
+    fn live(config: &Config) -> Result<Self> {
+        let providers = Self::default()
+            .with(
+                Provider::Cloudflare,
+                Backend::Cloudflare(CloudflareBackend::new(config)?),
+            )
+            .with(
+                Provider::DigitalOcean,
+                digitalocean::DigitalOceanBackend::new(config.timeouts())?,
+            )
+            .with(Provider::ZoneFile, zonefile::ZoneFileBackend::default());
+        #[cfg(feature = "rfc2136")]
+        let providers = providers.with(
+            Provider::Rfc2136,
+            rfc2136::Rfc2136Backend::new(config.timeouts()),
+        );
+        #[cfg(feature = "route53")]
+        let providers = providers.with(
+            Provider::Route53,
+            route53::Route53Backend::new(config.timeouts())?,
+        );
+        Ok(providers)
+    }
+
+    /// `mock` serves every account except zone-file ones, which never go online anyway.
+    fn offline(mock: impl DnsBackend + 'static) -> Self {
+        Self {
+            fallback: Some(Box::new(mock)),
+            ..Self::default()
+        }
+        .with(Provider::ZoneFile, zonefile::ZoneFileBackend::default())
+    }
+
+    fn with(mut self, provider: Provider, backend: impl DnsBackend + 'static) -> Self {
+        self.backends.insert(provider, Box::new(backend));
+        self
+    }
+
+    fn serving(&mut self, account: &Account) -> Result<&mut dyn DnsBackend> {
+        if let Some(backend) = self
+            .backends
+            .get_mut(&account.provider)
+            .or(self.fallback.as_mut())
+        {
+            return Ok(backend.as_mut());
+        }
+        let feature = match account.provider {
+            Provider::Route53 => "route53",
+            Provider::Rfc2136 => "rfc2136",
+            _ => return Err(anyhow!("No backend serves {}", account.name)),
+        };
+        Err(anyhow!(
+            "{} is a {} account, but this build has no support for it (build with --features {feature})",
+            account.name,
+            account.provider.label()
+        ))
+    }
+
+    /// The backend whose notices and rate usage the status line shows.
+    fn primary(&self) -> Option<&dyn DnsBackend> {
+        self.fallback
+            .as_deref()
+            .or_else(|| self.backends.get(&Provider::Cloudflare).map(|b| b.as_ref()))
+    }
+}
+
+impl DnsBackend for Providers {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        self.serving(account)?.list_zones(account)
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        self.serving(account)?.delete_zone(account, zone)
+    }
+
+    fn create_zone(&mut self, account: &Account, name: &str, setup: ZoneSetup) -> Result<Zone> {
+        self.serving(account)?.create_zone(account, name, setup)
+    }
+
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        self.serving(account)?
+            .list_records_page(account, zone, page, record_type)
+    }
+
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        self.serving(account)?.search_records(account, zone, query)
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.serving(account)?.create_record(account, zone, record)
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.serving(account)?.update_record(account, zone, record)
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        self.serving(account)?
+            .delete_record(account, zone, record_id)
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        self.serving(account)?.get_record(account, zone, record_id)
+    }
+
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        self.serving(account)?.batch(account, zone, request)
+    }
+
+    fn record_audit_log(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        self.serving(account)?
+            .record_audit_log(account, zone, record_id)
+    }
+
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        self.serving(account)?.verify_account(account)
+    }
+
+    fn list_cf_accounts(&mut self, account: &Account) -> Result<Vec<CloudflareAccount>> {
+        self.serving(account)?.list_cf_accounts(account)
+    }
+
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        self.serving(account)?.export_zone(account, zone)
+    }
+
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        proxied: bool,
+    ) -> Result<ImportResult> {
+        self.serving(account)?
+            .import_zone(account, zone, path, proxied)
+    }
+
+    fn dnssec_status(&mut self, account: &Account, zone: &Zone) -> Result<DnssecStatus> {
+        self.serving(account)?.dnssec_status(account, zone)
+    }
+
+    fn set_dnssec(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        enabled: bool,
+    ) -> Result<DnssecStatus> {
+        self.serving(account)?.set_dnssec(account, zone, enabled)
+    }
+
+    fn notice_board(&self) -> Option<NoticeBoard> {
+        self.primary()?.notice_board()
+    }
+
+    fn rate_meter(&self) -> Option<RateMeter> {
+        self.primary()?.rate_meter()
+    }
+
+    fn watch_cancel(&mut self, cancel: Cancel) {
+        for backend in self.backends.values_mut().chain(self.fallback.as_mut()) {
+            backend.watch_cancel(cancel.clone());
+        }
+    }
+}
+
+const DEFAULT_RATE_LIMIT_ATTEMPTS: u32 = 3;
+/// Wait used when a 429 response carries no usable Retry-After header.
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+const DEFAULT_TRANSIENT_ATTEMPTS: u32 = 3;
+/// Each transient retry waits this many times longer than the previous one.
+const BACKOFF_FACTOR: u32 = 4;
+
+/// How `CloudflareBackend` retries requests the API asks it to slow down for
+/// or that failed transiently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RetryPolicy {
+    /// Total attempts for a rate-limited (429) request, including the first.
+    rate_limit_attempts: u32,
+    /// Upper bound on a single Retry-After wait.
+    max_wait: Duration,
+    /// Total attempts for 5xx responses and connection failures, including the first.
+    transient_attempts: u32,
+    /// Delay before the first transient retry; later ones back off by `BACKOFF_FACTOR`.
+    backoff_base: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            rate_limit_attempts: DEFAULT_RATE_LIMIT_ATTEMPTS,
+            max_wait: Duration::from_secs(60),
+            transient_attempts: DEFAULT_TRANSIENT_ATTEMPTS,
+            backoff_base: Duration::from_millis(250),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before transient retry number `retry` (1-based): 250ms, 1s, 4s, … with
+    /// the upper half scaled by `jitter` (expected in `0.0..1.0`).
+    fn backoff_delay(&self, retry: u32, jitter: f64) -> Duration {
+        let full = self
+            .backoff_base
+            .saturating_mul(BACKOFF_FACTOR.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_wait);
+        full / 2 + (full / 2).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    /// Whether a request that failed with `failure` should be sent again after
+    /// `attempts` tries so far.
+    fn should_retry_transient(&self, failure: Failure, replay: Replay, attempts: u32) -> bool {
+        if attempts >= self.transient_attempts {
+            return false;
+        }
+        match (failure, replay) {
+            (Failure::Connect, _) => true,
+            (Failure::Timeout | Failure::ServerError, Replay::Always) => true,
+            (Failure::Timeout | Failure::ServerError, Replay::BeforeResponse) => false,
+        }
+    }
+}
+
+/// Whether a request may be sent twice without side effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Replay {
+    /// Reads, updates and deletes: repeating them leaves the same end state.
+    Always,
+    /// Creates: only replay when the first attempt never reached Cloudflare.
+    BeforeResponse,
+}
+
+/// A transient failure worth retrying, as classified by `CloudflareBackend::send`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Failure {
+    /// The connection could not be established, so nothing was sent.
+    Connect,
+    /// The request went out but no response arrived in time.
+    Timeout,
+    /// Cloudflare answered with a 500/502/503/504/524.
+    ServerError,
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 500 | 502 | 503 | 504 | 524)
+}
+
+/// A number in `0.0..1.0` that differs between calls, for spreading out retries.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(now_unix().unsigned_abs());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Parses a Retry-After header given in seconds.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: f64 = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// API calls made in the last `RATE_WINDOW`, plus the remaining allowance when
+/// Cloudflare reports one.
+#[derive(Debug, Default)]
+struct RateStats {
+    calls: VecDeque<Instant>,
+    remaining: Option<u64>,
+}
+
+impl RateStats {
+    /// Counts a request sent at `now`, taking the allowance from `headers` when the
+    /// response carried one.
+    fn record(&mut self, now: Instant, headers: Option<&HeaderMap>) {
+        self.calls.push_back(now);
+        self.prune(now);
+        if let Some(remaining) = headers.and_then(rate_limit_remaining) {
+            self.remaining = Some(remaining);
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .calls
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= RATE_WINDOW)
+        {
+            self.calls.pop_front();
+        }
+    }
+
+    /// E.g. "API: 118 calls / last 5m, 1,082 left".
+    fn rate_status(&mut self, now: Instant) -> String {
+        self.prune(now);
+        let calls = format!(
+            "API: {} calls / last {}",
+            format_count(self.calls.len() as u64),
+            format_age(RATE_WINDOW)
+        );
+        match self.remaining {
+            Some(remaining) => format!("{calls}, {} left", format_count(remaining)),
+            None => calls,
+        }
+    }
+}
+
+/// Reads the calls left in the current window from `X-RateLimit-Remaining`, or the
+/// `r=` parameter of the draft-standard `Ratelimit` header.
+fn rate_limit_remaining(headers: &HeaderMap) -> Option<u64> {
+    let header = |name: &str| headers.get(name)?.to_str().ok();
+    if let Some(value) = header("x-ratelimit-remaining") {
+        return value.trim().parse().ok();
+    }
+    header("ratelimit")?
+        .split([';', ','])
+        .find_map(|part| part.trim().strip_prefix("r="))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// HTTP timeouts, in seconds so errors can quote the config values to tune.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Timeouts {
+    request_secs: u64,
+    connect_secs: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Config::default().timeouts()
+    }
+}
+
+impl Timeouts {
+    /// Rewords a timed-out request so the status line says which setting fired.
+    fn explain(&self, err: reqwest::Error) -> anyhow::Error {
+        if !err.is_timeout() {
+            return err.into();
+        }
+        let hint = if err.is_connect() {
+            format!(
+                "Connection timed out after {}s (connect_timeout_secs)",
+                self.connect_secs
+            )
+        } else {
+            format!(
+                "Request timed out after {}s (request_timeout_secs)",
+                self.request_secs
+            )
+        };
+        anyhow::Error::new(err).context(hint)
+    }
+}
+
+struct CloudflareBackend {
+    client: Client,
+    base_url: String,
+    retry: RetryPolicy,
+    timeouts: Timeouts,
+    /// Transient progress note (e.g. a rate-limit wait) for the status line.
+    notice: NoticeBoard,
+    /// Calls sent and the allowance Cloudflare last reported, for the status line.
+    rate: RateMeter,
+    /// Set when the app abandons the fetch under way; checked before every request.
+    cancel: Cancel,
+}
+
+impl CloudflareBackend {
+    fn new(config: &Config) -> Result<Self> {
+        let mut backend = Self::with_timeouts(CF_API_BASE, config.timeouts())?;
+        if let Some(attempts) = config.rate_limit_attempts {
+            backend.retry.rate_limit_attempts = attempts.max(1);
+        }
+        Ok(backend)
+    }
+
+    #[cfg(test)]
+    fn new_with_base(base_url: impl Into<String>) -> Result<Self> {
+        Self::with_timeouts(base_url, Timeouts::default())
+    }
+
+    fn with_timeouts(base_url: impl Into<String>, timeouts: Timeouts) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent("nyxflare/0.1")
+            .timeout(Duration::from_secs(timeouts.request_secs))
+            .connect_timeout(Duration::from_secs(timeouts.connect_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: base_url.into(),
+            retry: RetryPolicy::default(),
+            timeouts,
+            notice: NoticeBoard::default(),
+            rate: RateMeter::default(),
+            cancel: Cancel::default(),
+        })
+    }
+
+    fn set_notice(&self, notice: Option<String>) {
+        if let Ok(mut slot) = self.notice.lock() {
+            *slot = notice;
+        }
+    }
+
+    /// Sends the request built by `build`, sleeping and retrying while Cloudflare answers
+    /// 429 (honoring Retry-After) or fails transiently (exponential backoff with jitter,
+    /// limited by `replay`). The final response or error is returned once attempts run out,
+    /// or as soon as the fetch is abandoned.
+    fn send(&self, replay: Replay, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+        if self.cancel.is_cancelled() {
+            return Err(anyhow!("Cancelled"));
+        }
+        let result = self.send_with_retries(replay, build);
+        self.set_notice(None);
+        result.map_err(|err| self.timeouts.explain(err))
+    }
+
+    fn send_with_retries(
+        &self,
+        replay: Replay,
+        build: impl Fn() -> RequestBuilder,
+    ) -> reqwest::Result<Response> {
+        let mut rate_limited = 1;
+        let mut transient = 1;
+        loop {
+            let sent = build().send();
+            if let Ok(mut rate) = self.rate.lock() {
+                rate.record(Instant::now(), sent.as_ref().ok().map(|r| r.headers()));
+            }
+            let (reason, last) = match sent {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    if rate_limited >= self.retry.rate_limit_attempts {
+                        return Ok(response);
+                    }
+                    let wait = retry_after(response.headers())
+                        .unwrap_or(DEFAULT_RATE_LIMIT_WAIT)
+                        .min(self.retry.max_wait);
+                    self.set_notice(Some(format!(
+                        "Rate limited, retrying in {}s…",
+                        wait.as_secs_f64().ceil() as u64
+                    )));
+                    if self.cancel.sleep(wait) {
+                        return Ok(response);
+                    }
+                    rate_limited += 1;
+                    continue;
+                }
+                Ok(response) if is_transient_status(response.status()) => {
+                    let reason = format!("Cloudflare returned {}", response.status());
+                    if !self
+                        .retry
+                        .should_retry_transient(Failure::ServerError, replay, transient)
+                    {
+                        return Ok(response);
+                    }
+                    (reason, Ok(response))
+                }
+                Ok(response) => return Ok(response),
+                Err(err) => {
+                    let failure = if err.is_connect() {
+                        Failure::Connect
+                    } else if err.is_timeout() {
+                        Failure::Timeout
+                    } else {
+                        return Err(err);
+                    };
+                    if !self
+                        .retry
+                        .should_retry_transient(failure, replay, transient)
+                    {
+                        return Err(err);
+                    }
+                    let reason = match failure {
+                        Failure::Connect => "Connection failed".to_string(),
+                        _ => "Request timed out".to_string(),
+                    };
+                    (reason, Err(err))
+                }
+            };
+
+            let wait = self.retry.backoff_delay(transient, jitter());
+            self.set_notice(Some(format!(
+                "{reason}, retrying in {:.1}s ({}/{})…",
+                wait.as_secs_f64(),
+                transient + 1,
+                self.retry.transient_attempts
+            )));
+            if self.cancel.sleep(wait) {
+                return last;
+            }
+            transient += 1;
+        }
+    }
+
+    /// The account's own API base when it has one, otherwise the backend's.
+    fn base_for<'a>(&'a self, account: &'a Account) -> &'a str {
+        account
+            .base_url
+            .as_deref()
+            .map(|base| base.trim_end_matches('/'))
+            .filter(|base| !base.is_empty())
+            .unwrap_or(&self.base_url)
+    }
+
+    fn with_auth(&self, request: RequestBuilder, account: &Account) -> RequestBuilder {
+        match account.auth_mode {
+            AuthMode::Token => request.bearer_auth(&account.api_token),
+            AuthMode::GlobalKey => {
+                if let Some(email) = &account.email {
+                    request
+                        .header("X-Auth-Email", email)
+                        .header("X-Auth-Key", &account.api_token)
+                } else {
+                    request
+                }
+            }
+        }
+    }
+
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        let url = format!("{}/zones", self.base_for(account));
+        let listing: Listing<CfZone> = self.get_all_pages(
+            account,
+            &url,
+            &self.zone_query(account),
+            "Zones",
+            &format!("Listing zones for {}", account.name),
+        )?;
+
+        Ok(Listing {
+            items: listing.items.into_iter().map(Zone::from).collect(),
+            pages: listing.pages,
+            truncated: listing.truncated,
+            total_count: listing.total_count,
+        })
+    }
+
+    fn create_zone(&mut self, account: &Account, name: &str, setup: ZoneSetup) -> Result<Zone> {
+        let account_id = account
+            .account_id
+            .as_deref()
+            .ok_or_else(|| anyhow!("an account ID is required to create zones"))?;
+        let url = format!("{}/zones", self.base_for(account));
+        let body = serde_json::json!({
+            "name": name,
+            "account": {"id": account_id},
+            "type": setup,
+        });
+        let response = self
+            .send(Replay::BeforeResponse, || {
+                self.with_auth(self.client.post(url.as_str()), account)
+                    .json(&body)
+            })
+            .with_context(|| format!("Creating zone {name}"))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<CfZone> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse create zone response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Create zone", status, parsed.error_message(), text).into());
+        }
+
+        parsed
+            .result
+            .map(Zone::from)
+            .ok_or_else(|| anyhow!("Create zone succeeded but missing result"))
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        let url = format!("{}/zones/{}", self.base_for(account), zone.id);
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.delete(url.as_str()), account)
+            })
+            .with_context(|| format!("Deleting zone {}", zone.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfDeleteResponse = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse delete zone response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Delete zone", status, parsed.error_message(), text).into());
+        }
+
+        Ok(())
+    }
+
+    /// Walks `page=1..total_pages` of a list endpoint, concatenating results in order.
+    fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        account: &Account,
+        url: &str,
+        query: &[(&'static str, String)],
+        label: &str,
+        context: &str,
+    ) -> Result<Listing<T>> {
+        collect_pages(|page| self.get_page(account, url, query, page, label, context))
+    }
+
+    fn get_page<T: DeserializeOwned>(
+        &self,
+        account: &Account,
+        url: &str,
+        query: &[(&'static str, String)],
+        page: u32,
+        label: &str,
+        context: &str,
+    ) -> Result<Page<T>> {
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url), account)
+                    .query(query)
+                    .query(&[("page", page)])
+            })
+            .with_context(|| format!("{context} (page {page})"))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfResponse<T> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse Cloudflare {label} response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new(label, status, parsed.error_message(), text).into());
+        }
+
+        let info = parsed.result_info.unwrap_or_default();
+        Ok(Page {
+            items: parsed.result.unwrap_or_default(),
+            page,
+            total_pages: info.total_pages.unwrap_or(1),
+            total_count: info.total_count,
+        })
+    }
+
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        let url = format!("{}/zones/{}/dns_records", self.base_for(account), zone.id);
+        let mut query = vec![("per_page", CF_PER_PAGE.to_string())];
+        if let Some(record_type) = record_type {
+            query.push(("type", record_type.to_string()));
+        }
+        let page: Page<CfRecord> = self.get_page(
+            account,
+            &url,
+            &query,
+            page,
+            "Records",
+            &format!("Listing records for zone {}", zone.name),
+        )?;
+        Ok(Page {
+            items: page
+                .items
+                .into_iter()
+                .map(CfRecord::into_dns_record)
+                .collect(),
+            page: page.page,
+            total_pages: page.total_pages,
+            total_count: page.total_count,
+        })
+    }
+
+    /// Matches on name or content (`match=any`), walking every page of results.
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        let url = format!("{}/zones/{}/dns_records", self.base_for(account), zone.id);
+        let listing: Listing<CfRecord> = self.get_all_pages(
+            account,
+            &url,
+            &[
+                ("per_page", CF_PER_PAGE.to_string()),
+                ("name.contains", query.to_string()),
+                ("content.contains", query.to_string()),
+                ("match", "any".to_string()),
+            ],
+            "Search",
+            &format!("Searching records in {}", zone.name),
+        )?;
+        Ok(listing
+            .items
+            .into_iter()
+            .map(CfRecord::into_dns_record)
+            .collect())
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let url = format!("{}/zones/{}/dns_records", self.base_for(account), zone.id);
+        let response = self
+            .send(Replay::BeforeResponse, || {
+                self.with_auth(self.client.post(url.as_str()), account)
+                    .json(&CfRecordWrite::from_record(&record))
+            })
+            .with_context(|| format!("Creating record {}", record.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<CfRecord> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse create record response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Create", status, parsed.error_message(), text).into());
+        }
+
+        let result = parsed
+            .result
+            .ok_or_else(|| anyhow!("Create succeeded but missing result"))?;
+        Ok(result.into_dns_record())
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            self.base_for(account),
+            zone.id,
+            record.id
+        );
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.put(url.as_str()), account)
+                    .json(&CfRecordWrite::from_record(&record))
+            })
+            .with_context(|| format!("Updating record {}", record.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<CfRecord> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse update record response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Update", status, parsed.error_message(), text).into());
+        }
+
+        let result = parsed
+            .result
+            .ok_or_else(|| anyhow!("Update succeeded but missing result"))?;
+        Ok(result.into_dns_record())
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            self.base_for(account),
+            zone.id,
+            record_id
+        );
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.delete(url.as_str()), account)
+            })
+            .with_context(|| format!("Deleting record {}", record_id))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfDeleteResponse = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse delete record response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Delete", status, parsed.error_message(), text).into());
+        }
+
+        Ok(())
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            self.base_for(account),
+            zone.id,
+            record_id
+        );
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url.as_str()), account)
+            })
+            .with_context(|| format!("Fetching record {record_id}"))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<CfRecord> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse record response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Record", status, parsed.error_message(), text).into());
+        }
+
+        let result = parsed
+            .result
+            .ok_or_else(|| anyhow!("Record lookup succeeded but missing result"))?;
+        Ok(result.into_dns_record())
+    }
+
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        let url = format!(
+            "{}/zones/{}/dns_records/batch",
+            self.base_for(account),
+            zone.id
+        );
+        // Replaying posts after a response could create them twice.
+        let replay = if request.posts.is_empty() {
+            Replay::Always
+        } else {
+            Replay::BeforeResponse
+        };
+        let body = CfBatchRequest::from_request(&request);
+        let response = self
+            .send(replay, || {
+                self.with_auth(self.client.post(url.as_str()), account)
+                    .json(&body)
+            })
+            .with_context(|| {
+                format!(
+                    "Applying {} batched change(s) to {}",
+                    request.len(),
+                    zone.name
+                )
+            })?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<CfBatchResult> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse batch response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Batch", status, parsed.error_message(), text).into());
+        }
+
+        Ok(parsed.result.unwrap_or_default().into_batch_result())
+    }
+
+    fn record_audit_log(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        let account_id = account
+            .account_id
+            .as_deref()
+            .ok_or_else(|| anyhow!("an account ID is required to read the audit log"))?;
+        let url = format!(
+            "{}/accounts/{}/audit_logs",
+            self.base_for(account),
+            account_id
+        );
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url.as_str()), account)
+                    .query(&[
+                        ("zone.name", zone.name.as_str()),
+                        ("per_page", "100"),
+                        ("direction", "desc"),
+                    ])
+            })
+            .with_context(|| format!("Reading audit log for {}", zone.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfResponse<CfAuditLog> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse audit log response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Audit log", status, parsed.error_message(), text).into());
+        }
+
+        Ok(parsed
+            .result
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|log| log.resource.as_ref().and_then(|r| r.id.as_deref()) == Some(record_id))
+            .filter_map(CfAuditLog::into_audit_entry)
+            .collect())
+    }
+
+    /// API tokens go through `/user/tokens/verify`; global keys have no status of their
+    /// own, so a successful `/user` lookup counts as active.
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        let path = match account.auth_mode {
+            AuthMode::Token => "user/tokens/verify",
+            AuthMode::GlobalKey => "user",
+        };
+        let url = format!("{}/{path}", self.base_for(account));
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url.as_str()), account)
+            })
+            .with_context(|| format!("Verifying credentials for {}", account.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<serde_json::Value> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse verify response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Verify", status, parsed.error_message(), text).into());
+        }
+
+        match account.auth_mode {
+            AuthMode::Token => serde_json::from_value(parsed.result.unwrap_or_default())
+                .with_context(|| format!("Unexpected verify result: {}", truncate_body(&text))),
+            AuthMode::GlobalKey => Ok(TokenStatus {
+                status: "active".to_string(),
+                expires_on: None,
+            }),
+        }
+    }
+
+    fn list_cf_accounts(&mut self, account: &Account) -> Result<Vec<CloudflareAccount>> {
+        let url = format!("{}/accounts", self.base_for(account));
+        let listing = self.get_all_pages(
+            account,
+            &url,
+            &[("per_page", CF_ACCOUNTS_PER_PAGE.to_string())],
+            "Accounts",
+            &format!("Listing Cloudflare accounts for {}", account.name),
+        )?;
+        Ok(listing.items)
+    }
+
+    fn dnssec_status(&mut self, account: &Account, zone: &Zone) -> Result<DnssecStatus> {
+        let url = format!("{}/zones/{}/dnssec", self.base_for(account), zone.id);
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url.as_str()), account)
+            })
+            .with_context(|| format!("Loading DNSSEC status for {}", zone.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<DnssecStatus> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse DNSSEC response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("DNSSEC", status, parsed.error_message(), text).into());
+        }
+
+        parsed
+            .result
+            .ok_or_else(|| anyhow!("DNSSEC lookup succeeded but missing result"))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        enabled: bool,
+    ) -> Result<DnssecStatus> {
+        let url = format!("{}/zones/{}/dnssec", self.base_for(account), zone.id);
+        let body = serde_json::json!({
+            "status": if enabled { "active" } else { "disabled" },
+        });
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.patch(url.as_str()), account)
+                    .json(&body)
+            })
+            .with_context(|| format!("Updating DNSSEC for {}", zone.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<DnssecStatus> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse DNSSEC response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(
+                ApiError::new("DNSSEC update", status, parsed.error_message(), text).into(),
+            );
+        }
+
+        parsed
+            .result
+            .ok_or_else(|| anyhow!("DNSSEC update succeeded but missing result"))
+    }
+
+    /// Successful exports are plain BIND text; only failures come back as JSON.
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        let url = format!(
+            "{}/zones/{}/dns_records/export",
+            self.base_for(account),
+            zone.id
+        );
+        let response = self
+            .send(Replay::Always, || {
+                self.with_auth(self.client.get(url.as_str()), account)
+            })
+            .with_context(|| format!("Exporting zone {}", zone.name))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .with_context(|| format!("Reading export of {}", zone.name))?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<CfDeleteResponse>(&text)
+                .map(|parsed| parsed.error_message())
+                .unwrap_or_else(|_| "Unknown Cloudflare API error".to_string());
+            return Err(ApiError::new("Export", status, message, text).into());
+        }
+        Ok(text)
+    }
+
+    /// Multipart upload of the zone file. Not replayed once Cloudflare has answered,
+    /// since a second upload would add the records again.
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        proxied: bool,
+    ) -> Result<ImportResult> {
+        let contents = fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "zone.txt".to_string());
+        let url = format!(
+            "{}/zones/{}/dns_records/import",
+            self.base_for(account),
+            zone.id
+        );
+        let response = self
+            .send(Replay::BeforeResponse, || {
+                let form = Form::new()
+                    .part(
+                        "file",
+                        Part::bytes(contents.clone()).file_name(file_name.clone()),
+                    )
+                    .text("proxied", proxied.to_string());
+                self.with_auth(self.client.post(url.as_str()), account)
+                    .multipart(form)
+            })
+            .with_context(|| format!("Importing {} into {}", path.display(), zone.name))?;
+
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let parsed: CfItemResponse<ImportResult> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse import response: {text}"))?;
+
+        if !status.is_success() || !parsed.success {
+            return Err(ApiError::new("Import", status, parsed.error_message(), text).into());
+        }
+
+        parsed
+            .result
+            .ok_or_else(|| anyhow!("Import succeeded but missing result"))
+    }
+
+    fn zone_query(&self, account: &Account) -> Vec<(&'static str, String)> {
+        let mut params = vec![("per_page", CF_PER_PAGE.to_string())];
+        if let Some(account_id) = &account.account_id {
+            params.push(("account.id", account_id.clone()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize)]
+struct CfResponse<T> {
+    success: bool,
+    errors: Vec<CfError>,
+    result: Option<Vec<T>>,
+    #[serde(default)]
+    result_info: Option<CfResultInfo>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+struct CfResultInfo {
+    page: Option<u32>,
+    per_page: Option<u32>,
+    count: Option<u32>,
+    total_count: Option<u32>,
+    total_pages: Option<u32>,
+}
+
+impl<T> CfResponse<T> {
+    fn error_message(&self) -> String {
+        self.errors
+            .first()
+            .map(|e| e.message.clone())
+            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct CfItemResponse<T> {
+    success: bool,
+    errors: Vec<CfError>,
+    result: Option<T>,
+}
+
+impl<T> CfItemResponse<T> {
+    fn error_message(&self) -> String {
+        self.errors
+            .first()
+            .map(|e| e.message.clone())
+            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct CfDeleteResponse {
+    success: bool,
+    errors: Vec<CfError>,
+}
+
+impl CfDeleteResponse {
+    fn error_message(&self) -> String {
+        self.errors
+            .first()
+            .map(|e| e.message.clone())
+            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+    }
+}
+
+/// A request Cloudflare answered with an error, kept whole so the error modal can show
+/// its parts and copy the full body.
+#[derive(Debug)]
+struct ApiError {
+    operation: String,
+    status: StatusCode,
+    message: String,
+    body: String,
+}
+
+impl ApiError {
+    fn new(
+        operation: impl Into<String>,
+        status: StatusCode,
+        message: String,
+        body: String,
+    ) -> Self {
+        ApiError {
+            operation: operation.into(),
+            status,
+            message,
+            body,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}): {} | body: {}",
+            self.operation,
+            self.status,
+            self.message,
+            truncate_body(&self.body)
+        )
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+fn truncate_body(text: &str) -> String {
+    const LIMIT: usize = 200;
+    if text.len() > LIMIT {
+        format!("{}...", &text[..LIMIT])
+    } else {
+        text.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct CfError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct CfZone {
+    id: String,
+    name: String,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    name_servers: Option<Vec<String>>,
+    #[serde(default)]
+    original_name_servers: Option<Vec<String>>,
+    #[serde(default)]
+    plan: Option<CfPlan>,
+}
+
+#[derive(Deserialize)]
+struct CfPlan {
+    name: String,
+}
+
+impl From<CfZone> for Zone {
+    fn from(zone: CfZone) -> Self {
+        Zone {
+            id: zone.id,
+            name: zone.name,
+            status: zone.status,
+            name_servers: zone.name_servers.unwrap_or_default(),
+            original_name_servers: zone.original_name_servers.unwrap_or_default(),
+            plan: zone.plan.map(|plan| plan.name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CfRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    ttl: Option<u32>,
+    proxied: Option<bool>,
+    #[serde(default)]
+    comment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    settings: Option<serde_json::Value>,
+    #[serde(default)]
+    modified_on: Option<String>,
+}
+
+impl CfRecord {
+    fn into_dns_record(self) -> DnsRecord {
+        let mut unmodeled = Vec::new();
+        if self.comment.as_deref().is_some_and(|c| !c.is_empty()) {
+            unmodeled.push("comment".to_string());
+        }
+        if !self.tags.is_empty() {
+            unmodeled.push("tags".to_string());
+        }
+        if let Some(serde_json::Value::Object(settings)) = &self.settings
+            && settings
+                .values()
+                .any(|v| !v.is_null() && v != &serde_json::Value::Bool(false))
+        {
+            unmodeled.push("settings".to_string());
+        }
+        DnsRecord {
+            id: self.id,
+            name: self.name,
+            record_type: self.record_type,
+            content: self.content,
+            ttl: self.ttl.unwrap_or(300),
+            proxied: self.proxied.unwrap_or(false),
+            unmodeled,
+            modified_on: self.modified_on,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CfAuditLog {
+    id: String,
+    when: String,
+    action: CfAuditAction,
+    actor: Option<CfAuditActor>,
+    resource: Option<CfAuditResource>,
+    #[serde(rename = "oldValueJson", default)]
+    old_value_json: Option<serde_json::Value>,
+    #[serde(rename = "newValueJson", default)]
+    new_value_json: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct CfAuditAction {
+    #[serde(rename = "type")]
+    action_type: String,
+}
+
+#[derive(Deserialize)]
+struct CfAuditActor {
+    email: Option<String>,
+    #[serde(rename = "type")]
+    actor_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CfAuditResource {
+    id: Option<String>,
+}
+
+impl CfAuditLog {
+    fn into_audit_entry(self) -> Option<AuditEntry> {
+        let action = ChangeAction::from_audit_type(&self.action.action_type)?;
+        let timestamp = parse_rfc3339(&self.when)?;
+        let fields = |value: Option<&serde_json::Value>| -> [Option<String>; 5] {
+            let Some(value) = value.filter(|v| v.is_object()) else {
+                return Default::default();
+            };
+            ["name", "type", "content", "ttl", "proxied"].map(|key| match value.get(key) {
+                Some(serde_json::Value::String(s)) => Some(s.clone()),
+                Some(serde_json::Value::Null) | None => None,
+                Some(other) => Some(other.to_string()),
+            })
+        };
+        Some(AuditEntry {
+            id: self.id,
+            timestamp,
+            action,
+            actor: self.actor.and_then(|a| a.email.or(a.actor_type)),
+            changes: diff_fields(
+                fields(self.old_value_json.as_ref()),
+                fields(self.new_value_json.as_ref()),
+            ),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct CfRecordWrite {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    ttl: u32,
+    proxied: bool,
+}
+
+impl CfRecordWrite {
+    fn from_record(record: &DnsRecord) -> Self {
+        Self {
+            name: record.name.clone(),
+            record_type: record.record_type.clone(),
+            content: record.content.clone(),
+            ttl: record.ttl,
+            proxied: record.proxied,
+        }
+    }
+}
+
+/// Record mutations applied atomically by `DnsBackend::batch`, executed in Cloudflare's
+/// order: deletes, patches, puts, then posts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct BatchRequest {
+    /// Ids of records to delete.
+    deletes: Vec<String>,
+    /// Records (by id) whose fields should be updated in place.
+    patches: Vec<DnsRecord>,
+    /// Records (by id) to overwrite entirely.
+    puts: Vec<DnsRecord>,
+    /// New records; ids are ignored.
+    posts: Vec<DnsRecord>,
+}
+
+impl BatchRequest {
+    fn len(&self) -> usize {
+        self.deletes.len() + self.patches.len() + self.puts.len() + self.posts.len()
+    }
+}
+
+/// The records each part of a batch touched, as returned by the backend.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct BatchResult {
+    deletes: Vec<DnsRecord>,
+    patches: Vec<DnsRecord>,
+    puts: Vec<DnsRecord>,
+    posts: Vec<DnsRecord>,
+}
+
+#[derive(Serialize)]
+struct CfBatchRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deletes: Vec<CfBatchId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    patches: Vec<CfBatchWrite>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    puts: Vec<CfBatchWrite>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    posts: Vec<CfRecordWrite>,
+}
+
+#[derive(Serialize)]
+struct CfBatchId {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct CfBatchWrite {
+    id: String,
+    #[serde(flatten)]
+    record: CfRecordWrite,
+}
+
+impl CfBatchRequest {
+    fn from_request(request: &BatchRequest) -> Self {
+        let writes = |records: &[DnsRecord]| {
+            records
+                .iter()
+                .map(|r| CfBatchWrite {
+                    id: r.id.clone(),
+                    record: CfRecordWrite::from_record(r),
+                })
+                .collect()
+        };
+        Self {
+            deletes: request
+                .deletes
+                .iter()
+                .map(|id| CfBatchId { id: id.clone() })
+                .collect(),
+            patches: writes(&request.patches),
+            puts: writes(&request.puts),
+            posts: request
+                .posts
+                .iter()
+                .map(CfRecordWrite::from_record)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+struct CfBatchResult {
+    #[serde(default)]
+    deletes: Vec<CfRecord>,
+    #[serde(default)]
+    patches: Vec<CfRecord>,
+    #[serde(default)]
+    puts: Vec<CfRecord>,
+    #[serde(default)]
+    posts: Vec<CfRecord>,
+}
+
+impl CfBatchResult {
+    fn into_batch_result(self) -> BatchResult {
+        let convert =
+            |records: Vec<CfRecord>| records.into_iter().map(CfRecord::into_dns_record).collect();
+        BatchResult {
+            deletes: convert(self.deletes),
+            patches: convert(self.patches),
+            puts: convert(self.puts),
+            posts: convert(self.posts),
+        }
+    }
+}
+
+struct MockBackend {
+    records: HashMap<String, Vec<DnsRecord>>,
+    audit_log: Vec<AuditEntry>,
+    /// Splits record listings into pages of this size to exercise paginated loading.
+    per_page: Option<usize>,
+    /// API tokens `verify_account` reports as invalid; everything else verifies.
+    rejected_tokens: Vec<String>,
+    /// Zones added with `create_zone`, by account name, listed after the generated ones.
+    created_zones: HashMap<String, Vec<Zone>>,
+    /// Ids of zones removed with `delete_zone`, hidden from listings.
+    deleted_zones: HashSet<String>,
+    /// Zones with DNSSEC turned on, by zone id.
+    dnssec: HashMap<String, bool>,
+    /// Number of `list_zones` calls, for checking what gets refetched.
+    zone_list_calls: usize,
+    /// Ticks on every record write, standing in for `modified_on` timestamps.
+    clock: u32,
+    /// Injected failures, checked at the start of every backend call.
+    failures: Vec<FailureRule>,
+    /// Delay added to every backend call, standing in for network round trips; cut short
+    /// when the fetch is abandoned.
+    latency: Duration,
+    cancel: Cancel,
+    /// Generate this many records per zone instead of the three samples.
+    generate: Option<usize>,
+    /// Fixture file the data came from; every change is written back to it, and no
+    /// sample zones or records are generated.
+    fixture: Option<PathBuf>,
+}
+
+/// Offline dataset for `MockBackend::new_from_file`, e.g. `fixtures/demo.json`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Fixture {
+    accounts: Vec<FixtureAccount>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    failures: Vec<FailureRule>,
+    /// Added to every call, like `CF_TUI_MOCK_LATENCY`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    latency_ms: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Matched to config accounts by `name`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FixtureAccount {
+    name: String,
+    #[serde(default)]
+    zones: Vec<FixtureZone>,
+}
+
+/// A zone (`id`, `name` and the other zone fields) with its records inline.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FixtureZone {
+    #[serde(flatten)]
+    zone: Zone,
+    #[serde(default)]
+    records: Vec<DnsRecord>,
+}
+
+/// Makes the mock fail `operation` (a `DnsBackend` method name such as `create_record`,
+/// or `*` for every call) with `message` on the calls `when` picks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FailureRule {
+    operation: String,
+    message: String,
+    #[serde(default)]
+    when: FailWhen,
+    /// Matching calls seen so far.
+    #[serde(skip)]
+    calls: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FailWhen {
+    #[default]
+    Always,
+    /// The next n calls, then never again.
+    Next(u32),
+    /// Every nth call.
+    Every(u32),
+}
+
+impl FailureRule {
+    fn new(operation: &str, when: FailWhen, message: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            message: message.to_string(),
+            when,
+            calls: 0,
+        }
+    }
+
+    /// Parses `operation:when:message`, where `when` is `always`, `next`, `next N` or
+    /// `every N`.
+    fn parse(spec: &str) -> Result<Self> {
+        let mut parts = spec.trim().splitn(3, ':');
+        let (Some(operation), Some(when), Some(message)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!(
+                "Failure rule {spec:?} should look like operation:when:message"
+            ));
+        };
+        let count = |n: Option<&str>| -> Result<u32> {
+            match n {
+                None => Ok(1),
+                Some(n) => match n.trim().parse() {
+                    Ok(n) if n > 0 => Ok(n),
+                    _ => Err(anyhow!("Failure rule {spec:?} needs a positive count")),
+                },
+            }
+        };
+        let mut words = when.split_whitespace();
+        let when = match words.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("always") => FailWhen::Always,
+            Some("next") => FailWhen::Next(count(words.next())?),
+            Some("every") => FailWhen::Every(count(words.next())?),
+            _ => {
+                return Err(anyhow!(
+                    "Failure rule {spec:?}: expected always, next [N] or every N"
+                ));
+            }
+        };
+        Ok(Self::new(operation.trim(), when, message.trim()))
+    }
+
+    /// Counts a call to `operation` and says whether it should fail.
+    fn trips(&mut self, operation: &str) -> bool {
+        if self.operation != "*" && self.operation != operation {
+            return false;
+        }
+        self.calls += 1;
+        match self.when {
+            FailWhen::Always => true,
+            FailWhen::Next(n) => self.calls <= n,
+            FailWhen::Every(n) => n > 0 && self.calls.is_multiple_of(n),
+        }
+    }
+}
+
+impl MockBackend {
+    fn new() -> Self {
+        Self {
+            records: HashMap::new(),
+            audit_log: Vec::new(),
+            per_page: None,
+            rejected_tokens: Vec::new(),
+            created_zones: HashMap::new(),
+            deleted_zones: HashSet::new(),
+            dnssec: HashMap::new(),
+            zone_list_calls: 0,
+            clock: 0,
+            failures: Vec::new(),
+            latency: Duration::ZERO,
+            cancel: Cancel::default(),
+            generate: None,
+            fixture: None,
+        }
+    }
+
+    /// A mock serving the accounts, zones and records in the JSON fixture at `path`.
+    fn new_from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Reading fixture {}", path.display()))?;
+        let fixture: Fixture = serde_json::from_str(&text)
+            .with_context(|| format!("Parsing fixture {}", path.display()))?;
+        let mut mock = Self::new();
+        mock.failures = fixture.failures;
+        mock.latency = Duration::from_millis(fixture.latency_ms);
+        for account in fixture.accounts {
+            let zones = mock.created_zones.entry(account.name).or_default();
+            for FixtureZone { zone, records } in account.zones {
+                mock.records.insert(zone.id.clone(), records);
+                zones.push(zone);
+            }
+        }
+        mock.fixture = Some(path);
+        Ok(mock)
+    }
+
+    /// Writes the current data back to the fixture file, if there is one.
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.fixture else {
+            return Ok(());
+        };
+        let mut accounts: Vec<FixtureAccount> = self
+            .created_zones
+            .iter()
+            .map(|(name, zones)| FixtureAccount {
+                name: name.clone(),
+                zones: zones
+                    .iter()
+                    .filter(|z| !self.deleted_zones.contains(&z.id))
+                    .map(|zone| FixtureZone {
+                        zone: zone.clone(),
+                        records: self.records.get(&zone.id).cloned().unwrap_or_default(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        let text = serde_json::to_string_pretty(&Fixture {
+            accounts,
+            failures: self.failures.clone(),
+            latency_ms: self.latency.as_millis() as u64,
+        })?;
+        fs::write(path, text).with_context(|| format!("Writing fixture {}", path.display()))
+    }
+
+    /// A mock whose zones each hold `count` generated records of assorted types.
+    fn generating(count: usize) -> Self {
+        Self {
+            generate: Some(count),
+            ..Self::new()
+        }
+    }
+
+    /// Sets the per-call latency from a millisecond count; empty leaves it as it is.
+    fn with_latency(mut self, millis: &str) -> Result<Self> {
+        if !millis.trim().is_empty() {
+            let millis: u64 = millis.trim().parse().with_context(|| {
+                format!("Mock latency should be a number of milliseconds, not {millis:?}")
+            })?;
+            self.latency = Duration::from_millis(millis);
+        }
+        Ok(self)
+    }
+
+    /// Adds the rules in `specs`, separated by `;` (see `FailureRule::parse`).
+    fn with_failures(mut self, specs: &str) -> Result<Self> {
+        for spec in specs.split(';').filter(|s| !s.trim().is_empty()) {
+            self.failures.push(FailureRule::parse(spec)?);
+        }
+        Ok(self)
+    }
+
+    /// Fails every record create, update and delete with `message`.
+    #[cfg(test)]
+    fn fail_writes(&mut self, message: &str) {
+        for operation in ["create_record", "update_record", "delete_record"] {
+            self.failures
+                .push(FailureRule::new(operation, FailWhen::Always, message));
+        }
+    }
+
+    /// Waits out the configured latency, then returns the error of the first rule that
+    /// trips for this call to `operation`. Every matching rule counts the call, even when
+    /// an earlier one already failed it.
+    fn inject(&mut self, operation: &str) -> Result<()> {
+        if !self.latency.is_zero() && self.cancel.sleep(self.latency) {
+            return Err(anyhow!("Cancelled"));
+        }
+        let mut failure = None;
+        for rule in &mut self.failures {
+            if rule.trips(operation) && failure.is_none() {
+                failure = Some(rule.message.clone());
+            }
+        }
+        match failure {
+            Some(message) => Err(anyhow!(message)),
+            None => Ok(()),
+        }
+    }
+
+    fn dnssec_of(&self, zone: &Zone) -> DnssecStatus {
+        if !self.dnssec.get(&zone.id).copied().unwrap_or(false) {
+            return DnssecStatus {
+                status: "disabled".to_string(),
+                ds: None,
+                digest: None,
+            };
+        }
+        let digest = "2A8E4B7F0C3D9E1A5B6C7D8E9F0A1B2C3D4E5F60718293A4B5C6D7E8F9A0B1C2".to_string();
+        DnssecStatus {
+            status: "active".to_string(),
+            ds: Some(format!("{}. 3600 IN DS 2371 13 2 {digest}", zone.name)),
+            digest: Some(digest),
+        }
+    }
+
+    fn tick_clock(&mut self) -> String {
+        self.clock += 1;
+        format!("2024-01-01T00:00:00.{:06}Z", self.clock)
+    }
+
+    fn ensure_zone(&mut self, zone: &Zone) {
+        let generate = self.fixture.is_none();
+        let count = self.generate;
+        self.records.entry(zone.id.clone()).or_insert_with(|| {
+            if !generate {
+                return Vec::new();
+            }
+            if let Some(count) = count {
+                return generated_records(zone, count);
+            }
+            vec![
+                DnsRecord {
+                    id: format!("{}-a", zone.id),
+                    name: format!("api.{}", zone.name),
+                    record_type: "A".to_string(),
+                    content: "203.0.113.10".to_string(),
+                    ttl: 300,
+                    proxied: true,
+                    unmodeled: Vec::new(),
+                    modified_on: None,
+                },
+                DnsRecord {
+                    id: format!("{}-b", zone.id),
+                    name: format!("cdn.{}", zone.name),
+                    record_type: "CNAME".to_string(),
+                    content: "edge.service.net".to_string(),
+                    ttl: 120,
+                    proxied: true,
+                    unmodeled: Vec::new(),
+                    modified_on: None,
+                },
+                DnsRecord {
+                    id: format!("{}-c", zone.id),
+                    name: format!("mail.{}", zone.name),
+                    record_type: "MX".to_string(),
+                    content: "mail.{zone}".replace("{zone}", &zone.name),
+                    ttl: 3600,
+                    proxied: false,
+                    unmodeled: Vec::new(),
+                    modified_on: None,
+                },
+            ]
+        });
+    }
+}
+
+/// `count` records with a spread of names, types, TTLs and proxy settings, the same
+/// every time for a given zone.
+fn generated_records(zone: &Zone, count: usize) -> Vec<DnsRecord> {
+    const LABELS: [&str; 8] = ["api", "www", "mail", "cdn", "app", "vpn", "git", "status"];
+    const TTLS: [u32; 5] = [1, 60, 300, 3600, 86400];
+    (0..count)
+        .map(|i| {
+            let label = LABELS[i % LABELS.len()];
+            let (record_type, content) = match i % 6 {
+                0 | 1 => ("A", format!("198.51.{}.{}", i / 250 % 250, i % 250 + 1)),
+                2 => ("AAAA", format!("2001:db8::{:x}", i + 1)),
+                3 => ("CNAME", format!("{label}.edge.service.net")),
+                4 => ("MX", format!("10 mx{}.{}", i % 3 + 1, zone.name)),
+                _ => ("TXT", format!("\"v=generated; n={i}\"")),
+            };
+            DnsRecord {
+                id: format!("{}-gen-{i}", zone.id),
+                name: format!("{label}-{i}.{}", zone.name),
+                record_type: record_type.to_string(),
+                content,
+                ttl: TTLS[i % TTLS.len()],
+                proxied: is_proxiable(record_type) && i % 3 == 0,
+                unmodeled: Vec::new(),
+                modified_on: None,
+            }
+        })
+        .collect()
+}
+
+fn mock_name_servers() -> Vec<String> {
+    vec![
+        "ada.ns.cloudflare.com".to_string(),
+        "bob.ns.cloudflare.com".to_string(),
+    ]
+}
+
+impl DnsBackend for MockBackend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        self.inject("list_zones")?;
+        self.zone_list_calls += 1;
+        // Generate deterministic mock zones based on account name so the UI feels connected.
+        let base = account.name.replace(' ', "").to_lowercase();
+        let mut zones = if self.fixture.is_some() {
+            Vec::new()
+        } else {
+            vec![
+                Zone {
+                    id: format!("{}-01", base),
+                    name: format!("{}.example.com", base),
+                    status: Some("active".to_string()),
+                    name_servers: mock_name_servers(),
+                    original_name_servers: vec![
+                        "dns1.registrar-servers.com".to_string(),
+                        "dns2.registrar-servers.com".to_string(),
+                    ],
+                    plan: Some("Free Website".to_string()),
+                },
+                Zone {
+                    id: format!("{}-02", base),
+                    name: format!("{}.services.io", base),
+                    status: Some("pending".to_string()),
+                    name_servers: mock_name_servers(),
+                    original_name_servers: vec!["ns1.example-host.net".to_string()],
+                    plan: Some("Pro Website".to_string()),
+                },
+            ]
+        };
+        zones.extend(
+            self.created_zones
+                .get(&account.name)
+                .into_iter()
+                .flatten()
+                .cloned(),
+        );
+        zones.retain(|z| !self.deleted_zones.contains(&z.id));
+        Ok(Listing::single(zones))
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        self.inject("delete_zone")?;
+        if let Some(created) = self.created_zones.get_mut(&account.name) {
+            created.retain(|z| z.id != zone.id);
+        }
+        self.records.remove(&zone.id);
+        self.deleted_zones.insert(zone.id.clone());
+        self.persist()
+    }
+
+    fn create_zone(&mut self, account: &Account, name: &str, _setup: ZoneSetup) -> Result<Zone> {
+        self.inject("create_zone")?;
+        if account.account_id.is_none() {
+            return Err(anyhow!("an account ID is required to create zones"));
+        }
+        let created = self.created_zones.entry(account.name.clone()).or_default();
+        let zone = Zone {
+            id: format!("mock-{name}"),
+            name: name.to_string(),
+            status: Some("pending".to_string()),
+            name_servers: mock_name_servers(),
+            original_name_servers: Vec::new(),
+            plan: Some("Free Website".to_string()),
+        };
+        created.push(zone.clone());
+        self.deleted_zones.remove(&zone.id);
+        // New zones start empty rather than with the generated sample records.
+        self.records.insert(zone.id.clone(), Vec::new());
+        self.persist()?;
+        Ok(zone)
+    }
+
+    fn list_records_page(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        self.inject("list_records_page")?;
+        self.ensure_zone(zone);
+        let records: Vec<DnsRecord> = self
+            .records
+            .get(&zone.id)
+            .into_iter()
+            .flatten()
+            .filter(|r| record_type.is_none_or(|t| r.record_type.eq_ignore_ascii_case(t)))
+            .cloned()
+            .collect();
+        let Some(per_page) = self.per_page.filter(|&n| n > 0) else {
+            return Ok(Page {
+                items: records,
+                page,
+                total_pages: 1,
+                total_count: None,
+            });
+        };
+        let total_pages = records.len().div_ceil(per_page).max(1) as u32;
+        let total_count = Some(records.len() as u32);
+        let start = (page.saturating_sub(1) as usize) * per_page;
+        Ok(Page {
+            items: records.into_iter().skip(start).take(per_page).collect(),
+            page,
+            total_pages,
+            total_count,
+        })
+    }
+
+    fn search_records(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        self.inject("search_records")?;
+        self.ensure_zone(zone);
+        let needle = query.to_lowercase();
+        Ok(self
+            .records
+            .get(&zone.id)
+            .map(|records| {
+                records
+                    .iter()
+                    .filter(|r| {
+                        r.name.to_lowercase().contains(&needle)
+                            || r.content.to_lowercase().contains(&needle)
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn create_record(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.inject("create_record")?;
+        self.ensure_zone(zone);
+        let modified_on = self.tick_clock();
+        let records = self.records.entry(zone.id.clone()).or_default();
+        let mut new_record = record;
+        new_record.id = format!("{}-{}", zone.id, records.len() + 1);
+        new_record.modified_on = Some(modified_on);
+        records.push(new_record.clone());
+        self.persist()?;
+        Ok(new_record)
+    }
+
+    fn update_record(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.inject("update_record")?;
+        self.ensure_zone(zone);
+        let mut record = record;
+        record.modified_on = Some(self.tick_clock());
+        if let Some(records) = self.records.get_mut(&zone.id)
+            && let Some(existing) = records.iter_mut().find(|r| r.id == record.id)
+        {
+            *existing = record.clone();
+        }
+        self.persist()?;
+        Ok(record)
+    }
+
+    fn delete_record(&mut self, _account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        self.inject("delete_record")?;
+        if let Some(records) = self.records.get_mut(&zone.id) {
+            records.retain(|r| r.id != record_id);
+        }
+        self.persist()
+    }
+
+    fn get_record(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        record_id: &str,
+    ) -> Result<DnsRecord> {
+        self.inject("get_record")?;
+        self.ensure_zone(zone);
+        self.records[&zone.id]
+            .iter()
+            .find(|r| r.id == record_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Record {record_id} not found"))
+    }
+
+    /// Works on a copy of the zone and only stores it once every operation succeeded.
+    fn batch(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        self.inject("batch")?;
+        self.ensure_zone(zone);
+        let mut records = self.records.get(&zone.id).cloned().unwrap_or_default();
+        let position = |records: &[DnsRecord], id: &str| {
+            records
+                .iter()
+                .position(|r| r.id == id)
+                .ok_or_else(|| anyhow!("Batch: record {id} not found; no changes were applied"))
+        };
+        let mut result = BatchResult::default();
+
+        for id in &request.deletes {
+            let idx = position(&records, id)?;
+            result.deletes.push(records.remove(idx));
+        }
+        for (updates, touched) in [
+            (&request.patches, &mut result.patches),
+            (&request.puts, &mut result.puts),
+        ] {
+            for update in updates {
+                let idx = position(&records, &update.id)?;
+                records[idx] = update.clone();
+                touched.push(update.clone());
+            }
+        }
+        for post in &request.posts {
+            let mut created = post.clone();
+            let mut n = records.len() + 1;
+            while records
+                .iter()
+                .any(|r| r.id == format!("{}-batch-{n}", zone.id))
+            {
+                n += 1;
+            }
+            created.id = format!("{}-batch-{n}", zone.id);
+            records.push(created.clone());
+            result.posts.push(created);
+        }
+
+        self.records.insert(zone.id.clone(), records);
+        self.persist()?;
+        Ok(result)
+    }
+
+    fn record_audit_log(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        self.inject("record_audit_log")?;
+        Ok(self.audit_log.clone())
+    }
+
+    fn import_zone(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        path: &Path,
+        proxied: bool,
+    ) -> Result<ImportResult> {
+        self.inject("import_zone")?;
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let parsed = bind::parse_zone(&text, &zone.name)?;
+        self.ensure_zone(zone);
+        let records = self.records.entry(zone.id.clone()).or_default();
+        let mut added = 0u32;
+        for mut record in parsed
+            .iter()
+            .filter(|r| !is_managed_by_cloudflare(r, &zone.name))
+            .cloned()
+        {
+            record.id = format!("{}-import-{}", zone.id, records.len() + 1);
+            record.proxied |= proxied && is_proxiable(&record.record_type);
+            records.push(record);
+            added += 1;
+        }
+        self.persist()?;
+        Ok(ImportResult {
+            added,
+            parsed: added,
+        })
+    }
+
+    fn export_zone(&mut self, _account: &Account, zone: &Zone) -> Result<String> {
+        self.inject("export_zone")?;
+        self.ensure_zone(zone);
+        let mut text = format!(";; Mock export of {}\n$ORIGIN {}.\n", zone.name, zone.name);
+        for record in self.records.get(&zone.id).into_iter().flatten() {
+            text.push_str(&format!(
+                "{}.\t{}\tIN\t{}\t{}\n",
+                record.name, record.ttl, record.record_type, record.content
+            ));
+        }
+        Ok(text)
+    }
+
+    fn list_cf_accounts(&mut self, _account: &Account) -> Result<Vec<CloudflareAccount>> {
+        self.inject("list_cf_accounts")?;
+        Ok(vec![
+            CloudflareAccount {
+                id: "0f1e2d3c4b5a69788796a5b4c3d2e1f0".to_string(),
+                name: "Mock Personal".to_string(),
+            },
+            CloudflareAccount {
+                id: "9a8b7c6d5e4f30211203f4e5d6c7b8a9".to_string(),
+                name: "Mock Agency".to_string(),
+            },
+        ])
+    }
+
+    fn dnssec_status(&mut self, _account: &Account, zone: &Zone) -> Result<DnssecStatus> {
+        self.inject("dnssec_status")?;
+        Ok(self.dnssec_of(zone))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        _account: &Account,
+        zone: &Zone,
+        enabled: bool,
+    ) -> Result<DnssecStatus> {
+        self.inject("set_dnssec")?;
+        self.dnssec.insert(zone.id.clone(), enabled);
+        Ok(self.dnssec_of(zone))
+    }
+
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        self.inject("verify_account")?;
+        if self.rejected_tokens.contains(&account.api_token) {
+            return Err(anyhow!("Verify (401 Unauthorized): Invalid API Token"));
+        }
+        Ok(TokenStatus {
+            status: "active".to_string(),
+            expires_on: None,
+        })
+    }
+
+    fn watch_cancel(&mut self, cancel: Cancel) {
+        self.cancel = cancel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_config_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("cloudflare_tui_test_{name}_{nanos}.json"))
+    }
+
+    fn test_account() -> Account {
+        Account {
+            name: "demo".to_string(),
+            api_token: "token".to_string(),
+            email: None,
+            account_id: None,
+            account_name: None,
+            auth_mode: AuthMode::Token,
+            base_url: None,
+            provider: Provider::Cloudflare,
+            aws_access_key_id: None,
+            zone_dir: None,
+            dns_server: None,
+            tsig_key_name: None,
+            tsig_algorithm: None,
+            zones: Vec::new(),
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl: None,
+            default_proxied: None,
+            label: None,
+            color: None,
+        }
+    }
+
+    fn record(id: &str, name: &str, record_type: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: id.to_string(),
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            content: content.to_string(),
+            ttl: 300,
+            proxied: false,
+            unmodeled: Vec::new(),
+            modified_on: None,
+        }
+    }
+
+    fn app_with_records(test_name: &str, records: Vec<DnsRecord>) -> App<MockBackend> {
+        let mut backend = MockBackend::new();
+        backend
+            .records
+            .insert("demo-01".to_string(), records.clone());
+
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app = App::new(temp_config_path(test_name), config, backend).unwrap();
+        app.records = records;
+        app.record_page = 0;
+        app.selected_record = 0;
+        app
+    }
+
+    #[test]
+    fn editing_only_the_account_name_keeps_the_token() {
+        let mut app = app_with_records("edit_account_name", Vec::new());
+        app.accounts[0].api_token = "cf-secret-123".to_string();
+        app.accounts[0].account_id = Some("acc-1".to_string());
+        app.accounts[0].account_name = Some("Demo Corp".to_string());
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('e'), &mut app).unwrap();
+        let Mode::AddingAccount(form) = &app.mode else {
+            panic!("expected the account form, got {:?}", app.mode);
+        };
+        assert_eq!((form.name.as_str(), form.api_token.as_str()), ("demo", ""));
+        assert_eq!(form.account_id, "acc-1");
+        let screen = draw_app(&mut app);
+        assert!(
+            screen.contains("•••••••• (saved; type to replace)"),
+            "{screen}"
+        );
+        assert!(!screen.contains("cf-secret-123"), "{screen}");
+
+        for c in "-prod".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        for _ in 0..4 {
+            handle_key(KeyCode::Enter, &mut app).unwrap();
+        }
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "Saved account demo-prod");
+        let account = &app.accounts[0];
+        assert_eq!(account.name, "demo-prod");
+        assert_eq!(account.api_token, "cf-secret-123");
+        assert_eq!(account.account_name.as_deref(), Some("Demo Corp"));
+        assert_eq!(app.accounts.len(), 1);
+        let saved = Config::load(&app.config_path).unwrap();
+        assert_eq!(saved.accounts, app.accounts);
+        assert!(!app.zones.is_empty(), "the account was reloaded");
+
+        handle_normal_key(KeyCode::Char('e'), &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        for c in "fresh".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.accounts[0].api_token, "fresh");
+        assert_eq!(app.accounts[0].name, "demo-prod");
+    }
+
+    #[test]
+    fn account_form_masks_the_token_until_f2() {
+        let mut app = app_with_records("mask_token", Vec::new());
+        app.mode = Mode::AddingAccount(AccountForm::default());
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        for c in "cf-secret-123".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        let screen = draw_app(&mut app);
+        assert!(!screen.contains("cf-secret-123"), "{screen}");
+        assert!(screen.contains("(13 chars, F2 to show)"), "{screen}");
+
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        assert!(draw_app(&mut app).contains("cf-secret-123"));
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        assert!(!draw_app(&mut app).contains("cf-secret-123"));
+
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('a'), &mut app).unwrap();
+        let Mode::AddingAccount(form) = &app.mode else {
+            panic!("expected the account form, got {:?}", app.mode);
+        };
+        assert!(!form.reveal_secret, "a new form starts masked");
+
+        assert_eq!(mask_secret("cf-secret-123"), "••••-123");
+        assert_eq!(mask_secret("short"), "••••");
+        app.accounts[0].api_token = "cf-secret-123".to_string();
+        assert!(!format!("{:?}", app.accounts[0]).contains("cf-secret"));
+    }
+
+    #[test]
+    fn pastes_land_whole_in_the_focused_field() {
+        let mut app = app_with_records("paste", Vec::new());
+        let before = app.mode.clone();
+        handle_paste("q", &mut app);
+        assert_eq!(app.mode, before, "Normal mode ignores pastes");
+
+        app.mode = Mode::AddingAccount(AccountForm::default());
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_paste("cf-Secret_token-9876\r\n", &mut app);
+        let Mode::AddingAccount(form) = &app.mode else {
+            panic!("a pasted newline must not submit, got {:?}", app.mode);
+        };
+        assert_eq!(form.api_token, "cf-Secret_token-9876");
+        assert_eq!(form.field_index, 2);
+
+        app.mode = Mode::Normal;
+        app.start_zone_filter();
+        handle_paste("no-such-zone\n", &mut app);
+        assert_eq!(app.zone_filter, "no-such-zone");
+        assert!(matches!(
+            &app.mode,
+            Mode::FilteringZones(prompt) if prompt.highlight.is_none()
+        ));
+    }
+
+    #[test]
+    fn adding_an_account_with_a_rejected_token_keeps_the_form_open() {
+        let mut backend = MockBackend::new();
+        backend.rejected_tokens.push("truncated".to_string());
+        let mut app = App::new(
+            temp_config_path("verify_reject"),
+            Config::default(),
+            backend,
+        )
+        .unwrap();
+        app.mode = Mode::AddingAccount(AccountForm {
+            name: "work".to_string(),
+            api_token: "truncated".to_string(),
+            field_index: AccountForm::SUBMIT_FROM_FIELD,
+            ..AccountForm::default()
+        });
+
+        handle_add_account_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert!(app.accounts.is_empty());
+        assert!(matches!(app.mode, Mode::AddingAccount(_)));
+        assert!(
+            app.last_message.contains("Invalid API Token"),
+            "{}",
+            app.last_message
+        );
+        assert!(!app.config_path.exists());
+    }
+
+    #[test]
+    fn adding_an_account_without_id_offers_the_discovered_accounts() {
+        let mut app = App::new(
+            temp_config_path("account_picker"),
+            Config::default(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        app.mode = Mode::AddingAccount(AccountForm {
+            name: "work".to_string(),
+            api_token: "token".to_string(),
+            field_index: AccountForm::SUBMIT_FROM_FIELD,
+            ..AccountForm::default()
+        });
+
+        handle_add_account_key(KeyCode::Enter, &mut app).unwrap();
+        let Mode::PickAccount(picker) = &app.mode else {
+            panic!("account picker did not open");
+        };
+        assert_eq!(picker.choices.len(), 2);
+        assert!(app.accounts.is_empty());
+
+        handle_account_picker_key(KeyCode::Down, &mut app).unwrap();
+        handle_account_picker_key(KeyCode::Down, &mut app).unwrap();
+        handle_account_picker_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert_eq!(app.mode, Mode::Normal);
+        let account = &app.accounts[0];
+        assert_eq!(
+            account.account_id.as_deref(),
+            Some("9a8b7c6d5e4f30211203f4e5d6c7b8a9")
+        );
+        assert_eq!(account.account_name.as_deref(), Some("Mock Agency"));
+        let saved = Config::load(&app.config_path).unwrap();
+        assert_eq!(
+            saved.accounts[0].account_name.as_deref(),
+            Some("Mock Agency")
+        );
+    }
+
+    #[test]
+    fn verify_key_reports_token_status() {
+        let mut app = app_with_records("verify_key", Vec::new());
+        handle_normal_key(KeyCode::Char('v'), &mut app).unwrap();
+        assert_eq!(app.last_message, "demo: token active (no expiry)");
+    }
+
+    #[test]
+    fn account_form_builds_token_accounts() {
+        let form = AccountForm {
+            name: "personal".to_string(),
+            api_token: "token".to_string(),
+            ..AccountForm::default()
+        };
+        let account = form.build_account().unwrap();
+        assert_eq!(account.auth_mode, AuthMode::Token);
+        assert_eq!(account.email, None);
+    }
+
+    #[test]
+    fn account_form_requires_email_for_global_key() {
+        let mut app = App::new(
+            temp_config_path("global_key_form"),
+            Config::default(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        app.start_add_account();
+        for c in "legacy".chars() {
+            handle_add_account_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_add_account_key(KeyCode::Tab, &mut app).unwrap();
+        handle_add_account_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_add_account_key(KeyCode::Tab, &mut app).unwrap();
+        for c in "global-key".chars() {
+            handle_add_account_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+
+        let Mode::AddingAccount(form) = &app.mode else {
+            panic!("app left add account mode");
+        };
+        assert_eq!(form.auth_mode, AuthMode::GlobalKey);
+        assert_eq!(form.secret_label(), "Global API Key");
+        assert!(!form.is_ready());
+        assert_eq!(
+            form.build_account().unwrap_err(),
+            "Email is required for Global API Key accounts"
+        );
+
+        let mut form = form.clone();
+        form.email = "ops@example.com".to_string();
+        let account = form.build_account().unwrap();
+        assert_eq!(account.auth_mode, AuthMode::GlobalKey);
+        assert_eq!(account.api_token, "global-key");
+        assert_eq!(account.email.as_deref(), Some("ops@example.com"));
+
+        form.field_index = AccountForm::AUTH_MODE_FIELD;
+        form.toggle_auth_mode();
+        assert_eq!(form.auth_mode, AuthMode::Token);
+        assert!(form.is_ready());
+    }
+
+    #[test]
+    fn add_account_form_allows_typing_command_keys() {
+        let mut app = App::new(
+            temp_config_path("add_form"),
+            Config::default(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        let quit = handle_add_account_key(KeyCode::Char('q'), &mut app).unwrap();
+
+        assert!(!quit, "q should not quit while typing");
+        if let Mode::AddingAccount(form) = app.mode {
+            assert_eq!(form.name, "q");
+        } else {
+            panic!("app left add account mode");
+        }
+    }
+
+    #[test]
+    fn normal_mode_can_start_add_account_when_accounts_exist() {
+        let mut app = app_with_records("add_account", vec![]);
+        let quit = handle_normal_key(KeyCode::Char('a'), &mut app).unwrap();
+
+        assert!(!quit, "a should not quit the app");
+        assert!(
+            matches!(app.mode, Mode::AddingAccount(_)),
+            "app should enter add-account mode"
+        );
+    }
+
+    #[test]
+    fn h_and_l_move_focus_between_panels_without_wrapping() {
+        let mut app = app_with_records("vim_focus", vec![]);
+        app.focus = Focus::Accounts;
+        let mut visited = Vec::new();
+        for key in ['l', 'l', 'l', 'h', 'h', 'h'] {
+            let quit = handle_normal_key(KeyCode::Char(key), &mut app).unwrap();
+            assert!(!quit);
+            assert_eq!(app.mode, Mode::Normal, "{key} opened something");
+            visited.push(app.focus);
+        }
+        assert_eq!(
+            visited,
+            [
+                Focus::Zones,
+                Focus::Records,
+                Focus::Records,
+                Focus::Zones,
+                Focus::Accounts,
+                Focus::Accounts
+            ]
+        );
+    }
+
+    #[test]
+    fn j_and_k_move_like_down_and_up_in_every_panel() {
+        let mut app = App::new(
+            temp_config_path("vim_move"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.selected_record, 2);
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert_eq!(app.selected_record, 1);
+
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.selected_zone, 1);
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert_eq!(app.selected_zone, 0);
+
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "other");
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "demo");
+        assert_eq!(app.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn each_zone_gets_its_filter_back_when_shown_again() {
+        let mut app = App::new(
+            temp_config_path("zone_filters"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        let search = |app: &mut App<MockBackend>, text: &str| {
+            app.focus = Focus::Records;
+            handle_normal_key(KeyCode::Char('/'), app).unwrap();
+            for c in text.chars() {
+                handle_key(KeyCode::Char(c), app).unwrap();
+            }
+            handle_key(KeyCode::Enter, app).unwrap();
+        };
+        search(&mut app, "_acme");
+
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
+        assert_eq!(
+            app.record_filter, "",
+            "a zone without a filter shows everything"
+        );
+        search(&mut app, "www");
+
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert_eq!(app.record_filter, "_acme");
+        assert!(draw_app(&mut app).contains("filtered \"_acme\""));
+        app.refresh_current().unwrap();
+        assert_eq!(app.record_filter, "_acme", "a refresh keeps it");
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.record_filter, "");
+        assert_eq!(app.last_message, "Record filter cleared");
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(
+            app.record_filter, "www",
+            "clearing one zone leaves the others"
+        );
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert_eq!(app.record_filter, "");
+    }
+
+    #[test]
+    fn the_last_account_and_zone_are_restored_at_startup() {
+        let path = temp_config_path("session_state");
+        let start = || App::new(&path, two_account_config(), MockBackend::new()).unwrap();
+        let mut app = start();
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        app.remember_selection();
+        let text = fs::read_to_string(SessionState::path_for(&path)).unwrap();
+        assert!(!text.contains("token"), "{text}");
+
+        let app = start();
+        assert_eq!(app.current_account().unwrap().name, "other");
+        assert_eq!(app.current_zone().unwrap().id, "other-02");
+        assert!(!app.records.is_empty(), "the restored zone's records load");
+    }
+
+    /// A session state file naming `account` and `zone_id`, as if saved by an earlier run.
+    fn saved_session(test_name: &str, account: &str, zone_id: &str) -> PathBuf {
+        let path = temp_config_path(test_name);
+        SessionState {
+            account: Some(account.to_string()),
+            zone_id: Some(zone_id.to_string()),
+        }
+        .save(&SessionState::path_for(&path))
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn a_remembered_zone_that_is_gone_falls_back_to_the_first() {
+        let path = saved_session("session_stale_zone", "other", "gone");
+        let mut app = App::new(&path, two_account_config(), MockBackend::new()).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "other");
+        assert_eq!(app.selected_zone, 0);
+        assert_eq!(app.current_zone().unwrap().id, "other-01");
+        assert!(!app.records.is_empty(), "the first zone's records load");
+        assert!(
+            app.messages.iter().all(|m| m.level != MessageLevel::Error),
+            "the fallback is silent"
+        );
+
+        app.remember_selection();
+        assert_eq!(
+            SessionState::load(&SessionState::path_for(&path)),
+            SessionState {
+                account: Some("other".to_string()),
+                zone_id: Some("other-01".to_string()),
+            },
+            "the stale zone is forgotten"
+        );
+    }
+
+    #[test]
+    fn a_remembered_account_that_is_gone_or_a_broken_file_starts_at_the_top() {
+        let path = saved_session("session_stale_account", "removed", "other-02");
+        let app = App::new(&path, two_account_config(), MockBackend::new()).unwrap();
+        assert_eq!((app.selected_account, app.selected_zone), (0, 0));
+
+        fs::write(SessionState::path_for(&path), "{not json").unwrap();
+        let app = App::new(&path, two_account_config(), MockBackend::new()).unwrap();
+        assert_eq!((app.selected_account, app.selected_zone), (0, 0));
+    }
+
+    #[test]
+    fn g_and_end_keys_jump_across_record_pages() {
+        let records = (1..=25)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("jump", records);
+        app.focus = Focus::Records;
+
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!((app.selected_record, app.record_page), (24, 2));
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        assert_eq!((app.selected_record, app.record_page), (0, 0));
+
+        // A single g followed by anything else is dropped.
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        assert_eq!(app.selected_record, 1);
+        assert_eq!(app.pending_key, Some('g'));
+
+        handle_normal_key(KeyCode::End, &mut app).unwrap();
+        assert_eq!(app.pending_key, None);
+        assert_eq!((app.selected_record, app.record_page), (24, 2));
+        assert_eq!(app.paged_records().last().unwrap().id, "25");
+        handle_normal_key(KeyCode::Home, &mut app).unwrap();
+        assert_eq!((app.selected_record, app.record_page), (0, 0));
+
+        app.record_filter = "r1".to_string();
+        handle_normal_key(KeyCode::End, &mut app).unwrap();
+        assert_eq!((app.selected_record, app.record_page), (10, 1));
+    }
+
+    #[test]
+    fn g_and_end_keys_jump_in_the_zone_and_account_lists() {
+        let mut app = App::new(
+            temp_config_path("jump_lists"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::End, &mut app).unwrap();
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
+        assert_eq!(app.records[0].id, "demo-02-a");
+        handle_normal_key(KeyCode::Home, &mut app).unwrap();
+        assert_eq!(app.current_zone().unwrap().id, "demo-01");
+
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "other");
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('g'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "demo");
+    }
+
+    fn click(app: &mut App<MockBackend>, column: u16, row: u16) {
+        let mouse = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        };
+        handle_mouse(mouse, app).unwrap();
+    }
+
+    /// Draws the app on a 100x30 test terminal and returns the screen's text.
+    fn draw_app(app: &mut App<MockBackend>) -> String {
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn clicks_focus_panels_and_select_rows() {
+        let mut app = App::new(
+            temp_config_path("mouse"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        draw_app(&mut app);
+        let PanelLayout {
+            accounts,
+            zones,
+            records,
+            ..
+        } = app.panels;
+
+        click(&mut app, zones.x + 3, zones.y + 2);
+        assert_eq!(app.focus, Focus::Zones);
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
+
+        // Below the last record: focuses the table without changing the selection.
+        click(&mut app, records.x + 3, records.y + 10);
+        assert_eq!(app.focus, Focus::Records);
+        assert_eq!(app.selected_record, 0);
+        // The header row isn't a record.
+        click(&mut app, records.x + 3, records.y + 1);
+        assert_eq!(app.selected_record, 0);
+        click(&mut app, records.x + 3, records.y + 4);
+        assert_eq!(app.selected_record, 2);
+        assert_eq!(app.mode, Mode::Normal);
+
+        click(&mut app, accounts.x, accounts.y + 2);
+        assert_eq!(app.focus, Focus::Accounts);
+        assert_eq!(app.current_account().unwrap().name, "demo", "border click");
+        click(&mut app, accounts.x + 3, accounts.y + 2);
+        assert_eq!(app.current_account().unwrap().name, "other");
+    }
+
+    #[test]
+    fn double_clicking_a_record_opens_the_edit_form() {
+        let records = (1..=25)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("mouse_edit", records);
+        draw_app(&mut app);
+        app.next_page();
+        let records = app.panels.records;
+
+        click(&mut app, records.x + 3, records.y + 3);
+        let selected = app.page_size() + 1;
+        assert_eq!(app.selected_record, selected);
+        click(&mut app, records.x + 3, records.y + 2);
+        click(&mut app, records.x + 3, records.y + 3);
+        assert_eq!(app.mode, Mode::Normal, "clicks on different rows");
+        click(&mut app, records.x + 3, records.y + 3);
+        match &app.mode {
+            Mode::RecordForm(form) => {
+                assert!(form.is_edit);
+                assert_eq!(form.target_id, Some((selected + 1).to_string()));
+            }
+            other => panic!("expected the edit form, got {other:?}"),
+        }
+
+        // Overlays keep the clicks to themselves.
+        click(&mut app, records.x + 3, records.y + 2);
+        assert_eq!(app.selected_record, selected);
+    }
+
+    fn scroll(app: &mut App<MockBackend>, area: Rect, down: bool) {
+        let mouse = MouseEvent {
+            kind: if down {
+                MouseEventKind::ScrollDown
+            } else {
+                MouseEventKind::ScrollUp
+            },
+            column: area.x + 3,
+            row: area.y + 1,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        };
+        handle_mouse(mouse, app).unwrap();
+    }
+
+    #[test]
+    fn scroll_wheel_moves_the_record_selection_across_pages() {
+        let records = (1..=25)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("wheel", records);
+        app.focus = Focus::Accounts;
+        draw_app(&mut app);
+        let area = app.panels.records;
+        let page_size = app.page_size();
+
+        for _ in 0..4 {
+            scroll(&mut app, area, true);
+        }
+        assert_eq!(app.focus, Focus::Records);
+        assert_eq!(app.selected_record, 12);
+        assert_eq!(app.record_page, 12 / page_size);
+        assert!(app.paged_records().iter().any(|r| r.id == "13"));
+
+        for _ in 0..10 {
+            scroll(&mut app, area, true);
+        }
+        assert_eq!(app.selected_record, 24, "stops at the last record");
+
+        app.config.scroll_step = Some(20);
+        scroll(&mut app, area, false);
+        assert_eq!((app.selected_record, app.record_page), (4, 0));
+        scroll(&mut app, area, false);
+        assert_eq!(app.selected_record, 0);
+    }
+
+    #[test]
+    fn scroll_wheel_over_zones_moves_the_zone_selection() {
+        let mut app = App::new(
+            temp_config_path("wheel_zones"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        draw_app(&mut app);
+        let area = app.panels.zones;
+
+        scroll(&mut app, area, true);
+        assert_eq!(app.focus, Focus::Zones);
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
+        assert_eq!(app.records[0].id, "demo-02-a");
+        scroll(&mut app, area, true);
+        assert_eq!(app.current_zone().unwrap().id, "demo-02", "no wrapping");
+        scroll(&mut app, area, false);
+        assert_eq!(app.current_zone().unwrap().id, "demo-01");
+    }
+
+    #[test]
+    fn help_overlay_scrolls_closes_and_runs_listed_keys() {
+        let mut app = app_with_records("help", vec![]);
+        app.focus = Focus::Zones;
+        handle_key(KeyCode::Char('?'), &mut app).unwrap();
+        assert_eq!(
+            app.mode,
+            Mode::Help(HelpView {
+                focus: Focus::Zones,
+                scroll: 0
+            })
+        );
+        let text = |line: &Line| {
+            line.spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect::<String>()
+        };
+        assert_eq!(text(&help_lines(Focus::Zones)[0]), "Zones (focused)");
+        assert_eq!(text(&help_lines(Focus::Records)[0]), "Records (focused)");
+
+        handle_key(KeyCode::Down, &mut app).unwrap();
+        handle_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_key(KeyCode::Up, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::Help(HelpView { scroll: 1, .. })));
+        for _ in 0..20 {
+            handle_key(KeyCode::PageDown, &mut app).unwrap();
+        }
+        let last_line = help_lines(Focus::Zones).len() - 1;
+        assert!(matches!(app.mode, Mode::Help(HelpView { scroll, .. }) if scroll == last_line));
+
+        handle_key(KeyCode::Char('?'), &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        handle_key(KeyCode::Char('?'), &mut app).unwrap();
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+
+        handle_key(KeyCode::Char('?'), &mut app).unwrap();
+        handle_key(KeyCode::Char('n'), &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::RecordForm(_)));
+    }
+
+    #[test]
+    fn every_key_the_main_screen_handles_is_in_the_help() {
+        let mut codes: Vec<KeyCode> = (' '..='~').map(KeyCode::Char).collect();
+        codes.extend([
+            KeyCode::Enter,
+            KeyCode::Esc,
+            KeyCode::Tab,
+            KeyCode::BackTab,
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::PageUp,
+            KeyCode::PageDown,
+            KeyCode::Home,
+            KeyCode::End,
+            KeyCode::Backspace,
+            KeyCode::Delete,
+        ]);
+        let state = |app: &App<MockBackend>| {
+            format!(
+                "{:?} {:?} {} {} {} {} {:?} {} {:?} {:?}",
+                app.mode,
+                app.focus,
+                app.selected_account,
+                app.selected_zone,
+                app.selected_record,
+                app.record_page,
+                app.pending_key,
+                app.last_message,
+                app.record_sorts,
+                app.type_filter
+            )
+        };
+        let mut undocumented = Vec::new();
+        for focus in [Focus::Accounts, Focus::Zones, Focus::Records] {
+            for &code in &codes {
+                let mut app = App::new(
+                    temp_config_path("help_keys"),
+                    two_account_config(),
+                    MockBackend::new(),
+                )
+                .unwrap();
+                app.focus = focus;
+                app.selected_record = 1;
+                let before = state(&app);
+                let quit = handle_normal_key(code, &mut app).unwrap();
+                let documented = BINDINGS.iter().any(|b| b.codes.contains(&code));
+                if (quit || state(&app) != before) && !documented {
+                    undocumented.push(format!("{code:?} in {focus:?}"));
+                }
+            }
+        }
+        assert!(
+            undocumented.is_empty(),
+            "missing from BINDINGS: {undocumented:?}"
+        );
+    }
+
+    #[test]
+    fn record_form_allows_typing_command_keys() {
+        let mut app = app_with_records("record_form", vec![]);
+        app.start_record_form(false);
+
+        let quit = handle_record_form_key(KeyCode::Char('q'), &mut app).unwrap();
+        assert!(!quit, "q should not quit while editing a record");
+
+        if let Mode::RecordForm(form) = app.mode {
+            assert_eq!(form.draft.name, "q");
+        } else {
+            panic!("app left record form mode");
+        }
+    }
+
+    #[test]
+    fn search_overlay_allows_typing_command_keys() {
+        let mut app = app_with_records("search_overlay", vec![]);
+        app.mode = Mode::Searching(SearchPrompt::default());
+
+        let quit = handle_search_key(KeyCode::Char('q'), &mut app).unwrap();
+        assert!(!quit, "q should not quit while searching");
+
+        if let Mode::Searching(prompt) = app.mode {
+            assert_eq!(prompt.text, "q");
+        } else {
+            panic!("app left search mode");
+        }
+    }
+
+    #[test]
+    fn search_filters_live_and_esc_restores_the_previous_filter() {
+        let records: Vec<_> = (1..=5)
+            .map(|i| record(&format!("r{i}"), &format!("web{i}.demo"), "A", "192.0.2.1"))
+            .chain([record("mx", "Mail.demo", "MX", "mx.example")])
+            .collect();
+        let mut app = app_with_records("search_live", records);
+        app.record_filter = "demo".to_string();
+        app.selected_record = 4;
+        let highlighted = app.current_record().unwrap().id.clone();
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        for _ in 0..4 {
+            handle_key(KeyCode::Backspace, &mut app).unwrap();
+        }
+        for c in "MAIL".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        assert!(matches!(app.mode, Mode::Searching(_)));
+        assert_eq!(app.record_filter, "demo", "not applied until Enter");
+        let shown: Vec<_> = app
+            .filtered_records()
+            .iter()
+            .map(|r| r.id.clone())
+            .collect();
+        assert_eq!(shown, ["mx"]);
+        assert_eq!((app.selected_record, app.record_page), (0, 0));
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("1 shown"), "{screen}");
+        assert!(screen.contains(FILTER_SYNTAX_HINT), "{screen}");
+
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.record_filter, "demo");
+        assert_eq!(app.filtered_records().len(), 6);
+        assert_eq!(app.current_record().unwrap().id, highlighted);
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        for _ in 0..4 {
+            handle_key(KeyCode::Backspace, &mut app).unwrap();
+        }
+        for c in "web".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        assert_eq!(app.current_record().unwrap().id, highlighted, "still shown");
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.record_filter, "web");
+        assert_eq!(app.filtered_records().len(), 5);
+    }
+
+    #[test]
+    fn server_search_merges_records_missing_from_a_partial_load() {
+        let records: Vec<_> = (1..=6)
+            .map(|i| record(&format!("r{i}"), &format!("host{i}.demo"), "A", "192.0.2.1"))
+            .chain([record("mx", "mail.demo", "MX", "mx.backup.example")])
+            .collect();
+        let mut app = app_with_records("server_search", records);
+        app.backend().per_page = Some(3);
+        app.refresh_records().unwrap();
+        assert!(app.records_partial());
+        assert_eq!(app.records.len(), 3);
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        let Mode::Searching(prompt) = &app.mode else {
+            panic!("search prompt did not open");
+        };
+        assert_eq!(prompt.scope, SearchScope::Server);
+        for c in "BACKUP".chars() {
+            handle_search_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_search_key(KeyCode::Enter, &mut app).unwrap();
+
+        let filtered = app.filtered_records();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, "mx");
+        assert!(app.status_message().1.contains("server search"));
+
+        // The remaining pages must not duplicate the merged match.
+        while app.is_busy() {
+            app.tick().unwrap();
+        }
+        assert_eq!(app.records.len(), 7);
+    }
+
+    #[test]
+    fn search_prompt_toggles_to_a_local_filter() {
+        let mut app = app_with_records(
+            "local_search",
+            vec![record("1", "api.demo", "A", "192.0.2.1")],
+        );
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_search_key(KeyCode::Tab, &mut app).unwrap();
+        handle_search_key(KeyCode::Tab, &mut app).unwrap();
+        handle_search_key(KeyCode::Char('a'), &mut app).unwrap();
+        handle_search_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert_eq!(app.record_filter_scope, SearchScope::Local);
+        assert!(app.status_message().1.contains("local filter"));
+    }
+
+    #[test]
+    fn mock_batch_is_all_or_nothing() {
+        let records = vec![
+            record("1", "a.demo", "A", "192.0.2.1"),
+            record("2", "b.demo", "A", "192.0.2.2"),
+        ];
+        let app = app_with_records("batch_atomic", records.clone());
+        let (account, zone) = (test_account(), app.current_zone().cloned().unwrap());
+
+        let err = app
+            .backend()
+            .batch(
+                &account,
+                &zone,
+                BatchRequest {
+                    deletes: vec!["1".to_string(), "missing".to_string()],
+                    ..BatchRequest::default()
+                },
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("no changes were applied"));
+        assert_eq!(app.backend().records["demo-01"], records);
+
+        let mut changed = records[1].clone();
+        changed.content = "192.0.2.22".to_string();
+        let result = app
+            .backend()
+            .batch(
+                &account,
+                &zone,
+                BatchRequest {
+                    deletes: vec!["1".to_string()],
+                    puts: vec![changed.clone()],
+                    posts: vec![record("", "c.demo", "A", "192.0.2.3")],
+                    ..BatchRequest::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(result.deletes, vec![records[0].clone()]);
+        let stored = &app.backend().records["demo-01"];
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0], changed);
+        assert_eq!(stored[1].name, "c.demo");
+        assert_eq!(result.posts[0].id, stored[1].id);
+    }
+
+    #[test]
+    fn bulk_delete_removes_filtered_records_in_one_batch() {
+        let records = vec![
+            record("1", "_acme-challenge.demo.example.com", "TXT", "one"),
+            record("2", "_acme-challenge.www.demo.example.com", "TXT", "two"),
+            record("3", "www.demo.example.com", "A", "192.0.2.1"),
+        ];
+        let mut app = app_with_records("bulk_delete", records);
+        app.focus = Focus::Records;
+
+        handle_normal_key(KeyCode::Char('D'), &mut app).unwrap();
+        assert_eq!(
+            app.mode,
+            Mode::Normal,
+            "unfiltered bulk delete must be refused"
+        );
+        assert_eq!(
+            app.messages.back().map(|m| m.text.as_str()),
+            Some("Filter the records first (/ or t); D deletes everything shown"),
+            "the refusal is in the history"
+        );
+
+        app.record_filter = "_acme".to_string();
+        handle_normal_key(KeyCode::Char('D'), &mut app).unwrap();
+        handle_confirm_bulk_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmBulkDelete(_)));
+        for c in "demo.example.com".chars() {
+            handle_confirm_bulk_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_confirm_bulk_delete_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.last_message,
+            "Deleted 2 record(s) from demo.example.com in one batch"
+        );
+        let remaining: Vec<_> = app.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(remaining, ["3"]);
+    }
+
+    #[test]
+    fn space_selects_records_across_pages_and_filters() {
+        let records = (1..=25)
+            .map(|i| {
+                record(
+                    &i.to_string(),
+                    &format!("r{i}.demo.example.com"),
+                    "A",
+                    "192.0.2.1",
+                )
+            })
+            .collect();
+        let mut app = app_with_records("multi_select", records);
+        app.focus = Focus::Records;
+
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_normal_key(KeyCode::PageDown, &mut app).unwrap();
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        assert_eq!(app.last_message, "3 selected");
+        let selected = app.current_record().unwrap().id.clone();
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        assert!(
+            app.selected_ids.contains(&selected),
+            "toggled off and on again"
+        );
+
+        // Records hidden by the filter stay selected and are still deleted.
+        app.record_filter = "r2".to_string();
+        app.selected_record = 0;
+        assert_eq!(app.selected_ids.len(), 3);
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        match &app.mode {
+            Mode::ConfirmBulkDelete(confirm) => {
+                assert!(confirm.selection);
+                let ids: Vec<&str> = confirm.records.iter().map(|r| r.id.as_str()).collect();
+                assert_eq!(ids, ["1", "2", selected.as_str()]);
+            }
+            other => panic!("expected the bulk delete confirmation, got {other:?}"),
+        }
+        for c in "demo.example.com".chars() {
+            handle_confirm_bulk_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_confirm_bulk_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.records.len(), 22);
+        assert!(app.selected_ids.is_empty());
+
+        // With nothing selected, d is back to deleting the highlighted record.
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmDelete(_)));
+    }
+
+    #[test]
+    fn selection_is_shown_and_cleared_by_esc_or_changing_zone() {
+        let mut app = App::new(
+            temp_config_path("select_clear"),
+            Config {
+                accounts: vec![test_account()],
+                ..Config::default()
+            },
+            MockBackend::new(),
+        )
+        .unwrap();
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("DNS Records — 1 selected"));
+        assert!(screen.contains("│* A"), "marker column");
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        assert!(app.selected_ids.is_empty());
+        assert_eq!(app.last_message, "Selection cleared");
+
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        app.cycle_type_filter().unwrap();
+        assert_eq!(app.selected_ids.len(), 1, "same zone, new filter");
+        app.select_zone_at(1).unwrap();
+        assert!(app.selected_ids.is_empty());
+    }
+
+    #[test]
+    fn bulk_delete_falls_back_to_single_deletes_and_reports_failures() {
+        let records: Vec<DnsRecord> = (1..=5)
+            .map(|i| {
+                record(
+                    &i.to_string(),
+                    &format!("r{i}.demo.example.com"),
+                    "A",
+                    "192.0.2.1",
+                )
+            })
+            .collect();
+        let mut app = app_with_records("bulk_partial", records);
+        app.backend().failures = vec![
+            FailureRule::parse("batch:always:Batch API unavailable").unwrap(),
+            FailureRule::parse("delete_record:every 2:Record is locked").unwrap(),
+        ];
+        app.focus = Focus::Records;
+        for _ in 0..4 {
+            handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+            handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        }
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        for c in "demo.example.com".chars() {
+            handle_confirm_bulk_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_confirm_bulk_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "Batch delete failed (Batch API unavailable); deleting 0/4…"
+        );
+        assert!(app.is_busy());
+
+        app.tick().unwrap();
+        assert_eq!(app.last_message, "Deleting 1/4…");
+        assert_eq!(app.records.len(), 4, "each delete shows up as it lands");
+        app.tick().unwrap();
+        assert_eq!(app.last_message, "Deleting 2/4…");
+        assert_eq!(app.records.len(), 4, "a failed delete leaves its record");
+
+        wait_until_idle(&mut app);
+        assert_eq!(
+            app.last_message,
+            "2 deleted, 2 failed — press L for details"
+        );
+        let remaining: Vec<&str> = app.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(remaining, ["2", "4", "5"]);
+        assert_eq!(app.backend().records["demo-01"].len(), 3);
+        let mut still_selected: Vec<&String> = app.selected_ids.iter().collect();
+        still_selected.sort();
+        assert_eq!(still_selected, ["2", "4"]);
+
+        handle_normal_key(KeyCode::Char('L'), &mut app).unwrap();
+        match &app.mode {
+            Mode::BulkReport(report) => assert_eq!(
+                report.failures,
+                [
+                    "A r2.demo.example.com: Record is locked",
+                    "A r4.demo.example.com: Record is locked"
+                ]
+            ),
+            other => panic!("expected the bulk report, got {other:?}"),
+        }
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn bulk_proxied_toggle_skips_unchanged_and_ineligible_records() {
+        let mut proxied = record("2", "b.demo.example.com", "A", "192.0.2.2");
+        proxied.proxied = true;
+        let records = vec![
+            record("1", "a.demo.example.com", "A", "192.0.2.1"),
+            proxied,
+            record("3", "c.demo.example.com", "CNAME", "a.demo.example.com"),
+            record("4", "d.demo.example.com", "TXT", "hello"),
+            record("5", "e.demo.example.com", "AAAA", "2001:db8::1"),
+        ];
+        let mut app = app_with_records("bulk_proxied", records);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('P'), &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "Select records with Space (or filter them) first"
+        );
+        assert_eq!(app.mode, Mode::Normal);
+
+        for _ in 0..5 {
+            handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+            handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        }
+        app.backend().failures =
+            vec![FailureRule::parse("update_record:next:Record is locked").unwrap()];
+        handle_normal_key(KeyCode::Char('P'), &mut app).unwrap();
+        match &app.mode {
+            Mode::ConfirmBulkUpdate(confirm) => {
+                assert_eq!(confirm.action, BulkAction::SetProxied(true));
+                let set = confirm.change_set();
+                let ids: Vec<&str> = set.steps.iter().map(|s| s.record().id.as_str()).collect();
+                assert_eq!(ids, ["1", "3", "5"]);
+                assert!(set.steps.iter().all(|s| s.record().proxied));
+                assert_eq!(set.skipped(), 2);
+                assert!(
+                    set.summary()
+                        .ends_with("1 unchanged, 1 skipped (TXT can't be proxied)"),
+                    "{}",
+                    set.summary()
+                );
+            }
+            other => panic!("expected the bulk confirmation, got {other:?}"),
+        }
+        assert!(!app.backend().records["demo-01"][0].proxied);
+
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "Updating 0/3…");
+        wait_until_idle(&mut app);
+        assert_eq!(
+            app.last_message,
+            "2 changed, 2 skipped, 1 failed — press L for details"
+        );
+        let proxied: Vec<String> = app.backend().records["demo-01"]
+            .iter()
+            .filter(|r| r.proxied)
+            .map(|r| r.id.clone())
+            .collect();
+        assert_eq!(proxied, ["2", "3", "5"]);
+        assert!(app.records.iter().find(|r| r.id == "5").unwrap().proxied);
+    }
+
+    #[test]
+    fn bulk_proxied_confirmation_can_flip_or_cancel() {
+        let mut a = record("1", "a.demo.example.com", "A", "192.0.2.1");
+        a.proxied = true;
+        let mut app = app_with_records("bulk_proxied_off", vec![a]);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('P'), &mut app).unwrap();
+        assert!(matches!(
+            &app.mode,
+            Mode::ConfirmBulkUpdate(c) if c.action == BulkAction::SetProxied(false)
+        ));
+        handle_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "Nothing to change (1 already set, 0 not applicable)"
+        );
+
+        handle_normal_key(KeyCode::Char('P'), &mut app).unwrap();
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "Bulk change cancelled");
+        assert!(app.backend().records["demo-01"][0].proxied);
+    }
+
+    #[test]
+    fn bulk_ttl_sets_filtered_records_and_skips_matching_ones() {
+        let mut low = record("2", "b.demo.example.com", "A", "192.0.2.2");
+        low.ttl = 60;
+        let records = vec![
+            record("1", "a.demo.example.com", "A", "192.0.2.1"),
+            low,
+            record("3", "c.demo.example.com", "TXT", "hello"),
+            record("4", "d.demo.example.com", "A", "192.0.2.4"),
+        ];
+        let mut app = app_with_records("bulk_ttl", records);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('t'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "10".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "TTL must be auto or a number of seconds from 30 to 86400"
+        );
+        assert!(matches!(app.mode, Mode::BulkTtl(_)));
+
+        handle_key(KeyCode::Backspace, &mut app).unwrap();
+        handle_key(KeyCode::Backspace, &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "Set TTL 60 on 2 record(s)?");
+        assert!(matches!(app.mode, Mode::ConfirmBulkUpdate(_)));
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "Updating 0/2…");
+        wait_until_idle(&mut app);
+        assert_eq!(app.last_message, "2 changed, 1 skipped, 0 failed");
+        let ttls: Vec<(String, u32)> = app.backend().records["demo-01"]
+            .iter()
+            .map(|r| (r.id.clone(), r.ttl))
+            .collect();
+        assert_eq!(
+            ttls,
+            [
+                ("1".to_string(), 60),
+                ("2".to_string(), 60),
+                ("3".to_string(), 300),
+                ("4".to_string(), 60)
+            ]
+        );
+
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "All 3 record(s) already have TTL 60");
+        assert_eq!(parse_bulk_ttl(" Auto ").unwrap(), 1);
+    }
+
+    #[test]
+    fn bulk_ttl_input_accepts_auto_and_the_cloudflare_range_only() {
+        for (text, ttl) in [
+            ("auto", 1),
+            ("AUTO", 1),
+            (" 1 ", 1),
+            ("30", 30),
+            ("3600", 3600),
+            ("86400", 86_400),
+        ] {
+            assert_eq!(parse_bulk_ttl(text).unwrap(), ttl, "{text:?}");
+        }
+        for text in [
+            "",
+            "0",
+            "2",
+            "29",
+            "86401",
+            "-60",
+            "1.5",
+            "60s",
+            "1h",
+            "automatic",
+        ] {
+            assert!(parse_bulk_ttl(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn bulk_ttl_reports_failed_updates_and_skips_ones_already_there() {
+        let mut auto = record("2", "b.demo.example.com", "A", "192.0.2.2");
+        auto.ttl = 1;
+        let records = vec![
+            record("1", "a.demo.example.com", "A", "192.0.2.1"),
+            auto,
+            record("3", "c.demo.example.com", "A", "192.0.2.3"),
+            record("4", "d.demo.example.com", "A", "192.0.2.4"),
+        ];
+        let mut app = app_with_records("bulk_ttl_failures", records);
+        app.backend().failures =
+            vec![FailureRule::parse("update_record:next:Record is locked").unwrap()];
+        app.focus = Focus::Records;
+        for _ in 0..3 {
+            handle_normal_key(KeyCode::Char(' '), &mut app).unwrap();
+            handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        }
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "auto".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "Set TTL auto on 2 record(s)?");
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+
+        assert_eq!(
+            app.last_message,
+            "1 changed, 1 skipped, 1 failed — press L for details"
+        );
+        let ttls: Vec<u32> = app.backend().records["demo-01"]
+            .iter()
+            .map(|r| r.ttl)
+            .collect();
+        assert_eq!(
+            ttls,
+            [300, 1, 1, 300],
+            "the unselected record is left alone"
+        );
+        handle_normal_key(KeyCode::Char('L'), &mut app).unwrap();
+        match &app.mode {
+            Mode::BulkReport(report) => {
+                assert_eq!(report.failures, ["A a.demo.example.com: Record is locked"])
+            }
+            other => panic!("expected the bulk report, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bulk_ttl_prompt_can_be_cancelled() {
+        let records = vec![
+            record("1", "a.demo.example.com", "A", "192.0.2.1"),
+            record("2", "b.demo.example.com", "A", "192.0.2.2"),
+        ];
+        let mut app = app_with_records("bulk_ttl_cancel", records);
+        app.focus = Focus::Records;
+        app.record_filter = "demo".to_string();
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "Bulk change cancelled");
+
+        // Backing out of the confirmation sends nothing either.
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmBulkUpdate(_)));
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(
+            app.backend().records["demo-01"]
+                .iter()
+                .all(|r| r.ttl == 300)
+        );
+    }
+
+    #[test]
+    fn osc52_sequences_encode_and_cut_at_the_limit() {
+        assert_eq!(
+            osc52_sequence("hello", 100, false),
+            ("\x1b]52;c;aGVsbG8=\x07".to_string(), 5)
+        );
+        assert_eq!(
+            osc52_sequence("hi", 100, true).0,
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+        // "é" is two bytes; a cut through it keeps only the whole characters before it.
+        assert_eq!(
+            osc52_sequence("aé", 2, false),
+            ("\x1b]52;c;YQ==\x07".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn copy_key_sends_the_record_content_through_osc52() {
+        let records = vec![record("1", "a.demo.example.com", "TXT", "v=spf1 -all")];
+        let mut app = app_with_records("osc52_copy", records);
+        app.config.clipboard = ClipboardMode::Osc52;
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('y'), &mut app).unwrap();
+        assert_eq!(app.last_message, "Copied the record content (OSC 52)");
+        assert!(app.terminal_output.contains("]52;c;dj1zcGYxIC1hbGw=\x07"));
+
+        app.terminal_output.clear();
+        app.config.osc52_max_bytes = Some(4);
+        handle_normal_key(KeyCode::Char('y'), &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "Copied only the first 4 of 11 bytes of the record content (osc52_max_bytes)"
+        );
+        assert!(app.terminal_output.contains("]52;c;dj1zcA==\x07"));
+    }
+
+    #[test]
+    fn type_filter_cycles_through_quick_types() {
+        let mut filter = TypeFilter::All;
+        let mut labels = Vec::new();
+        for _ in 0..7 {
+            filter = filter.next();
+            labels.push(filter.label());
+        }
+        assert_eq!(
+            labels,
+            [
+                "A",
+                "AAAA",
+                "CNAME",
+                "TXT",
+                "MX",
+                "other types",
+                "all types"
+            ]
+        );
+        assert!(TypeFilter::Other.matches("SRV"));
+        assert!(!TypeFilter::Other.matches("cname"));
+        assert_eq!(TypeFilter::Other.api_type(), None);
+        assert_eq!(TypeFilter::Only("TXT").api_type(), Some("TXT"));
+    }
+
+    #[test]
+    fn type_filter_key_narrows_and_restores_records() {
+        let records = vec![
+            record("1", "api.demo", "A", "192.0.2.1"),
+            record("2", "www.demo", "CNAME", "api.demo"),
+            record("3", "_sip.demo", "SRV", "0 5 5060 sip.demo"),
+        ];
+        let mut app = app_with_records("type_filter", records);
+        app.refresh_records().unwrap();
+        app.focus = Focus::Records;
+
+        handle_normal_key(KeyCode::Char('t'), &mut app).unwrap();
+        assert_eq!(app.type_filter, TypeFilter::Only("A"));
+        assert_eq!(app.records.len(), 1, "API should only return A records");
+        assert_eq!(app.last_message, "Showing A (1 record(s))");
+
+        for _ in 0..5 {
+            handle_normal_key(KeyCode::Char('t'), &mut app).unwrap();
+        }
+        assert_eq!(app.type_filter, TypeFilter::Other);
+        let others: Vec<_> = app
+            .filtered_records()
+            .iter()
+            .map(|r| r.id.clone())
+            .collect();
+        assert_eq!(others, ["3"]);
+
+        handle_normal_key(KeyCode::Char('t'), &mut app).unwrap();
+        assert_eq!(app.type_filter, TypeFilter::All);
+        assert_eq!(app.filtered_records().len(), 3);
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        let mut names = vec![
+            "a10.example",
+            "A2.example",
+            "a1.example",
+            "b.example",
+            "a02x",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "a1.example",
+                "A2.example",
+                "a02x",
+                "a10.example",
+                "b.example"
+            ]
+        );
+    }
+
+    #[test]
+    fn sorting_keeps_the_selected_record_and_is_kept_per_zone() {
+        let records = vec![
+            record("1", "a10.demo", "TXT", "x"),
+            record("2", "a2.demo", "A", "192.0.2.1"),
+            record("3", "b.demo", "CNAME", "a"),
+        ];
+        let mut app = app_with_records("sort", records);
+        app.focus = Focus::Records;
+        app.selected_record = 1;
+        let ids = |app: &App<MockBackend>| -> Vec<String> {
+            app.filtered_records()
+                .iter()
+                .map(|r| r.id.clone())
+                .collect()
+        };
+
+        handle_normal_key(KeyCode::Char('s'), &mut app).unwrap();
+        assert_eq!(ids(&app), ["2", "1", "3"]);
+        assert_eq!(app.current_record().unwrap().id, "2");
+        assert_eq!(app.record_sort().header(SortColumn::Name), "Name ▲");
+        assert_eq!(app.last_message, "Sorted by name (ascending)");
+
+        handle_normal_key(KeyCode::Char('S'), &mut app).unwrap();
+        assert_eq!(ids(&app), ["3", "1", "2"]);
+        assert_eq!(app.current_record().unwrap().id, "2");
+        assert_eq!(app.record_sort().header(SortColumn::Name), "Name ▼");
+        assert_eq!(app.record_sort().header(SortColumn::Type), "Type");
+
+        handle_normal_key(KeyCode::Char('s'), &mut app).unwrap();
+        assert_eq!(ids(&app), ["1", "3", "2"], "type, still descending");
+
+        // Another zone starts in API order; coming back restores the sort.
+        app.select_zone_at(1).unwrap();
+        assert_eq!(app.record_sort(), RecordSort::default());
+        app.select_zone_at(0).unwrap();
+        assert_eq!(app.record_sort().column, SortColumn::Type);
+
+        for _ in 0..4 {
+            handle_normal_key(KeyCode::Char('s'), &mut app).unwrap();
+        }
+        assert_eq!(app.record_sort().column, SortColumn::Api);
+        assert_eq!(app.last_message, "Records in API order");
+    }
+
+    #[test]
+    fn filtered_records_matches_across_fields() {
+        let records = vec![
+            record("1", "api.demo.example.com", "A", "203.0.113.1"),
+            record("2", "cdn.demo.example.com", "CNAME", "api.demo.example.com"),
+            record("3", "_acme.demo.example.com", "TXT", "challenge-token"),
+        ];
+        let mut app = app_with_records("filter", records);
+
+        app.record_filter = "acme".to_string();
+        let filtered = app.filtered_records();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].record_type, "TXT");
+
+        app.record_filter = "api.demo".to_string();
+        let filtered = app.filtered_records();
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.iter().any(|r| r.record_type == "A"));
+        assert!(filtered.iter().any(|r| r.record_type == "CNAME"));
+    }
+
+    #[test]
+    fn filtered_records_matches_regexes_and_reports_bad_ones() {
+        let records = vec![
+            record("1", "dev-api.internal.example.com", "A", "10.0.0.1"),
+            record("2", "dev-db.example.com", "A", "10.0.0.2"),
+            record("3", "prod-api.internal.example.com", "A", "10.0.1.1"),
+            record("4", "_acme.example.com", "TXT", "DEV-challenge"),
+        ];
+        let mut app = app_with_records("filter_regex", records);
+
+        app.record_filter = r"re:^dev-.*\.internal\.example\.com$".to_string();
+        let ids: Vec<_> = app
+            .filtered_records()
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["1"]);
+
+        app.record_filter = "re:^dev-".to_string();
+        let ids: Vec<_> = app
+            .filtered_records()
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["1", "2", "4"], "case-insensitive, and content counts");
+        assert!(draw_app(&mut app).contains("filtered by regex"));
+
+        app.record_filter = "re:".to_string();
+        assert_eq!(app.filtered_records().len(), 4);
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        handle_key(KeyCode::Char('('), &mut app).unwrap();
+        let Mode::Searching(prompt) = &app.mode else {
+            panic!("expected the search prompt, got {:?}", app.mode);
+        };
+        assert_eq!(prompt.text, "(", "F2 took the re: prefix off");
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        assert!(app.filtered_records().is_empty());
+        assert!(draw_app(&mut app).contains("Invalid regex: unclosed group"));
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(
+            matches!(app.mode, Mode::Searching(_)),
+            "bad patterns aren't applied"
+        );
+        assert_eq!(app.record_filter, "re:");
+    }
+
+    #[test]
+    fn fuzzy_filters_rank_matches_and_highlight_the_best() {
+        assert!(fuzzy_score("api.example.com", "apiex").is_some());
+        assert!(fuzzy_score("API.Example.com", "apiex").is_some());
+        assert!(fuzzy_score("example.com", "apiex").is_none());
+        assert!(
+            fuzzy_score("api.example.com", "apiex") > fuzzy_score("a.p.i.example.com", "apiex"),
+            "runs beat scattered letters"
+        );
+
+        let records = vec![
+            record("1", "mail.demo.example.com", "A", "192.0.2.1"),
+            record("2", "a-p-i.example.com", "A", "192.0.2.2"),
+            record("3", "api.example.com", "A", "192.0.2.3"),
+            record("4", "apex.example.com", "CNAME", "api.example.com"),
+            record("5", "www.example.com", "CNAME", "api.example.com"),
+        ];
+        let mut app = app_with_records("filter_fuzzy", records);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::F(3), &mut app).unwrap();
+        for c in "apiex".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        let ids: Vec<_> = app
+            .filtered_records()
+            .iter()
+            .map(|r| r.id.clone())
+            .collect();
+        assert_eq!(ids, ["3", "4", "5", "2"], "equal scores keep their order");
+        assert_eq!(
+            app.current_record().unwrap().id,
+            "3",
+            "the best match is highlighted"
+        );
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.record_filter, "fz:apiex");
+        assert_eq!(app.current_record().unwrap().id, "3");
+        assert!(draw_app(&mut app).contains("filtered by fuzzy"));
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        let Mode::Searching(prompt) = &app.mode else {
+            panic!("expected the search prompt, got {:?}", app.mode);
+        };
+        assert_eq!(prompt.text, "re:apiex", "F2 swaps fuzzy for regex");
+        handle_key(KeyCode::F(2), &mut app).unwrap();
+        let Mode::Searching(prompt) = &app.mode else {
+            panic!("expected the search prompt, got {:?}", app.mode);
+        };
+        assert_eq!(prompt.text, "apiex");
+    }
+
+    #[test]
+    fn located_searches_step_through_matches_without_filtering() {
+        let records = vec![
+            record("1", "api.demo", "A", "192.0.2.1"),
+            record("2", "web.demo", "A", "192.0.2.2"),
+            record("3", "mail.demo", "MX", "mx.demo"),
+            record("4", "webmail.demo", "CNAME", "mail.demo"),
+        ];
+        let mut app = app_with_records("locate", records);
+        app.refresh_current().unwrap();
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::F(4), &mut app).unwrap();
+        for c in "web".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        assert_eq!(app.filtered_records().len(), 4, "the list stays whole");
+        assert_eq!(
+            app.current_record().unwrap().id,
+            "2",
+            "live jump to a match"
+        );
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.record_filter, "");
+        assert!(draw_app(&mut app).contains("4 shown, match 1/2 for"));
+
+        handle_normal_key(KeyCode::Char('n'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "4");
+        handle_normal_key(KeyCode::Char('n'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "2");
+        assert_eq!(app.last_message, "Search wrapped to the top");
+        handle_normal_key(KeyCode::Char('N'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "4");
+        assert!(matches!(app.mode, Mode::Normal), "n/N don't open forms");
+
+        app.backend()
+            .records
+            .get_mut("demo-01")
+            .unwrap()
+            .push(record("5", "www.demo", "CNAME", "web.demo"));
+        app.refresh_current().unwrap();
+        assert!(
+            draw_app(&mut app).contains("match 2/3"),
+            "matches follow a refresh"
+        );
+        handle_normal_key(KeyCode::Char('n'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "5");
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.locate, "");
+        handle_normal_key(KeyCode::Char('n'), &mut app).unwrap();
+        assert!(
+            matches!(app.mode, Mode::RecordForm(_)),
+            "n is new record again"
+        );
+    }
+
+    #[test]
+    fn filter_matches_are_picked_out_inside_cells() {
+        // Highlighted byte spans of `text` in `column` under the filter `filter`.
+        let spans = |filter: &str, column: RecordColumn, text: &str| -> Vec<(usize, usize)> {
+            let query = RecordQuery::parse(filter).unwrap().unwrap();
+            query
+                .highlights(column, text)
+                .into_iter()
+                .map(|range| (range.start, range.end))
+                .collect()
+        };
+        assert_eq!(
+            spans("API", RecordColumn::Name, "api.demo.Api"),
+            [(0, 3), (9, 12)],
+            "every occurrence, ignoring case"
+        );
+        assert!(
+            spans("name:web -mail", RecordColumn::Content, "web.mail").is_empty(),
+            "scoped and negated terms only light up their own column"
+        );
+        assert_eq!(
+            spans("de mo", RecordColumn::Name, "demo"),
+            [(0, 4)],
+            "touching matches merge"
+        );
+        assert_eq!(spans("type:a", RecordColumn::Type, "A"), [(0, 1)]);
+        assert_eq!(
+            spans("re:[0-9]+$", RecordColumn::Content, "192.0.2.10"),
+            [(8, 10)]
+        );
+        assert_eq!(spans("fz:wdm", RecordColumn::Name, "www.demo"), [(0, 8)]);
+
+        let records = vec![
+            record("1", "web.demo", "A", "192.0.2.1"),
+            record("2", "webmail.demo", "CNAME", "web.demo"),
+        ];
+        let mut app = app_with_records("filter_cells", records);
+        app.focus = Focus::Records;
+        app.record_filter = "web".to_string();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let (x, y) = (0..buffer.area.height)
+            .find_map(|y| {
+                let line: String = (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect();
+                let at = line.find("webmail.demo")?;
+                Some((line[..at].chars().count() as u16, y))
+            })
+            .unwrap();
+        let reversed = |x| {
+            buffer
+                .get(x, y)
+                .style()
+                .add_modifier
+                .contains(Modifier::REVERSED)
+        };
+        assert!((x..x + 3).all(reversed), "the match is reversed");
+        assert!(!reversed(x + 3), "the rest of the cell isn't");
+        let first = (0..buffer.area.height)
+            .find(|&y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+                    .contains("192.0.2.1")
+            })
+            .unwrap();
+        let style = buffer.get(x, first).style();
+        assert!(
+            style
+                .add_modifier
+                .contains(Modifier::REVERSED | Modifier::BOLD),
+            "matches keep the highlighted row's look"
+        );
+        assert_eq!(style.fg, Some(Color::Cyan));
+    }
+
+    #[test]
+    fn filter_terms_parse_fields_negation_and_quotes() {
+        let term = |negated, test| FilterTerm { negated, test };
+        assert_eq!(
+            parse_filter_terms(r#"type:cname content:herokuapp"#).unwrap(),
+            [
+                term(false, FilterTest::Type("CNAME".to_string())),
+                term(false, FilterTest::Content("herokuapp".to_string())),
+            ]
+        );
+        assert_eq!(
+            parse_filter_terms("  name:API  -type:TXT ").unwrap(),
+            [
+                term(false, FilterTest::Name("api".to_string())),
+                term(true, FilterTest::Type("TXT".to_string())),
+            ]
+        );
+        assert_eq!(
+            parse_filter_terms(r#"content:"v=spf1 -all" -"two words""#).unwrap(),
+            [
+                term(false, FilterTest::Content("v=spf1 -all".to_string())),
+                term(true, FilterTest::Any("two words".to_string())),
+            ]
+        );
+        assert_eq!(
+            parse_filter_terms("ttl:auto ttl:300 proxied:YES -proxied:off").unwrap(),
+            [
+                term(false, FilterTest::Ttl(1)),
+                term(false, FilterTest::Ttl(300)),
+                term(false, FilterTest::Proxied(true)),
+                term(true, FilterTest::Proxied(false)),
+            ]
+        );
+        assert_eq!(
+            parse_filter_terms("include:_spf - name:").unwrap(),
+            [
+                term(false, FilterTest::Any("include:_spf".to_string())),
+                term(false, FilterTest::Any("-".to_string())),
+            ],
+            "unknown prefixes and a lone dash stay bare; an empty field is skipped"
+        );
+        assert!(parse_filter_terms("").unwrap().is_empty());
+        assert_eq!(
+            parse_filter_terms("ttl:soon").unwrap_err(),
+            "ttl: takes seconds or auto, not 'soon'"
+        );
+        assert!(parse_filter_terms("proxied:maybe").is_err());
+    }
+
+    #[test]
+    fn filtered_records_applies_scoped_and_negated_terms() {
+        let mut proxied = record("1", "api.demo.example.com", "CNAME", "app.herokuapp.com");
+        proxied.proxied = true;
+        let mut long_ttl = record("3", "api.demo.example.com", "TXT", "v=spf1 -all");
+        long_ttl.ttl = 3600;
+        let records = vec![
+            proxied,
+            record("2", "www.demo.example.com", "CNAME", "api.herokuapp.com"),
+            long_ttl,
+            record("4", "demo.example.com", "AAAA", "2001:db8::1"),
+        ];
+        let mut app = app_with_records("filter_terms", records);
+        let mut shown = |filter: &str| {
+            app.record_filter = filter.to_string();
+            let ids: Vec<_> = app
+                .filtered_records()
+                .iter()
+                .map(|r| r.id.clone())
+                .collect();
+            ids.join(",")
+        };
+
+        assert_eq!(shown("type:CNAME content:herokuapp"), "1,2");
+        assert_eq!(shown("name:api -type:TXT"), "1");
+        assert_eq!(shown("api"), "1,2,3", "bare terms search every field");
+        assert_eq!(shown(r#"content:"spf1 -all""#), "3");
+        assert_eq!(shown("ttl:3600"), "3");
+        assert_eq!(shown("proxied:yes"), "1");
+        assert_eq!(shown("-proxied:yes type:cname"), "2");
+        assert_eq!(shown("type:A"), "", "type matches exactly");
+        assert_eq!(shown("ttl:often"), "", "a bad term shows nothing");
+    }
+
+    #[test]
+    fn paged_records_respects_page_and_size() {
+        let records = (1..=5)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("paging", records);
+
+        app.update_record_page_size(5); // yields a page size of 2
+
+        app.record_page = 0;
+        let first_page = app.paged_records();
+        assert_eq!(first_page.len(), 2);
+        assert_eq!(first_page[0].name, "r1.demo");
+
+        app.record_page = 1;
+        let second_page = app.paged_records();
+        assert_eq!(second_page.len(), 2);
+        assert_eq!(second_page[0].name, "r3.demo");
+
+        app.record_page = 2;
+        let final_page = app.paged_records();
+        assert_eq!(final_page.len(), 1);
+        assert_eq!(final_page[0].name, "r5.demo");
+    }
+
+    #[test]
+    fn ensure_record_visible_moves_page_to_selection() {
+        let records = (1..=5)
+            .map(|i| record(&i.to_string(), &format!("rec-{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("visible", records);
+        app.update_record_page_size(6); // page size 3
+
+        app.record_page = 0;
+        app.selected_record = 4;
+        let total = app.filtered_records().len();
+        app.ensure_record_visible(total);
+
+        assert_eq!(app.record_page, 1);
+        assert_eq!(app.selected_record, 4);
+    }
+
+    #[test]
+    fn wrapped_content_shows_in_full_and_keeps_paging_correct() {
+        let long = format!("v=DKIM1; k=rsa; p={}", "A".repeat(60));
+        let mut records: Vec<DnsRecord> = (1..=12)
+            .map(|i| record(&i.to_string(), &format!("r{i:02}.demo"), "A", "192.0.2.1"))
+            .collect();
+        records[1] = record("2", "dkim.demo", "TXT", &long);
+        let mut app = app_with_records("wrap", records);
+        app.focus = Focus::Records;
+        let screen = draw_app(&mut app);
+        let page_size = app.page_size();
+        assert!(app.content_width < long.len());
+        assert!(!screen.contains("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"));
+
+        handle_normal_key(KeyCode::Right, &mut app).unwrap();
+        let screen = draw_app(&mut app);
+        assert_eq!(app.content_scroll, 8);
+        assert!(screen.contains("content from col 9"));
+        assert!(screen.contains("… k=rsa; p=AAA"));
+
+        handle_normal_key(KeyCode::Char('w'), &mut app).unwrap();
+        app.selected_record = 1;
+        let screen = draw_app(&mut app);
+        assert_eq!(app.content_scroll, 0, "wrapping resets the sideways scroll");
+        let lines = wrap_chars(&long, app.content_width);
+        assert!(lines.len() > 1);
+        assert_eq!(app.page_size(), page_size - (lines.len() - 1));
+        for line in &lines {
+            assert!(screen.contains(line.as_str()), "{line}");
+        }
+        assert_eq!(app.panels.wrapped_row, Some((1, lines.len())));
+
+        // The row under the wrapped one is pushed down by its extra lines.
+        let first_row = app.panels.records.top() + 2;
+        let column = app.panels.records.left() + 5;
+        click(&mut app, column, first_row + 1 + lines.len() as u16);
+        assert_eq!(app.selected_record, 2);
+
+        handle_normal_key(KeyCode::Char('w'), &mut app).unwrap();
+        draw_app(&mut app);
+        assert_eq!(app.page_size(), page_size);
+        assert_eq!(wrap_chars("abcde", 2), ["ab", "cd", "e"]);
+    }
+
+    #[test]
+    fn record_types_and_proxied_rows_are_colored_except_when_highlighted() {
+        let mut web = record("1", "web.demo", "A", "192.0.2.1");
+        web.proxied = true;
+        let records = vec![
+            web,
+            record("2", "spf.demo", "TXT", "v=spf1 -all"),
+            record("3", "srv.demo", "SRV", "0 5 5060 sip.demo"),
+            record("4", "api.demo", "A", "192.0.2.4"),
+        ];
+        let mut app = app_with_records("type_colors", records);
+        app.config
+            .type_colors
+            .insert("TXT".to_string(), "#ff8800".to_string());
+        app.focus = Focus::Records;
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        // Style of the first cell of `text` on the row that shows `name`.
+        fn style_of(buffer: &ratatui::buffer::Buffer, name: &str, text: &str) -> Style {
+            let area = buffer.area;
+            (area.top()..area.bottom())
+                .find_map(|y| {
+                    let line: String = (0..area.width).map(|x| buffer.get(x, y).symbol()).collect();
+                    line.contains(name).then(|| {
+                        let x = line[..line.find(text).unwrap()].chars().count() as u16;
+                        buffer.get(x, y).style()
+                    })
+                })
+                .unwrap()
+        }
+        let buffer = terminal.backend().buffer();
+
+        let selected = style_of(buffer, "web.demo", "A ");
+        assert_eq!(selected.fg, Some(Color::Cyan));
+        assert!(selected.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(
+            style_of(buffer, "web.demo", "Proxied").fg,
+            Some(Color::Cyan)
+        );
+        assert_eq!(style_of(buffer, "api.demo", "A ").fg, Some(Color::Green));
+        assert_eq!(
+            style_of(buffer, "spf.demo", "TXT").fg,
+            Some(Color::Rgb(0xff, 0x88, 0x00))
+        );
+        assert_eq!(style_of(buffer, "srv.demo", "SRV").fg, Some(Color::Reset));
+        assert_eq!(
+            style_of(buffer, "api.demo", "DNS only").fg,
+            Some(Color::Reset)
+        );
+
+        app.selected_record = 3;
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(
+            style_of(buffer, "web.demo", "Proxied").fg,
+            Some(PROXIED_COLOR)
+        );
+        assert_eq!(style_of(buffer, "web.demo", "A ").fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn hiding_the_accounts_panel_widens_records_and_skips_its_focus() {
+        let mut app = app_with_records("collapse", vec![record("1", "www.demo", "A", "192.0.2.1")]);
+        app.focus = Focus::Accounts;
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("┌Accounts"));
+        let narrow = app.content_width;
+
+        handle_normal_key(KeyCode::Char('z'), &mut app).unwrap();
+        assert_eq!(app.focus, Focus::Zones);
+        assert!(Config::load(&app.config_path).unwrap().collapse_accounts);
+        let screen = draw_app(&mut app);
+        assert!(!screen.contains("┌Accounts"));
+        assert!(screen.contains("DNS Records — demo › demo.example.com"));
+        assert!(app.content_width > narrow);
+        assert_eq!(app.panels.accounts, Rect::default());
+
+        let mut visited = Vec::new();
+        for key in [
+            KeyCode::Tab,
+            KeyCode::Tab,
+            KeyCode::BackTab,
+            KeyCode::Char('h'),
+        ] {
+            handle_normal_key(key, &mut app).unwrap();
+            visited.push(app.focus);
+        }
+        assert_eq!(
+            visited,
+            [Focus::Records, Focus::Zones, Focus::Records, Focus::Zones]
+        );
+        handle_normal_key(KeyCode::Char('h'), &mut app).unwrap();
+        assert_eq!(app.focus, Focus::Zones);
+
+        handle_normal_key(KeyCode::Char('z'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('h'), &mut app).unwrap();
+        assert_eq!(app.focus, Focus::Accounts);
+        assert!(!Config::load(&app.config_path).unwrap().collapse_accounts);
+    }
+
+    #[test]
+    fn bulk_changes_show_a_diff_and_can_stop_at_the_first_failure() {
+        let records = (1..=4)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "192.0.2.1"))
+            .collect();
+        let mut app = app_with_records("bulk_diff", records);
+        app.config.stop_on_failure = true;
+        app.backend().failures =
+            vec![FailureRule::parse("update_record:next:Record is locked").unwrap()];
+        app.focus = Focus::Records;
+        app.record_filter = "demo".to_string();
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        let screen = draw_app(&mut app);
+        assert!(
+            screen.contains("~ A r1.demo 192.0.2.1 (TTL 300 → 60)"),
+            "{screen}"
+        );
+        assert!(screen.contains("0 to create, 4 to update, 0 to delete, 0 unchanged"));
+
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(
+            app.last_message,
+            "0 changed, 0 skipped, 1 failed, stopped there with 3 not sent — press L for details"
+        );
+        let report = app.bulk_report.clone().unwrap();
+        assert_eq!(report.failures.len(), 4);
+        assert_eq!(report.failures[1], "A r2.demo: not sent");
+        assert!(
+            app.backend().records["demo-01"]
+                .iter()
+                .all(|r| r.ttl == 300)
+        );
+    }
+
+    #[test]
+    fn message_history_keeps_errors_and_collapses_progress() {
+        let records = (1..=3)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "192.0.2.1"))
+            .collect();
+        let mut app = app_with_records("messages", records);
+        app.backend().failures =
+            vec![FailureRule::parse("update_record:next:Record is locked").unwrap()];
+        app.focus = Focus::Records;
+        app.record_filter = "demo".to_string();
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        for c in "60".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+        app.push_message(MessageLevel::Info, "Loaded 12 zone(s) for demo");
+
+        let progress: Vec<&str> = app
+            .messages
+            .iter()
+            .filter(|m| m.text.starts_with("Updating"))
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(
+            progress,
+            ["Updating 3/3…"],
+            "only the latest progress is kept"
+        );
+        let last_two: Vec<(MessageLevel, &str)> = app
+            .messages
+            .iter()
+            .rev()
+            .take(2)
+            .map(|m| (m.level, m.text.as_str()))
+            .collect();
+        assert_eq!(
+            last_two,
+            [
+                (MessageLevel::Info, "Loaded 12 zone(s) for demo"),
+                (
+                    MessageLevel::Error,
+                    "2 changed, 0 skipped, 1 failed — press L for details"
+                ),
+            ]
+        );
+
+        handle_normal_key(KeyCode::Char('M'), &mut app).unwrap();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let (x, y) = (0..30)
+            .find_map(|y| {
+                let line: String = (0..100).map(|x| buffer.get(x, y).symbol()).collect();
+                let x = line.find("2 changed, 0 skipped")?;
+                Some((line[..x].chars().count() as u16, y))
+            })
+            .unwrap();
+        assert_eq!(buffer.get(x, y).fg, Color::Red);
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+
+        for i in 0..MESSAGE_HISTORY_LIMIT {
+            app.push_message(MessageLevel::Info, format!("message {i}"));
+        }
+        assert_eq!(app.messages.len(), MESSAGE_HISTORY_LIMIT);
+        assert_eq!(app.messages[0].text, "message 0");
+        app.amend_message(" (journal not written)");
+        assert_eq!(
+            app.messages.back().unwrap().text,
+            format!(
+                "message {} (journal not written)",
+                MESSAGE_HISTORY_LIMIT - 1
+            )
+        );
+    }
+
+    #[test]
+    fn update_record_page_size_clamps_page_and_selection() {
+        let records = (1..=5)
+            .map(|i| record(&i.to_string(), &format!("rec-{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("clamp", records);
+
+        app.record_page_size = 1;
+        app.record_page = 4;
+        app.selected_record = 4;
+
+        app.update_record_page_size(12); // page size grows to 9, shrinking page count to 1
+
+        assert_eq!(app.page_size(), 9);
+        assert_eq!(app.record_page, 0);
+        assert_eq!(app.selected_record, 4);
+    }
+
+    #[test]
+    fn status_message_reports_filter_and_paging() {
+        let records = (1..=3)
+            .map(|i| record(&i.to_string(), &format!("item-{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("status", records);
+
+        app.update_record_page_size(5); // page size 2
+        app.record_filter = "item".to_string();
+        let (_, details) = app.status_message();
+
+        assert!(
+            details.contains("Records: page 1/2"),
+            "status did not include page count: {}",
+            details
+        );
+        assert!(details.contains("filtered"), "status did not mark filter");
+    }
+
+    fn wait_until_idle<B: DnsBackend>(app: &mut App<B>) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while app.is_busy() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "background load did not finish"
+            );
+            app.tick().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn threaded_app_loads_zones_and_records_in_the_background() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app = App::threaded(
+            temp_config_path("threaded_load"),
+            config,
+            MockBackend::new(),
+        )
+        .unwrap();
+        assert!(app.is_busy());
+        assert_eq!(app.last_message, "Loading zones for demo…");
+
+        wait_until_idle(&mut app);
+        assert_eq!(app.zones.len(), 2);
+        assert_eq!(app.records.len(), 3);
+        assert_eq!(app.last_message, "3 record(s) in demo.example.com");
+
+        app.next_zone().unwrap();
+        assert!(app.records.is_empty(), "old zone's records must not linger");
+        assert_eq!(app.last_message, "Loading records for demo.services.io…");
+        wait_until_idle(&mut app);
+        assert!(
+            app.records
+                .iter()
+                .all(|r| r.name.ends_with("demo.services.io"))
+        );
+    }
+
+    #[test]
+    fn failed_background_load_clears_the_spinner() {
+        let mut server = mockito::Server::new();
+        let _zones = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .with_body(
+                json!({
+                    "success": false,
+                    "errors": [{"code": 9109, "message": "Invalid access token"}],
+                    "result": null
+                })
+                .to_string(),
+            )
+            .create();
+        let backend = Backend::Cloudflare(CloudflareBackend::new_with_base(server.url()).unwrap());
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app = App::threaded(temp_config_path("load_error"), config, backend).unwrap();
+        assert!(app.panel_spinner(Focus::Zones).is_some());
+        assert!(app.panel_spinner(Focus::Records).is_none());
+        assert!(
+            app.status_message().1.contains("Loading zones for demo…"),
+            "{}",
+            app.status_message().1
+        );
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let err = loop {
+            assert!(std::time::Instant::now() < deadline, "load never failed");
+            if let Err(err) = app.tick() {
+                break err;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        assert!(format!("{err:#}").contains("Invalid access token"));
+        assert_eq!(app.is_loading, None);
+        assert!(!app.is_busy());
+        assert!(app.panel_spinner(Focus::Zones).is_none());
+    }
+
+    fn two_account_config() -> Config {
+        let other = Account {
+            name: "other".to_string(),
+            ..test_account()
+        };
+        Config {
+            accounts: vec![test_account(), other],
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn d_in_accounts_removes_the_account_after_asking() {
+        let path = temp_config_path("delete_account");
+        let mut app = App::new(path.clone(), two_account_config(), MockBackend::new()).unwrap();
+        let zone_id = app.current_zone().unwrap().id.clone();
+        app.record_sorts
+            .insert(zone_id.clone(), RecordSort::default());
+        app.focus = Focus::Accounts;
+
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmDeleteAccount(_)));
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("┌Remove account"), "{screen}");
+        assert!(
+            screen.contains("Remove the Cloudflare account demo?"),
+            "{screen}"
+        );
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.accounts.len(), 2);
+
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "Removed account demo");
+        assert_eq!(app.current_account().unwrap().name, "other");
+        assert!(!app.zone_cache.contains_key("demo"));
+        assert!(!app.record_sorts.contains_key(&zone_id));
+        assert!(!app.zones.is_empty(), "the next account's zones are shown");
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.accounts.len(), 1);
+        assert_eq!(saved.accounts[0].name, "other");
+
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(app.accounts.is_empty());
+        assert!(app.zones.is_empty());
+        assert!(
+            matches!(app.mode, Mode::AddingAccount(_)),
+            "back to onboarding"
+        );
+        assert!(Config::load(&path).unwrap().accounts.is_empty());
+    }
+
+    #[test]
+    fn strict_mode_wants_the_account_name_typed_before_removing_it() {
+        let path = temp_config_path("delete_account_strict");
+        let config = Config {
+            strict: true,
+            ..two_account_config()
+        };
+        let mut app = App::new(path, config, MockBackend::new()).unwrap();
+        app.focus = Focus::Accounts;
+
+        handle_normal_key(KeyCode::Char('d'), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.accounts.len(), 2);
+        assert_eq!(app.last_message, "Type demo exactly to confirm");
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("Type the account name"), "{screen}");
+
+        for c in "dem".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.accounts.len(), 2);
+        handle_key(KeyCode::Char('o'), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "Removed account demo");
+        assert_eq!(app.accounts.len(), 1);
+    }
+
+    #[test]
+    fn number_keys_switch_accounts_from_any_panel() {
+        let mut app = App::new(
+            temp_config_path("number_keys"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('2'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "other");
+        assert_eq!(app.focus, Focus::Records);
+        assert_eq!(app.backend().zone_list_calls, 2);
+        assert!(!app.records.is_empty());
+
+        handle_normal_key(KeyCode::Char('5'), &mut app).unwrap();
+        assert_eq!(app.selected_account, 1);
+        assert_eq!(app.last_message, "No account 5; there are 2 account(s)");
+
+        handle_normal_key(KeyCode::Char('1'), &mut app).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "demo");
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("1 demo"));
+        assert!(screen.contains("2 other"));
+    }
+
+    #[test]
+    fn switching_accounts_reuses_cached_zone_lists() {
+        let mut app = App::new(
+            temp_config_path("zone_cache"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        assert_eq!(app.backend().zone_list_calls, 1);
+
+        app.next_account().unwrap();
+        app.next_account().unwrap();
+        app.next_account().unwrap();
+        assert_eq!(
+            app.backend().zone_list_calls,
+            2,
+            "each account fetched once"
+        );
+        assert_eq!(app.zones[0].name, "other.example.com");
+        assert_eq!(
+            app.records.len(),
+            3,
+            "records still load for the cached zone"
+        );
+
+        handle_normal_key(KeyCode::Char('r'), &mut app).unwrap();
+        assert_eq!(app.backend().zone_list_calls, 3, "r always refetches");
+    }
+
+    #[test]
+    fn cached_zone_lists_are_announced_and_expire() {
+        let mut app = App::threaded(
+            temp_config_path("zone_cache_message"),
+            two_account_config(),
+            MockBackend::new(),
+        )
+        .unwrap();
+        wait_until_idle(&mut app);
+        app.next_account().unwrap();
+        wait_until_idle(&mut app);
+
+        app.previous_account().unwrap();
+        assert_eq!(app.zones.len(), 2);
+        assert_eq!(
+            app.last_message,
+            "2 cached zone(s) for demo (loaded 0s ago; r to refresh)"
+        );
+        wait_until_idle(&mut app);
+        assert_eq!(app.backend().zone_list_calls, 2);
+
+        app.config.zone_cache_secs = Some(0);
+        app.next_account().unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(
+            app.backend().zone_list_calls,
+            3,
+            "a zero window never reuses"
+        );
+    }
+
+    fn threaded_demo_app(test_name: &str) -> App<MockBackend> {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app =
+            App::threaded(temp_config_path(test_name), config, MockBackend::new()).unwrap();
+        wait_until_idle(&mut app);
+        app
+    }
+
+    #[test]
+    fn creates_show_a_pending_row_until_confirmed() {
+        let mut app = threaded_demo_app("optimistic_create");
+
+        app.create_record(record("", "new.demo.example.com", "TXT", "hello"))
+            .unwrap();
+        assert_eq!(app.records.len(), 4);
+        let provisional = app.current_record().unwrap().id.clone();
+        assert!(app.is_pending_row(&provisional));
+        assert_eq!(app.last_message, "Creating new.demo.example.com…");
+
+        wait_until_idle(&mut app);
+        assert_eq!(app.records.len(), 4);
+        let created = app.current_record().unwrap();
+        assert_eq!(created.name, "new.demo.example.com");
+        assert!(!created.id.starts_with("pending-"));
+        assert!(!app.is_pending_row(&created.id));
+        assert_eq!(app.last_message, "Created new.demo.example.com");
+    }
+
+    #[test]
+    fn failed_writes_roll_back() {
+        let mut app = threaded_demo_app("optimistic_rollback");
+        let original = app.records.clone();
+        app.backend().fail_writes("Cloudflare said no");
+
+        let mut edited = app.records[1].clone();
+        edited.content = "other.service.net".to_string();
+        app.update_record(edited).unwrap();
+        assert_eq!(app.records[1].content, "other.service.net");
+        assert!(app.is_pending_row(&app.records[1].id));
+        wait_until_idle(&mut app);
+        assert_eq!(app.records, original);
+        assert_eq!(
+            app.last_message,
+            "Update failed and was rolled back: Cloudflare said no"
+        );
+
+        let id = app.records[1].id.clone();
+        app.delete_record(id).unwrap();
+        assert_eq!(app.records.len(), 2);
+        wait_until_idle(&mut app);
+        assert_eq!(app.records, original, "deleted row returns to its place");
+
+        app.create_record(record("", "new.demo.example.com", "A", "192.0.2.1"))
+            .unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(app.records, original);
+    }
+
+    #[test]
+    fn failed_create_reopens_the_form_with_the_typed_values() {
+        let records = vec![record("rec-1", "api.demo.example.com", "A", "203.0.113.1")];
+        let mut app = app_with_records("inject_create", records.clone());
+        app.backend().failures =
+            vec![FailureRule::parse("create_record:next:Quota exceeded").unwrap()];
+        app.start_record_form(false);
+        for c in "new".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        for c in "192.0.2.7".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+
+        match &app.mode {
+            Mode::Error(details) => {
+                assert_eq!(details.operation, "Create record new");
+                assert_eq!(details.message, "Quota exceeded");
+            }
+            other => panic!("expected the error modal, got {other:?}"),
+        }
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        match &app.mode {
+            Mode::RecordForm(form) => {
+                assert!(!form.is_edit);
+                assert_eq!(form.draft.content, "192.0.2.7");
+            }
+            other => panic!("expected the record form, got {other:?}"),
+        }
+        assert!(
+            app.last_message.contains("Quota exceeded"),
+            "{}",
+            app.last_message
+        );
+        assert_eq!(app.records, records);
+        assert_eq!(app.current_record().unwrap().id, "rec-1");
+
+        // The rule only covered one call, so resubmitting goes through.
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.backend().records["demo-01"].len(), 2);
+    }
+
+    #[test]
+    fn cloning_a_record_creates_a_new_one_and_leaves_the_source() {
+        let mut source = record("rec-1", "api.demo.example.com", "A", "203.0.113.1");
+        source.ttl = 120;
+        source.proxied = true;
+        let mut app = app_with_records("clone_record", vec![source.clone()]);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('c'), &mut app).unwrap();
+        match &app.mode {
+            Mode::RecordForm(form) => {
+                assert_eq!(form.draft, RecordDraft::from_record(&source));
+                assert_eq!(form.field_index, 0);
+                assert!(!form.is_edit);
+                assert_eq!(form.target_id, None);
+            }
+            other => panic!("expected the record form, got {other:?}"),
+        }
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Create DNS record (cloned from api.demo.example.com)"));
+
+        for _ in 0..source.name.len() {
+            handle_key(KeyCode::Backspace, &mut app).unwrap();
+        }
+        for c in "www.demo.example.com".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        for _ in 0..4 {
+            handle_key(KeyCode::Tab, &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+
+        let stored = &app.backend().records["demo-01"];
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0], source);
+        let clone = &stored[1];
+        assert_ne!(clone.id, source.id);
+        assert_eq!(clone.name, "www.demo.example.com");
+        assert_eq!(
+            (clone.content.as_str(), clone.ttl, clone.proxied),
+            ("203.0.113.1", 120, true)
+        );
+    }
+
+    #[test]
+    fn failed_update_keeps_the_selection_and_the_edit() {
+        let mut app = threaded_demo_app("inject_update");
+        app.backend().failures = vec![FailureRule::new(
+            "update_record",
+            FailWhen::Always,
+            "Rate limited",
+        )];
+        app.selected_record = 2;
+        let original = app.records.clone();
+        let selected = app.current_record().unwrap().id.clone();
+
+        app.start_record_form(true);
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Char('x'), &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Tab, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+
+        assert_eq!(app.records, original);
+        assert_eq!(app.current_record().unwrap().id, selected);
+        assert_eq!(
+            app.last_message,
+            "Update failed and was rolled back: Rate limited"
+        );
+        assert!(matches!(app.mode, Mode::Error(_)), "{:?}", app.mode);
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        match &app.mode {
+            Mode::RecordForm(form) => {
+                assert!(form.is_edit);
+                assert_eq!(form.target_id.as_deref(), Some(selected.as_str()));
+                assert!(form.draft.content.ends_with('x'));
+            }
+            other => panic!("expected the edit form, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn intermittent_listing_failures_leave_the_app_usable() {
+        let mut app = threaded_demo_app("inject_listing");
+        app.backend().failures =
+            vec![FailureRule::parse("list_records_page:every 2:Timed out").unwrap()];
+
+        // Rules count calls from when they were added, so this is the first.
+        handle_key(KeyCode::Char('r'), &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(app.records.len(), 3);
+
+        handle_key(KeyCode::Char('r'), &mut app).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let err = loop {
+            assert!(std::time::Instant::now() < deadline, "load never failed");
+            if let Err(err) = app.tick() {
+                break err;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(format!("{err:#}"), "Timed out");
+        assert!(!app.is_busy());
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.zones.len(),
+            2,
+            "the zone list came back before the failure"
+        );
+
+        handle_key(KeyCode::Char('r'), &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(app.records.len(), 3);
+    }
+
+    #[test]
+    fn credentials_revoked_mid_session_are_reported() {
+        let mut app = threaded_demo_app("inject_auth");
+        app.backend().failures = vec![FailureRule::new(
+            "*",
+            FailWhen::Always,
+            "Verify (401 Unauthorized): Invalid API Token",
+        )];
+        let selected = app.selected_record;
+
+        handle_key(KeyCode::Char('v'), &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert!(
+            app.last_message.contains("verification failed"),
+            "{}",
+            app.last_message
+        );
+
+        let id = app.current_record().unwrap().id.clone();
+        app.delete_record(id).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(app.records.len(), 3);
+        assert_eq!(app.selected_record, selected);
+        assert!(
+            app.last_message.contains("Invalid API Token"),
+            "{}",
+            app.last_message
+        );
+    }
+
+    #[test]
+    fn refresh_during_a_pending_create_keeps_one_row() {
+        let mut app = threaded_demo_app("optimistic_refresh");
+
+        // The reload is queued first, so its page comes back without the new record.
+        app.load_records(false).unwrap();
+        app.create_record(record("", "new.demo.example.com", "A", "192.0.2.1"))
+            .unwrap();
+        wait_until_idle(&mut app);
+
+        let names: Vec<&str> = app.records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names.len(), 4, "{names:?}");
+        assert_eq!(
+            names
+                .iter()
+                .filter(|n| **n == "new.demo.example.com")
+                .count(),
+            1
+        );
+        assert!(app.pending_writes.is_empty());
+    }
+
+    #[test]
+    fn esc_cancels_a_background_load() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app =
+            App::threaded(temp_config_path("cancel_load"), config, MockBackend::new()).unwrap();
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        assert!(!app.is_busy());
+        assert_eq!(app.last_message, "Cancelled loading zones for demo");
+
+        thread::sleep(Duration::from_millis(50));
+        app.tick().unwrap();
+        assert!(app.zones.is_empty(), "cancelled response must be dropped");
+        assert_eq!(app.last_message, "Cancelled loading zones for demo");
+    }
+
+    #[test]
+    fn esc_abandons_the_fetch_instead_of_waiting_for_it() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mock = MockBackend::new().with_latency("5000").unwrap();
+        let mut app = App::threaded(temp_config_path("abandon_load"), config, mock).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        let started = Instant::now();
+        app.backend().latency = Duration::ZERO;
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "the abandoned fetch kept the backend"
+        );
+
+        handle_normal_key(KeyCode::Char('r'), &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(app.zones.len(), 2);
+    }
+
+    #[test]
+    fn esc_keeps_whole_pages_of_a_cancelled_record_load() {
+        let records: Vec<DnsRecord> = (1..=5)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("cancel_pages", records);
+        app.backend().per_page = Some(2);
+        app.refresh_records().unwrap();
+        assert!(app.is_busy());
+
+        handle_normal_key(KeyCode::Esc, &mut app).unwrap();
+        app.tick().unwrap();
+
+        assert!(!app.is_busy());
+        assert_eq!(app.records.len(), 2);
+        assert!(app.records_partial());
+        assert_eq!(
+            app.last_message,
+            "Cancelled loading records for demo.example.com (2 of 5 loaded)"
+        );
+    }
+
+    #[test]
+    fn stale_load_responses_are_discarded() {
+        let mut app = app_with_records(
+            "stale_load",
+            vec![record("1", "www.demo.example.com", "A", "192.0.2.1")],
+        );
+        let stale = app.generation;
+        app.next_zone().unwrap();
+        let shown = app.records.clone();
+
+        let late = Loaded::Records {
+            account: test_account(),
+            zone: app.zones[0].clone(),
+            record_type: None,
+            page: Page {
+                items: vec![record("old", "old.demo.example.com", "A", "192.0.2.9")],
+                page: 1,
+                total_pages: 1,
+                total_count: None,
+            },
+        };
+        app.apply_loaded(stale, Ok(late)).unwrap();
+
+        assert_eq!(app.records, shown);
+        assert_eq!(app.zones[app.selected_zone].name, "demo.services.io");
+    }
+
+    #[test]
+    fn multi_page_record_fetch_continues_on_tick() {
+        let records: Vec<DnsRecord> = (1..=5)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("record_pages", records);
+        app.backend().per_page = Some(2);
+
+        app.refresh_records().unwrap();
+        assert_eq!(app.records.len(), 2);
+        assert!(app.is_busy());
+        assert!(
+            app.last_message.contains("page 1/3"),
+            "{}",
+            app.last_message
+        );
+
+        app.tick().unwrap();
+        assert_eq!(app.records.len(), 4);
+        app.tick().unwrap();
+        assert_eq!(app.records.len(), 5);
+        assert!(!app.is_busy());
+        assert!(
+            app.last_message.starts_with("5 record(s)"),
+            "{}",
+            app.last_message
+        );
+    }
+
+    #[test]
+    fn edits_patch_the_loaded_records_like_a_refresh_would() {
+        let records = (1..=4)
+            .map(|i| {
+                record(
+                    &i.to_string(),
+                    &format!("r{i}.demo.example.com"),
+                    "A",
+                    "192.0.2.1",
+                )
+            })
+            .collect();
+        let mut app = app_with_records("patch_records", records);
+        app.record_total_count = Some(4);
+
+        app.create_record(record("", "new.demo.example.com", "TXT", "hello"))
+            .unwrap();
+        assert_eq!(app.current_record().unwrap().name, "new.demo.example.com");
+
+        let mut edited = app.records[1].clone();
+        edited.content = "192.0.2.99".to_string();
+        app.update_record(edited).unwrap();
+        assert_eq!(
+            app.selected_record, 1,
+            "selection stays on the edited record"
+        );
+        assert_eq!(app.current_record().unwrap().content, "192.0.2.99");
+
+        app.delete_record("3".to_string()).unwrap();
+        app.delete_records(vec![app.records[0].clone()]).unwrap();
+        assert_eq!(app.record_total_count, Some(3));
+
+        let patched = app.records.clone();
+        app.refresh_records().unwrap();
+        assert_eq!(patched, app.records);
+    }
+
+    #[test]
+    fn full_refresh_after_edit_reloads_the_zone() {
+        let records = vec![record("1", "a.demo.example.com", "A", "192.0.2.1")];
+        let mut app = app_with_records("full_refresh_edit", records);
+        let changed_elsewhere = record("9", "z.demo.example.com", "A", "192.0.2.9");
+        app.backend()
+            .records
+            .get_mut("demo-01")
+            .unwrap()
+            .push(changed_elsewhere);
+
+        app.create_record(record("", "b.demo.example.com", "A", "192.0.2.2"))
+            .unwrap();
+        assert_eq!(app.records.len(), 2, "patching leaves other changes alone");
+
+        app.config.full_refresh_after_edit = true;
+        app.create_record(record("", "c.demo.example.com", "A", "192.0.2.3"))
+            .unwrap();
+        assert_eq!(app.records.len(), 4);
+        assert_eq!(app.current_record().unwrap().name, "c.demo.example.com");
+    }
+
+    /// Twenty-five records ten to a page, with the 18th (on the second page) highlighted.
+    fn app_on_record_18(test_name: &str) -> App<MockBackend> {
+        let records = (1..=25)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records(test_name, records);
+        app.focus = Focus::Records;
+        app.update_record_page_size(13); // ten rows a page
+        for _ in 0..17 {
+            handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        }
+        assert_eq!(app.current_record().unwrap().id, "18");
+        app
+    }
+
+    #[test]
+    fn a_refresh_finds_the_highlighted_record_again_by_id() {
+        let mut app = app_on_record_18("keep_highlight_found");
+        app.backend().records.get_mut("demo-01").unwrap().remove(0);
+        handle_normal_key(KeyCode::Char('r'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "18", "found again");
+        assert_eq!((app.selected_record, app.record_page), (16, 1));
+    }
+
+    #[test]
+    fn a_refresh_keeps_the_position_when_the_highlighted_record_vanished() {
+        let mut app = app_on_record_18("keep_highlight_vanished");
+        app.backend().records.get_mut("demo-01").unwrap().remove(17);
+        app.refresh_records().unwrap();
+        assert_eq!(app.selected_record, 17, "gone: the same position");
+        assert_eq!(app.current_record().unwrap().id, "19");
+        assert_eq!(app.record_page, 1);
+
+        // Everything from there down vanished too: clamped to the last one left.
+        app.backend()
+            .records
+            .get_mut("demo-01")
+            .unwrap()
+            .truncate(5);
+        app.refresh_records().unwrap();
+        assert_eq!(app.current_record().unwrap().id, "5");
+        assert_eq!((app.selected_record, app.record_page), (4, 0));
+    }
+
+    #[test]
+    fn deleting_the_highlighted_record_moves_to_the_next_one_down() {
+        let mut app = app_on_record_18("keep_highlight_delete");
+        app.config.full_refresh_after_edit = true;
+        app.delete_record("18".to_string()).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "19", "the next one down");
+        assert_eq!(app.record_page, 1);
+
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.selected_record, 0, "another zone starts at the top");
+    }
+
+    #[test]
+    fn deleting_the_last_record_on_the_last_page_keeps_a_record_highlighted() {
+        let records = (1..=21)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("delete_last_on_page", records);
+        app.focus = Focus::Records;
+        app.update_record_page_size(13); // ten rows a page
+        app.record_filter = "r".to_string();
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!((app.selected_record, app.record_page), (20, 2));
+
+        app.config.full_refresh_after_edit = true;
+        app.delete_record("21".to_string()).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "20");
+        assert_eq!((app.selected_record, app.record_page), (19, 1));
+    }
+
+    #[test]
+    fn a_shrinking_filter_pulls_the_highlight_back_and_empty_lists_say_so() {
+        let records = (1..=12)
+            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("filter_shrinks", records);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "12");
+
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::Char('1'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "12", "still shown");
+        handle_key(KeyCode::Char('0'), &mut app).unwrap();
+        assert_eq!(app.current_record().unwrap().id, "10");
+        handle_key(KeyCode::Char('0'), &mut app).unwrap();
+        assert_eq!(app.current_record(), None);
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("No records match"), "{screen}");
+        for key in ['e', 'd', 'p'] {
+            app.last_message.clear();
+            handle_normal_key(KeyCode::Char(key), &mut app).unwrap();
+            assert_eq!(app.mode, Mode::Normal, "{key}");
+            assert_eq!(app.last_message, "No record selected", "{key}");
+        }
+
+        app.record_filter = "r1".to_string();
+        app.selected_record = 40;
+        draw_app(&mut app);
+        assert_eq!(
+            app.current_record().unwrap().id,
+            "12",
+            "clamped on the next frame"
+        );
+    }
+
+    /// An app showing one record that someone else then edits on the server.
+    fn app_with_remote_edit(test_name: &str) -> App<MockBackend> {
+        let mut loaded = record("1", "api.demo.example.com", "A", "203.0.113.1");
+        loaded.modified_on = Some("2024-01-01T00:00:00Z".to_string());
+        let app = app_with_records(test_name, vec![loaded]);
+        {
+            let mut backend = app.backend();
+            let remote = &mut backend.records.get_mut("demo-01").unwrap()[0];
+            remote.content = "203.0.113.50".to_string();
+            remote.modified_on = Some("2024-02-01T00:00:00Z".to_string());
+        }
+        app
+    }
+
+    #[test]
+    fn updating_a_record_changed_elsewhere_opens_the_conflict_overlay() {
+        let mut app = app_with_remote_edit("conflict_update");
+        let mut edited = app.records[0].clone();
+        edited.content = "203.0.113.2".to_string();
+
+        app.update_record(edited.clone()).unwrap();
+        let Mode::RecordConflict(conflict) = &app.mode else {
+            panic!("expected a conflict, got {:?}", app.mode);
+        };
+        assert_eq!(conflict.remote.content, "203.0.113.50");
+        assert_eq!(conflict.pending, PendingChange::Update(edited.clone()));
+        assert_eq!(
+            app.backend().records["demo-01"][0].content,
+            "203.0.113.50",
+            "nothing is written until the conflict is settled"
+        );
+
+        handle_key(KeyCode::Char('r'), &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.records[0].content, "203.0.113.50");
+
+        app.update_record(edited).unwrap();
+        assert_eq!(
+            app.mode,
+            Mode::Normal,
+            "reloaded record no longer conflicts"
+        );
+        assert_eq!(app.backend().records["demo-01"][0].content, "203.0.113.2");
+    }
+
+    #[test]
+    fn bulk_deletes_leave_records_changed_elsewhere_alone() {
+        // Two records loaded, one of them since edited on the server.
+        let with_unchanged = |test_name| {
+            let mut app = app_with_remote_edit(test_name);
+            app.backend()
+                .records
+                .get_mut("demo-01")
+                .unwrap()
+                .push(record("2", "www.demo.example.com", "A", "192.0.2.1"));
+            app.refresh_records().unwrap();
+            app.records[0].modified_on = Some("2024-01-01T00:00:00Z".to_string());
+            app
+        };
+
+        let mut app = with_unchanged("conflict_batch_delete");
+        app.delete_records(app.records.clone()).unwrap();
+        assert_eq!(
+            app.last_message,
+            "Deleted 1 record(s) from demo.example.com in one batch; \
+             left api.demo.example.com alone, changed on the server since loaded"
+        );
+        let stored = &app.backend().records["demo-01"];
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].content, "203.0.113.50");
+        assert_eq!(app.records, *stored, "the server's copy is shown");
+
+        // One at a time after a failed batch, the changed record is reported as failed.
+        let mut app = with_unchanged("conflict_single_deletes");
+        app.backend().failures =
+            vec![FailureRule::parse("batch:always:Batch API unavailable").unwrap()];
+        app.delete_records(app.records.clone()).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(
+            app.last_message,
+            "1 deleted, 1 failed — press L for details"
+        );
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+        handle_normal_key(KeyCode::Char('L'), &mut app).unwrap();
+        match &app.mode {
+            Mode::BulkReport(report) => assert_eq!(
+                report.failures,
+                ["A api.demo.example.com: changed on the server since it was loaded"]
+            ),
+            other => panic!("expected the bulk report, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn conflicting_delete_can_be_cancelled_or_forced() {
+        let mut app = app_with_remote_edit("conflict_delete");
+
+        app.delete_record("1".to_string()).unwrap();
+        assert!(matches!(app.mode, Mode::RecordConflict(_)));
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+
+        app.delete_record("1".to_string()).unwrap();
+        handle_key(KeyCode::Char('o'), &mut app).unwrap();
+        assert!(app.backend().records["demo-01"].is_empty());
+        assert!(app.records.is_empty());
+    }
+
+    #[test]
+    fn create_record_asks_before_creating_duplicate() {
+        let records = vec![record(
+            "rec-xyz",
+            "api.demo.example.com",
+            "A",
+            "203.0.113.1",
+        )];
+        let mut app = app_with_records("duplicate", records);
+        app.start_record_form(false);
+        if let Mode::RecordForm(form) = &mut app.mode {
+            form.draft.name = "api".to_string();
+            form.draft.content = "203.0.113.1".to_string();
+            form.field_index = 4;
+        }
+
+        handle_record_form_key(KeyCode::Enter, &mut app).unwrap();
+        match &app.mode {
+            Mode::ConfirmCreate(confirm) => assert_eq!(
+                confirm.conflict,
+                CreateConflict::Duplicate {
+                    existing_id: "rec-xyz".to_string()
+                }
+            ),
+            other => panic!("expected duplicate confirmation, got {other:?}"),
+        }
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+
+        handle_confirm_create_key(KeyCode::Esc, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::RecordForm(_)));
+
+        handle_record_form_key(KeyCode::Enter, &mut app).unwrap();
+        handle_confirm_create_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::Normal));
+        assert_eq!(app.backend().records["demo-01"].len(), 2);
+    }
+
+    #[test]
+    fn find_create_conflict_detects_cname_clashes() {
+        let records = vec![
+            record("1", "www.example.com", "A", "203.0.113.1"),
+            record("2", "cdn.example.com", "CNAME", "edge.example.net"),
+        ];
+
+        let cname = record("new", "www", "CNAME", "example.com");
+        assert_eq!(
+            find_create_conflict(&records, "example.com", &cname),
+            Some(CreateConflict::CnameClash {
+                existing_id: "1".to_string(),
+                existing_type: "A".to_string(),
+            })
+        );
+
+        let a = record("new", "cdn.example.com", "A", "203.0.113.2");
+        assert!(matches!(
+            find_create_conflict(&records, "example.com", &a),
+            Some(CreateConflict::CnameClash { .. })
+        ));
+
+        let unrelated = record("new", "api", "A", "203.0.113.1");
+        assert_eq!(
+            find_create_conflict(&records, "example.com", &unrelated),
+            None
+        );
+
+        let second_a = record("new", "www", "A", "203.0.113.9");
+        assert_eq!(
+            find_create_conflict(&records, "example.com", &second_a),
+            None
+        );
+    }
+
+    fn journal_entry(timestamp: i64, action: ChangeAction, content: &str) -> JournalEntry {
+        let mut after = record("rec-1", "api.demo.example.com", "A", content);
+        after.ttl = 120;
+        JournalEntry {
+            timestamp,
+            account: "demo".to_string(),
+            zone_id: "demo-01".to_string(),
+            record_id: "rec-1".to_string(),
+            action,
+            before: None,
+            after: Some(after),
+        }
+    }
+
+    fn audit_entry(id: &str, timestamp: i64, action: ChangeAction) -> AuditEntry {
+        AuditEntry {
+            id: id.to_string(),
+            timestamp,
+            action,
+            actor: Some("ops@example.com".to_string()),
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merge_history_interleaves_and_dedupes_sources() {
+        let local = vec![
+            journal_entry(1_000, ChangeAction::Create, "203.0.113.1"),
+            journal_entry(5_000, ChangeAction::Update, "203.0.113.2"),
+        ];
+        let remote = vec![
+            audit_entry("cf-3", 9_000, ChangeAction::Update),
+            audit_entry("cf-2", 5_030, ChangeAction::Update),
+            audit_entry("cf-1", 3_000, ChangeAction::Update),
+        ];
+
+        let merged = merge_history(local, remote);
+        let summary: Vec<(i64, HistorySource)> =
+            merged.iter().map(|e| (e.timestamp, e.source)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1_000, HistorySource::Local),
+                (3_000, HistorySource::Cloudflare),
+                (5_000, HistorySource::Both),
+                (9_000, HistorySource::Cloudflare),
+            ]
+        );
+        assert_eq!(merged[2].actor.as_deref(), Some("ops@example.com"));
+        assert!(
+            merged[0]
+                .changes
+                .iter()
+                .any(|c| c.field == "content" && c.after.as_deref() == Some("203.0.113.1"))
+        );
+    }
+
+    #[test]
+    fn rfc3339_parsing_matches_formatting() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        let ts = parse_rfc3339("2024-02-29T13:45:10.250Z").unwrap();
+        assert_eq!(format_timestamp(ts), "2024-02-29 13:45:10 UTC");
+        assert_eq!(parse_rfc3339("2024-02-29T15:45:10+02:00"), Some(ts));
+        assert_eq!(parse_rfc3339("not a date"), None);
+    }
+
+    #[test]
+    fn record_history_tab_merges_journal_and_audit_log() {
+        let records = vec![record("rec-1", "api.demo.example.com", "A", "203.0.113.1")];
+        let mut app = app_with_records("history", records);
+        app.journal
+            .append(&journal_entry(2_000, ChangeAction::Update, "203.0.113.1"))
+            .unwrap();
+        app.backend().audit_log = vec![audit_entry("cf-1", 1_000, ChangeAction::Create)];
+
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Enter, &mut app).unwrap();
+        handle_record_detail_key(KeyCode::Char('h'), &mut app).unwrap();
+
+        let Mode::RecordDetail(detail) = &app.mode else {
+            panic!("record detail popup did not open");
+        };
+        assert_eq!(detail.tab, DetailTab::History);
+        let sources: Vec<HistorySource> = detail.history.iter().map(|e| e.source).collect();
+        assert_eq!(
+            sources,
+            vec![HistorySource::Cloudflare, HistorySource::Local]
+        );
+    }
+
+    #[test]
+    fn configs_round_trip_as_json_or_toml_and_toml_wins() {
+        let dir = temp_config_path("config_formats").with_extension("d");
+        let json_path = dir.join("accounts.json");
+        let toml_path = dir.join("accounts.toml");
+        let mut config = Config {
+            accounts: vec![test_account()],
+            collapse_accounts: true,
+            ..Config::default()
+        };
+        config
+            .type_colors
+            .insert("TXT".to_string(), "cyan".to_string());
+
+        config.save(&json_path).unwrap();
+        assert!(fs::read_to_string(&json_path).unwrap().starts_with('{'));
+        assert_eq!(preferred_config_path(&json_path), json_path);
+        assert!(shadowed_config_note(&json_path).is_none());
+        let loaded = Config::load(&json_path).unwrap();
+        assert_eq!(loaded.accounts, config.accounts);
+        assert_eq!(loaded.type_colors, config.type_colors);
+
+        fs::write(
+            &toml_path,
+            "# Work accounts\n# token rotated quarterly\n\naccounts = []\ncollapse_accounts = false\n",
+        )
+        .unwrap();
+        assert_eq!(preferred_config_path(&json_path), toml_path, "TOML wins");
+        assert!(
+            shadowed_config_note(&toml_path)
+                .unwrap()
+                .contains("accounts.json")
+        );
+        assert!(!Config::load(&toml_path).unwrap().collapse_accounts);
+
+        config.save(&toml_path).unwrap();
+        let text = fs::read_to_string(&toml_path).unwrap();
+        assert!(
+            text.starts_with("# Work accounts\n# token rotated quarterly\n\n"),
+            "{text}"
+        );
+        assert!(text.contains("[[accounts]]"), "{text}");
+        let loaded = Config::load(&toml_path).unwrap();
+        assert_eq!(loaded.accounts, config.accounts);
+        assert_eq!(loaded.type_colors, config.type_colors);
+        assert!(loaded.collapse_accounts);
+        config.save(&toml_path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&toml_path).unwrap(),
+            text,
+            "saving again doesn't pile up comments"
+        );
+
+        fs::write(&toml_path, format!("tokn = \"typo\"\n{text}")).unwrap();
+        let issues = config_issues(&toml_path, &fs::read_to_string(&toml_path).unwrap()).unwrap();
+        assert!(issues.iter().any(|i| i.contains("tokn")), "{issues:?}");
+        fs::write(&toml_path, "accounts = [").unwrap();
+        let Err(err) = Config::load(&toml_path) else {
+            panic!("a broken TOML file loads");
+        };
+        assert!(format!("{err:#}").contains("accounts.toml"), "{err:#}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Runs a headless subcommand given as its command line; returns stdout and stderr.
+    fn run_subcommand(
+        config: &Config,
+        backend: &mut MockBackend,
+        journal: &Journal,
+        args: &[&str],
+    ) -> Result<(String, String)> {
+        let request = CliArgs::parse(args.iter().map(|a| a.to_string()))?
+            .headless
+            .expect("a subcommand");
+        let (mut out, mut notes) = (Vec::new(), Vec::new());
+        headless::run(
+            &request,
+            config,
+            backend,
+            journal,
+            &mut out,
+            &mut notes,
+            |_| Err(anyhow!("No terminal to ask on")),
+        )?;
+        Ok((String::from_utf8(out)?, String::from_utf8(notes)?))
+    }
+
+    #[test]
+    fn list_subcommands_print_tables_or_json_lines_without_the_tui() {
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|a| a.to_string()));
+        assert_eq!(
+            args(&[
+                "list-records",
+                "--account=demo",
+                "--zone",
+                "demo.example.com",
+                "--json"
+            ])
+            .unwrap()
+            .headless,
+            Some(headless::Request {
+                command: headless::Command::ListRecords,
+                options: headless::Options {
+                    account: Some("demo".to_string()),
+                    zone: Some("demo.example.com".to_string()),
+                    json: true,
+                    ..headless::Options::default()
+                },
+            })
+        );
+        assert!(args(&["list-records"]).is_err());
+        assert!(args(&["list-zones", "--zone", "demo.example.com"]).is_err());
+        assert!(args(&["list-zones", "list-records"]).is_err());
+        assert!(args(&["list-zones", "--encrypt"]).is_err());
+        assert!(args(&["--json"]).is_err());
+
+        let config = Config {
+            accounts: vec![
+                test_account(),
+                Account {
+                    name: "other".to_string(),
+                    ..test_account()
+                },
+            ],
+            ..Config::default()
+        };
+        let journal = Journal::for_config(&temp_config_path("list_subcommands"));
+        let run = |list: &[&str]| {
+            run_subcommand(&config, &mut MockBackend::new(), &journal, list).map(|(out, _)| out)
+        };
+
+        let err = run(&["list-zones"]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Pick an account with --account: demo, other"
+        );
+        let err = run(&["list-zones", "--account", "nope"]).err().unwrap();
+        assert!(err.to_string().starts_with("No account named \"nope\""));
+
+        let table = run(&["list-zones", "--account", "demo"]).unwrap();
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("NAME "));
+        assert!(table.contains("demo.services.io  pending"), "{table}");
+
+        let json = run(&["list-zones", "--account", "demo", "--json"]).unwrap();
+        let zones: Vec<Zone> = json
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            zones,
+            MockBackend::new()
+                .list_zones(&test_account())
+                .unwrap()
+                .items
+        );
+
+        let records = run(&[
+            "list-records",
+            "--account",
+            "demo",
+            "--zone",
+            "DEMO.example.com.",
+            "--json",
+        ])
+        .unwrap();
+        assert!(!records.is_empty());
+        for line in records.lines() {
+            let record: DnsRecord = serde_json::from_str(line).unwrap();
+            assert!(record.name.ends_with("demo.example.com"), "{line}");
+        }
+        let err = run(&[
+            "list-records",
+            "--account",
+            "demo",
+            "--zone",
+            "missing.example",
+        ])
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "demo has no zone named missing.example");
+    }
+
+    #[test]
+    fn record_subcommands_create_update_and_delete_with_dry_runs() {
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|a| a.to_string()));
+        assert!(args(&["record"]).is_err());
+        assert!(args(&["record", "create", "--zone", "z", "--name", "www"]).is_err());
+        assert!(
+            args(&[
+                "record", "update", "--zone", "z", "--name", "api", "--type", "A"
+            ])
+            .is_err()
+        );
+        assert!(
+            args(&[
+                "record", "delete", "--zone", "z", "--id", "1", "--ttl", "60"
+            ])
+            .is_err()
+        );
+        assert!(args(&["list-zones", "--dry-run"]).is_err());
+
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let path = temp_config_path("record_subcommands");
+        let journal = Journal::for_config(&path);
+        let _ = fs::remove_file(&journal.path);
+        let mut backend = MockBackend::new();
+        let mut run = |list: &[&str]| {
+            let mut line = vec!["record"];
+            line.extend_from_slice(list);
+            line.extend(["--zone", "demo.example.com"]);
+            run_subcommand(&config, &mut backend, &journal, &line)
+        };
+
+        let (out, _) = run(&[
+            "create",
+            "--name",
+            "www",
+            "--type",
+            "txt",
+            "--content",
+            "v=1",
+            "--json",
+        ])
+        .unwrap();
+        let created: DnsRecord = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(
+            (created.record_type.as_str(), created.ttl, created.proxied),
+            ("TXT", 300, false)
+        );
+        let err = run(&[
+            "create",
+            "--name",
+            "www",
+            "--type",
+            "TXT",
+            "--content",
+            "v=1",
+        ])
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("nothing created"), "{err}");
+        let err = run(&[
+            "create",
+            "--name",
+            "x",
+            "--type",
+            "MX",
+            "--content",
+            "m",
+            "--proxied",
+        ])
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "MX records can't be proxied on Cloudflare");
+        let err = run(&[
+            "create",
+            "--name",
+            "x",
+            "--type",
+            "A",
+            "--content",
+            "1",
+            "--ttl",
+            "5",
+        ])
+        .err()
+        .unwrap();
+        assert!(err.to_string().starts_with("TTL must be auto"), "{err}");
+
+        // A dry run reads what it needs and sends nothing.
+        let (out, _) = run(&[
+            "update",
+            "--name",
+            "api",
+            "--type",
+            "A",
+            "--ttl",
+            "auto",
+            "--dry-run",
+        ])
+        .unwrap();
+        assert_eq!(
+            out,
+            "Would update A api.demo.example.com 203.0.113.10 (TTL auto, proxied, id demo-01-a)\n"
+        );
+        let (out, _) = run(&[
+            "update",
+            "--name",
+            "api",
+            "--type",
+            "A",
+            "--content",
+            "192.0.2.1",
+        ])
+        .unwrap();
+        assert!(
+            out.starts_with("Updated A api.demo.example.com 192.0.2.1 (TTL 300"),
+            "{out}"
+        );
+        let (_, notes) = run(&["update", "--id", "demo-01-a", "--content", "192.0.2.1"]).unwrap();
+        assert!(notes.contains("nothing sent"), "{notes}");
+
+        let (out, _) = run(&["delete", "--id", &created.id, "--dry-run"]).unwrap();
+        assert!(out.starts_with("Would delete TXT www v=1"), "{out}");
+        run(&[
+            "delete",
+            "--name",
+            "www",
+            "--type",
+            "TXT",
+            "--content",
+            "v=1",
+        ])
+        .unwrap();
+        let err = run(&["delete", "--id", &created.id]).err().unwrap();
+        assert!(format!("{err:#}").contains(&created.id), "{err:#}");
+
+        let actions: Vec<ChangeAction> = fs::read_to_string(&journal.path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<JournalEntry>(line).unwrap().action)
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                ChangeAction::Create,
+                ChangeAction::Update,
+                ChangeAction::Delete
+            ]
+        );
+        let _ = fs::remove_file(&journal.path);
+    }
+
+    #[test]
+    fn apply_plans_match_records_by_name_and_type() {
+        let api = DnsRecord {
+            unmodeled: vec!["comment".to_string()],
+            ..record("3", "api.example.com", "A", "192.0.2.3")
+        };
+        let live = vec![
+            record("1", "www.example.com", "A", "192.0.2.1"),
+            record("2", "www.example.com", "A", "192.0.2.2"),
+            api.clone(),
+            record("4", "Mail.Example.com.", "MX", "10 mx.example.net"),
+            record("5", "old.example.com", "TXT", "bye"),
+            record("6", "example.com", "SOA", "ns1 admin 1 2 3 4 5"),
+            record("7", "cdn.example.com", "CNAME", "Edge.Example.net."),
+        ];
+        let desired = vec![
+            record("", "www.example.com", "A", "192.0.2.2"),
+            record("", "www.example.com", "A", "192.0.2.9"),
+            record("", "www.example.com", "A", "192.0.2.10"),
+            DnsRecord {
+                ttl: 60,
+                ..record("", "api.example.com", "A", "192.0.2.3")
+            },
+            record("", "mail.example.com", "MX", "10 mx.example.net"),
+            record("", "cdn.example.com", "CNAME", "edge.example.net"),
+            record("", "www.example.com", "AAAA", "2001:db8::1"),
+        ];
+
+        // Round-robin members already live stay, one the file lacks takes a new content
+        // and keeps its id, and the one left over is created; names and host contents
+        // ignore case and a trailing dot.
+        let expected = vec![
+            Step::Update {
+                before: api.clone(),
+                after: DnsRecord { ttl: 60, ..api },
+            },
+            Step::Update {
+                before: live[0].clone(),
+                after: DnsRecord {
+                    content: "192.0.2.9".to_string(),
+                    ..live[0].clone()
+                },
+            },
+            Step::Create {
+                record: desired[2].clone(),
+            },
+            Step::Create {
+                record: desired[6].clone(),
+            },
+        ];
+        let plan = sync::plan(&live, &desired, false);
+        assert_eq!(plan.steps, expected);
+        assert_eq!((plan.unchanged, plan.skipped()), (3, 4));
+        assert_eq!(
+            plan.summary(),
+            "2 to create, 2 to update, 0 to delete, 3 unchanged, 1 not in the file left alone"
+        );
+        // Updates go past the policy like a form edit: strict mode takes out the one that
+        // would drop the comment, and otherwise it stays with a warning.
+        let mut lenient = plan.clone();
+        Policy { strict: false }.screen(&mut lenient);
+        assert_eq!(lenient.steps, expected);
+        assert_eq!(
+            lenient.warnings,
+            ["comment on api.example.com were not preserved"]
+        );
+        let mut strict = plan.clone();
+        Policy { strict: true }.screen(&mut strict);
+        assert_eq!(strict.steps[..], expected[1..]);
+        assert!(strict.summary().ends_with(
+            "1 refused in strict mode (an update would drop fields nyxflare doesn't model)"
+        ));
+        let lines: Vec<String> = plan
+            .steps
+            .iter()
+            .map(|step| format!("{} {}", step.sign(), step.describe()))
+            .collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "~ A api.example.com 192.0.2.3 (TTL 300 → 60)",
+                "~ A www.example.com 192.0.2.1 → 192.0.2.9 (TTL 300)",
+                "+ A www.example.com 192.0.2.10 (TTL 300)",
+            ]
+        );
+
+        // Pruning deletes the name and type the file lacks, first; never the SOA.
+        let pruned = sync::plan(&live, &desired, true);
+        assert_eq!(
+            pruned.steps[0],
+            Step::Delete {
+                record: live[4].clone()
+            }
+        );
+        assert_eq!(pruned.steps[1..], expected[..]);
+        assert_eq!((pruned.unchanged, pruned.skipped()), (3, 3));
+
+        // A shrinking set deletes without --prune; proxying alone is an update.
+        let live = vec![
+            record("1", "x.example.com", "A", "192.0.2.1"),
+            record("2", "x.example.com", "A", "192.0.2.2"),
+        ];
+        let proxied = DnsRecord {
+            proxied: true,
+            ..record("", "x.example.com", "A", "192.0.2.1")
+        };
+        let plan = sync::plan(&live, std::slice::from_ref(&proxied), false);
+        let signs: Vec<String> = plan
+            .steps
+            .iter()
+            .map(|step| format!("{} {}", step.sign(), step.describe()))
+            .collect();
+        assert_eq!(
+            signs,
+            [
+                "- A x.example.com 192.0.2.2 (TTL 300)",
+                "~ A x.example.com 192.0.2.1 (TTL 300, DNS only → proxied)",
+            ]
+        );
+
+        // Nothing live creates everything; nothing desired keeps or prunes everything.
+        let plan = sync::plan(&[], &desired, false);
+        assert!(
+            plan.steps
+                .iter()
+                .all(|step| matches!(step, Step::Create { .. }))
+        );
+        assert_eq!(plan.steps.len(), desired.len());
+        let plan = sync::plan(&live, &[], false);
+        assert_eq!((plan.steps.len(), plan.skipped()), (0, 2));
+        assert_eq!(sync::plan(&live, &[], true).steps.len(), 2);
+        assert_eq!(
+            sync::plan(&live, &live, true),
+            ChangeSet {
+                unchanged: 2,
+                ..ChangeSet::default()
+            }
+        );
+    }
+
+    #[test]
+    fn apply_subcommand_prints_the_plan_and_applies_it_only_when_told() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let dir = temp_config_path("apply_state").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let file = dir.join("demo.toml");
+        fs::write(
+            &file,
+            r#"zone = "demo.example.com"
+
+[[records]]
+name = "api"
+type = "A"
+content = ["203.0.113.10", "203.0.113.11"]
+ttl = 300
+proxied = true
+
+[[records]]
+name = "cdn"
+type = "CNAME"
+content = "edge.service.net"
+ttl = "auto"
+"#,
+        )
+        .unwrap();
+        let file = file.to_str().unwrap();
+        let mut backend = MockBackend::new();
+        let mut run = |list: &[&str]| {
+            let mut line = vec!["apply", "--file", file];
+            line.extend(list);
+            run_subcommand(&config, &mut backend, &journal, &line)
+        };
+
+        let (out, notes) = run(&["--dry-run"]).unwrap();
+        assert_eq!(
+            out,
+            "~ CNAME cdn.demo.example.com edge.service.net (TTL 120 → auto, proxied)\n\
+             + A api.demo.example.com 203.0.113.11 (TTL 300, proxied)\n"
+        );
+        assert_eq!(
+            notes,
+            "Plan for demo.example.com: 1 to create, 1 to update, 0 to delete, 1 unchanged, \
+             1 not in the file left alone\n"
+        );
+        // Without --yes it asks, and there's no terminal to ask on here.
+        let err = run(&[]).err().unwrap();
+        assert_eq!(err.to_string(), "No terminal to ask on");
+        let err = run(&["--zone", "demo.services.io"]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "The file is for demo.example.com, not demo.services.io"
+        );
+
+        let (out, notes) = run(&["--prune", "--yes", "--json"]).unwrap();
+        let steps: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        // The plan, then each step's outcome.
+        assert_eq!(steps.len(), 6, "{out}");
+        assert!(steps[..3].iter().all(|step| step.get("status").is_none()));
+        assert!(steps[3..].iter().all(|step| step["status"] == "done"));
+        let actions: Vec<String> = steps[3..]
+            .iter()
+            .map(|step| {
+                let record = &step[if step["action"] == "update" {
+                    "after"
+                } else {
+                    "record"
+                }];
+                format!("{} {}", step["action"], record["name"])
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                r#""delete" "mail.demo.example.com""#,
+                r#""update" "cdn.demo.example.com""#,
+                r#""create" "api.demo.example.com""#,
+            ]
+        );
+        assert!(
+            notes.ends_with("Applied 3 change(s) to demo.example.com\n"),
+            "{notes}"
+        );
+        assert_eq!(
+            journal.entries_for("demo-01", "demo-01-b").unwrap().len(),
+            1
+        );
+        let (out, notes) = run(&["--prune"]).unwrap();
+        assert_eq!(out, "");
+        assert_eq!(
+            notes,
+            "Nothing to change in demo.example.com: 0 to create, 0 to update, 0 to delete, \
+             3 unchanged\n"
+        );
+
+        let json = dir.join("bad.json");
+        fs::write(
+            &json,
+            r#"{"records": [{"name": "txt", "type": "TXT", "content": "a", "proxied": true}]}"#,
+        )
+        .unwrap();
+        let err = run_subcommand(
+            &config,
+            &mut MockBackend::new(),
+            &journal,
+            &["apply", "--file", json.to_str().unwrap()],
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().starts_with("apply needs --zone"), "{err}");
+        let err = run_subcommand(
+            &config,
+            &mut MockBackend::new(),
+            &journal,
+            &[
+                "apply",
+                "--zone",
+                "demo.example.com",
+                "--file",
+                json.to_str().unwrap(),
+            ],
+        )
+        .err()
+        .unwrap();
+        assert!(
+            format!("{err:#}").contains("TXT records can't be proxied"),
+            "{err:#}"
+        );
+        // Strict mode refuses a plan that would drop a record's comment, before the diff.
+        let mut commented = record("1", "cdn.demo.example.com", "CNAME", "edge.service.net");
+        commented.unmodeled = vec!["comment".to_string()];
+        let mut backend = MockBackend::new();
+        backend
+            .records
+            .insert("demo-01".to_string(), vec![commented]);
+        let strict = Config {
+            strict: true,
+            ..config.clone()
+        };
+        let err = run_subcommand(&strict, &mut backend, &journal, &["apply", "--file", file])
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("Strict mode:"), "{err}");
+        assert_eq!(backend.records["demo-01"].len(), 1);
+
+        // The same file as YAML plans the same changes.
+        let yaml = dir.join("demo.yaml");
+        fs::write(
+            &yaml,
+            "zone: demo.example.com
+records:
+  - name: api
+    type: A
+    content: [203.0.113.10, 203.0.113.11]
+    ttl: 300
+    proxied: true
+  - name: cdn
+    type: CNAME
+    content: edge.service.net
+    ttl: auto
+",
+        )
+        .unwrap();
+        let (out, _) = run_subcommand(
+            &config,
+            &mut MockBackend::new(),
+            &journal,
+            &["apply", "--dry-run", "--file", yaml.to_str().unwrap()],
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "~ CNAME cdn.demo.example.com edge.service.net (TTL 120 → auto, proxied)\n\
+             + A api.demo.example.com 203.0.113.11 (TTL 300, proxied)\n"
+        );
+        let yml = dir.join("broken.yml");
+        fs::write(&yml, "records:\n  - name: api\n    colour: blue\n").unwrap();
+        let err = sync::StateFile::read(&yml).err().unwrap();
+        assert_eq!(err.to_string(), format!("Reading {}", yml.display()));
+        assert!(
+            format!("{err:#}").contains("unknown field `colour`"),
+            "{err:#}"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backups_save_every_zone_and_restore_puts_one_back() {
+        let config = Config {
+            accounts: vec![
+                test_account(),
+                Account {
+                    name: "acme".to_string(),
+                    ..test_account()
+                },
+                Account {
+                    name: "locked".to_string(),
+                    token_error: Some("pass exited with status 1".to_string()),
+                    ..test_account()
+                },
+            ],
+            ..Config::default()
+        };
+        let dir = temp_config_path("backup").with_extension("d");
+        let _ = fs::remove_dir_all(&dir);
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let root = dir.join("snapshots");
+        let root_arg = root.to_str().unwrap();
+        let mut backend = MockBackend::new()
+            .with_failures("list_records_page:next:Rate limited")
+            .unwrap();
+        let args = ["backup", "--out", root_arg, "--pause", "0"];
+        let err = run_subcommand(&config, &mut backend, &journal, &args)
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("2 account(s) or zone(s) couldn't be backed up"),
+            "{err}"
+        );
+        // The locked account still fails, but every zone is saved this time.
+        let err = run_subcommand(&config, &mut backend, &journal, &args)
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("1 account(s)"), "{err}");
+        let mut runs: Vec<PathBuf> = fs::read_dir(&root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        runs.sort();
+        assert_eq!(runs.len(), 2);
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(runs[1].join("manifest.json")).unwrap())
+                .unwrap();
+        let zones = manifest["zones"].as_array().unwrap();
+        assert_eq!(zones.len(), 5, "{manifest}");
+        assert_eq!(zones[0]["zone"], "demo.example.com");
+        assert_eq!(zones[0]["records"], 3);
+        assert!(zones[4]["error"].as_str().unwrap().contains("no secret"));
+        let saved = runs[1].join("demo");
+        assert!(
+            fs::read_to_string(saved.join("demo.example.com.zone"))
+                .unwrap()
+                .contains("203.0.113.10")
+        );
+        assert!(saved.join("demo.services.io.json").exists());
+
+        let from = runs[1].to_str().unwrap();
+        let restore = |list: &[&'static str]| {
+            let mut line = vec!["restore", "--from", from, "--zone", "demo.example.com"];
+            line.extend(list);
+            line
+        };
+        let (_, notes) =
+            run_subcommand(&config, &mut backend, &journal, &restore(&["--dry-run"])).unwrap();
+        assert!(notes.ends_with("0 to create, 0 to update, 0 to delete, 3 unchanged\n"));
+        let api = [
+            "--account",
+            "demo",
+            "--zone",
+            "demo.example.com",
+            "--name",
+            "api",
+        ];
+        let line = [&["record", "delete", "--type", "A"][..], &api[..]].concat();
+        run_subcommand(&config, &mut backend, &journal, &line).unwrap();
+        let (out, _) =
+            run_subcommand(&config, &mut backend, &journal, &restore(&["--dry-run"])).unwrap();
+        assert_eq!(
+            out,
+            "+ A api.demo.example.com 203.0.113.10 (TTL 300, proxied)\n"
+        );
+        let (out, notes) = run_subcommand(&config, &mut backend, &journal, &restore(&[])).unwrap();
+        assert!(out.starts_with("Created"), "{out}");
+        assert_eq!(
+            notes,
+            "Restored 1 record(s) into demo.example.com; 2 were already there\n"
+        );
+        let err = run_subcommand(
+            &config,
+            &mut backend,
+            &journal,
+            &["restore", "--from", from, "--zone", "nowhere.example.com"],
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("has no backup of nowhere.example.com")
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ddns_reads_plain_or_json_answers_of_the_right_family() {
+        use ddns::{Family, parse_answer};
+        let v4 = parse_answer("198.51.100.7\n", Family::V4).unwrap();
+        assert_eq!(v4.to_string(), "198.51.100.7");
+        let v6 = parse_answer(r#"{"ip":"2001:db8::7"}"#, Family::V6).unwrap();
+        assert_eq!(v6.to_string(), "2001:db8::7");
+        let err = parse_answer("2001:db8::7", Family::V4).unwrap_err();
+        assert_eq!(err.to_string(), "2001:db8::7, which isn't an IPv4 address");
+        let err = parse_answer("<html>rate limited</html>", Family::V4).unwrap_err();
+        assert!(
+            err.to_string().ends_with("which isn't an IP address"),
+            "{err}"
+        );
+        assert!(ddns::check_source("ftp://example.com").is_err());
+        assert!(ddns::check_source(ddns::LOCAL_SOURCE).is_ok());
+    }
+
+    #[test]
+    fn ddns_creates_updates_or_leaves_the_record_and_says_which() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let dir = temp_config_path("ddns").with_extension("d");
+        let _ = fs::remove_dir_all(&dir);
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let mut backend = MockBackend::new();
+        let mut ddns = |list: &[&str]| -> Result<(headless::Finish, String, String)> {
+            let mut line = vec!["ddns", "--zone", "demo.example.com"];
+            line.extend(list);
+            let request = CliArgs::parse(line.iter().map(|a| a.to_string()))?
+                .headless
+                .expect("a subcommand");
+            let (mut out, mut notes) = (Vec::new(), Vec::new());
+            let finish = headless::run(
+                &request,
+                &config,
+                &mut backend,
+                &journal,
+                &mut out,
+                &mut notes,
+                |_| Err(anyhow!("No terminal to ask on")),
+            )?;
+            Ok((finish, String::from_utf8(out)?, String::from_utf8(notes)?))
+        };
+
+        let (finish, out, _) = ddns(&["--name", "home", "--ip", "198.51.100.7"]).unwrap();
+        assert_eq!(finish, headless::Finish::Done);
+        assert!(out.starts_with("Created"), "{out}");
+        assert!(out.contains("198.51.100.7"), "{out}");
+        let (finish, out, notes) = ddns(&["--name", "home", "--ip", "198.51.100.7"]).unwrap();
+        assert_eq!(finish, headless::Finish::Unchanged);
+        assert_eq!(out, "");
+        assert_eq!(notes, "A home.demo.example.com is already 198.51.100.7\n");
+
+        // The existing record keeps its TTL and proxying; only the address moves.
+        let (_, out, _) = ddns(&["--name", "api", "--ip", "198.51.100.8", "--dry-run"]).unwrap();
+        assert!(out.starts_with("Would update"), "{out}");
+        let (finish, out, _) = ddns(&["--name", "api", "--ip", "198.51.100.8"]).unwrap();
+        assert_eq!(finish, headless::Finish::Done);
+        assert!(out.starts_with("Updated"), "{out}");
+        assert!(out.contains("198.51.100.8") && out.contains("300"), "{out}");
+        let (finish, _, _) = ddns(&["--name", "api", "--ip", "198.51.100.8"]).unwrap();
+        assert_eq!(finish, headless::Finish::Unchanged);
+        let err = ddns(&["--name", "api", "--ip", "2001:db8::1"]).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("isn't an IPv4 address"),
+            "{err:#}"
+        );
+        let err = ddns(&["--name", "api", "--type", "MX"]).unwrap_err();
+        assert_eq!(err.to_string(), "ddns keeps A and AAAA records, not MX");
+
+        let mut commented = record("1", "home.demo.example.com", "A", "198.51.100.7");
+        commented.unmodeled = vec!["comment".to_string()];
+        let mut backend = MockBackend::new();
+        backend
+            .records
+            .insert("demo-01".to_string(), vec![commented]);
+        let strict = Config {
+            strict: true,
+            ..config.clone()
+        };
+        let line = [
+            "ddns",
+            "--zone",
+            "demo.example.com",
+            "--name",
+            "home",
+            "--ip",
+            "198.51.100.9",
+        ];
+        let err = run_subcommand(&strict, &mut backend, &journal, &line)
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("Strict mode:"), "{err}");
+        assert_eq!(backend.records["demo-01"][0].content, "198.51.100.7");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn imports_run_their_updates_past_the_policy() {
+        let dir = temp_config_path("import_policy").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let file = dir.join("demo.zone");
+        let file = file.to_str().unwrap();
+        fs::write(file, "api 600 IN A 203.0.113.1\n").unwrap();
+        let mut commented = record("1", "api.demo.example.com", "A", "203.0.113.1");
+        commented.unmodeled = vec!["comment".to_string()];
+        let line = ["import", "--file", file, "--zone", "demo.example.com"];
+
+        for strict in [false, true] {
+            let config = Config {
+                accounts: vec![test_account()],
+                strict,
+                ..Config::default()
+            };
+            let mut backend = MockBackend::new();
+            backend
+                .records
+                .insert("demo-01".to_string(), vec![commented.clone()]);
+            let result = run_subcommand(&config, &mut backend, &journal, &line);
+            let ttl = backend.records["demo-01"][0].ttl;
+            if strict {
+                let err = result.err().unwrap();
+                assert!(err.to_string().starts_with("Strict mode:"), "{err}");
+                assert_eq!(ttl, 300, "nothing is sent");
+            } else {
+                let (_, notes) = result.unwrap();
+                assert!(
+                    notes.starts_with("comment on api.demo.example.com were not preserved\n"),
+                    "{notes}"
+                );
+                assert_eq!(ttl, 600);
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exported_zones_import_back_to_the_same_records() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let dir = temp_config_path("zone_round_trip").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let file = dir.join("demo.zone");
+        let file = file.to_str().unwrap();
+        let mut backend = MockBackend::new();
+        let mut run = |list: &[&str]| {
+            let mut line = list.to_vec();
+            line.extend(["--zone", "demo.example.com"]);
+            run_subcommand(&config, &mut backend, &journal, &line)
+        };
+        let record_set = |listing: String| {
+            let mut set: Vec<(String, String, String, u32, bool)> = listing
+                .lines()
+                .map(|line| serde_json::from_str::<DnsRecord>(line).unwrap())
+                .map(|r| (r.name, r.record_type, r.content, r.ttl, r.proxied))
+                .collect();
+            set.sort();
+            set
+        };
+
+        let original = record_set(run(&["list-records", "--json"]).unwrap().0);
+        let (_, notes) = run(&["export", "--out", file]).unwrap();
+        assert!(notes.starts_with(&format!("Wrote {} record(s)", original.len())));
+        let text = fs::read_to_string(file).unwrap();
+        assert!(text.contains("api\t300\tIN\tA\t203.0.113.10 ; cf_tags=cf-proxied:true"));
+
+        run(&["record", "delete", "--name", "api", "--type", "A"]).unwrap();
+        run(&[
+            "record", "update", "--name", "cdn", "--type", "CNAME", "--ttl", "60",
+        ])
+        .unwrap();
+        run(&[
+            "record",
+            "create",
+            "--name",
+            "extra",
+            "--type",
+            "TXT",
+            "--content",
+            "x",
+        ])
+        .unwrap();
+
+        let (out, notes) = run(&["import", "--file", file, "--replace", "--dry-run"]).unwrap();
+        assert_eq!(out.lines().count(), 3, "{out}");
+        assert!(out.starts_with("- TXT extra x (TTL 300)\n~ CNAME"), "{out}");
+        assert_eq!(
+            notes,
+            "Would import into demo.example.com: 1 to create, 1 to update, 1 to delete, \
+             1 unchanged\n"
+        );
+        let (_, notes) = run(&["import", "--file", file, "--replace"]).unwrap();
+        assert!(
+            notes.ends_with("Imported into demo.example.com: 3 change(s) made, 1 unchanged\n"),
+            "{notes}"
+        );
+        assert_eq!(
+            record_set(run(&["list-records", "--json"]).unwrap().0),
+            original
+        );
+        let (out, _) = run(&["import", "--file", file, "--replace", "--json"]).unwrap();
+        assert!(out.is_empty(), "{out}");
+
+        // Comments, $TTL and a SOA the provider keeps to itself; without --replace
+        // nothing is deleted, and each failure is reported.
+        fs::write(
+            file,
+            "$TTL 120 ; two minutes\n@ IN SOA ns1 admin 1 2 3 4 5\nnew IN A 192.0.2.44 ; added\n\
+             next IN A 192.0.2.45\n",
+        )
+        .unwrap();
+        let mut failing = MockBackend::new()
+            .with_failures("create_record:always:Quota exceeded")
+            .unwrap();
+        let import = |list: &[&str]| {
+            let mut line = vec!["import", "--file", file, "--zone", "demo.example.com"];
+            line.extend(list);
+            CliArgs::parse(line.iter().map(|arg| arg.to_string()))
+                .unwrap()
+                .headless
+                .unwrap()
+        };
+        let err = run_subcommand(
+            &config,
+            &mut failing,
+            &journal,
+            &["import", "--file", file, "--zone", "demo.example.com"],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "2 of 2 change(s) failed");
+        // Stopping at the first failure reports the rest as not sent.
+        let mut out = Vec::new();
+        let err = headless::run(
+            &import(&["--on-failure", "stop", "--json"]),
+            &config,
+            &mut failing,
+            &journal,
+            &mut out,
+            &mut Vec::new(),
+            |_| Ok(false),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "1 of 2 change(s) failed; stopped there, 1 not sent"
+        );
+        let outcomes: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            (&outcomes[0]["status"], &outcomes[0]["error"]),
+            (&json!("failed"), &json!("Quota exceeded"))
+        );
+        assert_eq!(outcomes[1]["status"], "not sent");
+        assert!(CliArgs::parse(["import", "--on-failure", "halt"].map(String::from)).is_err());
+        let (out, _) = run(&["import", "--file", file, "--json"]).unwrap();
+        let step: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            (&step["action"], &step["record"]["ttl"], &step["status"]),
+            (&json!("create"), &json!(120), &json!("done"))
+        );
+        assert_eq!(
+            record_set(run(&["list-records", "--json"]).unwrap().0).len(),
+            original.len() + 2
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn config_flag_outranks_the_environment_and_missing_files_start_fresh() {
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|a| a.to_string()));
+        assert_eq!(args(&[]).unwrap(), CliArgs::default());
+        let work = PathBuf::from("work.toml");
+        assert_eq!(
+            args(&["--config", "work.toml"]).unwrap().config,
+            Some(work.clone())
+        );
+        assert_eq!(
+            args(&["--config=work.toml"]).unwrap().config,
+            Some(work.clone())
+        );
+        assert!(args(&["-V"]).unwrap().version);
+        assert!(args(&["--config"]).is_err());
+        assert!(args(&["--bogus"]).is_err());
+        assert!(args(&["--encrypt", "--decrypt"]).is_err());
+
+        let env_path = Some(std::ffi::OsString::from("home.json"));
+        assert_eq!(
+            ConfigSource::resolve(Some(work.clone()), env_path.clone()),
+            ConfigSource::Flag(work)
+        );
+        assert_eq!(
+            ConfigSource::resolve(None, env_path),
+            ConfigSource::Env(PathBuf::from("home.json"))
+        );
+        assert!(matches!(
+            ConfigSource::resolve(None, Some(std::ffi::OsString::new())),
+            ConfigSource::Default(_)
+        ));
+
+        let path = temp_config_path("config_flag")
+            .with_extension("d")
+            .join("work.json");
+        let source = ConfigSource::Flag(path.clone());
+        let (config_path, config) =
+            load_config(&source, |_| unreachable!(), |_| unreachable!()).unwrap();
+        assert_eq!(config_path, path);
+        assert!(config.accounts.is_empty());
+        assert!(
+            source
+                .describe(&path)
+                .contains("(from --config); not there yet")
+        );
+        let mut app = App::new(&config_path, config, MockBackend::new()).unwrap();
+        app.accounts.push(test_account());
+        app.save_accounts().unwrap();
+        assert_eq!(Config::load(&path).unwrap().accounts.len(), 1);
+        assert_eq!(
+            source.describe(&path),
+            format!("Config: {} (from --config)", path.display())
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn token_references_resolve_at_startup_and_are_saved_as_written() {
+        let dir = temp_config_path("token_refs").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let secret_file = dir.join("token");
+        fs::write(&secret_file, "from-file\n").unwrap();
+        let path = dir.join("accounts.json");
+        let account = |name: &str, token: &str| Account {
+            name: name.to_string(),
+            api_token: token.to_string(),
+            ..test_account()
+        };
+        let var = |name: &str| (name == "CF_TOKEN_DEMO").then(|| "from-env".to_string());
+
+        let mut config = Config {
+            accounts: vec![
+                account("demo", "env:CF_TOKEN_DEMO"),
+                account("files", &format!("file:{}", secret_file.display())),
+                account("plain", "literal"),
+            ],
+            ..Config::default()
+        };
+        config.resolve_token_refs(&path, var).unwrap();
+        let tokens: Vec<_> = config
+            .accounts
+            .iter()
+            .map(|a| a.api_token.as_str())
+            .collect();
+        assert_eq!(tokens, ["from-env", "from-file", "literal"]);
+
+        let mut app = App::new(&path, config, MockBackend::new()).unwrap();
+        app.save_accounts().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("env:CF_TOKEN_DEMO"), "{text}");
+        assert!(
+            !text.contains("from-env") && !text.contains("from-file"),
+            "{text}"
+        );
+
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('e'), &mut app).unwrap();
+        handle_key(KeyCode::Char('2'), &mut app).unwrap();
+        for _ in 0..4 {
+            handle_key(KeyCode::Enter, &mut app).unwrap();
+        }
+        assert_eq!(app.last_message, "Saved account demo2");
+        assert_eq!(app.accounts[0].api_token, "from-env");
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(
+            text.contains("env:CF_TOKEN_DEMO"),
+            "an untouched token keeps its reference"
+        );
+        app.accounts[0].api_token = "typed-in".to_string();
+        app.save_accounts().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(
+            text.contains("typed-in") && !text.contains("env:CF_TOKEN_DEMO"),
+            "a replaced token is saved as typed"
+        );
+
+        let mut broken = Config {
+            accounts: vec![
+                account("ci", "env:CF_TOKEN_CI"),
+                account("ok", "env:CF_TOKEN_DEMO"),
+                account("gone", "file:/nonexistent/token"),
+            ],
+            ..Config::default()
+        };
+        let err = broken
+            .resolve_token_refs(&path, var)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("2 API token reference(s)"), "{err}");
+        assert!(
+            err.contains("accounts[0] (ci): env:CF_TOKEN_CI is not set"),
+            "{err}"
+        );
+        assert!(
+            err.contains("accounts[2] (gone): file:/nonexistent/token can't be read"),
+            "{err}"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn token_commands_supply_secrets_and_failures_only_sideline_their_account() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_config_path("token_cmd").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.join(name);
+            fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            path.display().to_string()
+        };
+        let pass = script("pass", "echo \"secret-from-$1\"; echo");
+        let broken = script(
+            "broken",
+            "echo partial; echo 'gpg: decryption failed' >&2; exit 2",
+        );
+        let slow = script("slow", "sleep 5; echo late");
+        let account = |name: &str, command: String| Account {
+            name: name.to_string(),
+            api_token: String::new(),
+            token_cmd: Some(command),
+            ..test_account()
+        };
+        let mut config = Config {
+            accounts: vec![
+                account("personal", format!("{pass} personal")),
+                account("work", broken),
+                account("slow", slow),
+            ],
+            ..Config::default()
+        };
+
+        let failures = config.run_token_commands(Duration::from_millis(300));
+        assert_eq!(config.accounts[0].api_token, "secret-from-personal");
+        assert!(config.accounts[0].token_error.is_none());
+        assert_eq!(failures.len(), 2, "{failures:?}");
+        assert!(
+            failures[0].starts_with("token_cmd for work failed: exit status: 2")
+                && failures[0].ends_with("gpg: decryption failed"),
+            "{failures:?}"
+        );
+        assert!(!failures[0].contains("partial"), "stdout never shows up");
+        assert!(
+            failures[1].contains("timed out after 300ms"),
+            "{failures:?}"
+        );
+        assert_eq!(config.accounts[1].api_token, "");
+
+        let path = dir.join("accounts.json");
+        let mut app = App::new(&path, config, MockBackend::new()).unwrap();
+        assert!(!app.zones.is_empty(), "the working account loads");
+        app.save_accounts().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("personal\""), "{text}");
+        assert!(!text.contains("secret-from"), "{text}");
+        assert_eq!(
+            Config::load(&path).unwrap().accounts[0].token_cmd,
+            Some(format!("{pass} personal"))
+        );
+
+        app.focus = Focus::Accounts;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert!(app.zones.is_empty());
+        assert!(
+            app.last_message
+                .starts_with("No API token for work: token_cmd exit status: 2"),
+            "{}",
+            app.last_message
+        );
+        assert!(draw_app(&mut app).contains("work  no token"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypted_configs_round_trip_and_give_up_after_three_wrong_passphrases() {
+        let path = temp_config_path("encrypted");
+        let source = ConfigSource::Flag(path.clone());
+        let config = Config {
+            accounts: vec![Account {
+                api_token: "cf-secret-123".to_string(),
+                ..test_account()
+            }],
+            passphrase: Some("correct horse".to_string()),
+            ..Config::default()
+        };
+        config.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(
+            text.starts_with("NYXFLARE-ENCRYPTED v1 pbkdf2-sha256:"),
+            "{text}"
+        );
+        assert!(!text.contains("cf-secret-123") && !text.contains("demo"));
+        assert!(
+            Config::load(&path).is_err(),
+            "no peeking without the passphrase"
+        );
+
+        let mut prompts = Vec::new();
+        let mut answers = ["wrong", "correct horse"].into_iter();
+        let (_, loaded) = load_config(
+            &source,
+            |prompt| {
+                prompts.push(prompt.to_string());
+                Ok(answers.next().unwrap().to_string())
+            },
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(loaded.accounts, config.accounts);
+        assert!(prompts[1].starts_with("Wrong passphrase."), "{prompts:?}");
+        loaded.save(&path).unwrap();
+        assert!(
+            is_encrypted(&fs::read_to_string(&path).unwrap()),
+            "saves stay encrypted"
+        );
+
+        let mut asked = 0;
+        let err = load_config(
+            &source,
+            |_| {
+                asked += 1;
+                Ok("nope".to_string())
+            },
+            |_| unreachable!(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(asked, 3);
+        assert!(
+            err.to_string().contains("Wrong passphrase 3 times"),
+            "{err}"
+        );
+
+        let text = fs::read_to_string(&path).unwrap();
+        let (header, body) = text.split_once('\n').unwrap();
+        fs::write(&path, format!("{header}\n{}\n", &body[..20])).unwrap();
+        let mut asked = 0;
+        let err = load_config(
+            &source,
+            |_| {
+                asked += 1;
+                Ok("correct horse".to_string())
+            },
+            |_| unreachable!(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(asked, 1, "damage isn't worth retrying");
+        assert!(
+            format!("{err:#}").contains("is damaged: the encrypted body is cut short"),
+            "{err:#}"
+        );
+        fs::write(&path, format!("{header}\n%%%\n")).unwrap();
+        let err = load_config(&source, |_| Ok("x".to_string()), |_| unreachable!())
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("isn't valid base64"), "{err:#}");
+
+        let huge = header.replace(
+            &format!("pbkdf2-sha256:{}", encryption::ITERATIONS),
+            "pbkdf2-sha256:4294967295",
+        );
+        assert_ne!(huge, header);
+        fs::write(&path, format!("{huge}\n{body}")).unwrap();
+        let started = std::time::Instant::now();
+        let err = load_config(
+            &source,
+            |_| Ok("correct horse".to_string()),
+            |_| unreachable!(),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            format!("{err:#}").contains(&format!(
+                "the header asks for 4294967295 PBKDF2 rounds, more than the {} nyxflare accepts",
+                10 * encryption::ITERATIONS
+            )),
+            "{err:#}"
+        );
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "refused before deriving"
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    #[test]
+    fn encrypted_configs_need_the_feature() {
+        let path = temp_config_path("encrypted_without_feature");
+        fs::write(
+            &path,
+            "NYXFLARE-ENCRYPTED v1 pbkdf2-sha256:1000 chacha20poly1305\nAAAA\n",
+        )
+        .unwrap();
+        let err = load_config(
+            &ConfigSource::Flag(path.clone()),
+            |_| unreachable!(),
+            |_| unreachable!(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("--features encryption"), "{err}");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn strict_policy_turns_config_issues_into_startup_errors() {
+        let path = temp_config_path("strict_config");
+        fs::write(
+            &path,
+            json!({
+                "strict": true,
+                "accounts": [{
+                    "name": "prod",
+                    "api_token": "token",
+                    "email": null,
+                    "account_id": "not-an-id",
+                    "tokn": "typo"
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        let issues = config_issues(&path, &fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(
+            issues
+                .iter()
+                .any(|i| i == "accounts[0] (prod): unknown field `tokn`"),
+            "{issues:?}"
+        );
+        assert!(issues.iter().any(|i| i.contains("not-an-id")));
+        #[cfg(unix)]
+        assert!(issues.iter().any(|i| i.contains("644")));
+
+        let lenient = Policy { strict: false };
+        assert!(lenient.check_startup(&path, &issues).is_ok());
+
+        let strict = Policy::from_config(&Config::load(&path).unwrap());
+        let err = strict
+            .check_startup(&path, &issues)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("refusing to start"), "{err}");
+        assert!(err.contains("tokn"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn configs_are_saved_private_and_readable_ones_are_flagged_until_fixed() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let dir = temp_config_path("private").with_extension("d");
+        let path = dir.join("nyxflare").join("accounts.json");
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        config.save(&path).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let mut app = App::new(&path, config, MockBackend::new()).unwrap();
+        app.check_config_permissions();
+        assert!(
+            app.last_message.contains("mode 644"),
+            "{}",
+            app.last_message
+        );
+        let (warning, _) = app.status_message();
+        assert!(warning.contains("Press F to make it 600"), "{warning}");
+        assert!(draw_app(&mut app).contains("Warning: the config file is mode 644"));
+
+        handle_key(KeyCode::Char('F'), &mut app).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert!(
+            app.last_message.ends_with("is now mode 600"),
+            "{}",
+            app.last_message
+        );
+        let (help, _) = app.status_message();
+        assert!(help.starts_with("?: help"), "{help}");
+        app.check_config_permissions();
+        assert!(app.loose_config_mode.is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_keep_one_backup_that_can_replace_an_unreadable_config() {
+        let dir = temp_config_path("backup").with_extension("d");
+        let path = dir.join("accounts.json");
+        let source = ConfigSource::Flag(path.clone());
+        let read = |path: &Path| fs::read_to_string(path).unwrap();
+        let first = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        first.save(&path).unwrap();
+        assert!(!backup_path(&path).exists(), "nothing to back up yet");
+        let second = Config {
+            accounts: vec![
+                test_account(),
+                Account {
+                    name: "second".to_string(),
+                    ..test_account()
+                },
+            ],
+            ..Config::default()
+        };
+        second.save(&path).unwrap();
+        let backup = Config::parse(&path, &read(&backup_path(&path))).unwrap();
+        assert_eq!(backup.accounts, first.accounts);
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["accounts.json", "accounts.json.bak"],
+            "no temp files left"
+        );
+
+        fs::write(&path, "{ \"accounts\": [ oops").unwrap();
+        let mut prompt = String::new();
+        let err = load_config(
+            &source,
+            |_| unreachable!(),
+            |question| {
+                prompt = question.to_string();
+                Ok(false)
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(format!("{err:#}").contains("can't be read"), "{err:#}");
+        assert!(
+            prompt.contains("still reads fine (1 account(s))"),
+            "{prompt}"
+        );
+        assert!(
+            read(&path).contains("oops"),
+            "declining leaves the file alone"
+        );
+
+        let (_, restored) = load_config(&source, |_| unreachable!(), |_| Ok(true)).unwrap();
+        assert_eq!(restored.accounts, first.accounts);
+        assert_eq!(
+            Config::parse(&path, &read(&path)).unwrap().accounts,
+            first.accounts
+        );
+        assert!(read(&dir.join("accounts.json.broken")).contains("oops"));
+
+        fs::remove_file(backup_path(&path)).unwrap();
+        fs::write(&path, "{ \"accounts\": [ oops").unwrap();
+        let err = load_config(&source, |_| unreachable!(), |_| unreachable!())
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("can't be read"), "{err:#}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The error `Config::parse` gives for `text` read from `path`, with its causes.
+    fn parse_error(path: &str, text: &str) -> String {
+        format!("{:#}", Config::parse(Path::new(path), text).err().unwrap())
+    }
+
+    const CONFIG_JSON: &str = "/home/me/.config/nyxflare/accounts.json";
+
+    #[test]
+    fn config_errors_point_at_the_line_of_invalid_json() {
+        let err = parse_error(
+            CONFIG_JSON,
+            "{\n  \"accounts\": [\n    {\"name\": \"a\",}\n  ]\n}",
+        );
+        assert!(
+            err.starts_with(&format!("{CONFIG_JSON} isn't valid JSON: ")),
+            "{err}"
+        );
+        assert!(err.contains("(line 3 column"), "{err}");
+    }
+
+    #[test]
+    fn config_errors_name_the_account_missing_a_field() {
+        let err = parse_error(
+            CONFIG_JSON,
+            r#"{"accounts": [{"name": "a"}, {"api_token": "t"}]}"#,
+        );
+        assert!(err.contains(": accounts[1]: missing field `name`"), "{err}");
+    }
+
+    #[test]
+    fn config_errors_name_the_account_and_field_that_wont_read() {
+        let err = parse_error(
+            CONFIG_JSON,
+            "{\"accounts\": [\n  {\"name\": \"work\", \"auth_mode\": \"globalkey\"}\n]}",
+        );
+        assert!(
+            err.contains("accounts[0] (work), field `auth_mode`: unknown variant `globalkey`"),
+            "{err}"
+        );
+        assert!(err.contains("(line 2 column"), "{err}");
+    }
+
+    #[test]
+    fn config_errors_name_the_setting_that_wont_read() {
+        let err = parse_error(CONFIG_JSON, r#"{"accounts": [], "scroll_step": "three"}"#);
+        assert!(err.contains(": field `scroll_step`: invalid type"), "{err}");
+    }
+
+    #[test]
+    fn toml_config_errors_name_the_account_field_and_line() {
+        let err = parse_error(
+            "accounts.toml",
+            "[[accounts]]\nname = \"a\"\n\n[[accounts]]\nname = \"b\"\nemail = 5\n",
+        );
+        assert!(err.contains("accounts[1] (b), field `email`: "), "{err}");
+        assert!(err.ends_with("(line 6)"), "{err}");
+    }
+
+    #[test]
+    fn config_errors_say_when_toml_is_invalid() {
+        let err = parse_error("accounts.toml", "accounts = [\n");
+        assert!(err.starts_with("accounts.toml isn't valid TOML: "), "{err}");
+    }
+
+    #[test]
+    fn config_errors_report_global_keys_without_an_email() {
+        for email in ["", r#", "email": "  ""#] {
+            let err = parse_error(
+                CONFIG_JSON,
+                &format!(
+                    r#"{{"accounts": [{{"name": "work", "api_token": "k", "auth_mode": "global_key"{email}}}]}}"#
+                ),
+            );
+            assert!(err.contains("has 1 problem(s)"), "{err}");
+            assert!(
+                err.contains(
+                    "  - accounts[0] (work): auth_mode global_key needs the account's email"
+                ),
+                "{err}"
+            );
+        }
+        let with_email = r#"{"accounts": [{"name": "work", "api_token": "k",
+            "auth_mode": "global_key", "email": "me@example.com"}]}"#;
+        assert!(Config::parse(Path::new(CONFIG_JSON), with_email).is_ok());
+    }
+
+    #[test]
+    fn config_errors_report_duplicate_account_names() {
+        let err = parse_error(
+            CONFIG_JSON,
+            r#"{"accounts": [
+                {"name": "work", "api_token": "t"},
+                {"name": "home", "api_token": "t"},
+                {"name": "work", "api_token": "t"}
+            ]}"#,
+        );
+        assert!(
+            err.contains("  - accounts[2] (work): the name is already used by accounts[0]"),
+            "{err}"
+        );
+        let err = parse_error(
+            CONFIG_JSON,
+            r#"{"accounts": [], "profiles": {"ops": [
+                {"name": "a", "api_token": "t"}, {"name": "a", "api_token": "t"}
+            ]}}"#,
+        );
+        assert!(
+            err.contains("  - profiles.ops[1] (a): the name is already used by profiles.ops[0]"),
+            "{err}"
+        );
+
+        let across_profiles = r#"{"accounts": [{"name": "a", "api_token": "t"}],
+            "profiles": {"ops": [{"name": "a", "api_token": "t"}]}}"#;
+        assert!(
+            Config::parse(Path::new(CONFIG_JSON), across_profiles).is_ok(),
+            "one name in two profiles is fine"
+        );
+    }
+
+    #[test]
+    fn config_errors_report_default_ttls_cloudflare_would_refuse() {
+        for ttl in [0, 29, 86_401] {
+            let err = parse_error(
+                CONFIG_JSON,
+                &format!(
+                    r#"{{"accounts": [{{"name": "work", "api_token": "t", "default_ttl": {ttl}}}]}}"#
+                ),
+            );
+            assert!(
+                err.contains(&format!(
+                    "  - accounts[0] (work): default_ttl {ttl} isn't 1 (auto) or 30 to 86400 seconds"
+                )),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn config_errors_report_colors_that_dont_parse() {
+        let err = parse_error(
+            CONFIG_JSON,
+            r##"{"accounts": [{"name": "work", "api_token": "t", "color": "#ff88"}]}"##,
+        );
+        assert!(
+            err.contains(
+                r##"  - accounts[0] (work): color "#ff88" isn't a name like red or a hex like #ff8800"##
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn config_errors_report_a_profile_named_like_the_default_one() {
+        let err = parse_error(
+            CONFIG_JSON,
+            &format!(r#"{{"accounts": [], "profiles": {{"{DEFAULT_PROFILE}": []}}}}"#),
+        );
+        assert!(
+            err.contains(&format!(
+                "  - profiles.{DEFAULT_PROFILE}: the {DEFAULT_PROFILE} profile is the top-level accounts list"
+            )),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn config_errors_list_every_problem_at_once() {
+        let err = parse_error(
+            CONFIG_JSON,
+            r#"{"accounts": [
+                {"name": "work", "api_token": "k", "auth_mode": "global_key"},
+                {"name": "work", "api_token": "t"}
+            ]}"#,
+        );
+        assert!(
+            err.starts_with(&format!("{CONFIG_JSON} has 2 problem(s):\n")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn unknown_config_fields_warn_instead_of_failing() {
+        let path = temp_config_path("unknown_fields");
+        fs::write(
+            &path,
+            r#"{"accounts": [{"name": "work", "api_token": "t", "tokn": "x"}], "colour": 1}"#,
+        )
+        .unwrap();
+        let (_, config) = load_config(
+            &ConfigSource::Flag(path.clone()),
+            |_| unreachable!(),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.accounts.len(),
+            1,
+            "unknown fields don't stop the load"
+        );
+        assert_eq!(
+            config.warnings,
+            [
+                format!(
+                    "{}: accounts[0] (work): unknown field `tokn` is ignored",
+                    path.display()
+                ),
+                format!("{}: unknown field `colour` is ignored", path.display()),
+            ]
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn a_legacy_config_is_moved_to_the_default_path_once() {
+        let dir = temp_config_path("legacy").with_extension("d");
+        let legacy = dir.join("config").join("accounts.json");
+        let current = dir.join("nyxflare").join("accounts.json");
+        let source = ConfigSource::Default(current.clone());
+        let write = |path: &Path, name: &str| {
+            let config = Config {
+                accounts: vec![Account {
+                    name: name.to_string(),
+                    ..test_account()
+                }],
+                ..Config::default()
+            };
+            config.save(path).unwrap();
+        };
+        let names = |config: &Config| -> Vec<String> {
+            config.accounts.iter().map(|a| a.name.clone()).collect()
+        };
+        let migrated = dir.join("config").join("accounts.json.migrated");
+
+        write(&legacy, "legacy");
+        let mut prompt = String::new();
+        let (path, config) = load_config_at(
+            &source,
+            std::slice::from_ref(&legacy),
+            |_| unreachable!(),
+            |question| {
+                prompt = question.to_string();
+                Ok(true)
+            },
+        )
+        .unwrap();
+        assert_eq!(path, current);
+        assert_eq!(names(&config), ["legacy"]);
+        assert!(prompt.contains("Rename the old file to"), "{prompt}");
+        assert_eq!(names(&Config::load(&current).unwrap()), ["legacy"]);
+        assert!(!legacy.exists() && migrated.exists());
+        let note = config.migration.unwrap();
+        assert!(note.starts_with("Moved the config from"), "{note}");
+        assert!(note.ends_with(&format!("the old file is now {}", migrated.display())));
+
+        // Both files around with different accounts: the new location wins, untouched.
+        write(&legacy, "stale");
+        let (_, config) = load_config_at(
+            &source,
+            std::slice::from_ref(&legacy),
+            |_| unreachable!(),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(names(&config), ["legacy"]);
+        assert!(
+            config
+                .migration
+                .unwrap()
+                .ends_with("is no longer read and can be deleted")
+        );
+        assert_eq!(names(&Config::load(&legacy).unwrap()), ["stale"]);
+
+        // Declining the rename still migrates, leaving the old file where it was.
+        fs::remove_file(&current).unwrap();
+        let (_, config) = load_config_at(
+            &source,
+            std::slice::from_ref(&legacy),
+            |_| unreachable!(),
+            |_| Ok(false),
+        )
+        .unwrap();
+        assert_eq!(names(&config), ["stale"]);
+        assert_eq!(names(&Config::load(&current).unwrap()), ["stale"]);
+        assert!(legacy.exists());
+        assert!(config.migration.unwrap().ends_with("is no longer read"));
+
+        // A path picked on purpose never falls back to the legacy file.
+        let picked = dir.join("picked.json");
+        let (_, config) = load_config_at(
+            &ConfigSource::Flag(picked.clone()),
+            std::slice::from_ref(&legacy),
+            |_| unreachable!(),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert!(config.accounts.is_empty() && config.migration.is_none());
+        assert!(!picked.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn profiles_keep_separate_accounts_and_save_only_their_own() {
+        let path = temp_config_path("profiles");
+        fs::write(
+            &path,
+            json!({
+                "accounts": [{"name": "home", "api_token": "t1"}],
+                "profiles": {"work": [
+                    {"name": "corp", "api_token": "t2"},
+                    {"name": "corp-staging", "api_token": "t3"}
+                ]}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let names = |accounts: &[Account]| -> Vec<String> {
+            accounts.iter().map(|a| a.name.clone()).collect()
+        };
+        let args = ["--profile", "work"].map(String::from);
+        assert_eq!(
+            CliArgs::parse(args).unwrap().profile.as_deref(),
+            Some("work")
+        );
+
+        let broken = r#"{"profiles": {"work": [{"name": "corp", "auth_mode": 1}]}}"#;
+        let err = format!("{:#}", Config::parse(&path, broken).err().unwrap());
+        assert!(
+            err.contains(": profiles.work[0] (corp), field `auth_mode`: "),
+            "{err}"
+        );
+
+        let mut config = Config::load(&path).unwrap();
+        let err = config.use_profile("play", &path).err().unwrap().to_string();
+        assert!(err.ends_with("it has default, work"), "{err}");
+        config.use_profile("work", &path).unwrap();
+        let mut app = App::new(&path, config, MockBackend::new()).unwrap();
+        assert_eq!(names(&app.accounts), ["corp", "corp-staging"]);
+        let (_, status) = app.status_message();
+        assert!(
+            status.starts_with("Profile: work | Account: corp (1/2)"),
+            "{status}"
+        );
+
+        app.accounts[1].name = "corp-stage".to_string();
+        app.save_accounts().unwrap();
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(names(&saved.accounts), ["home"]);
+        assert_eq!(names(&saved.profiles["work"]), ["corp", "corp-stage"]);
+
+        handle_key(KeyCode::Char('o'), &mut app).unwrap();
+        let Mode::PickProfile(picker) = &app.mode else {
+            panic!("expected the profile list")
+        };
+        assert_eq!(
+            picker.profiles,
+            [("default".to_string(), 1), ("work".to_string(), 2)]
+        );
+        assert_eq!(picker.selected, 1);
+        assert!(draw_app(&mut app).contains("work  2 account(s), in use"));
+        handle_key(KeyCode::Up, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.config.profile_name(), "default");
+        assert_eq!(names(&app.accounts), ["home"]);
+        assert_eq!(app.selected_account, 0);
+        assert!(app.zone_cache.keys().all(|name| name == "home"));
+        let (_, status) = app.status_message();
+        assert!(
+            status.starts_with("Profile: default | Account: home"),
+            "{status}"
+        );
+
+        for key in [KeyCode::Char('o'), KeyCode::Char('n')]
+            .into_iter()
+            .chain("work".chars().map(KeyCode::Char))
+            .chain([KeyCode::Enter])
+        {
+            handle_key(key, &mut app).unwrap();
+        }
+        assert_eq!(app.last_message, "There's already a profile named work");
+        assert!(matches!(app.mode, Mode::PickProfile(_)));
+        for key in [KeyCode::Backspace; 4]
+            .into_iter()
+            .chain("lab".chars().map(KeyCode::Char))
+            .chain([KeyCode::Enter])
+        {
+            handle_key(key, &mut app).unwrap();
+        }
+        assert_eq!(app.config.profile_name(), "lab");
+        assert!(app.accounts.is_empty());
+        assert!(
+            matches!(app.mode, Mode::AddingAccount(_)),
+            "onboarding starts"
+        );
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(names(&saved.accounts), ["home"]);
+        assert_eq!(names(&saved.profiles["work"]), ["corp", "corp-stage"]);
+        assert!(saved.profiles["lab"].is_empty());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn new_records_start_from_the_accounts_ttl_and_proxy_defaults() {
+        let mut app = app_with_records("record_defaults", Vec::new());
+        app.focus = Focus::Records;
+        handle_key(KeyCode::Char('n'), &mut app).unwrap();
+        let Mode::RecordForm(form) = &app.mode else {
+            panic!("expected the record form")
+        };
+        assert_eq!((form.draft.ttl.as_str(), form.draft.proxied), ("300", true));
+        assert_eq!(form.account_defaults, None);
+
+        app.mode = Mode::Normal;
+        app.accounts[0].default_ttl = Some(1);
+        app.accounts[0].default_proxied = Some(false);
+        handle_key(KeyCode::Char('n'), &mut app).unwrap();
+        let Mode::RecordForm(form) = &app.mode else {
+            panic!("expected the record form")
+        };
+        assert_eq!((form.draft.ttl.as_str(), form.draft.proxied), ("1", false));
+        assert_eq!(form.account_defaults.as_deref(), Some("TTL auto, DNS only"));
+        assert!(
+            draw_app(&mut app).contains("Starts with this account's defaults: TTL auto, DNS only")
+        );
+
+        let mut form = AccountForm::for_account(0, &app.accounts[0]);
+        assert_eq!(
+            (form.default_ttl.as_str(), form.default_proxied.as_str()),
+            ("auto", "no")
+        );
+        form.default_ttl = "120".to_string();
+        form.default_proxied = "yes".to_string();
+        let account = form.build_account().unwrap();
+        assert_eq!(
+            (account.default_ttl, account.default_proxied),
+            (Some(120), Some(true))
+        );
+        form.default_ttl = "5".to_string();
+        assert!(form.build_account().is_err());
+        form.default_ttl.clear();
+        form.default_proxied = "maybe".to_string();
+        assert!(form.build_account().is_err());
+        form.default_proxied.clear();
+        let account = form.build_account().unwrap();
+        assert_eq!((account.default_ttl, account.default_proxied), (None, None));
+        let saved = serde_json::to_value(&account).unwrap();
+        assert!(saved.get("default_ttl").is_none() && saved.get("default_proxied").is_none());
+    }
+
+    #[test]
+    fn account_labels_are_drawn_in_their_color_and_make_deletes_loud() {
+        let mut app = app_with_records(
+            "account_labels",
+            vec![record("1", "api.demo.example.com", "A", "203.0.113.1")],
+        );
+        let mut form = AccountForm::for_account(0, &app.accounts[0]);
+        form.label = " PROD ".to_string();
+        form.color = "orange".to_string();
+        assert_eq!(
+            form.build_account().err(),
+            Some("Color must be a name like red or a hex like #ff8800")
+        );
+        form.color = "#ff8800".to_string();
+        let account = form.build_account().unwrap();
+        assert_eq!(
+            (account.label.as_deref(), account.color.as_deref()),
+            (Some("PROD"), Some("#ff8800"))
+        );
+        assert_eq!(AccountForm::for_account(0, &account).label.as_str(), "PROD");
+        app.accounts[0] = account;
+
+        app.focus = Focus::Records;
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("demo  PROD "));
+        assert!(screen.contains(" PROD  DNS Records"));
+
+        handle_key(KeyCode::Char('d'), &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmDelete(_)));
+        assert!(draw_app(&mut app).contains("PROD account demo"));
+
+        // Only the danger labels make confirmations loud; PROD stands in for an empty list.
+        app.accounts[0].label = Some("prod".to_string());
+        assert!(app.config.is_danger(&app.accounts[0]));
+        app.config.danger_labels = vec!["LIVE".to_string()];
+        assert!(!app.config.is_danger(&app.accounts[0]));
+        assert!(!draw_app(&mut app).contains("prod account demo"));
+        app.accounts[0].label = Some("live".to_string());
+        assert!(draw_app(&mut app).contains("live account demo"));
+
+        let config: Config = serde_json::from_value(json!({
+            "accounts": [{"name": "demo", "api_token": "t", "color": "orange"}]
+        }))
+        .unwrap();
+        assert_eq!(
+            config.problems(),
+            vec![
+                "accounts[0] (demo): color \"orange\" isn't a name like red or a hex like #ff8800"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_policy_refuses_lossy_updates() {
+        let mut commented = record("1", "api.demo.example.com", "A", "203.0.113.1");
+        commented.unmodeled = vec!["comment".to_string()];
+
+        for strict in [false, true] {
+            let mut app = app_with_records("strict_update", vec![commented.clone()]);
+            app.policy = Policy { strict };
+            let mut edited = commented.clone();
+            edited.content = "203.0.113.2".to_string();
+            edited.unmodeled.clear();
+
+            app.update_record(edited).unwrap();
+            let stored = &app.backend().records["demo-01"][0].content;
+            if strict {
+                assert_eq!(stored, "203.0.113.1");
+                assert!(app.last_message.contains("Strict mode"));
+            } else {
+                assert_eq!(stored, "203.0.113.2");
+                assert!(app.last_message.contains("comment"));
+            }
+        }
+    }
+
+    #[test]
+    fn strict_policy_screens_bulk_proxied_toggles() {
+        let mut commented = record("1", "api.demo.example.com", "A", "203.0.113.1");
+        commented.unmodeled = vec!["comment".to_string()];
+        let records = vec![
+            commented,
+            record("2", "www.demo.example.com", "A", "203.0.113.2"),
+        ];
+
+        for strict in [false, true] {
+            let mut app = app_with_records("strict_bulk_proxied", records.clone());
+            app.policy = Policy { strict };
+            app.open_bulk_update(BulkAction::SetProxied(true), records.clone());
+            let Mode::ConfirmBulkUpdate(confirm) = &app.mode else {
+                panic!("expected the bulk confirmation, got {:?}", app.mode);
+            };
+            let set = confirm.change_set();
+            let screen = draw_app(&mut app);
+            handle_key(KeyCode::Enter, &mut app).unwrap();
+            wait_until_idle(&mut app);
+            let proxied: Vec<bool> = app.backend().records["demo-01"]
+                .iter()
+                .map(|r| r.proxied)
+                .collect();
+            if strict {
+                assert_eq!(set.steps.len(), 1);
+                assert!(set.summary().contains("1 refused in strict mode"));
+                assert_eq!(proxied, [false, true]);
+            } else {
+                assert_eq!(set.steps.len(), 2);
+                assert!(
+                    screen.contains("comment on api.demo.example.com were not preserved"),
+                    "{screen}"
+                );
+                assert_eq!(proxied, [true, true]);
+            }
+        }
+    }
+
+    #[test]
+    fn strict_policy_screens_bulk_ttl_changes() {
+        let mut commented = record("1", "api.demo.example.com", "A", "203.0.113.1");
+        commented.unmodeled = vec!["comment".to_string(), "tags".to_string()];
+        let records = vec![
+            commented,
+            record("2", "www.demo.example.com", "A", "203.0.113.2"),
+        ];
+        let ttls = |app: &App<MockBackend>| -> Vec<u32> {
+            app.backend().records["demo-01"]
+                .iter()
+                .map(|r| r.ttl)
+                .collect()
+        };
+        let set_ttl = |app: &mut App<MockBackend>, ttl: &str| {
+            app.focus = Focus::Records;
+            handle_normal_key(KeyCode::Char('t'), app).unwrap();
+            handle_normal_key(KeyCode::Char('T'), app).unwrap();
+            for c in ttl.chars() {
+                handle_key(KeyCode::Char(c), app).unwrap();
+            }
+            handle_key(KeyCode::Enter, app).unwrap();
+        };
+
+        let mut app = app_with_records("lenient_bulk_ttl", records.clone());
+        set_ttl(&mut app, "600");
+        assert!(
+            draw_app(&mut app).contains("comment, tags on api.demo.example.com were not preserved")
+        );
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(ttls(&app), [600, 600]);
+
+        let mut app = app_with_records("strict_bulk_ttl", records.clone());
+        app.policy = Policy { strict: true };
+        set_ttl(&mut app, "600");
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        wait_until_idle(&mut app);
+        assert_eq!(ttls(&app), [300, 600], "the commented record is left alone");
+
+        let mut app = app_with_records("strict_bulk_ttl_refused", records[..1].to_vec());
+        app.policy = Policy { strict: true };
+        set_ttl(&mut app, "600");
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(
+            app.last_message.contains("1 refused in strict mode"),
+            "{}",
+            app.last_message
+        );
+        assert_eq!(ttls(&app), [300]);
+    }
+
+    #[test]
+    fn strict_policy_requires_typed_delete_confirmation() {
+        let records = vec![record("1", "api.demo.example.com", "A", "203.0.113.1")];
+
+        let mut app = app_with_records("lenient_delete", records.clone());
+        app.ask_delete_record();
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(app.backend().records["demo-01"].is_empty());
+
+        let mut app = app_with_records("strict_delete", records);
+        app.policy = Policy { strict: true };
+        app.ask_delete_record();
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+        assert!(matches!(app.mode, Mode::ConfirmDelete(_)));
+
+        for c in "api.demo.example.com".chars() {
+            let quit = handle_confirm_delete_key(KeyCode::Char(c), &mut app).unwrap();
+            assert!(!quit, "typing the name must not quit");
+        }
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(app.backend().records["demo-01"].is_empty());
+    }
+
+    #[test]
+    fn p_toggles_proxied_on_the_highlighted_record() {
+        let mut apex = record("1", "demo.example.com", "A", "203.0.113.1");
+        apex.proxied = true;
+        let records = vec![
+            apex,
+            record("2", "api.demo.example.com", "A", "203.0.113.2"),
+            record("3", "demo.example.com", "TXT", "v=spf1 -all"),
+        ];
+        let mut app = app_with_records("toggle_proxied", records);
+        app.focus = Focus::Records;
+        let proxied = |app: &App<MockBackend>, id: &str| {
+            app.backend().records["demo-01"]
+                .iter()
+                .find(|r| r.id == id)
+                .unwrap()
+                .proxied
+        };
+
+        app.selected_record = 1;
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert!(proxied(&app, "2"));
+        assert!(app.records[1].proxied);
+        assert_eq!(
+            app.last_message,
+            "Updated api.demo.example.com: DNS only → proxied"
+        );
+
+        app.selected_record = 2;
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "TXT records can't be proxied; only A, AAAA and CNAME can"
+        );
+
+        app.selected_record = 0;
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert!(proxied(&app, "1"), "the apex waits for a second p");
+        assert!(app.last_message.contains("press p again"));
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('k'), &mut app).unwrap();
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert!(proxied(&app, "1"), "another key in between starts over");
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert!(!proxied(&app, "1"));
+
+        app.backend().failures =
+            vec![FailureRule::parse("update_record:next:Zone is locked").unwrap()];
+        app.selected_record = 1;
+        let before = app.records.clone();
+        handle_normal_key(KeyCode::Char('p'), &mut app).unwrap();
+        assert_eq!(app.records, before);
+        assert!(matches!(app.mode, Mode::Error(_)));
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal, "no form to go back to");
+    }
+
+    #[test]
+    fn t_without_a_selection_edits_the_highlighted_ttl() {
+        let records = vec![
+            record("1", "api.demo.example.com", "A", "203.0.113.1"),
+            record("2", "www.demo.example.com", "CNAME", "api.demo.example.com"),
+        ];
+        let mut app = app_with_records("quick_ttl", records);
+        app.focus = Focus::Records;
+        app.selected_record = 1;
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        let Mode::QuickEdit(edit) = &app.mode else {
+            panic!("expected the quick edit prompt, got {:?}", app.mode);
+        };
+        assert_eq!(edit.text, "300", "prefilled with the current TTL");
+        let screen = draw_app(&mut app);
+        assert!(
+            screen.contains("TTL for www.demo.example.com (now 300; auto or seconds)"),
+            "{screen}"
+        );
+
+        for _ in 0..3 {
+            handle_key(KeyCode::Backspace, &mut app).unwrap();
+        }
+        for c in "10".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "TTL must be auto or a number of seconds from 30 to 86400"
+        );
+        assert!(
+            matches!(app.mode, Mode::QuickEdit(_)),
+            "a bad TTL keeps the prompt"
+        );
+
+        for _ in 0..2 {
+            handle_key(KeyCode::Backspace, &mut app).unwrap();
+        }
+        for c in "auto".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.last_message,
+            "Updated www.demo.example.com: TTL 300 → auto"
+        );
+        let stored = app.backend().records["demo-01"][1].clone();
+        assert_eq!(
+            (stored.ttl, stored.content.as_str()),
+            (1, "api.demo.example.com")
+        );
+
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "TTL left unchanged");
+
+        app.selected_ids.insert("1".to_string());
+        handle_normal_key(KeyCode::Char('T'), &mut app).unwrap();
+        assert!(
+            matches!(app.mode, Mode::BulkTtl(_)),
+            "a selection still goes bulk"
+        );
+    }
+
+    #[test]
+    fn editor_document_round_trips_and_keeps_edits_across_errors() {
+        let original = record("1", "demo.example.com", "TXT", "\"v=spf1 -all\"");
+        let text = editor_document(&original, true);
+        assert!(text.contains("content = \"v=spf1 -all\"\n"), "{text}");
+        let parsed = parse_editor_document(&text, &original).unwrap().unwrap();
+        assert_eq!(
+            RecordDraft::from_record(&parsed),
+            RecordDraft::from_record(&original)
+        );
+        assert_eq!(
+            parse_editor_document("# all gone\n\n", &original).unwrap(),
+            None
+        );
+        let err = parse_editor_document("colour = blue", &original).unwrap_err();
+        assert!(err.to_string().contains("unknown field `colour`"), "{err}");
+
+        let config_path = temp_config_path("editor").join("accounts.json");
+        let path = editor_path(&config_path).unwrap();
+        assert_eq!(
+            path.parent(),
+            config_path.parent().map(|dir| dir.join("edits")).as_deref()
+        );
+        let mut rounds = Vec::new();
+        let edited = edit_until_valid(&path, &original, true, |path| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+                assert_eq!(mode(path), 0o600);
+                assert_eq!(mode(path.parent().unwrap()), 0o700);
+            }
+            let text = fs::read_to_string(path).unwrap();
+            let text = match rounds.len() {
+                0 => text
+                    .replace("ttl = 300", "ttl = soon")
+                    .replace("-all", "include:_spf.example.net -all"),
+                _ => text.replace("ttl = soon", "ttl = 600"),
+            };
+            rounds.push(text.clone());
+            fs::write(path, text).unwrap();
+            Ok(())
+        })
+        .unwrap()
+        .unwrap();
+        assert!(
+            edit_until_valid(&path, &original, true, |_| unreachable!()).is_err(),
+            "a file already there isn't written through"
+        );
+        assert!(editor_path(&config_path).is_ok_and(|again| !again.exists()));
+        let _ = fs::remove_dir_all(config_path.parent().unwrap());
+
+        assert_eq!(rounds.len(), 2);
+        assert!(
+            rounds[1].starts_with("# error: TTL must be a number\n"),
+            "{}",
+            rounds[1]
+        );
+        assert!(
+            rounds[1].contains("include:_spf.example.net"),
+            "edits survive the error"
+        );
+        assert_eq!(edited.ttl, 600);
+        assert_eq!(edited.content, "\"v=spf1 include:_spf.example.net -all\"");
+
+        let mut app = app_with_records("external_edit", vec![original.clone()]);
+        app.focus = Focus::Records;
+        handle_normal_key(KeyCode::Char('E'), &mut app).unwrap();
+        assert_eq!(app.external_edit.as_ref(), Some(&original));
+        app.apply_external_edit(&original, Ok(Some(original.clone())))
+            .unwrap();
+        assert_eq!(app.last_message, "No changes to demo.example.com");
+        app.apply_external_edit(&original, Err(anyhow!("vi exited with exit status: 1")))
+            .unwrap();
+        assert_eq!(
+            app.last_message,
+            "Editing demo.example.com failed, nothing was saved: vi exited with exit status: 1"
+        );
+        app.apply_external_edit(&original, Ok(Some(edited)))
+            .unwrap();
+        let stored = app.backend().records["demo-01"][0].clone();
+        assert_eq!((stored.ttl, stored.content.contains("_spf")), (600, true));
+    }
+
+    #[test]
+    fn deletion_danger_flags_apex_wildcard_ns_and_mx() {
+        let zone = "demo.example.com";
+        let danger = |name: &str, record_type: &str| {
+            deletion_danger(&record("1", name, record_type, "x"), zone)
+        };
+        assert_eq!(danger("demo.example.com", "A"), Some("the zone apex"));
+        assert_eq!(danger("Demo.Example.com.", "TXT"), Some("the zone apex"));
+        assert_eq!(danger("*.demo.example.com", "CNAME"), Some("a wildcard"));
+        assert_eq!(
+            danger("eu.demo.example.com", "NS"),
+            Some("a name server delegation")
+        );
+        assert_eq!(danger("demo.example.com", "MX"), Some("the zone apex"));
+        assert_eq!(
+            danger("mail.demo.example.com", "MX"),
+            Some("a mail exchanger")
+        );
+        assert_eq!(danger("api.demo.example.com", "A"), None);
+        assert_eq!(danger("www.demo.example.com", "CNAME"), None);
+        assert_eq!(danger("sub.*.demo.example.com", "A"), None);
+    }
+
+    #[test]
+    fn dangerous_deletes_take_the_name_or_the_code() {
+        let records = vec![
+            record("1", "demo.example.com", "A", "203.0.113.1"),
+            record("2", "*.demo.example.com", "A", "203.0.113.1"),
+        ];
+        let mut app = app_with_records("dangerous_delete", records);
+        app.ask_delete_record();
+        let Mode::ConfirmDelete(confirm) = &app.mode else {
+            panic!("expected the delete confirmation, got {:?}", app.mode);
+        };
+        let code = confirm.code.clone();
+        assert_eq!(code.len(), 4);
+        assert!(confirm.require_typed);
+
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.backend().records["demo-01"].len(), 2);
+        for c in code.to_lowercase().chars().take(2) {
+            handle_confirm_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("This record is the zone apex."), "{screen}");
+        assert!(
+            screen.contains(&format!("Type demo.example.com or {code} to confirm:")),
+            "{screen}"
+        );
+        assert!(
+            screen.contains(&format!("> {}", &code.to_lowercase()[..2])),
+            "{screen}"
+        );
+
+        for c in code.to_lowercase().chars().skip(2) {
+            handle_confirm_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+
+        app.ask_delete_record();
+        for c in "*.demo.example.com".chars() {
+            handle_confirm_delete_key(KeyCode::Char(c), &mut app).unwrap();
+        }
+        handle_confirm_delete_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(app.backend().records["demo-01"].is_empty());
+    }
+
+    #[test]
+    fn cf_response_parses_result_info_when_present() {
+        let with_info: CfResponse<CfZone> = serde_json::from_value(json!({
+            "success": true,
+            "errors": [],
+            "result": [{"id": "z1", "name": "a.com"}],
+            "result_info": {"page": 1, "per_page": 200, "count": 200, "total_count": 1731, "total_pages": 9}
+        }))
+        .unwrap();
+        assert_eq!(
+            with_info.result_info,
+            Some(CfResultInfo {
+                page: Some(1),
+                per_page: Some(200),
+                count: Some(200),
+                total_count: Some(1731),
+                total_pages: Some(9),
+            })
+        );
+
+        let without_info: CfResponse<CfZone> = serde_json::from_value(json!({
+            "success": true,
+            "errors": [],
+            "result": []
+        }))
+        .unwrap();
+        assert_eq!(without_info.result_info, None);
+    }
+
+    #[test]
+    fn status_message_reports_server_side_totals() {
+        let records = (1..=3)
+            .map(|i| record(&i.to_string(), &format!("item-{i}.demo"), "A", "127.0.0.1"))
+            .collect();
+        let mut app = app_with_records("totals", records);
+
+        app.record_total_count = Some(1731);
+        let (_, details) = app.status_message();
+        assert!(
+            details.contains("showing 3 of 1,731"),
+            "status did not report truncation: {details}"
+        );
+
+        app.record_total_count = Some(3);
+        let (_, details) = app.status_message();
+        assert!(!details.contains("showing"), "{details}");
+    }
+
+    fn cf_account() -> Account {
+        Account {
+            name: "cf".to_string(),
+            api_token: "cf-token".to_string(),
+            email: Some("user@example.com".to_string()),
+            account_id: Some("acc-1".to_string()),
+            account_name: None,
+            auth_mode: AuthMode::Token,
+            base_url: None,
+            provider: Provider::Cloudflare,
+            aws_access_key_id: None,
+            zone_dir: None,
+            dns_server: None,
+            tsig_key_name: None,
+            tsig_algorithm: None,
+            zones: Vec::new(),
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl: None,
+            default_proxied: None,
+            label: None,
+            color: None,
+        }
+    }
+
+    fn cf_zone() -> Zone {
+        Zone {
+            id: "zone-1".to_string(),
+            name: "example.com".to_string(),
+            ..Zone::default()
+        }
+    }
+
+    #[test]
+    fn cloudflare_list_zones_parses_success() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/zones")
+            .match_header("authorization", "Bearer cf-token")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("per_page".into(), "200".into()),
+                mockito::Matcher::UrlEncoded("account.id".into(), "acc-1".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [
+                        {"id": "zone-1", "name": "example.com"},
+                        {"id": "zone-2", "name": "demo.net"}
+                    ]
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let zones = backend.list_zones(&cf_account()).unwrap().items;
+        assert_eq!(zones.len(), 2);
+        assert_eq!(zones[0].name, "example.com");
+    }
+
+    #[test]
+    fn cloudflare_zones_carry_status_plan_and_nameservers() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [{
+                        "id": "zone-1",
+                        "name": "example.com",
+                        "status": "pending",
+                        "name_servers": ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"],
+                        "original_name_servers": null,
+                        "plan": {"id": "free", "name": "Free Website"}
+                    }]
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let zone = backend.list_zones(&cf_account()).unwrap().items.remove(0);
+
+        assert!(zone.is_pending());
+        assert_eq!(zone.plan.as_deref(), Some("Free Website"));
+        assert_eq!(
+            zone.name_servers,
+            ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]
+        );
+        assert!(zone.original_name_servers.is_empty());
+    }
+
+    #[test]
+    fn cloudflare_errors_open_with_status_message_and_body() {
+        let mut server = mockito::Server::new();
+        let body = json!({
+            "success": false,
+            "errors": [{"code": 9109, "message": "Unauthorized to access requested resource"}],
+            "messages": [],
+            "result": null,
+            "padding": "x".repeat(300),
+        })
+        .to_string();
+        let _m = server
+            .mock("DELETE", "/zones/zone-1")
+            .with_status(403)
+            .with_body(&body)
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let err = backend.delete_zone(&cf_account(), &cf_zone()).unwrap_err();
+        let details = ErrorDetails::new(None, &err);
+
+        assert_eq!(details.operation, "Delete zone");
+        assert_eq!(details.status.as_deref(), Some("403 Forbidden"));
+        assert_eq!(details.message, "Unauthorized to access requested resource");
+        assert_eq!(details.body.as_deref(), Some(body.as_str()));
+        assert!(details.full_text().contains(&"x".repeat(300)));
+        assert!(
+            format!("{err:#}").ends_with("..."),
+            "the status line keeps the truncated body"
+        );
+
+        let mut app = app_with_records("error_modal", Vec::new());
+        app.config.clipboard = ClipboardMode::Osc52;
+        app.report_error(err);
+        assert!(matches!(app.mode, Mode::Error(_)));
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("403 Forbidden"), "{screen}");
+        handle_key(KeyCode::Char('y'), &mut app).unwrap();
+        assert_eq!(app.last_message, "Copied error details (OSC 52)");
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn cloudflare_list_zones_follows_pagination() {
+        let mut server = mockito::Server::new();
+        let page = |n: u32, zones: serde_json::Value| {
+            json!({
+                "success": true,
+                "errors": [],
+                "result": zones,
+                "result_info": {"page": n, "per_page": 2, "count": 2, "total_count": 3, "total_pages": 2}
+            })
+            .to_string()
+        };
+        let _p1 = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_status(200)
+            .with_body(page(
+                1,
+                json!([{"id": "z1", "name": "a.com"}, {"id": "z2", "name": "b.com"}]),
+            ))
+            .expect(1)
+            .create();
+        let _p2 = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_status(200)
+            .with_body(page(2, json!([{"id": "z3", "name": "c.com"}])))
+            .expect(1)
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let listing = backend.list_zones(&cf_account()).unwrap();
+        let names: Vec<&str> = listing.items.iter().map(|z| z.name.as_str()).collect();
+        assert_eq!(names, vec!["a.com", "b.com", "c.com"]);
+        assert_eq!(listing.pages, 2);
+        assert_eq!(listing.pages_suffix(), " (2 pages)");
+        _p1.assert();
+        _p2.assert();
+    }
+
+    #[test]
+    fn cloudflare_list_records_parses_success() {
+        let mut server = mockito::Server::new();
+        let zone = cf_zone();
+        let path = format!("/zones/{}/dns_records", zone.id);
+        let _m = server
+            .mock("GET", path.as_str())
+            .match_header("authorization", "Bearer cf-token")
+            .match_query(mockito::Matcher::UrlEncoded("per_page".into(), "200".into()))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [
+                        {"id": "rec-1", "name": "api.example.com", "type": "A", "content": "1.1.1.1", "ttl": 120, "proxied": true},
+                        {"id": "rec-2", "name": "edge.example.com", "type": "CNAME", "content": "api.example.com", "ttl": 300, "proxied": false}
+                    ]
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let records = backend
+            .list_records_page(&cf_account(), &zone, 1, None)
+            .unwrap()
+            .items;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].record_type, "A");
+        assert!(records[0].proxied);
+        assert_eq!(records[1].record_type, "CNAME");
+    }
+
+    #[test]
+    fn cloudflare_list_records_follows_pagination() {
+        let mut server = mockito::Server::new();
+        let zone = cf_zone();
+        let path = format!("/zones/{}/dns_records", zone.id);
+        let page = |n: u32| {
+            json!({
+                "success": true,
+                "errors": [],
+                "result": [{"id": format!("rec-{n}"), "name": format!("r{n}.example.com"), "type": "A", "content": "1.1.1.1"}],
+                "result_info": {"page": n, "per_page": 1, "count": 1, "total_count": 2, "total_pages": 2}
+            })
+            .to_string()
+        };
+        let _p1 = server
+            .mock("GET", path.as_str())
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_body(page(1))
+            .create();
+        let _p2 = server
+            .mock("GET", path.as_str())
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(page(2))
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let listing =
+            collect_pages(|page| backend.list_records_page(&cf_account(), &zone, page, None))
+                .unwrap();
+        let ids: Vec<&str> = listing.items.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["rec-1", "rec-2"]);
+        assert_eq!(listing.pages, 2);
+    }
+
+    #[test]
+    fn cloudflare_create_update_delete_roundtrip() {
+        let mut server = mockito::Server::new();
+        let zone = cf_zone();
+
+        let create_path = format!("/zones/{}/dns_records", zone.id);
+        let _create = server
+            .mock("POST", create_path.as_str())
+            .match_header("authorization", "Bearer cf-token")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "name": "new.example.com",
+                "type": "A",
+                "content": "2.2.2.2",
+                "ttl": 300,
+                "proxied": false
+            })))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "id": "rec-new",
+                        "name": "new.example.com",
+                        "type": "A",
+                        "content": "2.2.2.2",
+                        "ttl": 300,
+                        "proxied": false
+                    }
+                })
+                .to_string(),
+            )
+            .create();
+
+        let update_path = format!("/zones/{}/dns_records/rec-new", zone.id);
+        let _update = server
+            .mock("PUT", update_path.as_str())
+            .match_body(mockito::Matcher::PartialJson(json!({"content": "3.3.3.3"})))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "id": "rec-new",
+                        "name": "new.example.com",
+                        "type": "A",
+                        "content": "3.3.3.3",
+                        "ttl": 300,
+                        "proxied": false
+                    }
+                })
+                .to_string(),
+            )
+            .create();
+
+        let delete_path = format!("/zones/{}/dns_records/rec-new", zone.id);
+        let _delete = server
+            .mock("DELETE", delete_path.as_str())
+            .with_status(200)
+            .with_body(json!({"success": true, "errors": []}).to_string())
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let mut created = backend
+            .create_record(
+                &cf_account(),
+                &zone,
+                record("rec-new", "new.example.com", "A", "2.2.2.2"),
+            )
+            .unwrap();
+        assert_eq!(created.id, "rec-new");
+
+        created.content = "3.3.3.3".to_string();
+        let updated = backend
+            .update_record(&cf_account(), &zone, created)
+            .unwrap();
+        assert_eq!(updated.content, "3.3.3.3");
+
+        backend
+            .delete_record(&cf_account(), &zone, "rec-new")
+            .unwrap();
+    }
+
+    #[test]
+    fn cloudflare_gets_a_single_record_with_its_modified_time() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/zones/zone-1/dns_records/rec-1")
+            .match_header("authorization", "Bearer cf-token")
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "id": "rec-1",
+                        "name": "www.example.com",
+                        "type": "A",
+                        "content": "192.0.2.1",
+                        "ttl": 300,
+                        "proxied": false,
+                        "modified_on": "2024-05-01T12:00:00.000000Z"
+                    }
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let record = backend
+            .get_record(&cf_account(), &cf_zone(), "rec-1")
+            .unwrap();
+        assert_eq!(record.content, "192.0.2.1");
+        assert_eq!(
+            record.modified_on.as_deref(),
+            Some("2024-05-01T12:00:00.000000Z")
+        );
     }
 
-    let paragraph = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(
-        if form.is_edit {
-            "Edit record"
-        } else {
-            "Create record"
-        },
-    ));
+    #[test]
+    fn cloudflare_audit_log_filters_by_record() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/accounts/acc-1/audit_logs")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "zone.name".into(),
+                "example.com".into(),
+            ))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [
+                        {
+                            "id": "log-1",
+                            "when": "2024-05-01T12:00:00Z",
+                            "action": {"type": "rec_set"},
+                            "actor": {"email": "ops@example.com", "type": "user"},
+                            "resource": {"id": "rec-1", "type": "DNS_record"},
+                            "oldValueJson": {"content": "1.1.1.1", "ttl": 300},
+                            "newValueJson": {"content": "2.2.2.2", "ttl": 300}
+                        },
+                        {
+                            "id": "log-2",
+                            "when": "2024-05-01T12:05:00Z",
+                            "action": {"type": "rec_del"},
+                            "resource": {"id": "rec-other"}
+                        }
+                    ]
+                })
+                .to_string(),
+            )
+            .create();
 
-    frame.render_widget(Clear, area);
-    frame.render_widget(paragraph, area);
-}
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let entries = backend
+            .record_audit_log(&cf_account(), &cf_zone(), "rec-1")
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, ChangeAction::Update);
+        assert_eq!(entries[0].actor.as_deref(), Some("ops@example.com"));
+        assert_eq!(
+            entries[0].changes,
+            vec![FieldChange {
+                field: "content",
+                before: Some("1.1.1.1".to_string()),
+                after: Some("2.2.2.2".to_string()),
+            }]
+        );
+    }
 
-fn draw_confirm_delete(frame: &mut Frame<'_>, confirm: &ConfirmDelete) {
-    let area = centered_rect(60, 30, frame.size());
-    let lines = vec![
-        Line::from(Span::styled(
-            "Confirm delete",
-            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-        )),
-        Line::from(format!("Delete record {}?", confirm.record_name)),
-        Line::from("Enter to confirm • Esc to cancel"),
-    ];
-    let paragraph = Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Delete record"),
-    );
-    frame.render_widget(Clear, area);
-    frame.render_widget(paragraph, area);
-}
+    #[test]
+    fn cloudflare_uses_account_base_url_override() {
+        let mut default_server = mockito::Server::new();
+        let mut staging_server = mockito::Server::new();
+        let zones_body = |name: &str| {
+            json!({"success": true, "errors": [], "result": [{"id": "z1", "name": name}]})
+                .to_string()
+        };
+        let default_zones = default_server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_body(zones_body("prod.example.com"))
+            .expect(1)
+            .create();
+        let staging_zones = staging_server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_body(zones_body("staging.example.com"))
+            .expect(1)
+            .create();
 
-fn draw_search_overlay(frame: &mut Frame<'_>, text: &str) {
-    let area = centered_rect(60, 20, frame.size());
-    let lines = vec![
-        Line::from("Filter records (name/content/type)"),
-        Line::from(vec![
-            Span::styled("/ ", Style::default().fg(Color::Yellow)),
-            Span::raw(text),
-        ]),
-        Line::from("Enter to apply • Esc to cancel"),
-    ];
-    let paragraph =
-        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Search"));
-    frame.render_widget(Clear, area);
-    frame.render_widget(paragraph, area);
-}
+        let mut backend = CloudflareBackend::new_with_base(default_server.url()).unwrap();
+        let prod = cf_account();
+        let staging = Account {
+            name: "staging".to_string(),
+            base_url: Some(format!("{}/", staging_server.url())),
+            ..cf_account()
+        };
 
-fn form_line(label: &str, value: &str, active: bool, required: bool) -> Line<'static> {
-    let mut display = if value.is_empty() {
-        if required {
-            "<required>".to_string()
-        } else {
-            "<optional>".to_string()
-        }
-    } else {
-        value.to_string()
-    };
+        let prod_zones = backend.list_zones(&prod).unwrap().items;
+        let staging_zones_listed = backend.list_zones(&staging).unwrap().items;
 
-    if !required && value.is_empty() {
-        display.push_str(" (press Enter to skip)");
+        assert_eq!(prod_zones[0].name, "prod.example.com");
+        assert_eq!(staging_zones_listed[0].name, "staging.example.com");
+        default_zones.assert();
+        staging_zones.assert();
     }
 
-    let style = if active {
-        Style::default()
-            .fg(Color::Yellow)
-            .add_modifier(Modifier::BOLD)
-    } else {
-        Style::default()
-    };
+    #[test]
+    fn account_form_validates_base_url() {
+        let mut form = AccountForm {
+            name: "staging".to_string(),
+            api_token: "token".to_string(),
+            base_url: "staging.internal".to_string(),
+            ..AccountForm::default()
+        };
+        assert!(form.build_account().is_err());
 
-    Line::from(vec![
-        Span::styled(format!("{label}: "), style),
-        Span::styled(display, style),
-    ])
-}
+        form.base_url = " https://staging.internal/client/v4/ ".to_string();
+        let account = form.build_account().unwrap();
+        assert_eq!(
+            account.base_url.as_deref(),
+            Some("https://staging.internal/client/v4")
+        );
 
-fn centered_rect(
-    percent_x: u16,
-    percent_y: u16,
-    r: ratatui::prelude::Rect,
-) -> ratatui::prelude::Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_y) / 2),
-                Constraint::Percentage(percent_y),
-                Constraint::Percentage((100 - percent_y) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(r);
+        form.base_url.clear();
+        assert_eq!(form.build_account().unwrap().base_url, None);
+    }
 
-    let vertical = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_x) / 2),
-                Constraint::Percentage(percent_x),
-                Constraint::Percentage((100 - percent_x) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(popup_layout[1]);
+    #[test]
+    fn cloudflare_verifies_tokens() {
+        let mut server = mockito::Server::new();
+        let verify = server
+            .mock("GET", "/user/tokens/verify")
+            .match_header("authorization", "Bearer cf-token")
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "id": "tok-1",
+                        "status": "active",
+                        "expires_on": "2027-01-01T00:00:00Z"
+                    }
+                })
+                .to_string(),
+            )
+            .create();
 
-    vertical[1]
-}
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let token = backend.verify_account(&cf_account()).unwrap();
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct Account {
-    name: String,
-    api_token: String,
-    email: Option<String>,
-    #[serde(default)]
-    account_id: Option<String>,
-    #[serde(default)]
-    auth_mode: AuthMode,
-}
+        assert!(token.is_active());
+        assert_eq!(
+            token.describe(),
+            "token active (expires 2027-01-01 00:00:00 UTC)"
+        );
+        verify.assert();
+    }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-enum AuthMode {
-    #[default]
-    Token,
-    GlobalKey,
-}
+    #[test]
+    fn cloudflare_verify_reports_invalid_tokens() {
+        let mut server = mockito::Server::new();
+        let _verify = server
+            .mock("GET", "/user/tokens/verify")
+            .with_status(401)
+            .with_body(
+                json!({
+                    "success": false,
+                    "errors": [{"code": 1000, "message": "Invalid API Token"}],
+                    "result": null
+                })
+                .to_string(),
+            )
+            .create();
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct Zone {
-    id: String,
-    name: String,
-}
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let err = backend
+            .verify_account(&cf_account())
+            .unwrap_err()
+            .to_string();
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct DnsRecord {
-    id: String,
-    name: String,
-    record_type: String,
-    content: String,
-    ttl: u32,
-    proxied: bool,
-}
+        assert!(
+            err.contains("401") && err.contains("Invalid API Token"),
+            "{err}"
+        );
+    }
 
-#[derive(Default, Serialize, Deserialize)]
-struct Config {
-    accounts: Vec<Account>,
-}
+    #[test]
+    fn zone_export_writes_cloudflare_text_verbatim() {
+        let mut server = mockito::Server::new();
+        let _zones = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                json!({"success": true, "errors": [], "result": [{"id": "zone-1", "name": "example.com"}]})
+                    .to_string(),
+            )
+            .create();
+        let _records = server
+            .mock("GET", "/zones/zone-1/dns_records")
+            .match_query(mockito::Matcher::Any)
+            .with_body(json!({"success": true, "errors": [], "result": []}).to_string())
+            .create();
+        let bind = ";; Exported by Cloudflare\n$ORIGIN example.com.\nwww.example.com.\t300\tIN\tA\t192.0.2.1\n";
+        let export = server
+            .mock("GET", "/zones/zone-1/dns_records/export")
+            .with_header("content-type", "text/plain")
+            .with_body(bind)
+            .create();
 
-impl Config {
-    fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        let contents = match fs::read_to_string(path) {
-            Ok(text) => text,
-            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
-            Err(err) => return Err(err.into()),
+        let export_dir = temp_config_path("export_dir");
+        let config = Config {
+            accounts: vec![cf_account()],
+            export_dir: Some(export_dir.clone()),
+            ..Config::default()
         };
+        let backend = Backend::Cloudflare(CloudflareBackend::new_with_base(server.url()).unwrap());
+        let mut app = App::new(temp_config_path("export"), config, backend).unwrap();
+        app.focus = Focus::Zones;
 
-        Ok(serde_json::from_str(&contents)?)
-    }
+        handle_normal_key(KeyCode::Char('x'), &mut app).unwrap();
 
-    fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let text = serde_json::to_string_pretty(self)?;
-        fs::write(path, text)?;
-        Ok(())
+        export.assert();
+        let written: Vec<_> = fs::read_dir(&export_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(written.len(), 1);
+        let file_name = written[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert!(
+            file_name.starts_with("example.com.zone-") && file_name.ends_with(".txt"),
+            "{file_name}"
+        );
+        assert_eq!(fs::read_to_string(&written[0]).unwrap(), bind);
+        assert!(app.last_message.starts_with("Exported example.com to"));
+        fs::remove_dir_all(export_dir).unwrap();
     }
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Focus {
-    Accounts,
-    Zones,
-    Records,
-}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum Mode {
-    Normal,
-    AddingAccount(AccountForm),
-    RecordForm(RecordForm),
-    ConfirmDelete(ConfirmDelete),
-    Searching(String),
-}
+    #[test]
+    fn zone_export_errors_go_to_the_status_line() {
+        let mut app = app_with_records("export_error", vec![]);
+        // A regular file where the directory should be makes create_dir_all fail.
+        let blocker = temp_config_path("export_blocker");
+        fs::write(&blocker, "not a directory").unwrap();
+        app.config.export_dir = Some(blocker.join("exports"));
+        app.focus = Focus::Zones;
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
-struct AccountForm {
-    name: String,
-    api_token: String,
-    email: String,
-    account_id: String,
-    field_index: usize,
-}
+        let quit = handle_normal_key(KeyCode::Char('x'), &mut app).unwrap();
 
-impl AccountForm {
-    fn active_value_mut(&mut self) -> &mut String {
-        match self.field_index {
-            0 => &mut self.name,
-            1 => &mut self.api_token,
-            2 => &mut self.email,
-            _ => &mut self.account_id,
-        }
+        assert!(!quit);
+        assert!(
+            app.last_message
+                .starts_with("Export of demo.example.com failed: Creating"),
+            "{}",
+            app.last_message
+        );
+        fs::remove_file(blocker).unwrap();
     }
 
-    fn next_field(&mut self) {
-        self.field_index = (self.field_index + 1).min(3);
-    }
+    const SAMPLE_ZONE: &str = "$ORIGIN demo.example.com.
+$TTL 600
+@ IN SOA ns1.example.net. hostmaster.example.com. (
+        2024010101 ; serial
+        3600 900 604800 300 )
+@       IN NS   ns1.example.net.
+@       300 IN A 192.0.2.10 ; cf_tags=cf-proxied:true
+        IN AAAA 2001:db8::10
+www     IN CNAME @
+mail    IN MX   10 mx1
+_spf    IN TXT  \"v=spf1 include:_spf.example.net ~all\"
+";
 
-    fn previous_field(&mut self) {
-        if self.field_index == 0 {
-            self.field_index = 0;
-        } else {
-            self.field_index -= 1;
-        }
-    }
+    #[test]
+    fn bind_parser_reads_common_zone_syntax() {
+        let records = bind::parse_zone(SAMPLE_ZONE, "demo.example.com").unwrap();
+        let summary: Vec<_> = records
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} {} {} {}{}",
+                    r.name,
+                    r.ttl,
+                    r.record_type,
+                    r.content,
+                    if r.proxied { " (proxied)" } else { "" }
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                "demo.example.com 600 SOA ns1.example.net. hostmaster.example.com. 2024010101 3600 900 604800 300",
+                "demo.example.com 600 NS ns1.example.net",
+                "demo.example.com 300 A 192.0.2.10 (proxied)",
+                "demo.example.com 600 AAAA 2001:db8::10",
+                "www.demo.example.com 600 CNAME demo.example.com",
+                "mail.demo.example.com 600 MX 10 mx1.demo.example.com",
+                "_spf.demo.example.com 600 TXT v=spf1 include:_spf.example.net ~all",
+            ]
+        );
 
-    fn insert_char(&mut self, c: char) {
-        self.active_value_mut().push(c);
+        let preview = ImportPreview::from_records(&records, "demo.example.com");
+        assert_eq!(preview.records, 5);
+        assert_eq!(preview.skipped, 2);
+        assert_eq!(preview.type_summary(), "1 A, 1 AAAA, 1 CNAME, 1 MX, 1 TXT");
     }
 
-    fn backspace(&mut self) {
-        self.active_value_mut().pop();
+    #[test]
+    fn bind_parser_leaves_quoted_parentheses_and_spacing_alone() {
+        let text = r#"note IN TXT "smile :)  twice"
+open IN TXT "a (b" ; not a continuation
+multi IN TXT ( "first (one)"
+        "second)" )
+"#;
+        let contents: Vec<String> = bind::parse_zone(text, "example.com")
+            .unwrap()
+            .into_iter()
+            .map(|record| record.content)
+            .collect();
+        assert_eq!(
+            contents,
+            ["smile :)  twice", "a (b", r#""first (one)" "second)""#,]
+        );
     }
 
-    fn is_ready(&self) -> bool {
-        !self.name.trim().is_empty() && !self.api_token.trim().is_empty()
+    #[test]
+    fn bind_parser_accepts_ttl_units() {
+        let text = "$TTL 1h\nwww 1h30m IN A 192.0.2.1\napi IN 2D A 192.0.2.2\nmx IN MX 10 mail\n";
+        let ttls: Vec<u32> = bind::parse_zone(text, "example.com")
+            .unwrap()
+            .into_iter()
+            .map(|record| record.ttl)
+            .collect();
+        assert_eq!(ttls, [5400, 172_800, 3600]);
+        assert!(bind::parse_zone("$TTL 1x\n", "example.com").is_err());
     }
 
-    fn build_account(&self) -> Result<Account, &'static str> {
-        if !self.is_ready() {
-            return Err("Name and API token are required");
-        }
-        Ok(Account {
-            name: self.name.trim().to_string(),
-            api_token: self.api_token.trim().to_string(),
-            email: if self.email.trim().is_empty() {
-                None
-            } else {
-                Some(self.email.trim().to_string())
-            },
-            account_id: if self.account_id.trim().is_empty() {
-                None
-            } else {
-                Some(self.account_id.trim().to_string())
-            },
-            auth_mode: AuthMode::Token,
-        })
+    #[test]
+    fn bind_parser_reports_line_numbers() {
+        let err = bind::parse_zone("www IN A 192.0.2.1\nbroken IN\n", "example.com")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "line 2: missing record type");
+        assert!(bind::parse_zone("@ IN SOA (\n1 2 3", "example.com").is_err());
     }
-}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct RecordForm {
-    draft: RecordDraft,
-    field_index: usize,
-    is_edit: bool,
-    target_id: Option<String>,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct RecordDraft {
-    name: String,
-    record_type: String,
-    content: String,
-    ttl: String,
-    proxied: bool,
-}
+    #[test]
+    fn import_flow_previews_then_imports() {
+        let zone_file = temp_config_path("import_zone");
+        fs::write(&zone_file, SAMPLE_ZONE).unwrap();
+        let mut app = app_with_records("import", vec![]);
+        app.focus = Focus::Zones;
 
-impl Default for RecordDraft {
-    fn default() -> Self {
-        Self {
-            name: String::new(),
-            record_type: "A".to_string(),
-            content: String::new(),
-            ttl: "300".to_string(),
-            proxied: true,
+        handle_normal_key(KeyCode::Char('i'), &mut app).unwrap();
+        for c in zone_file.to_string_lossy().chars() {
+            handle_import_key(KeyCode::Char(c), &mut app).unwrap();
         }
-    }
-}
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
+        let Mode::ImportZone(form) = &app.mode else {
+            panic!("import overlay closed before confirming");
+        };
+        assert_eq!(form.preview.as_ref().map(|p| p.records), Some(5));
 
-impl RecordDraft {
-    fn from_record(record: &DnsRecord) -> Self {
-        Self {
-            name: record.name.clone(),
-            record_type: record.record_type.clone(),
-            content: record.content.clone(),
-            ttl: record.ttl.to_string(),
-            proxied: record.proxied,
-        }
-    }
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
 
-    fn to_record(&self, id: String) -> Result<DnsRecord> {
-        let ttl: u32 = self
-            .ttl
-            .trim()
-            .parse()
-            .map_err(|_| anyhow!("TTL must be a number"))?;
-        if self.name.trim().is_empty() || self.record_type.trim().is_empty() {
-            return Err(anyhow!("Name and type are required"));
-        }
-        Ok(DnsRecord {
-            id,
-            name: self.name.trim().to_string(),
-            record_type: self.record_type.trim().to_string(),
-            content: self.content.trim().to_string(),
-            ttl,
-            proxied: self.proxied,
-        })
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.last_message,
+            "Imported 5 of 5 record(s) into demo.example.com (0 failed)"
+        );
+        assert!(
+            app.records
+                .iter()
+                .any(|r| r.name == "mail.demo.example.com" && r.record_type == "MX")
+        );
+        fs::remove_file(zone_file).unwrap();
     }
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct ConfirmDelete {
-    record_id: String,
-    record_name: String,
-}
-
-struct App<B: DnsBackend> {
-    config_path: PathBuf,
-    backend: B,
-    accounts: Vec<Account>,
-    selected_account: usize,
-    zones: Vec<Zone>,
-    selected_zone: usize,
-    selected_record: usize,
-    records: Vec<DnsRecord>,
-    focus: Focus,
-    mode: Mode,
-    record_filter: String,
-    record_page: usize,
-    record_page_size: usize,
-    last_message: String,
-}
 
-impl<B: DnsBackend> App<B> {
-    fn new(config_path: impl Into<PathBuf>, accounts: Vec<Account>, backend: B) -> Result<Self> {
-        let mut app = Self {
-            config_path: config_path.into(),
-            backend,
-            accounts,
-            selected_account: 0,
-            zones: Vec::new(),
-            selected_zone: 0,
-            selected_record: 0,
-            records: Vec::new(),
-            focus: Focus::Accounts,
-            mode: Mode::Normal,
-            record_filter: String::new(),
-            record_page: 0,
-            record_page_size: 10,
-            last_message: String::new(),
+    #[test]
+    fn strict_policy_refuses_imports_that_drop_proxy_state() {
+        let zone_file = temp_config_path("strict_import_zone");
+        fs::write(&zone_file, SAMPLE_ZONE).unwrap();
+        let preview = |app: &mut App<MockBackend>| {
+            app.focus = Focus::Zones;
+            handle_normal_key(KeyCode::Char('i'), app).unwrap();
+            for c in zone_file.to_string_lossy().chars() {
+                handle_import_key(KeyCode::Char(c), app).unwrap();
+            }
+            handle_import_key(KeyCode::Enter, app).unwrap();
         };
 
-        app.refresh_current()?;
-        if app.accounts.is_empty() {
-            app.mode = Mode::AddingAccount(AccountForm::default());
-            app.last_message = "Add your first Cloudflare account (name + API token).".to_string();
-        }
+        let mut app = app_with_records("lenient_import", vec![]);
+        preview(&mut app);
+        assert!(draw_app(&mut app).contains("1 proxied record(s) will be imported unproxied"));
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.records.len(), 5);
 
-        Ok(app)
-    }
+        let mut app = app_with_records("strict_import", vec![]);
+        app.policy = Policy { strict: true };
+        preview(&mut app);
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ImportZone(_)));
+        assert_eq!(
+            app.last_message,
+            "Strict mode: importing into demo.example.com would leave 1 proxied record(s) unproxied"
+        );
+        assert!(app.records.is_empty());
 
-    fn current_account(&self) -> Option<&Account> {
-        self.accounts.get(self.selected_account)
+        // Proxying everything keeps the tagged record proxied, so strict mode lets it through.
+        handle_import_key(KeyCode::Tab, &mut app).unwrap();
+        handle_import_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.records.len(), 5);
+        fs::remove_file(zone_file).unwrap();
     }
 
-    fn current_zone(&self) -> Option<&Zone> {
-        self.zones.get(self.selected_zone)
-    }
+    #[test]
+    fn apply_overlay_shows_the_diff_then_sends_it_as_a_bulk_job() {
+        let file = temp_config_path("apply_overlay").with_extension("toml");
+        fs::write(
+            &file,
+            "[[records]]\nname = \"api\"\ntype = \"A\"\n\
+             content = [\"192.0.2.1\", \"192.0.2.2\"]\nttl = 300\nproxied = false\n",
+        )
+        .unwrap();
+        let mut app = app_with_records(
+            "apply_overlay",
+            vec![
+                record("1", "api.demo.example.com", "A", "192.0.2.1"),
+                record("2", "old.demo.example.com", "TXT", "bye"),
+            ],
+        );
+        app.focus = Focus::Zones;
 
-    fn next_account(&mut self) -> Result<()> {
-        if self.accounts.is_empty() {
-            return Ok(());
+        handle_normal_key(KeyCode::Char('A'), &mut app).unwrap();
+        for c in file.to_string_lossy().chars() {
+            handle_apply_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        self.selected_account = (self.selected_account + 1) % self.accounts.len();
-        self.selected_zone = 0;
-        self.refresh_current()
+        handle_apply_key(KeyCode::Tab, &mut app).unwrap();
+        handle_apply_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_apply_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(
+            app.last_message,
+            "demo.example.com: 1 to create, 0 to update, 1 to delete, 1 unchanged"
+        );
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("- TXT old.demo.example.com bye (TTL 300)"));
+        assert!(screen.contains("+ A api.demo.example.com 192.0.2.2 (TTL 300)"));
+
+        handle_apply_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        wait_until_idle(&mut app);
+        assert_eq!(app.last_message, "2 change(s) applied to demo.example.com");
+        let mut contents: Vec<&str> = app.records.iter().map(|r| r.content.as_str()).collect();
+        contents.sort_unstable();
+        assert_eq!(contents, ["192.0.2.1", "192.0.2.2"]);
+        assert_eq!(app.backend().records["demo-01"].len(), 2);
+        fs::remove_file(file).unwrap();
     }
 
-    fn previous_account(&mut self) -> Result<()> {
-        if self.accounts.is_empty() {
-            return Ok(());
-        }
+    #[test]
+    fn strict_mode_wants_the_zone_name_typed_before_an_apply_deletes() {
+        let file = temp_config_path("apply_strict").with_extension("toml");
+        fs::write(
+            &file,
+            "[[records]]\nname = \"api\"\ntype = \"A\"\ncontent = \"192.0.2.1\"\nttl = 300\n",
+        )
+        .unwrap();
+        let mut app = app_with_records(
+            "apply_strict",
+            vec![
+                record("1", "api.demo.example.com", "A", "192.0.2.1"),
+                record("2", "old.demo.example.com", "TXT", "bye"),
+            ],
+        );
+        app.policy = Policy { strict: true };
+        app.focus = Focus::Zones;
 
-        if self.selected_account == 0 {
-            self.selected_account = self.accounts.len() - 1;
-        } else {
-            self.selected_account -= 1;
+        handle_normal_key(KeyCode::Char('A'), &mut app).unwrap();
+        for c in file.to_string_lossy().chars() {
+            handle_apply_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        self.selected_zone = 0;
-        self.refresh_current()
-    }
+        handle_apply_key(KeyCode::Tab, &mut app).unwrap();
+        handle_apply_key(KeyCode::Char(' '), &mut app).unwrap();
+        handle_apply_key(KeyCode::Enter, &mut app).unwrap();
+        handle_apply_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ApplyState(_)));
+        assert_eq!(
+            app.last_message,
+            "Type demo.example.com exactly to confirm the deletes"
+        );
+        let screen = draw_app(&mut app);
+        assert!(screen.contains("Type the zone name to confirm"), "{screen}");
 
-    fn next_zone(&mut self) -> Result<()> {
-        if self.zones.is_empty() {
-            return Ok(());
+        for c in "demo.example.com".chars() {
+            handle_apply_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        self.selected_zone = (self.selected_zone + 1) % self.zones.len();
-        self.refresh_records()
+        handle_apply_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        wait_until_idle(&mut app);
+        assert_eq!(app.backend().records["demo-01"].len(), 1);
+        fs::remove_file(file).unwrap();
     }
 
-    fn previous_zone(&mut self) -> Result<()> {
-        if self.zones.is_empty() {
-            return Ok(());
-        }
-        if self.selected_zone == 0 {
-            self.selected_zone = self.zones.len() - 1;
-        } else {
-            self.selected_zone -= 1;
+    #[test]
+    fn zone_filter_narrows_the_list_and_opens_the_match() {
+        let mut app = app_with_records("zone_filter", vec![]);
+        app.accounts[0].account_id = Some("acc-1".to_string());
+        let account = app.accounts[0].clone();
+        for name in ["shop.example.org", "blog.example.org"] {
+            app.backend()
+                .create_zone(&account, name, ZoneSetup::Full)
+                .unwrap();
         }
-        self.refresh_records()
-    }
+        app.refresh_current().unwrap();
+        assert_eq!(app.zones.len(), 4);
+        app.focus = Focus::Zones;
 
-    fn next_record(&mut self) {
-        let total = self.filtered_records().len();
-        if total == 0 {
-            return;
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        for c in "ORG".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        self.selected_record = (self.selected_record + 1).min(total.saturating_sub(1));
-        self.ensure_record_visible(total);
-    }
+        assert_eq!(app.visible_zones(), [2, 3]);
+        assert_eq!(app.current_zone().unwrap().name, "demo.example.com");
+        handle_key(KeyCode::Down, &mut app).unwrap();
+        handle_key(KeyCode::Down, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.current_zone().unwrap().name, "blog.example.org");
+        assert_eq!(app.zone_position(), Some(1));
 
-    fn previous_record(&mut self) {
-        let total = self.filtered_records().len();
-        if total == 0 {
-            return;
-        }
-        if self.selected_record == 0 {
-            self.selected_record = 0;
-        } else {
-            self.selected_record -= 1;
-        }
-        self.ensure_record_visible(total);
-    }
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(
+            app.current_zone().unwrap().name,
+            "shop.example.org",
+            "wraps in the filter"
+        );
+        app.select_zone_at(1).unwrap();
+        assert_eq!(app.current_zone().unwrap().name, "blog.example.org");
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!(app.current_zone().unwrap().name, "blog.example.org");
 
-    fn next_page(&mut self) {
-        let total = self.filtered_records().len();
-        let page_count = self.record_page_count(total);
-        if page_count == 0 {
-            return;
-        }
-        self.record_page = (self.record_page + 1) % page_count;
-        self.selected_record = self.record_page * self.page_size();
-    }
+        handle_normal_key(KeyCode::Char('/'), &mut app).unwrap();
+        handle_key(KeyCode::Char('x'), &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.last_message, "No zones match 'ORGx'");
+        assert!(app.visible_zones().is_empty());
+        draw_app(&mut app);
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.zone_filter, "ORG");
+        assert_eq!(app.current_zone().unwrap().name, "blog.example.org");
 
-    fn previous_page(&mut self) {
-        let total = self.filtered_records().len();
-        let page_count = self.record_page_count(total);
-        if page_count == 0 {
-            return;
-        }
-        if self.record_page == 0 {
-            self.record_page = page_count - 1;
-        } else {
-            self.record_page -= 1;
-        }
-        self.selected_record = self.record_page * self.page_size();
-    }
+        // A reload that drops the selected zone lands on the first match, not outside it.
+        app.backend().created_zones.get_mut("demo").unwrap().pop();
+        app.refresh_current().unwrap();
+        assert_eq!(app.current_zone().unwrap().name, "shop.example.org");
 
-    fn refresh_current(&mut self) -> Result<()> {
-        self.refresh_zones()?;
-        self.refresh_records()
+        let mut other = account.clone();
+        other.name = "other".to_string();
+        app.accounts.push(other);
+        app.select_account_at(1).unwrap();
+        assert!(app.zone_filter.is_empty());
     }
 
-    fn refresh_zones(&mut self) -> Result<()> {
-        if let Some(account) = self.current_account().cloned() {
-            let zones = self.backend.list_zones(&account)?;
-            self.zones = zones;
-            if self.selected_zone >= self.zones.len() {
-                self.selected_zone = 0;
-            }
-            self.last_message = format!("Loaded {} zone(s) for {}", self.zones.len(), account.name);
-        } else {
-            self.zones.clear();
-            self.records.clear();
+    #[test]
+    fn the_selected_zone_stays_in_bounds_when_the_list_shrinks_under_it() {
+        let mut app = app_with_records("zone_list_shrinks", vec![]);
+        app.accounts[0].account_id = Some("acc-1".to_string());
+        let account = app.accounts[0].clone();
+        for name in ["a.example.org", "b.example.org", "c.example.org"] {
+            app.backend()
+                .create_zone(&account, name, ZoneSetup::Full)
+                .unwrap();
         }
-        Ok(())
-    }
+        app.refresh_current().unwrap();
+        assert_eq!(app.zones.len(), 5);
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('G'), &mut app).unwrap();
+        assert_eq!(app.current_zone().unwrap().name, "c.example.org");
 
-    fn refresh_records(&mut self) -> Result<()> {
-        match (
-            self.current_account().cloned(),
-            self.current_zone().cloned(),
-        ) {
-            (Some(account), Some(zone)) => {
-                let records = self.backend.list_records(&account, &zone)?;
-                self.records = records;
-                self.last_message = format!("{} record(s) in {}", self.records.len(), zone.name);
-            }
-            _ => self.records.clear(),
-        }
-        self.selected_record = 0;
-        self.record_page = 0;
-        Ok(())
-    }
+        // The highlighted zone and the two before it go away on the next reload.
+        app.backend().created_zones.get_mut("demo").unwrap().clear();
+        app.refresh_current().unwrap();
+        assert_eq!(app.zones.len(), 2);
+        assert!(app.selected_zone < app.zones.len());
+        assert!(app.current_zone().is_some());
+        draw_app(&mut app);
 
-    fn filtered_records(&self) -> Vec<&DnsRecord> {
-        if self.record_filter.trim().is_empty() {
-            return self.records.iter().collect();
-        }
-        let needle = self.record_filter.to_lowercase();
-        self.records
-            .iter()
-            .filter(|r| {
-                r.name.to_lowercase().contains(&needle)
-                    || r.content.to_lowercase().contains(&needle)
-                    || r.record_type.to_lowercase().contains(&needle)
-            })
-            .collect()
-    }
+        // Filtered, the selection is mapped into what the filter lets through.
+        app.zone_filter = "services".to_string();
+        app.selected_zone = 0;
+        handle_normal_key(KeyCode::Char('j'), &mut app).unwrap();
+        assert_eq!(app.zone_position(), Some(0));
+        assert_eq!(app.current_zone().unwrap().id, "demo-02");
 
-    fn update_record_page_size(&mut self, area_height: u16) {
-        // Table uses one row for the header and two for borders.
-        let usable_rows = area_height as usize;
-        let new_size = usable_rows.saturating_sub(3).max(1);
-        if new_size != self.record_page_size {
-            self.record_page_size = new_size;
-            let total = self.filtered_records().len();
-            let page_count = self.record_page_count(total);
-            self.record_page = self.record_page.min(page_count.saturating_sub(1));
-            self.selected_record = self.selected_record.min(total.saturating_sub(1));
-            self.ensure_record_visible(total);
+        // Nothing left at all: no zone, and moving or drawing doesn't panic.
+        app.zone_filter.clear();
+        app.zones.clear();
+        app.selected_zone = 3;
+        draw_app(&mut app);
+        assert_eq!(app.current_zone(), None);
+        for key in ['j', 'k', 'G'] {
+            handle_normal_key(KeyCode::Char(key), &mut app).unwrap();
         }
+        assert_eq!(app.current_zone(), None);
     }
 
-    fn page_size(&self) -> usize {
-        self.record_page_size.max(1)
-    }
+    #[test]
+    fn create_zone_flow_selects_the_new_zone() {
+        let mut app = app_with_records("create_zone", vec![]);
+        app.accounts[0].account_id = Some("acc-1".to_string());
+        app.focus = Focus::Zones;
 
-    fn record_page_count(&self, total: usize) -> usize {
-        if total == 0 {
-            0
-        } else {
-            total.div_ceil(self.page_size())
+        handle_normal_key(KeyCode::Char('N'), &mut app).unwrap();
+        for c in "new-site.org".chars() {
+            handle_zone_form_key(KeyCode::Char(c), &mut app).unwrap();
         }
+        handle_zone_form_key(KeyCode::Enter, &mut app).unwrap();
+        handle_zone_form_key(KeyCode::Char(' '), &mut app).unwrap();
+        let Mode::CreateZone(form) = &app.mode else {
+            panic!("zone form closed before submitting");
+        };
+        assert_eq!(form.setup, ZoneSetup::Partial);
+        handle_zone_form_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.zones.len(), 3);
+        assert_eq!(app.zones[app.selected_zone].name, "new-site.org");
+        assert!(app.records.is_empty());
+        assert_eq!(
+            app.last_message,
+            "Created zone new-site.org; point it at ada.ns.cloudflare.com, bob.ns.cloudflare.com"
+        );
     }
 
-    fn paged_records(&self) -> Vec<&DnsRecord> {
-        let filtered = self.filtered_records();
-        if filtered.is_empty() {
-            return filtered;
+    #[test]
+    fn zone_delete_requires_the_typed_name() {
+        let mut app = app_with_records(
+            "delete_zone",
+            vec![record("1", "www.demo.example.com", "A", "192.0.2.1")],
+        );
+        app.refresh_current().unwrap();
+        app.selected_zone = 1;
+        app.focus = Focus::Zones;
+
+        handle_normal_key(KeyCode::Char('D'), &mut app).unwrap();
+        let Mode::ConfirmDeleteZone(confirm) = &app.mode else {
+            panic!("zone delete confirmation did not open");
+        };
+        assert_eq!(confirm.zone.name, "demo.services.io");
+        assert_eq!(confirm.record_count, 3);
+
+        for c in "demo.services".chars() {
+            handle_confirm_delete_zone_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        let start = self.record_page * self.page_size();
-        let end = (start + self.page_size()).min(filtered.len());
-        filtered[start..end].to_vec()
-    }
+        handle_confirm_delete_zone_key(KeyCode::Enter, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::ConfirmDeleteZone(_)));
+        assert_eq!(app.zones.len(), 2);
 
-    fn ensure_record_visible(&mut self, total: usize) {
-        if total == 0 {
-            self.record_page = 0;
-            self.selected_record = 0;
-            return;
+        for c in ".io".chars() {
+            handle_confirm_delete_zone_key(KeyCode::Char(c), &mut app).unwrap();
         }
-        let page = self.selected_record / self.page_size();
-        self.record_page = page.min(self.record_page_count(total).saturating_sub(1));
+        handle_confirm_delete_zone_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.last_message, "Deleted zone demo.services.io");
+        assert_eq!(app.zones.len(), 1);
+        assert_eq!(app.selected_zone, 0);
+        assert_eq!(app.records.len(), 1);
     }
 
-    fn status_message(&self) -> (String, String) {
-        let help = "q: quit  a: add account  r: refresh  Tab/Shift+Tab: focus  ↑/↓: move  /: search  n/e/d: new/edit/del  PgUp/PgDn: pages";
-        if self.accounts.is_empty() {
-            return (
-                help.to_string(),
-                "No accounts configured. Press 'a' to add one. Tokens are stored locally.".to_string(),
-            );
-        }
+    #[test]
+    fn zone_detail_toggles_dnssec_after_confirmation() {
+        let mut app = app_with_records("zone_dnssec", vec![]);
+        app.focus = Focus::Zones;
 
-        let account_name = self
-            .current_account()
-            .map(|a| a.name.as_str())
-            .unwrap_or("No account");
-        let zone_name = self
-            .current_zone()
-            .map(|z| z.name.as_str())
-            .unwrap_or("No zone");
-        let account_index = if self.accounts.is_empty() {
-            0
-        } else {
-            self.selected_account + 1
+        handle_normal_key(KeyCode::Enter, &mut app).unwrap();
+        let Mode::ZoneDetail(detail) = &app.mode else {
+            panic!("zone detail did not open");
         };
-        let zone_index = if self.zones.is_empty() {
-            0
-        } else {
-            self.selected_zone + 1
+        assert_eq!(detail.dnssec.as_ref().unwrap().status, "disabled");
+
+        handle_zone_detail_key(KeyCode::Char('s'), &mut app).unwrap();
+        handle_zone_detail_key(KeyCode::Char('n'), &mut app).unwrap();
+        handle_zone_detail_key(KeyCode::Char('y'), &mut app).unwrap();
+        let Mode::ZoneDetail(detail) = &app.mode else {
+            panic!("zone detail closed when the toggle was dismissed");
         };
-        let zone_total = self.zones.len().max(1);
+        assert_eq!(detail.dnssec.as_ref().unwrap().status, "disabled");
 
-        let filtered_count = self.filtered_records().len();
-        let page_count = self.record_page_count(filtered_count).max(1);
-        let filter_suffix = if self.record_filter.trim().is_empty() {
-            ""
-        } else {
-            " filtered"
+        handle_zone_detail_key(KeyCode::Char('s'), &mut app).unwrap();
+        handle_zone_detail_key(KeyCode::Char('y'), &mut app).unwrap();
+        let Mode::ZoneDetail(detail) = &app.mode else {
+            panic!("zone detail closed after toggling");
         };
+        let dnssec = detail.dnssec.as_ref().unwrap();
+        assert_eq!(dnssec.status, "active");
+        let ds = dnssec.ds.as_deref().unwrap();
+        assert!(ds.starts_with("demo.example.com. 3600 IN DS 2371 13 2 "));
+        assert!(app.last_message.ends_with(ds));
 
-        (
-            help.to_string(),
-            format!(
-                "Account: {} ({}/{}) | Zone: {} ({}/{}) | Records: page {}/{} ({} shown{}) | {}",
-                account_name,
-                account_index,
-                self.accounts.len(),
-                zone_name,
-                zone_index,
-                zone_total,
-                self.record_page + 1,
-                page_count,
-                self.paged_records().len(),
-                filter_suffix,
-                self.last_message
-            ),
-        )
+        handle_zone_detail_key(KeyCode::Esc, &mut app).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
     }
 
-    fn start_add_account(&mut self) {
-        self.mode = Mode::AddingAccount(AccountForm::default());
-        self.last_message = "Add a Cloudflare API token for this account".to_string();
-    }
+    #[test]
+    fn create_zone_requires_an_account_id() {
+        let mut app = app_with_records("create_zone_no_id", vec![]);
+        app.focus = Focus::Zones;
 
-    fn finish_add_account(&mut self, account: Account) -> Result<()> {
-        let name = account.name.clone();
-        self.accounts.push(account);
-        self.selected_account = self.accounts.len().saturating_sub(1);
-        self.selected_zone = 0;
-        self.mode = Mode::Normal;
-        self.save_accounts()?;
-        self.refresh_current()?;
-        self.last_message = format!("Added account {name}");
-        Ok(())
-    }
+        handle_normal_key(KeyCode::Char('N'), &mut app).unwrap();
 
-    fn save_accounts(&self) -> Result<()> {
-        let config = Config {
-            accounts: self.accounts.clone(),
-        };
-        config.save(&self.config_path)
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.last_message,
+            "demo has no account ID configured; zone creation needs one"
+        );
     }
 
-    fn ensure_onboarding_prompt(&mut self) {
-        if self.accounts.is_empty() {
-            self.start_add_account();
+    #[test]
+    fn import_preview_reports_unreadable_files() {
+        let mut app = app_with_records("import_missing", vec![]);
+        app.focus = Focus::Zones;
+        handle_normal_key(KeyCode::Char('i'), &mut app).unwrap();
+        for c in "/nonexistent/zone.txt".chars() {
+            handle_import_key(KeyCode::Char(c), &mut app).unwrap();
         }
+        handle_import_key(KeyCode::Enter, &mut app).unwrap();
+
+        assert!(matches!(
+            app.mode,
+            Mode::ImportZone(ImportForm { preview: None, .. })
+        ));
+        assert!(
+            app.last_message.starts_with("Cannot import: Reading"),
+            "{}",
+            app.last_message
+        );
     }
 
-    fn start_record_form(&mut self, is_edit: bool) {
-        let draft = if is_edit {
-            if let Some(rec) = self.current_record() {
-                RecordDraft::from_record(rec)
-            } else {
-                RecordDraft::default()
+    #[test]
+    fn cloudflare_import_uploads_multipart_file() {
+        let zone_file = temp_config_path("cf_import");
+        fs::write(&zone_file, "www 300 IN A 192.0.2.1\n").unwrap();
+        let mut server = mockito::Server::new();
+        let import = server
+            .mock("POST", "/zones/zone-1/dns_records/import")
+            .match_header(
+                "content-type",
+                mockito::Matcher::Regex("^multipart/form-data; boundary=".into()),
+            )
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("name=\"file\"; filename=".into()),
+                mockito::Matcher::Regex("www 300 IN A 192.0.2.1".into()),
+                mockito::Matcher::Regex("name=\"proxied\"\r\n\r\ntrue".into()),
+            ]))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {"recs_added": 2, "total_records_parsed": 3}
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let result = backend
+            .import_zone(&cf_account(), &cf_zone(), &zone_file, true)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            ImportResult {
+                added: 2,
+                parsed: 3
             }
-        } else {
-            RecordDraft::default()
-        };
-        let target_id = self.current_record().map(|r| r.id.clone());
-        self.mode = Mode::RecordForm(RecordForm {
-            draft,
-            field_index: 0,
-            is_edit,
-            target_id,
-        });
-        self.last_message = if is_edit {
-            "Editing DNS record".to_string()
-        } else {
-            "Create DNS record".to_string()
-        };
+        );
+        assert_eq!(result.failed(), 1);
+        import.assert();
+        fs::remove_file(zone_file).unwrap();
     }
 
-    fn ask_delete_record(&mut self) {
-        if let Some(record) = self.current_record().cloned() {
-            self.mode = Mode::ConfirmDelete(ConfirmDelete {
-                record_id: record.id.clone(),
-                record_name: record.name.clone(),
-            });
-            self.last_message = format!("Delete {}?", record.name);
-        }
+    #[test]
+    fn cloudflare_creates_zones_under_the_account() {
+        let mut server = mockito::Server::new();
+        let create = server
+            .mock("POST", "/zones")
+            .match_body(mockito::Matcher::Json(json!({
+                "name": "example.org",
+                "account": {"id": "acc-1"},
+                "type": "partial"
+            })))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "id": "zone-9",
+                        "name": "example.org",
+                        "status": "pending",
+                        "name_servers": ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let created = backend
+            .create_zone(&cf_account(), "example.org", ZoneSetup::Partial)
+            .unwrap();
+
+        assert_eq!(created.id, "zone-9");
+        assert!(created.is_pending());
+        assert_eq!(
+            created.name_servers,
+            ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]
+        );
+        create.assert();
     }
 
-    fn current_record(&self) -> Option<&DnsRecord> {
-        self.filtered_records().get(self.selected_record).copied()
+    #[test]
+    fn cloudflare_deletes_zones() {
+        let mut server = mockito::Server::new();
+        let delete = server
+            .mock("DELETE", "/zones/zone-1")
+            .with_body(
+                json!({"success": true, "errors": [], "result": {"id": "zone-1"}}).to_string(),
+            )
+            .expect(1)
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.delete_zone(&cf_account(), &cf_zone()).unwrap();
+
+        delete.assert();
     }
 
-    fn create_record(&mut self, record: DnsRecord) -> Result<()> {
-        let (account, zone) = match (self.current_account(), self.current_zone()) {
-            (Some(a), Some(z)) => (a.clone(), z.clone()),
-            _ => return Ok(()),
-        };
+    #[test]
+    fn cloudflare_reads_and_updates_dnssec() {
+        let mut server = mockito::Server::new();
+        let status = server
+            .mock("GET", "/zones/zone-1/dnssec")
+            .with_body(
+                json!({"success": true, "errors": [], "result": {"status": "disabled"}})
+                    .to_string(),
+            )
+            .expect(1)
+            .create();
+        let enable = server
+            .mock("PATCH", "/zones/zone-1/dnssec")
+            .match_body(mockito::Matcher::Json(json!({"status": "active"})))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "status": "pending",
+                        "digest": "ABCD",
+                        "ds": "example.com. 3600 IN DS 2371 13 2 ABCD"
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
 
-        let created = self
-            .backend
-            .create_record(&account, &zone, record.clone())?;
-        self.last_message = format!("Created {}", created.name);
-        self.mode = Mode::Normal;
-        self.refresh_records()
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let before = backend.dnssec_status(&cf_account(), &cf_zone()).unwrap();
+        assert!(!before.is_enabled());
+        assert_eq!(before.ds, None);
+
+        let after = backend.set_dnssec(&cf_account(), &cf_zone(), true).unwrap();
+        assert!(after.is_enabled());
+        assert_eq!(after.digest.as_deref(), Some("ABCD"));
+        status.assert();
+        enable.assert();
     }
 
-    fn update_record(&mut self, record: DnsRecord) -> Result<()> {
-        let (account, zone) = match (self.current_account(), self.current_zone()) {
-            (Some(a), Some(z)) => (a.clone(), z.clone()),
-            _ => return Ok(()),
-        };
+    #[test]
+    fn cloudflare_lists_accounts() {
+        let mut server = mockito::Server::new();
+        let accounts = server
+            .mock("GET", "/accounts")
+            .match_query(mockito::Matcher::UrlEncoded("per_page".into(), "50".into()))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [{"id": "acc-1", "name": "Acme", "type": "standard"}]
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
 
-        let updated = self
-            .backend
-            .update_record(&account, &zone, record.clone())?;
-        self.last_message = format!("Updated {}", updated.name);
-        self.mode = Mode::Normal;
-        self.refresh_records()
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let found = backend.list_cf_accounts(&cf_account()).unwrap();
+
+        assert_eq!(
+            found,
+            [CloudflareAccount {
+                id: "acc-1".to_string(),
+                name: "Acme".to_string()
+            }]
+        );
+        accounts.assert();
     }
 
-    fn delete_record(&mut self, record_id: String) -> Result<()> {
-        let (account, zone) = match (self.current_account(), self.current_zone()) {
-            (Some(a), Some(z)) => (a.clone(), z.clone()),
-            _ => return Ok(()),
-        };
+    #[test]
+    fn compact_timestamp_is_file_name_safe() {
+        assert_eq!(compact_timestamp(1_700_000_000), "20231114T221320Z");
+    }
+
+    #[test]
+    fn cloudflare_batch_sends_one_request() {
+        let mut server = mockito::Server::new();
+        let batch = server
+            .mock("POST", "/zones/zone-1/dns_records/batch")
+            .match_body(mockito::Matcher::Json(json!({
+                "deletes": [{"id": "r1"}],
+                "patches": [{
+                    "id": "r2",
+                    "name": "www.example.com",
+                    "type": "A",
+                    "content": "192.0.2.2",
+                    "ttl": 300,
+                    "proxied": false
+                }]
+            })))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": {
+                        "deletes": [{"id": "r1", "name": "old.example.com", "type": "A", "content": "192.0.2.1", "ttl": 300, "proxied": false}],
+                        "patches": [{"id": "r2", "name": "www.example.com", "type": "A", "content": "192.0.2.2", "ttl": 300, "proxied": false}]
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let result = backend
+            .batch(
+                &cf_account(),
+                &cf_zone(),
+                BatchRequest {
+                    deletes: vec!["r1".to_string()],
+                    patches: vec![record("r2", "www.example.com", "A", "192.0.2.2")],
+                    ..BatchRequest::default()
+                },
+            )
+            .unwrap();
 
-        self.backend.delete_record(&account, &zone, &record_id)?;
-        self.last_message = "Record deleted".to_string();
-        self.refresh_records()
+        assert_eq!(result.deletes[0].name, "old.example.com");
+        assert_eq!(result.patches[0].id, "r2");
+        assert!(result.posts.is_empty());
+        batch.assert();
     }
-}
 
-trait DnsBackend {
-    fn list_zones(&mut self, account: &Account) -> Result<Vec<Zone>>;
-    fn list_records(&mut self, account: &Account, zone: &Zone) -> Result<Vec<DnsRecord>>;
-    fn create_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord>;
-    fn update_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord>;
-    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()>;
-}
+    #[test]
+    fn cloudflare_lists_records_by_type() {
+        let mut server = mockito::Server::new();
+        let listing = server
+            .mock("GET", "/zones/zone-1/dns_records")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("type".into(), "TXT".into()),
+                mockito::Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_body(json!({"success": true, "errors": [], "result": []}).to_string())
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend
+            .list_records_page(&cf_account(), &cf_zone(), 1, Some("TXT"))
+            .unwrap();
+
+        listing.assert();
+    }
+
+    #[test]
+    fn cloudflare_search_queries_name_or_content() {
+        let mut server = mockito::Server::new();
+        let search = server
+            .mock("GET", "/zones/zone-1/dns_records")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("name.contains".into(), "mail".into()),
+                mockito::Matcher::UrlEncoded("content.contains".into(), "mail".into()),
+                mockito::Matcher::UrlEncoded("match".into(), "any".into()),
+            ]))
+            .with_body(
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": [{
+                        "id": "r1",
+                        "name": "mail.example.com",
+                        "type": "A",
+                        "content": "192.0.2.5",
+                        "ttl": 300,
+                        "proxied": false
+                    }],
+                    "result_info": {"page": 1, "per_page": 200, "total_pages": 1, "total_count": 1}
+                })
+                .to_string(),
+            )
+            .create();
 
-enum Backend {
-    Cloudflare(CloudflareBackend),
-    Mock(MockBackend),
-}
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let found = backend
+            .search_records(&cf_account(), &cf_zone(), "mail")
+            .unwrap();
 
-impl DnsBackend for Backend {
-    fn list_zones(&mut self, account: &Account) -> Result<Vec<Zone>> {
-        match self {
-            Backend::Cloudflare(client) => client.list_zones(account),
-            Backend::Mock(mock) => mock.list_zones(account),
-        }
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "mail.example.com");
+        search.assert();
     }
 
-    fn list_records(&mut self, account: &Account, zone: &Zone) -> Result<Vec<DnsRecord>> {
-        match self {
-            Backend::Cloudflare(client) => client.list_records(account, zone),
-            Backend::Mock(mock) => mock.list_records(account, zone),
-        }
-    }
+    #[test]
+    fn cloudflare_retries_rate_limited_requests() {
+        let mut server = mockito::Server::new();
+        let limited = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(429)
+            .with_header("retry-after", "3")
+            .with_body(json!({"success": false, "errors": [{"message": "slow down"}]}).to_string())
+            .expect(1)
+            .create();
+        let ok = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(
+                json!({"success": true, "errors": [], "result": [{"id": "z1", "name": "a.com"}]})
+                    .to_string(),
+            )
+            .expect(1)
+            .create();
 
-    fn create_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        match self {
-            Backend::Cloudflare(client) => client.create_record(account, zone, record),
-            Backend::Mock(mock) => mock.create_record(account, zone, record),
-        }
-    }
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.retry.max_wait = Duration::from_millis(10);
+        let zones = backend.list_zones(&cf_account()).unwrap().items;
 
-    fn update_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        match self {
-            Backend::Cloudflare(client) => client.update_record(account, zone, record),
-            Backend::Mock(mock) => mock.update_record(account, zone, record),
-        }
+        assert_eq!(zones.len(), 1);
+        limited.assert();
+        ok.assert();
+        assert_eq!(*backend.notice.lock().unwrap(), None);
     }
 
-    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
-        match self {
-            Backend::Cloudflare(client) => client.delete_record(account, zone, record_id),
-            Backend::Mock(mock) => mock.delete_record(account, zone, record_id),
-        }
-    }
-}
+    #[test]
+    fn cloudflare_gives_up_after_rate_limit_attempts() {
+        let mut server = mockito::Server::new();
+        let limited = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(429)
+            .with_body(json!({"success": false, "errors": [{"message": "slow down"}]}).to_string())
+            .expect(2)
+            .create();
 
-struct CloudflareBackend {
-    client: Client,
-    base_url: String,
-}
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.retry = RetryPolicy {
+            rate_limit_attempts: 2,
+            max_wait: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        let err = backend.list_zones(&cf_account()).unwrap_err().to_string();
 
-impl CloudflareBackend {
-    fn new() -> Result<Self> {
-        Self::new_with_base(CF_API_BASE)
+        assert!(err.contains("429") && err.contains("slow down"), "{err}");
+        limited.assert();
     }
 
-    fn new_with_base(base_url: impl Into<String>) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent("nyxflare/0.1")
-            .timeout(Duration::from_secs(15))
-            .build()?;
-        Ok(Self {
-            client,
-            base_url: base_url.into(),
-        })
-    }
+    #[test]
+    fn cloudflare_retries_transient_server_errors() {
+        let mut server = mockito::Server::new();
+        let failing = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(502)
+            .with_body("<html>Bad gateway</html>")
+            .expect(1)
+            .create();
+        let ok = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(
+                json!({"success": true, "errors": [], "result": [{"id": "z1", "name": "a.com"}]})
+                    .to_string(),
+            )
+            .expect(1)
+            .create();
 
-    fn with_auth(&self, request: RequestBuilder, account: &Account) -> RequestBuilder {
-        match account.auth_mode {
-            AuthMode::Token => request.bearer_auth(&account.api_token),
-            AuthMode::GlobalKey => {
-                if let Some(email) = &account.email {
-                    request
-                        .header("X-Auth-Email", email)
-                        .header("X-Auth-Key", &account.api_token)
-                } else {
-                    request
-                }
-            }
-        }
-    }
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.retry.backoff_base = Duration::ZERO;
+        let zones = backend.list_zones(&cf_account()).unwrap().items;
 
-    fn list_zones(&mut self, account: &Account) -> Result<Vec<Zone>> {
-        let url = format!("{}/zones", self.base_url);
-        let response = self
-            .with_auth(self.client.get(url), account)
-            .query(&self.zone_query(account))
-            .send()
-            .with_context(|| format!("Listing zones for {}", account.name))?;
+        assert_eq!(zones.len(), 1);
+        failing.assert();
+        ok.assert();
+    }
 
-        let status = response.status();
-        let text = response.text().unwrap_or_default();
-        let parsed: CfResponse<CfZone> = serde_json::from_str(&text)
-            .with_context(|| format!("Failed to parse Cloudflare zones response: {text}"))?;
+    #[test]
+    fn cloudflare_does_not_replay_creates_after_a_response() {
+        let mut server = mockito::Server::new();
+        let failing = server
+            .mock("POST", "/zones/zone-1/dns_records")
+            .with_status(502)
+            .with_body(
+                json!({"success": false, "errors": [{"message": "bad gateway"}]}).to_string(),
+            )
+            .expect(1)
+            .create();
 
-        if !status.is_success() || !parsed.success {
-            return Err(anyhow!(format!(
-                "Zones ({status}): {} | body: {}",
-                parsed.error_message(),
-                truncate_body(&text)
-            )));
-        }
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.retry.backoff_base = Duration::ZERO;
+        let err = backend
+            .create_record(
+                &cf_account(),
+                &cf_zone(),
+                record("", "www.a.com", "A", "192.0.2.1"),
+            )
+            .unwrap_err()
+            .to_string();
 
-        Ok(parsed
-            .result
-            .unwrap_or_default()
-            .into_iter()
-            .map(|z| Zone {
-                id: z.id,
-                name: z.name,
-            })
-            .collect())
+        assert!(err.contains("502"), "{err}");
+        failing.assert();
     }
 
-    fn list_records(&mut self, account: &Account, zone: &Zone) -> Result<Vec<DnsRecord>> {
-        let url = format!("{}/zones/{}/dns_records", self.base_url, zone.id);
-        let response = self
-            .with_auth(self.client.get(url), account)
-            .query(&[("per_page", 200)])
-            .send()
-            .with_context(|| format!("Listing records for zone {}", zone.name))?;
-
-        let status = response.status();
-        let text = response.text().unwrap_or_default();
-        let parsed: CfResponse<CfRecord> = serde_json::from_str(&text)
-            .with_context(|| format!("Failed to parse Cloudflare dns_records response: {text}"))?;
+    #[test]
+    fn retry_policy_backs_off_exponentially_with_jitter() {
+        let policy = RetryPolicy::default();
+        let full: Vec<_> = (1..=3).map(|n| policy.backoff_delay(n, 1.0)).collect();
+        assert_eq!(
+            full,
+            [
+                Duration::from_millis(250),
+                Duration::from_secs(1),
+                Duration::from_secs(4)
+            ]
+        );
+        assert_eq!(policy.backoff_delay(2, 0.0), Duration::from_millis(500));
+        assert!(policy.backoff_delay(2, 0.5) < Duration::from_secs(1));
+        let j = jitter();
+        assert!((0.0..1.0).contains(&j));
+    }
 
-        if !status.is_success() || !parsed.success {
-            return Err(anyhow!(format!(
-                "Records ({status}): {} | body: {}",
-                parsed.error_message(),
-                truncate_body(&text)
-            )));
+    #[test]
+    fn retry_policy_only_replays_creates_that_never_connected() {
+        let policy = RetryPolicy::default();
+        for failure in [Failure::Connect, Failure::Timeout, Failure::ServerError] {
+            assert!(policy.should_retry_transient(failure, Replay::Always, 1));
+            assert!(!policy.should_retry_transient(failure, Replay::Always, 3));
         }
-
-        Ok(parsed
-            .result
-            .unwrap_or_default()
-            .into_iter()
-            .map(|r| DnsRecord {
-                id: r.id,
-                name: r.name,
-                record_type: r.record_type,
-                content: r.content,
-                ttl: r.ttl.unwrap_or(300),
-                proxied: r.proxied.unwrap_or(false),
-            })
-            .collect())
+        assert!(policy.should_retry_transient(Failure::Connect, Replay::BeforeResponse, 2));
+        assert!(!policy.should_retry_transient(Failure::Timeout, Replay::BeforeResponse, 1));
+        assert!(!policy.should_retry_transient(Failure::ServerError, Replay::BeforeResponse, 1));
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient_status(StatusCode::NOT_IMPLEMENTED));
     }
 
-    fn create_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        let url = format!("{}/zones/{}/dns_records", self.base_url, zone.id);
-        let response = self
-            .with_auth(self.client.post(url), account)
-            .json(&CfRecordWrite::from_record(&record))
-            .send()
-            .with_context(|| format!("Creating record {}", record.name))?;
-
-        let status = response.status();
-        let text = response.text().unwrap_or_default();
-        let parsed: CfItemResponse<CfRecord> = serde_json::from_str(&text)
-            .with_context(|| format!("Failed to parse create record response: {text}"))?;
+    #[test]
+    fn cloudflare_timeouts_name_the_setting() {
+        // Accepts connections but never answers, so only the request timeout can fire.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let timeouts = Timeouts {
+            request_secs: 1,
+            connect_secs: 1,
+        };
+        let mut backend = CloudflareBackend::with_timeouts(url, timeouts).unwrap();
+        backend.retry.transient_attempts = 1;
 
-        if !status.is_success() || !parsed.success {
-            return Err(anyhow!(format!(
-                "Create ({status}): {} | body: {}",
-                parsed.error_message(),
-                truncate_body(&text)
-            )));
-        }
+        let err = backend.list_zones(&cf_account()).unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("Request timed out after 1s (request_timeout_secs)"),
+            "{msg}"
+        );
+        drop(listener);
+    }
 
-        let result = parsed
-            .result
-            .ok_or_else(|| anyhow!("Create succeeded but missing result"))?;
-        Ok(result.into_dns_record())
+    #[test]
+    fn config_timeouts_default_and_override() {
+        assert_eq!(
+            Config::default().timeouts(),
+            Timeouts {
+                request_secs: DEFAULT_REQUEST_TIMEOUT_SECS,
+                connect_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            }
+        );
+        let config: Config =
+            serde_json::from_value(json!({"accounts": [], "request_timeout_secs": 60})).unwrap();
+        assert_eq!(config.timeouts().request_secs, 60);
+        assert_eq!(config.timeouts().connect_secs, DEFAULT_CONNECT_TIMEOUT_SECS);
+        assert!(
+            !serde_json::to_string(&Config::default())
+                .unwrap()
+                .contains("timeout")
+        );
     }
 
-    fn update_record(
-        &mut self,
-        account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        let url = format!(
-            "{}/zones/{}/dns_records/{}",
-            self.base_url, zone.id, record.id
+    #[test]
+    fn rate_stats_count_calls_in_a_rolling_window() {
+        let start = Instant::now();
+        let mut stats = RateStats::default();
+        stats.record(start, None);
+        stats.record(start + Duration::from_secs(120), None);
+        assert_eq!(
+            stats.rate_status(start + Duration::from_secs(200)),
+            "API: 2 calls / last 5m"
+        );
+        assert_eq!(
+            stats.rate_status(start + Duration::from_secs(301)),
+            "API: 1 calls / last 5m"
         );
-        let response = self
-            .with_auth(self.client.put(url), account)
-            .json(&CfRecordWrite::from_record(&record))
-            .send()
-            .with_context(|| format!("Updating record {}", record.name))?;
 
-        let status = response.status();
-        let text = response.text().unwrap_or_default();
-        let parsed: CfItemResponse<CfRecord> = serde_json::from_str(&text)
-            .with_context(|| format!("Failed to parse update record response: {text}"))?;
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit", "\"default\";r=1082;t=35".parse().unwrap());
+        stats.record(start + Duration::from_secs(302), Some(&headers));
+        assert_eq!(
+            stats.rate_status(start + Duration::from_secs(302)),
+            "API: 2 calls / last 5m, 1,082 left"
+        );
+    }
 
-        if !status.is_success() || !parsed.success {
-            return Err(anyhow!(format!(
-                "Update ({status}): {} | body: {}",
-                parsed.error_message(),
-                truncate_body(&text)
-            )));
-        }
+    #[test]
+    fn cloudflare_backend_counts_calls_for_the_status_line() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("x-ratelimit-remaining", "1199")
+            .with_body(json!({"success": true, "errors": [], "result": []}).to_string())
+            .create();
 
-        let result = parsed
-            .result
-            .ok_or_else(|| anyhow!("Update succeeded but missing result"))?;
-        Ok(result.into_dns_record())
-    }
+        let backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        let config = Config {
+            accounts: vec![cf_account()],
+            ..Config::default()
+        };
+        let app = App::new(
+            temp_config_path("rate_status"),
+            config,
+            Backend::Cloudflare(backend),
+        )
+        .unwrap();
 
-    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
-        let url = format!(
-            "{}/zones/{}/dns_records/{}",
-            self.base_url, zone.id, record_id
+        let (_, details) = app.status_message();
+        assert!(
+            details.ends_with("| API: 1 calls / last 5m, 1,199 left"),
+            "{details}"
         );
-        let response = self
-            .with_auth(self.client.delete(url), account)
-            .send()
-            .with_context(|| format!("Deleting record {}", record_id))?;
+    }
 
-        let status = response.status();
-        let text = response.text().unwrap_or_default();
-        let parsed: CfDeleteResponse = serde_json::from_str(&text)
-            .with_context(|| format!("Failed to parse delete record response: {text}"))?;
+    #[test]
+    fn retry_after_reads_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
 
-        if !status.is_success() || !parsed.success {
-            return Err(anyhow!(format!(
-                "Delete ({status}): {} | body: {}",
-                parsed.error_message(),
-                truncate_body(&text)
-            )));
+    #[test]
+    fn cloudflare_errors_propagate_context() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/zones")
+            .match_query(mockito::Matcher::Any)
+            .with_status(500)
+            .with_body(
+                json!({
+                    "success": false,
+                    "errors": [{"message": "boom"}],
+                    "result": null
+                })
+                .to_string(),
+            )
+            .create();
+
+        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
+        backend.retry.backoff_base = Duration::ZERO;
+        let err = backend.list_zones(&cf_account()).unwrap_err();
+        let msg = format!("{err}");
+        assert!(
+            msg.contains("boom") || msg.contains("Zones"),
+            "error lacked context: {msg}"
+        );
+    }
+
+    #[cfg(feature = "route53")]
+    fn route53_account(base_url: String) -> Account {
+        Account {
+            name: "aws".to_string(),
+            api_token: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            email: None,
+            account_id: None,
+            account_name: None,
+            auth_mode: AuthMode::Token,
+            base_url: Some(base_url),
+            provider: Provider::Route53,
+            aws_access_key_id: Some("AKIDEXAMPLE".to_string()),
+            zone_dir: None,
+            dns_server: None,
+            tsig_key_name: None,
+            tsig_algorithm: None,
+            zones: Vec::new(),
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl: None,
+            default_proxied: None,
+            label: None,
+            color: None,
         }
-
-        Ok(())
     }
 
-    fn zone_query(&self, account: &Account) -> Vec<(&'static str, String)> {
-        let mut params = vec![("per_page", "200".to_string())];
-        if let Some(account_id) = &account.account_id {
-            params.push(("account.id", account_id.clone()));
-        }
-        params
+    #[cfg(feature = "route53")]
+    #[test]
+    fn route53_signing_key_matches_the_aws_example() {
+        let key = route53::signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        // The signing key itself is opaque; signing the empty string with it and with
+        // the published key must agree.
+        let expected = ring::hmac::Key::new(
+            ring::hmac::HMAC_SHA256,
+            &(0..32)
+                .map(|i| {
+                    u8::from_str_radix(
+                        &"f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+                            [i * 2..i * 2 + 2],
+                        16,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<u8>>(),
+        );
+        assert_eq!(
+            ring::hmac::sign(&key, b"").as_ref(),
+            ring::hmac::sign(&expected, b"").as_ref()
+        );
     }
-}
 
-#[derive(Deserialize)]
-struct CfResponse<T> {
-    success: bool,
-    errors: Vec<CfError>,
-    result: Option<Vec<T>>,
-}
+    #[cfg(feature = "route53")]
+    #[test]
+    fn route53_sets_become_one_row_per_value() {
+        let set = route53::RecordSet::parse(
+            "<Name>\\052.example.com.</Name><Type>MX</Type><TTL>600</TTL>\
+             <ResourceRecords>\
+             <ResourceRecord><Value>10 mail.example.com.</Value></ResourceRecord>\
+             <ResourceRecord><Value>20 backup.example.com.</Value></ResourceRecord>\
+             </ResourceRecords>",
+        );
+        let rows: Vec<DnsRecord> = set.rows().collect();
 
-impl<T> CfResponse<T> {
-    fn error_message(&self) -> String {
-        self.errors
-            .first()
-            .map(|e| e.message.clone())
-            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].name, "*.example.com");
+        assert_eq!(rows[0].content, "10 mail.example.com");
+        assert_eq!(rows[1].content, "20 backup.example.com");
+        assert_eq!(rows[1].ttl, 600);
+        assert_eq!(rows[1].id, "\\052.example.com.|MX||1");
+        let (_, index) = route53::parse_row_id(&rows[1].id).unwrap();
+        assert_eq!(index, 1);
     }
-}
 
-#[derive(Deserialize)]
-struct CfItemResponse<T> {
-    success: bool,
-    errors: Vec<CfError>,
-    result: Option<T>,
-}
+    #[cfg(feature = "route53")]
+    #[test]
+    fn route53_lists_zones_and_records() {
+        let mut server = mockito::Server::new();
+        let _zones = server
+            .mock("GET", "/2013-04-01/hostedzone")
+            .match_query(mockito::Matcher::Any)
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".into()),
+            )
+            .with_body(
+                "<ListHostedZonesResponse><HostedZones>\
+                 <HostedZone><Id>/hostedzone/Z1</Id><Name>example.com.</Name></HostedZone>\
+                 </HostedZones><IsTruncated>false</IsTruncated></ListHostedZonesResponse>",
+            )
+            .create();
+        let _records = server
+            .mock("GET", "/2013-04-01/hostedzone/Z1/rrset")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                "<ListResourceRecordSetsResponse><ResourceRecordSets>\
+                 <ResourceRecordSet><Name>www.example.com.</Name><Type>A</Type><TTL>300</TTL>\
+                 <ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord>\
+                 <ResourceRecord><Value>192.0.2.2</Value></ResourceRecord></ResourceRecords>\
+                 </ResourceRecordSet></ResourceRecordSets>\
+                 <IsTruncated>false</IsTruncated></ListResourceRecordSetsResponse>",
+            )
+            .create();
 
-impl<T> CfItemResponse<T> {
-    fn error_message(&self) -> String {
-        self.errors
-            .first()
-            .map(|e| e.message.clone())
-            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+        let account = route53_account(server.url());
+        let mut backend = route53::Route53Backend::new(Timeouts::default()).unwrap();
+        let zones = backend.list_zones(&account).unwrap().items;
+        assert_eq!(zones[0].id, "Z1");
+        assert_eq!(zones[0].name, "example.com");
+
+        let page = backend
+            .list_records_page(&account, &zones[0], 1, None)
+            .unwrap();
+        let contents: Vec<&str> = page.items.iter().map(|r| r.content.as_str()).collect();
+        assert_eq!(contents, ["192.0.2.1", "192.0.2.2"]);
+        assert_eq!(page.total_pages, 1);
     }
-}
 
-#[derive(Deserialize)]
-struct CfDeleteResponse {
-    success: bool,
-    errors: Vec<CfError>,
-}
+    #[cfg(feature = "route53")]
+    #[test]
+    fn route53_delete_keeps_the_other_values_of_a_set() {
+        let mut server = mockito::Server::new();
+        let _set = server
+            .mock("GET", "/2013-04-01/hostedzone/Z1/rrset")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                "<ListResourceRecordSetsResponse><ResourceRecordSets>\
+                 <ResourceRecordSet><Name>www.example.com.</Name><Type>A</Type><TTL>300</TTL>\
+                 <ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord>\
+                 <ResourceRecord><Value>192.0.2.2</Value></ResourceRecord></ResourceRecords>\
+                 </ResourceRecordSet></ResourceRecordSets>\
+                 <IsTruncated>false</IsTruncated></ListResourceRecordSetsResponse>",
+            )
+            .create();
+        let change = server
+            .mock("POST", "/2013-04-01/hostedzone/Z1/rrset/")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("<Action>UPSERT</Action>".into()),
+                mockito::Matcher::Regex("<Value>192.0.2.2</Value>".into()),
+            ]))
+            .with_body("<ChangeResourceRecordSetsResponse/>")
+            .expect(1)
+            .create();
 
-impl CfDeleteResponse {
-    fn error_message(&self) -> String {
-        self.errors
-            .first()
-            .map(|e| e.message.clone())
-            .unwrap_or_else(|| "Unknown Cloudflare API error".to_string())
+        let account = route53_account(server.url());
+        let zone = Zone {
+            id: "Z1".to_string(),
+            name: "example.com".to_string(),
+            ..Zone::default()
+        };
+        let mut backend = route53::Route53Backend::new(Timeouts::default()).unwrap();
+        backend
+            .delete_record(&account, &zone, "www.example.com.|A||0")
+            .unwrap();
+        change.assert();
     }
-}
 
-fn truncate_body(text: &str) -> String {
-    const LIMIT: usize = 200;
-    if text.len() > LIMIT {
-        format!("{}...", &text[..LIMIT])
-    } else {
-        text.to_string()
+    #[cfg(not(feature = "route53"))]
+    #[test]
+    fn route53_accounts_need_the_feature() {
+        let mut providers = Providers::default().with(Provider::Cloudflare, MockBackend::new());
+        let account = Account {
+            provider: Provider::Route53,
+            ..test_account()
+        };
+        let err = providers.list_zones(&account).unwrap_err().to_string();
+        assert!(err.contains("--features route53"), "{err}");
     }
-}
-
-#[derive(Deserialize)]
-struct CfError {
-    message: String,
-}
 
-#[derive(Deserialize)]
-struct CfZone {
-    id: String,
-    name: String,
-}
+    fn do_account(base_url: String) -> Account {
+        Account {
+            name: "droplets".to_string(),
+            api_token: "do-token".to_string(),
+            email: None,
+            account_id: None,
+            account_name: None,
+            auth_mode: AuthMode::Token,
+            base_url: Some(base_url),
+            provider: Provider::DigitalOcean,
+            aws_access_key_id: None,
+            zone_dir: None,
+            dns_server: None,
+            tsig_key_name: None,
+            tsig_algorithm: None,
+            zones: Vec::new(),
+            token_ref: None,
+            token_cmd: None,
+            token_error: None,
+            default_ttl: None,
+            default_proxied: None,
+            label: None,
+            color: None,
+        }
+    }
 
-#[derive(Deserialize)]
-struct CfRecord {
-    id: String,
-    name: String,
-    #[serde(rename = "type")]
-    record_type: String,
-    content: String,
-    ttl: Option<u32>,
-    proxied: Option<bool>,
-}
+    #[test]
+    fn digitalocean_follows_links_pages() {
+        let mut server = mockito::Server::new();
+        let next = format!("{}/domains?page=2&per_page=200", server.url());
+        let _first = server
+            .mock("GET", "/domains")
+            .match_header("authorization", "Bearer do-token")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_body(
+                json!({
+                    "domains": [{"name": "example.com", "ttl": 1800}],
+                    "links": {"pages": {"next": next, "last": next}},
+                    "meta": {"total": 2}
+                })
+                .to_string(),
+            )
+            .create();
+        let _second = server
+            .mock("GET", "/domains")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(
+                json!({
+                    "domains": [{"name": "demo.net", "ttl": 1800}],
+                    "links": {"pages": {"first": "…", "prev": "…"}},
+                    "meta": {"total": 2}
+                })
+                .to_string(),
+            )
+            .create();
 
-impl CfRecord {
-    fn into_dns_record(self) -> DnsRecord {
-        DnsRecord {
-            id: self.id,
-            name: self.name,
-            record_type: self.record_type,
-            content: self.content,
-            ttl: self.ttl.unwrap_or(300),
-            proxied: self.proxied.unwrap_or(false),
-        }
+        let mut backend = digitalocean::DigitalOceanBackend::new(Timeouts::default()).unwrap();
+        let listing = backend.list_zones(&do_account(server.url())).unwrap();
+        let names: Vec<&str> = listing.items.iter().map(|z| z.name.as_str()).collect();
+        assert_eq!(names, ["example.com", "demo.net"]);
+        assert_eq!(listing.pages, 2);
     }
-}
 
-#[derive(Serialize)]
-struct CfRecordWrite {
-    name: String,
-    #[serde(rename = "type")]
-    record_type: String,
-    content: String,
-    ttl: u32,
-    proxied: bool,
-}
+    #[test]
+    fn digitalocean_records_get_full_names_and_string_ids() {
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("GET", "/domains/example.com/records")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                json!({
+                    "domain_records": [
+                        {"id": 28448429, "type": "A", "name": "@", "data": "192.0.2.1", "priority": null, "port": null, "ttl": 1800, "weight": null},
+                        {"id": 28448430, "type": "MX", "name": "@", "data": "mail.example.com.", "priority": 10, "port": null, "ttl": 1800, "weight": null},
+                        {"id": 28448431, "type": "CNAME", "name": "www", "data": "@", "priority": null, "port": null, "ttl": 3600, "weight": null}
+                    ],
+                    "links": {},
+                    "meta": {"total": 3}
+                })
+                .to_string(),
+            )
+            .create();
 
-impl CfRecordWrite {
-    fn from_record(record: &DnsRecord) -> Self {
-        Self {
-            name: record.name.clone(),
-            record_type: record.record_type.clone(),
-            content: record.content.clone(),
-            ttl: record.ttl,
-            proxied: record.proxied,
-        }
+        let mut backend = digitalocean::DigitalOceanBackend::new(Timeouts::default()).unwrap();
+        let zone = Zone {
+            id: "example.com".to_string(),
+            name: "example.com".to_string(),
+            ..Zone::default()
+        };
+        let page = backend
+            .list_records_page(&do_account(server.url()), &zone, 1, None)
+            .unwrap();
+
+        assert_eq!(page.total_pages, 1);
+        assert_eq!(page.items[0].id, "28448429");
+        assert_eq!(page.items[0].name, "example.com");
+        assert_eq!(page.items[1].content, "10 mail.example.com");
+        assert_eq!(page.items[2].name, "www.example.com");
+        assert_eq!(page.items[2].content, "example.com");
     }
-}
 
-struct MockBackend {
-    records: HashMap<String, Vec<DnsRecord>>,
-}
+    #[test]
+    fn digitalocean_writes_relative_names_and_split_priority() {
+        let mut server = mockito::Server::new();
+        let m = server
+            .mock("POST", "/domains/example.com/records")
+            .match_body(mockito::Matcher::Json(json!({
+                "type": "MX",
+                "name": "@",
+                "data": "mail.example.com.",
+                "ttl": 300,
+                "priority": 10
+            })))
+            .with_status(201)
+            .with_body(
+                json!({
+                    "domain_record": {"id": 7, "type": "MX", "name": "@", "data": "mail.example.com.", "priority": 10, "ttl": 300}
+                })
+                .to_string(),
+            )
+            .create();
 
-impl MockBackend {
-    fn new() -> Self {
-        Self {
-            records: HashMap::new(),
-        }
+        let mut backend = digitalocean::DigitalOceanBackend::new(Timeouts::default()).unwrap();
+        let zone = Zone {
+            id: "example.com".to_string(),
+            name: "example.com".to_string(),
+            ..Zone::default()
+        };
+        let created = backend
+            .create_record(
+                &do_account(server.url()),
+                &zone,
+                record("", "example.com", "MX", "10 mail.example.com"),
+            )
+            .unwrap();
+
+        m.assert();
+        assert_eq!(created.id, "7");
+        assert_eq!(created.content, "10 mail.example.com");
     }
 
-    fn ensure_zone(&mut self, zone: &Zone) {
-        self.records.entry(zone.id.clone()).or_insert_with(|| {
-            vec![
-                DnsRecord {
-                    id: format!("{}-a", zone.id),
-                    name: format!("api.{}", zone.name),
-                    record_type: "A".to_string(),
-                    content: "203.0.113.10".to_string(),
-                    ttl: 300,
-                    proxied: true,
-                },
-                DnsRecord {
-                    id: format!("{}-b", zone.id),
-                    name: format!("cdn.{}", zone.name),
-                    record_type: "CNAME".to_string(),
-                    content: "edge.service.net".to_string(),
-                    ttl: 120,
-                    proxied: true,
-                },
-                DnsRecord {
-                    id: format!("{}-c", zone.id),
-                    name: format!("mail.{}", zone.name),
-                    record_type: "MX".to_string(),
-                    content: "mail.{zone}".replace("{zone}", &zone.name),
-                    ttl: 3600,
-                    proxied: false,
-                },
-            ]
-        });
+    #[test]
+    fn proxied_column_only_applies_to_cloudflare_accounts() {
+        let mut app = app_with_records("do_proxy_column", Vec::new());
+        assert!(app.has_proxy());
+        app.accounts[0].provider = Provider::DigitalOcean;
+        assert!(!app.has_proxy());
     }
-}
 
-impl DnsBackend for MockBackend {
-    fn list_zones(&mut self, account: &Account) -> Result<Vec<Zone>> {
-        // Generate deterministic mock zones based on account name so the UI feels connected.
-        let base = account.name.replace(' ', "").to_lowercase();
-        let zones = vec![
-            Zone {
-                id: format!("{}-01", base),
-                name: format!("{}.example.com", base),
-            },
-            Zone {
-                id: format!("{}-02", base),
-                name: format!("{}.services.io", base),
+    #[test]
+    fn written_zone_files_parse_back_unchanged() {
+        let records = vec![
+            record("", "example.com", "MX", "10 mail.example.com"),
+            record("", "www.example.com", "CNAME", "example.com"),
+            record(
+                "",
+                "example.com",
+                "TXT",
+                "v=spf1 include:_spf.example.net ~all",
+            ),
+            record("", "other.org", "A", "192.0.2.7"),
+            DnsRecord {
+                proxied: true,
+                ..record("", "cdn.example.com", "A", "192.0.2.8")
             },
         ];
-        Ok(zones)
+        let text = bind::write_zone("example.com", &records);
+        assert!(text.contains("www\t300\tIN\tCNAME\texample.com."), "{text}");
+        assert!(
+            text.contains("192.0.2.8 ; cf_tags=cf-proxied:true"),
+            "{text}"
+        );
+
+        let parsed = bind::parse_zone(&text, "example.com").unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn txt_data_round_trips_quoted_or_not() {
+        let contents = [
+            r#""v=spf1 -all""#,
+            r#"say "hi""#,
+            r#""say \"hi\"""#,
+            r#""part one" "part two""#,
+            r"back\slash",
+        ];
+        let records: Vec<DnsRecord> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let name = format!("t{i}.demo.example.com");
+                record(&(i + 1).to_string(), &name, "TXT", content)
+            })
+            .collect();
+        let text = bind::write_zone("demo.example.com", &records);
+        let rdata: Vec<&str> = text
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').nth(4).unwrap())
+            .collect();
+        assert_eq!(
+            rdata,
+            [
+                r#""v=spf1 -all""#,
+                r#""say \"hi\"""#,
+                r#""say \"hi\"""#,
+                r#""part one" "part two""#,
+                r#""back\\slash""#,
+            ]
+        );
+        let parsed: Vec<String> = bind::parse_zone(&text, "demo.example.com")
+            .unwrap()
+            .into_iter()
+            .map(|record| record.content)
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                "v=spf1 -all",
+                r#"say "hi""#,
+                r#"say "hi""#,
+                r#""part one" "part two""#,
+                r"back\slash",
+            ]
+        );
+
+        // Importing the export back into the zone it came from changes nothing.
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let dir = temp_config_path("txt_round_trip").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("demo.zone");
+        fs::write(&file, &text).unwrap();
+        let mut backend = MockBackend::new();
+        backend.records.insert("demo-01".to_string(), records);
+        let line = [
+            "import",
+            "--zone",
+            "demo.example.com",
+            "--file",
+            file.to_str().unwrap(),
+            "--replace",
+            "--dry-run",
+        ];
+        let journal = Journal::for_config(&dir.join("accounts.json"));
+        let (out, notes) = run_subcommand(&config, &mut backend, &journal, &line).unwrap();
+        assert_eq!(out, "");
+        assert!(notes.ends_with("0 to delete, 5 unchanged\n"), "{notes}");
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    fn list_records(&mut self, _account: &Account, zone: &Zone) -> Result<Vec<DnsRecord>> {
-        self.ensure_zone(zone);
-        Ok(self.records.get(&zone.id).cloned().unwrap_or_default())
+    #[test]
+    fn soa_serials_move_to_today_or_increment() {
+        assert_eq!(bind::bump_serial(2024010102, (2026, 10, 15)), 2026101500);
+        assert_eq!(bind::bump_serial(2026101500, (2026, 10, 15)), 2026101501);
+        assert_eq!(bind::bump_serial(41, (2026, 10, 15)), 42);
     }
 
-    fn create_record(
-        &mut self,
-        _account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        self.ensure_zone(zone);
-        let records = self.records.entry(zone.id.clone()).or_default();
-        let mut new_record = record;
-        new_record.id = format!("{}-{}", zone.id, records.len() + 1);
-        records.push(new_record.clone());
-        Ok(new_record)
+    #[test]
+    fn zone_files_are_named_by_suffix_or_db_prefix() {
+        assert_eq!(
+            zonefile::zone_name("example.com.zone").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            zonefile::zone_name("db.example.net").as_deref(),
+            Some("example.net")
+        );
+        assert_eq!(zonefile::zone_name("README"), None);
+        assert_eq!(zonefile::zone_name(".example.com.zone.tmp"), None);
     }
 
-    fn update_record(
-        &mut self,
-        _account: &Account,
-        zone: &Zone,
-        record: DnsRecord,
-    ) -> Result<DnsRecord> {
-        self.ensure_zone(zone);
-        if let Some(records) = self.records.get_mut(&zone.id)
-            && let Some(existing) = records.iter_mut().find(|r| r.id == record.id)
-        {
-            *existing = record.clone();
-        }
-        Ok(record)
-    }
+    #[test]
+    fn zone_file_edits_keep_ids_and_bump_the_serial() {
+        let dir = temp_config_path("zone_dir").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("example.com.zone"),
+            "$ORIGIN example.com.\n$TTL 3600\n\
+             @ IN SOA ns1 hostmaster 2020010100 3600 900 1209600 300\n\
+             www 300 IN A 192.0.2.1\n",
+        )
+        .unwrap();
+        let account = Account {
+            provider: Provider::ZoneFile,
+            zone_dir: Some(dir.display().to_string()),
+            ..test_account()
+        };
+        let mut backend = zonefile::ZoneFileBackend::default();
 
-    fn delete_record(&mut self, _account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
-        if let Some(records) = self.records.get_mut(&zone.id) {
-            records.retain(|r| r.id != record_id);
-        }
-        Ok(())
+        let zones = backend.list_zones(&account).unwrap().items;
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].name, "example.com");
+        let records = backend
+            .list_records_page(&account, &zones[0], 1, None)
+            .unwrap()
+            .items;
+        let www = records.iter().find(|r| r.record_type == "A").unwrap();
+
+        let mut edited = www.clone();
+        edited.content = "192.0.2.2".to_string();
+        let updated = backend.update_record(&account, &zones[0], edited).unwrap();
+        assert_eq!(updated.id, www.id);
+        let created = backend
+            .create_record(
+                &account,
+                &zones[0],
+                record("", "api.example.com", "A", "192.0.2.3"),
+            )
+            .unwrap();
+
+        let text = fs::read_to_string(dir.join("example.com.zone")).unwrap();
+        assert!(text.contains("www\t300\tIN\tA\t192.0.2.2"), "{text}");
+        assert!(text.contains("api\t300\tIN\tA\t192.0.2.3"), "{text}");
+        assert!(!text.contains("2020010100"), "serial not bumped: {text}");
+        assert_eq!(
+            backend
+                .get_record(&account, &zones[0], &created.id)
+                .unwrap()
+                .content,
+            "192.0.2.3"
+        );
+
+        backend
+            .delete_record(&account, &zones[0], &updated.id)
+            .unwrap();
+        let text = fs::read_to_string(dir.join("example.com.zone")).unwrap();
+        assert!(!text.contains("192.0.2.2"), "{text}");
+        fs::remove_dir_all(&dir).ok();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-    use std::collections::HashMap;
-    use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    #[test]
+    fn account_form_builds_rfc2136_accounts() {
+        let mut form = AccountForm {
+            name: "bind".to_string(),
+            api_token: "c2VjcmV0".to_string(),
+            ..AccountForm::default()
+        };
+        form.field_index = AccountForm::AUTH_MODE_FIELD;
+        form.insert_char('p');
+        assert!(form.is_rfc2136());
+        assert_eq!(form.submit_from_field(), 5);
+        assert_eq!(
+            form.build_account().unwrap_err(),
+            "TSIG key name and server address are required"
+        );
 
-    fn temp_config_path(name: &str) -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        std::env::temp_dir().join(format!("cloudflare_tui_test_{name}_{nanos}.json"))
+        form.tsig_key_name = "nyxflare-key".to_string();
+        form.dns_server = "10.0.0.53".to_string();
+        form.zones = "example.com, internal.example".to_string();
+        let account = form.build_account().unwrap();
+        assert_eq!(account.provider, Provider::Rfc2136);
+        assert_eq!(account.zones, ["example.com", "internal.example"]);
+        assert_eq!(account.tsig_algorithm, None);
+
+        form.toggle_auth_mode();
+        assert!(!form.is_rfc2136());
+        assert_eq!(form.auth_mode, AuthMode::Token);
     }
 
-    fn test_account() -> Account {
-        Account {
-            name: "demo".to_string(),
-            api_token: "token".to_string(),
-            email: None,
-            account_id: None,
-            auth_mode: AuthMode::Token,
+    #[cfg(feature = "rfc2136")]
+    #[test]
+    fn rfc2136_rdata_round_trips() {
+        let cases = [
+            ("A", "192.0.2.1"),
+            ("AAAA", "2001:db8::1"),
+            ("CNAME", "example.com"),
+            ("MX", "10 mail.example.com"),
+            ("SRV", "10 5 5060 sip.example.com"),
+            ("TXT", "v=spf1 -all"),
+            ("TXT", "\"part one\" \"part two\""),
+            ("CAA", "0 issue \"letsencrypt.org\""),
+            ("TYPE65", "\\# 3 010203"),
+        ];
+        let mut message = rfc2136::Message::new(0);
+        message.question("example.com", 6);
+        for (record_type, content) in cases {
+            let rdata = rfc2136::encode_rdata(record_type, content).unwrap();
+            let code = match record_type {
+                "A" => 1,
+                "AAAA" => 28,
+                "CNAME" => 5,
+                "MX" => 15,
+                "SRV" => 33,
+                "TXT" => 16,
+                "CAA" => 257,
+                _ => 65,
+            };
+            message.record("www.example.com", code, 1, 300, &rdata);
         }
+
+        let parsed = rfc2136::parse_answers(&message.finish_as_answer()).unwrap();
+        let contents: Vec<(&str, &str)> = parsed
+            .iter()
+            .map(|r| (r.record_type.as_str(), r.content.as_str()))
+            .collect();
+        assert_eq!(contents, cases);
+        assert_eq!(parsed[3].id, "www.example.com|MX|10 mail.example.com");
     }
 
-    fn record(id: &str, name: &str, record_type: &str, content: &str) -> DnsRecord {
-        DnsRecord {
-            id: id.to_string(),
-            name: name.to_string(),
-            record_type: record_type.to_string(),
-            content: content.to_string(),
-            ttl: 300,
-            proxied: false,
-        }
+    #[cfg(feature = "rfc2136")]
+    #[test]
+    fn rfc2136_tsig_signatures_verify_and_detect_tampering() {
+        let key = rfc2136::TsigKey::new("nyxflare-key", "hmac-sha256", "c2VjcmV0LWtleQ==").unwrap();
+        let mut message = rfc2136::Message::new(5 << 11);
+        message.question("example.com", 6);
+        let mut signed = message.finish();
+        key.sign(&mut signed, 1_700_000_000);
+        assert!(key.verify(&signed));
+
+        let other = rfc2136::TsigKey::new("nyxflare-key", "hmac-sha256", "b3RoZXI=").unwrap();
+        assert!(!other.verify(&signed));
+        signed[13] ^= 1;
+        assert!(!key.verify(&signed));
     }
 
-    fn app_with_records(test_name: &str, records: Vec<DnsRecord>) -> App<MockBackend> {
-        let mut backend = MockBackend {
-            records: HashMap::new(),
+    #[cfg(feature = "rfc2136")]
+    #[test]
+    fn rfc2136_transfers_and_updates_over_tcp() {
+        use std::io::Write as _;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let key = || rfc2136::TsigKey::new("nyxflare-key", "hmac-sha256", "c2VjcmV0").unwrap();
+        let reply = |records: &[(&str, u16, &str, &str)]| {
+            let mut message = rfc2136::Message::new(0x8400);
+            message.question("example.com", 252);
+            for (name, code, record_type, content) in records {
+                let rdata = rfc2136::encode_rdata(record_type, content).unwrap();
+                message.record(name, *code, 1, 300, &rdata);
+            }
+            let message = message.finish_as_answer();
+            let mut framed = (message.len() as u16).to_be_bytes().to_vec();
+            framed.extend(message);
+            framed
         };
-        backend
-            .records
-            .insert("demo-01".to_string(), records.clone());
+        let soa = (
+            "example.com",
+            6,
+            "SOA",
+            "ns1.example.com. hostmaster.example.com. 1 3600 900 1209600 300",
+        );
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for responses in [
+                vec![
+                    reply(&[soa, ("www.example.com", 1, "A", "192.0.2.1")]),
+                    reply(&[("api.example.com", 1, "A", "192.0.2.2"), soa]),
+                ],
+                vec![reply(&[])],
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let request = rfc2136::read_message(&mut stream).unwrap();
+                assert!(key().verify(&request), "request was not signed");
+                requests.push(request);
+                for response in responses {
+                    stream.write_all(&response).unwrap();
+                }
+            }
+            requests
+        });
 
-        let mut app = App::new(temp_config_path(test_name), vec![test_account()], backend).unwrap();
-        app.records = records;
-        app.record_page = 0;
-        app.selected_record = 0;
-        app
+        let account = Account {
+            provider: Provider::Rfc2136,
+            api_token: "c2VjcmV0".to_string(),
+            dns_server: Some(format!("127.0.0.1:{port}")),
+            tsig_key_name: Some("nyxflare-key".to_string()),
+            zones: vec!["example.com".to_string()],
+            ..test_account()
+        };
+        let mut backend = rfc2136::Rfc2136Backend::new(Timeouts::default());
+        let zone = backend.list_zones(&account).unwrap().items.remove(0);
+        let records = backend
+            .list_records_page(&account, &zone, 1, None)
+            .unwrap()
+            .items;
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["example.com", "www.example.com", "api.example.com"]);
+
+        let mut edited = records[1].clone();
+        edited.content = "192.0.2.9".to_string();
+        let updated = backend.update_record(&account, &zone, edited).unwrap();
+        assert_eq!(updated.id, "www.example.com|A|192.0.2.9");
+
+        let requests = server.join().unwrap();
+        let update = &requests[1];
+        assert_eq!(update[2] >> 3, 5, "not an UPDATE");
+        assert_eq!(u16::from_be_bytes([update[8], update[9]]), 2);
+        let removed = [192, 0, 2, 1];
+        let added = [192, 0, 2, 9];
+        assert!(update.windows(4).any(|w| w == removed));
+        assert!(update.windows(4).any(|w| w == added));
     }
 
     #[test]
-    fn add_account_form_allows_typing_command_keys() {
-        let mut app = App::new(temp_config_path("add_form"), vec![], MockBackend::new()).unwrap();
-        let quit = handle_add_account_key(KeyCode::Char('q'), &mut app).unwrap();
+    fn accounts_are_served_by_their_own_provider() {
+        let mut droplets = MockBackend::new();
+        droplets.created_zones.insert(
+            "droplets".to_string(),
+            vec![Zone {
+                id: "do-1".to_string(),
+                name: "droplets-only.net".to_string(),
+                ..Zone::default()
+            }],
+        );
+        let providers = Providers::default()
+            .with(Provider::Cloudflare, MockBackend::new())
+            .with(Provider::DigitalOcean, droplets);
+        let config = Config {
+            accounts: vec![
+                test_account(),
+                Account {
+                    name: "droplets".to_string(),
+                    provider: Provider::DigitalOcean,
+                    ..test_account()
+                },
+            ],
+            ..Config::default()
+        };
+        let mut app = App::new(temp_config_path("providers"), config, providers).unwrap();
+        let zone_names = |app: &App<Providers>| -> Vec<String> {
+            app.zones.iter().map(|z| z.name.clone()).collect()
+        };
+        assert!(!zone_names(&app).contains(&"droplets-only.net".to_string()));
 
-        assert!(!quit, "q should not quit while typing");
-        if let Mode::AddingAccount(form) = app.mode {
-            assert_eq!(form.name, "q");
-        } else {
-            panic!("app left add account mode");
-        }
+        app.next_account().unwrap();
+        assert!(zone_names(&app).contains(&"droplets-only.net".to_string()));
+
+        app.previous_account().unwrap();
+        app.refresh_current().unwrap();
+        assert!(!zone_names(&app).contains(&"droplets-only.net".to_string()));
     }
 
     #[test]
-    fn normal_mode_can_start_add_account_when_accounts_exist() {
-        let mut app = app_with_records("add_account", vec![]);
-        let quit = handle_normal_key(KeyCode::Char('a'), &mut app).unwrap();
+    fn fixture_edits_are_written_back_and_reloaded() {
+        let path = temp_config_path("fixture");
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/demo.json"),
+            &path,
+        )
+        .unwrap();
+        let account = test_account();
 
-        assert!(!quit, "a should not quit the app");
-        assert!(
-            matches!(app.mode, Mode::AddingAccount(_)),
-            "app should enter add-account mode"
+        let mut mock = MockBackend::new_from_file(&path).unwrap();
+        let zones = mock.list_zones(&account).unwrap().items;
+        let names: Vec<&str> = zones.iter().map(|z| z.name.as_str()).collect();
+        assert_eq!(names, ["acme.test", "staging.acme.test"]);
+        let mut www = mock.get_record(&account, &zones[0], "acme-www").unwrap();
+        www.content = "edge.acme.test".to_string();
+        mock.update_record(&account, &zones[0], www).unwrap();
+        mock.delete_record(&account, &zones[1], "staging-api")
+            .unwrap();
+
+        let mut reloaded = MockBackend::new_from_file(&path).unwrap();
+        assert_eq!(
+            reloaded
+                .get_record(&account, &zones[0], "acme-www")
+                .unwrap()
+                .content,
+            "edge.acme.test"
         );
+        let staging = reloaded
+            .list_records_page(&account, &zones[1], 1, None)
+            .unwrap();
+        assert!(staging.items.is_empty());
+        fs::remove_file(&path).ok();
     }
 
     #[test]
-    fn record_form_allows_typing_command_keys() {
-        let mut app = app_with_records("record_form", vec![]);
-        app.start_record_form(false);
-
-        let quit = handle_record_form_key(KeyCode::Char('q'), &mut app).unwrap();
-        assert!(!quit, "q should not quit while editing a record");
+    fn failure_rules_parse_and_pick_their_calls() {
+        let mut mock = MockBackend::new()
+            .with_failures("create_record:next 2:Quota exceeded; list_zones:every 3:Timed out")
+            .unwrap();
+        let picked = |mock: &mut MockBackend, operation: &str| -> Vec<bool> {
+            (0..6).map(|_| mock.inject(operation).is_err()).collect()
+        };
+        assert_eq!(
+            picked(&mut mock, "create_record"),
+            [true, true, false, false, false, false]
+        );
+        assert_eq!(
+            picked(&mut mock, "list_zones"),
+            [false, false, true, false, false, true]
+        );
+        assert!(mock.inject("delete_record").is_ok());
+        let err = MockBackend::new()
+            .with_failures("create_record:Quota exceeded")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("operation:when:message"), "{err}");
+        assert!(FailureRule::parse("list_zones:every 0:x").is_err());
+        assert!(FailureRule::parse("list_zones:sometimes:x").is_err());
 
-        if let Mode::RecordForm(form) = app.mode {
-            assert_eq!(form.draft.name, "q");
-        } else {
-            panic!("app left record form mode");
-        }
+        let path = temp_config_path("fixture_failures");
+        fs::write(
+            &path,
+            json!({
+                "accounts": [],
+                "failures": [
+                    {"operation": "verify_account", "message": "Token revoked", "when": {"next": 1}}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let mut mock = MockBackend::new_from_file(&path).unwrap();
+        let err = mock.verify_account(&test_account()).unwrap_err();
+        assert_eq!(err.to_string(), "Token revoked");
+        assert!(mock.verify_account(&test_account()).is_ok());
+        fs::remove_file(&path).ok();
     }
 
     #[test]
-    fn search_overlay_allows_typing_command_keys() {
-        let mut app = app_with_records("search_overlay", vec![]);
-        app.mode = Mode::Searching(String::new());
+    fn generated_zones_filter_ten_thousand_records_quickly() {
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app = App::new(
+            temp_config_path("generated"),
+            config,
+            MockBackend::generating(10_000),
+        )
+        .unwrap();
+        assert_eq!(app.records.len(), 10_000);
+        let types: HashSet<&str> = app.records.iter().map(|r| r.record_type.as_str()).collect();
+        assert_eq!(types.len(), 5, "{types:?}");
+        let ttls: HashSet<u32> = app.records.iter().map(|r| r.ttl).collect();
+        assert!(ttls.len() > 1);
 
-        let quit = handle_search_key(KeyCode::Char('q'), &mut app).unwrap();
-        assert!(!quit, "q should not quit while searching");
+        app.record_filter = "mail".to_string();
+        let started = Instant::now();
+        let filtered = app.filtered_records().len();
+        let page = app.paged_records().len();
+        let elapsed = started.elapsed();
+        assert_eq!(filtered, 10_000 / 8, "one name in eight starts with mail");
+        assert_eq!(page, app.page_size().min(filtered));
+        assert!(
+            elapsed < Duration::from_millis(500),
+            "filtering 10k records took {elapsed:?}"
+        );
+    }
 
-        if let Mode::Searching(text) = app.mode {
-            assert_eq!(text, "q");
-        } else {
-            panic!("app left search mode");
-        }
+    #[test]
+    fn mock_latency_delays_calls_but_not_the_ui() {
+        let mut mock = MockBackend::new().with_latency("40").unwrap();
+        let started = Instant::now();
+        mock.list_zones(&test_account()).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        assert!(MockBackend::new().with_latency("soon").is_err());
+
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let started = Instant::now();
+        let mut app = App::threaded(temp_config_path("latency"), config, mock).unwrap();
+        app.tick().unwrap();
+        assert!(app.is_busy());
+        assert!(started.elapsed() < Duration::from_millis(40));
+        wait_until_idle(&mut app);
+        assert_eq!(app.records.len(), 3);
     }
 
     #[test]
-    fn filtered_records_matches_across_fields() {
-        let records = vec![
-            record("1", "api.demo.example.com", "A", "203.0.113.1"),
-            record("2", "cdn.demo.example.com", "CNAME", "api.demo.example.com"),
-            record("3", "_acme.demo.example.com", "TXT", "challenge-token"),
-        ];
-        let mut app = app_with_records("filter", records);
+    fn action_calls_run_off_the_ui_thread_one_at_a_time() {
+        let mock = MockBackend::new().with_latency("200").unwrap();
+        let config = Config {
+            accounts: vec![test_account()],
+            ..Config::default()
+        };
+        let mut app = App::threaded(temp_config_path("latency_calls"), config, mock).unwrap();
+        wait_until_idle(&mut app);
+        app.focus = Focus::Zones;
 
-        app.record_filter = "acme".to_string();
-        let filtered = app.filtered_records();
-        assert_eq!(filtered.len(), 1);
-        assert_eq!(filtered[0].record_type, "TXT");
+        let started = Instant::now();
+        handle_key(KeyCode::Char('D'), &mut app).unwrap();
+        handle_key(KeyCode::Char('v'), &mut app).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(
+            app.last_message,
+            "Still busy: Counting the records in demo.example.com"
+        );
+        assert_eq!(app.mode, Mode::Normal);
 
-        app.record_filter = "api.demo".to_string();
-        let filtered = app.filtered_records();
-        assert_eq!(filtered.len(), 2);
-        assert!(filtered.iter().any(|r| r.record_type == "A"));
-        assert!(filtered.iter().any(|r| r.record_type == "CNAME"));
+        wait_until_idle(&mut app);
+        let Mode::ConfirmDeleteZone(confirm) = &app.mode else {
+            panic!("zone delete confirmation did not open");
+        };
+        assert_eq!(confirm.record_count, 3);
     }
 
     #[test]
-    fn paged_records_respects_page_and_size() {
-        let records = (1..=5)
-            .map(|i| record(&i.to_string(), &format!("r{i}.demo"), "A", "127.0.0.1"))
-            .collect();
-        let mut app = app_with_records("paging", records);
-
-        app.update_record_page_size(5); // yields a page size of 2
+    fn wrangler_config_files_are_read_from_their_fixtures() {
+        let fixture = |name: &str| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/wrangler");
+            fs::read_to_string(path.join(name)).unwrap()
+        };
+        let token = credentials::parse_wrangler_config(&fixture("api-token.toml")).unwrap();
+        assert_eq!(token.name, "wrangler");
+        assert_eq!(token.api_token, "wrangler-fixture-token-0001");
+        assert_eq!(token.auth_mode, AuthMode::Token);
+        assert_eq!(token.email, None);
 
-        app.record_page = 0;
-        let first_page = app.paged_records();
-        assert_eq!(first_page.len(), 2);
-        assert_eq!(first_page[0].name, "r1.demo");
+        let key = credentials::parse_wrangler_config(&fixture("global-key.toml")).unwrap();
+        assert_eq!(key.api_token, "wrangler-fixture-global-key-0002");
+        assert_eq!(key.auth_mode, AuthMode::GlobalKey);
+        assert_eq!(key.email.as_deref(), Some("ops@example.com"));
 
-        app.record_page = 1;
-        let second_page = app.paged_records();
-        assert_eq!(second_page.len(), 2);
-        assert_eq!(second_page[0].name, "r3.demo");
+        let err = credentials::parse_wrangler_config(&fixture("oauth.toml"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("`wrangler login` session"), "{err}");
+        assert!(credentials::parse_wrangler_config("api_key = \"k\"").is_err());
+        assert!(credentials::parse_wrangler_config("api_token = [").is_err());
 
-        app.record_page = 2;
-        let final_page = app.paged_records();
-        assert_eq!(final_page.len(), 1);
-        assert_eq!(final_page[0].name, "r5.demo");
+        let home = Path::new("/home/me");
+        let paths = credentials::wrangler_config_paths(Some(home), |_| None);
+        assert_eq!(paths[0], home.join(".wrangler/config/default.toml"));
+        assert_eq!(paths[1], home.join(".config/.wrangler/config/default.toml"));
+        let xdg = credentials::wrangler_config_paths(None, |name| {
+            (name == "XDG_CONFIG_HOME").then(|| "/xdg".to_string())
+        });
+        assert_eq!(xdg, [PathBuf::from("/xdg/.wrangler/config/default.toml")]);
     }
 
     #[test]
-    fn ensure_record_visible_moves_page_to_selection() {
-        let records = (1..=5)
-            .map(|i| record(&i.to_string(), &format!("rec-{i}.demo"), "A", "127.0.0.1"))
+    fn wrangler_and_flarectl_variables_become_cloudflare_accounts() {
+        let env = HashMap::from([
+            ("CLOUDFLARE_API_TOKEN", "env-token-from-wrangler"),
+            ("CLOUDFLARE_ACCOUNT_ID", "acc-1"),
+            ("CF_API_KEY", "env-global-key-flarectl"),
+            ("CF_API_EMAIL", "me@example.com"),
+            ("CLOUDFLARE_API_KEY", "lonely-key"),
+            ("CF_API_TOKEN", " "),
+        ]);
+        let probe = credentials::from_env(|name| env.get(name).map(|v| v.to_string()));
+        let found: Vec<_> = probe
+            .found
+            .iter()
+            .map(|found| {
+                let account = &found.account;
+                (
+                    found.source.as_str(),
+                    account.name.as_str(),
+                    account.api_token.as_str(),
+                    account.auth_mode.clone(),
+                    account.email.as_deref(),
+                    account.account_id.as_deref(),
+                )
+            })
             .collect();
-        let mut app = app_with_records("visible", records);
-        app.update_record_page_size(6); // page size 3
+        assert_eq!(
+            found,
+            [
+                (
+                    "CLOUDFLARE_API_TOKEN",
+                    "wrangler",
+                    "env-token-from-wrangler",
+                    AuthMode::Token,
+                    None,
+                    Some("acc-1")
+                ),
+                (
+                    "CF_API_KEY + CF_API_EMAIL",
+                    "flarectl",
+                    "env-global-key-flarectl",
+                    AuthMode::GlobalKey,
+                    Some("me@example.com"),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            probe.notes,
+            ["CLOUDFLARE_API_KEY is set without CLOUDFLARE_EMAIL; a global API key needs both"]
+        );
+        assert!(credentials::from_env(|_| None).found.is_empty());
+    }
 
-        app.record_page = 0;
-        app.selected_record = 4;
-        let total = app.filtered_records().len();
-        app.ensure_record_visible(total);
+    #[test]
+    fn offline_flags_pick_the_mock_and_account_flags_where_the_tui_starts() {
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|s| s.to_string()));
+        assert_eq!(args(&["--offline"]).unwrap().offline, Some(Offline::Demo));
+        assert_eq!(
+            args(&["--fixture=fixtures/demo.json"]).unwrap().offline,
+            Some(Offline::Fixture(PathBuf::from("fixtures/demo.json")))
+        );
+        assert_eq!(
+            args(&["--generate", "5000"]).unwrap().offline,
+            Some(Offline::Generate(5000))
+        );
+        assert!(args(&["--generate", "many"]).is_err());
+        assert!(args(&["--offline", "--generate", "10"]).is_err());
+        assert_eq!(
+            Offline::from_env("generate:10").unwrap(),
+            Offline::Generate(10)
+        );
+        assert_eq!(Offline::from_env("1").unwrap(), Offline::Demo);
+        let parsed = args(&["--offline", "list-zones", "--account", "demo"]).unwrap();
+        assert_eq!(parsed.offline, Some(Offline::Demo));
+        assert_eq!(parsed.account, None, "the subcommand's option");
+        let parsed = args(&["--account", "acme", "--zone", "acme.services.io"]).unwrap();
+        assert_eq!(parsed.account.as_deref(), Some("acme"));
+        assert_eq!(parsed.zone.as_deref(), Some("acme.services.io"));
+        assert!(args(&["--zone", "acme.services.io"]).is_err());
+        assert!(args(&["--name", "www"]).is_err());
 
-        assert_eq!(app.record_page, 1);
-        assert_eq!(app.selected_record, 4);
+        let path = temp_config_path("start_at");
+        let mut config = Config {
+            accounts: vec![
+                test_account(),
+                Account {
+                    name: "acme".to_string(),
+                    ..test_account()
+                },
+            ],
+            ..Config::default()
+        };
+        let err = config.start_at("nobody", None, &path).unwrap_err();
+        assert!(err.to_string().ends_with("it has demo, acme"), "{err}");
+        config
+            .start_at("acme", Some("ACME.services.io.".to_string()), &path)
+            .unwrap();
+        let app = App::new(&path, config.clone(), MockBackend::new()).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "acme");
+        assert_eq!(app.current_zone().unwrap().name, "acme.services.io");
+        assert_eq!(app.focus, Focus::Records);
+        assert_eq!(app.records.len(), 3);
+
+        config
+            .start_at("acme", Some("nowhere.example.com".to_string()), &path)
+            .unwrap();
+        let app = App::new(&path, config, MockBackend::new()).unwrap();
+        assert_eq!(app.current_account().unwrap().name, "acme");
+        assert!(
+            app.messages
+                .iter()
+                .any(|message| message.level == MessageLevel::Error
+                    && message.text == "acme has no zone named nowhere.example.com")
+        );
+        assert_eq!(app.focus, Focus::Zones);
+        assert!(matches!(app.mode, Mode::Error(_)));
     }
 
     #[test]
-    fn update_record_page_size_clamps_page_and_selection() {
-        let records = (1..=5)
-            .map(|i| record(&i.to_string(), &format!("rec-{i}.demo"), "A", "127.0.0.1"))
-            .collect();
-        let mut app = app_with_records("clamp", records);
+    fn imported_credentials_are_confirmed_one_by_one_and_known_ones_skipped() {
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|s| s.to_string()));
+        assert!(args(&["--import-credentials"]).unwrap().import_credentials);
+        assert!(args(&["--import-credentials", "--encrypt"]).is_err());
+
+        let path = temp_config_path("import_credentials");
+        fs::write(
+            &path,
+            json!({"accounts": [
+                {"name": "wrangler", "api_token": "already-configured-token"}
+            ]})
+            .to_string(),
+        )
+        .unwrap();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/wrangler");
+        let paths = [
+            fixtures.join("api-token.toml"),
+            fixtures.join("oauth.toml"),
+            fixtures.join("missing.toml"),
+        ];
+        let env = HashMap::from([
+            ("CLOUDFLARE_API_TOKEN", "wrangler-fixture-token-0001"),
+            ("CF_API_TOKEN", "already-configured-token"),
+            ("CF_API_KEY", "env-global-key-flarectl"),
+            ("CF_API_EMAIL", "me@example.com"),
+        ]);
+        let probe = credentials::probe(&paths, |name| env.get(name).map(|v| v.to_string()));
+        assert_eq!(
+            probe.found.len(),
+            3,
+            "the env copy of the file's token is dropped"
+        );
+        assert_eq!(probe.notes.len(), 1, "{:?}", probe.notes);
 
-        app.record_page_size = 1;
-        app.record_page = 4;
-        app.selected_record = 4;
+        let mut prompts = Vec::new();
+        let config = Config::load(&path).unwrap();
+        import_credentials(&path, config, probe, |prompt| {
+            prompts.push(prompt.to_string());
+            Ok(prompts.len() == 1)
+        })
+        .unwrap();
+        assert_eq!(prompts.len(), 2, "the configured token isn't offered again");
+        assert!(
+            prompts[0].contains("API token ••••0001")
+                && prompts[0].contains("as account \"wrangler-2\"")
+                && !prompts[0].contains("wrangler-fixture-token"),
+            "{}",
+            prompts[0]
+        );
+        assert!(prompts[1].starts_with("CF_API_KEY + CF_API_EMAIL: global API key"));
 
-        app.update_record_page_size(12); // page size grows to 9, shrinking page count to 1
+        let saved = Config::load(&path).unwrap();
+        let names: Vec<_> = saved.accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["wrangler", "wrangler-2"]);
+        assert_eq!(saved.accounts[1].api_token, "wrangler-fixture-token-0001");
 
-        assert_eq!(app.page_size(), 9);
-        assert_eq!(app.record_page, 0);
-        assert_eq!(app.selected_record, 4);
+        let before = fs::read_to_string(&path).unwrap();
+        let config = Config::load(&path).unwrap();
+        import_credentials(&path, config, credentials::Probe::default(), |_| {
+            panic!("nothing to ask about")
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(backup_path(&path));
     }
 
     #[test]
-    fn status_message_reports_filter_and_paging() {
-        let records = (1..=3)
-            .map(|i| record(&i.to_string(), &format!("item-{i}.demo"), "A", "127.0.0.1"))
-            .collect();
-        let mut app = app_with_records("status", records);
+    fn shared_configs_carry_no_secrets_and_ask_for_them_on_import() {
+        let secrets = [
+            "cf-secret-personal-0001",
+            "cf-secret-resolved-0002",
+            "cf-secret-from-pass-0003",
+            "aws-secret-access-key-0004",
+        ];
+        let cloudflare = |name: &str, token: &str| Account {
+            name: name.to_string(),
+            api_token: token.to_string(),
+            account_id: Some(format!("{name}-id")),
+            ..test_account()
+        };
+        let mut config = Config {
+            collapse_accounts: true,
+            export_dir: Some(PathBuf::from("/home/me/dns-exports")),
+            type_colors: HashMap::from([("TXT".to_string(), "cyan".to_string())]),
+            ..Config::default()
+        };
+        config.accounts = vec![
+            Account {
+                default_ttl: Some(120),
+                ..cloudflare("personal", secrets[0])
+            },
+            Account {
+                token_ref: Some(TokenRef {
+                    raw: "env:CF_TOKEN_LAB".to_string(),
+                    resolved: secrets[1].to_string(),
+                }),
+                ..cloudflare("lab", secrets[1])
+            },
+            Account {
+                provider: Provider::Route53,
+                aws_access_key_id: Some("AKIDEXAMPLE".to_string()),
+                ..cloudflare("aws", secrets[3])
+            },
+        ];
+        config.profiles.insert(
+            "work".to_string(),
+            vec![Account {
+                token_cmd: Some("pass show cloudflare/corp".to_string()),
+                ..cloudflare("corp", secrets[2])
+            }],
+        );
+        let from = temp_config_path("shared_from");
+        let err = export_config(&config, &from, &from)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("the config in use"), "{err}");
 
-        app.update_record_page_size(5); // page size 2
-        app.record_filter = "item".to_string();
-        let (_, details) = app.status_message();
+        let json_path = temp_config_path("shared_json");
+        let toml_path = json_path.with_extension("toml");
+        for path in [&json_path, &toml_path] {
+            export_config(&config, &from, path).unwrap();
+            let bytes = fs::read(path).unwrap();
+            let text = String::from_utf8_lossy(&bytes);
+            for secret in secrets {
+                assert!(
+                    !bytes.windows(secret.len()).any(|w| w == secret.as_bytes()),
+                    "{secret} leaked into {}",
+                    path.display()
+                );
+            }
+            for gone in ["CF_TOKEN_LAB", "pass show", "dns-exports"] {
+                assert!(!text.contains(gone), "{gone} in {text}");
+            }
+            for kept in [
+                "personal-id",
+                "corp-id",
+                "AKIDEXAMPLE",
+                "cyan",
+                EXPORTED_SECRET,
+            ] {
+                assert!(text.contains(kept), "{kept} missing from {text}");
+            }
+        }
+        let exported = Config::load(&toml_path).unwrap();
+        assert_eq!(exported.accounts[0].default_ttl, Some(120));
+        assert!(exported.collapse_accounts);
+        assert!(exported.account_lists().all(|(_, accounts)| {
+            accounts
+                .iter()
+                .all(|a| a.lacks_secret() && a.token_cmd.is_none())
+        }));
 
+        let args = |list: &[&str]| CliArgs::parse(list.iter().map(|s| s.to_string()));
+        assert_eq!(
+            args(&["--export-config", "team.json"])
+                .unwrap()
+                .export_config,
+            Some(PathBuf::from("team.json"))
+        );
         assert!(
-            details.contains("Records: page 1/2"),
-            "status did not include page count: {}",
-            details
+            args(&["--import-config=team.json"])
+                .unwrap()
+                .import_config
+                .is_some()
         );
-        assert!(details.contains("filtered"), "status did not mark filter");
-    }
+        assert!(args(&["--import-config", "a.json", "--export-config", "b.json"]).is_err());
 
-    fn cf_account() -> Account {
-        Account {
-            name: "cf".to_string(),
-            api_token: "cf-token".to_string(),
-            email: Some("user@example.com".to_string()),
-            account_id: Some("acc-1".to_string()),
-            auth_mode: AuthMode::Token,
+        let path = temp_config_path("shared_into");
+        fs::write(
+            &path,
+            json!({
+                "scroll_step": 5,
+                "accounts": [{"name": "personal", "api_token": "my-own-token"}]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let mut mine = Config::load(&path).unwrap();
+        let notes = import_shared_config(&mut mine, &path, &json_path).unwrap();
+        assert!(notes.contains(&"Kept your own account personal in profile default".to_string()));
+        assert!(notes.contains(&"Added account corp to profile work".to_string()));
+        assert!(
+            notes
+                .contains(&"Took the setting collapse_accounts from the shared config".to_string())
+        );
+        let saved = Config::load(&path).unwrap();
+        let names: Vec<_> = saved.accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["personal", "lab", "aws"]);
+        assert_eq!(saved.accounts[0].api_token, "my-own-token");
+        assert_eq!(saved.scroll_step, Some(5));
+        assert!(saved.collapse_accounts && saved.export_dir.is_none());
+        assert_eq!(saved.type_colors["TXT"], "cyan");
+        assert!(saved.profiles["work"][0].lacks_secret());
+
+        let mut app = App::new(&path, saved, MockBackend::new()).unwrap();
+        app.ask_for_missing_secrets();
+        let Mode::AddingAccount(form) = &app.mode else {
+            panic!("expected the account form")
+        };
+        assert_eq!((form.editing, form.field_index), (Some(1), 2));
+        assert!(
+            form.secret().is_empty(),
+            "the placeholder isn't offered as the secret"
+        );
+        for c in "lab-token".chars() {
+            handle_key(KeyCode::Char(c), &mut app).unwrap();
         }
-    }
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        handle_key(KeyCode::Enter, &mut app).unwrap();
+        assert_eq!(app.accounts[1].api_token, "lab-token");
+        assert!(
+            matches!(app.mode, Mode::Normal),
+            "aws can't be edited in the form"
+        );
+        assert!(
+            app.last_message.contains("set api_token in"),
+            "{}",
+            app.last_message
+        );
+        assert_eq!(
+            Config::load(&path).unwrap().accounts[1].api_token,
+            "lab-token"
+        );
 
-    fn cf_zone() -> Zone {
-        Zone {
-            id: "zone-1".to_string(),
-            name: "example.com".to_string(),
+        app.switch_profile("work").unwrap();
+        assert!(matches!(app.mode, Mode::AddingAccount(_)));
+        handle_key(KeyCode::Esc, &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::Normal));
+        assert_eq!(app.current_account().unwrap().name, "corp");
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.text.starts_with("No secret for corp yet")),
+            "nothing is fetched for an account without its secret"
+        );
+
+        for file in [&json_path, &toml_path, &path] {
+            let _ = fs::remove_file(file);
+            let _ = fs::remove_file(backup_path(file));
         }
     }
 
     #[test]
-    fn cloudflare_list_zones_parses_success() {
-        let mut server = mockito::Server::new();
-        let _m = server
-            .mock("GET", "/zones")
-            .match_header("authorization", "Bearer cf-token")
-            .match_query(mockito::Matcher::AllOf(vec![
-                mockito::Matcher::UrlEncoded("per_page".into(), "200".into()),
-                mockito::Matcher::UrlEncoded("account.id".into(), "acc-1".into()),
-            ]))
-            .with_status(200)
-            .with_body(
-                json!({
-                    "success": true,
-                    "errors": [],
-                    "result": [
-                        {"id": "zone-1", "name": "example.com"},
-                        {"id": "zone-2", "name": "demo.net"}
-                    ]
-                })
-                .to_string(),
-            )
-            .create();
+    fn external_config_changes_are_reloaded_or_offered_as_a_conflict() {
+        let path = temp_config_path("hot_reload");
+        // Writes `accounts` as if another program had, with a later mtime so the change
+        // shows even on filesystems with coarse timestamps.
+        let write_externally = |accounts: serde_json::Value, offset: u64| {
+            fs::write(&path, json!({ "accounts": accounts }).to_string()).unwrap();
+            let later = SystemTime::now() + Duration::from_secs(offset);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(later)
+                .unwrap();
+        };
+        let names = |app: &App<MockBackend>| -> Vec<String> {
+            app.accounts.iter().map(|a| a.name.clone()).collect()
+        };
+        fs::write(
+            &path,
+            json!({"accounts": [
+                {"name": "alpha", "api_token": "t-alpha"},
+                {"name": "beta", "api_token": "t-beta"}
+            ]})
+            .to_string(),
+        )
+        .unwrap();
+        let mut app = App::new(&path, Config::load(&path).unwrap(), MockBackend::new()).unwrap();
+        app.select_account_at(1).unwrap();
+        app.tick().unwrap();
+        assert_eq!(names(&app), ["alpha", "beta"], "nothing changed yet");
 
-        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
-        let zones = backend.list_zones(&cf_account()).unwrap();
-        assert_eq!(zones.len(), 2);
-        assert_eq!(zones[0].name, "example.com");
-    }
+        write_externally(
+            json!([
+                {"name": "gamma", "api_token": "t-gamma"},
+                {"name": "alpha", "api_token": "t-alpha-rotated"},
+                {"name": "beta", "api_token": "t-beta"}
+            ]),
+            10,
+        );
+        app.tick().unwrap();
+        assert_eq!(names(&app), ["gamma", "alpha", "beta"]);
+        assert_eq!(app.current_account().unwrap().name, "beta", "kept by name");
+        assert_eq!(app.accounts[1].api_token, "t-alpha-rotated");
+        assert!(
+            app.last_message.ends_with("added gamma, changed alpha"),
+            "{}",
+            app.last_message
+        );
 
-    #[test]
-    fn cloudflare_list_records_parses_success() {
-        let mut server = mockito::Server::new();
-        let zone = cf_zone();
-        let path = format!("/zones/{}/dns_records", zone.id);
-        let _m = server
-            .mock("GET", path.as_str())
-            .match_header("authorization", "Bearer cf-token")
-            .match_query(mockito::Matcher::UrlEncoded("per_page".into(), "200".into()))
-            .with_status(200)
-            .with_body(
-                json!({
-                    "success": true,
-                    "errors": [],
-                    "result": [
-                        {"id": "rec-1", "name": "api.example.com", "type": "A", "content": "1.1.1.1", "ttl": 120, "proxied": true},
-                        {"id": "rec-2", "name": "edge.example.com", "type": "CNAME", "content": "api.example.com", "ttl": 300, "proxied": false}
-                    ]
-                })
-                .to_string(),
-            )
-            .create();
+        // A form open here holds off the reload until it closes.
+        write_externally(json!([{"name": "beta", "api_token": "t-beta"}]), 20);
+        app.start_edit_account();
+        app.tick().unwrap();
+        assert_eq!(app.accounts.len(), 3);
+        let edited = Account {
+            api_token: "t-beta-mine".to_string(),
+            ..app.accounts[2].clone()
+        };
+        let err = app
+            .finish_edit_account(2, edited)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("changed outside nyxflare"), "{err}");
+        app.tick().unwrap();
+        let Mode::ConfigConflict(conflict) = &app.mode else {
+            panic!("expected the conflict prompt")
+        };
+        assert_eq!(conflict.yours, ["changed beta"]);
+        assert_eq!(conflict.theirs, ["removed gamma", "removed alpha"]);
+        assert!(draw_app(&mut app).contains("Theirs: removed gamma, removed alpha"));
+        handle_key(KeyCode::Char('k'), &mut app).unwrap();
+        assert!(matches!(app.mode, Mode::Normal));
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.accounts.len(), 3);
+        assert_eq!(saved.accounts[2].api_token, "t-beta-mine");
 
-        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
-        let records = backend.list_records(&cf_account(), &zone).unwrap();
-        assert_eq!(records.len(), 2);
-        assert_eq!(records[0].record_type, "A");
-        assert!(records[0].proxied);
-        assert_eq!(records[1].record_type, "CNAME");
+        write_externally(json!([{"name": "delta", "api_token": "t-delta"}]), 30);
+        app.accounts[0].api_token = "unsaved".to_string();
+        app.tick().unwrap();
+        handle_key(KeyCode::Char('r'), &mut app).unwrap();
+        assert_eq!(names(&app), ["delta"]);
+        assert_eq!(app.current_account().unwrap().name, "delta");
+
+        fs::write(&path, "{ not json").unwrap();
+        app.tick().unwrap();
+        assert!(
+            app.last_message.starts_with("Not reloading"),
+            "{}",
+            app.last_message
+        );
+        let logged = app.messages.len();
+        app.tick().unwrap();
+        assert_eq!(app.messages.len(), logged, "a broken file is reported once");
+        assert_eq!(names(&app), ["delta"]);
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(backup_path(&path));
     }
 
     #[test]
-    fn cloudflare_create_update_delete_roundtrip() {
-        let mut server = mockito::Server::new();
-        let zone = cf_zone();
-
-        let create_path = format!("/zones/{}/dns_records", zone.id);
-        let _create = server
-            .mock("POST", create_path.as_str())
-            .match_header("authorization", "Bearer cf-token")
-            .match_body(mockito::Matcher::PartialJson(json!({
-                "name": "new.example.com",
-                "type": "A",
-                "content": "2.2.2.2",
-                "ttl": 300,
-                "proxied": false
-            })))
-            .with_status(200)
-            .with_body(
-                json!({
-                    "success": true,
-                    "errors": [],
-                    "result": {
-                        "id": "rec-new",
-                        "name": "new.example.com",
-                        "type": "A",
-                        "content": "2.2.2.2",
-                        "ttl": 300,
-                        "proxied": false
-                    }
-                })
-                .to_string(),
-            )
-            .create();
+    fn a_lock_file_keeps_a_second_instance_read_only_until_it_goes_stale() {
+        let path = temp_config_path("lock");
+        let lock = lock_path(&path);
+        let never = |_: &str| -> Result<bool> { panic!("nothing to ask") };
+        let me = std::process::id();
+        assert!(process_alive(me));
 
-        let update_path = format!("/zones/{}/dns_records/rec-new", zone.id);
-        let _update = server
-            .mock("PUT", update_path.as_str())
-            .match_body(mockito::Matcher::PartialJson(json!({"content": "3.3.3.3"})))
-            .with_status(200)
-            .with_body(
-                json!({
-                    "success": true,
-                    "errors": [],
-                    "result": {
-                        "id": "rec-new",
-                        "name": "new.example.com",
-                        "type": "A",
-                        "content": "3.3.3.3",
-                        "ttl": 300,
-                        "proxied": false
-                    }
-                })
-                .to_string(),
-            )
-            .create();
+        let state = lock_config(&path, |_| true, never).unwrap();
+        assert!(state.holder().is_none());
+        assert_eq!(lock_holder(&lock), Some(me));
+        drop(state);
+        assert!(!lock.exists(), "released on drop");
 
-        let delete_path = format!("/zones/{}/dns_records/rec-new", zone.id);
-        let _delete = server
-            .mock("DELETE", delete_path.as_str())
-            .with_status(200)
-            .with_body(json!({"success": true, "errors": []}).to_string())
-            .create();
+        fs::write(&lock, "4242\n").unwrap();
+        let state = lock_config(&path, |pid| pid == 4242, never).unwrap();
+        assert_eq!(state.holder().as_deref(), Some("nyxflare process 4242"));
+        drop(state);
+        assert_eq!(lock_holder(&lock), Some(4242), "someone else's lock stays");
 
-        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
-        let mut created = backend
-            .create_record(
-                &cf_account(),
-                &zone,
-                record("rec-new", "new.example.com", "A", "2.2.2.2"),
-            )
-            .unwrap();
-        assert_eq!(created.id, "rec-new");
+        let mut prompts = Vec::new();
+        let state = lock_config(
+            &path,
+            |_| false,
+            |prompt: &str| {
+                prompts.push(prompt.to_string());
+                Ok(false)
+            },
+        )
+        .unwrap();
+        assert_eq!(state.holder().as_deref(), Some("nyxflare process 4242"));
+        assert!(
+            prompts[0].contains("process 4242, which isn't running"),
+            "{}",
+            prompts[0]
+        );
+        let state = lock_config(&path, |_| false, |_: &str| Ok(true)).unwrap();
+        assert!(state.holder().is_none(), "a stale lock is taken over");
+        assert_eq!(lock_holder(&lock), Some(me));
+        // Taken over in turn by another instance: dropping ours leaves theirs alone.
+        fs::write(&lock, "5151").unwrap();
+        drop(state);
+        assert_eq!(lock_holder(&lock), Some(5151));
 
-        created.content = "3.3.3.3".to_string();
-        let updated = backend
-            .update_record(&cf_account(), &zone, created)
-            .unwrap();
-        assert_eq!(updated.content, "3.3.3.3");
+        fs::write(&lock, "garbage").unwrap();
+        let state = lock_config(
+            &path,
+            |_| true,
+            |prompt: &str| {
+                assert!(prompt.contains("didn't record its process"), "{prompt}");
+                Ok(false)
+            },
+        )
+        .unwrap();
+        assert_eq!(state.holder().as_deref(), Some("another nyxflare"));
+        let _ = fs::remove_file(&lock);
 
-        backend
-            .delete_record(&cf_account(), &zone, "rec-new")
-            .unwrap();
+        fs::write(
+            &path,
+            json!({"accounts": [{"name": "demo", "api_token": "token"}]}).to_string(),
+        )
+        .unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        let mut app = App::new(&path, Config::load(&path).unwrap(), MockBackend::new()).unwrap();
+        app.open_read_only("nyxflare process 4242".to_string());
+        app.focus = Focus::Accounts;
+        for key in ['a', 'e', 'd'] {
+            handle_key(KeyCode::Char(key), &mut app).unwrap();
+            assert!(matches!(app.mode, Mode::Normal), "{key} opens nothing");
+        }
+        assert_eq!(
+            app.last_message,
+            "Read-only: nyxflare process 4242 has the config open"
+        );
+        assert!(app.save_accounts().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+        assert!(draw_app(&mut app).contains("Read-only: nyxflare process 4242"));
+        let _ = fs::remove_file(&path);
     }
 
     #[test]
-    fn cloudflare_errors_propagate_context() {
-        let mut server = mockito::Server::new();
-        let _m = server
-            .mock("GET", "/zones")
-            .match_query(mockito::Matcher::Any)
-            .with_status(500)
-            .with_body(
-                json!({
-                    "success": false,
-                    "errors": [{"message": "boom"}],
-                    "result": null
-                })
-                .to_string(),
-            )
-            .create();
+    fn config_path_precedence_is_flag_env_default_then_older_locations() {
+        let dir = temp_config_path("precedence").with_extension("d");
+        let flag = dir.join("flag.json");
+        let env_path = dir.join("env.json");
+        let current = dir.join("platform").join("nyxflare").join("accounts.json");
+        let before_dirs = dir
+            .join("dot-config")
+            .join("nyxflare")
+            .join("accounts.json");
+        let cwd_legacy = dir.join("config").join("accounts.json");
+        let legacy = [before_dirs.clone(), cwd_legacy.clone()];
+        let env_value = Some(std::ffi::OsString::from(&env_path));
+        assert_eq!(
+            ConfigSource::resolve(Some(flag.clone()), env_value.clone()),
+            ConfigSource::Flag(flag.clone())
+        );
+        assert_eq!(
+            ConfigSource::resolve(None, env_value),
+            ConfigSource::Env(env_path)
+        );
+        assert!(matches!(
+            ConfigSource::resolve(None, None),
+            ConfigSource::Default(_)
+        ));
+        assert!(default_config_path().ends_with("nyxflare/accounts.json"));
+        let older = legacy_config_paths(&default_config_path());
+        assert_eq!(older.last(), Some(&PathBuf::from(LEGACY_CONFIG_PATH)));
+        assert!(!older.contains(&default_config_path()));
+        assert!(
+            CliArgs::parse(["--print-config-path".to_string()])
+                .unwrap()
+                .print_config_path
+        );
+        assert_eq!(
+            pending_migration_note(&ConfigSource::Flag(flag.clone())),
+            None
+        );
 
-        let mut backend = CloudflareBackend::new_with_base(server.url()).unwrap();
-        let err = backend.list_zones(&cf_account()).unwrap_err();
-        let msg = format!("{err}");
+        let write = |path: &Path, name: &str| {
+            let config = Config {
+                accounts: vec![Account {
+                    name: name.to_string(),
+                    ..test_account()
+                }],
+                ..Config::default()
+            };
+            config.save(path).unwrap();
+        };
+        let load = |source: &ConfigSource| {
+            let (path, config) =
+                load_config_at(source, &legacy, |_| unreachable!(), |_| Ok(false)).unwrap();
+            (path, config.accounts[0].name.clone())
+        };
+        let default = ConfigSource::Default(current.clone());
+        write(&cwd_legacy, "from-cwd");
+        write(&before_dirs, "from-dot-config");
+        let Err(err) = load_headless_config(&default, &legacy, |_| unreachable!()) else {
+            panic!("an older config still to be moved");
+        };
         assert!(
-            msg.contains("boom") || msg.contains("Zones"),
-            "error lacked context: {msg}"
+            err.to_string().starts_with(&format!(
+                "{} hasn't been moved to {} yet",
+                before_dirs.display(),
+                current.display()
+            )),
+            "{err}"
+        );
+        assert!(!current.exists(), "subcommands leave the move to the TUI");
+        assert_eq!(
+            load(&default),
+            (current.clone(), "from-dot-config".to_string()),
+            "the newer of two older locations is moved forward"
         );
+        write(&current, "current");
+        assert_eq!(load(&default), (current.clone(), "current".to_string()));
+        let (_, headless) = load_headless_config(&default, &legacy, |_| unreachable!()).unwrap();
+        assert_eq!(headless.accounts[0].name, "current");
+        write(&flag, "flagged");
+        assert_eq!(
+            load(&ConfigSource::Flag(flag.clone())),
+            (flag, "flagged".to_string())
+        );
+
+        fs::remove_file(&before_dirs).unwrap();
+        fs::remove_file(&current).unwrap();
+        assert_eq!(load(&default), (current, "from-cwd".to_string()));
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
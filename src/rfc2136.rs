@@ -0,0 +1,928 @@
+//! RFC 2136 dynamic updates behind `DnsBackend`, for authoritative servers such as BIND.
+//! Zones come from the account's static `zones` list (the protocol has no way to list
+//! them), records from a zone transfer (AXFR), and every change is one UPDATE message,
+//! signed with the account's TSIG key (RFC 8945). Messages go over TCP.
+//!
+//! There is no record id on the wire, so ids are `name|type|content`; an edit deletes
+//! exactly that record and adds its replacement in the same message.
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use ring::hmac;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{
+    Account, AuditEntry, BatchRequest, BatchResult, CloudflareAccount, DnsBackend, DnsRecord,
+    DnssecStatus, ImportResult, Listing, Page, Timeouts, TokenStatus, Zone, ZoneSetup, bind,
+    now_unix,
+};
+
+const DNS_PORT: u16 = 53;
+const CLASS_IN: u16 = 1;
+/// Class of "delete this exact record" entries in an UPDATE.
+const CLASS_NONE: u16 = 254;
+const CLASS_ANY: u16 = 255;
+const TYPE_SOA: u16 = 6;
+const TYPE_TSIG: u16 = 250;
+const TYPE_AXFR: u16 = 252;
+const OPCODE_UPDATE: u16 = 5;
+/// Allowed clock skew between us and the server, in seconds.
+const TSIG_FUDGE: u16 = 300;
+/// Safeguard against a transfer that never sends its closing SOA.
+const MAX_AXFR_MESSAGES: usize = 10_000;
+
+const TYPES: [(&str, u16); 13] = [
+    ("A", 1),
+    ("NS", 2),
+    ("CNAME", 5),
+    ("SOA", 6),
+    ("PTR", 12),
+    ("MX", 15),
+    ("TXT", 16),
+    ("AAAA", 28),
+    ("SRV", 33),
+    ("DNAME", 39),
+    ("SPF", 99),
+    ("CAA", 257),
+    ("TSIG", TYPE_TSIG),
+];
+
+pub(crate) struct Rfc2136Backend {
+    timeouts: Timeouts,
+}
+
+impl Rfc2136Backend {
+    pub(crate) fn new(timeouts: Timeouts) -> Self {
+        Self { timeouts }
+    }
+
+    fn connect(&self, account: &Account) -> Result<TcpStream> {
+        let server = account
+            .dns_server
+            .as_deref()
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| anyhow!("RFC 2136 account {} needs dns_server", account.name))?;
+        let target = if server.parse::<Ipv6Addr>().is_ok() {
+            format!("[{server}]:{DNS_PORT}")
+        } else if server.contains(':') {
+            server.to_string()
+        } else {
+            format!("{server}:{DNS_PORT}")
+        };
+        let addr = target
+            .to_socket_addrs()
+            .with_context(|| format!("Resolving {target}"))?
+            .next()
+            .ok_or_else(|| anyhow!("{target} did not resolve"))?;
+        let stream =
+            TcpStream::connect_timeout(&addr, Duration::from_secs(self.timeouts.connect_secs))
+                .with_context(|| format!("Connecting to {target}"))?;
+        let timeout = Some(Duration::from_secs(self.timeouts.request_secs));
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        Ok(stream)
+    }
+
+    /// Signs and sends `message`, returning the server's reply.
+    fn exchange(&self, account: &Account, mut message: Vec<u8>) -> Result<Vec<u8>> {
+        if let Some(key) = TsigKey::for_account(account)? {
+            key.sign(&mut message, now_unix() as u64);
+        }
+        let mut stream = self.connect(account)?;
+        send_message(&mut stream, &message)?;
+        let reply = read_message(&mut stream)?;
+        check_rcode(&reply)?;
+        Ok(reply)
+    }
+
+    /// Every record in the zone, by zone transfer. The closing SOA is dropped.
+    fn transfer(&self, account: &Account, zone: &Zone) -> Result<Vec<DnsRecord>> {
+        let mut query = Message::new(0);
+        query.question(&zone.name, TYPE_AXFR);
+        let mut message = query.finish();
+        if let Some(key) = TsigKey::for_account(account)? {
+            key.sign(&mut message, now_unix() as u64);
+        }
+        let mut stream = self.connect(account)?;
+        send_message(&mut stream, &message)?;
+
+        let mut records = Vec::new();
+        let mut soas = 0;
+        for _ in 0..MAX_AXFR_MESSAGES {
+            let reply = read_message(&mut stream)?;
+            check_rcode(&reply)?;
+            for record in parse_answers(&reply)? {
+                if record.record_type == "SOA" {
+                    soas += 1;
+                    if soas == 2 {
+                        return Ok(records);
+                    }
+                }
+                records.push(record);
+            }
+        }
+        Err(anyhow!("Zone transfer of {} never finished", zone.name))
+    }
+
+    fn update(&self, account: &Account, zone: &Zone, changes: &[Change<'_>]) -> Result<()> {
+        let mut message = Message::new(OPCODE_UPDATE << 11);
+        message.question(&zone.name, TYPE_SOA);
+        for change in changes {
+            let (record, class, ttl) = match change {
+                Change::Add(record) => (record, CLASS_IN, record.ttl),
+                Change::Delete(record) => (record, CLASS_NONE, 0),
+            };
+            message.record(
+                &record.name,
+                type_code(&record.record_type)?,
+                class,
+                ttl,
+                &encode_rdata(&record.record_type, &record.content)?,
+            );
+        }
+        self.exchange(account, message.finish())?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for Rfc2136Backend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        if account.zones.is_empty() {
+            return Err(anyhow!(
+                "RFC 2136 account {} lists no zones; add them to its \"zones\"",
+                account.name
+            ));
+        }
+        Ok(Listing::single(
+            account
+                .zones
+                .iter()
+                .map(|name| {
+                    let name = name.trim().trim_end_matches('.').to_string();
+                    Zone {
+                        id: name.clone(),
+                        name,
+                        ..Zone::default()
+                    }
+                })
+                .collect(),
+        ))
+    }
+
+    fn delete_zone(&mut self, _account: &Account, _zone: &Zone) -> Result<()> {
+        Err(anyhow!(
+            "Zones of RFC 2136 accounts are managed on the server; remove it from \"zones\""
+        ))
+    }
+
+    fn create_zone(&mut self, _account: &Account, _name: &str, _setup: ZoneSetup) -> Result<Zone> {
+        Err(anyhow!(
+            "Zones of RFC 2136 accounts are managed on the server; add it to \"zones\""
+        ))
+    }
+
+    /// A transfer returns the whole zone, so every record comes back as page 1.
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        let items: Vec<DnsRecord> = if page > 1 {
+            Vec::new()
+        } else {
+            self.transfer(account, zone)?
+                .into_iter()
+                .filter(|r| record_type.is_none_or(|t| r.record_type == t))
+                .collect()
+        };
+        Ok(Page {
+            total_count: Some(items.len() as u32),
+            items,
+            page,
+            total_pages: 1,
+        })
+    }
+
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        let needle = query.to_lowercase();
+        Ok(self
+            .transfer(account, zone)?
+            .into_iter()
+            .filter(|r| {
+                r.name.to_lowercase().contains(&needle)
+                    || r.content.to_lowercase().contains(&needle)
+            })
+            .collect())
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let record = identified(record);
+        self.update(account, zone, &[Change::Add(&record)])?;
+        Ok(record)
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let old = parse_record_id(&record.id)?;
+        let record = identified(record);
+        self.update(account, zone, &[Change::Delete(&old), Change::Add(&record)])?;
+        Ok(record)
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        let old = parse_record_id(record_id)?;
+        self.update(account, zone, &[Change::Delete(&old)])
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        self.transfer(account, zone)?
+            .into_iter()
+            .find(|r| r.id == record_id)
+            .ok_or_else(|| anyhow!("Record {record_id} not found"))
+    }
+
+    /// One UPDATE message carries the whole batch, which the server applies atomically.
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        let deletes = request
+            .deletes
+            .iter()
+            .map(|id| parse_record_id(id))
+            .collect::<Result<Vec<_>>>()?;
+        let replaced = request
+            .patches
+            .iter()
+            .chain(&request.puts)
+            .map(|r| parse_record_id(&r.id))
+            .collect::<Result<Vec<_>>>()?;
+        let result = BatchResult {
+            patches: request.patches.into_iter().map(identified).collect(),
+            puts: request.puts.into_iter().map(identified).collect(),
+            posts: request.posts.into_iter().map(identified).collect(),
+            deletes,
+        };
+        let changes: Vec<Change<'_>> = result
+            .deletes
+            .iter()
+            .chain(&replaced)
+            .map(Change::Delete)
+            .chain(
+                result
+                    .patches
+                    .iter()
+                    .chain(&result.puts)
+                    .chain(&result.posts)
+                    .map(Change::Add),
+            )
+            .collect();
+        self.update(account, zone, &changes)?;
+        Ok(result)
+    }
+
+    /// The protocol keeps no history.
+    fn record_audit_log(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(Vec::new())
+    }
+
+    /// Asks for the first zone's SOA with the account's key, which fails when the
+    /// server is unreachable or rejects the key.
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        let zone = account
+            .zones
+            .first()
+            .ok_or_else(|| anyhow!("RFC 2136 account {} lists no zones", account.name))?;
+        let mut query = Message::new(0);
+        query.question(zone, TYPE_SOA);
+        self.exchange(account, query.finish())?;
+        Ok(TokenStatus {
+            status: "active".to_string(),
+            expires_on: None,
+        })
+    }
+
+    fn list_cf_accounts(&mut self, _account: &Account) -> Result<Vec<CloudflareAccount>> {
+        Ok(Vec::new())
+    }
+
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        Ok(bind::write_zone(&zone.name, &self.transfer(account, zone)?))
+    }
+
+    /// Adds every record of the file except its SOA in one UPDATE.
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        _proxied: bool,
+    ) -> Result<ImportResult> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let records: Vec<DnsRecord> = bind::parse_zone(&text, &zone.name)?
+            .into_iter()
+            .filter(|r| r.record_type != "SOA")
+            .collect();
+        let changes: Vec<Change<'_>> = records.iter().map(Change::Add).collect();
+        self.update(account, zone, &changes)?;
+        let count = records.len() as u32;
+        Ok(ImportResult {
+            added: count,
+            parsed: count,
+        })
+    }
+
+    fn dnssec_status(&mut self, _account: &Account, _zone: &Zone) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for RFC 2136 accounts"))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _enabled: bool,
+    ) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for RFC 2136 accounts"))
+    }
+}
+
+enum Change<'a> {
+    Add(&'a DnsRecord),
+    Delete(&'a DnsRecord),
+}
+
+fn record_id(record: &DnsRecord) -> String {
+    format!(
+        "{}|{}|{}",
+        record.name.to_lowercase(),
+        record.record_type,
+        record.content
+    )
+}
+
+/// The record with its id recomputed from its name, type and content.
+fn identified(mut record: DnsRecord) -> DnsRecord {
+    record.name = record.name.trim().trim_end_matches('.').to_string();
+    record.record_type = record.record_type.trim().to_ascii_uppercase();
+    record.proxied = false;
+    record.id = record_id(&record);
+    record
+}
+
+/// The record an id stands for; its TTL is unknown and irrelevant to deletes.
+pub(crate) fn parse_record_id(id: &str) -> Result<DnsRecord> {
+    let mut parts = id.splitn(3, '|');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(name), Some(record_type), Some(content)) => Ok(DnsRecord {
+            id: id.to_string(),
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            content: content.to_string(),
+            ttl: 0,
+            proxied: false,
+            unmodeled: Vec::new(),
+            modified_on: None,
+        }),
+        _ => Err(anyhow!("{id} is not an RFC 2136 record id")),
+    }
+}
+
+fn type_code(record_type: &str) -> Result<u16> {
+    if let Some((_, code)) = TYPES.iter().find(|(name, _)| *name == record_type) {
+        return Ok(*code);
+    }
+    record_type
+        .strip_prefix("TYPE")
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| anyhow!("Unknown record type {record_type}"))
+}
+
+fn type_name(code: u16) -> String {
+    TYPES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("TYPE{code}"))
+}
+
+/// A TSIG key (RFC 8945) from the account: `tsig_key_name`, `tsig_algorithm`
+/// (`hmac-sha256` unless set) and the base64 secret in `api_token`.
+pub(crate) struct TsigKey {
+    name: String,
+    algorithm: &'static str,
+    key: hmac::Key,
+}
+
+impl TsigKey {
+    fn for_account(account: &Account) -> Result<Option<Self>> {
+        let Some(name) = account.tsig_key_name.as_deref().filter(|n| !n.is_empty()) else {
+            return Ok(None);
+        };
+        let algorithm = account.tsig_algorithm.as_deref().unwrap_or("hmac-sha256");
+        Self::new(name, algorithm, &account.api_token).map(Some)
+    }
+
+    pub(crate) fn new(name: &str, algorithm: &str, secret: &str) -> Result<Self> {
+        let (algorithm, hmac_algorithm) = match algorithm
+            .trim()
+            .trim_end_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "hmac-sha256" => ("hmac-sha256", hmac::HMAC_SHA256),
+            "hmac-sha384" => ("hmac-sha384", hmac::HMAC_SHA384),
+            "hmac-sha512" => ("hmac-sha512", hmac::HMAC_SHA512),
+            other => return Err(anyhow!("Unsupported TSIG algorithm {other}")),
+        };
+        let secret = base64::engine::general_purpose::STANDARD
+            .decode(secret.trim())
+            .context("TSIG secret must be base64")?;
+        Ok(Self {
+            name: name.trim().trim_end_matches('.').to_ascii_lowercase(),
+            algorithm,
+            key: hmac::Key::new(hmac_algorithm, &secret),
+        })
+    }
+
+    /// The MAC over `message` (as sent, without its TSIG record) and the TSIG variables.
+    fn mac(&self, message: &[u8], time_signed: u64) -> hmac::Tag {
+        let mut signed = message.to_vec();
+        put_name(&mut signed, &self.name);
+        put_u16(&mut signed, CLASS_ANY);
+        signed.extend_from_slice(&0u32.to_be_bytes());
+        put_name(&mut signed, self.algorithm);
+        signed.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        put_u16(&mut signed, TSIG_FUDGE);
+        put_u16(&mut signed, 0); // error
+        put_u16(&mut signed, 0); // other len
+        hmac::sign(&self.key, &signed)
+    }
+
+    /// Appends the TSIG record for `message` signed at `time_signed` (Unix seconds).
+    pub(crate) fn sign(&self, message: &mut Vec<u8>, time_signed: u64) {
+        let mac = self.mac(message, time_signed);
+        let mut rdata = Vec::new();
+        put_name(&mut rdata, self.algorithm);
+        rdata.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        put_u16(&mut rdata, TSIG_FUDGE);
+        put_u16(&mut rdata, mac.as_ref().len() as u16);
+        rdata.extend_from_slice(mac.as_ref());
+        rdata.extend_from_slice(&message[0..2]); // original id
+        put_u16(&mut rdata, 0); // error
+        put_u16(&mut rdata, 0); // other len
+
+        put_name(message, &self.name);
+        put_u16(message, TYPE_TSIG);
+        put_u16(message, CLASS_ANY);
+        message.extend_from_slice(&0u32.to_be_bytes());
+        put_u16(message, rdata.len() as u16);
+        message.extend_from_slice(&rdata);
+        let additional = u16::from_be_bytes([message[10], message[11]]) + 1;
+        message[10..12].copy_from_slice(&additional.to_be_bytes());
+    }
+
+    /// Whether `message` ends with a valid TSIG record from this key, checked the way a
+    /// server checks a request.
+    #[cfg(test)]
+    pub(crate) fn verify(&self, message: &[u8]) -> bool {
+        let Ok(records) = parse_records(message) else {
+            return false;
+        };
+        let Some(tsig) = records.last().filter(|r| r.rtype == TYPE_TSIG) else {
+            return false;
+        };
+        let mut reader = Reader::at(message, tsig.rdata_start);
+        let parsed = (|| {
+            reader.name()?;
+            let time = reader.bytes(6)?;
+            reader.u16()?;
+            let len = reader.u16()? as usize;
+            let mac = reader.bytes(len)?.to_vec();
+            let original_id = reader.bytes(2)?.to_vec();
+            Ok::<_, anyhow::Error>((time, mac, original_id))
+        })();
+        let Ok((time, mac, original_id)) = parsed else {
+            return false;
+        };
+        let mut unsigned = message[..tsig.start].to_vec();
+        unsigned[0..2].copy_from_slice(&original_id);
+        let additional = u16::from_be_bytes([unsigned[10], unsigned[11]]) - 1;
+        unsigned[10..12].copy_from_slice(&additional.to_be_bytes());
+        let mut time_bytes = [0u8; 8];
+        time_bytes[2..].copy_from_slice(time);
+        self.mac(&unsigned, u64::from_be_bytes(time_bytes)).as_ref() == mac
+    }
+}
+
+/// Builds a message with one question (or UPDATE zone) and any number of records in
+/// the third section (UPDATE's update section).
+pub(crate) struct Message {
+    buf: Vec<u8>,
+    records: u16,
+}
+
+impl Message {
+    pub(crate) fn new(flags: u16) -> Self {
+        let mut buf = Vec::with_capacity(512);
+        put_u16(&mut buf, (now_unix() as u16) ^ 0x5a5a);
+        put_u16(&mut buf, flags);
+        buf.extend_from_slice(&[0; 8]);
+        Self { buf, records: 0 }
+    }
+
+    pub(crate) fn question(&mut self, name: &str, qtype: u16) {
+        put_name(&mut self.buf, name);
+        put_u16(&mut self.buf, qtype);
+        put_u16(&mut self.buf, CLASS_IN);
+        self.buf[4..6].copy_from_slice(&1u16.to_be_bytes());
+    }
+
+    pub(crate) fn record(&mut self, name: &str, rtype: u16, class: u16, ttl: u32, rdata: &[u8]) {
+        put_name(&mut self.buf, name);
+        put_u16(&mut self.buf, rtype);
+        put_u16(&mut self.buf, class);
+        self.buf.extend_from_slice(&ttl.to_be_bytes());
+        put_u16(&mut self.buf, rdata.len() as u16);
+        self.buf.extend_from_slice(rdata);
+        self.records += 1;
+    }
+
+    /// Puts the records in the answer section, as a server's reply would.
+    #[cfg(test)]
+    pub(crate) fn finish_as_answer(mut self) -> Vec<u8> {
+        self.buf[6..8].copy_from_slice(&self.records.to_be_bytes());
+        self.buf
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.buf[8..10].copy_from_slice(&self.records.to_be_bytes());
+        self.buf
+    }
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Writes `name` (absolute, trailing dot optional) as uncompressed labels.
+fn put_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|l| !l.is_empty())
+    {
+        buf.push(label.len().min(63) as u8);
+        buf.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    buf.push(0);
+}
+
+fn send_message(stream: &mut TcpStream, message: &[u8]) -> Result<()> {
+    let mut framed = (message.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(message);
+    stream.write_all(&framed).context("Sending DNS message")
+}
+
+pub(crate) fn read_message(stream: &mut impl Read) -> Result<Vec<u8>> {
+    let mut len = [0u8; 2];
+    stream
+        .read_exact(&mut len)
+        .context("Reading DNS response")?;
+    let mut message = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream
+        .read_exact(&mut message)
+        .context("Reading DNS response")?;
+    Ok(message)
+}
+
+fn check_rcode(message: &[u8]) -> Result<()> {
+    let rcode = message.get(3).map(|b| b & 0x0f).unwrap_or(1);
+    let name = match rcode {
+        0 => return Ok(()),
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED (is the key allowed to update this zone?)",
+        6 => "YXDOMAIN",
+        7 => "YXRRSET",
+        8 => "NXRRSET",
+        9 => "NOTAUTH (check the TSIG key and the clocks)",
+        10 => "NOTZONE",
+        _ => "unknown error",
+    };
+    Err(anyhow!("DNS server answered {name} (rcode {rcode})"))
+}
+
+/// A resource record's position in a message.
+struct RawRecord {
+    /// Where the record begins; a TSIG check strips the message from here.
+    #[cfg(test)]
+    start: usize,
+    name: String,
+    rtype: u16,
+    ttl: u32,
+    rdata_start: usize,
+    rdata_len: usize,
+}
+
+/// Every record after the question section, in order.
+fn parse_records(message: &[u8]) -> Result<Vec<RawRecord>> {
+    let mut reader = Reader::at(message, 4);
+    let questions = reader.u16()?;
+    let count = [reader.u16()?, reader.u16()?, reader.u16()?]
+        .iter()
+        .map(|&n| n as usize)
+        .sum();
+    for _ in 0..questions {
+        reader.name()?;
+        reader.bytes(4)?;
+    }
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        #[cfg(test)]
+        let start = reader.pos;
+        let name = reader.name()?;
+        let rtype = reader.u16()?;
+        let _class = reader.u16()?;
+        let ttl = reader.u32()?;
+        let rdata_len = reader.u16()? as usize;
+        let rdata_start = reader.pos;
+        reader.bytes(rdata_len)?;
+        records.push(RawRecord {
+            #[cfg(test)]
+            start,
+            name,
+            rtype,
+            ttl,
+            rdata_start,
+            rdata_len,
+        });
+    }
+    Ok(records)
+}
+
+/// The answer records of a reply as rows, skipping TSIG.
+pub(crate) fn parse_answers(message: &[u8]) -> Result<Vec<DnsRecord>> {
+    let answers = u16::from_be_bytes([message[6], message[7]]) as usize;
+    parse_records(message)?
+        .into_iter()
+        .take(answers)
+        .filter(|r| r.rtype != TYPE_TSIG)
+        .map(|r| {
+            let record_type = type_name(r.rtype);
+            let content = decode_rdata(message, &record_type, r.rdata_start, r.rdata_len)?;
+            Ok(identified(DnsRecord {
+                id: String::new(),
+                name: r.name,
+                record_type,
+                content,
+                ttl: r.ttl,
+                proxied: false,
+                unmodeled: Vec::new(),
+                modified_on: None,
+            }))
+        })
+        .collect()
+}
+
+struct Reader<'a> {
+    message: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(message: &'a [u8], pos: usize) -> Self {
+        Self { message, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .message
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("Truncated DNS message"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    /// A possibly compressed name, without the trailing dot (`.` for the root).
+    fn name(&mut self) -> Result<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut resume = None;
+        for _ in 0..128 {
+            let len = *self
+                .message
+                .get(pos)
+                .ok_or_else(|| anyhow!("Truncated DNS name"))? as usize;
+            if len & 0xc0 == 0xc0 {
+                let low = *self
+                    .message
+                    .get(pos + 1)
+                    .ok_or_else(|| anyhow!("Truncated DNS name"))?
+                    as usize;
+                resume.get_or_insert(pos + 2);
+                pos = ((len & 0x3f) << 8) | low;
+                continue;
+            }
+            if len == 0 {
+                self.pos = resume.unwrap_or(pos + 1);
+                return Ok(if labels.is_empty() {
+                    ".".to_string()
+                } else {
+                    labels.join(".")
+                });
+            }
+            let label = self
+                .message
+                .get(pos + 1..pos + 1 + len)
+                .ok_or_else(|| anyhow!("Truncated DNS name"))?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+        Err(anyhow!("DNS name compression loop"))
+    }
+
+    /// A length-prefixed character string.
+    fn text(&mut self) -> Result<String> {
+        let len = self.u8()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+}
+
+/// Rdata in the app's presentation: names without the trailing dot, MX and SRV with
+/// their numbers in front, and unknown types as RFC 3597 `\# len hex`.
+fn decode_rdata(message: &[u8], record_type: &str, start: usize, len: usize) -> Result<String> {
+    let mut r = Reader::at(message, start);
+    let end = start + len;
+    Ok(match record_type {
+        "A" if len == 4 => Ipv4Addr::from(<[u8; 4]>::try_from(r.bytes(4)?)?).to_string(),
+        "AAAA" if len == 16 => Ipv6Addr::from(<[u8; 16]>::try_from(r.bytes(16)?)?).to_string(),
+        "NS" | "CNAME" | "PTR" | "DNAME" => r.name()?,
+        "MX" => format!("{} {}", r.u16()?, r.name()?),
+        "SRV" => format!("{} {} {} {}", r.u16()?, r.u16()?, r.u16()?, r.name()?),
+        "SOA" => format!(
+            "{}. {}. {} {} {} {} {}",
+            r.name()?,
+            r.name()?,
+            r.u32()?,
+            r.u32()?,
+            r.u32()?,
+            r.u32()?,
+            r.u32()?
+        ),
+        "TXT" | "SPF" => {
+            let mut strings = Vec::new();
+            while r.pos < end {
+                strings.push(r.text()?);
+            }
+            match strings.as_slice() {
+                [single] => single.clone(),
+                _ => strings
+                    .iter()
+                    .map(|s| format!("\"{}\"", s.replace('"', "\\\"")))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            }
+        }
+        "CAA" => {
+            let flags = r.u8()?;
+            let tag = r.text()?;
+            let value = String::from_utf8_lossy(r.bytes(end - r.pos)?).into_owned();
+            format!("{flags} {tag} \"{value}\"")
+        }
+        _ => {
+            let hex: String = r.bytes(len)?.iter().map(|b| format!("{b:02x}")).collect();
+            format!("\\# {len} {hex}")
+        }
+    })
+}
+
+/// The wire form of `content`, the inverse of `decode_rdata`.
+pub(crate) fn encode_rdata(record_type: &str, content: &str) -> Result<Vec<u8>> {
+    let content = content.trim();
+    let invalid = || anyhow!("Invalid {record_type} data: {content}");
+    let fields: Vec<&str> = content.split_whitespace().collect();
+    let number = |field: &str| field.parse::<u16>().map_err(|_| invalid());
+    let mut out = Vec::new();
+    if let Some(hex) = content.strip_prefix("\\#") {
+        let mut parts = hex.split_whitespace().skip(1);
+        let digits: String = parts.by_ref().collect();
+        for i in (0..digits.len()).step_by(2) {
+            out.push(
+                u8::from_str_radix(digits.get(i..i + 2).ok_or_else(invalid)?, 16)
+                    .map_err(|_| invalid())?,
+            );
+        }
+        return Ok(out);
+    }
+    match (record_type, fields.as_slice()) {
+        ("A", _) => out.extend(content.parse::<Ipv4Addr>().map_err(|_| invalid())?.octets()),
+        ("AAAA", _) => out.extend(content.parse::<Ipv6Addr>().map_err(|_| invalid())?.octets()),
+        ("NS" | "CNAME" | "PTR" | "DNAME", [target]) => put_name(&mut out, target),
+        ("MX", [priority, target]) => {
+            put_u16(&mut out, number(priority)?);
+            put_name(&mut out, target);
+        }
+        ("SRV", [priority, weight, port, target]) => {
+            for field in [priority, weight, port] {
+                put_u16(&mut out, number(field)?);
+            }
+            put_name(&mut out, target);
+        }
+        ("SOA", [mname, rname, numbers @ ..]) if numbers.len() == 5 => {
+            put_name(&mut out, mname);
+            put_name(&mut out, rname);
+            for field in numbers {
+                out.extend(field.parse::<u32>().map_err(|_| invalid())?.to_be_bytes());
+            }
+        }
+        ("TXT" | "SPF", _) => {
+            for string in txt_strings(content) {
+                for chunk in string.as_bytes().chunks(255) {
+                    out.push(chunk.len() as u8);
+                    out.extend_from_slice(chunk);
+                }
+            }
+        }
+        ("CAA", [flags, tag, ..]) => {
+            out.push(flags.parse().map_err(|_| invalid())?);
+            out.push(tag.len() as u8);
+            out.extend_from_slice(tag.as_bytes());
+            let value = content
+                .splitn(3, char::is_whitespace)
+                .nth(2)
+                .unwrap_or_default()
+                .trim()
+                .trim_matches('"');
+            out.extend_from_slice(value.as_bytes());
+        }
+        _ => return Err(invalid()),
+    }
+    Ok(out)
+}
+
+/// The character strings of TXT content: quoted strings as written, or the whole
+/// content as one string when it isn't quoted.
+fn txt_strings(content: &str) -> Vec<String> {
+    if !content.starts_with('"') {
+        return vec![content.to_string()];
+    }
+    let mut strings = Vec::new();
+    let mut chars = content.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '"' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => string.extend(chars.next()),
+                '"' => break,
+                ch => string.push(ch),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
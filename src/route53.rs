@@ -0,0 +1,788 @@
+//! Amazon Route53 behind `DnsBackend`: hosted zones map to `Zone`, and each value of a
+//! resource record set becomes its own `DnsRecord` row. Rows of one set share the set's
+//! id (`name|type|set identifier`) and differ only by their index, so edits rewrite the
+//! whole set. Requests go to the Route53 XML API, signed with SigV4.
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::Method;
+use reqwest::blocking::Client;
+use ring::{digest, hmac};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{
+    Account, AuditEntry, BatchRequest, BatchResult, CloudflareAccount, DnsBackend, DnsRecord,
+    DnssecStatus, ImportResult, Listing, Page, Timeouts, TokenStatus, Zone, ZoneSetup,
+    compact_timestamp, now_unix, truncate_body,
+};
+
+const ENDPOINT: &str = "https://route53.amazonaws.com";
+const API_VERSION: &str = "2013-04-01";
+/// Route53 is a global service signed for this region.
+const REGION: &str = "us-east-1";
+const SERVICE: &str = "route53";
+/// Safeguard against runaway pagination loops, as for Cloudflare.
+const MAX_PAGES: u32 = 50;
+
+/// Name and type a truncated record set listing resumes from.
+type SetMarker = (String, String);
+
+pub(crate) struct Route53Backend {
+    client: Client,
+}
+
+impl Route53Backend {
+    pub(crate) fn new(timeouts: Timeouts) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent("nyxflare/0.1")
+            .timeout(Duration::from_secs(timeouts.request_secs))
+            .connect_timeout(Duration::from_secs(timeouts.connect_secs))
+            .build()?;
+        Ok(Self { client })
+    }
+
+    /// Sends a signed request and returns the response body, or the error Route53 gave.
+    /// The account's `base_url` replaces the public endpoint when set.
+    fn request(
+        &self,
+        account: &Account,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<String>,
+    ) -> Result<String> {
+        let key_id = account
+            .aws_access_key_id
+            .as_deref()
+            .ok_or_else(|| anyhow!("Route53 account {} needs aws_access_key_id", account.name))?;
+        let endpoint = account
+            .base_url
+            .as_deref()
+            .map(|base| base.trim_end_matches('/'))
+            .filter(|base| !base.is_empty())
+            .unwrap_or(ENDPOINT);
+        let path = format!("/{API_VERSION}{path}");
+        let url = reqwest::Url::parse(&format!("{endpoint}{path}"))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let query = canonical_query(query);
+        let body = body.unwrap_or_default();
+        let credentials = Credentials {
+            key_id,
+            secret: &account.api_token,
+        };
+        let signed = sign(
+            &credentials,
+            method.as_str(),
+            &host,
+            &path,
+            &query,
+            &body,
+            now_unix(),
+        );
+
+        let full_url = if query.is_empty() {
+            format!("{endpoint}{path}")
+        } else {
+            format!("{endpoint}{path}?{query}")
+        };
+        let response = self
+            .client
+            .request(method, full_url)
+            .header("x-amz-date", signed.amz_date)
+            .header("authorization", signed.authorization)
+            .header("content-type", "application/xml")
+            .body(body)
+            .send()
+            .with_context(|| format!("Route53 request to {path}"))?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        if !status.is_success() {
+            let code = element(&text, "Code").unwrap_or("Error");
+            let message = element(&text, "Message").map(unescape).unwrap_or_default();
+            return Err(anyhow!(
+                "Route53 ({status}): {code}: {message} | body: {}",
+                truncate_body(&text)
+            ));
+        }
+        Ok(text)
+    }
+
+    fn list_sets(
+        &self,
+        account: &Account,
+        zone: &Zone,
+        start: Option<(&str, &str)>,
+        max_items: &str,
+    ) -> Result<(Vec<RecordSet>, Option<SetMarker>)> {
+        let mut query = vec![("maxitems", max_items)];
+        if let Some((name, record_type)) = start {
+            query.push(("name", name));
+            query.push(("type", record_type));
+        }
+        let text = self.request(
+            account,
+            Method::GET,
+            &format!("/hostedzone/{}/rrset", zone.id),
+            &query,
+            None,
+        )?;
+        let sets = elements(&text, "ResourceRecordSet")
+            .into_iter()
+            .map(RecordSet::parse)
+            .collect();
+        let next = (element(&text, "IsTruncated") == Some("true"))
+            .then(|| {
+                Some((
+                    unescape(element(&text, "NextRecordName")?),
+                    element(&text, "NextRecordType")?.to_string(),
+                ))
+            })
+            .flatten();
+        Ok((sets, next))
+    }
+
+    /// Every record set in the zone, following Route53's name/type markers.
+    fn all_sets(&self, account: &Account, zone: &Zone) -> Result<Vec<RecordSet>> {
+        let mut sets = Vec::new();
+        let mut start: Option<SetMarker> = None;
+        for _ in 0..MAX_PAGES {
+            let marker = start.as_ref().map(|(n, t)| (n.as_str(), t.as_str()));
+            let (page, next) = self.list_sets(account, zone, marker, "300")?;
+            sets.extend(page);
+            match next {
+                Some(next) => start = Some(next),
+                None => break,
+            }
+        }
+        Ok(sets)
+    }
+
+    /// The set with this key as it is now, if it exists.
+    fn find_set(&self, account: &Account, zone: &Zone, key: &SetKey) -> Result<Option<RecordSet>> {
+        let (sets, _) =
+            self.list_sets(account, zone, Some((&key.name, &key.record_type)), "100")?;
+        Ok(sets.into_iter().find(|set| set.key() == *key))
+    }
+
+    /// The set a row belongs to, as it is now.
+    fn fetch_set(&self, account: &Account, zone: &Zone, key: &SetKey) -> Result<RecordSet> {
+        self.find_set(account, zone, key)?
+            .ok_or_else(|| anyhow!("Record set {} {} not found", key.name, key.record_type))
+    }
+
+    fn change(&self, account: &Account, zone: &Zone, changes: &[(&str, &RecordSet)]) -> Result<()> {
+        let changes: String = changes
+            .iter()
+            .map(|(action, set)| {
+                format!("<Change><Action>{action}</Action>{}</Change>", set.to_xml())
+            })
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ChangeResourceRecordSetsRequest xmlns=\"https://route53.amazonaws.com/doc/{API_VERSION}/\">\
+             <ChangeBatch><Changes>{changes}</Changes></ChangeBatch>\
+             </ChangeResourceRecordSetsRequest>"
+        );
+        self.request(
+            account,
+            Method::POST,
+            &format!("/hostedzone/{}/rrset/", zone.id),
+            &[],
+            Some(body),
+        )?;
+        Ok(())
+    }
+
+    /// Adds `record`'s value to its set, creating the set when it doesn't exist. The
+    /// set takes the record's TTL. Returns the change and the row as stored.
+    fn add_value(
+        &self,
+        account: &Account,
+        zone: &Zone,
+        record: &DnsRecord,
+    ) -> Result<(&'static str, RecordSet, DnsRecord)> {
+        let key = SetKey::for_record(record);
+        let (action, mut set) = match self.find_set(account, zone, &key)? {
+            Some(set) => {
+                set.ensure_editable()?;
+                ("UPSERT", set)
+            }
+            None => ("CREATE", RecordSet::empty(key)),
+        };
+        set.ttl = Some(record.ttl);
+        set.values
+            .push(wire_value(&record.record_type, &record.content));
+        let row = set.row(set.values.len() - 1);
+        Ok((action, set, row))
+    }
+}
+
+impl DnsBackend for Route53Backend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        let mut zones = Vec::new();
+        let mut marker: Option<String> = None;
+        let mut pages = 0;
+        let mut truncated = false;
+        loop {
+            pages += 1;
+            let mut query = vec![("maxitems", "100")];
+            if let Some(marker) = &marker {
+                query.push(("marker", marker.as_str()));
+            }
+            let text = self.request(account, Method::GET, "/hostedzone", &query, None)?;
+            zones.extend(elements(&text, "HostedZone").into_iter().map(parse_zone));
+            if element(&text, "IsTruncated") != Some("true") {
+                break;
+            }
+            if pages >= MAX_PAGES {
+                truncated = true;
+                break;
+            }
+            marker = element(&text, "NextMarker").map(str::to_string);
+            if marker.is_none() {
+                break;
+            }
+        }
+        Ok(Listing {
+            items: zones,
+            pages,
+            truncated,
+            total_count: None,
+        })
+    }
+
+    fn delete_zone(&mut self, _account: &Account, _zone: &Zone) -> Result<()> {
+        Err(unsupported("Deleting zones"))
+    }
+
+    fn create_zone(&mut self, _account: &Account, _name: &str, _setup: ZoneSetup) -> Result<Zone> {
+        Err(unsupported("Creating zones"))
+    }
+
+    /// Route53 pages by name markers rather than numbers, so the whole zone comes back
+    /// as page 1.
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        if page > 1 {
+            return Ok(Page {
+                items: Vec::new(),
+                page,
+                total_pages: 1,
+                total_count: None,
+            });
+        }
+        let items: Vec<DnsRecord> = self
+            .all_sets(account, zone)?
+            .iter()
+            .filter(|set| record_type.is_none_or(|t| set.record_type == t))
+            .flat_map(RecordSet::rows)
+            .collect();
+        Ok(Page {
+            total_count: Some(items.len() as u32),
+            items,
+            page: 1,
+            total_pages: 1,
+        })
+    }
+
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        let needle = query.to_lowercase();
+        Ok(self
+            .all_sets(account, zone)?
+            .iter()
+            .flat_map(RecordSet::rows)
+            .filter(|r| {
+                r.name.to_lowercase().contains(&needle)
+                    || r.content.to_lowercase().contains(&needle)
+            })
+            .collect())
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let (action, set, row) = self.add_value(account, zone, &record)?;
+        self.change(account, zone, &[(action, &set)])?;
+        Ok(row)
+    }
+
+    /// Rewrites the row's value within its set; a changed name or type moves the value
+    /// to that set instead, in one atomic change batch.
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        let (key, index) = parse_row_id(&record.id)?;
+        let mut set = self.fetch_set(account, zone, &key)?;
+        set.ensure_editable()?;
+        if index >= set.values.len() {
+            return Err(anyhow!(
+                "Record {} changed on Route53; reload with r",
+                record.id
+            ));
+        }
+
+        if SetKey::for_record(&record) == key {
+            set.ttl = Some(record.ttl);
+            set.values[index] = wire_value(&record.record_type, &record.content);
+            self.change(account, zone, &[("UPSERT", &set)])?;
+            return Ok(set.row(index));
+        }
+
+        let (action, target, row) = self.add_value(account, zone, &record)?;
+        let remaining = set.without(index);
+        let source = match &remaining {
+            Some(remaining) => ("UPSERT", remaining),
+            None => ("DELETE", &set),
+        };
+        self.change(account, zone, &[source, (action, &target)])?;
+        Ok(row)
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        let (key, index) = parse_row_id(record_id)?;
+        let set = self.fetch_set(account, zone, &key)?;
+        set.ensure_editable()?;
+        match set.without(index) {
+            Some(remaining) => self.change(account, zone, &[("UPSERT", &remaining)]),
+            // Route53 deletes a set only when given its current values exactly.
+            None => self.change(account, zone, &[("DELETE", &set)]),
+        }
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        let (key, index) = parse_row_id(record_id)?;
+        let set = self.fetch_set(account, zone, &key)?;
+        set.rows()
+            .nth(index)
+            .ok_or_else(|| anyhow!("Record {record_id} not found"))
+    }
+
+    fn batch(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _request: BatchRequest,
+    ) -> Result<BatchResult> {
+        Err(unsupported("Batch changes"))
+    }
+
+    /// Route53 keeps no per-record history; CloudTrail is the place to look.
+    fn record_audit_log(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        self.request(account, Method::GET, "/hostedzonecount", &[], None)?;
+        Ok(TokenStatus {
+            status: "active".to_string(),
+            expires_on: None,
+        })
+    }
+
+    fn list_cf_accounts(&mut self, _account: &Account) -> Result<Vec<CloudflareAccount>> {
+        Ok(Vec::new())
+    }
+
+    fn export_zone(&mut self, _account: &Account, _zone: &Zone) -> Result<String> {
+        Err(unsupported("Zone export"))
+    }
+
+    fn import_zone(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _path: &Path,
+        _proxied: bool,
+    ) -> Result<ImportResult> {
+        Err(unsupported("Zone import"))
+    }
+
+    fn dnssec_status(&mut self, _account: &Account, _zone: &Zone) -> Result<DnssecStatus> {
+        Err(unsupported("DNSSEC"))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _enabled: bool,
+    ) -> Result<DnssecStatus> {
+        Err(unsupported("DNSSEC"))
+    }
+}
+
+fn unsupported(what: &str) -> anyhow::Error {
+    anyhow!("{what} isn't supported for Route53 accounts")
+}
+
+fn parse_zone(xml: &str) -> Zone {
+    let id = element(xml, "Id").unwrap_or_default();
+    Zone {
+        id: id.trim_start_matches("/hostedzone/").to_string(),
+        name: display_name(element(xml, "Name").unwrap_or_default()),
+        status: (element(xml, "PrivateZone") == Some("true")).then(|| "private".to_string()),
+        ..Zone::default()
+    }
+}
+
+/// Identifies a record set: Route53 allows several sets per name and type only when
+/// they carry distinct set identifiers (weighted, latency and similar routing).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SetKey {
+    /// Absolute, as Route53 writes it (`www.example.com.`).
+    name: String,
+    record_type: String,
+    set_identifier: Option<String>,
+}
+
+impl SetKey {
+    fn for_record(record: &DnsRecord) -> Self {
+        let name = record.name.trim().trim_end_matches('.');
+        Self {
+            name: format!("{name}."),
+            record_type: record.record_type.trim().to_ascii_uppercase(),
+            set_identifier: None,
+        }
+    }
+}
+
+/// `name|type|set identifier|index`, the row id of one value of a set.
+fn row_id(key: &SetKey, index: usize) -> String {
+    format!(
+        "{}|{}|{}|{index}",
+        key.name,
+        key.record_type,
+        key.set_identifier.as_deref().unwrap_or_default()
+    )
+}
+
+pub(crate) fn parse_row_id(id: &str) -> Result<(SetKey, usize)> {
+    let invalid = || anyhow!("{id} is not a Route53 record id");
+    let mut parts = id.splitn(3, '|');
+    let (name, record_type, rest) = (
+        parts.next().ok_or_else(invalid)?,
+        parts.next().ok_or_else(invalid)?,
+        parts.next().ok_or_else(invalid)?,
+    );
+    let (set_identifier, index) = rest.rsplit_once('|').ok_or_else(invalid)?;
+    Ok((
+        SetKey {
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            set_identifier: (!set_identifier.is_empty()).then(|| set_identifier.to_string()),
+        },
+        index.parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// A resource record set as Route53 returns it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RecordSet {
+    name: String,
+    record_type: String,
+    ttl: Option<u32>,
+    values: Vec<String>,
+    set_identifier: Option<String>,
+    /// Target of an alias set, which has no values of its own.
+    alias: Option<String>,
+}
+
+impl RecordSet {
+    pub(crate) fn parse(xml: &str) -> Self {
+        Self {
+            name: unescape(element(xml, "Name").unwrap_or_default()),
+            record_type: element(xml, "Type").unwrap_or_default().to_string(),
+            ttl: element(xml, "TTL").and_then(|ttl| ttl.parse().ok()),
+            values: elements(xml, "Value").into_iter().map(unescape).collect(),
+            set_identifier: element(xml, "SetIdentifier").map(unescape),
+            alias: element(xml, "DNSName").map(unescape),
+        }
+    }
+
+    fn empty(key: SetKey) -> Self {
+        Self {
+            name: key.name,
+            record_type: key.record_type,
+            ttl: None,
+            values: Vec::new(),
+            set_identifier: key.set_identifier,
+            alias: None,
+        }
+    }
+
+    fn key(&self) -> SetKey {
+        SetKey {
+            name: self.name.clone(),
+            record_type: self.record_type.clone(),
+            set_identifier: self.set_identifier.clone(),
+        }
+    }
+
+    /// Alias and routing-policy sets carry settings these rows can't express, so
+    /// rewriting them here would drop those settings.
+    fn ensure_editable(&self) -> Result<()> {
+        if self.alias.is_some() {
+            return Err(anyhow!("{} is an alias record; edit it in AWS", self.name));
+        }
+        if self.set_identifier.is_some() {
+            return Err(anyhow!(
+                "{} uses a routing policy; edit it in AWS",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// One row per value (a single row for an alias), sharing the set's id.
+    pub(crate) fn rows(&self) -> impl Iterator<Item = DnsRecord> + '_ {
+        let count = if self.alias.is_some() {
+            1
+        } else {
+            self.values.len()
+        };
+        (0..count).map(|index| self.row(index))
+    }
+
+    fn row(&self, index: usize) -> DnsRecord {
+        let mut unmodeled = Vec::new();
+        if self.alias.is_some() {
+            unmodeled.push("alias".to_string());
+        }
+        if self.set_identifier.is_some() {
+            unmodeled.push("routing policy".to_string());
+        }
+        let content = match &self.alias {
+            Some(target) => display_name(target),
+            None => display_value(&self.record_type, &self.values[index]),
+        };
+        DnsRecord {
+            id: row_id(&self.key(), index),
+            name: display_name(&self.name),
+            record_type: self.record_type.clone(),
+            content,
+            ttl: self.ttl.unwrap_or_default(),
+            proxied: false,
+            unmodeled,
+            modified_on: None,
+        }
+    }
+
+    /// The set minus the value at `index`, or `None` when that was its last value.
+    fn without(&self, index: usize) -> Option<Self> {
+        let mut rest = self.clone();
+        if index < rest.values.len() {
+            rest.values.remove(index);
+        }
+        (!rest.values.is_empty()).then_some(rest)
+    }
+
+    fn to_xml(&self) -> String {
+        let values: String = self
+            .values
+            .iter()
+            .map(|value| {
+                format!(
+                    "<ResourceRecord><Value>{}</Value></ResourceRecord>",
+                    escape(value)
+                )
+            })
+            .collect();
+        format!(
+            "<ResourceRecordSet><Name>{}</Name><Type>{}</Type><TTL>{}</TTL>\
+             <ResourceRecords>{values}</ResourceRecords></ResourceRecordSet>",
+            escape(&self.name),
+            escape(&self.record_type),
+            self.ttl.unwrap_or(300)
+        )
+    }
+}
+
+/// Drops the root dot and decodes `\052`-style escapes (`\052` is the `*` of wildcards).
+fn display_name(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if digits.len() == 3
+                && let Ok(code) = u8::from_str_radix(&digits, 8)
+            {
+                out.push(code as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// MX values keep their priority in front (`10 mail.example.com`); names lose the root dot.
+fn display_value(record_type: &str, value: &str) -> String {
+    match record_type {
+        "TXT" | "SPF" => value.to_string(),
+        _ => value.trim_end_matches('.').to_string(),
+    }
+}
+
+/// TXT values must be quoted on the wire.
+fn wire_value(record_type: &str, content: &str) -> String {
+    let content = content.trim();
+    match record_type.to_ascii_uppercase().as_str() {
+        "TXT" | "SPF" if !content.starts_with('"') => {
+            format!("\"{}\"", content.replace('"', "\\\""))
+        }
+        _ => content.to_string(),
+    }
+}
+
+/// The contents of each `<tag>…</tag>` element, in document order. Route53 responses
+/// never nest an element inside one of the same name, so no real parser is needed.
+pub(crate) fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else {
+            break;
+        };
+        found.push(&after[..end]);
+        rest = &after[end + close.len()..];
+    }
+    found
+}
+
+pub(crate) fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    elements(xml, tag).into_iter().next()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Query string with keys sorted and values percent-encoded, as SigV4 requires.
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k), uri_encode(v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn uri_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+pub(crate) struct Credentials<'a> {
+    pub(crate) key_id: &'a str,
+    pub(crate) secret: &'a str,
+}
+
+/// Headers carrying a SigV4 signature.
+pub(crate) struct Signed {
+    pub(crate) amz_date: String,
+    pub(crate) authorization: String,
+}
+
+/// Signs a request made at `timestamp` (Unix seconds) with AWS Signature Version 4.
+/// `query` must already be canonical.
+pub(crate) fn sign(
+    credentials: &Credentials<'_>,
+    method: &str,
+    host: &str,
+    path: &str,
+    query: &str,
+    body: &str,
+    timestamp: i64,
+) -> Signed {
+    let amz_date = compact_timestamp(timestamp);
+    let date = &amz_date[..8];
+    let canonical_request = format!(
+        "{method}\n{path}\n{query}\nhost:{host}\nx-amz-date:{amz_date}\n\nhost;x-amz-date\n{}",
+        hex_sha256(body.as_bytes())
+    );
+    let scope = format!("{date}/{REGION}/{SERVICE}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex_sha256(canonical_request.as_bytes())
+    );
+    let key = signing_key(credentials.secret, date, REGION, SERVICE);
+    let signature = hex(hmac::sign(&key, string_to_sign.as_bytes()).as_ref());
+    Signed {
+        authorization: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders=host;x-amz-date, Signature={signature}",
+            credentials.key_id
+        ),
+        amz_date,
+    }
+}
+
+pub(crate) fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> hmac::Key {
+    let mac = |key: &[u8], data: &str| {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+    };
+    let k_date = mac(format!("AWS4{secret}").as_bytes(), date);
+    let k_region = mac(k_date.as_ref(), region);
+    let k_service = mac(k_region.as_ref(), service);
+    let k_signing = mac(k_service.as_ref(), "aws4_request");
+    hmac::Key::new(hmac::HMAC_SHA256, k_signing.as_ref())
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
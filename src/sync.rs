@@ -0,0 +1,250 @@
+//! `nyxflare apply` and the TUI's `A`: the records a file says a zone should have, and
+//! the [`ChangeSet`] that gets the live zone there. [`plan`] only compares two record
+//! lists; sending the steps is up to the caller.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+use crate::changeset::{ChangeSet, Step};
+use crate::{Account, ConfigFormat, DnsRecord, bind, is_proxiable, parse_bulk_ttl, qualify_name};
+
+/// A desired-state file: every record one zone should have, as YAML, TOML or JSON.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StateFile {
+    /// The zone the file is for; `--zone`, when also given, has to agree.
+    #[serde(default)]
+    pub(crate) zone: Option<String>,
+    #[serde(default)]
+    records: Vec<StateRecord>,
+}
+
+/// A name and type in a [`StateFile`], with its content; a list of contents makes a
+/// round-robin set.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateRecord {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: Contents,
+    /// Seconds or `"auto"`; the account's `default_ttl`, else auto, when left out.
+    #[serde(default)]
+    ttl: Option<StateTtl>,
+    /// The account's `default_proxied` when left out, as for a new record.
+    #[serde(default)]
+    proxied: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Contents {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StateTtl {
+    Seconds(u32),
+    Text(String),
+}
+
+impl StateFile {
+    /// Reads `path` as YAML when it ends in `.yaml` or `.yml`, as TOML when it ends in
+    /// `.toml`, else as JSON.
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let shown = path.display();
+        let text = fs::read_to_string(path).with_context(|| format!("Reading {shown}"))?;
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+        {
+            return serde_yaml::from_str(&text).with_context(|| format!("Reading {shown}"));
+        }
+        match ConfigFormat::of(path) {
+            ConfigFormat::Toml => toml::from_str(&text).with_context(|| format!("Reading {shown}")),
+            ConfigFormat::Json => {
+                serde_json::from_str(&text).with_context(|| format!("Reading {shown}"))
+            }
+        }
+    }
+
+    /// The zone to apply the file to: `given`, which must match the file's own `zone`
+    /// when it names one, else that.
+    pub(crate) fn zone_name(&self, given: Option<&str>) -> Result<Option<String>> {
+        match (given, self.zone.as_deref()) {
+            (Some(given), Some(own))
+                if !given
+                    .trim_end_matches('.')
+                    .eq_ignore_ascii_case(own.trim_end_matches('.')) =>
+            {
+                Err(anyhow!("The file is for {own}, not {given}"))
+            }
+            (given, own) => Ok(given.or(own).map(str::to_string)),
+        }
+    }
+
+    /// The file's records in `zone_name`, one per content, with names qualified and the
+    /// account's defaults filled in.
+    pub(crate) fn records(&self, zone_name: &str, account: &Account) -> Result<Vec<DnsRecord>> {
+        let mut records: Vec<DnsRecord> = Vec::new();
+        for entry in &self.records {
+            let name = qualify_name(&entry.name, zone_name);
+            let record_type = entry.record_type.trim().to_uppercase();
+            if record_type.is_empty() {
+                return Err(anyhow!("{name} needs a type"));
+            }
+            if record_type == "SOA" {
+                return Err(anyhow!(
+                    "SOA records are left to the provider; take {name}'s out"
+                ));
+            }
+            let contents = match &entry.content {
+                Contents::One(content) => std::slice::from_ref(content),
+                Contents::Many(contents) => contents.as_slice(),
+            };
+            if contents.is_empty() || contents.iter().any(|content| content.trim().is_empty()) {
+                return Err(anyhow!("{record_type} {name} needs a content"));
+            }
+            let ttl = match &entry.ttl {
+                Some(StateTtl::Seconds(ttl)) => parse_bulk_ttl(&ttl.to_string()),
+                Some(StateTtl::Text(ttl)) => parse_bulk_ttl(ttl),
+                None => Ok(account.default_ttl.unwrap_or(1)),
+            }
+            .with_context(|| format!("{record_type} {name}"))?;
+            let allowed = account.provider.has_proxy() && is_proxiable(&record_type);
+            let proxied = match entry.proxied {
+                Some(true) if !allowed => {
+                    return Err(anyhow!(
+                        "{record_type} {name}: {record_type} records can't be proxied on {}",
+                        account.provider.label()
+                    ));
+                }
+                Some(proxied) => proxied,
+                None => allowed && account.default_proxied.unwrap_or(true),
+            };
+            for content in contents {
+                let record = DnsRecord {
+                    id: String::new(),
+                    name: name.clone(),
+                    record_type: record_type.clone(),
+                    content: content.trim().to_string(),
+                    ttl,
+                    proxied,
+                    unmodeled: Vec::new(),
+                    modified_on: None,
+                };
+                if records
+                    .iter()
+                    .any(|other| key(other) == key(&record) && same_content(other, &record))
+                {
+                    return Err(anyhow!(
+                        "{record_type} {name} {} is listed twice",
+                        record.content
+                    ));
+                }
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// The steps that turn `live` into `desired`. Records are matched by name and type
+/// (ignoring case and a trailing dot), so the file's contents for a name and type are
+/// the whole set: those already live stay, at most with a new TTL or proxying; the
+/// rest replace the live ones the file lacks, in order, keeping their ids; any left over
+/// are created or deleted. A name and type the file doesn't have at all is deleted only
+/// with `prune`. SOA records are never touched.
+pub(crate) fn plan(live: &[DnsRecord], desired: &[DnsRecord], prune: bool) -> ChangeSet {
+    // Per name and type: the live records, and the ones the file has.
+    let mut sets: BTreeMap<_, (Vec<&DnsRecord>, Vec<&DnsRecord>)> = BTreeMap::new();
+    let is_soa = |record: &&DnsRecord| record.record_type.eq_ignore_ascii_case("SOA");
+    for record in live.iter().filter(|record| !is_soa(record)) {
+        sets.entry(key(record)).or_default().0.push(record);
+    }
+    for record in desired.iter().filter(|record| !is_soa(record)) {
+        sets.entry(key(record)).or_default().1.push(record);
+    }
+
+    let (mut unchanged, mut kept) = (0, 0);
+    let (mut deletes, mut updates, mut creates) = (Vec::new(), Vec::new(), Vec::new());
+    let update = |before: &DnsRecord, wanted: &DnsRecord| Step::Update {
+        before: before.clone(),
+        after: DnsRecord {
+            content: wanted.content.clone(),
+            ttl: wanted.ttl,
+            proxied: wanted.proxied,
+            ..before.clone()
+        },
+    };
+    for (mut have, mut want) in sets.into_values() {
+        if want.is_empty() {
+            if prune {
+                deletes.extend(have.into_iter().map(|record| Step::Delete {
+                    record: record.clone(),
+                }));
+            } else {
+                kept += have.len();
+            }
+            continue;
+        }
+        want.retain(|wanted| {
+            let Some(pos) = have.iter().position(|live| same_content(live, wanted)) else {
+                return true;
+            };
+            let live = have.remove(pos);
+            if (live.ttl, live.proxied) == (wanted.ttl, wanted.proxied) {
+                unchanged += 1;
+            } else {
+                updates.push(update(live, wanted));
+            }
+            false
+        });
+        let mut have = have.into_iter();
+        for wanted in want {
+            match have.next() {
+                Some(live) => updates.push(update(live, wanted)),
+                None => creates.push(Step::Create {
+                    record: wanted.clone(),
+                }),
+            }
+        }
+        deletes.extend(have.map(|record| Step::Delete {
+            record: record.clone(),
+        }));
+    }
+    let mut plan = ChangeSet::ordered(deletes, updates, creates);
+    plan.unchanged = unchanged;
+    if kept > 0 {
+        plan.left_out
+            .push((kept, "not in the file left alone".to_string()));
+    }
+    plan
+}
+
+/// What records are matched by: the name without case or a trailing dot, and the type.
+pub(crate) fn key(record: &DnsRecord) -> (String, String) {
+    (
+        record.name.trim().trim_end_matches('.').to_lowercase(),
+        record.record_type.trim().to_uppercase(),
+    )
+}
+
+/// Whether two records of the same name and type hold the same content; host names
+/// compare without case or a trailing dot, and TXT data quoted or not.
+pub(crate) fn same_content(a: &DnsRecord, b: &DnsRecord) -> bool {
+    let (x, y) = (a.content.trim(), b.content.trim());
+    match key(a).1.as_str() {
+        "CNAME" | "NS" | "PTR" | "MX" => x
+            .trim_end_matches('.')
+            .eq_ignore_ascii_case(y.trim_end_matches('.')),
+        "TXT" | "SPF" => bind::normalize_txt(x) == bind::normalize_txt(y),
+        _ => x == y,
+    }
+}
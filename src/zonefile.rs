@@ -0,0 +1,420 @@
+//! Plain BIND zone files behind `DnsBackend`, for editing zones that never touch an API.
+//! Each file in the account's `zone_dir` is a zone; edits rewrite the whole file
+//! atomically (temp file + rename) and bump the SOA serial.
+//!
+//! Zone files carry no record ids, so the backend hands out its own and keeps them
+//! stable across its own writes. When a file changes on disk, records are matched back
+//! to their old ids by name, type and content.
+
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{
+    Account, AuditEntry, BatchRequest, BatchResult, CloudflareAccount, DnsBackend, DnsRecord,
+    DnssecStatus, ImportResult, Listing, Page, TokenStatus, Zone, ZoneSetup, bind, civil_from_days,
+    now_unix,
+};
+
+/// Suffix of files created for new zones; existing files may also use `db.<zone>`.
+const ZONE_SUFFIX: &str = ".zone";
+
+#[derive(Default)]
+pub(crate) struct ZoneFileBackend {
+    files: HashMap<PathBuf, ZoneFile>,
+    next_id: u64,
+}
+
+/// A zone file as last read or written, with the ids assigned to its records.
+struct ZoneFile {
+    modified: Option<SystemTime>,
+    records: Vec<DnsRecord>,
+}
+
+impl ZoneFileBackend {
+    fn dir(account: &Account) -> Result<PathBuf> {
+        account
+            .zone_dir
+            .as_deref()
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("Zone-file account {} needs zone_dir", account.name))
+    }
+
+    fn path(account: &Account, zone: &Zone) -> Result<PathBuf> {
+        Ok(Self::dir(account)?.join(&zone.id))
+    }
+
+    /// The zone's records, re-read when the file changed since it was last seen.
+    fn records(&mut self, account: &Account, zone: &Zone) -> Result<&mut Vec<DnsRecord>> {
+        let path = Self::path(account, zone)?;
+        let modified = modified(&path);
+        let stale = self
+            .files
+            .get(&path)
+            .is_none_or(|file| file.modified.is_none() || file.modified != modified);
+        if stale {
+            let text =
+                fs::read_to_string(&path).with_context(|| format!("Reading {}", path.display()))?;
+            let mut parsed = bind::parse_zone(&text, &zone.name)
+                .with_context(|| format!("Parsing {}", path.display()))?;
+            let mut previous = self
+                .files
+                .remove(&path)
+                .map(|file| file.records)
+                .unwrap_or_default();
+            for record in &mut parsed {
+                let reused = previous.iter().position(|old| {
+                    old.name == record.name
+                        && old.record_type == record.record_type
+                        && old.content == record.content
+                });
+                record.id = match reused {
+                    Some(idx) => previous.swap_remove(idx).id,
+                    None => self.new_id(),
+                };
+            }
+            self.files.insert(
+                path.clone(),
+                ZoneFile {
+                    modified,
+                    records: parsed,
+                },
+            );
+        }
+        Ok(&mut self.files.get_mut(&path).expect("just loaded").records)
+    }
+
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("zf-{}", self.next_id)
+    }
+
+    /// Applies `change` to the zone's records and writes the file back with a bumped
+    /// serial. Nothing is written when `change` fails.
+    fn edit<T>(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        change: impl FnOnce(&mut Self, &mut Vec<DnsRecord>) -> Result<T>,
+    ) -> Result<T> {
+        let mut records = self.records(account, zone)?.clone();
+        let result = change(self, &mut records)?;
+        bump_soa_serial(&mut records);
+        let path = Self::path(account, zone)?;
+        write_atomically(&path, &bind::write_zone(&zone.name, &records))?;
+        self.files.insert(
+            path.clone(),
+            ZoneFile {
+                modified: modified(&path),
+                records,
+            },
+        );
+        Ok(result)
+    }
+}
+
+impl DnsBackend for ZoneFileBackend {
+    fn list_zones(&mut self, account: &Account) -> Result<Listing<Zone>> {
+        let dir = Self::dir(account)?;
+        let mut zones: Vec<Zone> = fs::read_dir(&dir)
+            .with_context(|| format!("Reading {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+            .filter_map(|entry| {
+                let file = entry.file_name().into_string().ok()?;
+                let name = zone_name(&file)?;
+                Some(Zone {
+                    id: file,
+                    name,
+                    ..Zone::default()
+                })
+            })
+            .collect();
+        zones.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Listing::single(zones))
+    }
+
+    fn delete_zone(&mut self, account: &Account, zone: &Zone) -> Result<()> {
+        let path = Self::path(account, zone)?;
+        fs::remove_file(&path).with_context(|| format!("Deleting {}", path.display()))?;
+        self.files.remove(&path);
+        Ok(())
+    }
+
+    /// Starts `<name>.zone` with an SOA and one NS record to fill in.
+    fn create_zone(&mut self, account: &Account, name: &str, _setup: ZoneSetup) -> Result<Zone> {
+        let name = name.trim().trim_end_matches('.').to_string();
+        let zone = Zone {
+            id: format!("{name}{ZONE_SUFFIX}"),
+            name: name.clone(),
+            ..Zone::default()
+        };
+        let path = Self::path(account, &zone)?;
+        if path.exists() {
+            return Err(anyhow!("{} already exists", path.display()));
+        }
+        let (year, month, day) = civil_from_days(now_unix().div_euclid(86_400));
+        let records = [
+            DnsRecord {
+                id: String::new(),
+                name: name.clone(),
+                record_type: "SOA".to_string(),
+                content: format!(
+                    "ns1.{name}. hostmaster.{name}. {year:04}{month:02}{day:02}01 3600 900 1209600 300"
+                ),
+                ttl: 3600,
+                proxied: false,
+                unmodeled: Vec::new(),
+                modified_on: None,
+            },
+            DnsRecord {
+                id: String::new(),
+                name: name.clone(),
+                record_type: "NS".to_string(),
+                content: format!("ns1.{name}"),
+                ttl: 3600,
+                proxied: false,
+                unmodeled: Vec::new(),
+                modified_on: None,
+            },
+        ];
+        write_atomically(&path, &bind::write_zone(&name, &records))?;
+        Ok(zone)
+    }
+
+    /// Files are read whole, so every record comes back as page 1.
+    fn list_records_page(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        page: u32,
+        record_type: Option<&str>,
+    ) -> Result<Page<DnsRecord>> {
+        let records = self.records(account, zone)?;
+        let items: Vec<DnsRecord> = if page > 1 {
+            Vec::new()
+        } else {
+            records
+                .iter()
+                .filter(|r| record_type.is_none_or(|t| r.record_type == t))
+                .cloned()
+                .collect()
+        };
+        Ok(Page {
+            total_count: Some(items.len() as u32),
+            items,
+            page,
+            total_pages: 1,
+        })
+    }
+
+    fn search_records(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        query: &str,
+    ) -> Result<Vec<DnsRecord>> {
+        let needle = query.to_lowercase();
+        Ok(self
+            .records(account, zone)?
+            .iter()
+            .filter(|r| {
+                r.name.to_lowercase().contains(&needle)
+                    || r.content.to_lowercase().contains(&needle)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn create_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        mut record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.edit(account, zone, |backend, records| {
+            record.id = backend.new_id();
+            record.proxied = false;
+            records.push(record.clone());
+            Ok(record)
+        })
+    }
+
+    fn update_record(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        mut record: DnsRecord,
+    ) -> Result<DnsRecord> {
+        self.edit(account, zone, |_, records| {
+            let slot = find(records, &record.id)?;
+            record.proxied = false;
+            *slot = record.clone();
+            Ok(record)
+        })
+    }
+
+    fn delete_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<()> {
+        self.edit(account, zone, |_, records| {
+            find(records, record_id)?;
+            records.retain(|r| r.id != record_id);
+            Ok(())
+        })
+    }
+
+    fn get_record(&mut self, account: &Account, zone: &Zone, record_id: &str) -> Result<DnsRecord> {
+        Ok(find(self.records(account, zone)?, record_id)?.clone())
+    }
+
+    /// Applies every part in memory and writes the file once, so a batch lands whole
+    /// or not at all.
+    fn batch(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        request: BatchRequest,
+    ) -> Result<BatchResult> {
+        self.edit(account, zone, |backend, records| {
+            let mut result = BatchResult::default();
+            for id in &request.deletes {
+                let idx = records
+                    .iter()
+                    .position(|r| &r.id == id)
+                    .ok_or_else(|| anyhow!("Record {id} not found"))?;
+                result.deletes.push(records.remove(idx));
+            }
+            for (updates, done) in [
+                (&request.patches, &mut result.patches),
+                (&request.puts, &mut result.puts),
+            ] {
+                for record in updates {
+                    *find(records, &record.id)? = record.clone();
+                    done.push(record.clone());
+                }
+            }
+            for record in &request.posts {
+                let mut record = record.clone();
+                record.id = backend.new_id();
+                records.push(record.clone());
+                result.posts.push(record);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Zone files keep no history; version control is the place to look.
+    fn record_audit_log(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _record_id: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(Vec::new())
+    }
+
+    /// The "credentials" are the directory: it has to exist.
+    fn verify_account(&mut self, account: &Account) -> Result<TokenStatus> {
+        let dir = Self::dir(account)?;
+        if !dir.is_dir() {
+            return Err(anyhow!("{} is not a directory", dir.display()));
+        }
+        Ok(TokenStatus {
+            status: "active".to_string(),
+            expires_on: None,
+        })
+    }
+
+    fn list_cf_accounts(&mut self, _account: &Account) -> Result<Vec<CloudflareAccount>> {
+        Ok(Vec::new())
+    }
+
+    fn export_zone(&mut self, account: &Account, zone: &Zone) -> Result<String> {
+        let path = Self::path(account, zone)?;
+        fs::read_to_string(&path).with_context(|| format!("Reading {}", path.display()))
+    }
+
+    fn import_zone(
+        &mut self,
+        account: &Account,
+        zone: &Zone,
+        path: &Path,
+        _proxied: bool,
+    ) -> Result<ImportResult> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let parsed: Vec<DnsRecord> = bind::parse_zone(&text, &zone.name)?
+            .into_iter()
+            .filter(|r| r.record_type != "SOA")
+            .collect();
+        let count = parsed.len() as u32;
+        self.edit(account, zone, |backend, records| {
+            for mut record in parsed {
+                record.id = backend.new_id();
+                record.proxied = false;
+                records.push(record);
+            }
+            Ok(())
+        })?;
+        Ok(ImportResult {
+            added: count,
+            parsed: count,
+        })
+    }
+
+    fn dnssec_status(&mut self, _account: &Account, _zone: &Zone) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for zone-file accounts"))
+    }
+
+    fn set_dnssec(
+        &mut self,
+        _account: &Account,
+        _zone: &Zone,
+        _enabled: bool,
+    ) -> Result<DnssecStatus> {
+        Err(anyhow!("DNSSEC isn't supported for zone-file accounts"))
+    }
+}
+
+/// The zone a file holds: `example.com.zone` and `db.example.com` both hold
+/// `example.com`. Other files in the directory are ignored.
+pub(crate) fn zone_name(file: &str) -> Option<String> {
+    let name = file
+        .strip_suffix(ZONE_SUFFIX)
+        .or_else(|| file.strip_prefix("db."))?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn find<'a>(records: &'a mut [DnsRecord], id: &str) -> Result<&'a mut DnsRecord> {
+    records
+        .iter_mut()
+        .find(|r| r.id == id)
+        .ok_or_else(|| anyhow!("Record {id} not found"))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn bump_soa_serial(records: &mut [DnsRecord]) {
+    let today = civil_from_days(now_unix().div_euclid(86_400));
+    for soa in records.iter_mut().filter(|r| r.record_type == "SOA") {
+        let mut fields: Vec<String> = soa.content.split_whitespace().map(String::from).collect();
+        if let Some(serial) = fields.get_mut(2)
+            && let Ok(value) = serial.parse()
+        {
+            *serial = bind::bump_serial(value, today).to_string();
+            soa.content = fields.join(" ");
+        }
+    }
+}
+
+/// Writes `text` next to `path` and renames it into place, so BIND (or a crash) never
+/// sees a half-written zone.
+fn write_atomically(path: &Path, text: &str) -> Result<()> {
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{file}.tmp"));
+    fs::write(&tmp, text).with_context(|| format!("Writing {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))
+}